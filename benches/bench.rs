@@ -1,54 +1,128 @@
-#![feature(test)]
-
-extern crate test;
+#[macro_use]
+extern crate criterion;
 extern crate dwarf;
 
-#[bench]
-fn display_info(b: &mut test::Bencher) {
-    let path = std::env::args_os().next().unwrap(); // Note: not constant
-    let sections = dwarf::elf::load(path).unwrap();
-    let mut buf = Vec::new();
-    let mut f = dwarf::display::DefaultFormatter::new(&mut buf, 4);
-    b.iter(|| {
-        let mut units = sections.compilation_units();
-        while let Some(unit) = units.next().unwrap() {
-            let abbrev = sections.abbrev(&unit.common).unwrap();
-            unit.entries(&abbrev).display(&mut f).unwrap();
-        }
+use criterion::{black_box, Criterion};
+
+// Load the file to benchmark.
+//
+// This is the file given by the DWARF_BENCH_FILE environment variable, or
+// else the benchmark executable itself. Set the variable when comparing
+// results between builds, since the executable changes.
+fn load() -> dwarf::Sections<dwarf::AnyEndian> {
+    let path = match std::env::var_os("DWARF_BENCH_FILE") {
+        Some(path) => std::path::PathBuf::from(path),
+        None => std::env::current_exe().unwrap(),
+    };
+    dwarf::elf::load(path).unwrap()
+}
+
+fn leb128(c: &mut Criterion) {
+    let unit = dwarf::unit::UnitCommon::<dwarf::LittleEndian>::default();
+    let mut data = Vec::new();
+    for i in 0..1000u64 {
+        let value = dwarf::die::AttributeData::UData(i * i * i);
+        value.write(&mut data, &unit, dwarf::constant::DW_FORM_udata, false).unwrap();
+    }
+    c.bench_function("leb128", |b| {
+        b.iter(|| {
+            let mut r = &data[..];
+            while !r.is_empty() {
+                let value = dwarf::die::AttributeData::read(&mut r,
+                                                            &unit,
+                                                            dwarf::constant::DW_FORM_udata);
+                black_box(value.unwrap());
+            }
+        })
     });
 }
 
-#[bench]
-fn read_info(b: &mut test::Bencher) {
-    let path = std::env::args_os().next().unwrap(); // Note: not constant
-    let sections = dwarf::elf::load(path).unwrap();
-    b.iter(|| {
-        let mut units = sections.compilation_units();
-        while let Some(unit) = units.next().unwrap() {
-            let abbrev = sections.abbrev(&unit.common).unwrap();
-            let mut entries = unit.entries(&abbrev);
-            while let Some(entry) = entries.next().unwrap() {
-                test::black_box(entry.tag);
-                for attribute in &entry.attributes {
-                    test::black_box(attribute.at);
-                    test::black_box(&attribute.data);
+fn parse_abbrev(c: &mut Criterion) {
+    let sections = load();
+    let mut offsets = Vec::new();
+    let mut units = sections.compilation_units();
+    while let Some(unit) = units.next().unwrap() {
+        offsets.push(unit.common.abbrev_offset as usize);
+    }
+    c.bench_function("parse_abbrev_hash", |b| {
+        b.iter(|| for &offset in &offsets {
+            let abbrev = dwarf::abbrev::AbbrevHash::read(&mut &sections.debug_abbrev[offset..]);
+            black_box(abbrev.unwrap());
+        })
+    });
+    c.bench_function("parse_abbrev_vec", |b| {
+        b.iter(|| for &offset in &offsets {
+            let abbrev = dwarf::abbrev::AbbrevVec::read(&mut &sections.debug_abbrev[offset..]);
+            black_box(abbrev.unwrap());
+        })
+    });
+}
+
+fn display_info(c: &mut Criterion) {
+    let sections = load();
+    c.bench_function("display_info", |b| {
+        b.iter(|| {
+            let mut buf = Vec::new();
+            {
+                let mut f = dwarf::display::DefaultFormatter::new(&mut buf, 4);
+                let mut units = sections.compilation_units();
+                while let Some(unit) = units.next().unwrap() {
+                    let abbrev = sections.abbrev(&unit.common).unwrap();
+                    unit.entries(&abbrev).display(&mut f).unwrap();
                 }
             }
-        }
+            black_box(buf);
+        })
     });
 }
 
-#[bench]
-fn read_info_tree(b: &mut test::Bencher) {
-    let path = std::env::args_os().next().unwrap(); // Note: not constant
-    let sections = dwarf::elf::load(path).unwrap();
-    b.iter(|| {
-        let mut units = sections.compilation_units();
-        while let Some(unit) = units.next().unwrap() {
-            let abbrev = sections.abbrev(&unit.common).unwrap();
-            let mut entries = unit.entries(&abbrev).tree();
-            read_info_tree_inner(entries.iter());
-        }
+fn read_info(c: &mut Criterion) {
+    let sections = load();
+    c.bench_function("read_info", |b| {
+        b.iter(|| {
+            let mut units = sections.compilation_units();
+            while let Some(unit) = units.next().unwrap() {
+                let abbrev = sections.abbrev(&unit.common).unwrap();
+                let mut entries = unit.entries(&abbrev);
+                while let Some(entry) = entries.next().unwrap() {
+                    black_box(entry.tag);
+                    for attribute in &entry.attributes {
+                        black_box(attribute.at);
+                        black_box(&attribute.data);
+                    }
+                }
+            }
+        })
+    });
+}
+
+fn scan_info(c: &mut Criterion) {
+    let sections = load();
+    c.bench_function("scan_info", |b| {
+        b.iter(|| {
+            let mut units = sections.compilation_units();
+            while let Some(unit) = units.next().unwrap() {
+                let abbrev = sections.abbrev(&unit.common).unwrap();
+                unit.scan(&abbrev, |offset, tag, count| {
+                        black_box((offset, tag, count));
+                    })
+                    .unwrap();
+            }
+        })
+    });
+}
+
+fn read_info_tree(c: &mut Criterion) {
+    let sections = load();
+    c.bench_function("read_info_tree", |b| {
+        b.iter(|| {
+            let mut units = sections.compilation_units();
+            while let Some(unit) = units.next().unwrap() {
+                let abbrev = sections.abbrev(&unit.common).unwrap();
+                let mut entries = unit.entries(&abbrev).tree();
+                read_info_tree_inner(entries.iter());
+            }
+        })
     });
 }
 
@@ -56,30 +130,40 @@ fn read_info_tree_inner(mut tree: dwarf::die::DieTreeIterator<dwarf::AnyEndian>)
     while let Some(child) = tree.next().unwrap() {
         {
             let entry = child.entry();
-            test::black_box(entry.tag);
+            black_box(entry.tag);
             for attribute in &entry.attributes {
-                test::black_box(attribute.at);
-                test::black_box(&attribute.data);
+                black_box(attribute.at);
+                black_box(&attribute.data);
             }
         }
         read_info_tree_inner(child);
     }
 }
 
-#[bench]
-fn read_line(b: &mut test::Bencher) {
-    let path = std::env::args_os().next().unwrap(); // Note: not constant
-    let sections = dwarf::elf::load(path).unwrap();
-    b.iter(|| {
-        let mut units = sections.compilation_units();
-        while let Some(unit) = units.next().unwrap() {
-            let abbrev = sections.abbrev(&unit.common).unwrap();
-            if let Some(line_program) = sections.line_program(&unit, &abbrev).unwrap() {
-                let mut lines = line_program.lines();
-                while let Some(line) = lines.next().unwrap() {
-                    test::black_box(line);
+fn read_line(c: &mut Criterion) {
+    let sections = load();
+    c.bench_function("read_line", |b| {
+        b.iter(|| {
+            let mut units = sections.compilation_units();
+            while let Some(unit) = units.next().unwrap() {
+                let abbrev = sections.abbrev(&unit.common).unwrap();
+                if let Some(line_program) = sections.line_program(&unit, &abbrev).unwrap() {
+                    let mut lines = line_program.lines();
+                    while let Some(line) = lines.next().unwrap() {
+                        black_box(line);
+                    }
                 }
             }
-        }
+        })
     });
 }
+
+criterion_group!(benches,
+                 leb128,
+                 parse_abbrev,
+                 display_info,
+                 read_info,
+                 scan_info,
+                 read_info_tree,
+                 read_line);
+criterion_main!(benches);
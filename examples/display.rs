@@ -3,23 +3,43 @@ extern crate dwarf;
 use std::env;
 use std::path::Path;
 
+use dwarf::prelude::*;
+
+// Usage: display [--abbrev] FILE...
+//
+// With `--abbrev`, .debug_abbrev is also displayed, in the layout of
+// `readelf --debug-dump=abbrev`.
 fn main() {
-    for file in env::args_os().skip(1) {
-        display(file.as_ref()).unwrap();
+    let mut abbrev = false;
+    for arg in env::args_os().skip(1) {
+        if arg == "--abbrev" {
+            abbrev = true;
+        } else {
+            display(arg.as_ref(), abbrev).unwrap();
+        }
     }
 }
 
-fn display(path: &Path) -> Result<(), dwarf::ReadError> {
+fn display(path: &Path, abbrev: bool) -> Result<(), ReadError> {
     let sections = try!(dwarf::elf::load(path));
     let mut stdout = std::io::stdout();
-    let mut f = dwarf::display::DefaultFormatter::new(&mut stdout, 4);
+    let mut f = DefaultFormatter::new(&mut stdout, 4);
+
+    if abbrev {
+        try!(dwarf::display::display_debug_abbrev(&mut f, &sections.debug_abbrev));
+        try!(f.write_sep());
+    }
 
     let mut units = sections.compilation_units();
     while let Some(unit) = try!(units.next()) {
         let abbrev = try!(sections.abbrev(&unit.common));
+        try!(write!(f, "{}", unit));
         try!(unit.entries(&abbrev).display_depth(&mut f, 3));
 
-        if let Some(mut lines) = try!(sections.lines(&unit, &abbrev)) {
+        if let Some(program) = try!(sections.line_program(&unit, &abbrev)) {
+            try!(program.display(&mut f));
+            try!(f.write_sep());
+            let mut lines = program.into_lines();
             while let Some((lines, line)) = try!(lines.next()) {
                 try!(line.display(&mut f, lines.files()));
             }
@@ -29,6 +49,7 @@ fn display(path: &Path) -> Result<(), dwarf::ReadError> {
     let mut units = sections.type_units();
     while let Some(unit) = try!(units.next()) {
         let abbrev = try!(sections.abbrev(&unit.common));
+        try!(write!(f, "{}", unit));
         try!(unit.entries(&abbrev).display(&mut f));
     }
 
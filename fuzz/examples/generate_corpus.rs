@@ -0,0 +1,74 @@
+// Write a seed corpus for each fuzz target to `corpus/<target>/`.
+//
+// Run this from the `fuzz` directory: `cargo run --example generate_corpus`.
+//
+// The seeds are produced by the writers of the main crate, so that the
+// fuzzer starts from valid DWARF rather than having to discover the
+// formats itself.
+extern crate dwarf;
+extern crate dwarf_fuzz;
+
+use std::fs;
+use std::path::Path;
+
+use dwarf::constant::*;
+use dwarf::jit::JitDebugInfo;
+use dwarf::{LittleEndian, Sections};
+
+fn write_seed(target: &str, name: &str, data: &[u8]) {
+    let dir = Path::new("corpus").join(target);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join(name), data).unwrap();
+}
+
+fn write_sections(name: &str, sections: &Sections<LittleEndian>, address_size: u8) {
+    write_seed("abbrev", name, &sections.debug_abbrev);
+    write_seed("unit_header", name, &sections.debug_info);
+    write_seed("entries",
+               name,
+               &dwarf_fuzz::join(&[&sections.debug_abbrev, &sections.debug_info]));
+    if !sections.debug_line.is_empty() {
+        let mut data = vec![dwarf_fuzz::unit_flags(4, address_size, 4)];
+        data.extend_from_slice(&sections.debug_line);
+        write_seed("line_program", name, &data);
+    }
+}
+
+fn jit(address_size: u8) -> Sections<LittleEndian> {
+    let mut jit = JitDebugInfo::new(LittleEndian, address_size, 0, b"jit.c");
+    jit.set_comp_dir(b"/tmp");
+    let file = jit.add_file(b"jit.c");
+    jit.add_function(b"f", 0x1000, 0x20);
+    jit.add_function(b"g", 0x2000, 0x10);
+    jit.add_line(0x1000, file, 1);
+    jit.add_line(0x1010, file, 3);
+    jit.add_line(0x2000, file, 10);
+    jit.sections().unwrap()
+}
+
+fn main() {
+    for (name, sections) in dwarf::testing::all(LittleEndian) {
+        write_sections(name, &sections, 4);
+    }
+    write_sections("jit_4", &jit(4), 4);
+    write_sections("jit_8", &jit(8), 8);
+
+    // There is no expression writer, so these are encoded directly. The
+    // operands are small enough to be single byte LEB128 values.
+    let expressions: &[(&str, &[u8])] = &[
+        ("addr", &[DW_OP_addr.0, 0x00, 0x10, 0x00, 0x00]),
+        ("fbreg_piece", &[DW_OP_fbreg.0, 0x70, DW_OP_piece.0, 4, DW_OP_bregx.0, 6, 8]),
+        ("stack_value", &[DW_OP_lit1.0, DW_OP_plus_uconst.0, 2, DW_OP_stack_value.0]),
+        ("branch", &[DW_OP_lit0.0, DW_OP_bra.0, 1, 0, DW_OP_deref_size.0, 4]),
+        ("implicit_value", &[DW_OP_implicit_value.0, 2, 0xaa, 0xbb]),
+        ("entry_value", &[DW_OP_entry_value.0, 1, DW_OP_reg5.0, DW_OP_stack_value.0]),
+        ("const_type", &[DW_OP_const_type.0, 0x20, 2, 0x01, 0x02]),
+    ];
+    for &(name, expression) in expressions {
+        for &(address_size, offset_size) in &[(4, 4), (8, 8)] {
+            let mut data = vec![dwarf_fuzz::unit_flags(4, address_size, offset_size)];
+            data.extend_from_slice(expression);
+            write_seed("expression", &format!("{}_{}", name, address_size), &data);
+        }
+    }
+}
@@ -0,0 +1,24 @@
+// Read every abbreviation table in .debug_abbrev.
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate dwarf;
+extern crate dwarf_fuzz;
+
+use dwarf::abbrev::{AbbrevHash, AbbrevVec};
+
+fuzz_target!(|data: &[u8]| {
+    let data = dwarf_fuzz::split(data, 1)[0];
+    let mut r = data;
+    while !r.is_empty() {
+        if AbbrevHash::read(&mut r).is_err() {
+            break;
+        }
+    }
+    let mut r = data;
+    while !r.is_empty() {
+        if AbbrevVec::read(&mut r).is_err() {
+            break;
+        }
+    }
+});
@@ -0,0 +1,34 @@
+// Iterate over the entries of each unit, given .debug_abbrev and
+// .debug_info.
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate dwarf;
+extern crate dwarf_fuzz;
+
+use dwarf::unit::CompilationUnitIterator;
+use dwarf::LittleEndian;
+
+fuzz_target!(|data: &[u8]| {
+    let sections = dwarf_fuzz::split(data, 2);
+    let (debug_abbrev, debug_info) = (sections[0], sections[1]);
+    let mut units = CompilationUnitIterator::new(LittleEndian, debug_info);
+    while let Ok(Some(unit)) = units.next() {
+        let abbrev = match unit.abbrev(debug_abbrev) {
+            Ok(abbrev) => abbrev,
+            Err(_) => continue,
+        };
+
+        let mut entries = unit.entries_limited(dwarf_fuzz::MAX_ITEMS, &abbrev);
+        while let Ok(Some(entry)) = entries.next() {
+            for attribute in &entry.attributes {
+                let _ = attribute.data.as_string_strict(debug_info);
+            }
+        }
+
+        let mut entries = unit.entries_limited(dwarf_fuzz::MAX_ITEMS, &abbrev);
+        entries.enable_recovery();
+        entries.enable_sibling_validation();
+        while let Ok(Some(_)) = entries.next_sibling() {}
+    }
+});
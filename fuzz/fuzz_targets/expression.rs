@@ -0,0 +1,17 @@
+// Decode the operations of a DWARF expression.
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate dwarf;
+extern crate dwarf_fuzz;
+
+use dwarf::expr::ExpressionIterator;
+
+fuzz_target!(|data: &[u8]| {
+    let (unit, data) = dwarf_fuzz::unit(data);
+    let data = dwarf_fuzz::split(data, 1)[0];
+    let mut ops = ExpressionIterator::new(data, &unit);
+    while let Ok(Some(op)) = ops.next() {
+        let _ = op.memory_access();
+    }
+});
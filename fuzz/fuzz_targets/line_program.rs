@@ -0,0 +1,35 @@
+// Run a line program in .debug_line.
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate dwarf;
+extern crate dwarf_fuzz;
+
+use dwarf::line::LineProgram;
+use dwarf::LittleEndian;
+
+fuzz_target!(|data: &[u8]| {
+    let (unit, data) = dwarf_fuzz::unit(data);
+    let mut r = dwarf_fuzz::split(data, 1)[0];
+    let program = match LineProgram::read(&mut r,
+                                          0,
+                                          LittleEndian,
+                                          unit.address_size,
+                                          b"/comp_dir",
+                                          b"comp_name") {
+        Ok(program) => program,
+        Err(_) => return,
+    };
+    let mut lines = program.lines();
+    lines.enable_trace();
+    for _ in 0..dwarf_fuzz::MAX_ITEMS {
+        match lines.next() {
+            Ok(Some((iter, line))) => {
+                if let Some(file) = iter.file(line.file) {
+                    let _ = file.full_path(iter.directories());
+                }
+            }
+            _ => break,
+        }
+    }
+});
@@ -0,0 +1,23 @@
+// Read the unit headers in .debug_info and .debug_types.
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate dwarf;
+extern crate dwarf_fuzz;
+
+use dwarf::unit::{CompilationUnitIterator, TypeUnitIterator};
+use dwarf::LittleEndian;
+
+fuzz_target!(|data: &[u8]| {
+    let data = dwarf_fuzz::split(data, 1)[0];
+    let mut units = CompilationUnitIterator::new(LittleEndian, data);
+    units.skip_unsupported();
+    while let Ok(Some(unit)) = units.next() {
+        let _ = unit.end_offset();
+    }
+    let mut units = TypeUnitIterator::new(LittleEndian, data);
+    units.skip_unsupported();
+    while let Ok(Some(unit)) = units.next() {
+        let _ = unit.end_offset();
+    }
+});
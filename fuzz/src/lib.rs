@@ -0,0 +1,82 @@
+// Input formats shared by the fuzz targets and the corpus generator.
+extern crate dwarf;
+
+use dwarf::unit::UnitCommon;
+use dwarf::LittleEndian;
+
+// The largest input that the targets read.
+//
+// The readers only allocate in proportion to their input, so this mainly
+// keeps each run fast. Pass `-max_len` to libFuzzer to use a smaller limit.
+pub const MAX_INPUT_LEN: usize = 0x10000;
+
+// The number of entries or rows that a target reads before stopping.
+pub const MAX_ITEMS: usize = 0x1000;
+
+// Split an input into `count` sections.
+//
+// Every section except the last is preceded by its length, as a
+// little-endian u16. Sections that are missing or truncated are empty.
+pub fn split(mut data: &[u8], count: usize) -> Vec<&[u8]> {
+    if data.len() > MAX_INPUT_LEN {
+        data = &data[..MAX_INPUT_LEN];
+    }
+    let mut sections = Vec::with_capacity(count);
+    for _ in 1..count {
+        if data.len() < 2 {
+            sections.push(&data[..0]);
+            continue;
+        }
+        let len = data[0] as usize | (data[1] as usize) << 8;
+        let len = std::cmp::min(len, data.len() - 2);
+        sections.push(&data[2..2 + len]);
+        data = &data[2 + len..];
+    }
+    sections.push(data);
+    sections
+}
+
+// The inverse of `split`.
+pub fn join(sections: &[&[u8]]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for (i, section) in sections.iter().enumerate() {
+        if i + 1 < sections.len() {
+            let len = std::cmp::min(section.len(), 0xffff);
+            data.push(len as u8);
+            data.push((len >> 8) as u8);
+            data.extend_from_slice(&section[..len]);
+        } else {
+            data.extend_from_slice(section);
+        }
+    }
+    data
+}
+
+// Create a unit header from the first byte of an input, for targets that
+// read data whose format depends on the unit.
+//
+// Bits 0-1 are the version minus 2, bit 2 selects 8 byte addresses, and
+// bit 3 selects the 64-bit format.
+pub fn unit(data: &[u8]) -> (UnitCommon<LittleEndian>, &[u8]) {
+    let (flags, data) = match data.split_first() {
+        Some((&flags, data)) => (flags, data),
+        None => (0, data),
+    };
+    let mut unit = UnitCommon::default();
+    unit.version = 2 + (flags & 3) as u16;
+    unit.address_size = if flags & 4 != 0 { 8 } else { 4 };
+    unit.offset_size = if flags & 8 != 0 { 8 } else { 4 };
+    (unit, data)
+}
+
+// The inverse of `unit`.
+pub fn unit_flags(version: u16, address_size: u8, offset_size: u8) -> u8 {
+    let mut flags = (version.saturating_sub(2) & 3) as u8;
+    if address_size == 8 {
+        flags |= 4;
+    }
+    if offset_size == 8 {
+        flags |= 8;
+    }
+    flags
+}
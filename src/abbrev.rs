@@ -1,5 +1,6 @@
 use std;
 use std::io::Write;
+use std::rc::Rc;
 
 use constant;
 use leb128;
@@ -22,6 +23,8 @@ impl AbbrevHash {
         self.0.is_empty()
     }
 
+    // The order is arbitrary, and may differ between runs. Convert to an
+    // `AbbrevVec` for a deterministic order.
     pub fn iter(&self) -> std::collections::hash_map::Iter<u64, Abbrev> {
         self.0.iter()
     }
@@ -45,6 +48,42 @@ impl AbbrevHash {
     }
 }
 
+// A cache of abbreviation tables, keyed by their offset in .debug_abbrev.
+//
+// Units often share a table, so this avoids reading it for each unit.
+#[derive(Debug, Default)]
+pub struct AbbrevCache(std::collections::HashMap<u64, Rc<AbbrevHash>>);
+
+impl AbbrevCache {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    // Return the table at `offset`, reading it if it isn't in the cache.
+    pub fn get(&mut self, debug_abbrev: &[u8], offset: u64) -> Result<Rc<AbbrevHash>, ReadError> {
+        if let Some(abbrev_hash) = self.0.get(&offset) {
+            return Ok(abbrev_hash.clone());
+        }
+        if offset >= debug_abbrev.len() as u64 {
+            return Err(ReadError::Invalid);
+        }
+        let abbrev_hash = Rc::new(try!(AbbrevHash::read(&mut &debug_abbrev[offset as usize..])));
+        self.0.insert(offset, abbrev_hash.clone());
+        Ok(abbrev_hash)
+    }
+}
 
 #[derive(Debug)]
 pub struct AbbrevVec(Vec<Abbrev>);
@@ -66,6 +105,21 @@ impl AbbrevVec {
         self.0.iter()
     }
 
+    // Read abbreviations in the order they are declared.
+    //
+    // Unlike `AbbrevHash`, writing the result reproduces the input exactly.
+    pub fn read(r: &mut &[u8]) -> Result<AbbrevVec, ReadError> {
+        let mut abbrevs = Vec::new();
+        let mut codes = std::collections::HashSet::new();
+        while let Some(abbrev) = try!(Abbrev::read(r)) {
+            if !codes.insert(abbrev.code) {
+                return Err(ReadError::Invalid);
+            }
+            abbrevs.push(abbrev);
+        }
+        Ok(AbbrevVec(abbrevs))
+    }
+
     pub fn write<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
         for abbrev in self.iter() {
             try!(abbrev.write(w));
@@ -75,6 +129,88 @@ impl AbbrevVec {
     }
 }
 
+impl std::convert::From<AbbrevVec> for AbbrevHash {
+    fn from(abbrev_vec: AbbrevVec) -> Self {
+        let mut abbrev_hash = AbbrevHash::new();
+        for abbrev in abbrev_vec.0 {
+            abbrev_hash.insert(abbrev);
+        }
+        abbrev_hash
+    }
+}
+
+// The abbreviations are sorted by code, since a hash has no order.
+impl std::convert::From<AbbrevHash> for AbbrevVec {
+    fn from(abbrev_hash: AbbrevHash) -> Self {
+        let mut abbrevs: Vec<Abbrev> = abbrev_hash.0.into_values().collect();
+        abbrevs.sort_by_key(|abbrev| abbrev.code);
+        AbbrevVec(abbrevs)
+    }
+}
+
+// An abbreviation declaration, and where it is in .debug_abbrev.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AbbrevDecl {
+    // The offset of the declaration.
+    pub offset: usize,
+    // The offset of the table that contains the declaration. This is the
+    // value of `abbrev_offset` in the headers of units that use the table.
+    pub table_offset: usize,
+    pub abbrev: Abbrev,
+}
+
+// An iterator over the declarations in .debug_abbrev, in the order that
+// they are declared.
+//
+// This doesn't build a table, so it can be used to dump or rewrite the
+// section directly. The iteration continues across the null entries that
+// end each table, so all tables after the starting offset are included.
+#[derive(Debug, Clone)]
+pub struct AbbrevDeclIterator<'data> {
+    data: &'data [u8],
+    offset: usize,
+    table_offset: usize,
+}
+
+impl<'data> AbbrevDeclIterator<'data> {
+    // Iterate over the declarations starting at `offset` in `debug_abbrev`.
+    pub fn new(debug_abbrev: &'data [u8], offset: usize) -> Self {
+        AbbrevDeclIterator {
+            data: debug_abbrev,
+            offset: offset,
+            table_offset: offset,
+        }
+    }
+
+    // The offset of the next declaration, or of the end of the section.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
+    pub fn next(&mut self) -> Result<Option<AbbrevDecl>, ReadError> {
+        loop {
+            if self.offset >= self.data.len() {
+                return Ok(None);
+            }
+            let mut r = &self.data[self.offset..];
+            let abbrev = try!(Abbrev::read(&mut r));
+            let offset = self.offset;
+            self.offset = self.data.len() - r.len();
+            match abbrev {
+                Some(abbrev) => {
+                    return Ok(Some(AbbrevDecl {
+                        offset: offset,
+                        table_offset: self.table_offset,
+                        abbrev: abbrev,
+                    }));
+                }
+                None => self.table_offset = self.offset,
+            }
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct Abbrev {
     pub code: u64,
@@ -140,20 +276,37 @@ impl Abbrev {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AbbrevAttribute {
     pub at: constant::DwAt,
     pub form: constant::DwForm,
+    // The value of a DW_FORM_implicit_const attribute, which is stored in
+    // the abbreviation instead of in each entry. Otherwise this is 0.
+    pub implicit_const: i64,
 }
 
 impl AbbrevAttribute {
-    pub fn null() -> Self {
+    pub fn new(at: constant::DwAt, form: constant::DwForm) -> Self {
         AbbrevAttribute {
-            at: constant::DW_AT_null,
-            form: constant::DW_FORM_null,
+            at: at,
+            form: form,
+            implicit_const: 0,
         }
     }
 
+    // An attribute with the form DW_FORM_implicit_const and the given value.
+    pub fn implicit_const(at: constant::DwAt, value: i64) -> Self {
+        AbbrevAttribute {
+            at: at,
+            form: constant::DW_FORM_implicit_const,
+            implicit_const: value,
+        }
+    }
+
+    pub fn null() -> Self {
+        AbbrevAttribute::new(constant::DW_AT_null, constant::DW_FORM_null)
+    }
+
     pub fn is_null(&self) -> bool {
         self.at == constant::DW_AT_null && self.form == constant::DW_FORM_null
     }
@@ -161,10 +314,10 @@ impl AbbrevAttribute {
     pub fn read(r: &mut &[u8]) -> Result<Option<AbbrevAttribute>, ReadError> {
         let at = try!(leb128::read_u16(r));
         let form = try!(leb128::read_u16(r));
-        let attribute = AbbrevAttribute {
-            at: constant::DwAt(at),
-            form: constant::DwForm(form),
-        };
+        let mut attribute = AbbrevAttribute::new(constant::DwAt(at), constant::DwForm(form));
+        if attribute.form == constant::DW_FORM_implicit_const {
+            attribute.implicit_const = try!(leb128::read_i64(r));
+        }
         if attribute.is_null() {
             Ok(None)
         } else {
@@ -179,6 +332,9 @@ impl AbbrevAttribute {
     pub fn write<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
         try!(leb128::write_u16(w, self.at.0));
         try!(leb128::write_u16(w, self.form.0));
+        if self.form == constant::DW_FORM_implicit_const {
+            try!(leb128::write_i64(w, self.implicit_const));
+        }
         Ok(())
     }
 }
@@ -196,7 +352,7 @@ mod test {
                 tag: DW_TAG_namespace,
                 children: true,
                 attributes: vec![
-                    AbbrevAttribute { at: DW_AT_name, form: DW_FORM_strp },
+                    AbbrevAttribute::new(DW_AT_name, DW_FORM_strp),
                 ],
             },
         ]);
@@ -215,6 +371,35 @@ mod test {
         }
     }
 
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn abbrev_vec() {
+        // Codes are out of order.
+        let buf = [
+            2, 57, 0, 3, 8, 0, 0,
+            1, 17, 1, 0, 0,
+            0,
+        ];
+        let mut r = &buf[..];
+        let read_val = AbbrevVec::read(&mut r).unwrap();
+        assert_eq!(r.len(), 0);
+        assert_eq!(read_val.iter().map(|abbrev| abbrev.code).collect::<Vec<_>>(), vec![2, 1]);
+
+        let mut write_buf = Vec::new();
+        read_val.write(&mut write_buf).unwrap();
+        assert_eq!(&write_buf[..], &buf[..]);
+
+        let abbrev_hash = AbbrevHash::from(read_val);
+        assert_eq!(abbrev_hash.len(), 2);
+        assert_eq!(abbrev_hash.get(2).unwrap().tag, DW_TAG_namespace);
+        let abbrev_vec = AbbrevVec::from(abbrev_hash);
+        assert_eq!(abbrev_vec.iter().map(|abbrev| abbrev.code).collect::<Vec<_>>(), vec![1, 2]);
+
+        // Duplicate codes.
+        let buf = [1, 57, 0, 0, 0, 1, 17, 1, 0, 0, 0];
+        assert!(AbbrevVec::read(&mut &buf[..]).is_err());
+    }
+
     #[test]
     fn abbrev() {
         let write_val = Abbrev {
@@ -222,7 +407,7 @@ mod test {
             tag: DW_TAG_namespace,
             children: true,
             attributes: vec![
-                AbbrevAttribute { at: DW_AT_name, form: DW_FORM_strp },
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_strp),
             ],
         };
 
@@ -239,10 +424,7 @@ mod test {
 
     #[test]
     fn abbrev_attribute() {
-        let write_val = AbbrevAttribute {
-            at: DW_AT_sibling,
-            form: DW_FORM_ref4,
-        };
+        let write_val = AbbrevAttribute::new(DW_AT_sibling, DW_FORM_ref4);
 
         let mut buf = Vec::new();
         write_val.write(&mut buf).unwrap();
@@ -253,5 +435,81 @@ mod test {
         assert_eq!(&buf[..], [1, 19]);
         assert_eq!(r.len(), 0);
         assert_eq!(read_val, Some(write_val));
+
+        let write_val = AbbrevAttribute::implicit_const(DW_AT_decl_file, -2);
+        let mut buf = Vec::new();
+        write_val.write(&mut buf).unwrap();
+        let mut r = &buf[..];
+        let read_val = AbbrevAttribute::read(&mut r).unwrap();
+        assert_eq!(&buf[..], [0x3a, 0x21, 0x7e]);
+        assert_eq!(r.len(), 0);
+        assert_eq!(read_val, Some(write_val));
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn abbrev_decl_iterator() {
+        let mut data = Vec::new();
+        AbbrevVec::new(vec![
+            Abbrev {
+                code: 1,
+                tag: DW_TAG_compile_unit,
+                children: true,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_name, DW_FORM_strp),
+                ],
+            },
+            Abbrev {
+                code: 2,
+                tag: DW_TAG_base_type,
+                children: false,
+                attributes: vec![],
+            },
+        ]).write(&mut data).unwrap();
+        let second_table = data.len();
+        AbbrevVec::new(vec![
+            Abbrev {
+                code: 1,
+                tag: DW_TAG_type_unit,
+                children: false,
+                attributes: vec![],
+            },
+        ]).write(&mut data).unwrap();
+
+        let mut decls = Vec::new();
+        let mut iter = AbbrevDeclIterator::new(&data, 0);
+        while let Some(decl) = iter.next().unwrap() {
+            decls.push((decl.offset, decl.table_offset, decl.abbrev.code, decl.abbrev.tag));
+        }
+        assert_eq!(decls, vec![
+            (0, 0, 1, DW_TAG_compile_unit),
+            (7, 0, 2, DW_TAG_base_type),
+            (second_table, second_table, 1, DW_TAG_type_unit),
+        ]);
+        assert_eq!(iter.offset(), data.len());
+
+        let mut iter = AbbrevDeclIterator::new(&data, second_table);
+        assert_eq!(iter.next().unwrap().unwrap().abbrev.tag, DW_TAG_type_unit);
+        assert!(iter.next().unwrap().is_none());
+
+        let mut iter = AbbrevDeclIterator::new(&[1, 0x11, 2], 0);
+        assert!(iter.next().is_err());
+    }
+
+    #[test]
+    fn abbrev_cache() {
+        let abbrev_vec = AbbrevVec::new(vec![
+            Abbrev { code: 1, tag: DW_TAG_namespace, children: false, attributes: vec![] },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev_vec.write(&mut debug_abbrev).unwrap();
+
+        let mut cache = AbbrevCache::new();
+        let first = cache.get(&debug_abbrev, 0).unwrap();
+        assert_eq!(first.get(1).unwrap().tag, DW_TAG_namespace);
+        let second = cache.get(&debug_abbrev, 0).unwrap();
+        assert!(Rc::ptr_eq(&first, &second));
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get(&debug_abbrev, debug_abbrev.len() as u64).is_err());
     }
 }
@@ -0,0 +1,960 @@
+use std::collections::HashMap;
+
+use abbrev::{Abbrev, AbbrevAttribute, AbbrevHash, AbbrevVec};
+use constant;
+use die::{Attribute, AttributeData, Die};
+use endian::Endian;
+use leb128;
+use offset::{DebugInfoOffset, UnitOffset};
+use unit::{CompilationUnit, UnitCommon};
+use write::*;
+use Sections;
+
+// A reference to an entry added to a `SectionsBuilder`.
+//
+// The offset of the entry isn't known until the sections are written, so
+// references to it are recorded using this instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntryId {
+    // The index of the unit, in the order the units were added.
+    pub unit: usize,
+    // The index of the entry within the unit. The root is 0.
+    pub entry: usize,
+}
+
+// The value of an attribute added to a `SectionsBuilder`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildValue<'data> {
+    // A value that is written as is.
+    Data(AttributeData<'data>),
+    // A reference to an entry, which is resolved when the sections are
+    // written.
+    //
+    // With DW_FORM_ref_addr this may refer to an entry in any unit. The
+    // other reference forms are relative to the unit, so they can only
+    // refer to an entry in the same unit.
+    Entry(EntryId),
+}
+
+// Chooses the forms of attributes that were added without one.
+//
+// Each method is given the attribute and the version of its unit, and
+// must return a form that can encode the value in that version. Forms
+// that can't encode the value cause an error when writing.
+pub trait FormChooser {
+    // The form of an unsigned constant.
+    fn udata_form(&self, at: constant::DwAt, value: u64, version: u16) -> constant::DwForm;
+
+    // The form of a signed constant.
+    fn sdata_form(&self, at: constant::DwAt, value: i64, version: u16) -> constant::DwForm;
+
+    fn flag_form(&self, at: constant::DwAt, value: bool, version: u16) -> constant::DwForm;
+
+    // The form of a block of `len` bytes.
+    fn block_form(&self, at: constant::DwAt, len: usize, version: u16) -> constant::DwForm;
+
+    // The form of a reference to an entry in the same unit. `max_offset` is
+    // the largest offset of an entry in the unit.
+    fn reference_form(
+        &self,
+        at: constant::DwAt,
+        max_offset: u64,
+        version: u16
+    ) -> constant::DwForm;
+}
+
+// Chooses the smallest form for each value.
+//
+// Signed constants always use DW_FORM_sdata, since consumers may not
+// sign extend the fixed size forms. Before version 4, DW_FORM_data4 and
+// DW_FORM_data8 may be interpreted as section offsets, so they aren't
+// used for constants, and DW_FORM_flag_present isn't available.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SmallestForms;
+
+impl FormChooser for SmallestForms {
+    fn udata_form(&self, _: constant::DwAt, value: u64, version: u16) -> constant::DwForm {
+        if value <= 0xff {
+            constant::DW_FORM_data1
+        } else if value <= 0xffff {
+            constant::DW_FORM_data2
+        } else if version < 4 {
+            constant::DW_FORM_udata
+        } else if value <= 0xffff_ffff && leb128::u64_len(value) >= 4 {
+            constant::DW_FORM_data4
+        } else if leb128::u64_len(value) < 8 {
+            constant::DW_FORM_udata
+        } else {
+            constant::DW_FORM_data8
+        }
+    }
+
+    fn sdata_form(&self, _: constant::DwAt, _: i64, _: u16) -> constant::DwForm {
+        constant::DW_FORM_sdata
+    }
+
+    fn flag_form(&self, _: constant::DwAt, value: bool, version: u16) -> constant::DwForm {
+        if value && version >= 4 {
+            constant::DW_FORM_flag_present
+        } else {
+            constant::DW_FORM_flag
+        }
+    }
+
+    fn block_form(&self, _: constant::DwAt, len: usize, _: u16) -> constant::DwForm {
+        if len <= 0xff {
+            constant::DW_FORM_block1
+        } else if len <= 0xffff && leb128::u64_len(len as u64) >= 2 {
+            constant::DW_FORM_block2
+        } else if leb128::u64_len(len as u64) < 4 {
+            constant::DW_FORM_block
+        } else {
+            constant::DW_FORM_block4
+        }
+    }
+
+    fn reference_form(&self, _: constant::DwAt, max_offset: u64, _: u16) -> constant::DwForm {
+        if max_offset <= 0xff {
+            constant::DW_FORM_ref1
+        } else if max_offset <= 0xffff {
+            constant::DW_FORM_ref2
+        } else if max_offset <= 0xffff_ffff {
+            constant::DW_FORM_ref4
+        } else {
+            constant::DW_FORM_ref8
+        }
+    }
+}
+
+// Chooses forms with a fixed size that is large enough for most values,
+// so that values can be changed later with `Die::patch_attribute`.
+//
+// Negative signed constants use DW_FORM_sdata, since consumers may not
+// sign extend the fixed size forms, so they can't be patched.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FixedSizeForms;
+
+impl FormChooser for FixedSizeForms {
+    fn udata_form(&self, _: constant::DwAt, value: u64, _: u16) -> constant::DwForm {
+        if value <= 0xffff_ffff {
+            constant::DW_FORM_data4
+        } else {
+            constant::DW_FORM_data8
+        }
+    }
+
+    fn sdata_form(&self, _: constant::DwAt, value: i64, _: u16) -> constant::DwForm {
+        if value < 0 {
+            constant::DW_FORM_sdata
+        } else if value <= 0xffff_ffff {
+            constant::DW_FORM_data4
+        } else {
+            constant::DW_FORM_data8
+        }
+    }
+
+    fn flag_form(&self, _: constant::DwAt, _: bool, _: u16) -> constant::DwForm {
+        constant::DW_FORM_flag
+    }
+
+    fn block_form(&self, _: constant::DwAt, _: usize, _: u16) -> constant::DwForm {
+        constant::DW_FORM_block4
+    }
+
+    fn reference_form(&self, _: constant::DwAt, max_offset: u64, _: u16) -> constant::DwForm {
+        if max_offset <= 0xffff_ffff {
+            constant::DW_FORM_ref4
+        } else {
+            constant::DW_FORM_ref8
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BuildAttribute<'data> {
+    at: constant::DwAt,
+    // `None` if the form is chosen by a `FormChooser`.
+    form: Option<constant::DwForm>,
+    value: BuildValue<'data>,
+}
+
+#[derive(Debug, Clone)]
+struct BuildEntry<'data> {
+    tag: constant::DwTag,
+    attributes: Vec<BuildAttribute<'data>>,
+    children: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct BuildUnit<'data> {
+    version: u16,
+    address_size: u8,
+    offset_size: u8,
+    entries: Vec<BuildEntry<'data>>,
+}
+
+impl<'data> BuildUnit<'data> {
+    // The header of the unit. Units are always written as DW_UT_compile.
+    fn header<'a, E: Endian>(
+        &self,
+        endian: E,
+        offset: usize,
+        abbrev_offset: u64
+    ) -> UnitCommon<'a, E> {
+        UnitCommon {
+            offset: offset,
+            endian: endian,
+            version: self.version,
+            address_size: self.address_size,
+            offset_size: self.offset_size,
+            abbrev_offset: abbrev_offset,
+            unit_type: constant::DW_UT_compile,
+            unit_id: 0,
+            type_offset: 0,
+            data: &[],
+            padding: 0,
+            quirks: Default::default(),
+        }
+    }
+}
+
+// Builds .debug_abbrev, .debug_info and .debug_str for new compilation
+// units.
+//
+// Entries are added to a tree for each unit, and may refer to entries in
+// other units before any offsets are known. The abbreviations, forms of
+// references and offsets are all determined by `write`.
+#[derive(Debug, Clone)]
+pub struct SectionsBuilder<'data, E: Endian> {
+    endian: E,
+    units: Vec<BuildUnit<'data>>,
+    debug_str: Vec<u8>,
+    strings: HashMap<Vec<u8>, u64>,
+}
+
+impl<'data, E: Endian> SectionsBuilder<'data, E> {
+    pub fn new(endian: E) -> Self {
+        SectionsBuilder {
+            endian: endian,
+            units: Vec::new(),
+            debug_str: Vec::new(),
+            strings: HashMap::new(),
+        }
+    }
+
+    // Add a compilation unit, and return its root entry.
+    pub fn add_unit(
+        &mut self,
+        version: u16,
+        address_size: u8,
+        offset_size: u8,
+        tag: constant::DwTag
+    ) -> EntryId {
+        self.units.push(BuildUnit {
+            version: version,
+            address_size: address_size,
+            offset_size: offset_size,
+            entries: vec![BuildEntry {
+                              tag: tag,
+                              attributes: Vec::new(),
+                              children: Vec::new(),
+                          }],
+        });
+        EntryId {
+            unit: self.units.len() - 1,
+            entry: 0,
+        }
+    }
+
+    // Add an entry as the last child of `parent`.
+    //
+    // Panics if `parent` wasn't returned by this builder.
+    pub fn add_entry(&mut self, parent: EntryId, tag: constant::DwTag) -> EntryId {
+        let unit = &mut self.units[parent.unit];
+        let entry = unit.entries.len();
+        unit.entries[parent.entry].children.push(entry);
+        unit.entries.push(BuildEntry {
+            tag: tag,
+            attributes: Vec::new(),
+            children: Vec::new(),
+        });
+        EntryId {
+            unit: parent.unit,
+            entry: entry,
+        }
+    }
+
+    // Add an attribute to an entry.
+    //
+    // The value isn't checked against the form until the sections are
+    // written. Panics if `entry` wasn't returned by this builder.
+    pub fn add_attribute(
+        &mut self,
+        entry: EntryId,
+        at: constant::DwAt,
+        form: constant::DwForm,
+        value: BuildValue<'data>
+    ) {
+        self.units[entry.unit].entries[entry.entry].attributes.push(BuildAttribute {
+            at: at,
+            form: Some(form),
+            value: value,
+        });
+    }
+
+    // Add an attribute to an entry, with a form that is chosen when the
+    // sections are written.
+    //
+    // References to entries in other units always use DW_FORM_ref_addr.
+    // Panics if `entry` wasn't returned by this builder.
+    pub fn add_value(&mut self, entry: EntryId, at: constant::DwAt, value: BuildValue<'data>) {
+        self.units[entry.unit].entries[entry.entry].attributes.push(BuildAttribute {
+            at: at,
+            form: None,
+            value: value,
+        });
+    }
+
+    // Add a string to .debug_str, and return its offset for use with
+    // DW_FORM_strp.
+    //
+    // Identical strings are only added once.
+    pub fn add_string(&mut self, string: &[u8]) -> u64 {
+        if let Some(&offset) = self.strings.get(string) {
+            return offset;
+        }
+        let offset = self.debug_str.len() as u64;
+        self.debug_str.extend_from_slice(string);
+        self.debug_str.push(0);
+        self.strings.insert(string.to_vec(), offset);
+        offset
+    }
+
+    pub fn unit_count(&self) -> usize {
+        self.units.len()
+    }
+
+    // Lay out the units and write the sections, using `SmallestForms` for
+    // attributes that were added without a form.
+    //
+    // Units are written in the order they were added, and entries in the
+    // order they were added to their parent. Identical abbreviation tables
+    // are shared. The other sections of the result are empty.
+    //
+    // Returns the sections, and the offset in .debug_info of each entry,
+    // indexed by unit and then entry.
+    pub fn write(&self) -> Result<(Sections<E>, Vec<Vec<usize>>), WriteError> {
+        self.write_with(&SmallestForms)
+    }
+
+    // Like `write`, but use `chooser` for attributes that were added
+    // without a form.
+    pub fn write_with<C: FormChooser>(
+        &self,
+        chooser: &C
+    ) -> Result<(Sections<E>, Vec<Vec<usize>>), WriteError> {
+        let mut orders = Vec::with_capacity(self.units.len());
+        for unit in &self.units {
+            let mut order = Vec::new();
+            entry_order(unit, 0, &mut order);
+            orders.push(order);
+        }
+
+        // The sizes of references depend on the offsets, and the forms of
+        // references may depend on the size of the unit, so repeat the
+        // layout until the offsets don't change.
+        let mut offsets = None;
+        for _ in 0..8 {
+            let forms = try!(self.forms(chooser, offsets.as_ref()));
+            let (debug_abbrev, abbrevs) = try!(self.write_abbrevs(&orders, &forms));
+            let (debug_info, new_offsets) =
+                try!(self.layout(&orders, &forms, &abbrevs, offsets.as_ref()));
+            if offsets.as_ref() == Some(&new_offsets) {
+                let sections = Sections {
+                    debug_abbrev: debug_abbrev,
+                    debug_info: debug_info,
+                    debug_str: self.debug_str.clone(),
+                    ..Sections::new(self.endian)
+                };
+                return Ok((sections, new_offsets));
+            }
+            offsets = Some(new_offsets);
+        }
+        Err(WriteError::Invalid("entry offsets did not converge".to_string()))
+    }
+
+    // Choose the form of each attribute, indexed by unit, entry and then
+    // attribute.
+    fn forms<C: FormChooser>(
+        &self,
+        chooser: &C,
+        offsets: Option<&Vec<Vec<usize>>>
+    ) -> Result<Vec<Vec<Vec<constant::DwForm>>>, WriteError> {
+        let mut forms = Vec::with_capacity(self.units.len());
+        for (u, unit) in self.units.iter().enumerate() {
+            // The largest offset of an entry within the unit.
+            let max_offset = match offsets {
+                Some(offsets) => {
+                    let offsets = &offsets[u];
+                    let header_len = unit.header::<E>(self.endian, 0, 0).header_len();
+                    let max = offsets.iter().cloned().max().unwrap_or(0);
+                    (max + header_len - offsets[0]) as u64
+                }
+                None => 0,
+            };
+            let mut unit_forms = Vec::with_capacity(unit.entries.len());
+            for entry in &unit.entries {
+                let mut entry_forms = Vec::with_capacity(entry.attributes.len());
+                for attribute in &entry.attributes {
+                    entry_forms.push(try!(choose_form(chooser, unit, u, attribute, max_offset)));
+                }
+                unit_forms.push(entry_forms);
+            }
+            forms.push(unit_forms);
+        }
+        Ok(forms)
+    }
+
+    // Build an abbreviation table for each unit.
+    //
+    // Returns .debug_abbrev, and the offset, table and entry codes for
+    // each unit.
+    fn write_abbrevs(
+        &self,
+        orders: &[Vec<Option<usize>>],
+        forms: &[Vec<Vec<constant::DwForm>>]
+    ) -> Result<(Vec<u8>, Vec<UnitAbbrevs>), WriteError> {
+        let mut debug_abbrev = Vec::new();
+        let mut tables = HashMap::new();
+        let mut result = Vec::with_capacity(self.units.len());
+        for (u, (unit, order)) in self.units.iter().zip(orders).enumerate() {
+            let mut abbrevs: Vec<Abbrev> = Vec::new();
+            let mut codes = vec![0; unit.entries.len()];
+            for &i in order.iter().filter_map(|i| i.as_ref()) {
+                let entry = &unit.entries[i];
+                let attributes: Vec<AbbrevAttribute> = try!(entry.attributes
+                    .iter()
+                    .zip(&forms[u][i])
+                    .map(|(attribute, &form)| abbrev_attribute(attribute, form))
+                    .collect());
+                let children = !entry.children.is_empty();
+                codes[i] = match abbrevs.iter().find(|abbrev| {
+                    abbrev.tag == entry.tag && abbrev.children == children &&
+                    abbrev.attributes == attributes
+                }) {
+                    Some(abbrev) => abbrev.code,
+                    None => {
+                        let code = abbrevs.len() as u64 + 1;
+                        abbrevs.push(Abbrev {
+                            code: code,
+                            tag: entry.tag,
+                            children: children,
+                            attributes: attributes,
+                        });
+                        code
+                    }
+                };
+            }
+
+            let mut table = Vec::new();
+            try!(AbbrevVec::new(abbrevs).write(&mut table));
+            let abbrev = try!(AbbrevHash::read(&mut &table[..]));
+            let offset = match tables.get(&table) {
+                Some(&offset) => offset,
+                None => {
+                    let offset = debug_abbrev.len();
+                    debug_abbrev.extend_from_slice(&table);
+                    tables.insert(table, offset);
+                    offset
+                }
+            };
+            result.push(UnitAbbrevs {
+                offset: offset,
+                abbrev: abbrev,
+                codes: codes,
+            });
+        }
+        Ok((debug_abbrev, result))
+    }
+
+    // Write .debug_info, resolving references using `offsets` if given.
+    fn layout(
+        &self,
+        orders: &[Vec<Option<usize>>],
+        forms: &[Vec<Vec<constant::DwForm>>],
+        abbrevs: &[UnitAbbrevs],
+        offsets: Option<&Vec<Vec<usize>>>
+    ) -> Result<(Vec<u8>, Vec<Vec<usize>>), WriteError> {
+        let mut data = Vec::new();
+        let mut new_offsets = Vec::with_capacity(self.units.len());
+        for (u, build_unit) in self.units.iter().enumerate() {
+            let abbrevs = &abbrevs[u];
+            let mut unit = CompilationUnit {
+                common: build_unit.header(self.endian, data.len(), abbrevs.offset as u64),
+            };
+            let unit_offset = unit.common.offset;
+            let data_offset = unit.data_offset();
+            let mut entry_offsets = vec![0; build_unit.entries.len()];
+            let mut unit_data = Vec::new();
+            for &i in &orders[u] {
+                let i = match i {
+                    Some(i) => i,
+                    None => {
+                        try!(Die::write_null(&mut unit_data));
+                        continue;
+                    }
+                };
+                entry_offsets[i] = data_offset + unit_data.len();
+                let entry = &build_unit.entries[i];
+                let mut attributes = Vec::with_capacity(entry.attributes.len());
+                for (attribute, &form) in entry.attributes.iter().zip(&forms[u][i]) {
+                    let data = match attribute.value {
+                        BuildValue::Data(ref data) => try!(encode(data, form)),
+                        BuildValue::Entry(target) => {
+                            let offset = match offsets {
+                                Some(offsets) => try!(entry_offset(offsets, target)),
+                                None => 0,
+                            };
+                            if form == constant::DW_FORM_ref_addr {
+                                AttributeData::RefAddress(DebugInfoOffset(offset as u64))
+                            } else if is_ref_form(form) {
+                                if target.unit != u {
+                                    return Err(WriteError::Invalid(format!(
+                                        "{} reference from unit {} to unit {}",
+                                        form, u, target.unit)));
+                                }
+                                let offset = offset.saturating_sub(unit_offset);
+                                try!(encode(&AttributeData::Ref(UnitOffset(offset as u64)), form))
+                            } else {
+                                return Err(WriteError::Invalid(format!(
+                                    "entry reference with form {}", form)));
+                            }
+                        }
+                    };
+                    attributes.push(Attribute {
+                        at: attribute.at,
+                        data: data,
+                    });
+                }
+                let die = Die::new(0,
+                                   abbrevs.codes[i],
+                                   entry.tag,
+                                   !entry.children.is_empty(),
+                                   attributes);
+                try!(die.write(&mut unit_data, &unit.common, &abbrevs.abbrev));
+            }
+            new_offsets.push(entry_offsets);
+            unit.common.data = &unit_data;
+            try!(unit.write(&mut data));
+        }
+        Ok((data, new_offsets))
+    }
+}
+
+fn choose_form<C: FormChooser>(
+    chooser: &C,
+    unit: &BuildUnit,
+    u: usize,
+    attribute: &BuildAttribute,
+    max_offset: u64
+) -> Result<constant::DwForm, WriteError> {
+    if let Some(form) = attribute.form {
+        return Ok(form);
+    }
+    let at = attribute.at;
+    let version = unit.version;
+    let data = match attribute.value {
+        BuildValue::Entry(target) if target.unit == u => {
+            return Ok(chooser.reference_form(at, max_offset, version));
+        }
+        BuildValue::Entry(_) => return Ok(constant::DW_FORM_ref_addr),
+        BuildValue::Data(ref data) => data,
+    };
+    let form = match *data {
+        AttributeData::Null => {
+            return Err(WriteError::Invalid(format!("null value for attribute {}", at)))
+        }
+        AttributeData::Address(_) => constant::DW_FORM_addr,
+        AttributeData::Block(val) => chooser.block_form(at, val.len(), version),
+        AttributeData::Data1(val) => chooser.udata_form(at, val as u64, version),
+        AttributeData::Data2(val) => chooser.udata_form(at, val as u64, version),
+        AttributeData::Data4(val) => chooser.udata_form(at, val as u64, version),
+        AttributeData::Data8(val) |
+        AttributeData::UData(val) => chooser.udata_form(at, val, version),
+        AttributeData::Data16(_) => constant::DW_FORM_data16,
+        AttributeData::SData(val) => chooser.sdata_form(at, val, version),
+        AttributeData::Flag(val) => chooser.flag_form(at, val, version),
+        AttributeData::String(_) => constant::DW_FORM_string,
+        AttributeData::StringOffset(_) => constant::DW_FORM_strp,
+        AttributeData::LineStringOffset(_) => constant::DW_FORM_line_strp,
+        AttributeData::Ref(_) => chooser.reference_form(at, max_offset, version),
+        AttributeData::RefAddress(_) => constant::DW_FORM_ref_addr,
+        AttributeData::RefSig(_) => constant::DW_FORM_ref_sig8,
+        AttributeData::AddressIndex(_) if version >= 5 => constant::DW_FORM_addrx,
+        AttributeData::AddressIndex(_) => constant::DW_FORM_GNU_addr_index,
+        AttributeData::StringIndex(_) if version >= 5 => constant::DW_FORM_strx,
+        AttributeData::StringIndex(_) => constant::DW_FORM_GNU_str_index,
+        AttributeData::RangeListIndex(_) => constant::DW_FORM_rnglistx,
+        AttributeData::LocationListIndex(_) => constant::DW_FORM_loclistx,
+        AttributeData::SecOffset(_) if version >= 4 => constant::DW_FORM_sec_offset,
+        AttributeData::SecOffset(_) if unit.offset_size == 8 => constant::DW_FORM_data8,
+        AttributeData::SecOffset(_) => constant::DW_FORM_data4,
+        AttributeData::ExprLoc(_) if version >= 4 => constant::DW_FORM_exprloc,
+        AttributeData::ExprLoc(val) => chooser.block_form(at, val.len(), version),
+    };
+    Ok(form)
+}
+
+// Convert a value to the variant of `AttributeData` for a form.
+//
+// Returns an error if the value doesn't fit the form.
+fn encode<'data>(
+    data: &AttributeData<'data>,
+    form: constant::DwForm
+) -> Result<AttributeData<'data>, WriteError> {
+    let constant = match *data {
+        AttributeData::Data1(val) => Some((val as u64, false)),
+        AttributeData::Data2(val) => Some((val as u64, false)),
+        AttributeData::Data4(val) => Some((val as u64, false)),
+        AttributeData::Data8(val) |
+        AttributeData::UData(val) => Some((val, false)),
+        AttributeData::SData(val) => Some((val as u64, true)),
+        AttributeData::SecOffset(val) => Some((val, false)),
+        _ => None,
+    };
+    // Whether the value fits in `bits`.
+    let fits = |val: u64, signed: bool, bits: u32| {
+        if signed {
+            let val = val as i64;
+            (val << (64 - bits)) >> (64 - bits) == val
+        } else {
+            bits == 64 || val >> bits == 0
+        }
+    };
+    let invalid = || {
+        Err(WriteError::Invalid(format!("value {:?} does not fit form {}", data, form)))
+    };
+    let result = match (data, constant, form) {
+        (_, Some((val, signed)), constant::DW_FORM_data1) if fits(val, signed, 8) => {
+            AttributeData::Data1(val as u8)
+        }
+        (_, Some((val, signed)), constant::DW_FORM_data2) if fits(val, signed, 16) => {
+            AttributeData::Data2(val as u16)
+        }
+        (_, Some((val, signed)), constant::DW_FORM_data4) if fits(val, signed, 32) => {
+            AttributeData::Data4(val as u32)
+        }
+        (_, Some((val, _)), constant::DW_FORM_data8) => AttributeData::Data8(val),
+        (_, Some((val, false)), constant::DW_FORM_udata) => AttributeData::UData(val),
+        (_, Some((val, _)), constant::DW_FORM_sdata) => AttributeData::SData(val as i64),
+        (_, Some((val, signed)), constant::DW_FORM_implicit_const) if signed ||
+                                                                      fits(val, false, 63) => {
+            AttributeData::SData(val as i64)
+        }
+        (_, Some(_), constant::DW_FORM_data1) |
+        (_, Some(_), constant::DW_FORM_data2) |
+        (_, Some(_), constant::DW_FORM_data4) => return invalid(),
+        (&AttributeData::Block(val), _, constant::DW_FORM_exprloc) |
+        (&AttributeData::ExprLoc(val), _, constant::DW_FORM_exprloc) => {
+            AttributeData::ExprLoc(val)
+        }
+        (&AttributeData::Block(val), _, _) |
+        (&AttributeData::ExprLoc(val), _, _) => {
+            let bits = match form {
+                constant::DW_FORM_block1 => 8,
+                constant::DW_FORM_block2 => 16,
+                constant::DW_FORM_block4 => 32,
+                _ => 64,
+            };
+            if !fits(val.len() as u64, false, bits) {
+                return invalid();
+            }
+            AttributeData::Block(val)
+        }
+        (&AttributeData::Flag(false), _, constant::DW_FORM_flag_present) => return invalid(),
+        (&AttributeData::Ref(val), _, _) => {
+            let bits = match form {
+                constant::DW_FORM_ref1 => 8,
+                constant::DW_FORM_ref2 => 16,
+                constant::DW_FORM_ref4 => 32,
+                _ => 64,
+            };
+            if !fits(val.0, false, bits) {
+                return invalid();
+            }
+            AttributeData::Ref(val)
+        }
+        _ => data.clone(),
+    };
+    Ok(result)
+}
+
+// Return the abbreviation attribute for an attribute with the given form.
+//
+// The value of a DW_FORM_implicit_const attribute is stored in the
+// abbreviation, so entries only share the abbreviation if their values
+// are the same.
+fn abbrev_attribute(
+    attribute: &BuildAttribute,
+    form: constant::DwForm
+) -> Result<AbbrevAttribute, WriteError> {
+    if form != constant::DW_FORM_implicit_const {
+        return Ok(AbbrevAttribute::new(attribute.at, form));
+    }
+    let data = match attribute.value {
+        BuildValue::Data(ref data) => try!(encode(data, form)),
+        BuildValue::Entry(_) => {
+            return Err(WriteError::Invalid(format!("entry reference with form {}", form)));
+        }
+    };
+    match data {
+        AttributeData::SData(val) => Ok(AbbrevAttribute::implicit_const(attribute.at, val)),
+        _ => Err(WriteError::Invalid(format!("value {:?} does not fit form {}", data, form))),
+    }
+}
+
+struct UnitAbbrevs {
+    offset: usize,
+    abbrev: AbbrevHash,
+    // The abbreviation code of each entry.
+    codes: Vec<u64>,
+}
+
+// The entries of a subtree in the order they are written, with `None`
+// for the null entries that end each list of children.
+fn entry_order(unit: &BuildUnit, entry: usize, order: &mut Vec<Option<usize>>) {
+    order.push(Some(entry));
+    let children = &unit.entries[entry].children;
+    if !children.is_empty() {
+        for &child in children {
+            entry_order(unit, child, order);
+        }
+        order.push(None);
+    }
+}
+
+fn entry_offset(offsets: &[Vec<usize>], target: EntryId) -> Result<usize, WriteError> {
+    match offsets.get(target.unit).and_then(|unit| unit.get(target.entry)) {
+        Some(&offset) => Ok(offset),
+        None => Err(WriteError::Invalid(format!("invalid entry reference {:?}", target))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use constant::*;
+    use die::DieRef;
+    use endian::*;
+    use write::WriteError;
+    use Sections;
+
+    #[test]
+    fn cross_unit_references() {
+        let mut builder = SectionsBuilder::new(LittleEndian);
+        let root1 = builder.add_unit(4, 8, 4, DW_TAG_compile_unit);
+        let root2 = builder.add_unit(4, 8, 4, DW_TAG_compile_unit);
+
+        // Refer to an entry before it is added.
+        let var = builder.add_entry(root1, DW_TAG_variable);
+        let ty = EntryId {
+            unit: 1,
+            entry: 1,
+        };
+        builder.add_attribute(var, DW_AT_type, DW_FORM_ref_addr, BuildValue::Entry(ty));
+        let name = builder.add_string(b"int");
+        assert_eq!(builder.add_string(b"int"), name);
+        assert_eq!(builder.add_entry(root2, DW_TAG_base_type), ty);
+        builder.add_attribute(ty,
+                              DW_AT_name,
+                              DW_FORM_strp,
+                              BuildValue::Data(AttributeData::StringOffset(name)));
+        let pointer = builder.add_entry(root2, DW_TAG_pointer_type);
+        builder.add_attribute(pointer, DW_AT_type, DW_FORM_ref_udata, BuildValue::Entry(ty));
+
+        let (sections, offsets) = builder.write().unwrap();
+        assert_eq!(&sections.debug_str[..], b"int\0");
+        let mut units = sections.compilation_units();
+        let unit1 = units.next().unwrap().unwrap();
+        let unit2 = units.next().unwrap().unwrap();
+        assert!(units.next().unwrap().is_none());
+        assert_eq!(offsets[0][0], unit1.data_offset());
+        assert_eq!(offsets[1][0], unit2.data_offset());
+
+        let entry = DieRef::new(&sections, 0, offsets[0][1]).entry().unwrap();
+        assert_eq!(entry.tag, DW_TAG_variable);
+        let target = match entry.attr(DW_AT_type) {
+            Some(&AttributeData::RefAddress(offset)) => offset.0 as usize,
+            otherwise => panic!("{:?}", otherwise),
+        };
+        assert_eq!(target, offsets[1][1]);
+        let entry = DieRef::new(&sections, unit2.common.offset, target).entry().unwrap();
+        assert_eq!(entry.tag, DW_TAG_base_type);
+
+        let entry = DieRef::new(&sections, unit2.common.offset, offsets[1][2]).entry().unwrap();
+        match entry.attr(DW_AT_type) {
+            Some(&AttributeData::Ref(offset)) => {
+                assert_eq!(unit2.common.offset + offset.0 as usize, target)
+            }
+            otherwise => panic!("{:?}", otherwise),
+        }
+    }
+
+    #[test]
+    fn version_5() {
+        let mut builder = SectionsBuilder::new(LittleEndian);
+        let root = builder.add_unit(5, 8, 4, DW_TAG_compile_unit);
+        let ty = builder.add_entry(root, DW_TAG_base_type);
+        let var = builder.add_entry(root, DW_TAG_variable);
+        builder.add_value(var, DW_AT_type, BuildValue::Entry(ty));
+        for &entry in &[ty, var] {
+            builder.add_attribute(entry,
+                                  DW_AT_decl_file,
+                                  DW_FORM_implicit_const,
+                                  BuildValue::Data(AttributeData::SData(-2)));
+        }
+
+        let (sections, offsets) = builder.write().unwrap();
+        let unit = sections.compilation_units().next().unwrap().unwrap();
+        assert_eq!(unit.common.version, 5);
+        assert_eq!(offsets[0][0], unit.data_offset());
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let entry = DieRef::new(&sections, 0, offsets[0][2]).entry().unwrap();
+        assert_eq!(entry.attr(DW_AT_decl_file), Some(&AttributeData::SData(-2)));
+        let ty = match entry.attr(DW_AT_type) {
+            Some(&AttributeData::Ref(offset)) => offset.0 as usize,
+            otherwise => panic!("{:?}", otherwise),
+        };
+        assert_eq!(ty, offsets[0][1]);
+        assert_eq!(abbrev.get(2).unwrap().attributes[0],
+                   AbbrevAttribute::implicit_const(DW_AT_decl_file, -2));
+    }
+
+    // Add the same entries using `add_value`, or using the largest forms.
+    fn values(naive: bool) -> SectionsBuilder<'static, LittleEndian> {
+        static BLOCK: [u8; 300] = [0; 300];
+        let mut builder = SectionsBuilder::new(LittleEndian);
+        let root = builder.add_unit(4, 8, 4, DW_TAG_compile_unit);
+        let ty = builder.add_entry(root, DW_TAG_base_type);
+        let values = [
+            (DW_AT_byte_size, DW_FORM_data8, AttributeData::UData(4)),
+            (DW_AT_bit_size, DW_FORM_data8, AttributeData::UData(0x1234)),
+            (DW_AT_bit_offset, DW_FORM_data8, AttributeData::UData(0x1_0000_0000)),
+            (DW_AT_lower_bound, DW_FORM_sdata, AttributeData::SData(-3)),
+            (DW_AT_upper_bound, DW_FORM_sdata, AttributeData::SData(7)),
+            (DW_AT_external, DW_FORM_flag, AttributeData::Flag(true)),
+            (DW_AT_location, DW_FORM_block4, AttributeData::Block(&BLOCK[..2])),
+            (DW_AT_const_value, DW_FORM_block4, AttributeData::Block(&BLOCK)),
+        ];
+        for &(at, form, ref data) in &values {
+            if naive {
+                builder.add_attribute(ty, at, form, BuildValue::Data(data.clone()));
+            } else {
+                builder.add_value(ty, at, BuildValue::Data(data.clone()));
+            }
+        }
+        let var = builder.add_entry(root, DW_TAG_variable);
+        if naive {
+            builder.add_attribute(var, DW_AT_type, DW_FORM_ref4, BuildValue::Entry(ty));
+        } else {
+            builder.add_value(var, DW_AT_type, BuildValue::Entry(ty));
+        }
+        builder
+    }
+
+    // Read the attributes of the base type, and the type of the variable.
+    fn read_values<'a>(sections: &'a Sections<LittleEndian>) -> (Vec<Attribute<'a>>, usize) {
+        let unit = sections.compilation_units().next().unwrap().unwrap();
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let mut entries = unit.entries(&abbrev);
+        entries.next().unwrap();
+        let attributes = entries.next().unwrap().unwrap().attributes.clone();
+        let ty = match *entries.next().unwrap().unwrap().attr(DW_AT_type).unwrap() {
+            AttributeData::Ref(offset) => offset.0 as usize,
+            ref otherwise => panic!("{:?}", otherwise),
+        };
+        (attributes, ty)
+    }
+
+    #[test]
+    fn form_chooser() {
+        let (naive, _) = values(true).write().unwrap();
+        let (smallest, _) = values(false).write().unwrap();
+        let (fixed, _) = values(false).write_with(&FixedSizeForms).unwrap();
+        assert!(smallest.debug_info.len() < fixed.debug_info.len());
+        assert!(fixed.debug_info.len() < naive.debug_info.len());
+
+        let (naive, naive_ty) = read_values(&naive);
+        for sections in &[smallest, fixed] {
+            let (attributes, ty) = read_values(sections);
+            assert_eq!(ty, naive_ty);
+            assert_eq!(attributes.len(), naive.len());
+            for (attribute, expect) in attributes.iter().zip(&naive) {
+                assert_eq!(attribute.at, expect.at);
+                match (&attribute.data, &expect.data) {
+                    (&AttributeData::SData(val), &AttributeData::SData(expect)) => {
+                        assert_eq!(val, expect)
+                    }
+                    (data, &AttributeData::SData(expect)) => {
+                        assert!(expect >= 0);
+                        assert_eq!(data.as_udata(), Some(expect as u64))
+                    }
+                    (data, &AttributeData::Data8(expect)) => {
+                        assert_eq!(data.as_udata(), Some(expect))
+                    }
+                    (data, expect) => assert_eq!(data, expect),
+                }
+            }
+        }
+
+        let forms: Vec<DwForm> = [4, 0x1234, 0x1_0000_0000, 0xffff_ffff]
+            .iter()
+            .map(|&val| SmallestForms.udata_form(DW_AT_byte_size, val, 4))
+            .collect();
+        assert_eq!(forms, vec![DW_FORM_data1, DW_FORM_data2, DW_FORM_udata, DW_FORM_data4]);
+        assert_eq!(SmallestForms.udata_form(DW_AT_byte_size, 0x1_0000, 3), DW_FORM_udata);
+        assert_eq!(SmallestForms.flag_form(DW_AT_external, true, 3), DW_FORM_flag);
+        assert_eq!(SmallestForms.block_form(DW_AT_location, 300, 4), DW_FORM_block2);
+        assert_eq!(SmallestForms.reference_form(DW_AT_type, 0x100, 4), DW_FORM_ref2);
+        assert_eq!(FixedSizeForms.sdata_form(DW_AT_lower_bound, -1, 4), DW_FORM_sdata);
+        assert_eq!(FixedSizeForms.sdata_form(DW_AT_lower_bound, 0xffff_ffff, 4), DW_FORM_data4);
+        assert_eq!(FixedSizeForms.sdata_form(DW_AT_lower_bound, 1 << 32, 4), DW_FORM_data8);
+
+        // Explicit forms must fit the value.
+        let mut builder = SectionsBuilder::new(LittleEndian);
+        let root = builder.add_unit(4, 8, 4, DW_TAG_compile_unit);
+        builder.add_attribute(root,
+                              DW_AT_byte_size,
+                              DW_FORM_data1,
+                              BuildValue::Data(AttributeData::UData(0x100)));
+        match builder.write() {
+            Err(WriteError::Invalid(_)) => {}
+            otherwise => panic!("{:?}", otherwise),
+        }
+    }
+
+    #[test]
+    fn invalid_references() {
+        let mut builder = SectionsBuilder::new(LittleEndian);
+        let root1 = builder.add_unit(4, 8, 4, DW_TAG_compile_unit);
+        let root2 = builder.add_unit(4, 8, 4, DW_TAG_compile_unit);
+        let var = builder.add_entry(root1, DW_TAG_variable);
+        builder.add_attribute(var, DW_AT_type, DW_FORM_ref4, BuildValue::Entry(root2));
+        match builder.write() {
+            Err(WriteError::Invalid(_)) => {}
+            otherwise => panic!("{:?}", otherwise),
+        }
+
+        let mut builder = SectionsBuilder::new(LittleEndian);
+        let root = builder.add_unit(4, 8, 4, DW_TAG_compile_unit);
+        let missing = EntryId {
+            unit: 0,
+            entry: 5,
+        };
+        builder.add_attribute(root, DW_AT_type, DW_FORM_ref_addr, BuildValue::Entry(missing));
+        match builder.write() {
+            Err(WriteError::Invalid(_)) => {}
+            otherwise => panic!("{:?}", otherwise),
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use std;
+use std::ops::Deref;
+
+use abbrev::AbbrevHash;
+use constant;
+use endian::Endian;
+use read::*;
+use unit::UnsupportedUnit;
+use Sections;
+
+// A part of DWARF that this crate may or may not be able to read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    // Unit headers in .debug_info.
+    CompilationUnit,
+    // Unit headers in .debug_types. Version 5 type units are in
+    // .debug_info instead.
+    TypeUnit,
+    // Line program headers in .debug_line.
+    LineProgram,
+    // Attribute values with a form.
+    Form(constant::DwForm),
+    // A section, such as ".debug_str", that `Sections` holds.
+    Section(&'static str),
+}
+
+// The unit versions that can be read.
+pub const UNIT_VERSIONS: [u16; 4] = [2, 3, 4, 5];
+
+// The line program versions that can be read.
+pub const LINE_VERSIONS: [u16; 4] = [2, 3, 4, 5];
+
+// The forms that `AttributeData::read` can decode.
+pub const FORMS: [constant::DwForm; 42] = [
+    constant::DW_FORM_addr,
+    constant::DW_FORM_block2,
+    constant::DW_FORM_block4,
+    constant::DW_FORM_data2,
+    constant::DW_FORM_data4,
+    constant::DW_FORM_data8,
+    constant::DW_FORM_string,
+    constant::DW_FORM_block,
+    constant::DW_FORM_block1,
+    constant::DW_FORM_data1,
+    constant::DW_FORM_flag,
+    constant::DW_FORM_sdata,
+    constant::DW_FORM_strp,
+    constant::DW_FORM_udata,
+    constant::DW_FORM_ref_addr,
+    constant::DW_FORM_ref1,
+    constant::DW_FORM_ref2,
+    constant::DW_FORM_ref4,
+    constant::DW_FORM_ref8,
+    constant::DW_FORM_ref_udata,
+    constant::DW_FORM_indirect,
+    constant::DW_FORM_sec_offset,
+    constant::DW_FORM_exprloc,
+    constant::DW_FORM_flag_present,
+    constant::DW_FORM_strx,
+    constant::DW_FORM_addrx,
+    constant::DW_FORM_data16,
+    constant::DW_FORM_line_strp,
+    constant::DW_FORM_ref_sig8,
+    constant::DW_FORM_implicit_const,
+    constant::DW_FORM_strx1,
+    constant::DW_FORM_strx2,
+    constant::DW_FORM_strx3,
+    constant::DW_FORM_strx4,
+    constant::DW_FORM_addrx1,
+    constant::DW_FORM_addrx2,
+    constant::DW_FORM_addrx3,
+    constant::DW_FORM_addrx4,
+    constant::DW_FORM_GNU_addr_index,
+    constant::DW_FORM_GNU_str_index,
+    constant::DW_FORM_rnglistx,
+    constant::DW_FORM_loclistx,
+];
+
+// The sections in `Sections`.
+pub const SECTIONS: [&str; 14] = [
+    ".debug_abbrev",
+    ".debug_addr",
+    ".debug_aranges",
+    ".debug_frame",
+    ".debug_info",
+    ".debug_line",
+    ".debug_line_str",
+    ".debug_loc",
+    ".debug_loclists",
+    ".debug_ranges",
+    ".debug_rnglists",
+    ".debug_str",
+    ".debug_str_offsets",
+    ".debug_types",
+];
+
+// Return true if this crate can read `feature` in a unit of `version`.
+//
+// Forms and sections are supported for every unit version that can be
+// read, even if they were only defined by a later version, since
+// producers use them as extensions.
+pub fn supports(version: u16, feature: Feature) -> bool {
+    if !UNIT_VERSIONS.contains(&version) {
+        return false;
+    }
+    match feature {
+        Feature::CompilationUnit => true,
+        Feature::TypeUnit => version < 5,
+        Feature::LineProgram => LINE_VERSIONS.contains(&version),
+        Feature::Form(form) => FORMS.contains(&form),
+        Feature::Section(name) => SECTIONS.contains(&name),
+    }
+}
+
+// A summary of the versions, forms and sections that this crate can
+// read, for tools that report their own capabilities.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    pub unit_versions: Vec<u16>,
+    pub line_versions: Vec<u16>,
+    pub forms: Vec<constant::DwForm>,
+    pub sections: Vec<&'static str>,
+}
+
+impl Capabilities {
+    // The capabilities of this crate.
+    pub fn new() -> Self {
+        Capabilities {
+            unit_versions: UNIT_VERSIONS.to_vec(),
+            line_versions: LINE_VERSIONS.to_vec(),
+            forms: FORMS.to_vec(),
+            sections: SECTIONS.to_vec(),
+        }
+    }
+
+    // Find the parts of `sections` that can't be read.
+    //
+    // This finds units and line programs with an unsupported version, and
+    // abbrev tables that use an unsupported form. Each abbrev table and
+    // line program is only reported once, even if many units use it.
+    pub fn check<E, S>(sections: &Sections<E, S>) -> Result<Vec<UnsupportedFeature>, ReadError>
+        where E: Endian,
+              S: Deref<Target = [u8]>
+    {
+        let mut check = Check::default();
+        let mut units = sections.compilation_units();
+        units.skip_unsupported();
+        while let Some(unit) = try!(units.next()) {
+            let abbrev = match sections.abbrev(&unit.common) {
+                Ok(abbrev) => abbrev,
+                Err(_) => continue,
+            };
+            check.abbrev(unit.common.abbrev_offset as usize, unit.common.version, &abbrev);
+
+            let context = sections.attribute_context(&unit.common);
+            let mut entries = unit.entries(&abbrev);
+            let offset = match entries.next() {
+                Ok(Some(entry)) => {
+                    entry.attr(constant::DW_AT_stmt_list)
+                        .and_then(|data| data.as_offset_in(&context))
+                }
+                _ => None,
+            };
+            if let Some(offset) = offset {
+                check.line_program(&*sections.debug_line, offset, sections.endian);
+            }
+        }
+        check.units(".debug_info", Feature::CompilationUnit, units.unsupported());
+
+        let mut units = sections.type_units();
+        units.skip_unsupported();
+        while let Some(unit) = try!(units.next()) {
+            if let Ok(abbrev) = sections.abbrev(&unit.common) {
+                check.abbrev(unit.common.abbrev_offset as usize, unit.common.version, &abbrev);
+            }
+        }
+        check.units(".debug_types", Feature::TypeUnit, units.unsupported());
+
+        Ok(check.unsupported)
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities::new()
+    }
+}
+
+// A part of the sections of a file that can't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedFeature {
+    pub section: &'static str,
+    // The offset of the unit, abbrev table or line program.
+    pub offset: usize,
+    pub feature: Feature,
+    pub version: u16,
+}
+
+#[derive(Debug, Default)]
+struct Check {
+    unsupported: Vec<UnsupportedFeature>,
+    abbrev_offsets: Vec<usize>,
+    line_offsets: Vec<usize>,
+}
+
+impl Check {
+    fn add(&mut self, section: &'static str, offset: usize, feature: Feature, version: u16) {
+        self.unsupported.push(UnsupportedFeature {
+            section: section,
+            offset: offset,
+            feature: feature,
+            version: version,
+        });
+    }
+
+    fn units(&mut self, section: &'static str, feature: Feature, units: &[UnsupportedUnit]) {
+        for unit in units {
+            self.add(section, unit.offset, feature, unit.version);
+        }
+    }
+
+    fn abbrev(&mut self, offset: usize, version: u16, abbrev_hash: &AbbrevHash) {
+        if self.abbrev_offsets.contains(&offset) {
+            return;
+        }
+        self.abbrev_offsets.push(offset);
+        let mut forms = Vec::new();
+        for (_, abbrev) in abbrev_hash.iter() {
+            for attribute in &abbrev.attributes {
+                let feature = Feature::Form(attribute.form);
+                if !supports(version, feature) && !forms.contains(&attribute.form) {
+                    forms.push(attribute.form);
+                }
+            }
+        }
+        forms.sort_by_key(|form| form.0);
+        for form in forms {
+            self.add(".debug_abbrev", offset, Feature::Form(form), version);
+        }
+    }
+
+    fn line_program<E: Endian>(&mut self, debug_line: &[u8], offset: usize, endian: E) {
+        if self.line_offsets.contains(&offset) || offset >= debug_line.len() {
+            return;
+        }
+        self.line_offsets.push(offset);
+        let mut r = &debug_line[offset..];
+        let version = match read_initial_length(&mut r, endian)
+            .and_then(|_| endian.read_u16(&mut r)) {
+            Ok(version) => version,
+            // The read errors are reported by `LineProgram::read`.
+            Err(_) => return,
+        };
+        if !LINE_VERSIONS.contains(&version) {
+            self.add(".debug_line", offset, Feature::LineProgram, version);
+        }
+    }
+}
+
+impl std::fmt::Display for Feature {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Feature::CompilationUnit => write!(f, "compilation unit"),
+            Feature::TypeUnit => write!(f, "type unit"),
+            Feature::LineProgram => write!(f, "line program"),
+            Feature::Form(form) => write!(f, "form {}", form),
+            Feature::Section(name) => write!(f, "section {}", name),
+        }
+    }
+}
+
+impl std::fmt::Display for UnsupportedFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f,
+               "{}+0x{:x}: {} is not supported in version {}",
+               self.section,
+               self.offset,
+               self.feature,
+               self.version)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use constant::*;
+    use die::AttributeData;
+    use endian::LittleEndian;
+    use unit::UnitCommon;
+
+    #[test]
+    fn forms_match_read() {
+        let unit = UnitCommon::<LittleEndian>::default();
+        let data = [0; 16];
+        for form in 1..0x30 {
+            let form = DwForm(form);
+            let result = AttributeData::read(&mut &data[..], &unit, form);
+            let unsupported = matches!(result, Err(ReadError::Unsupported));
+            // DW_FORM_indirect reads form 0 from the data.
+            if form != DW_FORM_indirect {
+                assert_eq!(supports(4, Feature::Form(form)), !unsupported, "{}", form);
+            }
+        }
+        assert!(supports(4, Feature::Form(DW_FORM_indirect)));
+        assert!(!supports(4, Feature::Form(DW_FORM_null)));
+        assert!(!supports(6, Feature::Form(DW_FORM_data1)));
+        assert!(supports(2, Feature::Section(".debug_str")));
+        assert!(supports(4, Feature::Section(".debug_rnglists")));
+        assert!(!supports(4, Feature::Section(".debug_macro")));
+        assert!(supports(4, Feature::TypeUnit));
+        assert!(!supports(5, Feature::TypeUnit));
+    }
+
+    #[test]
+    fn check() {
+        let mut sections = ::testing::all(LittleEndian).remove(0).1;
+        assert_eq!(Capabilities::check(&sections).unwrap(), vec![]);
+
+        // Change the version of the first unit.
+        sections.debug_info[4] = 6;
+        let unsupported = Capabilities::check(&sections).unwrap();
+        assert_eq!(unsupported,
+                   vec![UnsupportedFeature {
+                            section: ".debug_info",
+                            offset: 0,
+                            feature: Feature::CompilationUnit,
+                            version: 6,
+                        }]);
+        assert_eq!(unsupported[0].to_string(),
+                   ".debug_info+0x0: compilation unit is not supported in version 6");
+    }
+}
@@ -0,0 +1,291 @@
+use std;
+
+use endian::Endian;
+use read::*;
+
+// The default size of the buffer of a `ChunkReader`.
+pub const DEFAULT_CHUNK_SIZE: usize = 256;
+
+// Section data that is read on demand.
+//
+// This is for environments that can't map whole sections in memory, such
+// as a device reading debug info from flash.
+pub trait ReadAt {
+    // The size of the section.
+    fn len(&self) -> usize;
+
+    // Whether the section is empty.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Fill `buf` with the data starting at `offset`.
+    //
+    // Returns `ReadError::Eof` if the section is too short.
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<(), ReadError>;
+}
+
+impl ReadAt for [u8] {
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<(), ReadError> {
+        if offset > self.len() || buf.len() > self.len() - offset {
+            return Err(ReadError::Eof);
+        }
+        buf.copy_from_slice(&self[offset..offset + buf.len()]);
+        Ok(())
+    }
+}
+
+impl ReadAt for Vec<u8> {
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<(), ReadError> {
+        self[..].read_at(offset, buf)
+    }
+}
+
+// Reads from a `ReadAt` through a small buffer.
+//
+// Each read that isn't contained in the buffer refills the buffer with a
+// chunk starting at the read offset. The buffer grows if a single read is
+// larger than the chunk size.
+#[derive(Debug)]
+pub struct ChunkReader<'a, R: ReadAt + ?Sized + 'a> {
+    source: &'a R,
+    chunk_size: usize,
+    buf: Vec<u8>,
+    // The offset in the source of the start of `buf`.
+    buf_offset: usize,
+}
+
+impl<'a, R: ReadAt + ?Sized> ChunkReader<'a, R> {
+    pub fn new(source: &'a R) -> Self {
+        ChunkReader::with_chunk_size(source, DEFAULT_CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(source: &'a R, chunk_size: usize) -> Self {
+        ChunkReader {
+            source: source,
+            chunk_size: chunk_size,
+            buf: Vec::new(),
+            buf_offset: 0,
+        }
+    }
+
+    // The size of the source.
+    pub fn len(&self) -> usize {
+        self.source.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.source.len() == 0
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    // Return the `len` bytes starting at `offset`.
+    pub fn read(&mut self, offset: usize, len: usize) -> Result<&[u8], ReadError> {
+        let end = try!(offset.checked_add(len).ok_or(ReadError::Eof));
+        if end > self.source.len() {
+            return Err(ReadError::Eof);
+        }
+        if offset < self.buf_offset || end > self.buf_offset + self.buf.len() {
+            let size = std::cmp::max(len, self.chunk_size);
+            let size = std::cmp::min(size, self.source.len() - offset);
+            self.buf.resize(size, 0);
+            if let Err(e) = self.source.read_at(offset, &mut self.buf) {
+                self.buf.clear();
+                return Err(e);
+            }
+            self.buf_offset = offset;
+        }
+        let start = offset - self.buf_offset;
+        Ok(&self.buf[start..start + len])
+    }
+
+    // Return the bytes starting at `offset`, including at least `len`
+    // bytes or the rest of the source if that is shorter.
+    //
+    // This also returns any following bytes that are already in the
+    // buffer, so that readers of variable length data can avoid refilling
+    // the buffer for each item.
+    pub fn fill(&mut self, offset: usize, len: usize) -> Result<&[u8], ReadError> {
+        if offset > self.source.len() {
+            return Err(ReadError::Eof);
+        }
+        let len = std::cmp::min(len, self.source.len() - offset);
+        try!(self.read(offset, len));
+        Ok(&self.buf[offset - self.buf_offset..])
+    }
+}
+
+// An address range in .debug_aranges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arange {
+    // The offset of the compilation unit in .debug_info.
+    pub unit_offset: usize,
+    pub address: u64,
+    pub length: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ArangeSet {
+    unit_offset: usize,
+    address_size: u8,
+    segment_size: usize,
+    // The offset of the end of the set.
+    end: usize,
+}
+
+// An iterator over the address ranges in .debug_aranges, which reads the
+// section through a `ChunkReader`.
+#[derive(Debug)]
+pub struct ArangeIterator<'a, E: Endian, R: ReadAt + ?Sized + 'a> {
+    reader: ChunkReader<'a, R>,
+    endian: E,
+    offset: usize,
+    set: Option<ArangeSet>,
+}
+
+impl<'a, E: Endian, R: ReadAt + ?Sized> ArangeIterator<'a, E, R> {
+    pub fn new(reader: ChunkReader<'a, R>, endian: E) -> Self {
+        ArangeIterator {
+            reader: reader,
+            endian: endian,
+            offset: 0,
+            set: None,
+        }
+    }
+
+    // The offset in .debug_aranges of the next tuple or set.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
+    pub fn next(&mut self) -> Result<Option<Arange>, ReadError> {
+        loop {
+            if let Some(set) = self.set {
+                let address_size = set.address_size as usize;
+                if self.offset + set.segment_size + 2 * address_size <= set.end {
+                    let mut r = try!(self.reader
+                        .read(self.offset + set.segment_size, 2 * address_size));
+                    let address = try!(read_address(&mut r, self.endian, set.address_size));
+                    let length = try!(read_address(&mut r, self.endian, set.address_size));
+                    self.offset += set.segment_size + 2 * address_size;
+                    if address != 0 || length != 0 {
+                        return Ok(Some(Arange {
+                            unit_offset: set.unit_offset,
+                            address: address,
+                            length: length,
+                        }));
+                    }
+                }
+                // Anything after the terminating tuple is padding.
+                self.offset = set.end;
+                self.set = None;
+            }
+            if self.offset >= self.reader.len() {
+                return Ok(None);
+            }
+            self.set = Some(try!(self.read_set_header()));
+        }
+    }
+
+    fn read_set_header(&mut self) -> Result<ArangeSet, ReadError> {
+        let start = self.offset;
+        let len = self.reader.len();
+        // The size of the header fields when using the 64-bit format.
+        let header = try!(self.reader.fill(start, 12 + 2 + 8 + 2));
+        let mut r = header;
+        let (offset_size, set_len) = try!(read_unit_length(&mut r, self.endian));
+        let end = try!((start + header.len() - r.len())
+            .checked_add(set_len)
+            .ok_or(ReadError::Invalid));
+        if end > len {
+            return Err(ReadError::Invalid);
+        }
+        let version = try!(self.endian.read_u16(&mut r));
+        if version != 2 {
+            return Err(ReadError::UnsupportedVersion(version));
+        }
+        let unit_offset = try!(read_offset(&mut r, self.endian, offset_size)) as usize;
+        let address_size = try!(read_u8(&mut r));
+        let segment_size = try!(read_u8(&mut r)) as usize;
+        if address_size == 0 {
+            return Err(ReadError::Invalid);
+        }
+
+        // Tuples are aligned to twice the address size, relative to the
+        // start of the set.
+        let tuple_size = 2 * address_size as usize;
+        let mut position = header.len() - r.len();
+        position += (tuple_size - position % tuple_size) % tuple_size;
+        self.offset = start + position;
+        Ok(ArangeSet {
+            unit_offset: unit_offset,
+            address_size: address_size,
+            segment_size: segment_size,
+            end: end,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use endian::*;
+
+    #[test]
+    fn chunk_reader() {
+        let data: Vec<u8> = (0..10).collect();
+        let mut reader = ChunkReader::with_chunk_size(&data, 4);
+        assert_eq!(reader.read(1, 2).unwrap(), &[1, 2]);
+        assert_eq!(reader.fill(2, 1).unwrap(), &[2, 3, 4]);
+        assert_eq!(reader.read(3, 6).unwrap(), &[3, 4, 5, 6, 7, 8]);
+        assert_eq!(reader.fill(8, 4).unwrap(), &[8, 9]);
+        assert_eq!(reader.fill(10, 4).unwrap(), &[]);
+        assert!(reader.read(8, 3).is_err());
+        assert!(reader.fill(11, 1).is_err());
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn aranges() {
+        let debug_aranges = [
+            // Unit 0x10, 4 byte addresses, aligned to 8 bytes.
+            0x24, 0, 0, 0, 2, 0, 0x10, 0, 0, 0, 4, 0, 0, 0, 0, 0,
+            0x00, 0x10, 0, 0, 0x20, 0, 0, 0,
+            0x00, 0x20, 0, 0, 0x10, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+            // Unit 0x40, 8 byte addresses, aligned to 16 bytes.
+            0x2c, 0, 0, 0, 2, 0, 0x40, 0, 0, 0, 8, 0, 0, 0, 0, 0,
+            0x00, 0x30, 0, 0, 0, 0, 0, 0, 0x08, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        for chunk_size in 1..debug_aranges.len() + 1 {
+            let reader = ChunkReader::with_chunk_size(&debug_aranges[..], chunk_size);
+            let mut aranges = ArangeIterator::new(reader, LittleEndian);
+            let mut result = Vec::new();
+            while let Some(arange) = aranges.next().unwrap() {
+                result.push(arange);
+            }
+            assert_eq!(result, vec![
+                Arange { unit_offset: 0x10, address: 0x1000, length: 0x20 },
+                Arange { unit_offset: 0x10, address: 0x2000, length: 0x10 },
+                Arange { unit_offset: 0x40, address: 0x3000, length: 0x8 },
+            ]);
+        }
+
+        let reader = ChunkReader::new(&debug_aranges[..20]);
+        let mut aranges = ArangeIterator::new(reader, LittleEndian);
+        assert!(aranges.next().is_err());
+    }
+}
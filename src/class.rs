@@ -0,0 +1,256 @@
+use constant;
+
+// The classes of attribute values, as defined in section 7.5.4 of the
+// DWARF 5 standard.
+//
+// The DWARF 4 `loclistptr` and `rangelistptr` classes are represented by
+// `LocList` and `RangeList`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeClass {
+    Address,
+    AddrPtr,
+    Block,
+    Constant,
+    ExprLoc,
+    Flag,
+    LinePtr,
+    LocList,
+    LocListsPtr,
+    MacPtr,
+    RangeList,
+    RangeListsPtr,
+    Reference,
+    String,
+    StrOffsetsPtr,
+}
+
+use self::AttributeClass::*;
+
+// Return the classes that a value encoded with the form may belong to.
+//
+// This is the union over all DWARF versions, so that it can be used without
+// knowing the unit version. In particular, DWARF 2 and 3 used `data4`
+// and `data8` for section offsets, and blocks for location expressions.
+//
+// Returns `None` for `DW_FORM_indirect`, since the actual form is only
+// known when reading the DIE.
+pub fn form_classes(form: constant::DwForm) -> Option<&'static [AttributeClass]> {
+    let classes: &'static [AttributeClass] = match form {
+        constant::DW_FORM_addr |
+        constant::DW_FORM_addrx |
+        constant::DW_FORM_addrx1 |
+        constant::DW_FORM_addrx2 |
+        constant::DW_FORM_addrx3 |
+        constant::DW_FORM_addrx4 |
+        constant::DW_FORM_GNU_addr_index => &[Address],
+        constant::DW_FORM_block1 |
+        constant::DW_FORM_block2 |
+        constant::DW_FORM_block4 |
+        constant::DW_FORM_block => &[Block, ExprLoc],
+        constant::DW_FORM_data1 |
+        constant::DW_FORM_data2 |
+        constant::DW_FORM_data16 |
+        constant::DW_FORM_sdata |
+        constant::DW_FORM_udata |
+        constant::DW_FORM_implicit_const => &[Constant],
+        constant::DW_FORM_data4 |
+        constant::DW_FORM_data8 => &[Constant, LinePtr, LocList, MacPtr, RangeList],
+        constant::DW_FORM_exprloc => &[ExprLoc],
+        constant::DW_FORM_flag |
+        constant::DW_FORM_flag_present => &[Flag],
+        constant::DW_FORM_sec_offset => {
+            &[AddrPtr, LinePtr, LocList, LocListsPtr, MacPtr, RangeList, RangeListsPtr,
+              StrOffsetsPtr]
+        }
+        constant::DW_FORM_loclistx => &[LocList],
+        constant::DW_FORM_rnglistx => &[RangeList],
+        constant::DW_FORM_ref1 |
+        constant::DW_FORM_ref2 |
+        constant::DW_FORM_ref4 |
+        constant::DW_FORM_ref8 |
+        constant::DW_FORM_ref_udata |
+        constant::DW_FORM_ref_addr |
+        constant::DW_FORM_ref_sig8 |
+        constant::DW_FORM_ref_sup4 |
+        constant::DW_FORM_ref_sup8 => &[Reference],
+        constant::DW_FORM_string |
+        constant::DW_FORM_strp |
+        constant::DW_FORM_line_strp |
+        constant::DW_FORM_strp_sup |
+        constant::DW_FORM_strx |
+        constant::DW_FORM_strx1 |
+        constant::DW_FORM_strx2 |
+        constant::DW_FORM_strx3 |
+        constant::DW_FORM_strx4 |
+        constant::DW_FORM_GNU_str_index => &[String],
+        constant::DW_FORM_indirect => return None,
+        _ => &[],
+    };
+    Some(classes)
+}
+
+// Return the classes that a value of the attribute may belong to.
+//
+// Returns `None` for unknown and vendor attributes.
+#[cfg_attr(feature = "clippy", allow(match_same_arms))]
+pub fn attribute_classes(at: constant::DwAt) -> Option<&'static [AttributeClass]> {
+    let classes: &'static [AttributeClass] = match at {
+        constant::DW_AT_sibling => &[Reference],
+        constant::DW_AT_location => &[ExprLoc, LocList],
+        constant::DW_AT_name => &[String],
+        constant::DW_AT_ordering => &[Constant],
+        constant::DW_AT_byte_size => &[Constant, ExprLoc, Reference],
+        constant::DW_AT_bit_offset => &[Constant, ExprLoc, Reference],
+        constant::DW_AT_bit_size => &[Constant, ExprLoc, Reference],
+        constant::DW_AT_stmt_list => &[LinePtr],
+        constant::DW_AT_low_pc => &[Address],
+        constant::DW_AT_high_pc => &[Address, Constant],
+        constant::DW_AT_language => &[Constant],
+        constant::DW_AT_discr => &[Reference],
+        constant::DW_AT_discr_value => &[Constant],
+        constant::DW_AT_visibility => &[Constant],
+        constant::DW_AT_import => &[Reference],
+        constant::DW_AT_string_length => &[ExprLoc, LocList, Reference],
+        constant::DW_AT_common_reference => &[Reference],
+        constant::DW_AT_comp_dir => &[String],
+        constant::DW_AT_const_value => &[Block, Constant, String],
+        constant::DW_AT_containing_type => &[Reference],
+        constant::DW_AT_default_value => &[Constant, Reference, Flag],
+        constant::DW_AT_inline => &[Constant],
+        constant::DW_AT_is_optional => &[Flag],
+        constant::DW_AT_lower_bound => &[Constant, ExprLoc, Reference],
+        constant::DW_AT_producer => &[String],
+        constant::DW_AT_prototyped => &[Flag],
+        constant::DW_AT_return_addr => &[ExprLoc, LocList],
+        constant::DW_AT_start_scope => &[Constant, RangeList],
+        constant::DW_AT_bit_stride => &[Constant, ExprLoc, Reference],
+        constant::DW_AT_upper_bound => &[Constant, ExprLoc, Reference],
+        constant::DW_AT_abstract_origin => &[Reference],
+        constant::DW_AT_accessibility => &[Constant],
+        constant::DW_AT_address_class => &[Constant],
+        constant::DW_AT_artificial => &[Flag],
+        constant::DW_AT_base_types => &[Reference],
+        constant::DW_AT_calling_convention => &[Constant],
+        constant::DW_AT_count => &[Constant, ExprLoc, Reference],
+        constant::DW_AT_data_member_location => &[Constant, ExprLoc, LocList],
+        constant::DW_AT_decl_column => &[Constant],
+        constant::DW_AT_decl_file => &[Constant],
+        constant::DW_AT_decl_line => &[Constant],
+        constant::DW_AT_declaration => &[Flag],
+        constant::DW_AT_discr_list => &[Block],
+        constant::DW_AT_encoding => &[Constant],
+        constant::DW_AT_external => &[Flag],
+        constant::DW_AT_frame_base => &[ExprLoc, LocList],
+        constant::DW_AT_friend => &[Reference],
+        constant::DW_AT_identifier_case => &[Constant],
+        constant::DW_AT_macro_info => &[MacPtr],
+        constant::DW_AT_namelist_item => &[Reference],
+        constant::DW_AT_priority => &[Reference],
+        constant::DW_AT_segment => &[ExprLoc, LocList],
+        constant::DW_AT_specification => &[Reference],
+        constant::DW_AT_static_link => &[ExprLoc, LocList],
+        constant::DW_AT_type => &[Reference],
+        constant::DW_AT_use_location => &[ExprLoc, LocList],
+        constant::DW_AT_variable_parameter => &[Flag],
+        constant::DW_AT_virtuality => &[Constant],
+        constant::DW_AT_vtable_elem_location => &[ExprLoc, LocList],
+        constant::DW_AT_allocated => &[Constant, ExprLoc, Reference],
+        constant::DW_AT_associated => &[Constant, ExprLoc, Reference],
+        constant::DW_AT_data_location => &[ExprLoc],
+        constant::DW_AT_byte_stride => &[Constant, ExprLoc, Reference],
+        constant::DW_AT_entry_pc => &[Address, Constant],
+        constant::DW_AT_use_UTF8 => &[Flag],
+        constant::DW_AT_extension => &[Reference],
+        constant::DW_AT_ranges => &[RangeList],
+        constant::DW_AT_trampoline => &[Address, Flag, Reference, String],
+        constant::DW_AT_call_column => &[Constant],
+        constant::DW_AT_call_file => &[Constant],
+        constant::DW_AT_call_line => &[Constant],
+        constant::DW_AT_description => &[String],
+        constant::DW_AT_binary_scale => &[Constant],
+        constant::DW_AT_decimal_scale => &[Constant],
+        constant::DW_AT_small => &[Reference],
+        constant::DW_AT_decimal_sign => &[Constant],
+        constant::DW_AT_digit_count => &[Constant],
+        constant::DW_AT_picture_string => &[String],
+        constant::DW_AT_mutable => &[Flag],
+        constant::DW_AT_threads_scaled => &[Flag],
+        constant::DW_AT_explicit => &[Flag],
+        constant::DW_AT_object_pointer => &[Reference],
+        constant::DW_AT_endianity => &[Constant],
+        constant::DW_AT_elemental => &[Flag],
+        constant::DW_AT_pure => &[Flag],
+        constant::DW_AT_recursive => &[Flag],
+        constant::DW_AT_signature => &[Reference],
+        constant::DW_AT_main_subprogram => &[Flag],
+        constant::DW_AT_data_bit_offset => &[Constant],
+        constant::DW_AT_const_expr => &[Flag],
+        constant::DW_AT_enum_class => &[Flag],
+        constant::DW_AT_linkage_name => &[String],
+        constant::DW_AT_string_length_bit_size => &[Constant],
+        constant::DW_AT_string_length_byte_size => &[Constant],
+        constant::DW_AT_rank => &[Constant, ExprLoc],
+        constant::DW_AT_str_offsets_base => &[StrOffsetsPtr],
+        constant::DW_AT_addr_base => &[AddrPtr],
+        constant::DW_AT_rnglists_base => &[RangeListsPtr],
+        constant::DW_AT_dwo_name => &[String],
+        constant::DW_AT_reference => &[Flag],
+        constant::DW_AT_rvalue_reference => &[Flag],
+        constant::DW_AT_macros => &[MacPtr],
+        constant::DW_AT_call_all_calls => &[Flag],
+        constant::DW_AT_call_all_source_calls => &[Flag],
+        constant::DW_AT_call_all_tail_calls => &[Flag],
+        constant::DW_AT_call_return_pc => &[Address],
+        constant::DW_AT_call_value => &[ExprLoc],
+        // The standard says exprloc, but producers use a reference.
+        constant::DW_AT_call_origin => &[ExprLoc, Reference],
+        constant::DW_AT_call_parameter => &[Reference],
+        constant::DW_AT_call_pc => &[Address],
+        constant::DW_AT_call_tail_call => &[Flag],
+        constant::DW_AT_call_target => &[ExprLoc],
+        constant::DW_AT_call_target_clobbered => &[ExprLoc],
+        constant::DW_AT_call_data_location => &[ExprLoc],
+        constant::DW_AT_call_data_value => &[ExprLoc],
+        constant::DW_AT_noreturn => &[Flag],
+        constant::DW_AT_alignment => &[Constant],
+        constant::DW_AT_export_symbols => &[Flag],
+        constant::DW_AT_deleted => &[Flag],
+        constant::DW_AT_defaulted => &[Constant],
+        constant::DW_AT_loclists_base => &[LocListsPtr],
+        _ => return None,
+    };
+    Some(classes)
+}
+
+// Return true if the form may be used to encode a value of the attribute.
+//
+// Unknown attributes and `DW_FORM_indirect` are assumed to be valid.
+pub fn is_valid_form(at: constant::DwAt, form: constant::DwForm) -> bool {
+    let attribute_classes = match attribute_classes(at) {
+        Some(classes) => classes,
+        None => return true,
+    };
+    let form_classes = match form_classes(form) {
+        Some(classes) => classes,
+        None => return true,
+    };
+    form_classes.iter().any(|class| attribute_classes.contains(class))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use constant::*;
+
+    #[test]
+    fn valid_form() {
+        assert!(is_valid_form(DW_AT_name, DW_FORM_strp));
+        assert!(!is_valid_form(DW_AT_name, DW_FORM_addr));
+        assert!(is_valid_form(DW_AT_stmt_list, DW_FORM_data4));
+        assert!(!is_valid_form(DW_AT_stmt_list, DW_FORM_data1));
+        assert!(is_valid_form(DW_AT_location, DW_FORM_block1));
+        assert!(is_valid_form(DW_AT_name, DW_FORM_indirect));
+        assert!(is_valid_form(DW_AT_lo_user, DW_FORM_addr));
+        assert!(!is_valid_form(DW_AT_sibling, DwForm(0x7f)));
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Deref;
+
+use endian::Endian;
+use line::LineIterator;
+use read::ReadError;
+use Sections;
+
+// A range of addresses, including `begin` but excluding `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AddressRange {
+    pub begin: u64,
+    pub end: u64,
+}
+
+// A map from source lines to the addresses that implement them.
+//
+// This is the inverse of the line number information: rather than mapping
+// an address to a line, it maps a line to addresses.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CoverageMap {
+    // The address ranges for each line of each file.
+    //
+    // Files are keyed by their full path, so that rows from different
+    // line programs for the same file are combined. The ranges for a line
+    // are sorted, and adjacent or overlapping ranges are merged.
+    pub files: BTreeMap<Vec<u8>, BTreeMap<u64, Vec<AddressRange>>>,
+}
+
+impl CoverageMap {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // Build a map from the line programs of all compilation units.
+    pub fn from_sections<E, S>(sections: &Sections<E, S>) -> Result<CoverageMap, ReadError>
+        where E: Endian,
+              S: Deref<Target = [u8]>
+    {
+        let mut map = CoverageMap::new();
+        let mut units = sections.compilation_units();
+        while let Some(unit) = try!(units.next()) {
+            let abbrev = try!(sections.abbrev(&unit.common));
+            if let Some(lines) = try!(sections.lines(&unit, &abbrev)) {
+                try!(map.add_lines(lines));
+            }
+        }
+        Ok(map)
+    }
+
+    // Add the rows of a line program.
+    //
+    // Each row covers the addresses up to the next row in its sequence.
+    // Rows for line 0 have no source line, and are ignored.
+    pub fn add_lines<'data, E: Endian>(
+        &mut self,
+        mut lines: LineIterator<'data, E>
+    ) -> Result<(), ReadError> {
+        // Paths indexed by file number, since most rows reuse a few files.
+        let mut paths = HashMap::new();
+        let mut previous: Option<(u64, u64, u64)> = None;
+        while let Some((iter, line)) = try!(lines.next()) {
+            if let Some((address, file, line_number)) = previous {
+                if line.address > address && line_number != 0 {
+                    let path = paths.entry(file)
+                        .or_insert_with(|| match iter.file(file) {
+                            Some(entry) => entry.full_path(iter.directories()),
+                            None => Vec::new(),
+                        })
+                        .clone();
+                    self.files
+                        .entry(path)
+                        .or_default()
+                        .entry(line_number)
+                        .or_default()
+                        .push(AddressRange {
+                            begin: address,
+                            end: line.address,
+                        });
+                }
+            }
+            previous = if line.sequence_end {
+                None
+            } else {
+                Some((line.address, line.file, line.line))
+            };
+        }
+        for lines in self.files.values_mut() {
+            for ranges in lines.values_mut() {
+                normalize(ranges);
+            }
+        }
+        Ok(())
+    }
+
+    // Return the address ranges for a line of a file.
+    //
+    // `path` must be the full path, as returned by `FileEntry::full_path`.
+    pub fn ranges(&self, path: &[u8], line: u64) -> &[AddressRange] {
+        match self.files.get(path).and_then(|lines| lines.get(&line)) {
+            Some(ranges) => ranges,
+            None => &[],
+        }
+    }
+}
+
+// Sort the ranges and merge adjacent or overlapping ranges.
+fn normalize(ranges: &mut Vec<AddressRange>) {
+    ranges.sort();
+    let mut merged: Vec<AddressRange> = Vec::with_capacity(ranges.len());
+    for range in ranges.drain(..) {
+        if let Some(last) = merged.last_mut() {
+            if range.begin <= last.end {
+                if range.end > last.end {
+                    last.end = range.end;
+                }
+                continue;
+            }
+        }
+        merged.push(range);
+    }
+    *ranges = merged;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use constant::*;
+    use endian::*;
+    use leb128;
+    use line::*;
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn coverage() {
+        let mut data = Vec::new();
+        // Sequence 1: line 1 at 0x1000, line 2 at 0x1004, line 1 at 0x1008,
+        // end at 0x100c.
+        data.extend_from_slice(&[0, 5, DW_LNE_set_address.0, 0x00, 0x10, 0x00, 0x00]);
+        data.push(DW_LNS_copy.0);
+        data.push(DW_LNS_advance_pc.0);
+        leb128::write_u64(&mut data, 4).unwrap();
+        data.push(DW_LNS_advance_line.0);
+        leb128::write_i64(&mut data, 1).unwrap();
+        data.push(DW_LNS_copy.0);
+        data.push(DW_LNS_advance_pc.0);
+        leb128::write_u64(&mut data, 4).unwrap();
+        data.push(DW_LNS_advance_line.0);
+        leb128::write_i64(&mut data, -1).unwrap();
+        data.push(DW_LNS_copy.0);
+        data.push(DW_LNS_advance_pc.0);
+        leb128::write_u64(&mut data, 4).unwrap();
+        data.extend_from_slice(&[0, 1, DW_LNE_end_sequence.0]);
+        // Sequence 2: line 1 of file 2 at 0x2000, line 0 at 0x2002,
+        // end at 0x2004.
+        data.extend_from_slice(&[0, 5, DW_LNE_set_address.0, 0x00, 0x20, 0x00, 0x00]);
+        data.push(DW_LNS_set_file.0);
+        leb128::write_u64(&mut data, 2).unwrap();
+        data.push(DW_LNS_copy.0);
+        data.push(DW_LNS_advance_pc.0);
+        leb128::write_u64(&mut data, 2).unwrap();
+        data.push(DW_LNS_advance_line.0);
+        leb128::write_i64(&mut data, -1).unwrap();
+        data.push(DW_LNS_copy.0);
+        data.push(DW_LNS_advance_pc.0);
+        leb128::write_u64(&mut data, 2).unwrap();
+        data.extend_from_slice(&[0, 1, DW_LNE_end_sequence.0]);
+
+        let program = LineProgram {
+            offset: 0,
+            endian: LittleEndian,
+            version: 2,
+            address_size: 4,
+            offset_size: 4,
+            address_step: 1,
+            operation_range: 1,
+            default_statement: true,
+            line_base: -5,
+            line_range: 14,
+            opcode_base: 13,
+            standard_opcode_lengths: &[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1],
+            include_directories: vec![b"/a", b"inc"],
+            files: vec![
+                FileEntry { path: b"main.c", ..Default::default() },
+                FileEntry { path: b"main.c", directory: 0, ..Default::default() },
+                FileEntry { path: b"x.h", directory: 1, ..Default::default() },
+            ],
+            data: &data,
+        };
+
+        let mut map = CoverageMap::new();
+        map.add_lines(program.lines()).unwrap();
+        assert_eq!(map.ranges(b"/a/main.c", 1), &[
+            AddressRange { begin: 0x1000, end: 0x1004 },
+            AddressRange { begin: 0x1008, end: 0x100c },
+        ]);
+        assert_eq!(map.ranges(b"/a/main.c", 2), &[
+            AddressRange { begin: 0x1004, end: 0x1008 },
+        ]);
+        assert_eq!(map.ranges(b"inc/x.h", 1), &[
+            AddressRange { begin: 0x2000, end: 0x2002 },
+        ]);
+        assert_eq!(map.ranges(b"inc/x.h", 0), &[]);
+
+        // Adding the same rows again doesn't duplicate the ranges.
+        let before = map.clone();
+        map.add_lines(program.lines()).unwrap();
+        assert_eq!(map, before);
+    }
+}
@@ -0,0 +1,150 @@
+use std;
+use std::io::Read;
+use std::path::Path;
+
+use endian::Endian;
+use read::*;
+
+// The contents of a .gnu_debuglink section, which names a separate file
+// containing the debug info.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugLink<'data> {
+    // The file name of the debug file, without a directory.
+    pub filename: &'data [u8],
+    // The CRC of the whole debug file, computed by `crc32`.
+    pub crc: u32,
+}
+
+impl<'data> DebugLink<'data> {
+    // Parse the data of a .gnu_debuglink section.
+    //
+    // The file name is followed by padding to a 4 byte boundary, and then
+    // the CRC in the byte order of the file.
+    pub fn read<E: Endian>(data: &'data [u8], endian: E) -> Result<DebugLink<'data>, ReadError> {
+        let mut r = data;
+        let filename = try!(read_string(&mut r));
+        let padding = (4 - (filename.len() + 1) % 4) % 4;
+        try!(read_block(&mut r, padding));
+        let crc = try!(endian.read_u32(&mut r));
+        Ok(DebugLink {
+            filename: filename,
+            crc: crc,
+        })
+    }
+
+    // Return true if the file at `path` has the CRC of the link.
+    //
+    // The file is read in chunks rather than loaded.
+    pub fn matches<P: AsRef<Path>>(&self, path: P) -> std::io::Result<bool> {
+        let file = try!(std::fs::File::open(path));
+        Ok(try!(read_crc32(file)) == self.crc)
+    }
+}
+
+// An incremental computation of the CRC used by .gnu_debuglink.
+//
+// This is the CRC-32 used by zlib and gzip.
+#[derive(Clone)]
+pub struct Crc32 {
+    table: [u32; 256],
+    crc: u32,
+}
+
+impl Crc32 {
+    pub fn new() -> Self {
+        let mut table = [0; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            let mut val = i as u32;
+            for _ in 0..8 {
+                val = if val & 1 != 0 {
+                    0xedb8_8320 ^ (val >> 1)
+                } else {
+                    val >> 1
+                };
+            }
+            *entry = val;
+        }
+        Crc32 {
+            table: table,
+            crc: 0xffff_ffff,
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        let mut crc = self.crc;
+        for &byte in data {
+            crc = self.table[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+        }
+        self.crc = crc;
+    }
+
+    // The CRC of the data so far.
+    pub fn value(&self) -> u32 {
+        !self.crc
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Crc32::new()
+    }
+}
+
+impl std::fmt::Debug for Crc32 {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Crc32({:08x})", self.value())
+    }
+}
+
+// Compute the CRC used by .gnu_debuglink for some data.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.value()
+}
+
+// Compute the CRC used by .gnu_debuglink for the data of a reader, such
+// as a candidate debug file.
+pub fn read_crc32<R: Read>(mut r: R) -> std::io::Result<u32> {
+    let mut crc = Crc32::new();
+    let mut buf = vec![0; 0x10000];
+    loop {
+        let len = match r.read(&mut buf) {
+            Ok(0) => return Ok(crc.value()),
+            Ok(len) => len,
+            Err(ref e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        crc.update(&buf[..len]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use endian::*;
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+
+        let mut crc = Crc32::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.value(), 0xcbf4_3926);
+        assert_eq!(read_crc32(&b"123456789"[..]).unwrap(), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn debug_link() {
+        let data = b"test.debug\0\0\x26\x39\xf4\xcb";
+        assert_eq!(DebugLink::read(data, LittleEndian).unwrap(),
+                   DebugLink {
+                       filename: b"test.debug",
+                       crc: 0xcbf4_3926,
+                   });
+        assert_eq!(DebugLink::read(data, BigEndian).unwrap().crc, 0x2639_f4cb);
+        assert!(DebugLink::read(&data[..14], LittleEndian).is_err());
+    }
+}
@@ -0,0 +1,845 @@
+use std;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+use std::ops::Deref;
+
+use abbrev::{Abbrev, AbbrevAttribute, AbbrevHash, AbbrevVec};
+use constant;
+use die::{AttributeContext, AttributeData, Die};
+use endian::Endian;
+use line::FileEntry;
+use offset::{DebugInfoOffset, UnitOffset};
+use read::ReadError;
+use rewrite::OffsetMap;
+use unit::{CompilationUnit, UnitCommon};
+use write::{is_ref_form, WriteError};
+use Sections;
+
+// Duplicate elimination for .debug_info, similar to dwz.
+//
+// Candidates are the subtrees of the top level children of each compilation
+// unit. Two subtrees are identical if they have the same structure and
+// attribute values, where:
+// - string attributes are compared by their contents
+// - file attributes are compared by the file path in the line program
+// - references within the subtree are compared by relative position
+// - references to other candidates are compared by the candidate's identity
+// - references to anything else are compared by the target offset
+// - indexes into the unit's contributions to .debug_str_offsets,
+//   .debug_addr, .debug_rnglists and .debug_loclists are only equal
+//   within a unit
+// - DW_AT_sibling is ignored
+//
+// So subtrees with references to entries outside of any candidate are only
+// duplicates if they refer to the same entry.
+//
+// Duplicates are moved into partial units, and replaced by an imported unit
+// entry. Since entries in other units may refer to the moved entries, all
+// references are converted to DW_FORM_ref_addr. The partial units copy the
+// DW_AT_*_base attributes of the compilation unit, so that indexes still
+// refer to the same contributions.
+
+struct Entry<'data> {
+    die: Die<'data>,
+    forms: Vec<constant::DwForm>,
+}
+
+struct DedupUnit<'data, E: Endian> {
+    unit: CompilationUnit<'data, E>,
+    entries: Vec<Entry<'data>>,
+    // The file paths from the line program, used for DW_AT_decl_file.
+    files: Option<Vec<Vec<u8>>>,
+}
+
+fn read_units<'data, E, S>(
+    sections: &'data Sections<E, S>
+) -> Result<Vec<DedupUnit<'data, E>>, ReadError>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    let mut units = Vec::new();
+    let mut iter = sections.compilation_units();
+    while let Some(unit) = try!(iter.next()) {
+        let abbrev = try!(sections.abbrev(&unit.common));
+        let mut entries = Vec::new();
+        {
+            let mut iter = unit.entries(&abbrev);
+            while let Some(die) = try!(iter.next()) {
+                let forms = match abbrev.get(die.code) {
+                    Some(abbrev) => abbrev.attributes.iter().map(|a| a.form).collect(),
+                    None => Vec::new(),
+                };
+                entries.push(Entry {
+                    die: die.clone(),
+                    forms: forms,
+                });
+            }
+        }
+        let files = match sections.lines(&unit, &abbrev) {
+            Ok(Some(lines)) => Some(file_paths(lines.directories(), lines.files())),
+            _ => None,
+        };
+        units.push(DedupUnit {
+            unit: unit,
+            entries: entries,
+            files: files,
+        });
+    }
+    Ok(units)
+}
+
+fn file_paths(directories: &[&[u8]], files: &[FileEntry]) -> Vec<Vec<u8>> {
+    files.iter().map(|file| file.full_path(directories)).collect()
+}
+
+// Return the index after the subtree starting at `start`.
+fn subtree_end<'data, T, F>(entries: &[T], start: usize, die: F) -> usize
+    where F: Fn(&T) -> &Die<'data>
+{
+    if !die(&entries[start]).children {
+        return start + 1;
+    }
+    let mut depth = 1;
+    let mut i = start + 1;
+    while i < entries.len() {
+        let entry = die(&entries[i]);
+        if entry.is_null() {
+            depth -= 1;
+        } else if entry.children {
+            depth += 1;
+        }
+        i += 1;
+        if depth == 0 {
+            break;
+        }
+    }
+    i
+}
+
+#[derive(Clone, Copy)]
+struct Candidate {
+    unit: usize,
+    start: usize,
+    end: usize,
+}
+
+struct Hasher<'a, 'data: 'a, E: Endian + 'a> {
+    units: &'a [DedupUnit<'data, E>],
+    debug_str: &'data [u8],
+    debug_line_str: &'data [u8],
+    // Map from entry offset to (unit index, entry index).
+    index: HashMap<usize, (usize, usize)>,
+    // Map from candidate root offset to candidate.
+    candidates: BTreeMap<usize, Candidate>,
+    // Map from candidate root offset to identity.
+    ids: HashMap<usize, usize>,
+    keys: HashMap<String, usize>,
+    active: HashSet<usize>,
+}
+
+impl<'a, 'data, E: Endian> Hasher<'a, 'data, E> {
+    fn new(
+        units: &'a [DedupUnit<'data, E>],
+        debug_str: &'data [u8],
+        debug_line_str: &'data [u8]
+    ) -> Self {
+        let mut index = HashMap::new();
+        let mut candidates = BTreeMap::new();
+        for (u, unit) in units.iter().enumerate() {
+            for (i, entry) in unit.entries.iter().enumerate() {
+                index.insert(entry.die.offset, (u, i));
+            }
+            // DW_TAG_partial_unit and DW_TAG_imported_unit require DWARF 3.
+            if unit.unit.common.version < 3 || unit.entries.is_empty() ||
+               !unit.entries[0].die.children {
+                continue;
+            }
+            let mut i = 1;
+            while i < unit.entries.len() && !unit.entries[i].die.is_null() {
+                let end = subtree_end(&unit.entries, i, |e| &e.die);
+                let indirect = unit.entries[i..end]
+                    .iter()
+                    .any(|e| e.forms.contains(&constant::DW_FORM_indirect));
+                // Imported units are already shared.
+                let imported = unit.entries[i].die.tag == constant::DW_TAG_imported_unit;
+                if !indirect && !imported {
+                    candidates.insert(unit.entries[i].die.offset,
+                                      Candidate {
+                                          unit: u,
+                                          start: i,
+                                          end: end,
+                                      });
+                }
+                i = end;
+            }
+        }
+        Hasher {
+            units: units,
+            debug_str: debug_str,
+            debug_line_str: debug_line_str,
+            index: index,
+            candidates: candidates,
+            ids: HashMap::new(),
+            keys: HashMap::new(),
+            active: HashSet::new(),
+        }
+    }
+
+    fn id(&mut self, offset: usize) -> usize {
+        if let Some(&id) = self.ids.get(&offset) {
+            return id;
+        }
+        let key = self.key(offset);
+        let len = self.keys.len();
+        let id = *self.keys.entry(key).or_insert(len);
+        self.ids.insert(offset, id);
+        id
+    }
+
+    fn key(&mut self, offset: usize) -> String {
+        let mut key = String::new();
+        let candidate = self.candidates[&offset];
+        self.active.insert(offset);
+        let unit = &self.units[candidate.unit];
+        let context = AttributeContext::new(&unit.unit.common, self.debug_str, self.debug_line_str);
+        for entry in &unit.entries[candidate.start..candidate.end] {
+            if entry.die.is_null() {
+                key.push(';');
+                continue;
+            }
+            write!(key, "{}:{}:", entry.die.tag.0, entry.die.children).unwrap();
+            for attribute in &entry.die.attributes {
+                if attribute.at == constant::DW_AT_sibling {
+                    continue;
+                }
+                write!(key, "{}=", attribute.at.0).unwrap();
+                match attribute.data {
+                    AttributeData::Ref(val) => {
+                        let target = val.to_section_offset(&unit.unit.common);
+                        self.ref_key(&mut key, target, candidate);
+                    }
+                    AttributeData::RefAddress(val) => {
+                        self.ref_key(&mut key, val.0 as usize, candidate);
+                    }
+                    AttributeData::String(val) => write!(key, "s{:?}", val).unwrap(),
+                    AttributeData::StringOffset(_) |
+                    AttributeData::LineStringOffset(_) => {
+                        match attribute.data.as_string_in(&context) {
+                            Some(s) => write!(key, "s{:?}", s).unwrap(),
+                            None => write!(key, "{:?}", attribute.data).unwrap(),
+                        }
+                    }
+                    AttributeData::StringIndex(_) |
+                    AttributeData::AddressIndex(_) |
+                    AttributeData::RangeListIndex(_) |
+                    AttributeData::LocationListIndex(_) => {
+                        write!(key, "{:?}u{}", attribute.data, unit.unit.common.offset).unwrap()
+                    }
+                    ref data if attribute.at == constant::DW_AT_decl_file ||
+                                attribute.at == constant::DW_AT_call_file => {
+                        let quirks = &unit.unit.common.quirks;
+                        let path = data.as_udata()
+                            .map(|index| quirks.file_index(index))
+                            .and_then(|index| unit.files.as_ref().and_then(|f| f.get(index as usize)));
+                        match path {
+                            Some(path) => write!(key, "f{:?}", path).unwrap(),
+                            None => write!(key, "u{}", unit.unit.common.offset).unwrap(),
+                        }
+                    }
+                    ref data => write!(key, "{:?}", data).unwrap(),
+                }
+                key.push(',');
+            }
+            key.push(';');
+        }
+        self.active.remove(&offset);
+        key
+    }
+
+    fn ref_key(&mut self, key: &mut String, target: usize, candidate: Candidate) {
+        match self.index.get(&target) {
+            Some(&(u, i)) if u == candidate.unit && i >= candidate.start && i < candidate.end => {
+                write!(key, "i{}", i - candidate.start).unwrap();
+                return;
+            }
+            _ => {}
+        }
+        if self.candidates.contains_key(&target) && !self.active.contains(&target) {
+            let id = self.id(target);
+            write!(key, "k{}", id).unwrap();
+        } else {
+            write!(key, "u{}", target).unwrap();
+        }
+    }
+
+    // Return the groups of identical candidates, in offset order.
+    fn duplicates(&mut self) -> Vec<Vec<usize>> {
+        let offsets: Vec<usize> = self.candidates.keys().cloned().collect();
+        let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        let mut first = HashMap::new();
+        for offset in offsets {
+            let id = self.id(offset);
+            let first = *first.entry(id).or_insert(offset);
+            groups.entry(first).or_default().push(offset);
+        }
+        groups.into_values().filter(|group| group.len() > 1).collect()
+    }
+}
+
+// Find identical subtrees in the compilation units.
+//
+// Returns groups of the offsets of the subtree roots.
+pub fn find_duplicates<E, S>(sections: &Sections<E, S>) -> Result<Vec<Vec<usize>>, ReadError>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    let units = try!(read_units(sections));
+    let mut hasher = Hasher::new(&units, &*sections.debug_str, &*sections.debug_line_str);
+    Ok(hasher.duplicates())
+}
+
+#[derive(Clone, Copy)]
+enum Target {
+    // An entry offset in the original .debug_info.
+    Old(usize),
+    // A (unit, entry) index in the new units.
+    New(usize, usize),
+}
+
+struct NewEntry<'data> {
+    die: Die<'data>,
+    forms: Vec<constant::DwForm>,
+    targets: Vec<Option<Target>>,
+    old_offset: Option<usize>,
+}
+
+impl<'data> NewEntry<'data> {
+    fn copy(entry: &Entry<'data>, unit_offset: usize) -> Self {
+        let mut die = entry.die.clone();
+        let mut forms = entry.forms.clone();
+        let mut targets = Vec::with_capacity(forms.len());
+        for (attribute, form) in die.attributes.iter_mut().zip(forms.iter_mut()) {
+            let target = match attribute.data {
+                AttributeData::Ref(val) if attribute.at != constant::DW_AT_sibling => {
+                    Some(Target::Old(unit_offset + val.0 as usize))
+                }
+                AttributeData::RefAddress(val) => Some(Target::Old(val.0 as usize)),
+                _ => None,
+            };
+            if target.is_some() && is_ref_form(*form) {
+                *form = constant::DW_FORM_ref_addr;
+                attribute.data = AttributeData::RefAddress(DebugInfoOffset(0));
+            }
+            targets.push(target);
+        }
+        NewEntry {
+            die: die,
+            forms: forms,
+            targets: targets,
+            old_offset: Some(entry.die.offset),
+        }
+    }
+
+    fn new(die: Die<'data>, forms: Vec<constant::DwForm>, targets: Vec<Option<Target>>) -> Self {
+        NewEntry {
+            die: die,
+            forms: forms,
+            targets: targets,
+            old_offset: None,
+        }
+    }
+}
+
+struct NewUnit<'data> {
+    // The index of the original unit that the header is copied from.
+    source: usize,
+    entries: Vec<NewEntry<'data>>,
+    abbrev_offset: usize,
+    abbrev: AbbrevHash,
+}
+
+// Move duplicate subtrees into partial units.
+//
+// Returns the new sections, and the mapping from old to new entry offsets.
+// Entries in removed duplicates are mapped to the retained copy.
+//
+// References to removed entries are updated to refer to the retained copy,
+// so running this again may find more duplicates.
+//
+// .debug_abbrev is rewritten, but the original contents are kept if
+// there are type units, since they use the same section.
+pub fn eliminate_duplicates<E, S>(
+    sections: &Sections<E, S>
+) -> Result<(Sections<E>, OffsetMap), WriteError>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    let units = try!(read_units(sections));
+    let mut hasher = Hasher::new(&units, &*sections.debug_str, &*sections.debug_line_str);
+    let groups = hasher.duplicates();
+
+    // Map from removed root offset to the retained root offset.
+    let mut removed = HashMap::new();
+    // Map from original unit index to the retained roots it provides.
+    let mut retained: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for group in &groups {
+        for &offset in group {
+            removed.insert(offset, group[0]);
+        }
+        let unit = hasher.candidates[&group[0]].unit;
+        retained.entry(unit).or_default().push(group[0]);
+    }
+    let partial_index: HashMap<usize, usize> =
+        retained.keys().enumerate().map(|(i, &unit)| (unit, units.len() + i)).collect();
+
+    let mut new_units = Vec::new();
+    for (u, unit) in units.iter().enumerate() {
+        let unit_offset = unit.unit.common.offset;
+        let mut entries = Vec::new();
+        let mut imports = Vec::new();
+        let mut i = 0;
+        while i < unit.entries.len() {
+            let offset = unit.entries[i].die.offset;
+            if let Some(&retained) = removed.get(&offset) {
+                let source = hasher.candidates[&retained].unit;
+                imports.push(partial_index[&source]);
+                i = hasher.candidates[&offset].end;
+                continue;
+            }
+            entries.push(NewEntry::copy(&unit.entries[i], unit_offset));
+            i += 1;
+        }
+        imports.sort();
+        imports.dedup();
+        let imports = imports.into_iter().map(|partial| {
+            let attributes = vec![::die::Attribute {
+                                      at: constant::DW_AT_import,
+                                      data: AttributeData::RefAddress(DebugInfoOffset(0)),
+                                  }];
+            let die = Die::new(0, 0, constant::DW_TAG_imported_unit, false, attributes);
+            NewEntry::new(die,
+                          vec![constant::DW_FORM_ref_addr],
+                          vec![Some(Target::New(partial, 0))])
+        });
+        let tail = entries.split_off(std::cmp::min(1, entries.len()));
+        entries.extend(imports);
+        entries.extend(tail);
+        new_units.push(NewUnit {
+            source: u,
+            entries: entries,
+            abbrev_offset: 0,
+            abbrev: AbbrevHash::new(),
+        });
+    }
+
+    for (&u, roots) in &retained {
+        let unit = &units[u];
+        let root = &unit.entries[0];
+        let mut die = Die::null(0);
+        let mut forms = Vec::new();
+        die.tag = constant::DW_TAG_partial_unit;
+        die.children = true;
+        for (attribute, &form) in root.die.attributes.iter().zip(&root.forms) {
+            match attribute.at {
+                constant::DW_AT_stmt_list |
+                constant::DW_AT_comp_dir |
+                constant::DW_AT_name |
+                constant::DW_AT_str_offsets_base |
+                constant::DW_AT_addr_base |
+                constant::DW_AT_rnglists_base |
+                constant::DW_AT_loclists_base |
+                constant::DW_AT_GNU_addr_base |
+                constant::DW_AT_GNU_ranges_base => {
+                    die.attributes.push(attribute.clone());
+                    forms.push(form);
+                }
+                _ => {}
+            }
+        }
+        die.update_attribute_mask();
+        let targets = vec![None; forms.len()];
+        let mut entries = vec![NewEntry::new(die, forms, targets)];
+        for root in roots {
+            let candidate = hasher.candidates[root];
+            for entry in &unit.entries[candidate.start..candidate.end] {
+                entries.push(NewEntry::copy(entry, unit.unit.common.offset));
+            }
+        }
+        entries.push(NewEntry::new(Die::null(0), Vec::new(), Vec::new()));
+        new_units.push(NewUnit {
+            source: u,
+            entries: entries,
+            abbrev_offset: 0,
+            abbrev: AbbrevHash::new(),
+        });
+    }
+
+    // Map original entry offsets to their new location.
+    let mut locations = HashMap::new();
+    for (u, unit) in new_units.iter().enumerate() {
+        for (i, entry) in unit.entries.iter().enumerate() {
+            if let Some(offset) = entry.old_offset {
+                locations.insert(offset, (u, i));
+            }
+        }
+    }
+    for group in &groups {
+        let retained = hasher.candidates[&group[0]];
+        for offset in &group[1..] {
+            let candidate = hasher.candidates[offset];
+            let old = &units[candidate.unit].entries[candidate.start..candidate.end];
+            let new = &units[retained.unit].entries[retained.start..retained.end];
+            for (old, new) in old.iter().zip(new) {
+                let location = locations[&new.die.offset];
+                locations.insert(old.die.offset, location);
+            }
+        }
+    }
+
+    let debug_abbrev = try!(write_abbrevs(sections, &mut new_units));
+
+    let mut positions = None;
+    for _ in 0..8 {
+        let (debug_info, new_positions) =
+            try!(layout(&units, &new_units, &locations, positions.as_ref()));
+        if positions.as_ref() == Some(&new_positions) {
+            let mut map = OffsetMap::new();
+            for (&old, &(u, i)) in &locations {
+                map.insert(old, new_positions[u].1[i]);
+            }
+            let sections = Sections {
+                endian: sections.endian,
+                debug_abbrev: debug_abbrev,
+                debug_addr: sections.debug_addr.to_vec(),
+                // The unit offsets in .debug_aranges are no longer valid.
+                debug_aranges: Vec::new(),
+                debug_frame: sections.debug_frame.to_vec(),
+                debug_info: debug_info,
+                debug_line: sections.debug_line.to_vec(),
+                debug_line_str: sections.debug_line_str.to_vec(),
+                debug_loc: sections.debug_loc.to_vec(),
+                debug_loclists: sections.debug_loclists.to_vec(),
+                debug_ranges: sections.debug_ranges.to_vec(),
+                debug_rnglists: sections.debug_rnglists.to_vec(),
+                debug_str: sections.debug_str.to_vec(),
+                debug_str_offsets: sections.debug_str_offsets.to_vec(),
+                debug_types: sections.debug_types.to_vec(),
+            };
+            return Ok((sections, map));
+        }
+        positions = Some(new_positions);
+    }
+    Err(WriteError::Invalid("entry offsets did not converge".to_string()))
+}
+
+// Build an abbreviation table for each unit, and assign the entry codes.
+fn write_abbrevs<E, S>(
+    sections: &Sections<E, S>,
+    units: &mut [NewUnit]
+) -> Result<Vec<u8>, WriteError>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    let mut debug_abbrev = if sections.debug_types.is_empty() {
+        Vec::new()
+    } else {
+        sections.debug_abbrev.to_vec()
+    };
+    let mut tables = HashMap::new();
+    for unit in units {
+        let mut abbrevs: Vec<Abbrev> = Vec::new();
+        for entry in &mut unit.entries {
+            if entry.die.tag == constant::DW_TAG_null {
+                continue;
+            }
+            let attributes: Vec<AbbrevAttribute> = entry.die
+                .attributes
+                .iter()
+                .zip(&entry.forms)
+                .map(|(attribute, &form)| match (form, &attribute.data) {
+                    (constant::DW_FORM_implicit_const, &AttributeData::SData(val)) => {
+                        AbbrevAttribute::implicit_const(attribute.at, val)
+                    }
+                    _ => AbbrevAttribute::new(attribute.at, form),
+                })
+                .collect();
+            let code = match abbrevs.iter().find(|abbrev| {
+                abbrev.tag == entry.die.tag && abbrev.children == entry.die.children &&
+                abbrev.attributes == attributes
+            }) {
+                Some(abbrev) => abbrev.code,
+                None => {
+                    let code = abbrevs.len() as u64 + 1;
+                    abbrevs.push(Abbrev {
+                        code: code,
+                        tag: entry.die.tag,
+                        children: entry.die.children,
+                        attributes: attributes,
+                    });
+                    code
+                }
+            };
+            entry.die.code = code;
+        }
+
+        let mut table = Vec::new();
+        try!(AbbrevVec::new(abbrevs).write(&mut table));
+        unit.abbrev = try!(AbbrevHash::read(&mut &table[..]));
+        unit.abbrev_offset = match tables.get(&table) {
+            Some(&offset) => offset,
+            None => {
+                let offset = debug_abbrev.len();
+                debug_abbrev.extend_from_slice(&table);
+                tables.insert(table, offset);
+                offset
+            }
+        };
+    }
+    Ok(debug_abbrev)
+}
+
+// The unit offset, and the offset of each entry and the end of the unit.
+type Positions = Vec<(usize, Vec<usize>)>;
+
+fn layout<'data, E: Endian>(
+    units: &[DedupUnit<'data, E>],
+    new_units: &[NewUnit<'data>],
+    locations: &HashMap<usize, (usize, usize)>,
+    positions: Option<&Positions>
+) -> Result<(Vec<u8>, Positions), WriteError> {
+    let mut data = Vec::new();
+    let mut new_positions = Vec::new();
+    for (u, new_unit) in new_units.iter().enumerate() {
+        let source = &units[new_unit.source].unit.common;
+        let mut unit = CompilationUnit {
+            common: UnitCommon {
+                offset: data.len(),
+                endian: source.endian,
+                version: source.version,
+                address_size: source.address_size,
+                offset_size: source.offset_size,
+                abbrev_offset: new_unit.abbrev_offset as u64,
+                unit_type: source.unit_type,
+                unit_id: source.unit_id,
+                type_offset: source.type_offset,
+                data: &[],
+                padding: 0,
+                quirks: source.quirks,
+            },
+        };
+        let data_offset = unit.data_offset();
+        let mut offsets = Vec::new();
+        let mut unit_data = Vec::new();
+        for (i, entry) in new_unit.entries.iter().enumerate() {
+            offsets.push(data_offset + unit_data.len());
+            let mut die = entry.die.clone();
+            if let Some(positions) = positions {
+                for (attribute, target) in die.attributes.iter_mut().zip(&entry.targets) {
+                    let (tu, ti) = match *target {
+                        Some(Target::Old(offset)) => {
+                            match locations.get(&offset) {
+                                Some(&location) => location,
+                                None => {
+                                    return Err(WriteError::Invalid(format!("invalid reference {:x}",
+                                                                           offset)))
+                                }
+                            }
+                        }
+                        Some(Target::New(tu, ti)) => (tu, ti),
+                        None if attribute.at == constant::DW_AT_sibling => {
+                            let end = subtree_end(&new_unit.entries, i, |e| &e.die);
+                            let (unit_offset, ref offsets) = positions[u];
+                            let offset = UnitOffset((offsets[end] - unit_offset) as u64);
+                            attribute.data = AttributeData::Ref(offset);
+                            continue;
+                        }
+                        None => continue,
+                    };
+                    let offset = DebugInfoOffset(positions[tu].1[ti] as u64);
+                    attribute.data = AttributeData::RefAddress(offset);
+                }
+            }
+            try!(die.write(&mut unit_data, &unit.common, &new_unit.abbrev));
+        }
+        offsets.push(data_offset + unit_data.len());
+        new_positions.push((unit.common.offset, offsets));
+        unit.common.data = &unit_data;
+        try!(unit.write(&mut data));
+    }
+    Ok((data, new_positions))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use abbrev::*;
+    use constant::*;
+    use die::*;
+    use endian::*;
+    use unit::*;
+    use Sections;
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn test_sections() -> Sections<LittleEndian> {
+        let abbrev = AbbrevVec::new(vec![
+            Abbrev { code: 1, tag: DW_TAG_compile_unit, children: true, attributes: vec![] },
+            Abbrev { code: 2, tag: DW_TAG_base_type, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+            ] },
+            Abbrev { code: 3, tag: DW_TAG_structure_type, children: true, attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+            ] },
+            Abbrev { code: 4, tag: DW_TAG_member, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                AbbrevAttribute::new(DW_AT_type, DW_FORM_ref4),
+            ] },
+            Abbrev { code: 5, tag: DW_TAG_variable, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                AbbrevAttribute::new(DW_AT_type, DW_FORM_ref4),
+            ] },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev.write(&mut debug_abbrev).unwrap();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        fn die<'a>(code: u64, tag: DwTag, children: bool, attributes: Vec<Attribute<'a>>) -> Die<'a> {
+            Die::new(0, code, tag, children, attributes)
+        }
+        fn name(name: &'static str) -> Attribute<'static> {
+            Attribute { at: DW_AT_name, data: AttributeData::String(name.as_bytes()) }
+        }
+        fn ty(offset: u64) -> Attribute<'static> {
+            Attribute { at: DW_AT_type, data: AttributeData::Ref(UnitOffset(offset)) }
+        }
+
+        // Unit offsets: 11 root, 12 int, 17 struct, 20 member, 27 null,
+        // 28 variable, 35 null.
+        let mut debug_info = Vec::new();
+        for &var in &["a", "b"] {
+            let entries = [
+                die(1, DW_TAG_compile_unit, true, vec![]),
+                die(2, DW_TAG_base_type, false, vec![name("int")]),
+                die(3, DW_TAG_structure_type, true, vec![name("s")]),
+                die(4, DW_TAG_member, false, vec![name("m"), ty(12)]),
+                Die::null(0),
+                die(5, DW_TAG_variable, false, vec![name(var), ty(17)]),
+                Die::null(0),
+            ];
+            let mut unit = CompilationUnit::<LittleEndian>::default();
+            unit.common.offset = debug_info.len();
+            let mut data = Vec::new();
+            for entry in &entries {
+                entry.write(&mut data, &unit.common, &abbrev_hash).unwrap();
+            }
+            unit.common.data = &data;
+            unit.write(&mut debug_info).unwrap();
+        }
+
+        Sections {
+            debug_abbrev: debug_abbrev,
+            debug_info: debug_info,
+            ..Sections::new(LittleEndian)
+        }
+    }
+
+    #[test]
+    fn duplicates() {
+        let sections = test_sections();
+        let groups = find_duplicates(&sections).unwrap();
+        assert_eq!(groups, vec![vec![12, 48], vec![17, 53]]);
+    }
+
+    #[test]
+    fn eliminate() {
+        let sections = test_sections();
+        let (sections, map) = eliminate_duplicates(&sections).unwrap();
+        assert_eq!(find_duplicates(&sections).unwrap(), Vec::<Vec<usize>>::new());
+
+        let mut units = sections.compilation_units();
+        let mut tags = Vec::new();
+        let mut types = Vec::new();
+        while let Some(unit) = units.next().unwrap() {
+            let abbrev = sections.abbrev(&unit.common).unwrap();
+            let mut entries = unit.entries(&abbrev);
+            while let Some(entry) = entries.next().unwrap() {
+                tags.push(entry.tag);
+                if let Some(&AttributeData::RefAddress(offset)) = entry.attr(DW_AT_type) {
+                    types.push(offset.0 as usize);
+                }
+            }
+        }
+        assert_eq!(tags, vec![
+            DW_TAG_compile_unit, DW_TAG_imported_unit, DW_TAG_variable, DW_TAG_null,
+            DW_TAG_compile_unit, DW_TAG_imported_unit, DW_TAG_variable, DW_TAG_null,
+            DW_TAG_partial_unit, DW_TAG_base_type, DW_TAG_structure_type, DW_TAG_member,
+            DW_TAG_null, DW_TAG_null,
+        ]);
+        // Both variables and the member refer to the retained copies.
+        let structure = map.get(17).unwrap();
+        let base = map.get(12).unwrap();
+        assert_eq!(map.get(53), Some(structure));
+        assert_eq!(map.get(48), Some(base));
+        assert_eq!(types, vec![structure, structure, base]);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn string_index() {
+        let abbrev = AbbrevVec::new(vec![
+            Abbrev { code: 1, tag: DW_TAG_compile_unit, children: true, attributes: vec![
+                AbbrevAttribute::new(DW_AT_str_offsets_base, DW_FORM_sec_offset),
+            ] },
+            Abbrev { code: 2, tag: DW_TAG_base_type, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_strx1),
+            ] },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev.write(&mut debug_abbrev).unwrap();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        // Unit offsets: 11 root, 16 and 18 base types, 20 null. The same
+        // string index refers to different strings in each unit.
+        let mut debug_info = Vec::new();
+        for &base in &[8, 16] {
+            let name = Attribute { at: DW_AT_name, data: AttributeData::StringIndex(0) };
+            let entries = [
+                Die::new(0, 1, DW_TAG_compile_unit, true, vec![
+                    Attribute { at: DW_AT_str_offsets_base, data: AttributeData::SecOffset(base) },
+                ]),
+                Die::new(0, 2, DW_TAG_base_type, false, vec![name.clone()]),
+                Die::new(0, 2, DW_TAG_base_type, false, vec![name.clone()]),
+                Die::null(0),
+            ];
+            let mut unit = CompilationUnit::<LittleEndian>::default();
+            unit.common.offset = debug_info.len();
+            let mut data = Vec::new();
+            for entry in &entries {
+                entry.write(&mut data, &unit.common, &abbrev_hash).unwrap();
+            }
+            unit.common.data = &data;
+            unit.write(&mut debug_info).unwrap();
+        }
+        let sections = Sections {
+            debug_abbrev: debug_abbrev,
+            debug_info: debug_info,
+            ..Sections::new(LittleEndian)
+        };
+        assert_eq!(find_duplicates(&sections).unwrap(), vec![vec![16, 18], vec![37, 39]]);
+
+        // The partial units use the string offsets of their source unit.
+        let (sections, _) = eliminate_duplicates(&sections).unwrap();
+        let mut bases = Vec::new();
+        let mut units = sections.compilation_units();
+        while let Some(unit) = units.next().unwrap() {
+            let abbrev = sections.abbrev(&unit.common).unwrap();
+            let root = unit.entries(&abbrev).next().unwrap().unwrap().clone();
+            bases.push((root.tag, root.attr(DW_AT_str_offsets_base).cloned()));
+        }
+        assert_eq!(bases, vec![
+            (DW_TAG_compile_unit, Some(AttributeData::SecOffset(8))),
+            (DW_TAG_compile_unit, Some(AttributeData::SecOffset(16))),
+            (DW_TAG_partial_unit, Some(AttributeData::SecOffset(8))),
+            (DW_TAG_partial_unit, Some(AttributeData::SecOffset(16))),
+        ]);
+    }
+}
@@ -1,24 +1,94 @@
 use std;
+use std::collections::VecDeque;
 use std::io::Write;
+use std::ops::Deref;
 
 use abbrev::{AbbrevHash, AbbrevAttribute};
 use constant;
 use endian::Endian;
 use leb128;
+use line::LineStrings;
+use offset::{DebugInfoOffset, UnitOffset};
 use read::*;
 use write::*;
-use unit::UnitCommon;
+use unit::{CompilationUnit, UnitCommon, UnitIndex};
+use Sections;
 
 #[derive(Debug)]
 pub struct DieIterator<'a, 'data, E>
     where 'data: 'a,
           E: Endian + 'a
 {
-    r: &'data [u8],
-    offset: usize,
+    // The data following the current entry.
+    r: Bytes<'data>,
+    // The data starting at the current entry.
+    entry_r: Bytes<'data>,
     unit: &'a UnitCommon<'data, E>,
     abbrev: &'a AbbrevHash,
     entry: Die<'data>,
+    // The offset at which to stop reading entries.
+    end: Option<usize>,
+    // The number of non-null entries left to read.
+    remaining: Option<usize>,
+    recovery: Option<Recovery>,
+    validation: Option<SiblingValidation>,
+    // The depth of the current entry.
+    depth: isize,
+    // The depth of the entry that will be returned by `next`.
+    next_depth: isize,
+    spans: bool,
+}
+
+// The position of a `DieIterator`, which can be restored to read the
+// entries again. See `DieIterator::save`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DieIteratorState<'data> {
+    r: Bytes<'data>,
+    // The data starting at the current entry, or `None` if it is a null.
+    entry_r: Option<Bytes<'data>>,
+    entry_offset: usize,
+    remaining: Option<usize>,
+    depth: isize,
+    next_depth: isize,
+}
+
+// A range of entries that were skipped after a read error.
+#[derive(Debug)]
+pub struct SkippedRange {
+    // The offset of the entry that couldn't be read.
+    pub offset: usize,
+    // The offset at which reading resumed.
+    pub end: usize,
+    pub error: ReadError,
+}
+
+#[derive(Debug, Default)]
+struct Recovery {
+    // The sibling offsets of the entries whose children are being read.
+    parents: Vec<Option<usize>>,
+    // The number of null entries to return before reading more entries.
+    nulls: usize,
+    skipped: Vec<SkippedRange>,
+}
+
+// A DW_AT_sibling attribute that doesn't match the offset of the entry
+// following the children of the entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiblingMismatch {
+    // The offset of the entry with the DW_AT_sibling attribute.
+    pub offset: usize,
+    // The offset given by the DW_AT_sibling attribute.
+    pub sibling: usize,
+    // The offset of the actual sibling.
+    pub actual: usize,
+}
+
+#[derive(Debug, Default)]
+struct SiblingValidation {
+    // The offsets and sibling offsets of the entries whose children are
+    // being read.
+    parents: Vec<(usize, Option<usize>)>,
+    mismatches: Vec<SiblingMismatch>,
 }
 
 impl<'a, 'data, E: Endian> DieIterator<'a, 'data, E> {
@@ -29,17 +99,155 @@ impl<'a, 'data, E: Endian> DieIterator<'a, 'data, E> {
         abbrev: &'a AbbrevHash
     ) -> Self {
         DieIterator {
-            r: r,
-            offset: offset,
+            r: Bytes::new(r, offset),
+            entry_r: Bytes::new(r, offset),
             unit: unit,
             abbrev: abbrev,
             entry: Die::null(0),
+            end: None,
+            remaining: None,
+            recovery: None,
+            validation: None,
+            depth: 0,
+            next_depth: 0,
+            spans: false,
         }
     }
 
+    // Stop before the first entry at or after `offset`.
+    //
+    // An entry that starts before `offset` is read completely, even if it
+    // extends past it. After the iterator stops, `offset` is the offset of
+    // the next entry, which can be used to resume with `CompilationUnit::entry`.
+    pub fn until(mut self, offset: usize) -> Self {
+        self.end = Some(offset);
+        self
+    }
+
+    // Stop after reading `count` entries, not including null entries.
+    //
+    // After the iterator stops, `offset` is the offset of the entry
+    // following the last one read.
+    pub fn limited(mut self, count: usize) -> Self {
+        self.remaining = Some(count);
+        self
+    }
+
+    // Continue after errors instead of returning them.
+    //
+    // When an entry can't be read, the iterator skips to the DW_AT_sibling
+    // offset of the nearest parent that has one, or to the end of the unit
+    // if there is none. Null entries are returned for each parent that is
+    // skipped, so that the depth of the following entries is correct.
+    //
+    // The skipped ranges are available from `skipped`. This should be called
+    // before reading any entries.
+    pub fn enable_recovery(&mut self) {
+        self.recovery = Some(Default::default());
+    }
+
+    // The ranges that were skipped due to errors when recovery is enabled.
+    pub fn skipped(&self) -> &[SkippedRange] {
+        match self.recovery {
+            Some(ref recovery) => &recovery.skipped,
+            None => &[],
+        }
+    }
+
+    // Check DW_AT_sibling attributes against the entries that are read.
+    //
+    // When this is enabled, `next_sibling` reads the children of entries
+    // instead of using DW_AT_sibling to skip them, so that the mismatches
+    // can be found. The mismatches are available from `sibling_mismatches`.
+    // This should be called before reading any entries.
+    pub fn enable_sibling_validation(&mut self) {
+        self.validation = Some(Default::default());
+    }
+
+    // The DW_AT_sibling attributes that were incorrect when sibling
+    // validation is enabled.
+    pub fn sibling_mismatches(&self) -> &[SiblingMismatch] {
+        match self.validation {
+            Some(ref validation) => &validation.mismatches,
+            None => &[],
+        }
+    }
+
+    // Record the location of each attribute in `Die::attribute_spans`.
+    //
+    // This is useful for tools that patch the section or report errors
+    // in it, but slows down reading.
+    pub fn enable_attribute_spans(&mut self) {
+        self.spans = true;
+    }
+
+    // The unit that the entries belong to.
+    pub fn unit(&self) -> &'a UnitCommon<'data, E> {
+        self.unit
+    }
+
     #[inline]
     pub fn offset(&self) -> usize {
-        self.offset
+        self.r.offset()
+    }
+
+    // Return the position of the iterator, so that `restore` can later
+    // continue from the current entry.
+    //
+    // This is cheap, and doesn't copy the current entry.
+    pub fn save(&self) -> DieIteratorState<'data> {
+        DieIteratorState {
+            r: self.r,
+            entry_r: if self.entry.is_null() { None } else { Some(self.entry_r) },
+            entry_offset: self.entry.offset,
+            remaining: self.remaining,
+            depth: self.depth,
+            next_depth: self.next_depth,
+        }
+    }
+
+    // Continue from a position returned by `save` for this iterator.
+    //
+    // The current entry is read again, so that `next_sibling` can skip its
+    // children. This isn't supported if recovery or sibling validation is
+    // enabled, since their state can't be restored.
+    pub fn restore(&mut self, state: DieIteratorState<'data>) -> Result<(), ReadError> {
+        if self.recovery.is_some() || self.validation.is_some() {
+            return Err(ReadError::Unsupported);
+        }
+        match state.entry_r {
+            Some(entry_r) => {
+                let mut r = entry_r.as_slice();
+                try!(self.entry
+                    .read_inner(&mut r, entry_r.offset(), self.unit, self.abbrev, self.spans));
+                self.entry_r = entry_r;
+            }
+            None => self.entry.set_null(state.entry_offset),
+        }
+        self.r = state.r;
+        self.remaining = state.remaining;
+        self.depth = state.depth;
+        self.next_depth = state.next_depth;
+        Ok(())
+    }
+
+    // The depth of the entry that was last returned.
+    //
+    // The first entry has a depth of 0, and its children have a depth of 1.
+    // A null entry has the depth of the siblings that it terminates.
+    // The depth is negative for entries that follow a null entry at depth 0.
+    #[inline]
+    pub fn depth(&self) -> isize {
+        self.depth
+    }
+
+    fn update_depth(&mut self) {
+        self.depth = self.next_depth;
+        if self.entry.is_null() {
+            self.next_depth -= 1;
+        } else if self.entry.children {
+            self.next_depth += 1;
+        }
     }
 
     // Get the next entry.
@@ -48,15 +256,127 @@ impl<'a, 'data, E: Endian> DieIterator<'a, 'data, E> {
     // Returns `None` when the end of input is reached.
     #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
     pub fn next(&mut self) -> Result<Option<&Die<'data>>, ReadError> {
-        if self.r.len() == 0 {
-            return Ok(None);
+        loop {
+            if let Some(ref mut recovery) = self.recovery {
+                if recovery.nulls > 0 {
+                    recovery.nulls -= 1;
+                    self.entry.set_null(self.r.offset());
+                    self.update_depth();
+                    return Ok(Some(&self.entry));
+                }
+            }
+
+            if self.r.is_empty() || self.remaining == Some(0) {
+                return Ok(None);
+            }
+            if let Some(end) = self.end {
+                if self.r.offset() >= end {
+                    return Ok(None);
+                }
+            }
+
+            let mut r = self.r.as_slice();
+            self.entry_r = self.r;
+            let offset = self.r.offset();
+            match self.entry.read_inner(&mut r, offset, self.unit, self.abbrev, self.spans) {
+                Ok(()) => {}
+                Err(e) => {
+                    if self.recovery.is_none() {
+                        return Err(e);
+                    }
+                    try!(self.resynchronize(e));
+                    continue;
+                }
+            }
+            try!(self.r.advance_to(r));
+
+            if let Some(ref mut remaining) = self.remaining {
+                if !self.entry.is_null() {
+                    *remaining -= 1;
+                }
+            }
+            if self.recovery.is_some() {
+                let sibling = self.sibling_offset();
+                if let Some(ref mut recovery) = self.recovery {
+                    if self.entry.is_null() {
+                        recovery.parents.pop();
+                    } else if self.entry.children {
+                        recovery.parents.push(sibling);
+                    }
+                }
+            }
+            if self.validation.is_some() {
+                self.validate_sibling();
+            }
+            self.update_depth();
+            return Ok(Some(&self.entry));
         }
+    }
 
-        let mut r = self.r;
-        try!(self.entry.read(&mut r, self.offset, self.unit, self.abbrev));
-        self.offset += self.r.len() - r.len();
-        self.r = r;
-        Ok(Some(&self.entry))
+    // The offset of the DW_AT_sibling of the current entry.
+    fn sibling_offset(&self) -> Option<usize> {
+        match self.entry.attr(constant::DW_AT_sibling) {
+            Some(&AttributeData::Ref(offset)) => Some(offset.to_section_offset(self.unit)),
+            _ => None,
+        }
+    }
+
+    // Check the sibling of the current entry, or the sibling of its parent
+    // if the current entry is a null.
+    fn validate_sibling(&mut self) {
+        let sibling = self.sibling_offset();
+        let validation = match self.validation {
+            Some(ref mut validation) => validation,
+            None => return,
+        };
+        let check = if self.entry.is_null() {
+            validation.parents.pop()
+        } else if self.entry.children {
+            validation.parents.push((self.entry.offset, sibling));
+            None
+        } else {
+            Some((self.entry.offset, sibling))
+        };
+        if let Some((offset, Some(sibling))) = check {
+            if sibling != self.r.offset() {
+                validation.mismatches.push(SiblingMismatch {
+                    offset: offset,
+                    sibling: sibling,
+                    actual: self.r.offset(),
+                });
+            }
+        }
+    }
+
+    // Skip past the entry at the current offset after a read error.
+    fn resynchronize(&mut self, error: ReadError) -> Result<(), ReadError> {
+        let recovery = match self.recovery {
+            Some(ref mut recovery) => recovery,
+            None => return Err(error),
+        };
+        let offset = self.r.offset();
+        let len = self.r.len();
+        let parent = recovery.parents.iter().rposition(|sibling| match *sibling {
+            Some(sibling) => sibling > offset && sibling - offset <= len,
+            None => false,
+        });
+        let (depth, end) = match parent {
+            Some(index) => (index, recovery.parents[index].unwrap()),
+            None => (0, offset + len),
+        };
+        recovery.nulls = recovery.parents.len() - depth;
+        recovery.parents.truncate(depth);
+        if let Some(ref mut validation) = self.validation {
+            // The children of the skipped parents can't be validated.
+            validation.parents.truncate(depth);
+        }
+        recovery.skipped.push(SkippedRange {
+            offset: offset,
+            end: end,
+            error: error,
+        });
+        self.entry.set_null(end);
+        self.r.skip_to(end)
     }
 
     // Get the next sibling entry.
@@ -66,7 +386,8 @@ impl<'a, 'data, E: Endian> DieIterator<'a, 'data, E> {
     //
     // If the current entry has children, then its child entries (and
     // the associated null) are skipped over. The DW_AT_sibling attribute
-    // is used to accelerate this if possible.
+    // is used to accelerate this if possible, unless sibling validation
+    // is enabled.
     //
     // If the returned entry is a null, then there are no more siblings.
     // Note that this means a subsequent call to this method will return
@@ -76,26 +397,29 @@ impl<'a, 'data, E: Endian> DieIterator<'a, 'data, E> {
     pub fn next_sibling(&mut self) -> Result<Option<&Die<'data>>, ReadError> {
         let mut depth = 0;
         loop {
-            if self.entry.children {
+            if self.entry.children && self.validation.is_some() {
+                depth += 1;
+            } else if self.entry.children {
                 depth += 1;
                 let mut sibling_offset = 0;
                 for attribute in &self.entry.attributes {
                     if attribute.at == constant::DW_AT_sibling {
                         if let AttributeData::Ref(offset) = attribute.data {
-                            sibling_offset = self.unit.offset + offset as usize;
+                            sibling_offset = offset.to_section_offset(self.unit);
                         }
                         break;
                     }
                 }
                 // This is outside the for loop due to borrow check
-                if sibling_offset > self.offset {
-                    let relative_offset = sibling_offset - self.offset;
-                    if relative_offset <= self.r.len() {
-                        self.entry.set_null(0);
-                        self.offset = sibling_offset;
-                        self.r = &self.r[relative_offset..];
-                        depth -= 1;
+                if sibling_offset > self.r.offset() && sibling_offset <= self.r.end_offset() {
+                    try!(self.r.skip_to(sibling_offset));
+                    if let Some(ref mut recovery) = self.recovery {
+                        // The children and null of the entry are skipped.
+                        recovery.parents.pop();
                     }
+                    self.entry.set_null(0);
+                    self.next_depth -= 1;
+                    depth -= 1;
                 }
             }
             if try!(self.next()).is_none() {
@@ -113,6 +437,100 @@ impl<'a, 'data, E: Endian> DieIterator<'a, 'data, E> {
     pub fn tree(self) -> DieTree<'a, 'data, E> {
         DieTree::new(self)
     }
+
+    pub fn breadth_first(self) -> DieBreadthIterator<'a, 'data, E> {
+        DieBreadthIterator::new(self)
+    }
+
+    pub fn with_offsets(self) -> DieOffsetIterator<'a, 'data, E> {
+        DieOffsetIterator { iter: self }
+    }
+
+    // Iterate over the direct children of the next entry.
+    pub fn children(self) -> DieChildIterator<'a, 'data, E> {
+        DieChildIterator {
+            iter: self,
+            first: true,
+            done: false,
+        }
+    }
+}
+
+// An iterator over the direct children of an entry.
+//
+// The children of the children are skipped using `next_sibling`, so they
+// aren't read if DW_AT_sibling is present. Null entries aren't returned.
+#[derive(Debug)]
+pub struct DieChildIterator<'a, 'data, E>
+    where 'data: 'a,
+          E: Endian + 'a
+{
+    iter: DieIterator<'a, 'data, E>,
+    first: bool,
+    done: bool,
+}
+
+impl<'a, 'data, E> DieChildIterator<'a, 'data, E>
+    where E: Endian
+{
+    // Get the next child.
+    //
+    // Returns `None` when there are no more children.
+    #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
+    pub fn next(&mut self) -> Result<Option<&Die<'data>>, ReadError> {
+        if self.done {
+            return Ok(None);
+        }
+        let found = if self.first {
+            self.first = false;
+            // Read the parent, and then its first child.
+            match try!(self.iter.next()) {
+                Some(parent) if parent.children => try!(self.iter.next()).is_some(),
+                _ => false,
+            }
+        } else {
+            try!(self.iter.next_sibling()).is_some()
+        };
+        if !found || self.iter.entry.is_null() {
+            self.done = true;
+            return Ok(None);
+        }
+        Ok(Some(&self.iter.entry))
+    }
+}
+
+// An iterator that returns each entry along with its offset and length.
+//
+// This avoids the need to use `DieIterator::offset`, which is the offset
+// of the entry following the one that was returned by `next`.
+#[derive(Debug)]
+pub struct DieOffsetIterator<'a, 'data, E>
+    where 'data: 'a,
+          E: Endian + 'a
+{
+    iter: DieIterator<'a, 'data, E>,
+}
+
+impl<'a, 'data, E> DieOffsetIterator<'a, 'data, E>
+    where E: Endian
+{
+    pub fn into_inner(self) -> DieIterator<'a, 'data, E> {
+        self.iter
+    }
+
+    // Get the next entry, and the section offset and encoded length of it.
+    //
+    // Null entries are included. Null entries that are returned for entries
+    // that were skipped during recovery have a length of 0.
+    #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
+    pub fn next(&mut self) -> Result<Option<(usize, &Die<'data>, usize)>, ReadError> {
+        if try!(self.iter.next()).is_none() {
+            return Ok(None);
+        }
+        let entry = &self.iter.entry;
+        let len = self.iter.offset().saturating_sub(entry.offset);
+        Ok(Some((entry.offset, entry, len)))
+    }
 }
 
 #[derive(Debug)]
@@ -229,16 +647,532 @@ impl<'a, 'b, 'data, E> DieTreeIterator<'a, 'b, 'data, E>
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+// An iterator over entries in breadth-first order.
+//
+// All of the entries at a depth are returned before the entries at the
+// next depth. Each list of siblings is read with `next_sibling`, so the
+// children of an entry are skipped using DW_AT_sibling until their depth
+// is reached, and aren't read at all if they are deeper than `max_depth`.
+//
+// Null entries aren't returned. Recovery and sibling validation of the
+// original iterator aren't used.
+#[derive(Debug)]
+pub struct DieBreadthIterator<'a, 'data, E>
+    where 'data: 'a,
+          E: Endian + 'a
+{
+    // The entries from the original iterator, starting at `start`.
+    data: &'data [u8],
+    start: usize,
+    unit: &'a UnitCommon<'data, E>,
+    abbrev: &'a AbbrevHash,
+    // The siblings that are being read, and their depth.
+    siblings: DieIterator<'a, 'data, E>,
+    first: bool,
+    depth: isize,
+    max_depth: Option<isize>,
+    // The offsets of the first children of entries that have been read,
+    // and the depth of the children.
+    queue: VecDeque<(usize, isize)>,
+}
+
+impl<'a, 'data, E> DieBreadthIterator<'a, 'data, E>
+    where E: Endian
+{
+    fn new(iter: DieIterator<'a, 'data, E>) -> Self {
+        DieBreadthIterator {
+            data: iter.r.as_slice(),
+            start: iter.r.offset(),
+            unit: iter.unit,
+            abbrev: iter.abbrev,
+            siblings: iter,
+            first: true,
+            depth: 0,
+            max_depth: None,
+            queue: VecDeque::new(),
+        }
+    }
+
+    // Don't return entries that are deeper than `depth`.
+    //
+    // The first entries have a depth of 0, so a depth of 0 only returns
+    // those, and a depth of 1 returns their children too.
+    pub fn max_depth(mut self, depth: isize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    // The depth of the entry that was last returned.
+    pub fn depth(&self) -> isize {
+        self.depth
+    }
+
+    // Get the next entry.
+    //
+    // Returns `None` when all of the entries have been returned.
+    #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
+    pub fn next(&mut self) -> Result<Option<&Die<'data>>, ReadError> {
+        loop {
+            let found = if self.first {
+                self.first = false;
+                try!(self.siblings.next()).is_some()
+            } else {
+                try!(self.siblings.next_sibling()).is_some()
+            };
+            if found && !self.siblings.entry.is_null() {
+                if self.siblings.entry.children &&
+                   self.max_depth.is_none_or(|max| self.depth < max) {
+                    self.queue.push_back((self.siblings.offset(), self.depth + 1));
+                }
+                return Ok(Some(&self.siblings.entry));
+            }
+
+            // The siblings have ended, so start on the children of the
+            // next entry.
+            let (offset, depth) = match self.queue.pop_front() {
+                Some(next) => next,
+                None => return Ok(None),
+            };
+            if offset < self.start || offset - self.start > self.data.len() {
+                return Err(ReadError::Invalid);
+            }
+            let data = &self.data[offset - self.start..];
+            self.siblings = DieIterator::new(data, offset, self.unit, self.abbrev);
+            self.first = true;
+            self.depth = depth;
+        }
+    }
+}
+
+// A handle to an entry in .debug_info.
+//
+// This contains the context that is needed to read the entry again, and
+// to resolve the strings and references in its attributes.
+#[derive(Debug)]
+pub struct DieRef<'a, E: Endian + 'a, S: 'a = Vec<u8>> {
+    pub sections: &'a Sections<E, S>,
+    // The offset of the unit header in .debug_info.
+    pub unit_offset: usize,
+    // The offset of the entry in .debug_info.
+    pub offset: usize,
+}
+
+impl<'a, E: Endian, S> Clone for DieRef<'a, E, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, E: Endian, S> Copy for DieRef<'a, E, S> {}
+
+impl<'a, E, S> DieRef<'a, E, S>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    pub fn new(sections: &'a Sections<E, S>, unit_offset: usize, offset: usize) -> Self {
+        DieRef {
+            sections: sections,
+            unit_offset: unit_offset,
+            offset: offset,
+        }
+    }
+
+    // Read the header of the unit containing the entry.
+    pub fn unit(&self) -> Result<CompilationUnit<'a, E>, ReadError> {
+        let debug_info = &*self.sections.debug_info;
+        if self.unit_offset >= debug_info.len() {
+            return Err(ReadError::Invalid);
+        }
+        let mut r = &debug_info[self.unit_offset..];
+        CompilationUnit::read(&mut r, self.unit_offset, self.sections.endian)
+    }
+
+    // Read the entry.
+    pub fn entry(&self) -> Result<Die<'a>, ReadError> {
+        let unit = try!(self.unit());
+        let abbrev = try!(self.sections.abbrev(&unit.common));
+        let mut entries = try!(unit.entry(self.offset, &abbrev).ok_or(ReadError::Invalid));
+        match try!(entries.next()) {
+            Some(entry) => Ok(entry.clone()),
+            None => Err(ReadError::Invalid),
+        }
+    }
+
+    // Return the direct children of the entry.
+    pub fn children(&self) -> Result<Vec<DieRef<'a, E, S>>, ReadError> {
+        let unit = try!(self.unit());
+        let abbrev = try!(self.sections.abbrev(&unit.common));
+        let mut children = try!(unit.children(self.offset, &abbrev).ok_or(ReadError::Invalid));
+        let mut refs = Vec::new();
+        while let Some(child) = try!(children.next()) {
+            refs.push(DieRef::new(self.sections, self.unit_offset, child.offset));
+        }
+        Ok(refs)
+    }
+
+    // Return the value of a string attribute of the entry.
+    pub fn string(&self, at: constant::DwAt) -> Result<Option<&'a [u8]>, ReadError> {
+        let entry = try!(self.entry());
+        match entry.attr(at) {
+            Some(data) => {
+                let unit = try!(self.unit());
+                data.as_string_strict_in(&self.sections.attribute_context(&unit.common))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Return the entry referenced by an attribute of the entry.
+    //
+    // Returns an error if the attribute isn't a reference to an entry
+    // in .debug_info.
+    //
+    // This reads every unit header to find the target of DW_FORM_ref_addr
+    // references. Use `reference_in` if many references are resolved.
+    pub fn reference(&self, at: constant::DwAt) -> Result<Option<DieRef<'a, E, S>>, ReadError> {
+        self.resolve(at, None)
+    }
+
+    // Like `reference`, but use `index` to find the target unit.
+    pub fn reference_in(
+        &self,
+        at: constant::DwAt,
+        index: &UnitIndex
+    ) -> Result<Option<DieRef<'a, E, S>>, ReadError> {
+        self.resolve(at, Some(index))
+    }
+
+    fn resolve(
+        &self,
+        at: constant::DwAt,
+        index: Option<&UnitIndex>
+    ) -> Result<Option<DieRef<'a, E, S>>, ReadError> {
+        let entry = try!(self.entry());
+        match entry.attr(at) {
+            Some(&AttributeData::Ref(offset)) => {
+                let unit = try!(self.unit());
+                let offset = offset.to_section_offset(&unit.common);
+                Ok(Some(DieRef::new(self.sections, self.unit_offset, offset)))
+            }
+            Some(&AttributeData::RefAddress(offset)) => {
+                let offset = offset.0 as usize;
+                if let Some(index) = index {
+                    let unit_offset = try!(index.unit_containing(offset)
+                        .ok_or(ReadError::Invalid));
+                    return Ok(Some(DieRef::new(self.sections, unit_offset, offset)));
+                }
+                let mut units = self.sections.compilation_units();
+                while let Some(unit) = try!(units.next()) {
+                    if offset >= unit.common.offset && offset < unit.end_offset() {
+                        return Ok(Some(DieRef::new(self.sections, unit.common.offset, offset)));
+                    }
+                }
+                Err(ReadError::Invalid)
+            }
+            Some(_) => Err(ReadError::Invalid),
+            None => Ok(None),
+        }
+    }
+}
+
+// The default limit on the number of entries in a `ReferenceChain`.
+pub const MAX_REFERENCE_CHAIN: usize = 64;
+
+// The entries visited while following a chain of references, such as
+// DW_AT_specification or DW_AT_type, so that corrupt input can't cause
+// an infinite loop.
+//
+// Functions that follow references should call `visit` for each entry
+// before reading it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceChain {
+    visited: Vec<usize>,
+    limit: usize,
+}
+
+impl ReferenceChain {
+    // Start a chain at the entry at `offset`.
+    pub fn new(offset: usize) -> Self {
+        ReferenceChain::with_limit(offset, MAX_REFERENCE_CHAIN)
+    }
+
+    // Start a chain that may contain at most `limit` entries.
+    pub fn with_limit(offset: usize, limit: usize) -> Self {
+        ReferenceChain {
+            visited: vec![offset],
+            limit: limit,
+        }
+    }
+
+    // Add the entry at `offset` to the chain.
+    //
+    // Returns `ReadError::ReferenceCycle` if the entry is already in the
+    // chain, or `ReadError::ReferenceLimit` if the chain is full.
+    pub fn visit(&mut self, offset: usize) -> Result<(), ReadError> {
+        if self.visited.contains(&offset) {
+            return Err(ReadError::ReferenceCycle(offset));
+        }
+        if self.visited.len() >= self.limit {
+            return Err(ReadError::ReferenceLimit(offset));
+        }
+        self.visited.push(offset);
+        Ok(())
+    }
+
+    // The offsets of the entries in the chain, in the order visited.
+    pub fn offsets(&self) -> &[usize] {
+        &self.visited
+    }
+}
+
+// Finds the entries referenced by attributes, for use by
+// `Die::attr_resolved`.
+pub trait DieResolver<'data> {
+    // Return the entry referenced by `data`, which is the value of an
+    // attribute of `entry`.
+    fn resolve(
+        &mut self,
+        entry: &Die<'data>,
+        data: &AttributeData<'data>
+    ) -> Result<Die<'data>, ReadError>;
+
+    // Return the direct children of `entry`, not including the null entry.
+    //
+    // The default implementation returns `ReadError::Unsupported`.
+    fn children(&mut self, _entry: &Die<'data>) -> Result<Vec<Die<'data>>, ReadError> {
+        Err(ReadError::Unsupported)
+    }
+
+    // Return the address size of the unit containing `entry`.
+    //
+    // The default implementation returns `ReadError::Unsupported`.
+    fn address_size(&mut self, _entry: &Die<'data>) -> Result<u8, ReadError> {
+        Err(ReadError::Unsupported)
+    }
+
+    // Return the DW_AT_language of the unit containing `entry`, or `None`
+    // if the root entry doesn't have one.
+    //
+    // The default implementation returns `ReadError::Unsupported`.
+    fn language(&mut self, _entry: &Die<'data>) -> Result<Option<constant::DwLang>, ReadError> {
+        Err(ReadError::Unsupported)
+    }
+}
+
+// Return the DW_AT_language of a root entry.
+fn root_language(root: Option<&Die>) -> Option<constant::DwLang> {
+    root.and_then(|root| root.attr(constant::DW_AT_language))
+        .and_then(AttributeData::as_udata)
+        .map(|language| constant::DwLang(language as u16))
+}
+
+// Resolves references to entries in a single compilation unit.
+//
+// DW_FORM_ref_addr references to other units return
+// `ReadError::Unsupported`. Use `SectionsResolver` for those.
+#[derive(Debug)]
+pub struct UnitResolver<'a, 'data: 'a, E: Endian + 'a> {
+    pub unit: &'a CompilationUnit<'data, E>,
+    pub abbrev: &'a AbbrevHash,
+}
+
+impl<'a, 'data, E: Endian> UnitResolver<'a, 'data, E> {
+    pub fn new(unit: &'a CompilationUnit<'data, E>, abbrev: &'a AbbrevHash) -> Self {
+        UnitResolver {
+            unit: unit,
+            abbrev: abbrev,
+        }
+    }
+}
+
+impl<'a, 'data, E: Endian> DieResolver<'data> for UnitResolver<'a, 'data, E> {
+    fn resolve(
+        &mut self,
+        _entry: &Die<'data>,
+        data: &AttributeData<'data>
+    ) -> Result<Die<'data>, ReadError> {
+        let offset = match *data {
+            AttributeData::Ref(offset) => offset.to_section_offset(&self.unit.common),
+            AttributeData::RefAddress(offset) => {
+                let offset = offset.0 as usize;
+                if offset < self.unit.common.offset || offset >= self.unit.end_offset() {
+                    return Err(ReadError::Unsupported);
+                }
+                offset
+            }
+            _ => return Err(ReadError::Invalid),
+        };
+        let mut entries = try!(self.unit.entry(offset, self.abbrev).ok_or(ReadError::Invalid));
+        match try!(entries.next()) {
+            Some(entry) => Ok(entry.clone()),
+            None => Err(ReadError::Invalid),
+        }
+    }
+
+    fn children(&mut self, entry: &Die<'data>) -> Result<Vec<Die<'data>>, ReadError> {
+        let mut entries = try!(self.unit
+            .children(entry.offset, self.abbrev)
+            .ok_or(ReadError::Invalid));
+        let mut children = Vec::new();
+        while let Some(child) = try!(entries.next()) {
+            children.push(child.clone());
+        }
+        Ok(children)
+    }
+
+    fn address_size(&mut self, _entry: &Die<'data>) -> Result<u8, ReadError> {
+        Ok(self.unit.common.address_size)
+    }
+
+    fn language(&mut self, _entry: &Die<'data>) -> Result<Option<constant::DwLang>, ReadError> {
+        let mut entries = self.unit.entries(self.abbrev);
+        Ok(root_language(try!(entries.next())))
+    }
+}
+
+// Resolves references to entries in any compilation unit in .debug_info.
+//
+// The abbreviations of the target unit are read for every reference.
+#[derive(Debug)]
+pub struct SectionsResolver<'a, E: Endian + 'a, S: 'a = Vec<u8>> {
+    sections: &'a Sections<E, S>,
+    index: UnitIndex,
+}
+
+impl<'a, E, S> SectionsResolver<'a, E, S>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    pub fn new(sections: &'a Sections<E, S>) -> Result<Self, ReadError> {
+        Ok(SectionsResolver {
+            sections: sections,
+            index: try!(sections.unit_index()),
+        })
+    }
+
+    // Read the header of the unit containing `entry`.
+    fn unit(&self, entry: &Die<'a>) -> Result<CompilationUnit<'a, E>, ReadError> {
+        let unit_offset = try!(self.index.unit_containing(entry.offset).ok_or(ReadError::Invalid));
+        DieRef::new(self.sections, unit_offset, entry.offset).unit()
+    }
+}
+
+impl<'a, E, S> DieResolver<'a> for SectionsResolver<'a, E, S>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    fn resolve(
+        &mut self,
+        entry: &Die<'a>,
+        data: &AttributeData<'a>
+    ) -> Result<Die<'a>, ReadError> {
+        let (unit_offset, offset) = match *data {
+            AttributeData::Ref(offset) => {
+                let unit_offset = try!(self.index
+                    .unit_containing(entry.offset)
+                    .ok_or(ReadError::Invalid));
+                (unit_offset, unit_offset + offset.0 as usize)
+            }
+            AttributeData::RefAddress(offset) => {
+                let offset = offset.0 as usize;
+                let unit_offset = try!(self.index
+                    .unit_containing(offset)
+                    .ok_or(ReadError::Invalid));
+                (unit_offset, offset)
+            }
+            _ => return Err(ReadError::Invalid),
+        };
+        DieRef::new(self.sections, unit_offset, offset).entry()
+    }
+
+    fn children(&mut self, entry: &Die<'a>) -> Result<Vec<Die<'a>>, ReadError> {
+        let unit = try!(self.unit(entry));
+        let abbrev = try!(self.sections.abbrev(&unit.common));
+        let mut entries = try!(unit.children(entry.offset, &abbrev).ok_or(ReadError::Invalid));
+        let mut children = Vec::new();
+        while let Some(child) = try!(entries.next()) {
+            children.push(child.clone());
+        }
+        Ok(children)
+    }
+
+    fn address_size(&mut self, entry: &Die<'a>) -> Result<u8, ReadError> {
+        self.unit(entry).map(|unit| unit.common.address_size)
+    }
+
+    fn language(&mut self, entry: &Die<'a>) -> Result<Option<constant::DwLang>, ReadError> {
+        let unit = try!(self.unit(entry));
+        let abbrev = try!(self.sections.abbrev(&unit.common));
+        let mut entries = unit.entries(&abbrev);
+        Ok(root_language(try!(entries.next())))
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Die<'data> {
     pub offset: usize,
     pub code: u64,
     pub tag: constant::DwTag,
     pub children: bool,
+    // Call `update_attribute_mask` after changing the attribute names, so
+    // that `has_attr` stays correct. Changing only the values is fine.
     pub attributes: Vec<Attribute<'data>>,
+    // The attributes that are present, for use by `has_attr`.
+    //
+    // This is set by `new` and `read`. Call `update_attribute_mask` after
+    // adding or removing attributes.
+    attribute_mask: AttributeMask,
+    // The location of each attribute in the section, in the same order as
+    // `attributes`.
+    //
+    // This is only set by `read_spans`, or by a `DieIterator` after calling
+    // `enable_attribute_spans`. Otherwise it is empty.
+    pub attribute_spans: Vec<AttributeSpan>,
+}
+
+// Entries are equal if they have the same offset, code, tag, children and
+// attributes. The attribute mask is ignored, since it is derived from the
+// attributes, and `attribute_spans` is ignored, since it depends on how the
+// entry was read.
+impl<'data> PartialEq for Die<'data> {
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset && self.code == other.code && self.tag == other.tag &&
+        self.children == other.children && self.attributes == other.attributes
+    }
+}
+
+impl<'data> Eq for Die<'data> {}
+
+// The location of the encoded value of an attribute in a section.
+//
+// For DW_FORM_indirect, this includes the form that precedes the value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttributeSpan {
+    pub offset: usize,
+    pub len: usize,
 }
 
 impl<'data> Die<'data> {
+    pub fn new(
+        offset: usize,
+        code: u64,
+        tag: constant::DwTag,
+        children: bool,
+        attributes: Vec<Attribute<'data>>
+    ) -> Self {
+        let mut die = Die {
+            offset: offset,
+            code: code,
+            tag: tag,
+            children: children,
+            attributes: attributes,
+            attribute_mask: AttributeMask::new(),
+            attribute_spans: Vec::new(),
+        };
+        die.update_attribute_mask();
+        die
+    }
+
     pub fn null(offset: usize) -> Self {
         Die {
             offset: offset,
@@ -246,6 +1180,8 @@ impl<'data> Die<'data> {
             tag: constant::DW_TAG_null,
             children: false,
             attributes: Vec::new(),
+            attribute_mask: AttributeMask::new(),
+            attribute_spans: Vec::new(),
         }
     }
 
@@ -255,24 +1191,155 @@ impl<'data> Die<'data> {
         self.tag = constant::DW_TAG_null;
         self.children = false;
         self.attributes.clear();
+        self.attribute_mask = AttributeMask::new();
+        self.attribute_spans.clear();
     }
 
     pub fn is_null(&self) -> bool {
         self.code == 0
     }
 
+    pub fn update_attribute_mask(&mut self) {
+        self.attribute_mask = AttributeMask::new();
+        for attribute in &self.attributes {
+            self.attribute_mask.insert(attribute.at);
+        }
+    }
+
+    // Return true if the entry has the attribute.
+    //
+    // This only needs to search the attributes for vendor attributes.
+    pub fn has_attr(&self, at: constant::DwAt) -> bool {
+        match self.attribute_mask.contains(at) {
+            Some(present) => present,
+            None => self.attributes.iter().any(|attr| attr.at == at),
+        }
+    }
+
     pub fn attr(&self, at: constant::DwAt) -> Option<&AttributeData<'data>> {
         self.attributes.iter().find(|attr| attr.at == at).map(|attr| &attr.data)
     }
 
+    // Return the address and size of the code of the entry, from its
+    // DW_AT_low_pc and DW_AT_high_pc.
+    //
+    // Returns `None` if the size is 0, or if DW_AT_low_pc is 0, which
+    // usually means that the linker removed the code.
+    pub fn pc_range(&self) -> Option<(u64, u64)> {
+        let low_pc = match self.attr(constant::DW_AT_low_pc) {
+            Some(&AttributeData::Address(low_pc)) if low_pc != 0 => low_pc,
+            _ => return None,
+        };
+        let size = match self.attr(constant::DW_AT_high_pc) {
+            Some(&AttributeData::Address(high_pc)) => high_pc.checked_sub(low_pc),
+            Some(data) => data.as_udata(),
+            None => None,
+        };
+        match size {
+            Some(size) if size != 0 && low_pc.checked_add(size).is_some() => Some((low_pc, size)),
+            _ => None,
+        }
+    }
+
+    // Return the value of an attribute of the entry, or of the entries it
+    // refers to with DW_AT_abstract_origin or DW_AT_specification.
+    //
+    // Concrete instances of inlined functions and definitions of declared
+    // functions usually omit attributes such as DW_AT_name and
+    // DW_AT_decl_line, which are only present on the referenced entry.
+    //
+    // Attributes that only describe the entry itself, such as
+    // DW_AT_declaration or DW_AT_sibling, should be read with `attr`
+    // instead. Returns `ReadError::ReferenceCycle` if the references form
+    // a cycle.
+    pub fn attr_resolved<R>(
+        &self,
+        at: constant::DwAt,
+        resolver: &mut R
+    ) -> Result<Option<AttributeData<'data>>, ReadError>
+        where R: DieResolver<'data>
+    {
+        if let Some(data) = self.attr(at) {
+            return Ok(Some(data.clone()));
+        }
+        let mut chain = ReferenceChain::new(self.offset);
+        let mut next = try!(self.origin(resolver));
+        while let Some(entry) = next {
+            try!(chain.visit(entry.offset));
+            if let Some(data) = entry.attr(at) {
+                return Ok(Some(data.clone()));
+            }
+            next = try!(entry.origin(resolver));
+        }
+        Ok(None)
+    }
+
+    fn origin<R>(&self, resolver: &mut R) -> Result<Option<Die<'data>>, ReadError>
+        where R: DieResolver<'data>
+    {
+        match self.attr(constant::DW_AT_abstract_origin)
+            .or_else(|| self.attr(constant::DW_AT_specification)) {
+            Some(data) => resolver.resolve(self, data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // Return the line program file index of a DW_AT_decl_file or
+    // DW_AT_call_file attribute, corrected for the quirks of the unit.
+    pub fn file_index<E: Endian>(&self, at: constant::DwAt, unit: &UnitCommon<E>) -> Option<u64> {
+        self.attr(at).and_then(AttributeData::as_udata).map(|index| unit.quirks.file_index(index))
+    }
+
+    // Return the address space of a pointer or reference type, or of the
+    // code of a subprogram.
+    //
+    // The values other than DW_ADDR_none are target specific.
+    pub fn address_class(&self) -> Option<constant::DwAddr> {
+        self.attr(constant::DW_AT_address_class)
+            .and_then(AttributeData::as_udata)
+            .map(constant::DwAddr)
+    }
+
+    // Return the location of an attribute in the section, if it was
+    // recorded when the entry was read.
+    pub fn attribute_span(&self, at: constant::DwAt) -> Option<AttributeSpan> {
+        self.attributes
+            .iter()
+            .position(|attribute| attribute.at == at)
+            .and_then(|index| self.attribute_spans.get(index).cloned())
+    }
+
     pub fn read<'unit, E: Endian>(
         &mut self,
         r: &mut &'data [u8],
         offset: usize,
         unit: &UnitCommon<'unit, E>,
         abbrev_hash: &AbbrevHash
+    ) -> Result<(), ReadError> {
+        self.read_inner(r, offset, unit, abbrev_hash, false)
+    }
+
+    // Like `read`, but also set `attribute_spans`.
+    pub fn read_spans<'unit, E: Endian>(
+        &mut self,
+        r: &mut &'data [u8],
+        offset: usize,
+        unit: &UnitCommon<'unit, E>,
+        abbrev_hash: &AbbrevHash
+    ) -> Result<(), ReadError> {
+        self.read_inner(r, offset, unit, abbrev_hash, true)
+    }
+
+    fn read_inner<'unit, E: Endian>(
+        &mut self,
+        r: &mut &'data [u8],
+        offset: usize,
+        unit: &UnitCommon<'unit, E>,
+        abbrev_hash: &AbbrevHash,
+        spans: bool
     ) -> Result<(), ReadError> {
         self.set_null(offset);
+        let len = r.len();
 
         self.code = try!(leb128::read_u64(r));
         if self.code == 0 {
@@ -287,8 +1354,19 @@ impl<'data> Die<'data> {
         self.tag = abbrev.tag;
         self.children = abbrev.children;
         self.attributes.reserve(abbrev.attributes.len());
+        if spans {
+            self.attribute_spans.reserve(abbrev.attributes.len());
+        }
         for abbrev_attribute in &abbrev.attributes {
+            let start = len - r.len();
             self.attributes.push(try!(Attribute::read(r, unit, abbrev_attribute)));
+            self.attribute_mask.insert(abbrev_attribute.at);
+            if spans {
+                self.attribute_spans.push(AttributeSpan {
+                    offset: offset + start,
+                    len: len - r.len() - start,
+                });
+            }
         }
 
         Ok(())
@@ -324,9 +1402,108 @@ impl<'data> Die<'data> {
         }
         Ok(())
     }
+
+    // Overwrite the value of an attribute of this entry in `section`.
+    //
+    // `section` must have the same layout as the section this entry was
+    // read from (typically it is a copy of it). The value is encoded using
+    // the attribute's existing form, which must have a fixed size, so the
+    // rest of the section is unchanged. The value must not be truncated by
+    // the form.
+    pub fn patch_attribute<'unit, E: Endian>(
+        &self,
+        section: &mut [u8],
+        unit: &UnitCommon<'unit, E>,
+        abbrev_hash: &AbbrevHash,
+        at: constant::DwAt,
+        value: &AttributeData
+    ) -> Result<(), WriteError> {
+        let abbrev = match abbrev_hash.get(self.code) {
+            Some(abbrev) => abbrev,
+            None => return Err(WriteError::Invalid(format!("missing abbrev {}", self.code))),
+        };
+        if self.offset >= section.len() {
+            return Err(WriteError::Invalid(format!("die offset {:x}", self.offset)));
+        }
+
+        let (position, form) = {
+            let data = &section[self.offset..];
+            let mut r = data;
+            if try!(leb128::read_u64(&mut r)) != self.code {
+                return Err(WriteError::Invalid("die/abbrev code mismatch".to_string()));
+            }
+            let mut found = None;
+            for abbrev_attribute in &abbrev.attributes {
+                let mut form = abbrev_attribute.form;
+                if form == constant::DW_FORM_indirect {
+                    form = constant::DwForm(try!(leb128::read_u16(&mut r)));
+                }
+                if abbrev_attribute.at == at {
+                    found = Some((self.offset + data.len() - r.len(), form));
+                    break;
+                }
+                try!(AttributeData::skip(&mut r, unit, form));
+            }
+            match found {
+                Some(found) => found,
+                None => return Err(WriteError::Invalid(format!("missing attribute {}", at))),
+            }
+        };
+        if form == constant::DW_FORM_implicit_const {
+            return Err(WriteError::Unsupported(format!("attribute {} value is in abbrev", at)));
+        }
+        if !is_fixed_size(form) {
+            return Err(WriteError::Unsupported(format!("variable length form {}", form)));
+        }
+        // DW_FORM_flag_present has no data, so it can only be true.
+        if form == constant::DW_FORM_flag_present && *value != AttributeData::Flag(true) {
+            return Err(WriteError::Invalid(format!("value does not fit form {}", form)));
+        }
+
+        let mut buf = Vec::new();
+        try!(value.write(&mut buf, unit, form, false));
+        if try!(AttributeData::read(&mut &buf[..], unit, form)) != *value {
+            return Err(WriteError::Invalid(format!("value does not fit form {}", form)));
+        }
+        if position + buf.len() > section.len() {
+            return Err(WriteError::Invalid(format!("attribute offset {:x}", position)));
+        }
+        section[position..position + buf.len()].copy_from_slice(&buf);
+        Ok(())
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+// A set of the standard attributes, which have values less than
+// DW_AT_lo_user.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AttributeMask([u64; 4]);
+
+impl AttributeMask {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // Add an attribute. Vendor attributes are ignored.
+    pub fn insert(&mut self, at: constant::DwAt) {
+        let index = at.0 as usize;
+        if index < 256 {
+            self.0[index / 64] |= 1 << (index % 64);
+        }
+    }
+
+    // Return whether the attribute is in the set, or `None` for vendor
+    // attributes.
+    pub fn contains(&self, at: constant::DwAt) -> Option<bool> {
+        let index = at.0 as usize;
+        if index < 256 {
+            Some(self.0[index / 64] & (1 << (index % 64)) != 0)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Attribute<'data> {
     pub at: constant::DwAt,
     pub data: AttributeData<'data>,
@@ -345,7 +1522,11 @@ impl<'data> Attribute<'data> {
         unit: &UnitCommon<'unit, E>,
         abbrev: &AbbrevAttribute
     ) -> Result<Attribute<'data>, ReadError> {
-        let data = try!(AttributeData::read(r, unit, abbrev.form));
+        let data = if abbrev.form == constant::DW_FORM_implicit_const {
+            AttributeData::SData(abbrev.implicit_const)
+        } else {
+            try!(AttributeData::read(r, unit, abbrev.form))
+        };
         Ok(Attribute {
             at: abbrev.at,
             data: data,
@@ -361,12 +1542,47 @@ impl<'data> Attribute<'data> {
         if self.at != abbrev.at {
             return Err(WriteError::Invalid("attribute type mismatch".to_string()));
         }
+        if abbrev.form == constant::DW_FORM_implicit_const &&
+           self.data != AttributeData::SData(abbrev.implicit_const) {
+            return Err(WriteError::Invalid("attribute/abbrev implicit_const mismatch".to_string()));
+        }
         try!(self.data.write(w, unit, abbrev.form, false));
         Ok(())
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+// The information needed to interpret attribute values whose class
+// depends on the unit, or that refer to other sections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttributeContext<'data> {
+    pub offset_size: u8,
+    pub debug_str: &'data [u8],
+    pub debug_line_str: &'data [u8],
+}
+
+impl<'data> AttributeContext<'data> {
+    pub fn new<E: Endian>(
+        unit: &UnitCommon<E>,
+        debug_str: &'data [u8],
+        debug_line_str: &'data [u8]
+    ) -> Self {
+        AttributeContext {
+            offset_size: unit.offset_size,
+            debug_str: debug_str,
+            debug_line_str: debug_line_str,
+        }
+    }
+
+    // The string sections for reading the line program of the unit.
+    pub fn line_strings(&self) -> LineStrings<'data> {
+        LineStrings {
+            debug_str: self.debug_str,
+            debug_line_str: self.debug_line_str,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AttributeData<'data> {
     Null,
     Address(u64),
@@ -375,35 +1591,82 @@ pub enum AttributeData<'data> {
     Data2(u16),
     Data4(u32),
     Data8(u64),
+    // A 16 byte constant, for DW_FORM_data16. The bytes are in the order
+    // they are stored.
+    Data16(&'data [u8]),
     UData(u64),
     SData(i64),
     Flag(bool),
     String(&'data [u8]),
     StringOffset(u64),
-    Ref(u64),
-    RefAddress(u64),
+    // An offset in .debug_line_str.
+    LineStringOffset(u64),
+    Ref(UnitOffset),
+    RefAddress(DebugInfoOffset),
     RefSig(u64),
     SecOffset(u64),
     ExprLoc(&'data [u8]),
+    // An index in the .debug_addr section of the skeleton unit, for
+    // DW_FORM_GNU_addr_index, DW_FORM_addrx and DW_FORM_addrx1-4. See
+    // `Sections::indexed_address`.
+    AddressIndex(u64),
+    // An index in .debug_str_offsets, for DW_FORM_GNU_str_index,
+    // DW_FORM_strx and DW_FORM_strx1-4. See `Sections::indexed_string`.
+    StringIndex(u64),
+    // An index in the offsets of the unit's table in .debug_rnglists, for
+    // DW_FORM_rnglistx. See `Sections::rnglistx_offset`.
+    RangeListIndex(u64),
+    // An index in the offsets of the unit's table in .debug_loclists, for
+    // DW_FORM_loclistx. See `Sections::loclistx_offset`.
+    LocationListIndex(u64),
 }
 
 impl<'data> AttributeData<'data> {
-    pub fn as_string(&self, debug_str: &'data [u8]) -> Option<&'data [u8]> {
+    // Return the value of a string attribute, which may be in any of the
+    // string sections of `context`.
+    pub fn as_string_in(&self, context: &AttributeContext<'data>) -> Option<&'data [u8]> {
+        self.as_string_strict_in(context).unwrap_or(None)
+    }
+
+    // Like `as_string_in`, but return an error for string offsets that
+    // are outside of their section or not terminated.
+    pub fn as_string_strict_in(&self,
+                               context: &AttributeContext<'data>)
+                               -> Result<Option<&'data [u8]>, ReadError> {
+        let (section, offset) = match *self {
+            AttributeData::String(val) => return Ok(Some(val)),
+            AttributeData::StringOffset(val) => (context.debug_str, val),
+            AttributeData::LineStringOffset(val) => (context.debug_line_str, val),
+            _ => return Ok(None),
+        };
+        let offset = offset as usize;
+        if offset >= section.len() {
+            return Err(ReadError::Invalid);
+        }
+        let mut r = &section[offset..];
+        read_string(&mut r).map(Some).map_err(|_| ReadError::Invalid)
+    }
+
+    // Return the value of an unsigned constant.
+    //
+    // DW_FORM_implicit_const values are read as `SData`, so non-negative
+    // `SData` values are also accepted.
+    pub fn as_udata(&self) -> Option<u64> {
         match *self {
-            AttributeData::String(val) => Some(val),
-            AttributeData::StringOffset(val) => {
-                let val = val as usize;
-                if val < debug_str.len() {
-                    let mut r = &debug_str[val..];
-                    read_string(&mut r).ok()
-                } else {
-                    None
-                }
-            }
+            AttributeData::Data1(val) => Some(val as u64),
+            AttributeData::Data2(val) => Some(val as u64),
+            AttributeData::Data4(val) => Some(val as u64),
+            AttributeData::Data8(val) => Some(val),
+            AttributeData::UData(val) => Some(val),
+            AttributeData::SData(val) if val >= 0 => Some(val as u64),
             _ => None,
         }
     }
 
+    // Return the value of an attribute that is an offset in another
+    // section, assuming the unit uses the 32-bit format.
+    //
+    // Use `as_offset_in` if the unit may use the 64-bit format.
     pub fn as_offset(&self) -> Option<usize> {
         match *self {
             AttributeData::Data4(val) => Some(val as usize),
@@ -412,6 +1675,22 @@ impl<'data> AttributeData<'data> {
         }
     }
 
+    // Return the value of an attribute that is an offset in another
+    // section.
+    //
+    // Before DWARF 4 these offsets use the data form that has the size of
+    // an offset, which is DW_FORM_data8 in the 64-bit format. Producers
+    // still use that form in later versions, so it is accepted for any
+    // version.
+    pub fn as_offset_in(&self, context: &AttributeContext) -> Option<usize> {
+        match (self, context.offset_size) {
+            (&AttributeData::Data4(val), 4) => Some(val as usize),
+            (&AttributeData::Data8(val), 8) => Some(val as usize),
+            (&AttributeData::SecOffset(val), _) => Some(val as usize),
+            _ => None,
+        }
+    }
+
     pub fn read<'unit, E: Endian>(
         r: &mut &'data [u8],
         unit: &UnitCommon<'unit, E>,
@@ -435,6 +1714,7 @@ impl<'data> AttributeData<'data> {
             constant::DW_FORM_data2 => AttributeData::Data2(try!(unit.endian.read_u16(r))),
             constant::DW_FORM_data4 => AttributeData::Data4(try!(unit.endian.read_u32(r))),
             constant::DW_FORM_data8 => AttributeData::Data8(try!(unit.endian.read_u64(r))),
+            constant::DW_FORM_data16 => AttributeData::Data16(try!(read_slice(r, 16))),
             constant::DW_FORM_string => AttributeData::String(try!(read_string(r))),
             constant::DW_FORM_block => {
                 let len = try!(leb128::read_u64(r)) as usize;
@@ -453,20 +1733,30 @@ impl<'data> AttributeData<'data> {
                 let val = try!(read_offset(r, unit.endian, unit.offset_size));
                 AttributeData::StringOffset(val)
             }
+            constant::DW_FORM_line_strp => {
+                let val = try!(read_offset(r, unit.endian, unit.offset_size));
+                AttributeData::LineStringOffset(val)
+            }
             constant::DW_FORM_udata => AttributeData::UData(try!(leb128::read_u64(r))),
             constant::DW_FORM_ref_addr => {
-                let val = if unit.version == 2 {
-                    try!(read_address(r, unit.endian, unit.address_size))
-                } else {
-                    try!(read_offset(r, unit.endian, unit.offset_size))
-                };
-                AttributeData::RefAddress(val)
-            }
-            constant::DW_FORM_ref1 => AttributeData::Ref(try!(read_u8(r)) as u64),
-            constant::DW_FORM_ref2 => AttributeData::Ref(try!(unit.endian.read_u16(r)) as u64),
-            constant::DW_FORM_ref4 => AttributeData::Ref(try!(unit.endian.read_u32(r)) as u64),
-            constant::DW_FORM_ref8 => AttributeData::Ref(try!(unit.endian.read_u64(r)) as u64),
-            constant::DW_FORM_ref_udata => AttributeData::Ref(try!(leb128::read_u64(r))),
+                let val = try!(read_address(r, unit.endian, unit.ref_addr_size()));
+                AttributeData::RefAddress(DebugInfoOffset(val))
+            }
+            constant::DW_FORM_ref1 => {
+                AttributeData::Ref(UnitOffset(try!(read_u8(r)) as u64))
+            }
+            constant::DW_FORM_ref2 => {
+                AttributeData::Ref(UnitOffset(try!(unit.endian.read_u16(r)) as u64))
+            }
+            constant::DW_FORM_ref4 => {
+                AttributeData::Ref(UnitOffset(try!(unit.endian.read_u32(r)) as u64))
+            }
+            constant::DW_FORM_ref8 => {
+                AttributeData::Ref(UnitOffset(try!(unit.endian.read_u64(r))))
+            }
+            constant::DW_FORM_ref_udata => {
+                AttributeData::Ref(UnitOffset(try!(leb128::read_u64(r))))
+            }
             constant::DW_FORM_indirect => {
                 let val = try!(leb128::read_u16(r));
                 try!(AttributeData::read(r, unit, constant::DwForm(val)))
@@ -483,11 +1773,105 @@ impl<'data> AttributeData<'data> {
             }
             constant::DW_FORM_flag_present => AttributeData::Flag(true),
             constant::DW_FORM_ref_sig8 => AttributeData::RefSig(try!(unit.endian.read_u64(r))),
+            constant::DW_FORM_GNU_addr_index |
+            constant::DW_FORM_addrx => AttributeData::AddressIndex(try!(leb128::read_u64(r))),
+            constant::DW_FORM_addrx1 => AttributeData::AddressIndex(try!(read_u8(r)) as u64),
+            constant::DW_FORM_addrx2 => {
+                AttributeData::AddressIndex(try!(unit.endian.read_u16(r)) as u64)
+            }
+            constant::DW_FORM_addrx3 => {
+                AttributeData::AddressIndex(try!(read_u24(r, unit.endian)) as u64)
+            }
+            constant::DW_FORM_addrx4 => {
+                AttributeData::AddressIndex(try!(unit.endian.read_u32(r)) as u64)
+            }
+            constant::DW_FORM_GNU_str_index |
+            constant::DW_FORM_strx => AttributeData::StringIndex(try!(leb128::read_u64(r))),
+            constant::DW_FORM_strx1 => AttributeData::StringIndex(try!(read_u8(r)) as u64),
+            constant::DW_FORM_strx2 => {
+                AttributeData::StringIndex(try!(unit.endian.read_u16(r)) as u64)
+            }
+            constant::DW_FORM_strx3 => {
+                AttributeData::StringIndex(try!(read_u24(r, unit.endian)) as u64)
+            }
+            constant::DW_FORM_strx4 => {
+                AttributeData::StringIndex(try!(unit.endian.read_u32(r)) as u64)
+            }
+            // The value is stored in the abbreviation, so it is read by
+            // `Attribute::read` instead.
+            constant::DW_FORM_implicit_const => return Err(ReadError::Invalid),
+            constant::DW_FORM_rnglistx => AttributeData::RangeListIndex(try!(leb128::read_u64(r))),
+            constant::DW_FORM_loclistx => {
+                AttributeData::LocationListIndex(try!(leb128::read_u64(r)))
+            }
             _ => return Err(ReadError::Unsupported),
         };
         Ok(data)
     }
 
+    // Skip over a value without decoding it.
+    //
+    // This is faster than `read` when the value isn't needed.
+    pub fn skip<'unit, E: Endian>(
+        r: &mut &[u8],
+        unit: &UnitCommon<'unit, E>,
+        form: constant::DwForm
+    ) -> Result<(), ReadError> {
+        let len = match form {
+            constant::DW_FORM_flag_present |
+            constant::DW_FORM_implicit_const => 0,
+            constant::DW_FORM_data1 |
+            constant::DW_FORM_flag |
+            constant::DW_FORM_ref1 |
+            constant::DW_FORM_strx1 |
+            constant::DW_FORM_addrx1 => 1,
+            constant::DW_FORM_data2 |
+            constant::DW_FORM_ref2 |
+            constant::DW_FORM_strx2 |
+            constant::DW_FORM_addrx2 => 2,
+            constant::DW_FORM_strx3 |
+            constant::DW_FORM_addrx3 => 3,
+            constant::DW_FORM_data4 |
+            constant::DW_FORM_ref4 |
+            constant::DW_FORM_strx4 |
+            constant::DW_FORM_addrx4 => 4,
+            constant::DW_FORM_data8 |
+            constant::DW_FORM_ref8 |
+            constant::DW_FORM_ref_sig8 => 8,
+            constant::DW_FORM_data16 => 16,
+            constant::DW_FORM_addr => unit.address_size as usize,
+            constant::DW_FORM_strp |
+            constant::DW_FORM_line_strp |
+            constant::DW_FORM_sec_offset => unit.offset_size as usize,
+            constant::DW_FORM_ref_addr => unit.ref_addr_size() as usize,
+            constant::DW_FORM_udata |
+            constant::DW_FORM_sdata |
+            constant::DW_FORM_ref_udata |
+            constant::DW_FORM_GNU_addr_index |
+            constant::DW_FORM_GNU_str_index |
+            constant::DW_FORM_addrx |
+            constant::DW_FORM_strx |
+            constant::DW_FORM_rnglistx |
+            constant::DW_FORM_loclistx => return leb128::skip(r),
+            constant::DW_FORM_string => {
+                try!(read_string(r));
+                return Ok(());
+            }
+            constant::DW_FORM_block1 => try!(read_u8(r)) as usize,
+            constant::DW_FORM_block2 => try!(unit.endian.read_u16(r)) as usize,
+            constant::DW_FORM_block4 => try!(unit.endian.read_u32(r)) as usize,
+            constant::DW_FORM_block |
+            constant::DW_FORM_exprloc => try!(leb128::read_u64(r)) as usize,
+            constant::DW_FORM_indirect => {
+                let val = try!(leb128::read_u16(r));
+                return AttributeData::skip(r, unit, constant::DwForm(val));
+            }
+            _ => return Err(ReadError::Unsupported),
+        };
+        try!(read_block(r, len));
+        Ok(())
+    }
+
     #[cfg_attr(feature = "clippy", allow(match_same_arms))]
     pub fn write<'unit, E: Endian, W: Write>(
         &self,
@@ -531,6 +1915,12 @@ impl<'data> AttributeData<'data> {
             (&AttributeData::Data8(ref val), constant::DW_FORM_data8) => {
                 try!(unit.endian.write_u64(w, *val));
             }
+            (&AttributeData::Data16(val), constant::DW_FORM_data16) => {
+                if val.len() != 16 {
+                    return Err(WriteError::Invalid(format!("data16 length {}", val.len())));
+                }
+                try!(w.write_all(val));
+            }
             (&AttributeData::UData(ref val), constant::DW_FORM_udata) => {
                 try!(leb128::write_u64(w, *val));
             }
@@ -547,30 +1937,27 @@ impl<'data> AttributeData<'data> {
                 try!(w.write_all(val));
                 try!(write_u8(w, 0));
             }
-            (&AttributeData::StringOffset(ref val), constant::DW_FORM_strp) => {
+            (&AttributeData::StringOffset(ref val), constant::DW_FORM_strp) |
+            (&AttributeData::LineStringOffset(ref val), constant::DW_FORM_line_strp) => {
                 try!(write_offset(w, unit.endian, unit.offset_size, *val));
             }
             (&AttributeData::Ref(ref val), constant::DW_FORM_ref1) => {
-                try!(write_u8(w, *val as u8));
+                try!(write_u8(w, try!(fit_form(val.0, 0xff, form)) as u8));
             }
             (&AttributeData::Ref(ref val), constant::DW_FORM_ref2) => {
-                try!(unit.endian.write_u16(w, *val as u16));
+                try!(unit.endian.write_u16(w, try!(fit_form(val.0, 0xffff, form)) as u16));
             }
             (&AttributeData::Ref(ref val), constant::DW_FORM_ref4) => {
-                try!(unit.endian.write_u32(w, *val as u32));
+                try!(unit.endian.write_u32(w, try!(fit_form(val.0, 0xffffffff, form)) as u32));
             }
             (&AttributeData::Ref(ref val), constant::DW_FORM_ref8) => {
-                try!(unit.endian.write_u64(w, *val as u64));
+                try!(unit.endian.write_u64(w, val.0));
             }
             (&AttributeData::Ref(ref val), constant::DW_FORM_ref_udata) => {
-                try!(leb128::write_u64(w, *val as u64));
+                try!(leb128::write_u64(w, val.0));
             }
             (&AttributeData::RefAddress(ref val), constant::DW_FORM_ref_addr) => {
-                if unit.version == 2 {
-                    try!(write_address(w, unit.endian, unit.address_size, *val));
-                } else {
-                    try!(write_offset(w, unit.endian, unit.offset_size, *val));
-                }
+                try!(write_address(w, unit.endian, unit.ref_addr_size(), val.0));
             }
             (&AttributeData::RefSig(ref val), constant::DW_FORM_ref_sig8) => {
                 try!(unit.endian.write_u64(w, *val));
@@ -582,12 +1969,78 @@ impl<'data> AttributeData<'data> {
                 try!(leb128::write_u64(w, val.len() as u64));
                 try!(w.write_all(val));
             }
+            (&AttributeData::AddressIndex(ref val), constant::DW_FORM_GNU_addr_index) |
+            (&AttributeData::AddressIndex(ref val), constant::DW_FORM_addrx) |
+            (&AttributeData::StringIndex(ref val), constant::DW_FORM_GNU_str_index) |
+            (&AttributeData::StringIndex(ref val), constant::DW_FORM_strx) |
+            (&AttributeData::RangeListIndex(ref val), constant::DW_FORM_rnglistx) |
+            (&AttributeData::LocationListIndex(ref val), constant::DW_FORM_loclistx) => {
+                try!(leb128::write_u64(w, *val));
+            }
+            (&AttributeData::AddressIndex(val), constant::DW_FORM_addrx1) |
+            (&AttributeData::StringIndex(val), constant::DW_FORM_strx1) => {
+                try!(write_u8(w, try!(fit_form(val, 0xff, form)) as u8));
+            }
+            (&AttributeData::AddressIndex(val), constant::DW_FORM_addrx2) |
+            (&AttributeData::StringIndex(val), constant::DW_FORM_strx2) => {
+                try!(unit.endian.write_u16(w, try!(fit_form(val, 0xffff, form)) as u16));
+            }
+            (&AttributeData::AddressIndex(val), constant::DW_FORM_addrx3) |
+            (&AttributeData::StringIndex(val), constant::DW_FORM_strx3) => {
+                try!(write_u24(w, unit.endian, try!(fit_form(val, 0xffffff, form)) as u32));
+            }
+            (&AttributeData::AddressIndex(val), constant::DW_FORM_addrx4) |
+            (&AttributeData::StringIndex(val), constant::DW_FORM_strx4) => {
+                try!(unit.endian.write_u32(w, try!(fit_form(val, 0xffffffff, form)) as u32));
+            }
+            // The value is written in the abbreviation by `Attribute::write`.
+            (&AttributeData::SData(_), constant::DW_FORM_implicit_const) => {}
             _ => return Err(WriteError::Unsupported(format!("attribute form {}", form.0))),
         }
         Ok(())
     }
 }
 
+// Return `val` if it is at most `max`, for writing a reference or index
+// with a fixed size form.
+fn fit_form(val: u64, max: u64, form: constant::DwForm) -> Result<u64, WriteError> {
+    if val > max {
+        return Err(WriteError::Invalid(format!("value {:x} does not fit form {}", val, form)));
+    }
+    Ok(val)
+}
+
+fn is_fixed_size(form: constant::DwForm) -> bool {
+    matches!(
+        form,
+        constant::DW_FORM_addr |
+        constant::DW_FORM_data1 |
+        constant::DW_FORM_data2 |
+        constant::DW_FORM_data4 |
+        constant::DW_FORM_data8 |
+        constant::DW_FORM_data16 |
+        constant::DW_FORM_flag |
+        constant::DW_FORM_flag_present |
+        constant::DW_FORM_strp |
+        constant::DW_FORM_line_strp |
+        constant::DW_FORM_ref_addr |
+        constant::DW_FORM_ref1 |
+        constant::DW_FORM_ref2 |
+        constant::DW_FORM_ref4 |
+        constant::DW_FORM_ref8 |
+        constant::DW_FORM_ref_sig8 |
+        constant::DW_FORM_sec_offset |
+        constant::DW_FORM_strx1 |
+        constant::DW_FORM_strx2 |
+        constant::DW_FORM_strx3 |
+        constant::DW_FORM_strx4 |
+        constant::DW_FORM_addrx1 |
+        constant::DW_FORM_addrx2 |
+        constant::DW_FORM_addrx3 |
+        constant::DW_FORM_addrx4
+    )
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -595,6 +2048,7 @@ mod test {
     use constant::*;
     use endian::*;
     use unit::*;
+    use Sections;
 
     #[test]
     #[cfg_attr(rustfmt, rustfmt_skip)]
@@ -605,7 +2059,7 @@ mod test {
             tag: DW_TAG_namespace,
             children: true,
             attributes: vec![
-                AbbrevAttribute { at: DW_AT_name, form: DW_FORM_string },
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
             ],
         });
         abbrev_hash.insert(Abbrev {
@@ -613,20 +2067,14 @@ mod test {
             tag: DW_TAG_namespace,
             children: false,
             attributes: vec![
-                AbbrevAttribute { at: DW_AT_name, form: DW_FORM_string },
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
             ],
         });
 
         fn entry<'data>(name: &'data str, children: bool) -> Die<'data> {
-            Die {
-                offset: 0,
-                code: if children { 1 } else { 2 },
-                tag: DW_TAG_namespace,
-                children: children,
-                attributes: vec![
-                    Attribute { at: DW_AT_name, data: AttributeData::String(name.as_bytes()) },
-                ],
-            }
+            Die::new(0, if children { 1 } else { 2 }, DW_TAG_namespace, children, vec![
+                Attribute { at: DW_AT_name, data: AttributeData::String(name.as_bytes()) },
+            ])
         }
 
         let mut write_val = [
@@ -664,6 +2112,13 @@ mod test {
         }
         assert!(entries.next().unwrap().is_none());
 
+        let depths = [0, 1, 1, 2, 1, 2, 2, 1, 2, 3, 4, 3, 2, 1, 1, 0];
+        let mut entries = unit.entries(0, &abbrev_hash);
+        for &depth in &depths {
+            assert!(entries.next().unwrap().is_some());
+            assert_eq!(entries.depth(), depth);
+        }
+
         let mut entries = unit.entries(0, &abbrev_hash);
         assert_eq!(*entries.next_sibling().unwrap().unwrap(), write_val[0]);
         assert_eq!(*entries.next().unwrap().unwrap(), write_val[1]);
@@ -675,8 +2130,73 @@ mod test {
         assert_eq!(*entries.next_sibling().unwrap().unwrap(), write_val[15]);
         assert!(entries.next_sibling().unwrap().is_none());
 
+        let mut entries = unit.entries(0, &abbrev_hash);
+        let start = entries.save();
+        assert_eq!(*entries.next().unwrap().unwrap(), write_val[0]);
+        assert_eq!(*entries.next().unwrap().unwrap(), write_val[1]);
+        assert_eq!(*entries.next().unwrap().unwrap(), write_val[2]);
+        let state = entries.save();
+        assert_eq!(*entries.next().unwrap().unwrap(), write_val[3]);
+        assert_eq!(*entries.next().unwrap().unwrap(), write_val[4]);
+        assert_eq!(entries.depth(), 1);
+        entries.restore(state).unwrap();
+        assert_eq!(entries.depth(), 1);
+        assert_eq!(*entries.next_sibling().unwrap().unwrap(), write_val[4]);
+        assert_eq!(*entries.next().unwrap().unwrap(), write_val[5]);
+        assert_eq!(*entries.next().unwrap().unwrap(), write_val[6]);
+        let state = entries.save();
+        assert_eq!(*entries.next_sibling().unwrap().unwrap(), write_val[7]);
+        entries.restore(state).unwrap();
+        assert_eq!(*entries.next().unwrap().unwrap(), write_val[7]);
+        assert_eq!(entries.depth(), 1);
+        entries.restore(start).unwrap();
+        assert_eq!(*entries.next_sibling().unwrap().unwrap(), write_val[0]);
+        assert_eq!(*entries.next_sibling().unwrap().unwrap(), write_val[15]);
+        entries.enable_recovery();
+        match entries.restore(start) {
+            Err(ReadError::Unsupported) => {}
+            otherwise => panic!("{:?}", otherwise),
+        }
+
+        let mut entries = unit.entries(0, &abbrev_hash).with_offsets();
+        let mut offset = 0;
+        for i in 0..write_val.len() {
+            let (entry_offset, read_val, len) = entries.next().unwrap().unwrap();
+            assert_eq!(entry_offset, offset);
+            assert_eq!(*read_val, write_val[i]);
+            let next_offset = write_val.get(i + 1).map(|val| val.offset).unwrap_or(data.len());
+            assert_eq!(len, next_offset - offset);
+            offset += len;
+        }
+        assert!(entries.next().unwrap().is_none());
+
         // TODO test DW_AT_sibling
 
+        let mut entries = unit.entries(0, &abbrev_hash).breadth_first();
+        for &(i, depth) in &[(0, 0), (15, 0), (1, 1), (2, 1), (4, 1), (7, 1), (13, 1),
+                             (5, 2), (8, 2), (9, 3)] {
+            assert_eq!(*entries.next().unwrap().unwrap(), write_val[i]);
+            assert_eq!(entries.depth(), depth);
+        }
+        assert!(entries.next().unwrap().is_none());
+
+        let mut entries = unit.entries(0, &abbrev_hash).breadth_first().max_depth(1);
+        for &i in &[0, 15, 1, 2, 4, 7, 13] {
+            assert_eq!(*entries.next().unwrap().unwrap(), write_val[i]);
+        }
+        assert!(entries.next().unwrap().is_none());
+
+        for &(parent, ref children) in &[(0, vec![1, 2, 4, 7, 13]), (7, vec![8]),
+                                         (1, vec![]), (2, vec![])] {
+            let offset = write_val[parent].offset;
+            let mut entries = unit.entry(0, offset, &abbrev_hash).unwrap().children();
+            for &i in children {
+                assert_eq!(*entries.next().unwrap().unwrap(), write_val[i]);
+            }
+            assert!(entries.next().unwrap().is_none());
+            assert!(entries.next().unwrap().is_none());
+        }
+
         let mut tree = unit.entries(0, &abbrev_hash).tree();
         let mut tree = tree.iter();
         {
@@ -726,26 +2246,411 @@ mod test {
     }
 
     #[test]
-    fn die() {
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn die_ref() {
+        let abbrev = AbbrevVec::new(vec![
+            Abbrev { code: 1, tag: DW_TAG_compile_unit, children: true, attributes: vec![] },
+            Abbrev { code: 2, tag: DW_TAG_base_type, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_strp),
+            ] },
+            Abbrev { code: 3, tag: DW_TAG_variable, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                AbbrevAttribute::new(DW_AT_type, DW_FORM_ref4),
+                AbbrevAttribute::new(DW_AT_specification, DW_FORM_ref_addr),
+            ] },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev.write(&mut debug_abbrev).unwrap();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        // Unit offsets: 11 root, 12 int, 17 variable, 28 null.
+        let mut debug_info = Vec::new();
+        for &var in &["a", "b"] {
+            let entries = [
+                Die::new(0, 1, DW_TAG_compile_unit, true, vec![]),
+                Die::new(0, 2, DW_TAG_base_type, false, vec![
+                    Attribute { at: DW_AT_name, data: AttributeData::StringOffset(0) },
+                ]),
+                Die::new(0, 3, DW_TAG_variable, false, vec![
+                    Attribute { at: DW_AT_name, data: AttributeData::String(var.as_bytes()) },
+                    Attribute { at: DW_AT_type, data: AttributeData::Ref(UnitOffset(12)) },
+                    Attribute { at: DW_AT_specification, data: AttributeData::RefAddress(DebugInfoOffset(17)) },
+                ]),
+                Die::null(0),
+            ];
+            let mut unit = CompilationUnit::<LittleEndian>::default();
+            unit.common.offset = debug_info.len();
+            let mut data = Vec::new();
+            for entry in &entries {
+                entry.write(&mut data, &unit.common, &abbrev_hash).unwrap();
+            }
+            unit.common.data = &data;
+            unit.write(&mut debug_info).unwrap();
+        }
+        let sections = Sections {
+            debug_abbrev: debug_abbrev,
+            debug_info: debug_info,
+            debug_str: b"int\0".to_vec(),
+            ..Sections::new(LittleEndian)
+        };
+
+        let var = DieRef::new(&sections, 29, 46);
+        assert_eq!(var.entry().unwrap().tag, DW_TAG_variable);
+        assert_eq!(var.string(DW_AT_name).unwrap(), Some(&b"b"[..]));
+
+        let ty = var.reference(DW_AT_type).unwrap().unwrap();
+        assert_eq!((ty.unit_offset, ty.offset), (29, 41));
+        assert_eq!(ty.string(DW_AT_name).unwrap(), Some(&b"int"[..]));
+
+        let spec = var.reference(DW_AT_specification).unwrap().unwrap();
+        assert_eq!((spec.unit_offset, spec.offset), (0, 17));
+        let index = sections.unit_index().unwrap();
+        let spec = var.reference_in(DW_AT_specification, &index).unwrap().unwrap();
+        assert_eq!((spec.unit_offset, spec.offset), (0, 17));
+        assert_eq!(spec.string(DW_AT_name).unwrap(), Some(&b"a"[..]));
+
+        let children = DieRef::new(&sections, 29, 40).children().unwrap();
+        assert_eq!(children.iter().map(|child| child.offset).collect::<Vec<_>>(), vec![41, 46]);
+        assert!(var.children().unwrap().is_empty());
+
+        assert!(var.reference(DW_AT_sibling).unwrap().is_none());
+        assert!(var.reference(DW_AT_name).is_err());
+    }
+
+    #[test]
+    fn reference_chain() {
+        let mut chain = ReferenceChain::with_limit(0x10, 3);
+        chain.visit(0x20).unwrap();
+        match chain.visit(0x10) {
+            Err(ReadError::ReferenceCycle(0x10)) => {}
+            otherwise => panic!("{:?}", otherwise),
+        }
+        chain.visit(0x30).unwrap();
+        match chain.visit(0x40) {
+            Err(ReadError::ReferenceLimit(0x40)) => {}
+            otherwise => panic!("{:?}", otherwise),
+        }
+        assert_eq!(chain.offsets(), &[0x10, 0x20, 0x30]);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn attr_resolved() {
+        let abbrev = AbbrevVec::new(vec![
+            Abbrev { code: 1, tag: DW_TAG_compile_unit, children: true, attributes: vec![] },
+            Abbrev { code: 2, tag: DW_TAG_subprogram, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                AbbrevAttribute::new(DW_AT_decl_line, DW_FORM_data1),
+            ] },
+            Abbrev { code: 3, tag: DW_TAG_subprogram, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_specification, DW_FORM_ref4),
+            ] },
+            Abbrev { code: 4, tag: DW_TAG_subprogram, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_abstract_origin, DW_FORM_ref4),
+            ] },
+            Abbrev { code: 5, tag: DW_TAG_subprogram, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_abstract_origin, DW_FORM_ref_addr),
+            ] },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev.write(&mut debug_abbrev).unwrap();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        // First unit offsets: 11 root, 12 declaration, 16 definition,
+        // 21 concrete instance, 26 and 31 cycle, 36 null.
+        // Second unit offsets: 48 root, 49 concrete instance, 54 null.
+        let units = [
+            vec![
+                Die::new(0, 1, DW_TAG_compile_unit, true, vec![]),
+                Die::new(0, 2, DW_TAG_subprogram, false, vec![
+                    Attribute { at: DW_AT_name, data: AttributeData::String(b"f") },
+                    Attribute { at: DW_AT_decl_line, data: AttributeData::Data1(3) },
+                ]),
+                Die::new(0, 3, DW_TAG_subprogram, false, vec![
+                    Attribute { at: DW_AT_specification, data: AttributeData::Ref(UnitOffset(12)) },
+                ]),
+                Die::new(0, 4, DW_TAG_subprogram, false, vec![
+                    Attribute { at: DW_AT_abstract_origin, data: AttributeData::Ref(UnitOffset(16)) },
+                ]),
+                Die::new(0, 4, DW_TAG_subprogram, false, vec![
+                    Attribute { at: DW_AT_abstract_origin, data: AttributeData::Ref(UnitOffset(31)) },
+                ]),
+                Die::new(0, 4, DW_TAG_subprogram, false, vec![
+                    Attribute { at: DW_AT_abstract_origin, data: AttributeData::Ref(UnitOffset(26)) },
+                ]),
+                Die::null(0),
+            ],
+            vec![
+                Die::new(0, 1, DW_TAG_compile_unit, true, vec![]),
+                Die::new(0, 5, DW_TAG_subprogram, false, vec![
+                    Attribute {
+                        at: DW_AT_abstract_origin,
+                        data: AttributeData::RefAddress(DebugInfoOffset(16)),
+                    },
+                ]),
+                Die::null(0),
+            ],
+        ];
+        let mut debug_info = Vec::new();
+        for entries in &units {
+            let mut unit = CompilationUnit::<LittleEndian>::default();
+            unit.common.offset = debug_info.len();
+            let mut data = Vec::new();
+            for entry in entries {
+                entry.write(&mut data, &unit.common, &abbrev_hash).unwrap();
+            }
+            unit.common.data = &data;
+            unit.write(&mut debug_info).unwrap();
+        }
+        let sections = Sections {
+            debug_abbrev: debug_abbrev,
+            debug_info: debug_info,
+            ..Sections::new(LittleEndian)
+        };
+
+        let mut units = sections.compilation_units();
+        let unit = units.next().unwrap().unwrap();
+        fn entry<'data>(
+            unit: &CompilationUnit<'data, LittleEndian>,
+            abbrev: &AbbrevHash,
+            offset: usize
+        ) -> Die<'data> {
+            unit.entry(offset, abbrev).unwrap().next().unwrap().unwrap().clone()
+        }
+        let mut resolver = UnitResolver::new(&unit, &abbrev_hash);
+        let concrete = entry(&unit, &abbrev_hash, 21);
+        assert_eq!(concrete.attr_resolved(DW_AT_name, &mut resolver).unwrap(),
+                   Some(AttributeData::String(b"f")));
+        assert_eq!(concrete.attr_resolved(DW_AT_decl_line, &mut resolver).unwrap(),
+                   Some(AttributeData::Data1(3)));
+        assert_eq!(concrete.attr_resolved(DW_AT_abstract_origin, &mut resolver).unwrap(),
+                   Some(AttributeData::Ref(UnitOffset(16))));
+        assert_eq!(concrete.attr_resolved(DW_AT_low_pc, &mut resolver).unwrap(), None);
+        match entry(&unit, &abbrev_hash, 26).attr_resolved(DW_AT_name, &mut resolver) {
+            Err(ReadError::ReferenceCycle(26)) => {}
+            otherwise => panic!("{:?}", otherwise),
+        }
+
+        let unit = units.next().unwrap().unwrap();
+        let concrete = entry(&unit, &abbrev_hash, 49);
+        let mut resolver = UnitResolver::new(&unit, &abbrev_hash);
+        assert!(concrete.attr_resolved(DW_AT_name, &mut resolver).is_err());
+        let mut resolver = SectionsResolver::new(&sections).unwrap();
+        assert_eq!(concrete.attr_resolved(DW_AT_name, &mut resolver).unwrap(),
+                   Some(AttributeData::String(b"f")));
+        assert_eq!(entry(&unit, &abbrev_hash, 49).attr_resolved(DW_AT_decl_line, &mut resolver).unwrap(),
+                   Some(AttributeData::Data1(3)));
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn sibling_validation() {
         let mut abbrev_hash = AbbrevHash::new();
-        let code = 1;
         abbrev_hash.insert(Abbrev {
-            code: code,
+            code: 1,
+            tag: DW_TAG_namespace,
+            children: true,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_sibling, DW_FORM_ref4),
+            ],
+        });
+        abbrev_hash.insert(Abbrev {
+            code: 2,
+            tag: DW_TAG_namespace,
+            children: false,
+            attributes: vec![],
+        });
+        abbrev_hash.insert(Abbrev {
+            code: 3,
+            tag: DW_TAG_compile_unit,
+            children: true,
+            attributes: vec![],
+        });
+
+        // The unit is at offset 0, and the entries start at offset 11.
+        let data = [
+            3,
+            1, 19, 0, 0, 0,
+            2,
+            0,
+            // The sibling should be 26.
+            1, 27, 0, 0, 0,
+            2,
+            0,
+            2,
+            0,
+        ];
+        let mut unit = UnitCommon { endian: LittleEndian, ..Default::default() };
+        unit.data = &data;
+
+        // Without validation, the entry at 26 is skipped.
+        let mut entries = unit.entries(11, &abbrev_hash);
+        entries.next().unwrap();
+        entries.next().unwrap();
+        assert_eq!(entries.next_sibling().unwrap().unwrap().offset, 19);
+        assert!(entries.next_sibling().unwrap().unwrap().is_null());
+        assert!(entries.sibling_mismatches().is_empty());
+
+        let mismatch = SiblingMismatch { offset: 19, sibling: 27, actual: 26 };
+
+        let mut entries = unit.entries(11, &abbrev_hash);
+        entries.enable_sibling_validation();
+        entries.next().unwrap();
+        entries.next().unwrap();
+        assert_eq!(entries.next_sibling().unwrap().unwrap().offset, 19);
+        assert_eq!(entries.next_sibling().unwrap().unwrap().offset, 26);
+        assert_eq!(entries.sibling_mismatches(), std::slice::from_ref(&mismatch));
+
+        let mut entries = unit.entries(11, &abbrev_hash);
+        entries.enable_sibling_validation();
+        while entries.next().unwrap().is_some() {}
+        assert_eq!(entries.sibling_mismatches(), &[mismatch]);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn recovery() {
+        let mut abbrev_hash = AbbrevHash::new();
+        abbrev_hash.insert(Abbrev {
+            code: 1,
             tag: DW_TAG_namespace,
             children: true,
             attributes: vec![
-                AbbrevAttribute { at: DW_AT_name, form: DW_FORM_string },
+                AbbrevAttribute::new(DW_AT_sibling, DW_FORM_ref4),
             ],
         });
-        let write_val = Die {
-            offset: 0,
+        abbrev_hash.insert(Abbrev {
+            code: 2,
+            tag: DW_TAG_namespace,
+            children: false,
+            attributes: vec![],
+        });
+        abbrev_hash.insert(Abbrev {
+            code: 3,
+            tag: DW_TAG_compile_unit,
+            children: true,
+            attributes: vec![],
+        });
+
+        // The unit is at offset 0, and the entries start at offset 11.
+        let data = [
+            3,
+            1, 20, 0, 0, 0,
+            // Invalid code.
+            0x7f,
+            2,
+            0,
+            2,
+            0,
+        ];
+        let mut unit = UnitCommon { endian: LittleEndian, ..Default::default() };
+        unit.data = &data;
+
+        let mut entries = unit.entries(11, &abbrev_hash);
+        assert!(entries.next().is_ok());
+        assert!(entries.next().is_ok());
+        assert!(entries.next().is_err());
+
+        // The invalid entry is skipped using the sibling.
+        let mut entries = unit.entries(11, &abbrev_hash);
+        assert!(entries.next().is_ok());
+        assert!(entries.next().is_ok());
+        assert_eq!(entries.next_sibling().unwrap().unwrap().offset, 20);
+        assert_eq!(entries.depth(), 1);
+        assert!(entries.next().unwrap().unwrap().is_null());
+        assert_eq!(entries.depth(), 1);
+
+        let mut entries = unit.entries(11, &abbrev_hash);
+        entries.enable_recovery();
+        let mut read = Vec::new();
+        let mut depths = Vec::new();
+        while let Some(entry) = entries.next().unwrap() {
+            read.push((entry.offset, entry.tag));
+            depths.push(entries.depth());
+        }
+        assert_eq!(read, vec![
+            (11, DW_TAG_compile_unit),
+            (12, DW_TAG_namespace),
+            (20, DW_TAG_null),
+            (20, DW_TAG_namespace),
+            (21, DW_TAG_null),
+        ]);
+        assert_eq!(depths, vec![0, 1, 2, 1, 1]);
+        assert_eq!(entries.skipped().len(), 1);
+        assert_eq!(entries.skipped()[0].offset, 17);
+        assert_eq!(entries.skipped()[0].end, 20);
+
+        // Without a sibling, the rest of the unit is skipped.
+        let data = [3, 0x7f, 2, 0];
+        unit.data = &data;
+        let mut entries = unit.entries(11, &abbrev_hash);
+        entries.enable_recovery();
+        let mut read = Vec::new();
+        while let Some(entry) = entries.next().unwrap() {
+            read.push((entry.offset, entry.tag));
+        }
+        assert_eq!(read, vec![(11, DW_TAG_compile_unit), (15, DW_TAG_null)]);
+        assert_eq!(entries.skipped()[0].offset, 12);
+        assert_eq!(entries.skipped()[0].end, 15);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn bounded() {
+        let mut abbrev_hash = AbbrevHash::new();
+        abbrev_hash.insert(Abbrev {
+            code: 1,
+            tag: DW_TAG_compile_unit,
+            children: true,
+            attributes: vec![],
+        });
+        abbrev_hash.insert(Abbrev {
+            code: 2,
+            tag: DW_TAG_namespace,
+            children: false,
+            attributes: vec![],
+        });
+
+        // The entries start at offset 11.
+        let data = [1, 2, 2, 0];
+        let mut unit = UnitCommon { endian: LittleEndian, ..Default::default() };
+        unit.data = &data;
+
+        fn offsets<E: Endian>(entries: &mut DieIterator<E>) -> Vec<usize> {
+            let mut offsets = Vec::new();
+            while let Some(entry) = entries.next().unwrap() {
+                offsets.push(entry.offset);
+            }
+            offsets
+        }
+
+        let mut entries = unit.entries(11, &abbrev_hash).limited(2);
+        assert_eq!(offsets(&mut entries), vec![11, 12]);
+        assert_eq!(entries.offset(), 13);
+
+        let mut entries = unit.entries(11, &abbrev_hash).until(13);
+        assert_eq!(offsets(&mut entries), vec![11, 12]);
+        assert_eq!(entries.offset(), 13);
+
+        let mut entries = unit.entry(11, entries.offset(), &abbrev_hash).unwrap();
+        assert_eq!(offsets(&mut entries), vec![13, 14]);
+    }
+
+    #[test]
+    fn die() {
+        let mut abbrev_hash = AbbrevHash::new();
+        let code = 1;
+        abbrev_hash.insert(Abbrev {
             code: code,
             tag: DW_TAG_namespace,
             children: true,
             attributes: vec![
-                Attribute { at: DW_AT_name, data: AttributeData::String(b"test") },
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
             ],
-        };
+        });
+        let write_val = Die::new(0, code, DW_TAG_namespace, true, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(b"test") },
+        ]);
 
         let mut data = Vec::new();
         let mut unit = UnitCommon { endian: LittleEndian, ..Default::default() };
@@ -761,15 +2666,154 @@ mod test {
         assert_eq!(read_val, write_val);
     }
 
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn has_attr() {
+        let mut die = Die::new(0, 1, DW_TAG_variable, false, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(b"v") },
+            Attribute { at: DW_AT_lo_user, data: AttributeData::Flag(true) },
+        ]);
+        assert!(die.has_attr(DW_AT_name));
+        assert!(!die.has_attr(DW_AT_type));
+        assert!(die.has_attr(DW_AT_lo_user));
+        assert!(!die.has_attr(DwAt(DW_AT_lo_user.0 + 1)));
+        assert_eq!(die.attribute_mask.contains(DW_AT_lo_user), None);
+
+        die.attributes.push(Attribute { at: DW_AT_type, data: AttributeData::Ref(UnitOffset(0x10)) });
+        die.update_attribute_mask();
+        assert!(die.has_attr(DW_AT_type));
+
+        die.set_null(0);
+        assert!(!die.has_attr(DW_AT_name));
+        assert!(!die.has_attr(DW_AT_lo_user));
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn eq() {
+        let die = Die::new(0, 1, DW_TAG_variable, false, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(b"v") },
+        ]);
+        let mut other = Die::new(0, 1, DW_TAG_variable, false, vec![]);
+        other.attributes = die.attributes.clone();
+        other.update_attribute_mask();
+        other.attribute_spans = vec![AttributeSpan { offset: 1, len: 2 }];
+        assert_eq!(die, other);
+
+        other.attributes[0].data = AttributeData::String(b"w");
+        assert_ne!(die, other);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn address_class() {
+        let die = Die::new(0, 1, DW_TAG_pointer_type, false, vec![
+            Attribute { at: DW_AT_address_class, data: AttributeData::Data1(2) },
+        ]);
+        assert_eq!(die.address_class(), Some(DwAddr(2)));
+        let die = Die::new(0, 1, DW_TAG_pointer_type, false, vec![]);
+        assert_eq!(die.address_class(), None);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn patch_attribute() {
+        let mut abbrev_hash = AbbrevHash::new();
+        abbrev_hash.insert(Abbrev {
+            code: 1,
+            tag: DW_TAG_variable,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                AbbrevAttribute::new(DW_AT_external, DW_FORM_flag),
+                AbbrevAttribute::new(DW_AT_type, DW_FORM_ref1),
+                AbbrevAttribute::new(DW_AT_low_pc, DW_FORM_addr),
+                AbbrevAttribute::new(DW_AT_declaration, DW_FORM_flag_present),
+            ],
+        });
+        let die = Die::new(0, 1, DW_TAG_variable, false, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(b"v") },
+            Attribute { at: DW_AT_external, data: AttributeData::Flag(false) },
+            Attribute { at: DW_AT_type, data: AttributeData::Ref(UnitOffset(0x10)) },
+            Attribute { at: DW_AT_low_pc, data: AttributeData::Address(0x1000) },
+            Attribute { at: DW_AT_declaration, data: AttributeData::Flag(true) },
+        ]);
+
+        let mut data = Vec::new();
+        let unit = UnitCommon { endian: LittleEndian, ..Default::default() };
+        die.write(&mut data, &unit, &abbrev_hash).unwrap();
+        let len = data.len();
+
+        die.patch_attribute(&mut data, &unit, &abbrev_hash, DW_AT_external,
+                            &AttributeData::Flag(true)).unwrap();
+        die.patch_attribute(&mut data, &unit, &abbrev_hash, DW_AT_low_pc,
+                            &AttributeData::Address(0x2000)).unwrap();
+        assert!(die.patch_attribute(&mut data, &unit, &abbrev_hash, DW_AT_name,
+                                    &AttributeData::String(b"w")).is_err());
+        assert!(die.patch_attribute(&mut data, &unit, &abbrev_hash, DW_AT_type,
+                                    &AttributeData::Ref(UnitOffset(0x100))).is_err());
+        assert!(die.patch_attribute(&mut data, &unit, &abbrev_hash, DW_AT_byte_size,
+                                    &AttributeData::Data1(1)).is_err());
+        die.patch_attribute(&mut data, &unit, &abbrev_hash, DW_AT_declaration,
+                            &AttributeData::Flag(true)).unwrap();
+        assert!(die.patch_attribute(&mut data, &unit, &abbrev_hash, DW_AT_declaration,
+                                    &AttributeData::Flag(false)).is_err());
+        assert_eq!(data.len(), len);
+
+        let mut read_val = Die::null(0);
+        read_val.read(&mut &data[..], 0, &unit, &abbrev_hash).unwrap();
+        assert_eq!(read_val.attr(DW_AT_external), Some(&AttributeData::Flag(true)));
+        assert_eq!(read_val.attr(DW_AT_type), Some(&AttributeData::Ref(UnitOffset(0x10))));
+        assert_eq!(read_val.attr(DW_AT_low_pc), Some(&AttributeData::Address(0x2000)));
+        assert_eq!(read_val.attr(DW_AT_declaration), Some(&AttributeData::Flag(true)));
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn attribute_spans() {
+        let mut abbrev_hash = AbbrevHash::new();
+        abbrev_hash.insert(Abbrev {
+            code: 1,
+            tag: DW_TAG_variable,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                AbbrevAttribute::new(DW_AT_external, DW_FORM_flag),
+                AbbrevAttribute::new(DW_AT_low_pc, DW_FORM_addr),
+            ],
+        });
+        let die = Die::new(0x10, 1, DW_TAG_variable, false, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(b"v") },
+            Attribute { at: DW_AT_external, data: AttributeData::Flag(true) },
+            Attribute { at: DW_AT_low_pc, data: AttributeData::Address(0x1000) },
+        ]);
+
+        let mut data = Vec::new();
+        let unit = UnitCommon { endian: LittleEndian, ..Default::default() };
+        die.write(&mut data, &unit, &abbrev_hash).unwrap();
+
+        let mut entries = DieIterator::new(&data, 0x10, &unit, &abbrev_hash);
+        assert!(entries.next().unwrap().unwrap().attribute_spans.is_empty());
+
+        let mut entries = DieIterator::new(&data, 0x10, &unit, &abbrev_hash);
+        entries.enable_attribute_spans();
+        let read_val = entries.next().unwrap().unwrap();
+        assert_eq!(read_val.attribute_spans, vec![
+            AttributeSpan { offset: 0x11, len: 2 },
+            AttributeSpan { offset: 0x13, len: 1 },
+            AttributeSpan { offset: 0x14, len: 4 },
+        ]);
+        assert_eq!(read_val.attribute_span(DW_AT_low_pc),
+                   Some(AttributeSpan { offset: 0x14, len: 4 }));
+        assert_eq!(read_val.attribute_span(DW_AT_type), None);
+    }
+
     #[test]
     fn attribute() {
-        let abbrev = AbbrevAttribute {
-            at: DW_AT_sibling,
-            form: DW_FORM_ref4,
-        };
+        let abbrev = AbbrevAttribute::new(DW_AT_sibling, DW_FORM_ref4);
         let write_val = Attribute {
             at: DW_AT_sibling,
-            data: AttributeData::Ref(0x01234567),
+            data: AttributeData::Ref(UnitOffset(0x01234567)),
         };
 
         let mut data = Vec::new();
@@ -785,6 +2829,25 @@ mod test {
         assert_eq!(read_val, write_val);
     }
 
+    #[test]
+    fn attribute_implicit_const() {
+        let abbrev = AbbrevAttribute::implicit_const(DW_AT_decl_file, -2);
+        let unit = UnitCommon { endian: LittleEndian, ..Default::default() };
+
+        let mut r = &[][..];
+        let read_val = Attribute::read(&mut r, &unit, &abbrev).unwrap();
+        assert_eq!(read_val.data, AttributeData::SData(-2));
+
+        let mut data = Vec::new();
+        read_val.write(&mut data, &unit, &abbrev).unwrap();
+        assert!(data.is_empty());
+        let write_val = Attribute {
+            at: DW_AT_decl_file,
+            data: AttributeData::SData(2),
+        };
+        assert!(write_val.write(&mut data, &unit, &abbrev).is_err());
+    }
+
     #[test]
     #[cfg_attr(rustfmt, rustfmt_skip)]
     fn attribute_data() {
@@ -810,15 +2873,31 @@ mod test {
             (AttributeData::Flag(true), DW_FORM_flag_present, &[][..]),
             (AttributeData::String(b"test"), DW_FORM_string, &[b't', b'e', b's', b't', 0][..]),
             (AttributeData::StringOffset(0x01234567), DW_FORM_strp, &[0x67, 0x45, 0x23, 0x01][..]),
-            (AttributeData::Ref(0x01), DW_FORM_ref1, &[0x01][..]),
-            (AttributeData::Ref(0x0123), DW_FORM_ref2, &[0x23, 0x01][..]),
-            (AttributeData::Ref(0x01234567), DW_FORM_ref4, &[0x67, 0x45, 0x23, 0x01][..]),
-            (AttributeData::Ref(0x0123456789abcdef), DW_FORM_ref8, &[0xef, 0xcd, 0xab, 0x89, 0x67, 0x45, 0x23, 0x01][..]),
-            (AttributeData::Ref(0x01234567), DW_FORM_ref_udata, &[231, 138, 141, 9][..]),
-            (AttributeData::RefAddress(0x12345678), DW_FORM_ref_addr, &[0x78, 0x56, 0x34, 0x12][..]),
+            (AttributeData::LineStringOffset(0x01234567), DW_FORM_line_strp, &[0x67, 0x45, 0x23, 0x01][..]),
+            (AttributeData::Ref(UnitOffset(0x01)), DW_FORM_ref1, &[0x01][..]),
+            (AttributeData::Ref(UnitOffset(0x0123)), DW_FORM_ref2, &[0x23, 0x01][..]),
+            (AttributeData::Ref(UnitOffset(0x01234567)), DW_FORM_ref4, &[0x67, 0x45, 0x23, 0x01][..]),
+            (AttributeData::Ref(UnitOffset(0x0123456789abcdef)), DW_FORM_ref8, &[0xef, 0xcd, 0xab, 0x89, 0x67, 0x45, 0x23, 0x01][..]),
+            (AttributeData::Ref(UnitOffset(0x01234567)), DW_FORM_ref_udata, &[231, 138, 141, 9][..]),
+            (AttributeData::RefAddress(DebugInfoOffset(0x12345678)), DW_FORM_ref_addr, &[0x78, 0x56, 0x34, 0x12][..]),
             (AttributeData::RefSig(0x0123456789abcdef), DW_FORM_ref_sig8, &[0xef, 0xcd, 0xab, 0x89, 0x67, 0x45, 0x23, 0x01][..]),
             (AttributeData::SecOffset(0x12345678), DW_FORM_sec_offset, &[0x78, 0x56, 0x34, 0x12][..]),
             (AttributeData::ExprLoc(&[0x11, 0x22, 0x33]), DW_FORM_exprloc, &[0x3, 0x11, 0x22, 0x33][..]),
+            (AttributeData::AddressIndex(0x01234567), DW_FORM_GNU_addr_index, &[231, 138, 141, 9][..]),
+            (AttributeData::StringIndex(0x80), DW_FORM_GNU_str_index, &[0x80, 0x01][..]),
+            (AttributeData::RangeListIndex(0x02), DW_FORM_rnglistx, &[0x02][..]),
+            (AttributeData::LocationListIndex(0x03), DW_FORM_loclistx, &[0x03][..]),
+            (AttributeData::Data16(&[0x11; 16]), DW_FORM_data16, &[0x11; 16][..]),
+            (AttributeData::StringIndex(0x80), DW_FORM_strx, &[0x80, 0x01][..]),
+            (AttributeData::StringIndex(0x01), DW_FORM_strx1, &[0x01][..]),
+            (AttributeData::StringIndex(0x0123), DW_FORM_strx2, &[0x23, 0x01][..]),
+            (AttributeData::StringIndex(0x012345), DW_FORM_strx3, &[0x45, 0x23, 0x01][..]),
+            (AttributeData::StringIndex(0x01234567), DW_FORM_strx4, &[0x67, 0x45, 0x23, 0x01][..]),
+            (AttributeData::AddressIndex(0x80), DW_FORM_addrx, &[0x80, 0x01][..]),
+            (AttributeData::AddressIndex(0x01), DW_FORM_addrx1, &[0x01][..]),
+            (AttributeData::AddressIndex(0x0123), DW_FORM_addrx2, &[0x23, 0x01][..]),
+            (AttributeData::AddressIndex(0x012345), DW_FORM_addrx3, &[0x45, 0x23, 0x01][..]),
+            (AttributeData::AddressIndex(0x01234567), DW_FORM_addrx4, &[0x67, 0x45, 0x23, 0x01][..]),
         ] {
             attribute_data_inner(&mut unit, write_val, form, expect);
         }
@@ -837,7 +2916,9 @@ mod test {
         for &(ref write_val, form, expect) in &[
             (AttributeData::StringOffset(0x0123456789), DW_FORM_strp,
                 &[0x89, 0x67, 0x45, 0x23, 0x01, 0x00, 0x00, 0x00][..]),
-            (AttributeData::RefAddress(0x0123456789), DW_FORM_ref_addr,
+            (AttributeData::LineStringOffset(0x0123456789), DW_FORM_line_strp,
+                &[0x89, 0x67, 0x45, 0x23, 0x01, 0x00, 0x00, 0x00][..]),
+            (AttributeData::RefAddress(DebugInfoOffset(0x0123456789)), DW_FORM_ref_addr,
                 &[0x89, 0x67, 0x45, 0x23, 0x01, 0x00, 0x00, 0x00][..]),
             (AttributeData::SecOffset(0x0123456789), DW_FORM_sec_offset,
                 &[0x89, 0x67, 0x45, 0x23, 0x01, 0x00, 0x00, 0x00][..]),
@@ -849,11 +2930,21 @@ mod test {
         unit.address_size = 8;
         unit.offset_size = 4;
         for &(ref write_val, form, expect) in &[
-            (AttributeData::RefAddress(0x0123456789), DW_FORM_ref_addr,
+            (AttributeData::RefAddress(DebugInfoOffset(0x0123456789)), DW_FORM_ref_addr,
                 &[0x89, 0x67, 0x45, 0x23, 0x01, 0x00, 0x00, 0x00][..]),
         ] {
             attribute_data_inner(&mut unit, write_val, form, expect);
         }
+
+        // Values that don't fit the form.
+        for &(ref write_val, form) in &[
+            (AttributeData::Ref(UnitOffset(0x100)), DW_FORM_ref1),
+            (AttributeData::Ref(UnitOffset(0x10000)), DW_FORM_ref2),
+            (AttributeData::Ref(UnitOffset(0x100000000)), DW_FORM_ref4),
+            (AttributeData::StringIndex(0x100), DW_FORM_strx1),
+        ] {
+            assert!(write_val.write(&mut Vec::new(), &unit, form, false).is_err());
+        }
     }
 
     fn attribute_data_inner<'data, 'b, E: Endian>(
@@ -872,8 +2963,9 @@ mod test {
             let read_val = AttributeData::read(&mut r, unit, read_form).unwrap();
 
             if indirect {
-                assert_eq!(buf[0] as u16, form.0);
-                assert_eq!(&buf[1..], expect);
+                let mut r = buf;
+                assert_eq!(leb128::read_u16(&mut r).unwrap(), form.0);
+                assert_eq!(r, expect);
             } else {
                 assert_eq!(&buf[..], expect);
             }
@@ -881,4 +2973,53 @@ mod test {
             assert_eq!(read_val, *write_val);
         }
     }
+
+    #[test]
+    fn as_string_strict() {
+        let context = AttributeContext {
+            offset_size: 4,
+            debug_str: b"\0main\0int",
+            debug_line_str: &[],
+        };
+        let data = AttributeData::String(b"name");
+        assert_eq!(data.as_string_strict_in(&context).unwrap(), Some(&b"name"[..]));
+        let data = AttributeData::StringOffset(1);
+        assert_eq!(data.as_string_strict_in(&context).unwrap(), Some(&b"main"[..]));
+        assert_eq!(data.as_string_in(&context), Some(&b"main"[..]));
+        let data = AttributeData::StringOffset(6);
+        assert!(data.as_string_strict_in(&context).is_err());
+        assert_eq!(data.as_string_in(&context), None);
+        let data = AttributeData::StringOffset(20);
+        assert!(data.as_string_strict_in(&context).is_err());
+        assert_eq!(data.as_string_in(&context), None);
+        let data = AttributeData::Data1(1);
+        assert_eq!(data.as_string_strict_in(&context).unwrap(), None);
+    }
+
+    #[test]
+    fn attribute_context() {
+        let mut context = AttributeContext {
+            offset_size: 4,
+            debug_str: b"\0main\0",
+            debug_line_str: b"/src\0",
+        };
+        let data = AttributeData::StringOffset(1);
+        assert_eq!(data.as_string_in(&context), Some(&b"main"[..]));
+        let data = AttributeData::LineStringOffset(0);
+        assert_eq!(data.as_string_in(&context), Some(&b"/src"[..]));
+        let data = AttributeData::LineStringOffset(5);
+        assert!(data.as_string_strict_in(&context).is_err());
+
+        let data4 = AttributeData::Data4(0x10);
+        let data8 = AttributeData::Data8(0x20);
+        let sec_offset = AttributeData::SecOffset(0x30);
+        assert_eq!(data4.as_offset_in(&context), Some(0x10));
+        assert_eq!(data8.as_offset_in(&context), None);
+        assert_eq!(sec_offset.as_offset_in(&context), Some(0x30));
+        context.offset_size = 8;
+        assert_eq!(data4.as_offset_in(&context), None);
+        assert_eq!(data8.as_offset_in(&context), Some(0x20));
+        assert_eq!(sec_offset.as_offset_in(&context), Some(0x30));
+        assert_eq!(AttributeData::UData(0x10).as_offset_in(&context), None);
+    }
 }
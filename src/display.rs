@@ -2,20 +2,100 @@ use std;
 use std::fmt;
 
 use super::*;
+use abbrev::*;
 use die::*;
+use expr::*;
 use line::*;
+use unit::{CompilationUnit, TypeUnit, UnitCommon};
 
 pub trait Formatter {
     fn indent(&mut self);
     fn unindent(&mut self);
     fn write_fmt(&mut self, fmt: std::fmt::Arguments) -> Result<(), std::io::Error>;
     fn write_sep(&mut self) -> Result<(), std::io::Error>;
+
+    // How to display strings and paths that aren't valid UTF-8.
+    fn string_policy(&self) -> StringPolicy {
+        StringPolicy::default()
+    }
+}
+
+// How to display strings from the debug info, which may not be UTF-8.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StringPolicy {
+    // Display the length of strings that aren't valid UTF-8.
+    #[default]
+    Length,
+    // Replace invalid sequences with U+FFFD.
+    Lossy,
+    // Display the bytes of invalid sequences as `\xHH`. Backslashes are
+    // also escaped, so that the original bytes can be recovered.
+    Escape,
+}
+
+// Displays a string using a `StringPolicy`.
+#[derive(Debug, Clone, Copy)]
+pub struct StringDisplay<'data> {
+    data: &'data [u8],
+    policy: StringPolicy,
+}
+
+impl<'data> StringDisplay<'data> {
+    pub fn new(data: &'data [u8], policy: StringPolicy) -> Self {
+        StringDisplay {
+            data: data,
+            policy: policy,
+        }
+    }
+}
+
+impl<'data> fmt::Display for StringDisplay<'data> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.policy {
+            StringPolicy::Length => {
+                match std::str::from_utf8(self.data) {
+                    Ok(val) => write!(f, "{}", val),
+                    Err(_) => write!(f, "len {}", self.data.len()),
+                }
+            }
+            StringPolicy::Lossy => write!(f, "{}", String::from_utf8_lossy(self.data)),
+            StringPolicy::Escape => {
+                let mut data = self.data;
+                loop {
+                    let (valid, invalid) = match std::str::from_utf8(data) {
+                        Ok(val) => (val, 0),
+                        Err(e) => {
+                            let valid = e.valid_up_to();
+                            let invalid = e.error_len().unwrap_or(data.len() - valid);
+                            // The prefix is known to be valid.
+                            (std::str::from_utf8(&data[..valid]).unwrap(), invalid)
+                        }
+                    };
+                    for c in valid.chars() {
+                        match c {
+                            '\\' => try!(write!(f, "\\\\")),
+                            _ => try!(write!(f, "{}", c)),
+                        }
+                    }
+                    data = &data[valid.len()..];
+                    if invalid == 0 {
+                        return Ok(());
+                    }
+                    for byte in &data[..invalid] {
+                        try!(write!(f, "\\x{:02x}", byte));
+                    }
+                    data = &data[invalid..];
+                }
+            }
+        }
+    }
 }
 
 pub struct DefaultFormatter<'a> {
     w: &'a mut std::io::Write,
     indent: usize,
     current_indent: usize,
+    string_policy: StringPolicy,
 }
 
 impl<'a> DefaultFormatter<'a> {
@@ -24,8 +104,13 @@ impl<'a> DefaultFormatter<'a> {
             w: w,
             indent: indent,
             current_indent: 0,
+            string_policy: StringPolicy::default(),
         }
     }
+
+    pub fn set_string_policy(&mut self, policy: StringPolicy) {
+        self.string_policy = policy;
+    }
 }
 
 impl<'a> Formatter for DefaultFormatter<'a> {
@@ -51,15 +136,161 @@ impl<'a> Formatter for DefaultFormatter<'a> {
         try!(write!(self.w, "\n"));
         Ok(())
     }
+
+    fn string_policy(&self) -> StringPolicy {
+        self.string_policy
+    }
+}
+
+// Displays the header in the layout of readelf's banner for each unit.
+impl<'data, E: Endian> fmt::Display for CompilationUnit<'data, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "  Compilation Unit @ offset {}:\n", AltHex(self.common.offset as u64)));
+        display_unit_header(f, &self.common, self.total_len())
+    }
+}
+
+impl<'data, E: Endian> fmt::Display for TypeUnit<'data, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "  Type Unit @ offset {}:\n", AltHex(self.common.offset as u64)));
+        try!(display_unit_header(f, &self.common, self.total_len()));
+        try!(write!(f, "   Signature:     0x{:016x}\n", self.type_signature));
+        writeln!(f, "   Type Offset:   0x{:x}", self.type_offset)
+    }
+}
+
+fn display_unit_header<E: Endian>(
+    f: &mut fmt::Formatter,
+    unit: &UnitCommon<E>,
+    total_len: usize
+) -> fmt::Result {
+    // The length doesn't include the initial length field.
+    let (initial_len, format) = match unit.offset_size {
+        8 => (12, "64-bit"),
+        _ => (4, "32-bit"),
+    };
+    try!(write!(f, "   Length:        0x{:x} ({})\n", total_len - initial_len, format));
+    try!(write!(f, "   Version:       {}\n", unit.version));
+    if unit.version >= 5 {
+        try!(write!(f, "   Unit Type:     {} ({})\n", FullName(unit.unit_type), unit.unit_type.0));
+    }
+    try!(write!(f, "   Abbrev Offset: {}\n", AltHex(unit.abbrev_offset)));
+    try!(write!(f, "   Pointer Size:  {}\n", unit.address_size));
+    if unit.version >= 5 {
+        match unit.unit_type {
+            constant::DW_UT_skeleton | constant::DW_UT_split_compile => {
+                try!(write!(f, "   DWO ID:        0x{:016x}\n", unit.unit_id));
+            }
+            constant::DW_UT_type | constant::DW_UT_split_type => {
+                try!(write!(f, "   Signature:     0x{:016x}\n", unit.unit_id));
+                try!(write!(f, "   Type Offset:   0x{:x}\n", unit.type_offset));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
 }
 
+// Display all of the declarations in .debug_abbrev, in the layout of
+// `readelf --debug-dump=abbrev`.
+pub fn display_debug_abbrev<F: Formatter>(f: &mut F, debug_abbrev: &[u8]) -> Result<(), ReadError> {
+    try!(write!(f, "Contents of the .debug_abbrev section:\n\n"));
+    let mut decls = AbbrevDeclIterator::new(debug_abbrev, 0);
+    let mut table_offset = None;
+    while let Some(decl) = try!(decls.next()) {
+        if table_offset != Some(decl.table_offset) {
+            table_offset = Some(decl.table_offset);
+            try!(display_abbrev_table_header(f, decl.table_offset));
+        }
+        try!(decl.abbrev.display(f));
+    }
+    Ok(())
+}
+
+fn display_abbrev_table_header<F: Formatter>(f: &mut F, offset: usize) -> Result<(), std::io::Error> {
+    writeln!(f, "  Number TAG ({})", AltHex(offset as u64))
+}
+
+// Displays a value in hex in the same way as the `%#x` format of readelf,
+// which has no `0x` for 0.
+struct AltHex(u64);
+
+impl fmt::Display for AltHex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0 == 0 {
+            write!(f, "0")
+        } else {
+            write!(f, "0x{:x}", self.0)
+        }
+    }
+}
+
+impl Abbrev {
+    // Display the declaration in the layout of `readelf --debug-dump=abbrev`.
+    pub fn display<F: Formatter>(&self, f: &mut F) -> Result<(), std::io::Error> {
+        let children = if self.children { "has children" } else { "no children" };
+        try!(write!(f, "   {}      {}    [{}]\n", self.code, FullName(self.tag), children));
+        for attribute in &self.attributes {
+            try!(write!(f, "    {:<18} {}\n", FullName(attribute.at), FullName(attribute.form)));
+        }
+        writeln!(f, "    DW_AT value: 0     DW_FORM value: 0")
+    }
+}
+
+impl AbbrevVec {
+    // Display the table, which is at `offset` in .debug_abbrev.
+    pub fn display<F: Formatter>(&self, f: &mut F, offset: usize) -> Result<(), std::io::Error> {
+        try!(display_abbrev_table_header(f, offset));
+        for abbrev in self.iter() {
+            try!(abbrev.display(f));
+        }
+        Ok(())
+    }
+}
+
+impl AbbrevHash {
+    // Display the table, which is at `offset` in .debug_abbrev.
+    //
+    // The declarations are sorted by code, since a hash has no order.
+    pub fn display<F: Formatter>(&self, f: &mut F, offset: usize) -> Result<(), std::io::Error> {
+        let mut abbrevs: Vec<&Abbrev> = self.iter().map(|(_, abbrev)| abbrev).collect();
+        abbrevs.sort_by_key(|abbrev| abbrev.code);
+        try!(display_abbrev_table_header(f, offset));
+        for abbrev in abbrevs {
+            try!(abbrev.display(f));
+        }
+        Ok(())
+    }
+}
+
+// Displays a constant with its `DW_XXX_` prefix, as readelf does.
+struct FullName<T>(T);
+
+macro_rules! full_name {
+    ($($ty:ident),*) => {
+        $(
+            impl fmt::Display for FullName<constant::$ty> {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    match self.0.name() {
+                        Some(name) => f.pad(name),
+                        None => f.pad(&self.0.to_string()),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+full_name!(DwTag, DwAt, DwForm, DwUt);
+
 impl<'a, 'data, E: Endian> DieIterator<'a, 'data, E> {
     pub fn display<F: Formatter>(&mut self, f: &mut F) -> Result<(), ReadError> {
+        let unit = self.unit();
         while let Some(die) = try!(self.next()) {
             if die.is_null() {
                 f.unindent();
             } else {
-                try!(die.display(f));
+                try!(die.display_unit(f, unit));
                 try!(f.write_sep());
                 if die.children {
                     f.indent();
@@ -74,6 +305,7 @@ impl<'a, 'data, E: Endian> DieIterator<'a, 'data, E> {
         f: &mut F,
         max_depth: usize
     ) -> Result<(), ReadError> {
+        let unit = self.unit();
         let mut depth = 1;
         let mut next_sibling = false;
         loop {
@@ -93,7 +325,7 @@ impl<'a, 'data, E: Endian> DieIterator<'a, 'data, E> {
                     f.unindent();
                 }
             } else {
-                try!(die.display(f));
+                try!(die.display_unit(f, unit));
                 try!(f.write_sep());
                 next_sibling = false;
                 if depth < max_depth && die.children {
@@ -121,6 +353,116 @@ impl<'data> Die<'data> {
         }
         Ok(())
     }
+
+    // Display the entry, using the unit to decode attribute values that
+    // depend on it, such as expressions.
+    pub fn display_unit<F, E>(&self, f: &mut F, unit: &UnitCommon<E>) -> Result<(), std::io::Error>
+        where F: Formatter,
+              E: Endian
+    {
+        let policy = f.string_policy();
+        try!(write!(f, "{}\n", self.tag));
+        try!(write!(f, "offset: {:x}\n", self.offset));
+        for attribute in &self.attributes {
+            match attribute.data {
+                AttributeData::String(val) => {
+                    try!(write!(f,
+                                "{}: (string) {}\n",
+                                attribute.at,
+                                StringDisplay::new(val, policy)));
+                }
+                AttributeData::ExprLoc(val) => {
+                    try!(write!(f,
+                                "{}: (expr_loc) {}\n",
+                                attribute.at,
+                                ExpressionDisplay::new(val, unit)));
+                }
+                _ => try!(write!(f, "{}\n", attribute)),
+            }
+        }
+        Ok(())
+    }
+}
+
+// Displays the operations of an expression, such as "DW_OP_fbreg -24".
+//
+// Operations are separated by "; ". If the expression can't be decoded,
+// then its bytes are displayed in hex instead.
+pub struct ExpressionDisplay<'a, 'data, E>
+    where 'data: 'a,
+          E: Endian + 'a
+{
+    data: &'data [u8],
+    unit: &'a UnitCommon<'a, E>,
+}
+
+impl<'a, 'data, E: Endian> ExpressionDisplay<'a, 'data, E> {
+    pub fn new(data: &'data [u8], unit: &'a UnitCommon<'a, E>) -> Self {
+        ExpressionDisplay {
+            data: data,
+            unit: unit,
+        }
+    }
+}
+
+impl<'a, 'data, E: Endian> fmt::Display for ExpressionDisplay<'a, 'data, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut ops = Vec::new();
+        let mut r = self.data;
+        while !r.is_empty() {
+            let offset = self.data.len() - r.len();
+            match Operation::read(&mut r, offset, self.unit) {
+                Ok(op) => ops.push(op),
+                Err(_) => {
+                    try!(write!(f, "["));
+                    try!(display_bytes(f, self.data));
+                    return write!(f, "]");
+                }
+            }
+        }
+        for (i, op) in ops.iter().enumerate() {
+            if i != 0 {
+                try!(write!(f, "; "));
+            }
+            try!(write!(f, "{}", op));
+        }
+        Ok(())
+    }
+}
+
+impl<'data> fmt::Display for Operation<'data> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.opcode.name() {
+            Some(name) => try!(write!(f, "{}", name)),
+            None => try!(write!(f, "{}", self.opcode)),
+        }
+        match self.operands {
+            Operands::None => Ok(()),
+            Operands::Address(val) => write!(f, " 0x{:x}", val),
+            Operands::Unsigned(val) => write!(f, " {}", val),
+            Operands::Signed(val) => write!(f, " {}", val),
+            Operands::UnsignedPair(first, second) => write!(f, " {} {}", first, second),
+            Operands::UnsignedSigned(first, second) => write!(f, " {} {}", first, second),
+            Operands::Block(val) => {
+                try!(write!(f, " "));
+                display_bytes(f, val)
+            }
+            Operands::UnsignedBlock(first, val) => {
+                try!(write!(f, " {} ", first));
+                display_bytes(f, val)
+            }
+        }
+    }
+}
+
+fn display_bytes(f: &mut fmt::Formatter, bytes: &[u8]) -> fmt::Result {
+    for (i, byte) in bytes.iter().enumerate() {
+        if i != 0 {
+            try!(write!(f, " "));
+        }
+        try!(write!(f, "{:02x}", byte));
+    }
+    Ok(())
 }
 
 impl<'data> fmt::Display for Attribute<'data> {
@@ -140,191 +482,90 @@ impl<'data> fmt::Display for AttributeData<'data> {
             AttributeData::Data2(val) => write!(f, "(data2) {:x}", val),
             AttributeData::Data4(val) => write!(f, "(data4) {:x}", val),
             AttributeData::Data8(val) => write!(f, "(data8) {:x}", val),
+            AttributeData::Data16(val) => {
+                try!(write!(f, "(data16) "));
+                for byte in val {
+                    try!(write!(f, "{:02x}", byte));
+                }
+                Ok(())
+            }
             AttributeData::UData(val) => write!(f, "(udata) {:x}", val),
             AttributeData::SData(val) => write!(f, "(sdata) {:x}", val),
             AttributeData::Flag(val) => write!(f, "(flag) {}", val),
             AttributeData::String(val) => {
-                match std::str::from_utf8(val) {
-                    Ok(val) => write!(f, "(string) {}", val),
-                    Err(_) => write!(f, "(string) len {}", val.len()),
-                }
+                write!(f, "(string) {}", StringDisplay::new(val, StringPolicy::Length))
             }
             // TODO: display the string too
             AttributeData::StringOffset(val) => write!(f, "(strp) {}", val),
+            AttributeData::LineStringOffset(val) => write!(f, "(line_strp) {}", val),
             AttributeData::Ref(val) => write!(f, "(ref) {}", val),
             AttributeData::RefAddress(val) => write!(f, "(ref_address) {}", val),
             AttributeData::RefSig(val) => write!(f, "(ref_sig) {:x}", val),
             AttributeData::SecOffset(val) => write!(f, "(sec_offset) {:x}", val),
             AttributeData::ExprLoc(val) => write!(f, "(expr_loc) len {}", val.len()),
+            AttributeData::AddressIndex(val) => write!(f, "(addr_index) {:x}", val),
+            AttributeData::StringIndex(val) => write!(f, "(str_index) {:x}", val),
+            AttributeData::RangeListIndex(val) => write!(f, "(rnglistx) {:x}", val),
+            AttributeData::LocationListIndex(val) => write!(f, "(loclistx) {:x}", val),
         }
     }
 }
 
-impl fmt::Display for constant::DwTag {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            constant::DW_TAG_array_type => write!(f, "array_type"),
-            constant::DW_TAG_class_type => write!(f, "class_type"),
-            constant::DW_TAG_entry_point => write!(f, "entry_point"),
-            constant::DW_TAG_enumeration_type => write!(f, "enumeration_type"),
-            constant::DW_TAG_formal_parameter => write!(f, "formal_parameter"),
-            constant::DW_TAG_imported_declaration => write!(f, "imported_declaration"),
-            constant::DW_TAG_label => write!(f, "label"),
-            constant::DW_TAG_lexical_block => write!(f, "lexical_block"),
-            constant::DW_TAG_member => write!(f, "member"),
-            constant::DW_TAG_pointer_type => write!(f, "pointer_type"),
-            constant::DW_TAG_reference_type => write!(f, "reference_type"),
-            constant::DW_TAG_compile_unit => write!(f, "compile_unit"),
-            constant::DW_TAG_string_type => write!(f, "string_type"),
-            constant::DW_TAG_structure_type => write!(f, "structure_type"),
-            constant::DW_TAG_subroutine_type => write!(f, "subroutine_type"),
-            constant::DW_TAG_typedef => write!(f, "typedef"),
-            constant::DW_TAG_union_type => write!(f, "union_type"),
-            constant::DW_TAG_unspecified_parameters => write!(f, "unspecified_parameters"),
-            constant::DW_TAG_variant => write!(f, "variant"),
-            constant::DW_TAG_common_block => write!(f, "common_block"),
-            constant::DW_TAG_common_inclusion => write!(f, "common_inclusion"),
-            constant::DW_TAG_inheritance => write!(f, "inheritance"),
-            constant::DW_TAG_inlined_subroutine => write!(f, "inlined_subroutine"),
-            constant::DW_TAG_module => write!(f, "module"),
-            constant::DW_TAG_ptr_to_member_type => write!(f, "ptr_to_member_type"),
-            constant::DW_TAG_set_type => write!(f, "set_type"),
-            constant::DW_TAG_subrange_type => write!(f, "subrange_type"),
-            constant::DW_TAG_with_stmt => write!(f, "with_stmt"),
-            constant::DW_TAG_access_declaration => write!(f, "access_declaration"),
-            constant::DW_TAG_base_type => write!(f, "base_type"),
-            constant::DW_TAG_catch_block => write!(f, "catch_block"),
-            constant::DW_TAG_const_type => write!(f, "const_type"),
-            constant::DW_TAG_constant => write!(f, "constant"),
-            constant::DW_TAG_enumerator => write!(f, "enumerator"),
-            constant::DW_TAG_file_type => write!(f, "file_type"),
-            constant::DW_TAG_friend => write!(f, "friend"),
-            constant::DW_TAG_namelist => write!(f, "namelist"),
-            constant::DW_TAG_namelist_item => write!(f, "namelist_item"),
-            constant::DW_TAG_packed_type => write!(f, "packed_type"),
-            constant::DW_TAG_subprogram => write!(f, "subprogram"),
-            constant::DW_TAG_template_type_parameter => write!(f, "template_type_parameter"),
-            constant::DW_TAG_template_value_parameter => write!(f, "template_value_parameter"),
-            constant::DW_TAG_thrown_type => write!(f, "thrown_type"),
-            constant::DW_TAG_try_block => write!(f, "try_block"),
-            constant::DW_TAG_variant_part => write!(f, "variant_part"),
-            constant::DW_TAG_variable => write!(f, "variable"),
-            constant::DW_TAG_volatile_type => write!(f, "volatile_type"),
-            constant::DW_TAG_dwarf_procedure => write!(f, "dwarf_procedure"),
-            constant::DW_TAG_restrict_type => write!(f, "restrict_type"),
-            constant::DW_TAG_interface_type => write!(f, "interface_type"),
-            constant::DW_TAG_namespace => write!(f, "namespace"),
-            constant::DW_TAG_imported_module => write!(f, "imported_module"),
-            constant::DW_TAG_unspecified_type => write!(f, "unspecified_type"),
-            constant::DW_TAG_partial_unit => write!(f, "partial_unit"),
-            constant::DW_TAG_imported_unit => write!(f, "imported_unit"),
-            constant::DW_TAG_condition => write!(f, "condition"),
-            constant::DW_TAG_shared_type => write!(f, "shared_type"),
-            constant::DW_TAG_type_unit => write!(f, "type_unit"),
-            constant::DW_TAG_rvalue_reference_type => write!(f, "rvalue_reference_type"),
-            constant::DW_TAG_template_alias => write!(f, "template_alias"),
-            _ => write!(f, "tag({})", self.0),
+impl<'data, E: Endian> LineProgram<'data, E> {
+    // Display the header in the layout of `readelf --debug-dump=rawline`.
+    //
+    // The lengths aren't displayed, since they aren't kept after reading.
+    // Entry 0 of the directory and file tables is the compilation unit's
+    // directory and file, which aren't encoded in the header before
+    // version 5, so they are only displayed for version 5.
+    pub fn display<F: Formatter>(&self, f: &mut F) -> Result<(), std::io::Error> {
+        let policy = f.string_policy();
+        try!(write!(f, "  Offset:                      0x{:x}\n", self.offset));
+        try!(write!(f, "  DWARF Version:               {}\n", self.version));
+        if self.version >= 5 {
+            try!(write!(f, "  Address size (bytes):        {}\n", self.address_size));
         }
-    }
-}
+        try!(write!(f, "  Minimum Instruction Length:  {}\n", self.address_step));
+        if self.version >= 4 {
+            try!(write!(f, "  Maximum Ops per Instruction: {}\n", self.operation_range));
+        }
+        try!(write!(f, "  Initial value of 'is_stmt':  {}\n", self.default_statement as u8));
+        try!(write!(f, "  Line Base:                   {}\n", self.line_base));
+        try!(write!(f, "  Line Range:                  {}\n", self.line_range));
+        try!(write!(f, "  Opcode Base:                 {}\n", self.opcode_base));
 
-impl fmt::Display for constant::DwAt {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            constant::DW_AT_sibling => write!(f, "sibling"),
-            constant::DW_AT_location => write!(f, "location"),
-            constant::DW_AT_name => write!(f, "name"),
-            constant::DW_AT_ordering => write!(f, "ordering"),
-            constant::DW_AT_byte_size => write!(f, "byte_size"),
-            constant::DW_AT_bit_offset => write!(f, "bit_offset"),
-            constant::DW_AT_bit_size => write!(f, "bit_size"),
-            constant::DW_AT_stmt_list => write!(f, "stmt_list"),
-            constant::DW_AT_low_pc => write!(f, "low_pc"),
-            constant::DW_AT_high_pc => write!(f, "high_pc"),
-            constant::DW_AT_language => write!(f, "language"),
-            constant::DW_AT_discr => write!(f, "discr"),
-            constant::DW_AT_discr_value => write!(f, "discr_value"),
-            constant::DW_AT_visibility => write!(f, "visibility"),
-            constant::DW_AT_import => write!(f, "import"),
-            constant::DW_AT_string_length => write!(f, "string_length"),
-            constant::DW_AT_common_reference => write!(f, "common_reference"),
-            constant::DW_AT_comp_dir => write!(f, "comp_dir"),
-            constant::DW_AT_const_value => write!(f, "const_value"),
-            constant::DW_AT_containing_type => write!(f, "containing_type"),
-            constant::DW_AT_default_value => write!(f, "default_value"),
-            constant::DW_AT_inline => write!(f, "inline"),
-            constant::DW_AT_is_optional => write!(f, "is_optional"),
-            constant::DW_AT_lower_bound => write!(f, "lower_bound"),
-            constant::DW_AT_producer => write!(f, "producer"),
-            constant::DW_AT_prototyped => write!(f, "prototyped"),
-            constant::DW_AT_return_addr => write!(f, "return_addr"),
-            constant::DW_AT_start_scope => write!(f, "start_scope"),
-            constant::DW_AT_bit_stride => write!(f, "bit_stride"),
-            constant::DW_AT_upper_bound => write!(f, "upper_bound"),
-            constant::DW_AT_abstract_origin => write!(f, "abstract_origin"),
-            constant::DW_AT_accessibility => write!(f, "accessibility"),
-            constant::DW_AT_address_class => write!(f, "address_class"),
-            constant::DW_AT_artificial => write!(f, "artificial"),
-            constant::DW_AT_base_types => write!(f, "base_types"),
-            constant::DW_AT_calling_convention => write!(f, "calling_convention"),
-            constant::DW_AT_count => write!(f, "count"),
-            constant::DW_AT_data_member_location => write!(f, "data_member_location"),
-            constant::DW_AT_decl_column => write!(f, "decl_column"),
-            constant::DW_AT_decl_file => write!(f, "decl_file"),
-            constant::DW_AT_decl_line => write!(f, "decl_line"),
-            constant::DW_AT_declaration => write!(f, "declaration"),
-            constant::DW_AT_discr_list => write!(f, "discr_list"),
-            constant::DW_AT_encoding => write!(f, "encoding"),
-            constant::DW_AT_external => write!(f, "external"),
-            constant::DW_AT_frame_base => write!(f, "frame_base"),
-            constant::DW_AT_friend => write!(f, "friend"),
-            constant::DW_AT_identifier_case => write!(f, "identifier_case"),
-            constant::DW_AT_macro_info => write!(f, "macro_info"),
-            constant::DW_AT_namelist_item => write!(f, "namelist_item"),
-            constant::DW_AT_priority => write!(f, "priority"),
-            constant::DW_AT_segment => write!(f, "segment"),
-            constant::DW_AT_specification => write!(f, "specification"),
-            constant::DW_AT_static_link => write!(f, "static_link"),
-            constant::DW_AT_type => write!(f, "type"),
-            constant::DW_AT_use_location => write!(f, "use_location"),
-            constant::DW_AT_variable_parameter => write!(f, "variable_parameter"),
-            constant::DW_AT_virtuality => write!(f, "virtuality"),
-            constant::DW_AT_vtable_elem_location => write!(f, "vtable_elem_location"),
-            constant::DW_AT_allocated => write!(f, "allocated"),
-            constant::DW_AT_associated => write!(f, "associated"),
-            constant::DW_AT_data_location => write!(f, "data_location"),
-            constant::DW_AT_byte_stride => write!(f, "byte_stride"),
-            constant::DW_AT_entry_pc => write!(f, "entry_pc"),
-            constant::DW_AT_use_UTF8 => write!(f, "use_UTF8"),
-            constant::DW_AT_extension => write!(f, "extension"),
-            constant::DW_AT_ranges => write!(f, "ranges"),
-            constant::DW_AT_trampoline => write!(f, "trampoline"),
-            constant::DW_AT_call_column => write!(f, "call_column"),
-            constant::DW_AT_call_file => write!(f, "call_file"),
-            constant::DW_AT_call_line => write!(f, "call_line"),
-            constant::DW_AT_description => write!(f, "description"),
-            constant::DW_AT_binary_scale => write!(f, "binary_scale"),
-            constant::DW_AT_decimal_scale => write!(f, "decimal_scale"),
-            constant::DW_AT_small => write!(f, "small"),
-            constant::DW_AT_decimal_sign => write!(f, "decimal_sign"),
-            constant::DW_AT_digit_count => write!(f, "digit_count"),
-            constant::DW_AT_picture_string => write!(f, "picture_string"),
-            constant::DW_AT_mutable => write!(f, "mutable"),
-            constant::DW_AT_threads_scaled => write!(f, "threads_scaled"),
-            constant::DW_AT_explicit => write!(f, "explicit"),
-            constant::DW_AT_object_pointer => write!(f, "object_pointer"),
-            constant::DW_AT_endianity => write!(f, "endianity"),
-            constant::DW_AT_elemental => write!(f, "elemental"),
-            constant::DW_AT_pure => write!(f, "pure"),
-            constant::DW_AT_recursive => write!(f, "recursive"),
-            constant::DW_AT_signature => write!(f, "signature"),
-            constant::DW_AT_main_subprogram => write!(f, "main_subprogram"),
-            constant::DW_AT_data_bit_offset => write!(f, "data_bit_offset"),
-            constant::DW_AT_const_expr => write!(f, "const_expr"),
-            constant::DW_AT_enum_class => write!(f, "enum_class"),
-            constant::DW_AT_linkage_name => write!(f, "linkage_name"),
-            _ => write!(f, "attr({})", self.0),
+        try!(write!(f, "\n Opcodes:\n"));
+        for (i, len) in self.standard_opcode_lengths.iter().enumerate() {
+            let args = if *len == 1 { "arg" } else { "args" };
+            try!(write!(f, "  Opcode {} has {} {}\n", i + 1, len, args));
         }
+
+        let first = if self.version >= 5 { 0 } else { 1 };
+        if self.include_directories.len() <= first {
+            try!(write!(f, "\n The Directory Table is empty.\n"));
+        } else {
+            try!(write!(f, "\n The Directory Table:\n"));
+            for (i, dir) in self.include_directories.iter().enumerate().skip(first) {
+                try!(write!(f, "  {}\t{}\n", i, StringDisplay::new(dir, policy)));
+            }
+        }
+
+        if self.files.len() <= first {
+            try!(write!(f, "\n The File Name Table is empty.\n"));
+        } else {
+            try!(write!(f, "\n The File Name Table:\n"));
+            try!(write!(f, "  Entry\tDir\tTime\tSize\tName\n"));
+            for (i, file) in self.files.iter().enumerate().skip(first) {
+                try!(write!(f,
+                            "  {}\t{}\t{}\t{}\t{}\n",
+                            i,
+                            file.directory,
+                            file.timestamp,
+                            file.length,
+                            StringDisplay::new(file.path, policy)));
+            }
+        }
+        Ok(())
     }
 }
 
@@ -358,13 +599,205 @@ impl Line {
         }
         match files.get(self.file as usize) {
             Some(file) => {
-                match std::str::from_utf8(file.path) {
-                    Ok(val) => try!(write!(f, " uri: {}", val)),
-                    Err(_) => try!(write!(f, " uri: len {}", file.path.len())),
-                }
+                let policy = f.string_policy();
+                try!(write!(f, " uri: {}", StringDisplay::new(file.path, policy)));
             }
             _ => try!(write!(f, " uri: index {}", self.file)),
         }
         writeln!(f, "")
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use constant::*;
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn expression() {
+        let unit = UnitCommon { endian: LittleEndian, ..Default::default() };
+        let data = [DW_OP_fbreg.0, 0x68];
+        assert_eq!(ExpressionDisplay::new(&data, &unit).to_string(), "DW_OP_fbreg -24");
+        let data = [
+            DW_OP_addr.0, 0x00, 0x10, 0x00, 0x00,
+            DW_OP_bit_piece.0, 8, 16,
+            DW_OP_implicit_value.0, 2, 0xab, 0xcd,
+            DW_OP_stack_value.0,
+        ];
+        assert_eq!(ExpressionDisplay::new(&data, &unit).to_string(),
+                   "DW_OP_addr 0x1000; DW_OP_bit_piece 8 16; DW_OP_implicit_value ab cd; \
+                    DW_OP_stack_value");
+
+        // Undecodable expressions are displayed as bytes.
+        let data = [DW_OP_lit1.0, DW_OP_lo_user.0, 0x01];
+        assert_eq!(ExpressionDisplay::new(&data, &unit).to_string(), "[31 e0 01]");
+
+        let die = Die::new(0, 1, DW_TAG_variable, false, vec![
+            Attribute { at: DW_AT_location, data: AttributeData::ExprLoc(&[DW_OP_fbreg.0, 0x68]) },
+        ]);
+        let mut buf = Vec::new();
+        die.display_unit(&mut DefaultFormatter::new(&mut buf, 4), &unit).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(),
+                   "variable\noffset: 0\nlocation: (expr_loc) DW_OP_fbreg -24\n");
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn abbrev() {
+        let abbrev_vec = AbbrevVec::new(vec![
+            Abbrev {
+                code: 1,
+                tag: DW_TAG_compile_unit,
+                children: true,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_producer, DW_FORM_strp),
+                    AbbrevAttribute::new(DW_AT_language, DW_FORM_data1),
+                ],
+            },
+            Abbrev {
+                code: 2,
+                tag: DwTag(0x5000),
+                children: false,
+                attributes: vec![],
+            },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev_vec.write(&mut debug_abbrev).unwrap();
+        let table_offset = debug_abbrev.len();
+        abbrev_vec.write(&mut debug_abbrev).unwrap();
+
+        let table = "   1      DW_TAG_compile_unit    [has children]\n\
+                    \x20   DW_AT_producer     DW_FORM_strp\n\
+                    \x20   DW_AT_language     DW_FORM_data1\n\
+                    \x20   DW_AT value: 0     DW_FORM value: 0\n\
+                    \x20  2      tag(20480)    [no children]\n\
+                    \x20   DW_AT value: 0     DW_FORM value: 0\n";
+
+        let mut buf = Vec::new();
+        display_debug_abbrev(&mut DefaultFormatter::new(&mut buf, 4), &debug_abbrev).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(),
+                   format!("Contents of the .debug_abbrev section:\n\n\
+                            \x20 Number TAG (0)\n{}\
+                            \x20 Number TAG (0x{:x})\n{}",
+                           table, table_offset, table));
+
+        let mut buf = Vec::new();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+        abbrev_hash.display(&mut DefaultFormatter::new(&mut buf, 4), 0).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), format!("  Number TAG (0)\n{}", table));
+    }
+
+    #[test]
+    fn unit_header() {
+        let data = [0; 0x10];
+        let mut unit = CompilationUnit::<LittleEndian>::default();
+        unit.common.offset = 0x40;
+        unit.common.abbrev_offset = 0x18;
+        unit.common.data = &data;
+        assert_eq!(unit.to_string(),
+                   "  Compilation Unit @ offset 0x40:\n   \
+                    Length:        0x17 (32-bit)\n   \
+                    Version:       4\n   \
+                    Abbrev Offset: 0x18\n   \
+                    Pointer Size:  4\n");
+
+        let unit = TypeUnit {
+            common: UnitCommon {
+                endian: LittleEndian,
+                offset_size: 8,
+                address_size: 8,
+                data: &data,
+                ..Default::default()
+            },
+            type_signature: 0x0123456789abcdef,
+            type_offset: 0x25,
+        };
+        assert_eq!(unit.to_string(),
+                   "  Type Unit @ offset 0:\n   \
+                    Length:        0x2b (64-bit)\n   \
+                    Version:       4\n   \
+                    Abbrev Offset: 0\n   \
+                    Pointer Size:  8\n   \
+                    Signature:     0x0123456789abcdef\n   \
+                    Type Offset:   0x25\n");
+    }
+
+    #[test]
+    fn line_program() {
+        let program = LineProgram {
+            offset: 0x20,
+            endian: LittleEndian,
+            version: 4,
+            address_size: 4,
+            offset_size: 4,
+            address_step: 1,
+            operation_range: 1,
+            default_statement: true,
+            line_base: -5,
+            line_range: 14,
+            opcode_base: 4,
+            standard_opcode_lengths: &[0, 1, 1],
+            include_directories: vec![b"/build", b"/usr/include"],
+            files: vec![FileEntry { path: b"main.c", ..Default::default() },
+                        FileEntry { path: b"main.c", ..Default::default() },
+                        FileEntry {
+                            path: b"stdio.h",
+                            directory: 1,
+                            timestamp: 2,
+                            length: 3,
+                        }],
+            data: &[],
+        };
+        let mut buf = Vec::new();
+        program.display(&mut DefaultFormatter::new(&mut buf, 4)).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(),
+                   "  Offset:                      0x20\n\
+                    \x20 DWARF Version:               4\n\
+                    \x20 Minimum Instruction Length:  1\n\
+                    \x20 Maximum Ops per Instruction: 1\n\
+                    \x20 Initial value of 'is_stmt':  1\n\
+                    \x20 Line Base:                   -5\n\
+                    \x20 Line Range:                  14\n\
+                    \x20 Opcode Base:                 4\n\
+                    \n Opcodes:\n\
+                    \x20 Opcode 1 has 0 args\n\
+                    \x20 Opcode 2 has 1 arg\n\
+                    \x20 Opcode 3 has 1 arg\n\
+                    \n The Directory Table:\n\
+                    \x20 1\t/usr/include\n\
+                    \n The File Name Table:\n\
+                    \x20 Entry\tDir\tTime\tSize\tName\n\
+                    \x20 1\t0\t0\t0\tmain.c\n\
+                    \x20 2\t1\t2\t3\tstdio.h\n");
+    }
+
+    #[test]
+    fn string_policy() {
+        let data = b"C:\\\xe9t\xe9";
+        assert_eq!(StringDisplay::new(b"main", StringPolicy::Length).to_string(), "main");
+        assert_eq!(StringDisplay::new(data, StringPolicy::Length).to_string(), "len 6");
+        assert_eq!(StringDisplay::new(data, StringPolicy::Lossy).to_string(),
+                   "C:\\\u{fffd}t\u{fffd}");
+        assert_eq!(StringDisplay::new(data, StringPolicy::Escape).to_string(),
+                   "C:\\\\\\xe9t\\xe9");
+        // A truncated sequence at the end.
+        assert_eq!(StringDisplay::new(b"a\xe2\x82", StringPolicy::Escape).to_string(),
+                   "a\\xe2\\x82");
+
+        let die = Die::new(0, 1, DW_TAG_variable, false, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(data) },
+        ]);
+        let unit = UnitCommon { endian: LittleEndian, ..Default::default() };
+        let mut buf = Vec::new();
+        {
+            let mut f = DefaultFormatter::new(&mut buf, 4);
+            die.display_unit(&mut f, &unit).unwrap();
+            f.set_string_policy(StringPolicy::Escape);
+            die.display_unit(&mut f, &unit).unwrap();
+        }
+        assert_eq!(String::from_utf8(buf).unwrap(),
+                   "variable\noffset: 0\nname: (string) len 6\n\
+                    variable\noffset: 0\nname: (string) C:\\\\\\xe9t\\xe9\n");
+    }
+}
@@ -24,22 +24,44 @@ pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Sections<AnyEndian>, R
         _ => return Err(ReadError::Unsupported),
     };
     let debug_abbrev = get_section(&mut file, ".debug_abbrev");
+    let debug_addr = get_section(&mut file, ".debug_addr");
+    let debug_aranges = get_section(&mut file, ".debug_aranges");
+    let debug_frame = get_section(&mut file, ".debug_frame");
     let debug_info = get_section(&mut file, ".debug_info");
     let debug_line = get_section(&mut file, ".debug_line");
+    let debug_line_str = get_section(&mut file, ".debug_line_str");
+    let debug_loc = get_section(&mut file, ".debug_loc");
+    let debug_loclists = get_section(&mut file, ".debug_loclists");
+    let debug_ranges = get_section(&mut file, ".debug_ranges");
+    let debug_rnglists = get_section(&mut file, ".debug_rnglists");
     let debug_str = get_section(&mut file, ".debug_str");
+    let debug_str_offsets = get_section(&mut file, ".debug_str_offsets");
     let debug_types = get_section(&mut file, ".debug_types");
     Ok(Sections {
         endian: endian,
         debug_abbrev: debug_abbrev,
+        debug_addr: debug_addr,
+        debug_aranges: debug_aranges,
+        debug_frame: debug_frame,
         debug_info: debug_info,
         debug_line: debug_line,
+        debug_line_str: debug_line_str,
+        debug_loc: debug_loc,
+        debug_loclists: debug_loclists,
+        debug_ranges: debug_ranges,
+        debug_rnglists: debug_rnglists,
         debug_str: debug_str,
+        debug_str_offsets: debug_str_offsets,
         debug_types: debug_types,
     })
 }
 
+// Split DWARF object files use the same section names with a `.dwo` suffix.
 fn get_section(file: &mut elf::File, name: &str) -> Vec<u8> {
-    match file.sections.iter().position(|section| section.shdr.name == name) {
+    let dwo_name = format!("{}.dwo", name);
+    match file.sections
+        .iter()
+        .position(|section| section.shdr.name == name || section.shdr.name == dwo_name) {
         Some(index) => file.sections.swap_remove(index).data,
         None => Vec::new(),
     }
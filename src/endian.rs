@@ -1,6 +1,6 @@
 use std;
 use std::io::Write;
-use read::ReadError;
+use read::{read_slice, ReadError};
 
 pub trait Endian: Copy {
     fn read_u16(&self, r: &mut &[u8]) -> Result<u16, ReadError>;
@@ -9,30 +9,43 @@ pub trait Endian: Copy {
     fn write_u16<W: Write>(&self, w: &mut W, val: u16) -> Result<(), std::io::Error>;
     fn write_u32<W: Write>(&self, w: &mut W, val: u32) -> Result<(), std::io::Error>;
     fn write_u64<W: Write>(&self, w: &mut W, val: u64) -> Result<(), std::io::Error>;
+
+    // Return true if the most significant byte is stored first.
+    fn is_big_endian(&self) -> bool {
+        let mut r = &[0, 1][..];
+        self.read_u16(&mut r).ok() == Some(1)
+    }
 }
 
+// The bytes are combined with shifts rather than copied into the value,
+// so that no unsafe code is needed.
 macro_rules! read_endian {
-    ($r:ident, $ty:ty, $to:ident) => ({
-        let len = std::mem::size_of::<$ty>();
-        if $r.len() < len {
-            return Err(ReadError::Eof);
-        }
-        let mut val: $ty = 0;
-        unsafe {
-            std::ptr::copy_nonoverlapping($r.as_ptr(), &mut val as *mut $ty as *mut u8, len);
-        }
-        *$r = &$r[len..];
-        Ok(val.$to())
+    ($r:ident, $ty:ty, to_le) => ({
+        let bytes = try!(read_slice($r, std::mem::size_of::<$ty>()));
+        Ok(bytes.iter().rev().fold(0, |val, &byte| (val << 8) | byte as $ty))
+    });
+    ($r:ident, $ty:ty, to_be) => ({
+        let bytes = try!(read_slice($r, std::mem::size_of::<$ty>()));
+        Ok(bytes.iter().fold(0, |val, &byte| (val << 8) | byte as $ty))
     });
 }
 
 macro_rules! write_endian {
-    ($w:ident, $ty:ty, $to:ident, $val:ident) => ({
-        let val: $ty = $val.$to();
-        let buf = unsafe {
-            std::slice::from_raw_parts(&val as *const $ty as *const u8, std::mem::size_of::<$ty>())
-        };
-        $w.write_all(buf)
+    ($w:ident, $ty:ty, to_le, $val:ident) => ({
+        let len = std::mem::size_of::<$ty>();
+        let mut buf = [0; 8];
+        for (i, byte) in buf[..len].iter_mut().enumerate() {
+            *byte = ($val >> (8 * i)) as u8;
+        }
+        $w.write_all(&buf[..len])
+    });
+    ($w:ident, $ty:ty, to_be, $val:ident) => ({
+        let len = std::mem::size_of::<$ty>();
+        let mut buf = [0; 8];
+        for (i, byte) in buf[..len].iter_mut().rev().enumerate() {
+            *byte = ($val >> (8 * i)) as u8;
+        }
+        $w.write_all(&buf[..len])
     });
 }
 
@@ -167,3 +180,32 @@ impl Endian for AnyEndian {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn endian() {
+        let data = [1, 2, 3, 4, 5, 6, 7, 8];
+        let mut r = &data[..];
+        assert_eq!(LittleEndian.read_u16(&mut r).unwrap(), 0x0201);
+        assert_eq!(BigEndian.read_u16(&mut r).unwrap(), 0x0304);
+        assert_eq!(AnyEndian::Little.read_u32(&mut r).unwrap(), 0x0807_0605);
+        assert!(BigEndian.read_u16(&mut r).is_err());
+        let mut r = &data[..];
+        assert_eq!(BigEndian.read_u64(&mut r).unwrap(), 0x0102_0304_0506_0708);
+        let mut r = &data[..7];
+        assert!(LittleEndian.read_u64(&mut r).is_err());
+        assert_eq!(r.len(), 7);
+
+        let mut buf = Vec::new();
+        LittleEndian.write_u16(&mut buf, 0x0201).unwrap();
+        BigEndian.write_u16(&mut buf, 0x0304).unwrap();
+        AnyEndian::Little.write_u32(&mut buf, 0x0807_0605).unwrap();
+        assert_eq!(buf, data);
+        let mut buf = Vec::new();
+        AnyEndian::Big.write_u64(&mut buf, 0x0102_0304_0506_0708).unwrap();
+        assert_eq!(buf, data);
+    }
+}
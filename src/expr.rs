@@ -0,0 +1,280 @@
+use constant;
+use endian::Endian;
+use leb128;
+use read::*;
+use unit::UnitCommon;
+
+// The operands of a DWARF expression operation.
+//
+// The meaning of the operands depends on the opcode. Operands that are
+// implied by the opcode, such as the register number of DW_OP_reg0, are
+// not included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operands<'data> {
+    None,
+    Address(u64),
+    Unsigned(u64),
+    Signed(i64),
+    // DW_OP_bit_piece, DW_OP_regval_type, DW_OP_deref_type, DW_OP_xderef_type,
+    // and their GNU equivalents
+    UnsignedPair(u64, u64),
+    // DW_OP_bregx, DW_OP_implicit_pointer
+    UnsignedSigned(u64, i64),
+    // DW_OP_implicit_value, DW_OP_entry_value
+    Block(&'data [u8]),
+    // DW_OP_const_type
+    UnsignedBlock(u64, &'data [u8]),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operation<'data> {
+    // The offset of the operation within the expression.
+    pub offset: usize,
+    pub opcode: constant::DwOp,
+    pub operands: Operands<'data>,
+}
+
+// A memory access performed by a dereference operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryAccess {
+    // The number of bytes read, or `None` for the size of an address.
+    pub size: Option<u8>,
+    // True if the address space is given by the second stack entry, as for
+    // DW_OP_xderef. The address space identifier is target specific, and is
+    // typically one of the values used for DW_AT_address_class.
+    pub address_space: bool,
+}
+
+impl<'data> Operation<'data> {
+    // Return the memory access performed by this operation, if any.
+    pub fn memory_access(&self) -> Option<MemoryAccess> {
+        let (size, address_space) = match (self.opcode, self.operands) {
+            (constant::DW_OP_deref, _) => (None, false),
+            (constant::DW_OP_xderef, _) => (None, true),
+            (constant::DW_OP_deref_size, Operands::Unsigned(size)) => (Some(size as u8), false),
+            (constant::DW_OP_xderef_size, Operands::Unsigned(size)) => (Some(size as u8), true),
+            (constant::DW_OP_deref_type, Operands::UnsignedPair(size, _)) |
+            (constant::DW_OP_GNU_deref_type, Operands::UnsignedPair(size, _)) => {
+                (Some(size as u8), false)
+            }
+            (constant::DW_OP_xderef_type, Operands::UnsignedPair(size, _)) => {
+                (Some(size as u8), true)
+            }
+            _ => return None,
+        };
+        Some(MemoryAccess {
+            size: size,
+            address_space: address_space,
+        })
+    }
+
+    pub fn read<'unit, E: Endian>(
+        r: &mut &'data [u8],
+        offset: usize,
+        unit: &UnitCommon<'unit, E>
+    ) -> Result<Operation<'data>, ReadError> {
+        let endian = unit.endian;
+        let opcode = constant::DwOp(try!(read_u8(r)));
+        let operands = match opcode {
+            constant::DW_OP_addr => {
+                Operands::Address(try!(read_address(r, endian, unit.address_size)))
+            }
+            constant::DW_OP_const1u |
+            constant::DW_OP_pick |
+            constant::DW_OP_deref_size |
+            constant::DW_OP_xderef_size => Operands::Unsigned(try!(read_u8(r)) as u64),
+            constant::DW_OP_const1s => Operands::Signed(try!(read_i8(r)) as i64),
+            constant::DW_OP_const2u |
+            constant::DW_OP_call2 => Operands::Unsigned(try!(endian.read_u16(r)) as u64),
+            constant::DW_OP_const2s |
+            constant::DW_OP_skip |
+            constant::DW_OP_bra => Operands::Signed(try!(endian.read_u16(r)) as i16 as i64),
+            constant::DW_OP_const4u |
+            constant::DW_OP_call4 |
+            constant::DW_OP_GNU_parameter_ref => {
+                Operands::Unsigned(try!(endian.read_u32(r)) as u64)
+            }
+            constant::DW_OP_const4s => Operands::Signed(try!(endian.read_u32(r)) as i32 as i64),
+            constant::DW_OP_const8u => Operands::Unsigned(try!(endian.read_u64(r))),
+            constant::DW_OP_const8s => Operands::Signed(try!(endian.read_u64(r)) as i64),
+            constant::DW_OP_constu |
+            constant::DW_OP_plus_uconst |
+            constant::DW_OP_regx |
+            constant::DW_OP_piece |
+            constant::DW_OP_addrx |
+            constant::DW_OP_constx |
+            constant::DW_OP_convert |
+            constant::DW_OP_reinterpret |
+            constant::DW_OP_GNU_addr_index |
+            constant::DW_OP_GNU_const_index |
+            constant::DW_OP_GNU_convert |
+            constant::DW_OP_GNU_reinterpret => Operands::Unsigned(try!(leb128::read_u64(r))),
+            constant::DW_OP_consts |
+            constant::DW_OP_fbreg => Operands::Signed(try!(leb128::read_i64(r))),
+            constant::DW_OP_call_ref |
+            constant::DW_OP_GNU_variable_value => {
+                Operands::Unsigned(try!(read_offset(r, endian, unit.offset_size)))
+            }
+            constant::DW_OP_bregx => {
+                let register = try!(leb128::read_u64(r));
+                let offset = try!(leb128::read_i64(r));
+                Operands::UnsignedSigned(register, offset)
+            }
+            constant::DW_OP_implicit_pointer |
+            constant::DW_OP_GNU_implicit_pointer => {
+                let entry = try!(read_offset(r, endian, unit.offset_size));
+                let offset = try!(leb128::read_i64(r));
+                Operands::UnsignedSigned(entry, offset)
+            }
+            constant::DW_OP_bit_piece |
+            constant::DW_OP_regval_type |
+            constant::DW_OP_GNU_regval_type => {
+                let first = try!(leb128::read_u64(r));
+                let second = try!(leb128::read_u64(r));
+                Operands::UnsignedPair(first, second)
+            }
+            constant::DW_OP_deref_type |
+            constant::DW_OP_xderef_type |
+            constant::DW_OP_GNU_deref_type => {
+                let size = try!(read_u8(r)) as u64;
+                let base_type = try!(leb128::read_u64(r));
+                Operands::UnsignedPair(size, base_type)
+            }
+            constant::DW_OP_implicit_value |
+            constant::DW_OP_entry_value |
+            constant::DW_OP_GNU_entry_value => {
+                let len = try!(leb128::read_u64(r)) as usize;
+                Operands::Block(try!(read_block(r, len)))
+            }
+            constant::DW_OP_const_type |
+            constant::DW_OP_GNU_const_type => {
+                let base_type = try!(leb128::read_u64(r));
+                let len = try!(read_u8(r)) as usize;
+                Operands::UnsignedBlock(base_type, try!(read_block(r, len)))
+            }
+            constant::DwOp(op) if op >= constant::DW_OP_breg0.0 &&
+                                  op <= constant::DW_OP_breg31.0 => {
+                Operands::Signed(try!(leb128::read_i64(r)))
+            }
+            constant::DW_OP_GNU_uninit => Operands::None,
+            _ => {
+                // The operands of unknown and vendor operations can't be
+                // skipped. This includes DW_OP_GNU_push_tls_address, because
+                // other vendors have used the same value.
+                if opcode.name().is_none() || opcode.0 >= constant::DW_OP_lo_user.0 {
+                    return Err(ReadError::Unsupported);
+                }
+                Operands::None
+            }
+        };
+        Ok(Operation {
+            offset: offset,
+            opcode: opcode,
+            operands: operands,
+        })
+    }
+}
+
+// An iterator over the operations of a DWARF expression, such as the value
+// of a DW_FORM_exprloc attribute.
+#[derive(Debug, Clone)]
+pub struct ExpressionIterator<'a, 'data, E>
+    where 'data: 'a,
+          E: Endian + 'a
+{
+    data: &'data [u8],
+    r: &'data [u8],
+    unit: &'a UnitCommon<'data, E>,
+}
+
+impl<'a, 'data, E: Endian> ExpressionIterator<'a, 'data, E> {
+    pub fn new(data: &'data [u8], unit: &'a UnitCommon<'data, E>) -> Self {
+        ExpressionIterator {
+            data: data,
+            r: data,
+            unit: unit,
+        }
+    }
+
+    #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
+    pub fn next(&mut self) -> Result<Option<Operation<'data>>, ReadError> {
+        if self.r.is_empty() {
+            return Ok(None);
+        }
+        let offset = self.data.len() - self.r.len();
+        let mut r = self.r;
+        let operation = try!(Operation::read(&mut r, offset, self.unit));
+        self.r = r;
+        Ok(Some(operation))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use constant::*;
+    use endian::*;
+    use unit::*;
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn expression() {
+        let unit = UnitCommon { endian: LittleEndian, ..Default::default() };
+        let data = [
+            DW_OP_addr.0, 0x00, 0x10, 0x00, 0x00,
+            DW_OP_lit3.0,
+            DW_OP_swap.0,
+            DW_OP_xderef_size.0, 2,
+            DW_OP_breg5.0, 0x7c,
+            DW_OP_bra.0, 0xfe, 0xff,
+            DW_OP_bit_piece.0, 8, 16,
+            DW_OP_deref.0,
+        ];
+        let mut ops = Vec::new();
+        let mut iter = ExpressionIterator::new(&data, &unit);
+        while let Some(op) = iter.next().unwrap() {
+            ops.push((op.offset, op.opcode, op.operands, op.memory_access()));
+        }
+        assert_eq!(ops, vec![
+            (0, DW_OP_addr, Operands::Address(0x1000), None),
+            (5, DW_OP_lit3, Operands::None, None),
+            (6, DW_OP_swap, Operands::None, None),
+            (7, DW_OP_xderef_size, Operands::Unsigned(2),
+             Some(MemoryAccess { size: Some(2), address_space: true })),
+            (9, DW_OP_breg5, Operands::Signed(-4), None),
+            (11, DW_OP_bra, Operands::Signed(-2), None),
+            (14, DW_OP_bit_piece, Operands::UnsignedPair(8, 16), None),
+            (17, DW_OP_deref, Operands::None,
+             Some(MemoryAccess { size: None, address_space: false })),
+        ]);
+
+        // GNU extensions that use the same encoding as DWARF 5 operations.
+        let data = [
+            DW_OP_GNU_entry_value.0, 2, DW_OP_reg5.0, DW_OP_stack_value.0,
+            DW_OP_GNU_regval_type.0, 3, 0x20,
+            DW_OP_GNU_deref_type.0, 4, 0x20,
+            DW_OP_GNU_parameter_ref.0, 0x10, 0, 0, 0,
+            DW_OP_GNU_uninit.0,
+        ];
+        let mut ops = Vec::new();
+        let mut iter = ExpressionIterator::new(&data, &unit);
+        while let Some(op) = iter.next().unwrap() {
+            ops.push((op.offset, op.opcode, op.operands, op.memory_access()));
+        }
+        assert_eq!(ops, vec![
+            (0, DW_OP_GNU_entry_value, Operands::Block(&[DW_OP_reg5.0, DW_OP_stack_value.0]), None),
+            (4, DW_OP_GNU_regval_type, Operands::UnsignedPair(3, 0x20), None),
+            (7, DW_OP_GNU_deref_type, Operands::UnsignedPair(4, 0x20),
+             Some(MemoryAccess { size: Some(4), address_space: false })),
+            (10, DW_OP_GNU_parameter_ref, Operands::Unsigned(0x10), None),
+            (15, DW_OP_GNU_uninit, Operands::None, None),
+        ]);
+
+        let data = [DW_OP_lo_user.0];
+        assert!(ExpressionIterator::new(&data, &unit).next().is_err());
+        let data = [DW_OP_GNU_encoded_addr.0, 0];
+        assert!(ExpressionIterator::new(&data, &unit).next().is_err());
+        let data = [DW_OP_const4u.0, 0];
+        assert!(ExpressionIterator::new(&data, &unit).next().is_err());
+    }
+}
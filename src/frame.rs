@@ -0,0 +1,1138 @@
+use std::io::Write;
+
+use constant;
+use endian::Endian;
+use leb128;
+use read::*;
+use write::*;
+
+// A Common Information Entry in .debug_frame.
+//
+// This contains the information that is shared by the FDEs that refer to
+// it, including the instructions that give the initial rules for each
+// register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cie<'data> {
+    pub offset: usize,
+    pub offset_size: u8,
+    pub version: u8,
+    pub augmentation: &'data [u8],
+    pub address_size: u8,
+    pub segment_size: u8,
+    pub code_alignment_factor: u64,
+    pub data_alignment_factor: i64,
+    pub return_address_register: u64,
+    // The augmentation data, if the augmentation string starts with "z".
+    pub augmentation_data: Option<&'data [u8]>,
+    pub initial_instructions: &'data [u8],
+}
+
+impl<'data> Cie<'data> {
+    // Read the CIE at `offset` in `debug_frame`.
+    //
+    // Versions 1 and 3 don't contain the address size, so `address_size`
+    // is used for those.
+    //
+    // Augmentations other than an empty string, or a string that starts
+    // with "z", return `ReadError::Unsupported` because the size of their
+    // data isn't known.
+    pub fn read<E: Endian>(
+        debug_frame: &'data [u8],
+        offset: usize,
+        endian: E,
+        address_size: u8
+    ) -> Result<Cie<'data>, ReadError> {
+        let (offset_size, mut r) = try!(read_entry(debug_frame, offset, endian));
+        let id = try!(read_offset(&mut r, endian, offset_size));
+        if id != cie_id(offset_size) {
+            return Err(ReadError::Invalid);
+        }
+        Cie::read_fields(r, offset, offset_size, address_size)
+    }
+
+    fn read_fields(
+        mut r: &'data [u8],
+        offset: usize,
+        offset_size: u8,
+        address_size: u8
+    ) -> Result<Cie<'data>, ReadError> {
+        let version = try!(read_u8(&mut r));
+        match version {
+            1 | 3 | 4 => {}
+            _ => return Err(ReadError::UnsupportedVersion(version as u16)),
+        }
+        let augmentation = try!(read_string(&mut r));
+        if !augmentation.is_empty() && augmentation[0] != b'z' {
+            return Err(ReadError::Unsupported);
+        }
+        let (address_size, segment_size) = if version >= 4 {
+            (try!(read_u8(&mut r)), try!(read_u8(&mut r)))
+        } else {
+            (address_size, 0)
+        };
+        let code_alignment_factor = try!(leb128::read_u64(&mut r));
+        let data_alignment_factor = try!(leb128::read_i64(&mut r));
+        let return_address_register = if version == 1 {
+            try!(read_u8(&mut r)) as u64
+        } else {
+            try!(leb128::read_u64(&mut r))
+        };
+        let augmentation_data = if augmentation.is_empty() {
+            None
+        } else {
+            Some(try!(read_augmentation_data(&mut r)))
+        };
+        Ok(Cie {
+            offset: offset,
+            offset_size: offset_size,
+            version: version,
+            augmentation: augmentation,
+            address_size: address_size,
+            segment_size: segment_size,
+            code_alignment_factor: code_alignment_factor,
+            data_alignment_factor: data_alignment_factor,
+            return_address_register: return_address_register,
+            augmentation_data: augmentation_data,
+            initial_instructions: r,
+        })
+    }
+
+    // Iterate over the initial instructions.
+    pub fn instructions<E: Endian>(&self, endian: E) -> InstructionIterator<'data, E> {
+        InstructionIterator::new(self.initial_instructions, endian, self.address_size)
+    }
+}
+
+// A Frame Description Entry in .debug_frame.
+//
+// This contains the instructions that describe the changes to the
+// register rules for a range of addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fde<'data> {
+    pub offset: usize,
+    pub offset_size: u8,
+    // The offset of the CIE in .debug_frame.
+    pub cie_offset: u64,
+    pub segment_selector: u64,
+    pub initial_location: u64,
+    pub address_range: u64,
+    // The augmentation data, if the augmentation string of the CIE starts
+    // with "z".
+    pub augmentation_data: Option<&'data [u8]>,
+    pub instructions: &'data [u8],
+}
+
+impl<'data> Fde<'data> {
+    fn read_fields<E: Endian>(
+        mut r: &'data [u8],
+        offset: usize,
+        offset_size: u8,
+        cie_offset: u64,
+        cie: &Cie,
+        endian: E
+    ) -> Result<Fde<'data>, ReadError> {
+        let segment_selector = if cie.segment_size != 0 {
+            try!(read_address(&mut r, endian, cie.segment_size))
+        } else {
+            0
+        };
+        let initial_location = try!(read_address(&mut r, endian, cie.address_size));
+        let address_range = try!(read_address(&mut r, endian, cie.address_size));
+        let augmentation_data = if cie.augmentation.is_empty() {
+            None
+        } else {
+            Some(try!(read_augmentation_data(&mut r)))
+        };
+        Ok(Fde {
+            offset: offset,
+            offset_size: offset_size,
+            cie_offset: cie_offset,
+            segment_selector: segment_selector,
+            initial_location: initial_location,
+            address_range: address_range,
+            augmentation_data: augmentation_data,
+            instructions: r,
+        })
+    }
+
+    // Iterate over the instructions. `cie` is the CIE that the FDE refers to.
+    pub fn instructions<E: Endian>(&self, endian: E, cie: &Cie) -> InstructionIterator<'data, E> {
+        InstructionIterator::new(self.instructions, endian, cie.address_size)
+    }
+
+    // Return true if the FDE describes the address.
+    pub fn contains(&self, address: u64) -> bool {
+        address >= self.initial_location && address - self.initial_location < self.address_range
+    }
+
+    // Execute the initial instructions of `cie` and the instructions of the
+    // FDE, and return the rows of the table for the addresses of the FDE.
+    //
+    // Rows that don't contain any addresses are omitted. The rules saved by
+    // DW_CFA_remember_state include the CFA rule.
+    pub fn rows<E: Endian>(
+        &self,
+        cie: &Cie<'data>,
+        endian: E
+    ) -> Result<Vec<UnwindRow<'data>>, ReadError> {
+        let mut state = RuleState::default();
+        let mut instructions = cie.instructions(endian);
+        while let Some(instruction) = try!(instructions.next()) {
+            // The initial instructions can't change the location.
+            if try!(state.execute(instruction, cie, 0, None)).is_some() {
+                return Err(ReadError::Invalid);
+            }
+        }
+        let initial = state.clone();
+
+        let end = try!(self.initial_location
+            .checked_add(self.address_range)
+            .ok_or(ReadError::Overflow));
+        let mut rows = Vec::new();
+        let mut address = self.initial_location;
+        let mut instructions = self.instructions(endian, cie);
+        loop {
+            let instruction = try!(instructions.next());
+            let next = match instruction {
+                Some(instruction) => {
+                    match try!(state.execute(instruction, cie, address, Some(&initial))) {
+                        Some(next) => next,
+                        None => continue,
+                    }
+                }
+                None => end,
+            };
+            if next < address || next > end {
+                return Err(ReadError::Invalid);
+            }
+            if next > address {
+                rows.push(UnwindRow {
+                    address: address,
+                    end: next,
+                    cfa: try!(state.cfa.ok_or(ReadError::Invalid)),
+                    registers: state.registers.clone(),
+                });
+                address = next;
+            }
+            if instruction.is_none() {
+                return Ok(rows);
+            }
+        }
+    }
+}
+
+// The rule for computing the CFA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CfaRule<'data> {
+    // The value of the register plus the offset.
+    RegisterOffset { register: u64, offset: i64 },
+    // The value of a DWARF expression.
+    Expression(&'data [u8]),
+}
+
+// The rule for finding the value of a register in the previous frame.
+//
+// Offsets are relative to the CFA, and have already been multiplied by the
+// data alignment factor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterRule<'data> {
+    Undefined,
+    SameValue,
+    // The value is saved at the CFA plus the offset.
+    Offset(i64),
+    // The value is the CFA plus the offset.
+    ValOffset(i64),
+    // The value is saved in another register.
+    Register(u64),
+    // The value is saved at the address given by a DWARF expression.
+    Expression(&'data [u8]),
+    // The value is given by a DWARF expression.
+    ValExpression(&'data [u8]),
+}
+
+// A row of the table described by a CIE and FDE.
+//
+// The rules apply to the addresses from `address` up to `end`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnwindRow<'data> {
+    pub address: u64,
+    pub end: u64,
+    pub cfa: CfaRule<'data>,
+    // The rules for the registers that have one, sorted by register.
+    pub registers: Vec<(u64, RegisterRule<'data>)>,
+}
+
+impl<'data> UnwindRow<'data> {
+    // Return the rule for a register, if it has one.
+    pub fn register(&self, register: u64) -> Option<RegisterRule<'data>> {
+        rule(&self.registers, register)
+    }
+}
+
+fn rule<'data>(registers: &[(u64, RegisterRule<'data>)], register: u64) -> Option<RegisterRule<'data>> {
+    registers.binary_search_by_key(&register, |&(r, _)| r).ok().map(|index| registers[index].1)
+}
+
+// The register rules of a row, sorted by register.
+type RegisterRules<'data> = Vec<(u64, RegisterRule<'data>)>;
+
+// The rules of the row that is being built.
+#[derive(Debug, Default, Clone)]
+struct RuleState<'data> {
+    cfa: Option<CfaRule<'data>>,
+    registers: RegisterRules<'data>,
+    // The rules saved by DW_CFA_remember_state.
+    stack: Vec<(Option<CfaRule<'data>>, RegisterRules<'data>)>,
+}
+
+impl<'data> RuleState<'data> {
+    // Execute an instruction at `address`. Returns the new address if the
+    // instruction changes the location.
+    //
+    // `initial` is the state after the initial instructions of the CIE,
+    // which is used by DW_CFA_restore. It is `None` while executing the
+    // initial instructions.
+    fn execute(
+        &mut self,
+        instruction: CallFrameInstruction<'data>,
+        cie: &Cie,
+        address: u64,
+        initial: Option<&RuleState<'data>>
+    ) -> Result<Option<u64>, ReadError> {
+        let factor = cie.data_alignment_factor;
+        match instruction {
+            CallFrameInstruction::SetLoc(address) => return Ok(Some(address)),
+            CallFrameInstruction::AdvanceLoc(delta) => {
+                let next = delta.checked_mul(cie.code_alignment_factor)
+                    .and_then(|delta| address.checked_add(delta));
+                return next.map(Some).ok_or(ReadError::Overflow);
+            }
+            // The offsets of DW_CFA_def_cfa and DW_CFA_def_cfa_offset aren't
+            // factored.
+            CallFrameInstruction::DefCfa { register, offset } => {
+                self.cfa = Some(CfaRule::RegisterOffset {
+                    register: register,
+                    offset: try!(signed(offset)),
+                });
+            }
+            CallFrameInstruction::DefCfaSf { register, offset } => {
+                self.cfa = Some(CfaRule::RegisterOffset {
+                    register: register,
+                    offset: try!(factored(offset, factor)),
+                });
+            }
+            CallFrameInstruction::DefCfaRegister(register) => {
+                let offset = try!(self.cfa_offset());
+                self.cfa = Some(CfaRule::RegisterOffset {
+                    register: register,
+                    offset: offset,
+                });
+            }
+            CallFrameInstruction::DefCfaOffset(offset) => {
+                let register = try!(self.cfa_register());
+                self.cfa = Some(CfaRule::RegisterOffset {
+                    register: register,
+                    offset: try!(signed(offset)),
+                });
+            }
+            CallFrameInstruction::DefCfaOffsetSf(offset) => {
+                let register = try!(self.cfa_register());
+                self.cfa = Some(CfaRule::RegisterOffset {
+                    register: register,
+                    offset: try!(factored(offset, factor)),
+                });
+            }
+            CallFrameInstruction::DefCfaExpression(expression) => {
+                self.cfa = Some(CfaRule::Expression(expression));
+            }
+            CallFrameInstruction::Undefined(register) => {
+                self.set(register, Some(RegisterRule::Undefined));
+            }
+            CallFrameInstruction::SameValue(register) => {
+                self.set(register, Some(RegisterRule::SameValue));
+            }
+            CallFrameInstruction::Offset { register, offset } => {
+                let offset = try!(factored(try!(signed(offset)), factor));
+                self.set(register, Some(RegisterRule::Offset(offset)));
+            }
+            CallFrameInstruction::OffsetSf { register, offset } => {
+                let offset = try!(factored(offset, factor));
+                self.set(register, Some(RegisterRule::Offset(offset)));
+            }
+            CallFrameInstruction::ValOffset { register, offset } => {
+                let offset = try!(factored(try!(signed(offset)), factor));
+                self.set(register, Some(RegisterRule::ValOffset(offset)));
+            }
+            CallFrameInstruction::ValOffsetSf { register, offset } => {
+                let offset = try!(factored(offset, factor));
+                self.set(register, Some(RegisterRule::ValOffset(offset)));
+            }
+            CallFrameInstruction::Register { register, target } => {
+                self.set(register, Some(RegisterRule::Register(target)));
+            }
+            CallFrameInstruction::Expression { register, expression } => {
+                self.set(register, Some(RegisterRule::Expression(expression)));
+            }
+            CallFrameInstruction::ValExpression { register, expression } => {
+                self.set(register, Some(RegisterRule::ValExpression(expression)));
+            }
+            CallFrameInstruction::Restore(register) => {
+                let initial = try!(initial.ok_or(ReadError::Invalid));
+                self.set(register, rule(&initial.registers, register));
+            }
+            CallFrameInstruction::RememberState => {
+                self.stack.push((self.cfa, self.registers.clone()));
+            }
+            CallFrameInstruction::RestoreState => {
+                let (cfa, registers) = try!(self.stack.pop().ok_or(ReadError::Invalid));
+                self.cfa = cfa;
+                self.registers = registers;
+            }
+            CallFrameInstruction::ArgsSize(_) |
+            CallFrameInstruction::WindowSave |
+            CallFrameInstruction::Nop => {}
+        }
+        Ok(None)
+    }
+
+    fn cfa_register(&self) -> Result<u64, ReadError> {
+        match self.cfa {
+            Some(CfaRule::RegisterOffset { register, .. }) => Ok(register),
+            _ => Err(ReadError::Invalid),
+        }
+    }
+
+    fn cfa_offset(&self) -> Result<i64, ReadError> {
+        match self.cfa {
+            Some(CfaRule::RegisterOffset { offset, .. }) => Ok(offset),
+            _ => Err(ReadError::Invalid),
+        }
+    }
+
+    // Set the rule for a register, or remove it if `rule` is `None`.
+    fn set(&mut self, register: u64, rule: Option<RegisterRule<'data>>) {
+        match (self.registers.binary_search_by_key(&register, |&(r, _)| r), rule) {
+            (Ok(index), Some(rule)) => self.registers[index].1 = rule,
+            (Ok(index), None) => {
+                self.registers.remove(index);
+            }
+            (Err(index), Some(rule)) => self.registers.insert(index, (register, rule)),
+            (Err(_), None) => {}
+        }
+    }
+}
+
+fn signed(offset: u64) -> Result<i64, ReadError> {
+    if offset > i64::max_value() as u64 {
+        return Err(ReadError::Overflow);
+    }
+    Ok(offset as i64)
+}
+
+fn factored(offset: i64, factor: i64) -> Result<i64, ReadError> {
+    offset.checked_mul(factor).ok_or(ReadError::Overflow)
+}
+
+// An entry in .debug_frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameEntry<'data> {
+    Cie(Cie<'data>),
+    // An FDE and the CIE that it refers to.
+    Fde(Fde<'data>, Cie<'data>),
+}
+
+// An iterator over the CIEs and FDEs in .debug_frame, in the order that
+// they are stored.
+#[derive(Debug, Clone)]
+pub struct FrameEntryIterator<'data, E: Endian> {
+    debug_frame: &'data [u8],
+    offset: usize,
+    endian: E,
+    address_size: u8,
+}
+
+impl<'data, E: Endian> FrameEntryIterator<'data, E> {
+    // `address_size` is used for CIEs that don't contain the address size.
+    pub fn new(debug_frame: &'data [u8], endian: E, address_size: u8) -> Self {
+        FrameEntryIterator {
+            debug_frame: debug_frame,
+            offset: 0,
+            endian: endian,
+            address_size: address_size,
+        }
+    }
+
+    // The offset of the next entry.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
+    pub fn next(&mut self) -> Result<Option<FrameEntry<'data>>, ReadError> {
+        if self.offset >= self.debug_frame.len() {
+            return Ok(None);
+        }
+        let offset = self.offset;
+        let (offset_size, mut r) = try!(read_entry(self.debug_frame, offset, self.endian));
+        // The initial length field is 4 bytes, or 12 bytes for 64-bit DWARF.
+        self.offset = offset + (offset_size as usize * 2 - 4) + r.len();
+        let id = try!(read_offset(&mut r, self.endian, offset_size));
+        if id == cie_id(offset_size) {
+            let cie = try!(Cie::read_fields(r, offset, offset_size, self.address_size));
+            return Ok(Some(FrameEntry::Cie(cie)));
+        }
+        let cie = try!(Cie::read(self.debug_frame, id as usize, self.endian, self.address_size));
+        let fde = try!(Fde::read_fields(r, offset, offset_size, id, &cie, self.endian));
+        Ok(Some(FrameEntry::Fde(fde, cie)))
+    }
+}
+
+// The CIE_id of a CIE in .debug_frame, which distinguishes it from an FDE.
+fn cie_id(offset_size: u8) -> u64 {
+    if offset_size == 8 {
+        u64::max_value()
+    } else {
+        u32::max_value() as u64
+    }
+}
+
+// Return the offset size and the data of the entry at `offset`, following
+// its length.
+fn read_entry<E: Endian>(
+    debug_frame: &[u8],
+    offset: usize,
+    endian: E
+) -> Result<(u8, &[u8]), ReadError> {
+    if offset >= debug_frame.len() {
+        return Err(ReadError::Invalid);
+    }
+    let mut r = &debug_frame[offset..];
+    let (offset_size, len) = try!(read_initial_length(&mut r, endian));
+    Ok((offset_size, &r[..len]))
+}
+
+fn read_augmentation_data<'data>(r: &mut &'data [u8]) -> Result<&'data [u8], ReadError> {
+    let len = try!(leb128::read_u64(r));
+    if len > r.len() as u64 {
+        return Err(ReadError::Invalid);
+    }
+    read_block(r, len as usize)
+}
+
+// A call frame instruction.
+//
+// Offsets are factored, and must be multiplied by the data alignment
+// factor of the CIE. Advances must be multiplied by the code alignment
+// factor. Instructions with an extended form are returned as the same
+// variant as the primary form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallFrameInstruction<'data> {
+    SetLoc(u64),
+    AdvanceLoc(u64),
+    DefCfa { register: u64, offset: u64 },
+    DefCfaSf { register: u64, offset: i64 },
+    DefCfaRegister(u64),
+    DefCfaOffset(u64),
+    DefCfaOffsetSf(i64),
+    DefCfaExpression(&'data [u8]),
+    Undefined(u64),
+    SameValue(u64),
+    Offset { register: u64, offset: u64 },
+    OffsetSf { register: u64, offset: i64 },
+    ValOffset { register: u64, offset: u64 },
+    ValOffsetSf { register: u64, offset: i64 },
+    Register { register: u64, target: u64 },
+    Expression { register: u64, expression: &'data [u8] },
+    ValExpression { register: u64, expression: &'data [u8] },
+    Restore(u64),
+    RememberState,
+    RestoreState,
+    ArgsSize(u64),
+    WindowSave,
+    Nop,
+}
+
+impl<'data> CallFrameInstruction<'data> {
+    // Read an instruction.
+    pub fn read<E: Endian>(
+        r: &mut &'data [u8],
+        endian: E,
+        address_size: u8
+    ) -> Result<CallFrameInstruction<'data>, ReadError> {
+        let byte = try!(read_u8(r));
+        let operand = (byte & 0x3f) as u64;
+        match constant::DwCfa(byte & 0xc0) {
+            constant::DW_CFA_advance_loc => return Ok(CallFrameInstruction::AdvanceLoc(operand)),
+            constant::DW_CFA_offset => {
+                return Ok(CallFrameInstruction::Offset {
+                    register: operand,
+                    offset: try!(leb128::read_u64(r)),
+                });
+            }
+            constant::DW_CFA_restore => return Ok(CallFrameInstruction::Restore(operand)),
+            _ => {}
+        }
+        let instruction = match constant::DwCfa(byte) {
+            constant::DW_CFA_nop => CallFrameInstruction::Nop,
+            constant::DW_CFA_set_loc => {
+                CallFrameInstruction::SetLoc(try!(read_address(r, endian, address_size)))
+            }
+            constant::DW_CFA_advance_loc1 => CallFrameInstruction::AdvanceLoc(try!(read_u8(r)) as u64),
+            constant::DW_CFA_advance_loc2 => {
+                CallFrameInstruction::AdvanceLoc(try!(endian.read_u16(r)) as u64)
+            }
+            constant::DW_CFA_advance_loc4 => {
+                CallFrameInstruction::AdvanceLoc(try!(endian.read_u32(r)) as u64)
+            }
+            constant::DW_CFA_MIPS_advance_loc8 => {
+                CallFrameInstruction::AdvanceLoc(try!(endian.read_u64(r)))
+            }
+            constant::DW_CFA_offset_extended => {
+                CallFrameInstruction::Offset {
+                    register: try!(leb128::read_u64(r)),
+                    offset: try!(leb128::read_u64(r)),
+                }
+            }
+            constant::DW_CFA_restore_extended => {
+                CallFrameInstruction::Restore(try!(leb128::read_u64(r)))
+            }
+            constant::DW_CFA_undefined => CallFrameInstruction::Undefined(try!(leb128::read_u64(r))),
+            constant::DW_CFA_same_value => CallFrameInstruction::SameValue(try!(leb128::read_u64(r))),
+            constant::DW_CFA_register => {
+                CallFrameInstruction::Register {
+                    register: try!(leb128::read_u64(r)),
+                    target: try!(leb128::read_u64(r)),
+                }
+            }
+            constant::DW_CFA_remember_state => CallFrameInstruction::RememberState,
+            constant::DW_CFA_restore_state => CallFrameInstruction::RestoreState,
+            constant::DW_CFA_def_cfa => {
+                CallFrameInstruction::DefCfa {
+                    register: try!(leb128::read_u64(r)),
+                    offset: try!(leb128::read_u64(r)),
+                }
+            }
+            constant::DW_CFA_def_cfa_register => {
+                CallFrameInstruction::DefCfaRegister(try!(leb128::read_u64(r)))
+            }
+            constant::DW_CFA_def_cfa_offset => {
+                CallFrameInstruction::DefCfaOffset(try!(leb128::read_u64(r)))
+            }
+            constant::DW_CFA_def_cfa_expression => {
+                CallFrameInstruction::DefCfaExpression(try!(read_expression(r)))
+            }
+            constant::DW_CFA_expression => {
+                CallFrameInstruction::Expression {
+                    register: try!(leb128::read_u64(r)),
+                    expression: try!(read_expression(r)),
+                }
+            }
+            constant::DW_CFA_offset_extended_sf => {
+                CallFrameInstruction::OffsetSf {
+                    register: try!(leb128::read_u64(r)),
+                    offset: try!(leb128::read_i64(r)),
+                }
+            }
+            constant::DW_CFA_def_cfa_sf => {
+                CallFrameInstruction::DefCfaSf {
+                    register: try!(leb128::read_u64(r)),
+                    offset: try!(leb128::read_i64(r)),
+                }
+            }
+            constant::DW_CFA_def_cfa_offset_sf => {
+                CallFrameInstruction::DefCfaOffsetSf(try!(leb128::read_i64(r)))
+            }
+            constant::DW_CFA_val_offset => {
+                CallFrameInstruction::ValOffset {
+                    register: try!(leb128::read_u64(r)),
+                    offset: try!(leb128::read_u64(r)),
+                }
+            }
+            constant::DW_CFA_val_offset_sf => {
+                CallFrameInstruction::ValOffsetSf {
+                    register: try!(leb128::read_u64(r)),
+                    offset: try!(leb128::read_i64(r)),
+                }
+            }
+            constant::DW_CFA_val_expression => {
+                CallFrameInstruction::ValExpression {
+                    register: try!(leb128::read_u64(r)),
+                    expression: try!(read_expression(r)),
+                }
+            }
+            constant::DW_CFA_GNU_window_save => CallFrameInstruction::WindowSave,
+            constant::DW_CFA_GNU_args_size => {
+                CallFrameInstruction::ArgsSize(try!(leb128::read_u64(r)))
+            }
+            constant::DW_CFA_GNU_negative_offset_extended => {
+                let register = try!(leb128::read_u64(r));
+                let offset = try!(leb128::read_u64(r));
+                CallFrameInstruction::OffsetSf {
+                    register: register,
+                    offset: try!((offset as i64).checked_neg().ok_or(ReadError::Overflow)),
+                }
+            }
+            _ => return Err(ReadError::Unsupported),
+        };
+        Ok(instruction)
+    }
+
+    // Write the instruction, using the smallest encoding for advances and
+    // for registers that fit in the primary opcode.
+    pub fn write<W: Write, E: Endian>(
+        &self,
+        w: &mut W,
+        endian: E,
+        address_size: u8
+    ) -> Result<(), WriteError> {
+        match *self {
+            CallFrameInstruction::SetLoc(address) => {
+                try!(write_u8(w, constant::DW_CFA_set_loc.0));
+                try!(write_address(w, endian, address_size, address));
+            }
+            CallFrameInstruction::AdvanceLoc(delta) => {
+                if delta <= 0x3f {
+                    try!(write_u8(w, constant::DW_CFA_advance_loc.0 | delta as u8));
+                } else if delta <= 0xff {
+                    try!(write_u8(w, constant::DW_CFA_advance_loc1.0));
+                    try!(write_u8(w, delta as u8));
+                } else if delta <= 0xffff {
+                    try!(write_u8(w, constant::DW_CFA_advance_loc2.0));
+                    try!(endian.write_u16(w, delta as u16));
+                } else if delta <= 0xffff_ffff {
+                    try!(write_u8(w, constant::DW_CFA_advance_loc4.0));
+                    try!(endian.write_u32(w, delta as u32));
+                } else {
+                    return Err(WriteError::Invalid(format!("advance 0x{:x} does not fit in 4 bytes",
+                                                           delta)));
+                }
+            }
+            CallFrameInstruction::DefCfa { register, offset } => {
+                try!(write_u8(w, constant::DW_CFA_def_cfa.0));
+                try!(leb128::write_u64(w, register));
+                try!(leb128::write_u64(w, offset));
+            }
+            CallFrameInstruction::DefCfaSf { register, offset } => {
+                try!(write_u8(w, constant::DW_CFA_def_cfa_sf.0));
+                try!(leb128::write_u64(w, register));
+                try!(leb128::write_i64(w, offset));
+            }
+            CallFrameInstruction::DefCfaRegister(register) => {
+                try!(write_u8(w, constant::DW_CFA_def_cfa_register.0));
+                try!(leb128::write_u64(w, register));
+            }
+            CallFrameInstruction::DefCfaOffset(offset) => {
+                try!(write_u8(w, constant::DW_CFA_def_cfa_offset.0));
+                try!(leb128::write_u64(w, offset));
+            }
+            CallFrameInstruction::DefCfaOffsetSf(offset) => {
+                try!(write_u8(w, constant::DW_CFA_def_cfa_offset_sf.0));
+                try!(leb128::write_i64(w, offset));
+            }
+            CallFrameInstruction::DefCfaExpression(expression) => {
+                try!(write_u8(w, constant::DW_CFA_def_cfa_expression.0));
+                try!(write_expression(w, expression));
+            }
+            CallFrameInstruction::Undefined(register) => {
+                try!(write_u8(w, constant::DW_CFA_undefined.0));
+                try!(leb128::write_u64(w, register));
+            }
+            CallFrameInstruction::SameValue(register) => {
+                try!(write_u8(w, constant::DW_CFA_same_value.0));
+                try!(leb128::write_u64(w, register));
+            }
+            CallFrameInstruction::Offset { register, offset } => {
+                if register <= 0x3f {
+                    try!(write_u8(w, constant::DW_CFA_offset.0 | register as u8));
+                } else {
+                    try!(write_u8(w, constant::DW_CFA_offset_extended.0));
+                    try!(leb128::write_u64(w, register));
+                }
+                try!(leb128::write_u64(w, offset));
+            }
+            CallFrameInstruction::OffsetSf { register, offset } => {
+                try!(write_u8(w, constant::DW_CFA_offset_extended_sf.0));
+                try!(leb128::write_u64(w, register));
+                try!(leb128::write_i64(w, offset));
+            }
+            CallFrameInstruction::ValOffset { register, offset } => {
+                try!(write_u8(w, constant::DW_CFA_val_offset.0));
+                try!(leb128::write_u64(w, register));
+                try!(leb128::write_u64(w, offset));
+            }
+            CallFrameInstruction::ValOffsetSf { register, offset } => {
+                try!(write_u8(w, constant::DW_CFA_val_offset_sf.0));
+                try!(leb128::write_u64(w, register));
+                try!(leb128::write_i64(w, offset));
+            }
+            CallFrameInstruction::Register { register, target } => {
+                try!(write_u8(w, constant::DW_CFA_register.0));
+                try!(leb128::write_u64(w, register));
+                try!(leb128::write_u64(w, target));
+            }
+            CallFrameInstruction::Expression { register, expression } => {
+                try!(write_u8(w, constant::DW_CFA_expression.0));
+                try!(leb128::write_u64(w, register));
+                try!(write_expression(w, expression));
+            }
+            CallFrameInstruction::ValExpression { register, expression } => {
+                try!(write_u8(w, constant::DW_CFA_val_expression.0));
+                try!(leb128::write_u64(w, register));
+                try!(write_expression(w, expression));
+            }
+            CallFrameInstruction::Restore(register) => {
+                if register <= 0x3f {
+                    try!(write_u8(w, constant::DW_CFA_restore.0 | register as u8));
+                } else {
+                    try!(write_u8(w, constant::DW_CFA_restore_extended.0));
+                    try!(leb128::write_u64(w, register));
+                }
+            }
+            CallFrameInstruction::RememberState => {
+                try!(write_u8(w, constant::DW_CFA_remember_state.0))
+            }
+            CallFrameInstruction::RestoreState => {
+                try!(write_u8(w, constant::DW_CFA_restore_state.0))
+            }
+            CallFrameInstruction::ArgsSize(size) => {
+                try!(write_u8(w, constant::DW_CFA_GNU_args_size.0));
+                try!(leb128::write_u64(w, size));
+            }
+            CallFrameInstruction::WindowSave => {
+                try!(write_u8(w, constant::DW_CFA_GNU_window_save.0))
+            }
+            CallFrameInstruction::Nop => try!(write_u8(w, constant::DW_CFA_nop.0)),
+        }
+        Ok(())
+    }
+}
+
+fn write_expression<W: Write>(w: &mut W, expression: &[u8]) -> Result<(), WriteError> {
+    try!(leb128::write_u64(w, expression.len() as u64));
+    try!(w.write_all(expression));
+    Ok(())
+}
+
+fn read_expression<'data>(r: &mut &'data [u8]) -> Result<&'data [u8], ReadError> {
+    let len = try!(leb128::read_u64(r));
+    if len > r.len() as u64 {
+        return Err(ReadError::Invalid);
+    }
+    read_block(r, len as usize)
+}
+
+// An iterator over the instructions of a CIE or FDE.
+#[derive(Debug, Clone)]
+pub struct InstructionIterator<'data, E: Endian> {
+    r: &'data [u8],
+    endian: E,
+    address_size: u8,
+}
+
+impl<'data, E: Endian> InstructionIterator<'data, E> {
+    pub fn new(instructions: &'data [u8], endian: E, address_size: u8) -> Self {
+        InstructionIterator {
+            r: instructions,
+            endian: endian,
+            address_size: address_size,
+        }
+    }
+
+    #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
+    pub fn next(&mut self) -> Result<Option<CallFrameInstruction<'data>>, ReadError> {
+        if self.r.is_empty() {
+            return Ok(None);
+        }
+        CallFrameInstruction::read(&mut self.r, self.endian, self.address_size).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use constant::*;
+    use endian::LittleEndian;
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn frame_entries() {
+        let debug_frame = [
+            // 0x00: CIE version 1
+            0x10, 0x00, 0x00, 0x00,
+            0xff, 0xff, 0xff, 0xff,
+            0x01, 0x00, 0x01, 0x78, 0x10,
+            DW_CFA_def_cfa.0, 0x07, 0x08,
+            DW_CFA_offset.0 | 0x10, 0x01,
+            DW_CFA_nop.0, DW_CFA_nop.0,
+            // 0x14: FDE
+            0x25, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            DW_CFA_advance_loc.0 | 0x04,
+            DW_CFA_def_cfa_offset.0, 0x10,
+            DW_CFA_offset.0 | 0x06, 0x02,
+            DW_CFA_advance_loc1.0, 0x10,
+            DW_CFA_def_cfa_expression.0, 0x02, DW_OP_breg7.0, 0x08,
+            DW_CFA_remember_state.0,
+            DW_CFA_restore.0 | 0x06,
+            DW_CFA_offset_extended_sf.0, 0x03, 0x7e,
+            DW_CFA_nop.0,
+            // 0x3d: CIE version 4 with augmentation data
+            0x11, 0x00, 0x00, 0x00,
+            0xff, 0xff, 0xff, 0xff,
+            0x04, b'z', 0x00, 0x04, 0x00, 0x04, 0x7c, 0x1e, 0x01, 0xaa,
+            DW_CFA_def_cfa.0, 0x1f, 0x00,
+            // 0x52: FDE
+            0x12, 0x00, 0x00, 0x00,
+            0x3d, 0x00, 0x00, 0x00,
+            0x00, 0x20, 0x00, 0x00,
+            0x10, 0x00, 0x00, 0x00,
+            0x00,
+            DW_CFA_set_loc.0, 0x08, 0x20, 0x00, 0x00,
+        ];
+
+        let mut entries = FrameEntryIterator::new(&debug_frame, LittleEndian, 8);
+        let cie = match entries.next().unwrap() {
+            Some(FrameEntry::Cie(cie)) => cie,
+            otherwise => panic!("{:?}", otherwise),
+        };
+        assert_eq!(cie.offset, 0);
+        assert_eq!(cie.version, 1);
+        assert_eq!(cie.augmentation, b"");
+        assert_eq!(cie.address_size, 8);
+        assert_eq!(cie.code_alignment_factor, 1);
+        assert_eq!(cie.data_alignment_factor, -8);
+        assert_eq!(cie.return_address_register, 16);
+        assert_eq!(cie.augmentation_data, None);
+        let mut instructions = cie.instructions(LittleEndian);
+        assert_eq!(instructions.next().unwrap(),
+                   Some(CallFrameInstruction::DefCfa { register: 7, offset: 8 }));
+        assert_eq!(instructions.next().unwrap(),
+                   Some(CallFrameInstruction::Offset { register: 16, offset: 1 }));
+        assert_eq!(instructions.next().unwrap(), Some(CallFrameInstruction::Nop));
+        assert_eq!(instructions.next().unwrap(), Some(CallFrameInstruction::Nop));
+        assert_eq!(instructions.next().unwrap(), None);
+        assert_eq!(entries.offset(), 0x14);
+
+        let (fde, fde_cie) = match entries.next().unwrap() {
+            Some(FrameEntry::Fde(fde, cie)) => (fde, cie),
+            otherwise => panic!("{:?}", otherwise),
+        };
+        assert_eq!(fde_cie, cie);
+        assert_eq!(fde.offset, 0x14);
+        assert_eq!(fde.cie_offset, 0);
+        assert_eq!((fde.initial_location, fde.address_range), (0x1000, 0x20));
+        assert!(fde.contains(0x101f));
+        assert!(!fde.contains(0x1020));
+        let mut instructions = fde.instructions(LittleEndian, &cie);
+        for instruction in &[
+            CallFrameInstruction::AdvanceLoc(4),
+            CallFrameInstruction::DefCfaOffset(16),
+            CallFrameInstruction::Offset { register: 6, offset: 2 },
+            CallFrameInstruction::AdvanceLoc(0x10),
+            CallFrameInstruction::DefCfaExpression(&[DW_OP_breg7.0, 0x08]),
+            CallFrameInstruction::RememberState,
+            CallFrameInstruction::Restore(6),
+            CallFrameInstruction::OffsetSf { register: 3, offset: -2 },
+            CallFrameInstruction::Nop,
+        ] {
+            assert_eq!(instructions.next().unwrap(), Some(*instruction));
+        }
+        assert_eq!(instructions.next().unwrap(), None);
+
+        let cie = match entries.next().unwrap() {
+            Some(FrameEntry::Cie(cie)) => cie,
+            otherwise => panic!("{:?}", otherwise),
+        };
+        assert_eq!(cie.offset, 0x3d);
+        assert_eq!(cie.version, 4);
+        assert_eq!(cie.augmentation, b"z");
+        assert_eq!((cie.address_size, cie.segment_size), (4, 0));
+        assert_eq!(cie.code_alignment_factor, 4);
+        assert_eq!(cie.data_alignment_factor, -4);
+        assert_eq!(cie.return_address_register, 30);
+        assert_eq!(cie.augmentation_data, Some(&[0xaa][..]));
+
+        let fde = match entries.next().unwrap() {
+            Some(FrameEntry::Fde(fde, _)) => fde,
+            otherwise => panic!("{:?}", otherwise),
+        };
+        assert_eq!(fde.cie_offset, 0x3d);
+        assert_eq!((fde.initial_location, fde.address_range), (0x2000, 0x10));
+        assert_eq!(fde.augmentation_data, Some(&[][..]));
+        let mut instructions = fde.instructions(LittleEndian, &cie);
+        assert_eq!(instructions.next().unwrap(), Some(CallFrameInstruction::SetLoc(0x2008)));
+        assert_eq!(instructions.next().unwrap(), None);
+
+        assert!(entries.next().unwrap().is_none());
+
+        // The offset of an FDE isn't a CIE.
+        assert!(Cie::read(&debug_frame, 0x14, LittleEndian, 8).is_err());
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn rows() {
+        let debug_frame = [
+            // 0x00: CIE
+            0x10, 0x00, 0x00, 0x00,
+            0xff, 0xff, 0xff, 0xff,
+            0x01, 0x00, 0x01, 0x78, 0x10,
+            DW_CFA_def_cfa.0, 0x07, 0x08,
+            DW_CFA_offset.0 | 0x10, 0x01,
+            DW_CFA_nop.0, DW_CFA_nop.0,
+            // 0x14: FDE
+            0x25, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x10, 0x00, 0x00,
+            0x20, 0x00, 0x00, 0x00,
+            DW_CFA_advance_loc.0 | 0x04,
+            DW_CFA_def_cfa_offset.0, 0x10,
+            DW_CFA_offset.0 | 0x06, 0x02,
+            DW_CFA_advance_loc.0 | 0x04,
+            DW_CFA_remember_state.0,
+            DW_CFA_def_cfa_expression.0, 0x02, DW_OP_breg7.0, 0x08,
+            DW_CFA_restore.0 | 0x06,
+            DW_CFA_offset_extended_sf.0, 0x03, 0x7e,
+            DW_CFA_advance_loc.0 | 0x08,
+            DW_CFA_restore_state.0,
+            DW_CFA_set_loc.0, 0x18, 0x10, 0x00, 0x00,
+            DW_CFA_def_cfa_register.0, 0x06,
+            DW_CFA_nop.0,
+        ];
+
+        let mut entries = FrameEntryIterator::new(&debug_frame, LittleEndian, 4);
+        entries.next().unwrap();
+        let (fde, cie) = match entries.next().unwrap() {
+            Some(FrameEntry::Fde(fde, cie)) => (fde, cie),
+            otherwise => panic!("{:?}", otherwise),
+        };
+        let rows = fde.rows(&cie, LittleEndian).unwrap();
+        assert_eq!(rows, vec![
+            UnwindRow {
+                address: 0x1000,
+                end: 0x1004,
+                cfa: CfaRule::RegisterOffset { register: 7, offset: 8 },
+                registers: vec![(16, RegisterRule::Offset(-8))],
+            },
+            UnwindRow {
+                address: 0x1004,
+                end: 0x1008,
+                cfa: CfaRule::RegisterOffset { register: 7, offset: 16 },
+                registers: vec![(6, RegisterRule::Offset(-16)), (16, RegisterRule::Offset(-8))],
+            },
+            UnwindRow {
+                address: 0x1008,
+                end: 0x1010,
+                cfa: CfaRule::Expression(&[DW_OP_breg7.0, 0x08]),
+                registers: vec![(3, RegisterRule::Offset(16)), (16, RegisterRule::Offset(-8))],
+            },
+            UnwindRow {
+                address: 0x1010,
+                end: 0x1018,
+                cfa: CfaRule::RegisterOffset { register: 7, offset: 16 },
+                registers: vec![(6, RegisterRule::Offset(-16)), (16, RegisterRule::Offset(-8))],
+            },
+            UnwindRow {
+                address: 0x1018,
+                end: 0x1020,
+                cfa: CfaRule::RegisterOffset { register: 6, offset: 16 },
+                registers: vec![(6, RegisterRule::Offset(-16)), (16, RegisterRule::Offset(-8))],
+            },
+        ]);
+        assert_eq!(rows[1].register(6), Some(RegisterRule::Offset(-16)));
+        assert_eq!(rows[1].register(3), None);
+
+        // DW_CFA_def_cfa_offset needs a CFA register.
+        let cie = Cie {
+            initial_instructions: &[DW_CFA_def_cfa_offset.0, 0x08],
+            ..cie
+        };
+        match fde.rows(&cie, LittleEndian) {
+            Err(ReadError::Invalid) => {}
+            otherwise => panic!("{:?}", otherwise),
+        }
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn unsupported() {
+        let debug_frame = [
+            0x0c, 0x00, 0x00, 0x00,
+            0xff, 0xff, 0xff, 0xff,
+            0x01, b'e', b'h', 0x00, 0x01, 0x78, 0x10, 0x00,
+        ];
+        match Cie::read(&debug_frame, 0, LittleEndian, 8) {
+            Err(ReadError::Unsupported) => {}
+            otherwise => panic!("{:?}", otherwise),
+        }
+
+        let debug_frame = [
+            0x08, 0x00, 0x00, 0x00,
+            0xff, 0xff, 0xff, 0xff,
+            0x02, 0x00, 0x01, 0x78,
+        ];
+        match Cie::read(&debug_frame, 0, LittleEndian, 8) {
+            Err(ReadError::UnsupportedVersion(2)) => {}
+            otherwise => panic!("{:?}", otherwise),
+        }
+
+        let mut instructions = InstructionIterator::new(&[0x3f], LittleEndian, 8);
+        match instructions.next() {
+            Err(ReadError::Unsupported) => {}
+            otherwise => panic!("{:?}", otherwise),
+        }
+    }
+
+    #[test]
+    fn write_instructions() {
+        let instructions = [
+            CallFrameInstruction::SetLoc(0x1000),
+            CallFrameInstruction::AdvanceLoc(0x3f),
+            CallFrameInstruction::AdvanceLoc(0x40),
+            CallFrameInstruction::AdvanceLoc(0x100),
+            CallFrameInstruction::AdvanceLoc(0x10000),
+            CallFrameInstruction::DefCfa { register: 7, offset: 8 },
+            CallFrameInstruction::DefCfaSf { register: 7, offset: -2 },
+            CallFrameInstruction::DefCfaRegister(6),
+            CallFrameInstruction::DefCfaOffset(0x80),
+            CallFrameInstruction::DefCfaOffsetSf(-1),
+            CallFrameInstruction::DefCfaExpression(&[0x77, 0x08]),
+            CallFrameInstruction::Undefined(16),
+            CallFrameInstruction::SameValue(3),
+            CallFrameInstruction::Offset { register: 16, offset: 1 },
+            CallFrameInstruction::Offset { register: 0x40, offset: 2 },
+            CallFrameInstruction::OffsetSf { register: 6, offset: -3 },
+            CallFrameInstruction::ValOffset { register: 6, offset: 4 },
+            CallFrameInstruction::ValOffsetSf { register: 6, offset: -5 },
+            CallFrameInstruction::Register { register: 6, target: 3 },
+            CallFrameInstruction::Expression { register: 6, expression: &[0x70, 0x00] },
+            CallFrameInstruction::ValExpression { register: 6, expression: &[] },
+            CallFrameInstruction::Restore(6),
+            CallFrameInstruction::Restore(0x40),
+            CallFrameInstruction::RememberState,
+            CallFrameInstruction::RestoreState,
+            CallFrameInstruction::ArgsSize(16),
+            CallFrameInstruction::WindowSave,
+            CallFrameInstruction::Nop,
+        ];
+        let mut data = Vec::new();
+        for instruction in &instructions {
+            instruction.write(&mut data, LittleEndian, 8).unwrap();
+        }
+        let mut iter = InstructionIterator::new(&data, LittleEndian, 8);
+        let mut read = Vec::new();
+        while let Some(instruction) = iter.next().unwrap() {
+            read.push(instruction);
+        }
+        assert_eq!(&read[..], &instructions[..]);
+        assert_eq!(data[9], DW_CFA_advance_loc.0 | 0x3f);
+        assert_eq!(data[10], DW_CFA_advance_loc1.0);
+
+        let mut data = Vec::new();
+        assert!(CallFrameInstruction::AdvanceLoc(0x1_0000_0000)
+            .write(&mut data, LittleEndian, 8)
+            .is_err());
+    }
+}
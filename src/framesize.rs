@@ -0,0 +1,220 @@
+use std;
+use std::ops::Deref;
+
+use endian::Endian;
+use frame::{CfaRule, FrameEntry, FrameEntryIterator};
+use read::ReadError;
+use symbols::FunctionIndex;
+use Sections;
+
+// The approximate stack frame size of a function, from the CFA rules of
+// its FDE.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameSize {
+    // The address range of the FDE.
+    pub address: u64,
+    pub size: u64,
+    // The DW_AT_name of the function containing `address`, or an empty name
+    // if it isn't known.
+    pub name: Vec<u8>,
+    // The largest offset of the CFA rules that are a register plus an offset.
+    //
+    // When the CFA is relative to the stack pointer, this is the size of
+    // the frame, including the return address if the call pushes it.
+    pub max_cfa_offset: i64,
+    // True if the frame may be larger than `max_cfa_offset`.
+    //
+    // This is the case when a CFA rule is an expression, or uses a different
+    // register from the first row (usually a frame pointer), since later
+    // changes to the stack pointer aren't described by the rules.
+    pub lower_bound: bool,
+}
+
+// The frame sizes of the functions that have call frame information.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FrameSizes {
+    // Sorted by address.
+    pub functions: Vec<FrameSize>,
+}
+
+impl FrameSizes {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // Compute the frame sizes for the FDEs in .debug_frame, and name them
+    // using the functions in .debug_info.
+    pub fn from_sections<E, S>(
+        sections: &Sections<E, S>,
+        address_size: u8
+    ) -> Result<FrameSizes, ReadError>
+        where E: Endian,
+              S: Deref<Target = [u8]>
+    {
+        let functions = try!(FunctionIndex::from_sections(sections));
+        let mut sizes = FrameSizes::new();
+        try!(sizes.add_entries(sections.frame_entries(address_size), sections.endian, &functions));
+        Ok(sizes)
+    }
+
+    // Add the frame sizes for the FDEs of an iterator.
+    pub fn add_entries<E: Endian>(
+        &mut self,
+        mut entries: FrameEntryIterator<E>,
+        endian: E,
+        functions: &FunctionIndex
+    ) -> Result<(), ReadError> {
+        while let Some(entry) = try!(entries.next()) {
+            let (fde, cie) = match entry {
+                FrameEntry::Fde(fde, cie) => (fde, cie),
+                FrameEntry::Cie(_) => continue,
+            };
+            let mut frame = FrameSize {
+                address: fde.initial_location,
+                size: fde.address_range,
+                name: functions.find(fde.initial_location)
+                    .map(|function| function.name.clone())
+                    .unwrap_or_default(),
+                max_cfa_offset: 0,
+                lower_bound: false,
+            };
+            let mut first_register = None;
+            for row in try!(fde.rows(&cie, endian)) {
+                match row.cfa {
+                    CfaRule::RegisterOffset { register, offset } => {
+                        if *first_register.get_or_insert(register) != register {
+                            frame.lower_bound = true;
+                        }
+                        frame.max_cfa_offset = std::cmp::max(frame.max_cfa_offset, offset);
+                    }
+                    CfaRule::Expression(_) => frame.lower_bound = true,
+                }
+            }
+            self.functions.push(frame);
+        }
+        self.functions.sort_by_key(|frame| frame.address);
+        Ok(())
+    }
+}
+
+// One line per function: the address and size in hex, the maximum CFA
+// offset (followed by `+` if it is a lower bound), and the name.
+impl std::fmt::Display for FrameSizes {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for frame in &self.functions {
+            try!(writeln!(f,
+                          "{:x} {:x} {}{} {}",
+                          frame.address,
+                          frame.size,
+                          frame.max_cfa_offset,
+                          if frame.lower_bound { "+" } else { "" },
+                          String::from_utf8_lossy(&frame.name)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use abbrev::*;
+    use constant::*;
+    use endian::LittleEndian;
+    use unit::*;
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn frame_sizes() {
+        let abbrevs = AbbrevVec::new(vec![
+            Abbrev {
+                code: 1,
+                tag: DW_TAG_compile_unit,
+                children: true,
+                attributes: vec![],
+            },
+            Abbrev {
+                code: 2,
+                tag: DW_TAG_subprogram,
+                children: false,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                    AbbrevAttribute::new(DW_AT_low_pc, DW_FORM_addr),
+                    AbbrevAttribute::new(DW_AT_high_pc, DW_FORM_data1),
+                ],
+            },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrevs.write(&mut debug_abbrev).unwrap();
+        let abbrev = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+        let data = [
+            1,
+            2, b'f', 0, 0x00, 0x10, 0x00, 0x00, 0x20,
+            2, b'g', 0, 0x00, 0x20, 0x00, 0x00, 0x10,
+            0,
+        ];
+        let unit = CompilationUnit {
+            common: UnitCommon {
+                endian: LittleEndian,
+                address_size: 4,
+                data: &data,
+                ..Default::default()
+            },
+        };
+        let sections = Sections::new(LittleEndian);
+        let mut functions = FunctionIndex::new();
+        functions.add_unit(&sections, &unit, &abbrev).unwrap();
+
+        let debug_frame = [
+            // 0x00: CIE
+            0x10, 0x00, 0x00, 0x00,
+            0xff, 0xff, 0xff, 0xff,
+            0x01, 0x00, 0x01, 0x78, 0x10,
+            DW_CFA_def_cfa.0, 0x07, 0x08,
+            DW_CFA_offset.0 | 0x10, 0x01,
+            DW_CFA_nop.0, DW_CFA_nop.0,
+            // 0x14: FDE for f, which adjusts the stack pointer
+            0x18, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x10, 0x00, 0x00,
+            0x20, 0x00, 0x00, 0x00,
+            DW_CFA_advance_loc.0 | 0x01,
+            DW_CFA_def_cfa_offset.0, 0x10,
+            DW_CFA_advance_loc.0 | 0x03,
+            DW_CFA_def_cfa_offset.0, 0x40,
+            DW_CFA_advance_loc.0 | 0x18,
+            DW_CFA_def_cfa_offset.0, 0x08,
+            DW_CFA_nop.0, DW_CFA_nop.0, DW_CFA_nop.0,
+            // 0x30: FDE for g, which uses a frame pointer
+            0x14, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x20, 0x00, 0x00,
+            0x10, 0x00, 0x00, 0x00,
+            DW_CFA_advance_loc.0 | 0x01,
+            DW_CFA_def_cfa_offset.0, 0x10,
+            DW_CFA_advance_loc.0 | 0x03,
+            DW_CFA_def_cfa_register.0, 0x06,
+            DW_CFA_nop.0, DW_CFA_nop.0,
+        ];
+        let entries = FrameEntryIterator::new(&debug_frame, LittleEndian, 4);
+
+        let mut sizes = FrameSizes::new();
+        sizes.add_entries(entries, LittleEndian, &functions).unwrap();
+        assert_eq!(sizes.functions, vec![
+            FrameSize {
+                address: 0x1000,
+                size: 0x20,
+                name: b"f".to_vec(),
+                max_cfa_offset: 0x40,
+                lower_bound: false,
+            },
+            FrameSize {
+                address: 0x2000,
+                size: 0x10,
+                name: b"g".to_vec(),
+                max_cfa_offset: 0x10,
+                lower_bound: true,
+            },
+        ]);
+        assert_eq!(sizes.to_string(), "1000 20 64 f\n2000 10 16+ g\n");
+    }
+}
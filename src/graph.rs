@@ -0,0 +1,329 @@
+use std::collections::HashSet;
+use std::io::Write;
+
+use constant;
+use die::*;
+use endian::Endian;
+use read::ReadError;
+use unit::UnitCommon;
+use write::WriteError;
+
+// A graph of entries, for visualizing the entry tree and the references
+// between entries.
+//
+// Nodes are entries, identified by their section offset. Edges are either
+// from a parent to a child, or from an entry to the target of one of its
+// reference attributes.
+#[derive(Debug, Default)]
+pub struct Graph<'data> {
+    pub nodes: Vec<Node<'data>>,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node<'data> {
+    pub offset: usize,
+    pub tag: constant::DwTag,
+    pub name: Option<&'data [u8]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    Child,
+    Reference(constant::DwAt),
+}
+
+impl<'data> Graph<'data> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // Add the entries of a unit to the graph.
+    //
+    // `entries` must be an iterator for `unit`, such as the one returned by
+    // `CompilationUnit::entries`. Units can be added to the same graph so
+    // that references between units are shown.
+    pub fn add_entries<'a, E: Endian>(
+        &mut self,
+        unit: &UnitCommon<'data, E>,
+        mut entries: DieIterator<'a, 'data, E>,
+        context: &AttributeContext<'data>
+    ) -> Result<(), ReadError> {
+        // The offsets of the entries whose children are being read, so that
+        // deeply nested entries don't overflow the call stack.
+        let mut parents = Vec::new();
+        while let Some(die) = try!(entries.next()) {
+            if die.is_null() {
+                parents.pop();
+                continue;
+            }
+            self.add_entry(die, parents.last().cloned(), unit, context);
+            if die.children {
+                parents.push(die.offset);
+            }
+        }
+        Ok(())
+    }
+
+    fn add_entry<E: Endian>(
+        &mut self,
+        die: &Die<'data>,
+        parent: Option<usize>,
+        unit: &UnitCommon<'data, E>,
+        context: &AttributeContext<'data>
+    ) {
+        self.nodes.push(Node {
+            offset: die.offset,
+            tag: die.tag,
+            name: die.attr(constant::DW_AT_name).and_then(|name| name.as_string_in(context)),
+        });
+        if let Some(parent) = parent {
+            self.edges.push(Edge {
+                from: parent,
+                to: die.offset,
+                kind: EdgeKind::Child,
+            });
+        }
+        for attribute in &die.attributes {
+            let to = match attribute.data {
+                // DW_AT_sibling duplicates the tree structure.
+                AttributeData::Ref(_) if attribute.at == constant::DW_AT_sibling => continue,
+                AttributeData::Ref(offset) => offset.to_section_offset(unit),
+                AttributeData::RefAddress(offset) => offset.0 as usize,
+                _ => continue,
+            };
+            self.edges.push(Edge {
+                from: die.offset,
+                to: to,
+                kind: EdgeKind::Reference(attribute.at),
+            });
+        }
+    }
+
+    // Return the edges whose target is a node in the graph.
+    //
+    // References to entries that haven't been added are omitted from the
+    // output, since GraphML requires all edge targets to be declared.
+    fn resolved_edges(&self) -> Vec<&Edge> {
+        let offsets: HashSet<usize> = self.nodes.iter().map(|node| node.offset).collect();
+        self.edges.iter().filter(|edge| offsets.contains(&edge.to)).collect()
+    }
+
+    // Write the graph in the DOT language used by Graphviz.
+    pub fn write_dot<W: Write>(&self, w: &mut W) -> Result<(), WriteError> {
+        try!(writeln!(w, "digraph dwarf {{"));
+        try!(writeln!(w, "    node [shape=box];"));
+        for node in &self.nodes {
+            let mut label = node.tag.to_string();
+            if let Some(name) = node.name {
+                label.push_str("\\n");
+                label.push_str(&dot_escape(&String::from_utf8_lossy(name)));
+            }
+            try!(writeln!(w, "    die_{:x} [label=\"{}\"];", node.offset, label));
+        }
+        for edge in self.resolved_edges() {
+            match edge.kind {
+                EdgeKind::Child => {
+                    try!(writeln!(w, "    die_{:x} -> die_{:x};", edge.from, edge.to));
+                }
+                EdgeKind::Reference(at) => {
+                    try!(writeln!(w,
+                                  "    die_{:x} -> die_{:x} [style=dashed, label=\"{}\"];",
+                                  edge.from,
+                                  edge.to,
+                                  at));
+                }
+            }
+        }
+        try!(writeln!(w, "}}"));
+        Ok(())
+    }
+
+    // Write the graph in GraphML.
+    //
+    // Nodes have `tag`, `name` and `offset` data, and edges have `kind`
+    // data, which is either `child` or the name of the reference attribute.
+    pub fn write_graphml<W: Write>(&self, w: &mut W) -> Result<(), WriteError> {
+        try!(writeln!(w, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+        try!(writeln!(w, "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">"));
+        try!(writeln!(w,
+                      "  <key id=\"tag\" for=\"node\" attr.name=\"tag\" attr.type=\"string\"/>"));
+        try!(writeln!(w,
+                      "  <key id=\"name\" for=\"node\" attr.name=\"name\" attr.type=\"string\"/>"));
+        try!(writeln!(w,
+                      "  <key id=\"offset\" for=\"node\" attr.name=\"offset\" attr.type=\"long\"/>"));
+        try!(writeln!(w,
+                      "  <key id=\"kind\" for=\"edge\" attr.name=\"kind\" attr.type=\"string\"/>"));
+        try!(writeln!(w, "  <graph id=\"dwarf\" edgedefault=\"directed\">"));
+        for node in &self.nodes {
+            try!(writeln!(w, "    <node id=\"die_{:x}\">", node.offset));
+            try!(writeln!(w, "      <data key=\"tag\">{}</data>", node.tag));
+            if let Some(name) = node.name {
+                try!(writeln!(w,
+                              "      <data key=\"name\">{}</data>",
+                              xml_escape(&String::from_utf8_lossy(name))));
+            }
+            try!(writeln!(w, "      <data key=\"offset\">{}</data>", node.offset));
+            try!(writeln!(w, "    </node>"));
+        }
+        for edge in self.resolved_edges() {
+            let kind = match edge.kind {
+                EdgeKind::Child => "child".to_string(),
+                EdgeKind::Reference(at) => at.to_string(),
+            };
+            try!(writeln!(w,
+                          "    <edge source=\"die_{:x}\" target=\"die_{:x}\">",
+                          edge.from,
+                          edge.to));
+            try!(writeln!(w, "      <data key=\"kind\">{}</data>", kind));
+            try!(writeln!(w, "    </edge>"));
+        }
+        try!(writeln!(w, "  </graph>"));
+        try!(writeln!(w, "</graphml>"));
+        Ok(())
+    }
+}
+
+fn dot_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use abbrev::*;
+    use constant::*;
+    use endian::*;
+    use offset::*;
+    use testing;
+    use unit::*;
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn graph() {
+        let mut abbrev_hash = AbbrevHash::new();
+        abbrev_hash.insert(Abbrev {
+            code: 1,
+            tag: DW_TAG_compile_unit,
+            children: true,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+            ],
+        });
+        abbrev_hash.insert(Abbrev {
+            code: 2,
+            tag: DW_TAG_variable,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                AbbrevAttribute::new(DW_AT_type, DW_FORM_ref4),
+            ],
+        });
+        abbrev_hash.insert(Abbrev {
+            code: 3,
+            tag: DW_TAG_base_type,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+            ],
+        });
+
+        let header = CompilationUnit {
+            common: UnitCommon { endian: LittleEndian, ..Default::default() },
+        };
+        // The unit is at offset 0, so section offsets are also unit offsets.
+        let data_offset = header.data_offset();
+        let mut data = Vec::new();
+        Die::new(0, 1, DW_TAG_compile_unit, true, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(b"a<b>.c") },
+        ]).write(&mut data, &header.common, &abbrev_hash).unwrap();
+        let variable = data_offset + data.len();
+        // The variable entry is 7 bytes, and the base type follows it.
+        let base_type = variable + 7;
+        Die::new(0, 2, DW_TAG_variable, false, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(b"v") },
+            Attribute { at: DW_AT_type, data: AttributeData::Ref(UnitOffset(base_type as u64)) },
+        ]).write(&mut data, &header.common, &abbrev_hash).unwrap();
+        assert_eq!(data_offset + data.len(), base_type);
+        Die::new(0, 3, DW_TAG_base_type, false, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(b"int") },
+        ]).write(&mut data, &header.common, &abbrev_hash).unwrap();
+        Die::write_null(&mut data).unwrap();
+        let unit = CompilationUnit {
+            common: UnitCommon { data: &data, ..header.common },
+        };
+
+        let mut graph = Graph::new();
+        let context = AttributeContext::new(&unit.common, &[], &[]);
+        graph.add_entries(&unit.common, unit.entries(&abbrev_hash), &context).unwrap();
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(graph.nodes[2], Node {
+            offset: base_type,
+            tag: DW_TAG_base_type,
+            name: Some(b"int"),
+        });
+        assert_eq!(graph.edges, vec![
+            Edge { from: data_offset, to: variable, kind: EdgeKind::Child },
+            Edge { from: variable, to: base_type, kind: EdgeKind::Reference(DW_AT_type) },
+            Edge { from: data_offset, to: base_type, kind: EdgeKind::Child },
+        ]);
+
+        let mut dot = Vec::new();
+        graph.write_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.contains("[label=\"compile_unit\\na<b>.c\"]"));
+        assert!(dot.contains(&format!("die_{:x} -> die_{:x} [style=dashed, label=\"type\"];",
+                                      variable, base_type)));
+
+        let mut graphml = Vec::new();
+        graph.write_graphml(&mut graphml).unwrap();
+        let graphml = String::from_utf8(graphml).unwrap();
+        assert!(graphml.contains("<data key=\"name\">a&lt;b&gt;.c</data>"));
+        assert_eq!(graphml.matches("<edge ").count(), 3);
+    }
+
+    #[test]
+    fn nested_entries() {
+        let sections = testing::nested_entries(LittleEndian, 100_000);
+        let unit = sections.compilation_units().next().unwrap().unwrap();
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let context = sections.attribute_context(&unit.common);
+        let mut graph = Graph::new();
+        graph.add_entries(&unit.common, unit.entries(&abbrev), &context).unwrap();
+        assert_eq!(graph.nodes.len(), 100_001);
+        assert_eq!(graph.edges.len(), 100_000);
+        assert!(graph.edges.windows(2).all(|edges| edges[0].to == edges[1].from));
+    }
+}
@@ -0,0 +1,757 @@
+use std::io::Write;
+
+use build::{BuildValue, SectionsBuilder};
+use constant;
+use die::AttributeData;
+use endian::Endian;
+use frame::CallFrameInstruction;
+use leb128;
+use line::{FileEntry, LineProgram};
+use write::*;
+use Sections;
+
+// A function in JIT compiled code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JitFunction {
+    pub name: Vec<u8>,
+    pub address: u64,
+    pub size: u64,
+}
+
+// A row of the line table for JIT compiled code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JitLine {
+    pub address: u64,
+    // The index returned by `JitDebugInfo::add_file`.
+    pub file: u64,
+    pub line: u64,
+}
+
+// The call frame instructions of a function in JIT compiled code.
+//
+// The instructions describe the changes to the rules of the CIE within
+// the function, and advances are relative to the start of the function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JitFrame {
+    // The address of the function.
+    pub address: u64,
+    pub instructions: Vec<CallFrameInstruction<'static>>,
+}
+
+// The CIE that is shared by the FDEs of all functions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JitCie {
+    pub code_alignment_factor: u64,
+    pub data_alignment_factor: i64,
+    pub return_address_register: u64,
+    // The rules at the start of each function.
+    pub initial_instructions: Vec<CallFrameInstruction<'static>>,
+}
+
+impl JitCie {
+    // The CIE for the rules at the entry of a function that was called by
+    // the call instruction of `machine`, or `None` if the machine isn't
+    // known.
+    pub fn for_machine(machine: u16) -> Option<JitCie> {
+        let (code_alignment_factor, data_alignment_factor, return_address_register, instructions) =
+            match machine {
+                // x86: the CFA is %esp + 4, and the return address is at CFA - 4.
+                3 => (1, -4, 8, vec![
+                    CallFrameInstruction::DefCfa { register: 4, offset: 4 },
+                    CallFrameInstruction::Offset { register: 8, offset: 1 },
+                ]),
+                // x86-64: the CFA is %rsp + 8, and the return address is at CFA - 8.
+                62 => (1, -8, 16, vec![
+                    CallFrameInstruction::DefCfa { register: 7, offset: 8 },
+                    CallFrameInstruction::Offset { register: 16, offset: 1 },
+                ]),
+                // AArch64: the CFA is sp, and the return address is in x30.
+                183 => (4, -8, 30, vec![CallFrameInstruction::DefCfa { register: 31, offset: 0 }]),
+                _ => return None,
+            };
+        Some(JitCie {
+            code_alignment_factor: code_alignment_factor,
+            data_alignment_factor: data_alignment_factor,
+            return_address_register: return_address_register,
+            initial_instructions: instructions,
+        })
+    }
+}
+
+// Generates the debug info for JIT compiled code.
+//
+// This produces a single compilation unit containing a subprogram for
+// each function, and a line program with a sequence for each function.
+// `write_elf` wraps the sections in an ELF image, which can be given to a
+// debugger through the GDB JIT interface (`__jit_debug_register_code`).
+//
+// .debug_frame contains a CIE, and an FDE for each function. An FDE only
+// has the rules of the CIE unless `add_frame` is called for its function,
+// so the instructions must be added for any function that changes the
+// stack pointer or saves registers. If the CIE isn't known for the machine
+// and isn't set with `set_cie`, then there is no call frame information,
+// and debuggers must unwind JIT frames using the frame pointer.
+#[derive(Debug, Clone)]
+pub struct JitDebugInfo<E: Endian> {
+    endian: E,
+    address_size: u8,
+    machine: u16,
+    name: Vec<u8>,
+    comp_dir: Vec<u8>,
+    files: Vec<Vec<u8>>,
+    functions: Vec<JitFunction>,
+    lines: Vec<JitLine>,
+    cie: Option<JitCie>,
+    frames: Vec<JitFrame>,
+}
+
+impl<E: Endian> JitDebugInfo<E> {
+    // `machine` is the ELF `e_machine` of the code, such as 62 for x86-64.
+    // It is also used to choose the CIE. `name` is the name of the
+    // compilation unit.
+    pub fn new(endian: E, address_size: u8, machine: u16, name: &[u8]) -> Self {
+        JitDebugInfo {
+            endian: endian,
+            address_size: address_size,
+            machine: machine,
+            name: name.to_vec(),
+            comp_dir: Vec::new(),
+            files: Vec::new(),
+            functions: Vec::new(),
+            lines: Vec::new(),
+            cie: JitCie::for_machine(machine),
+            frames: Vec::new(),
+        }
+    }
+
+    // Set the CIE, replacing the one for the machine.
+    pub fn set_cie(&mut self, cie: JitCie) {
+        self.cie = Some(cie);
+    }
+
+    // Set the DW_AT_comp_dir of the compilation unit. Relative file paths
+    // are relative to this.
+    pub fn set_comp_dir(&mut self, comp_dir: &[u8]) {
+        self.comp_dir = comp_dir.to_vec();
+    }
+
+    // Add a source file, and return its index for use in `JitLine`.
+    //
+    // Identical paths are only added once.
+    pub fn add_file(&mut self, path: &[u8]) -> u64 {
+        let index = match self.files.iter().position(|file| &file[..] == path) {
+            Some(index) => index,
+            None => {
+                self.files.push(path.to_vec());
+                self.files.len() - 1
+            }
+        };
+        // Index 0 is the compilation unit name.
+        index as u64 + 1
+    }
+
+    pub fn add_function(&mut self, name: &[u8], address: u64, size: u64) {
+        self.functions.push(JitFunction {
+            name: name.to_vec(),
+            address: address,
+            size: size,
+        });
+    }
+
+    // Add a row of the line table. Each row must be within a function,
+    // and applies until the next row or the end of the function.
+    pub fn add_line(&mut self, address: u64, file: u64, line: u64) {
+        self.lines.push(JitLine {
+            address: address,
+            file: file,
+            line: line,
+        });
+    }
+
+    // Set the call frame instructions for the function at `address`,
+    // replacing any that were previously set.
+    pub fn add_frame(&mut self, address: u64, instructions: &[CallFrameInstruction<'static>]) {
+        self.frames.retain(|frame| frame.address != address);
+        self.frames.push(JitFrame {
+            address: address,
+            instructions: instructions.to_vec(),
+        });
+    }
+
+    // The functions, sorted by address.
+    fn sorted_functions(&self) -> Vec<&JitFunction> {
+        let mut functions: Vec<&JitFunction> = self.functions.iter().collect();
+        functions.sort_by_key(|function| (function.address, function.size));
+        functions
+    }
+
+    // Write .debug_abbrev, .debug_info, .debug_line, .debug_str and
+    // .debug_frame.
+    pub fn sections(&self) -> Result<Sections<E>, WriteError> {
+        let functions = self.sorted_functions();
+        let mut builder = SectionsBuilder::new(self.endian);
+        let root = builder.add_unit(4, self.address_size, 4, constant::DW_TAG_compile_unit);
+        let name = builder.add_string(&self.name);
+        builder.add_attribute(root,
+                              constant::DW_AT_name,
+                              constant::DW_FORM_strp,
+                              BuildValue::Data(AttributeData::StringOffset(name)));
+        if !self.comp_dir.is_empty() {
+            let comp_dir = builder.add_string(&self.comp_dir);
+            builder.add_attribute(root,
+                                  constant::DW_AT_comp_dir,
+                                  constant::DW_FORM_strp,
+                                  BuildValue::Data(AttributeData::StringOffset(comp_dir)));
+        }
+        builder.add_attribute(root,
+                              constant::DW_AT_stmt_list,
+                              constant::DW_FORM_sec_offset,
+                              BuildValue::Data(AttributeData::SecOffset(0)));
+        if let Some((begin, end)) = code_range(&functions) {
+            add_pc_range(&mut builder, root, begin, end);
+        }
+        for function in &functions {
+            let entry = builder.add_entry(root, constant::DW_TAG_subprogram);
+            let name = builder.add_string(&function.name);
+            builder.add_attribute(entry,
+                                  constant::DW_AT_name,
+                                  constant::DW_FORM_strp,
+                                  BuildValue::Data(AttributeData::StringOffset(name)));
+            add_pc_range(&mut builder,
+                         entry,
+                         function.address,
+                         function.address.saturating_add(function.size));
+        }
+        let (mut sections, _) = try!(builder.write());
+
+        let data = try!(self.line_opcodes(&functions));
+        let mut files = vec![FileEntry {
+                                 path: &self.name,
+                                 ..Default::default()
+                             }];
+        files.extend(self.files.iter().map(|path| {
+            FileEntry {
+                path: path,
+                ..Default::default()
+            }
+        }));
+        let program = LineProgram {
+            offset: 0,
+            endian: self.endian,
+            version: 4,
+            address_size: self.address_size,
+            offset_size: 4,
+            address_step: 1,
+            operation_range: 1,
+            default_statement: true,
+            line_base: -5,
+            line_range: 14,
+            opcode_base: 13,
+            standard_opcode_lengths: &[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1],
+            include_directories: vec![&self.comp_dir],
+            files: files,
+            data: &data,
+        };
+        try!(program.write(&mut sections.debug_line));
+        try!(self.write_frames(&mut sections.debug_frame, &functions));
+        Ok(sections)
+    }
+
+    // Write a CIE, and an FDE for each function.
+    fn write_frames(&self, w: &mut Vec<u8>, functions: &[&JitFunction]) -> Result<(), WriteError> {
+        for frame in &self.frames {
+            if !functions.iter().any(|function| function.address == frame.address) {
+                return Err(WriteError::Invalid(format!("frame address 0x{:x} is not a function",
+                                                       frame.address)));
+            }
+        }
+        let cie = match self.cie {
+            Some(ref cie) => cie,
+            None if self.frames.is_empty() => return Ok(()),
+            None => {
+                return Err(WriteError::Unsupported(format!("call frame information for machine {}",
+                                                           self.machine)))
+            }
+        };
+        let mut data = Vec::new();
+        // The CIE_id, version, empty augmentation, address size and segment size.
+        try!(self.endian.write_u32(&mut data, u32::max_value()));
+        try!(data.write_all(&[4, 0, self.address_size, 0]));
+        try!(leb128::write_u64(&mut data, cie.code_alignment_factor));
+        try!(leb128::write_i64(&mut data, cie.data_alignment_factor));
+        try!(leb128::write_u64(&mut data, cie.return_address_register));
+        for instruction in &cie.initial_instructions {
+            try!(instruction.write(&mut data, self.endian, self.address_size));
+        }
+        try!(self.write_frame_entry(w, &mut data));
+        for function in functions {
+            data.clear();
+            // The CIE_pointer is the offset of the CIE.
+            try!(self.endian.write_u32(&mut data, 0));
+            try!(write_address(&mut data, self.endian, self.address_size, function.address));
+            try!(write_address(&mut data, self.endian, self.address_size, function.size));
+            let frame = self.frames.iter().find(|frame| frame.address == function.address);
+            for instruction in frame.iter().flat_map(|frame| &frame.instructions) {
+                try!(instruction.write(&mut data, self.endian, self.address_size));
+            }
+            try!(self.write_frame_entry(w, &mut data));
+        }
+        Ok(())
+    }
+
+    // Write the length and data of a CIE or FDE, padding it with DW_CFA_nop
+    // so that the next entry is aligned to the address size.
+    fn write_frame_entry(&self, w: &mut Vec<u8>, data: &mut Vec<u8>) -> Result<(), WriteError> {
+        let len = align(data.len() as u64 + 4, self.address_size as u64) - 4;
+        if len > u32::max_value() as u64 {
+            return Err(WriteError::Invalid(format!("frame entry length 0x{:x}", len)));
+        }
+        data.resize(len as usize, constant::DW_CFA_nop.0);
+        try!(self.endian.write_u32(w, len as u32));
+        try!(w.write_all(data));
+        Ok(())
+    }
+
+    // Encode a sequence for each function, using only standard opcodes.
+    fn line_opcodes(&self, functions: &[&JitFunction]) -> Result<Vec<u8>, WriteError> {
+        let mut lines = self.lines.clone();
+        lines.sort_by_key(|line| line.address);
+        let mut lines = &lines[..];
+        let mut data = Vec::new();
+        for function in functions {
+            let end = function.address.saturating_add(function.size);
+            let count = lines.iter()
+                .take_while(|line| line.address >= function.address && line.address < end)
+                .count();
+            if count == 0 {
+                continue;
+            }
+            let mut address = function.address;
+            let mut file = 1;
+            let mut line = 1;
+            try!(write_set_address(&mut data, self.endian, self.address_size, address));
+            for row in &lines[..count] {
+                if row.file == 0 || row.file > self.files.len() as u64 {
+                    return Err(WriteError::Invalid(format!("line file index {}", row.file)));
+                }
+                if row.file != file {
+                    try!(write_u8(&mut data, constant::DW_LNS_set_file.0));
+                    try!(leb128::write_u64(&mut data, row.file));
+                    file = row.file;
+                }
+                if row.line != line {
+                    try!(write_u8(&mut data, constant::DW_LNS_advance_line.0));
+                    try!(leb128::write_i64(&mut data, row.line.wrapping_sub(line) as i64));
+                    line = row.line;
+                }
+                if row.address != address {
+                    try!(write_u8(&mut data, constant::DW_LNS_advance_pc.0));
+                    try!(leb128::write_u64(&mut data, row.address - address));
+                    address = row.address;
+                }
+                try!(write_u8(&mut data, constant::DW_LNS_copy.0));
+            }
+            if end != address {
+                try!(write_u8(&mut data, constant::DW_LNS_advance_pc.0));
+                try!(leb128::write_u64(&mut data, end - address));
+            }
+            try!(data.write_all(&[0, 1, constant::DW_LNE_end_sequence.0]));
+            lines = &lines[count..];
+        }
+        if let Some(line) = lines.first() {
+            return Err(WriteError::Invalid(format!("line address 0x{:x} is not in a function",
+                                                   line.address)));
+        }
+        Ok(data)
+    }
+
+    // Write an ELF relocatable object containing the debug sections, a
+    // .text section header for the address range of the functions, and a
+    // symbol for each function.
+    //
+    // The code isn't included; .text has no data, but its address is set
+    // to the address of the code.
+    pub fn write_elf(&self) -> Result<Vec<u8>, WriteError> {
+        let sections = try!(self.sections());
+        let functions = self.sorted_functions();
+        let elf64 = match self.address_size {
+            4 => false,
+            8 => true,
+            _ => return Err(WriteError::Unsupported(format!("address size {}", self.address_size))),
+        };
+        let word = self.address_size;
+        let (text_address, text_size) = match code_range(&functions) {
+            Some((begin, end)) => (begin, end - begin),
+            None => (0, 0),
+        };
+
+        let mut symtab = Vec::new();
+        let mut strtab = vec![0];
+        try!(write_symbol(&mut symtab, self.endian, elf64, 0, 0, 0, 0, 0));
+        for function in &functions {
+            let name = strtab.len() as u32;
+            strtab.extend_from_slice(&function.name);
+            strtab.push(0);
+            try!(write_symbol(&mut symtab,
+                              self.endian,
+                              elf64,
+                              name,
+                              ELF_STB_GLOBAL << 4 | ELF_STT_FUNC,
+                              TEXT_INDEX,
+                              function.address,
+                              function.size));
+        }
+
+        let mut elf_sections = vec![
+            ElfSection::new(".text", ELF_SHT_NOBITS, &[]),
+            ElfSection::new(".debug_abbrev", ELF_SHT_PROGBITS, &sections.debug_abbrev),
+            ElfSection::new(".debug_info", ELF_SHT_PROGBITS, &sections.debug_info),
+            ElfSection::new(".debug_line", ELF_SHT_PROGBITS, &sections.debug_line),
+            ElfSection::new(".debug_str", ELF_SHT_PROGBITS, &sections.debug_str),
+        ];
+        {
+            let text = &mut elf_sections[0];
+            text.flags = ELF_SHF_ALLOC | ELF_SHF_EXECINSTR;
+            text.address = text_address;
+            text.size = text_size;
+            text.align = 16;
+        }
+        if !sections.debug_frame.is_empty() {
+            let mut debug_frame =
+                ElfSection::new(".debug_frame", ELF_SHT_PROGBITS, &sections.debug_frame);
+            debug_frame.align = word as u64;
+            elf_sections.push(debug_frame);
+        }
+        let mut symtab_section = ElfSection::new(".symtab", ELF_SHT_SYMTAB, &symtab);
+        // The index of .strtab, which follows the null section and .symtab,
+        // and of the first global symbol.
+        symtab_section.link = elf_sections.len() as u32 + 2;
+        symtab_section.info = 1;
+        symtab_section.align = word as u64;
+        symtab_section.entsize = if elf64 { 24 } else { 16 };
+        elf_sections.push(symtab_section);
+        elf_sections.push(ElfSection::new(".strtab", ELF_SHT_STRTAB, &strtab));
+        let mut shstrtab = vec![0];
+        for section in &mut elf_sections {
+            section.name = shstrtab.len() as u32;
+            shstrtab.extend_from_slice(section.name_str.as_bytes());
+            shstrtab.push(0);
+        }
+        let shstrtab_name = shstrtab.len() as u32;
+        shstrtab.extend_from_slice(b".shstrtab\0");
+        let mut shstrtab_section = ElfSection::new(".shstrtab", ELF_SHT_STRTAB, &shstrtab);
+        shstrtab_section.name = shstrtab_name;
+        elf_sections.push(shstrtab_section);
+
+        let header_size = if elf64 { 64 } else { 52 };
+        let mut offset = header_size as u64;
+        for section in &mut elf_sections {
+            if section.kind != ELF_SHT_NOBITS {
+                offset = align(offset, section.align);
+                section.offset = offset;
+                offset += section.data.len() as u64;
+            }
+        }
+        let section_headers = align(offset, word as u64);
+
+        let mut data = Vec::new();
+        let endian = self.endian;
+        let mut ident = [0; 16];
+        ident[..4].copy_from_slice(b"\x7fELF");
+        ident[4] = if elf64 { 2 } else { 1 };
+        ident[5] = if is_little_endian(endian) { 1 } else { 2 };
+        ident[6] = 1;
+        try!(data.write_all(&ident));
+        try!(endian.write_u16(&mut data, ELF_ET_REL));
+        try!(endian.write_u16(&mut data, self.machine));
+        try!(endian.write_u32(&mut data, 1));
+        try!(write_offset(&mut data, endian, word, 0));
+        try!(write_offset(&mut data, endian, word, 0));
+        try!(write_offset(&mut data, endian, word, section_headers));
+        try!(endian.write_u32(&mut data, 0));
+        try!(endian.write_u16(&mut data, header_size));
+        try!(endian.write_u16(&mut data, 0));
+        try!(endian.write_u16(&mut data, 0));
+        try!(endian.write_u16(&mut data, if elf64 { 64 } else { 40 }));
+        try!(endian.write_u16(&mut data, elf_sections.len() as u16 + 1));
+        try!(endian.write_u16(&mut data, elf_sections.len() as u16));
+
+        for section in &elf_sections {
+            if section.kind != ELF_SHT_NOBITS {
+                data.resize(section.offset as usize, 0);
+                try!(data.write_all(section.data));
+            }
+        }
+        data.resize(section_headers as usize, 0);
+        try!(ElfSection::new("", 0, &[]).write(&mut data, endian, word));
+        for section in &elf_sections {
+            try!(section.write(&mut data, endian, word));
+        }
+        Ok(data)
+    }
+}
+
+// The range from the start of the first function to the end of the last.
+fn code_range(functions: &[&JitFunction]) -> Option<(u64, u64)> {
+    let begin = functions.iter().map(|function| function.address).min();
+    let end = functions.iter().map(|function| function.address.saturating_add(function.size)).max();
+    match (begin, end) {
+        (Some(begin), Some(end)) => Some((begin, end)),
+        _ => None,
+    }
+}
+
+fn add_pc_range<'data, E: Endian>(
+    builder: &mut SectionsBuilder<'data, E>,
+    entry: ::build::EntryId,
+    begin: u64,
+    end: u64
+) {
+    builder.add_attribute(entry,
+                          constant::DW_AT_low_pc,
+                          constant::DW_FORM_addr,
+                          BuildValue::Data(AttributeData::Address(begin)));
+    builder.add_attribute(entry,
+                          constant::DW_AT_high_pc,
+                          constant::DW_FORM_addr,
+                          BuildValue::Data(AttributeData::Address(end)));
+}
+
+fn write_set_address<W: Write, E: Endian>(
+    w: &mut W,
+    endian: E,
+    address_size: u8,
+    address: u64
+) -> Result<(), WriteError> {
+    try!(write_u8(w, 0));
+    try!(leb128::write_u64(w, 1 + address_size as u64));
+    try!(write_u8(w, constant::DW_LNE_set_address.0));
+    write_offset(w, endian, address_size, address)
+}
+
+const ELF_ET_REL: u16 = 1;
+const ELF_SHT_PROGBITS: u32 = 1;
+const ELF_SHT_SYMTAB: u32 = 2;
+const ELF_SHT_STRTAB: u32 = 3;
+const ELF_SHT_NOBITS: u32 = 8;
+const ELF_SHF_ALLOC: u64 = 0x2;
+const ELF_SHF_EXECINSTR: u64 = 0x4;
+const ELF_STB_GLOBAL: u8 = 1;
+const ELF_STT_FUNC: u8 = 2;
+// The section index of .text.
+const TEXT_INDEX: u16 = 1;
+
+struct ElfSection<'a> {
+    name_str: &'static str,
+    name: u32,
+    kind: u32,
+    flags: u64,
+    address: u64,
+    offset: u64,
+    size: u64,
+    link: u32,
+    info: u32,
+    align: u64,
+    entsize: u64,
+    data: &'a [u8],
+}
+
+impl<'a> ElfSection<'a> {
+    fn new(name: &'static str, kind: u32, data: &'a [u8]) -> Self {
+        ElfSection {
+            name_str: name,
+            name: 0,
+            kind: kind,
+            flags: 0,
+            address: 0,
+            offset: 0,
+            size: data.len() as u64,
+            link: 0,
+            info: 0,
+            align: 1,
+            entsize: 0,
+            data: data,
+        }
+    }
+
+    fn write<W: Write, E: Endian>(&self, w: &mut W, endian: E, word: u8) -> Result<(), WriteError> {
+        try!(endian.write_u32(w, self.name));
+        try!(endian.write_u32(w, self.kind));
+        try!(write_offset(w, endian, word, self.flags));
+        try!(write_offset(w, endian, word, self.address));
+        try!(write_offset(w, endian, word, self.offset));
+        try!(write_offset(w, endian, word, self.size));
+        try!(endian.write_u32(w, self.link));
+        try!(endian.write_u32(w, self.info));
+        try!(write_offset(w, endian, word, self.align));
+        try!(write_offset(w, endian, word, self.entsize));
+        Ok(())
+    }
+}
+
+#[cfg_attr(feature = "clippy", allow(too_many_arguments))]
+fn write_symbol<W: Write, E: Endian>(
+    w: &mut W,
+    endian: E,
+    elf64: bool,
+    name: u32,
+    info: u8,
+    section: u16,
+    value: u64,
+    size: u64
+) -> Result<(), WriteError> {
+    try!(endian.write_u32(w, name));
+    if elf64 {
+        try!(write_u8(w, info));
+        try!(write_u8(w, 0));
+        try!(endian.write_u16(w, section));
+        try!(endian.write_u64(w, value));
+        try!(endian.write_u64(w, size));
+    } else {
+        try!(endian.write_u32(w, value as u32));
+        try!(endian.write_u32(w, size as u32));
+        try!(write_u8(w, info));
+        try!(write_u8(w, 0));
+        try!(endian.write_u16(w, section));
+    }
+    Ok(())
+}
+
+fn align(offset: u64, align: u64) -> u64 {
+    offset.div_ceil(align) * align
+}
+
+fn is_little_endian<E: Endian>(endian: E) -> bool {
+    let mut buf = Vec::new();
+    endian.write_u16(&mut buf, 1).unwrap();
+    buf[0] == 1
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use endian::*;
+    use frame::{CfaRule, FrameEntry, RegisterRule, UnwindRow};
+    use symbols::FunctionIndex;
+
+    fn jit() -> JitDebugInfo<LittleEndian> {
+        let mut jit = JitDebugInfo::new(LittleEndian, 8, 62, b"jit");
+        jit.set_comp_dir(b"/src");
+        let main = jit.add_file(b"main.js");
+        let lib = jit.add_file(b"lib.js");
+        assert_eq!(jit.add_file(b"main.js"), main);
+        jit.add_function(b"g", 0x2000, 0x10);
+        jit.add_function(b"f", 0x1000, 0x20);
+        jit.add_line(0x1000, main, 10);
+        jit.add_line(0x1008, main, 8);
+        jit.add_line(0x1010, lib, 3);
+        jit.add_line(0x2000, lib, 20);
+        jit
+    }
+
+    #[test]
+    fn jit_sections() {
+        let sections = jit().sections().unwrap();
+        let mut units = sections.compilation_units();
+        let unit = units.next().unwrap().unwrap();
+        assert!(units.next().unwrap().is_none());
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+
+        let mut functions = FunctionIndex::new();
+        functions.add_unit(&sections, &unit, &abbrev).unwrap();
+        let functions: Vec<_> = functions.iter()
+            .map(|function| (&function.name[..], function.address, function.size))
+            .collect();
+        assert_eq!(functions, vec![(&b"f"[..], 0x1000, 0x20), (&b"g"[..], 0x2000, 0x10)]);
+
+        let mut lines = sections.lines(&unit, &abbrev).unwrap().unwrap();
+        let mut rows = Vec::new();
+        while let Some((iter, line)) = lines.next().unwrap() {
+            let path = iter.files()[line.file as usize].path;
+            rows.push((line.address, path.to_vec(), line.line, line.sequence_end));
+        }
+        assert_eq!(rows,
+                   vec![(0x1000, b"main.js".to_vec(), 10, false),
+                        (0x1008, b"main.js".to_vec(), 8, false),
+                        (0x1010, b"lib.js".to_vec(), 3, false),
+                        (0x1020, b"lib.js".to_vec(), 3, true),
+                        (0x2000, b"lib.js".to_vec(), 20, false),
+                        (0x2010, b"lib.js".to_vec(), 20, true)]);
+    }
+
+    #[test]
+    fn jit_invalid_lines() {
+        let mut jit = jit();
+        jit.add_line(0x3000, 1, 1);
+        assert!(jit.sections().is_err());
+
+        let mut jit = JitDebugInfo::new(LittleEndian, 8, 62, b"jit");
+        jit.add_function(b"f", 0x1000, 0x20);
+        jit.add_line(0x1000, 1, 1);
+        assert!(jit.sections().is_err());
+    }
+
+    #[test]
+    fn jit_frames() {
+        let mut jit = jit();
+        jit.add_frame(0x1000,
+                      &[CallFrameInstruction::AdvanceLoc(1),
+                        CallFrameInstruction::DefCfaOffset(16),
+                        CallFrameInstruction::Offset { register: 6, offset: 2 },
+                        CallFrameInstruction::AdvanceLoc(3),
+                        CallFrameInstruction::DefCfaRegister(6)]);
+        let sections = jit.sections().unwrap();
+        assert_eq!(sections.debug_frame.len() % 8, 0);
+        let mut entries = sections.frame_entries(8);
+        match entries.next().unwrap() {
+            Some(FrameEntry::Cie(cie)) => {
+                assert_eq!((cie.version, cie.address_size), (4, 8));
+                assert_eq!(cie.return_address_register, 16);
+            }
+            otherwise => panic!("{:?}", otherwise),
+        }
+        let mut fdes = Vec::new();
+        while let Some(entry) = entries.next().unwrap() {
+            match entry {
+                FrameEntry::Fde(fde, cie) => fdes.push(fde.rows(&cie, LittleEndian).unwrap()),
+                otherwise => panic!("{:?}", otherwise),
+            }
+        }
+        let ra = (16, RegisterRule::Offset(-8));
+        assert_eq!(fdes,
+                   vec![vec![UnwindRow {
+                                 address: 0x1000,
+                                 end: 0x1001,
+                                 cfa: CfaRule::RegisterOffset { register: 7, offset: 8 },
+                                 registers: vec![ra],
+                             },
+                             UnwindRow {
+                                 address: 0x1001,
+                                 end: 0x1004,
+                                 cfa: CfaRule::RegisterOffset { register: 7, offset: 16 },
+                                 registers: vec![(6, RegisterRule::Offset(-16)), ra],
+                             },
+                             UnwindRow {
+                                 address: 0x1004,
+                                 end: 0x1020,
+                                 cfa: CfaRule::RegisterOffset { register: 6, offset: 16 },
+                                 registers: vec![(6, RegisterRule::Offset(-16)), ra],
+                             }],
+                        vec![UnwindRow {
+                                 address: 0x2000,
+                                 end: 0x2010,
+                                 cfa: CfaRule::RegisterOffset { register: 7, offset: 8 },
+                                 registers: vec![ra],
+                             }]]);
+
+        let mut invalid = self::jit();
+        invalid.add_frame(0x1001, &[]);
+        assert!(invalid.sections().is_err());
+
+        let mut jit = JitDebugInfo::new(LittleEndian, 8, 0, b"jit");
+        jit.add_function(b"f", 0x1000, 0x20);
+        assert!(jit.sections().unwrap().debug_frame.is_empty());
+        jit.add_frame(0x1000, &[]);
+        assert!(jit.sections().is_err());
+    }
+}
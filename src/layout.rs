@@ -0,0 +1,681 @@
+use std;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::ops::Deref;
+
+use abbrev::AbbrevHash;
+use constant;
+use die::{AttributeData, DieIterator};
+use endian::Endian;
+use location::LocListEntry;
+use ranges::{ListsHeader, RngListEntry};
+use read::*;
+use unit::{UnitBases, UnitCommon};
+use Sections;
+
+// The kind of data in a contribution to a section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContributionKind {
+    CompilationUnit,
+    TypeUnit,
+    AbbrevTable,
+    LineProgram,
+    RangeList,
+    LocationList,
+    // The header and offsets array of the lists of a unit in
+    // .debug_rnglists or .debug_loclists.
+    ListsHeader,
+    // A set of address ranges in .debug_aranges.
+    AddressRanges,
+    // Bytes that aren't part of any other contribution, such as padding
+    // or data that no unit refers to.
+    Unused,
+}
+
+impl ContributionKind {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ContributionKind::CompilationUnit => "compilation_unit",
+            ContributionKind::TypeUnit => "type_unit",
+            ContributionKind::AbbrevTable => "abbrev_table",
+            ContributionKind::LineProgram => "line_program",
+            ContributionKind::RangeList => "range_list",
+            ContributionKind::LocationList => "location_list",
+            ContributionKind::ListsHeader => "lists_header",
+            ContributionKind::AddressRanges => "address_ranges",
+            ContributionKind::Unused => "unused",
+        }
+    }
+}
+
+// The offset of a unit header in .debug_info or .debug_types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnitId {
+    pub section: &'static str,
+    pub offset: usize,
+}
+
+// A range of a section that contains a single unit, table or list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contribution {
+    pub offset: usize,
+    pub size: usize,
+    pub kind: ContributionKind,
+    // The units that the contribution belongs to, sorted by offset.
+    //
+    // Abbrev tables, line programs, range lists, location lists and lists
+    // headers may be shared by many units. This is empty for unused bytes.
+    pub units: Vec<UnitId>,
+}
+
+// The contributions to a section, sorted by offset.
+//
+// Contributions may overlap if the section is corrupt, or if a range
+// list or location list is a suffix of another list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionLayout {
+    pub name: &'static str,
+    pub size: usize,
+    pub contributions: Vec<Contribution>,
+}
+
+// A map of the contributions to each section, as a table of contents
+// for the DWARF in a file.
+//
+// Sections that are empty are omitted.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Layout {
+    pub sections: Vec<SectionLayout>,
+}
+
+// The contributions to a section, keyed by offset so that shared
+// contributions are only added once.
+#[derive(Debug, Default)]
+struct SectionBuilder {
+    contributions: BTreeMap<usize, Contribution>,
+}
+
+impl SectionBuilder {
+    fn add(&mut self, offset: usize, size: usize, kind: ContributionKind, unit: UnitId) {
+        let contribution = self.contributions.entry(offset).or_insert_with(|| {
+            Contribution {
+                offset: offset,
+                size: size,
+                kind: kind,
+                units: Vec::new(),
+            }
+        });
+        if !contribution.units.contains(&unit) {
+            contribution.units.push(unit);
+            contribution.units.sort();
+        }
+    }
+
+    // Add a contribution for the header of each list table in
+    // .debug_rnglists or .debug_loclists, owned by the units of the lists
+    // in the table.
+    //
+    // Headers of tables that contain no referenced lists are left unused.
+    fn add_lists_headers<E: Endian>(&mut self, data: &[u8], endian: E) -> Result<(), ReadError> {
+        let mut offset = 0;
+        while offset < data.len() {
+            let header = try!(ListsHeader::read(data, offset, endian));
+            let size = header.offsets_base() - offset +
+                       header.offset_entry_count as usize * header.offset_size as usize;
+            let units: Vec<UnitId> = self.contributions
+                .range(offset..header.end_offset())
+                .flat_map(|(_, contribution)| contribution.units.iter().cloned())
+                .collect();
+            for unit in units {
+                self.add(offset, size, ContributionKind::ListsHeader, unit);
+            }
+            offset = header.end_offset();
+        }
+        Ok(())
+    }
+
+    fn build(self, name: &'static str, size: usize) -> Option<SectionLayout> {
+        if size == 0 && self.contributions.is_empty() {
+            return None;
+        }
+        let mut contributions = Vec::new();
+        let mut end = 0;
+        for (offset, contribution) in self.contributions {
+            if offset > end {
+                contributions.push(unused(end, offset - end));
+            }
+            end = std::cmp::max(end, offset + contribution.size);
+            contributions.push(contribution);
+        }
+        if size > end {
+            contributions.push(unused(end, size - end));
+        }
+        Some(SectionLayout {
+            name: name,
+            size: size,
+            contributions: contributions,
+        })
+    }
+}
+
+fn unused(offset: usize, size: usize) -> Contribution {
+    Contribution {
+        offset: offset,
+        size: size,
+        kind: ContributionKind::Unused,
+        units: Vec::new(),
+    }
+}
+
+#[derive(Debug, Default)]
+struct LayoutBuilder {
+    debug_info: SectionBuilder,
+    debug_types: SectionBuilder,
+    debug_abbrev: SectionBuilder,
+    debug_line: SectionBuilder,
+    debug_ranges: SectionBuilder,
+    debug_rnglists: SectionBuilder,
+    debug_loc: SectionBuilder,
+    debug_loclists: SectionBuilder,
+    debug_aranges: SectionBuilder,
+}
+
+impl LayoutBuilder {
+    // Add the abbrev table, line program, range lists and location lists
+    // of a unit.
+    //
+    // Lists are in .debug_rnglists and .debug_loclists for DWARF 5 units,
+    // and in .debug_ranges and .debug_loc for earlier versions.
+    fn add_unit<'a, 'data, E, S>(
+        &mut self,
+        sections: &Sections<E, S>,
+        unit: &UnitCommon<E>,
+        id: UnitId,
+        mut entries: DieIterator<'a, 'data, E>
+    ) -> Result<(), ReadError>
+        where E: Endian,
+              S: Deref<Target = [u8]>
+    {
+        let offset = unit.abbrev_offset as usize;
+        let size = try!(abbrev_table_size(&*sections.debug_abbrev, offset));
+        self.debug_abbrev.add(offset, size, ContributionKind::AbbrevTable, id);
+
+        let context = sections.attribute_context(unit);
+        let mut bases = None;
+        while let Some(entry) = try!(entries.next()) {
+            let bases = *bases.get_or_insert_with(|| UnitBases::from_root(unit, entry));
+            for attribute in &entry.attributes {
+                let data = &attribute.data;
+                match attribute.at {
+                    constant::DW_AT_stmt_list => {
+                        let offset = try!(data.as_offset_in(&context).ok_or(ReadError::Invalid));
+                        let size = try!(line_program_size(&*sections.debug_line,
+                                                          offset,
+                                                          unit.endian));
+                        self.debug_line.add(offset, size, ContributionKind::LineProgram, id);
+                    }
+                    constant::DW_AT_ranges if unit.version >= 5 => {
+                        let offset = match *data {
+                            AttributeData::RangeListIndex(index) => {
+                                try!(sections.rnglistx_offset(unit, bases.rnglists_base, index))
+                                    as usize
+                            }
+                            _ => try!(data.as_offset_in(&context).ok_or(ReadError::Invalid)),
+                        };
+                        let size = try!(list_size(&*sections.debug_rnglists, offset, |r| {
+                            RngListEntry::read(r, unit.endian, unit.address_size)
+                                .map(|entry| entry.is_some())
+                        }));
+                        self.debug_rnglists.add(offset, size, ContributionKind::RangeList, id);
+                    }
+                    constant::DW_AT_ranges => {
+                        let offset = try!(data.as_offset_in(&context).ok_or(ReadError::Invalid));
+                        let size = try!(range_list_size(&*sections.debug_ranges,
+                                                        offset,
+                                                        unit.endian,
+                                                        unit.address_size));
+                        self.debug_ranges.add(offset, size, ContributionKind::RangeList, id);
+                    }
+                    at if is_location_attribute(at) && unit.version >= 5 => {
+                        let offset = match *data {
+                            AttributeData::LocationListIndex(index) => {
+                                try!(sections.loclistx_offset(unit, bases.loclists_base, index))
+                                    as usize
+                            }
+                            AttributeData::SecOffset(offset) => offset as usize,
+                            _ => continue,
+                        };
+                        let size = try!(list_size(&*sections.debug_loclists, offset, |r| {
+                            LocListEntry::read(r, unit.endian, unit.address_size)
+                                .map(|entry| entry.is_some())
+                        }));
+                        self.debug_loclists.add(offset, size, ContributionKind::LocationList, id);
+                    }
+                    at if is_location_attribute(at) => {
+                        // DWARF 4 requires DW_FORM_sec_offset, since data4
+                        // and data8 are constants.
+                        let offset = match *data {
+                            AttributeData::SecOffset(offset) => offset as usize,
+                            _ if unit.version >= 4 => continue,
+                            _ => {
+                                match data.as_offset_in(&context) {
+                                    Some(offset) => offset,
+                                    None => continue,
+                                }
+                            }
+                        };
+                        let size = try!(location_list_size(&*sections.debug_loc,
+                                                           offset,
+                                                           unit.endian,
+                                                           unit.address_size));
+                        self.debug_loc.add(offset, size, ContributionKind::LocationList, id);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn add_aranges<E: Endian>(&mut self, debug_aranges: &[u8], endian: E) -> Result<(), ReadError> {
+        let mut r = debug_aranges;
+        while !r.is_empty() {
+            let offset = debug_aranges.len() - r.len();
+            let (offset_size, len) = try!(read_initial_length(&mut r, endian));
+            let mut data = &r[..len];
+            r = &r[len..];
+            let size = debug_aranges.len() - r.len() - offset;
+
+            try!(endian.read_u16(&mut data));
+            let unit_offset = try!(read_offset(&mut data, endian, offset_size)) as usize;
+            let id = UnitId {
+                section: ".debug_info",
+                offset: unit_offset,
+            };
+            self.debug_aranges.add(offset, size, ContributionKind::AddressRanges, id);
+        }
+        Ok(())
+    }
+}
+
+fn abbrev_table_size(debug_abbrev: &[u8], offset: usize) -> Result<usize, ReadError> {
+    if offset >= debug_abbrev.len() {
+        return Err(ReadError::Invalid);
+    }
+    let mut r = &debug_abbrev[offset..];
+    try!(AbbrevHash::read(&mut r));
+    Ok(debug_abbrev.len() - offset - r.len())
+}
+
+fn line_program_size<E: Endian>(
+    debug_line: &[u8],
+    offset: usize,
+    endian: E
+) -> Result<usize, ReadError> {
+    if offset >= debug_line.len() {
+        return Err(ReadError::Invalid);
+    }
+    let mut r = &debug_line[offset..];
+    let (_, len) = try!(read_initial_length(&mut r, endian));
+    Ok(debug_line.len() - offset - r.len() + len)
+}
+
+// Return true if a value of the attribute may be a location list.
+fn is_location_attribute(at: constant::DwAt) -> bool {
+    matches!(
+        at,
+        constant::DW_AT_location |
+        constant::DW_AT_string_length |
+        constant::DW_AT_return_addr |
+        constant::DW_AT_data_member_location |
+        constant::DW_AT_frame_base |
+        constant::DW_AT_segment |
+        constant::DW_AT_static_link |
+        constant::DW_AT_use_location |
+        constant::DW_AT_vtable_elem_location
+    )
+}
+
+// The size of the list at `offset`, reading entries with `read_entry`
+// until it returns false for the end of list entry.
+fn list_size<F>(data: &[u8], offset: usize, mut read_entry: F) -> Result<usize, ReadError>
+    where F: FnMut(&mut &[u8]) -> Result<bool, ReadError>
+{
+    if offset >= data.len() {
+        return Err(ReadError::Invalid);
+    }
+    let mut r = &data[offset..];
+    while try!(read_entry(&mut r)) {}
+    Ok(data.len() - offset - r.len())
+}
+
+// The size of a range list in .debug_ranges, including the end of list
+// entry.
+fn range_list_size<E: Endian>(
+    debug_ranges: &[u8],
+    offset: usize,
+    endian: E,
+    address_size: u8
+) -> Result<usize, ReadError> {
+    list_size(debug_ranges, offset, |r| {
+        let begin = try!(read_address(r, endian, address_size));
+        let end = try!(read_address(r, endian, address_size));
+        Ok(begin != 0 || end != 0)
+    })
+}
+
+// The size of a location list in .debug_loc, including the end of list
+// entry.
+fn location_list_size<E: Endian>(
+    debug_loc: &[u8],
+    offset: usize,
+    endian: E,
+    address_size: u8
+) -> Result<usize, ReadError> {
+    let max = match address_size {
+        1 => 0xff,
+        2 => 0xffff,
+        4 => 0xffff_ffff,
+        _ => u64::max_value(),
+    };
+    list_size(debug_loc, offset, |r| {
+        let begin = try!(read_address(r, endian, address_size));
+        let end = try!(read_address(r, endian, address_size));
+        if begin == 0 && end == 0 {
+            return Ok(false);
+        }
+        if begin != max {
+            // Not a base address selection entry, so skip the expression.
+            let len = try!(endian.read_u16(r));
+            try!(read_block(r, len as usize));
+        }
+        Ok(true)
+    })
+}
+
+impl Layout {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // Find the contributions of all units to the sections.
+    //
+    // Abbrev tables, line programs, range lists and location lists are
+    // found from the references in the units, so any that aren't referenced
+    // are reported as unused.
+    pub fn from_sections<E, S>(sections: &Sections<E, S>) -> Result<Layout, ReadError>
+        where E: Endian,
+              S: Deref<Target = [u8]>
+    {
+        let mut builder = LayoutBuilder::default();
+
+        let mut units = sections.compilation_units();
+        while let Some(unit) = try!(units.next()) {
+            let id = UnitId {
+                section: ".debug_info",
+                offset: unit.common.offset,
+            };
+            builder.debug_info.add(unit.common.offset,
+                                   unit.end_offset() - unit.common.offset,
+                                   ContributionKind::CompilationUnit,
+                                   id);
+            let abbrev = try!(sections.abbrev(&unit.common));
+            try!(builder.add_unit(sections, &unit.common, id, unit.entries(&abbrev)));
+        }
+
+        let mut units = sections.type_units();
+        while let Some(unit) = try!(units.next()) {
+            let id = UnitId {
+                section: ".debug_types",
+                offset: unit.common.offset,
+            };
+            builder.debug_types.add(unit.common.offset,
+                                    unit.end_offset() - unit.common.offset,
+                                    ContributionKind::TypeUnit,
+                                    id);
+            let abbrev = try!(sections.abbrev(&unit.common));
+            try!(builder.add_unit(sections, &unit.common, id, unit.entries(&abbrev)));
+        }
+
+        try!(builder.add_aranges(&*sections.debug_aranges, sections.endian));
+        try!(builder.debug_rnglists.add_lists_headers(&*sections.debug_rnglists, sections.endian));
+        try!(builder.debug_loclists.add_lists_headers(&*sections.debug_loclists, sections.endian));
+
+        let mut layout = Layout::new();
+        let builders = vec![
+            (".debug_info", sections.debug_info.len(), builder.debug_info),
+            (".debug_types", sections.debug_types.len(), builder.debug_types),
+            (".debug_abbrev", sections.debug_abbrev.len(), builder.debug_abbrev),
+            (".debug_line", sections.debug_line.len(), builder.debug_line),
+            (".debug_ranges", sections.debug_ranges.len(), builder.debug_ranges),
+            (".debug_rnglists", sections.debug_rnglists.len(), builder.debug_rnglists),
+            (".debug_loc", sections.debug_loc.len(), builder.debug_loc),
+            (".debug_loclists", sections.debug_loclists.len(), builder.debug_loclists),
+            (".debug_aranges", sections.debug_aranges.len(), builder.debug_aranges),
+        ];
+        for (name, size, builder) in builders {
+            if let Some(section) = builder.build(name, size) {
+                layout.sections.push(section);
+            }
+        }
+        Ok(layout)
+    }
+
+    // Write the layout with one line per section or contribution.
+    pub fn write_text<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        for section in &self.sections {
+            try!(writeln!(w, "{}: 0x{:x} bytes", section.name, section.size));
+            for contribution in &section.contributions {
+                try!(write!(w,
+                            "  0x{:08x} 0x{:08x} {}",
+                            contribution.offset,
+                            contribution.size,
+                            contribution.kind.name()));
+                for unit in &contribution.units {
+                    try!(write!(w, " {}+0x{:x}", unit.section, unit.offset));
+                }
+                try!(writeln!(w));
+            }
+        }
+        Ok(())
+    }
+
+    // Write the layout as a JSON object with a "sections" array.
+    pub fn write_json<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        try!(write!(w, "{{\"sections\":["));
+        for (i, section) in self.sections.iter().enumerate() {
+            if i != 0 {
+                try!(write!(w, ","));
+            }
+            try!(write!(w,
+                        "{{\"name\":\"{}\",\"size\":{},\"contributions\":[",
+                        section.name,
+                        section.size));
+            for (i, contribution) in section.contributions.iter().enumerate() {
+                if i != 0 {
+                    try!(write!(w, ","));
+                }
+                try!(write!(w,
+                            "{{\"offset\":{},\"size\":{},\"kind\":\"{}\",\"units\":[",
+                            contribution.offset,
+                            contribution.size,
+                            contribution.kind.name()));
+                for (i, unit) in contribution.units.iter().enumerate() {
+                    if i != 0 {
+                        try!(write!(w, ","));
+                    }
+                    try!(write!(w,
+                                "{{\"section\":\"{}\",\"offset\":{}}}",
+                                unit.section,
+                                unit.offset));
+                }
+                try!(write!(w, "]}}"));
+            }
+            try!(write!(w, "]}}"));
+        }
+        writeln!(w, "]}}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use abbrev::*;
+    use constant::*;
+    use die::*;
+    use endian::*;
+    use testing;
+    use unit::*;
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn layout() {
+        let mut sections = testing::empty_line_program(LittleEndian);
+        sections.debug_line.extend_from_slice(&[0; 3]);
+        // An address range set for the unit, containing no ranges.
+        sections.debug_aranges = vec![
+            0x14, 0, 0, 0, 2, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        let layout = Layout::from_sections(&sections).unwrap();
+
+        let mut buf = Vec::new();
+        layout.write_text(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "\
+.debug_info: 0x14 bytes
+  0x00000000 0x00000014 compilation_unit .debug_info+0x0
+.debug_abbrev: 0xa bytes
+  0x00000000 0x0000000a abbrev_table .debug_info+0x0
+.debug_line: 0x21 bytes
+  0x00000000 0x0000001e line_program .debug_info+0x0
+  0x0000001e 0x00000003 unused
+.debug_aranges: 0x18 bytes
+  0x00000000 0x00000018 address_ranges .debug_info+0x0
+");
+
+        let layout = Layout {
+            sections: vec![layout.sections[2].clone()],
+        };
+        let mut buf = Vec::new();
+        layout.write_json(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), concat!(
+            r#"{"sections":[{"name":".debug_line","size":33,"contributions":["#,
+            r#"{"offset":0,"size":30,"kind":"line_program","units":[{"section":".debug_info","offset":0}]},"#,
+            r#"{"offset":30,"size":3,"kind":"unused","units":[]}]}]}"#,
+            "\n"));
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn lists() {
+        let abbrev = AbbrevVec::new(vec![
+            Abbrev { code: 1, tag: DW_TAG_compile_unit, children: true, attributes: vec![
+                AbbrevAttribute::new(DW_AT_rnglists_base, DW_FORM_sec_offset),
+                AbbrevAttribute::new(DW_AT_loclists_base, DW_FORM_sec_offset),
+                AbbrevAttribute::new(DW_AT_ranges, DW_FORM_rnglistx),
+            ] },
+            Abbrev { code: 2, tag: DW_TAG_variable, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_location, DW_FORM_loclistx),
+            ] },
+            Abbrev { code: 3, tag: DW_TAG_compile_unit, children: true, attributes: vec![
+                AbbrevAttribute::new(DW_AT_ranges, DW_FORM_sec_offset),
+            ] },
+            Abbrev { code: 4, tag: DW_TAG_member, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_location, DW_FORM_sec_offset),
+                AbbrevAttribute::new(DW_AT_data_member_location, DW_FORM_data4),
+            ] },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev.write(&mut debug_abbrev).unwrap();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        let mut debug_info = Vec::new();
+        let mut unit = CompilationUnit::<LittleEndian>::default();
+        unit.common.version = 5;
+        let mut data = Vec::new();
+        Die::new(0, 1, DW_TAG_compile_unit, true, vec![
+            Attribute { at: DW_AT_rnglists_base, data: AttributeData::SecOffset(12) },
+            Attribute { at: DW_AT_loclists_base, data: AttributeData::SecOffset(12) },
+            Attribute { at: DW_AT_ranges, data: AttributeData::RangeListIndex(0) },
+        ]).write(&mut data, &unit.common, &abbrev_hash).unwrap();
+        Die::new(0, 2, DW_TAG_variable, false, vec![
+            Attribute { at: DW_AT_location, data: AttributeData::LocationListIndex(0) },
+        ]).write(&mut data, &unit.common, &abbrev_hash).unwrap();
+        Die::write_null(&mut data).unwrap();
+        unit.common.data = &data;
+        unit.write(&mut debug_info).unwrap();
+
+        let mut unit = CompilationUnit::<LittleEndian>::default();
+        let mut data = Vec::new();
+        Die::new(0, 3, DW_TAG_compile_unit, true, vec![
+            Attribute { at: DW_AT_ranges, data: AttributeData::SecOffset(0) },
+        ]).write(&mut data, &unit.common, &abbrev_hash).unwrap();
+        // The data4 DW_AT_data_member_location is a constant in DWARF 4.
+        Die::new(0, 4, DW_TAG_member, false, vec![
+            Attribute { at: DW_AT_location, data: AttributeData::SecOffset(0) },
+            Attribute { at: DW_AT_data_member_location, data: AttributeData::Data4(0x20) },
+        ]).write(&mut data, &unit.common, &abbrev_hash).unwrap();
+        Die::write_null(&mut data).unwrap();
+        unit.common.data = &data;
+        unit.write(&mut debug_info).unwrap();
+
+        let sections = Sections {
+            debug_abbrev: debug_abbrev,
+            debug_info: debug_info,
+            debug_ranges: vec![
+                0, 0, 0, 0, 0x10, 0, 0, 0,
+                0, 0, 0, 0, 0, 0, 0, 0,
+            ],
+            debug_rnglists: vec![
+                0x10, 0x00, 0x00, 0x00, 0x05, 0x00, 0x04, 0x00, 0x01, 0x00, 0x00, 0x00,
+                0x04, 0x00, 0x00, 0x00,
+                DW_RLE_offset_pair.0, 0x00, 0x10, DW_RLE_end_of_list.0,
+            ],
+            debug_loc: vec![
+                0xff, 0xff, 0xff, 0xff, 0x00, 0x10, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x01, 0x00, DW_OP_reg0.0,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ],
+            debug_loclists: vec![
+                0x12, 0x00, 0x00, 0x00, 0x05, 0x00, 0x04, 0x00, 0x01, 0x00, 0x00, 0x00,
+                0x04, 0x00, 0x00, 0x00,
+                DW_LLE_offset_pair.0, 0x00, 0x10, 0x01, DW_OP_reg0.0, DW_LLE_end_of_list.0,
+            ],
+            ..Sections::new(LittleEndian)
+        };
+        let layout = Layout::from_sections(&sections).unwrap();
+
+        // Omit .debug_info and .debug_abbrev.
+        let layout = Layout {
+            sections: layout.sections[2..].to_vec(),
+        };
+        let mut buf = Vec::new();
+        layout.write_text(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "\
+.debug_ranges: 0x10 bytes
+  0x00000000 0x00000010 range_list .debug_info+0x19
+.debug_rnglists: 0x14 bytes
+  0x00000000 0x00000010 lists_header .debug_info+0x0
+  0x00000010 0x00000004 range_list .debug_info+0x0
+.debug_loc: 0x1b bytes
+  0x00000000 0x0000001b location_list .debug_info+0x19
+.debug_loclists: 0x16 bytes
+  0x00000000 0x00000010 lists_header .debug_info+0x0
+  0x00000010 0x00000006 location_list .debug_info+0x0
+");
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn range_list_size() {
+        let debug_ranges = [
+            1, 0, 0, 0, 2, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+            3, 0, 0, 0,
+        ];
+        assert_eq!(super::range_list_size(&debug_ranges, 0, LittleEndian, 4).unwrap(), 16);
+        assert_eq!(super::range_list_size(&debug_ranges, 8, LittleEndian, 4).unwrap(), 8);
+        assert!(super::range_list_size(&debug_ranges, 16, LittleEndian, 4).is_err());
+        assert!(super::range_list_size(&debug_ranges, 20, LittleEndian, 4).is_err());
+    }
+}
@@ -41,6 +41,17 @@ pub fn read_i64(r: &mut &[u8]) -> Result<i64, ReadError> {
     }
 }
 
+// Skip over a signed or unsigned value without decoding it.
+pub fn skip(r: &mut &[u8]) -> Result<(), ReadError> {
+    match r.iter().position(|&byte| byte & 0x80 == 0) {
+        Some(len) => {
+            *r = &r[len + 1..];
+            Ok(())
+        }
+        None => Err(ReadError::Eof),
+    }
+}
+
 pub fn read_u16(r: &mut &[u8]) -> Result<u16, ReadError> {
     let val = try!(read_u64(r));
     if val > std::u16::MAX as u64 {
@@ -74,6 +85,16 @@ pub fn write_i64<W: Write>(w: &mut W, mut value: i64) -> std::io::Result<()> {
     }
 }
 
+// The number of bytes used to encode a value with `write_u64`.
+pub fn u64_len(mut value: u64) -> usize {
+    let mut len = 1;
+    while value >= 0x80 {
+        value >>= 7;
+        len += 1;
+    }
+    len
+}
+
 pub fn write_u16<W: Write>(w: &mut W, value: u16) -> std::io::Result<()> {
     try!(write_u64(w, value as u64));
     Ok(())
@@ -160,6 +181,7 @@ mod test {
             vec.clear();
             write_u64(&mut vec, value).unwrap();
             assert_eq!(vec, r);
+            assert_eq!(u64_len(value), r.len());
 
             assert_eq!(read_u64(&mut r).unwrap(), value);
             assert_eq!(r.len(), 0);
@@ -295,4 +317,27 @@ mod test {
             };
         }
     }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn test_skip() {
+        for &(mut r, len) in &[
+            (&[0x00, 0x01][..], 1),
+            (&[0x7f][..], 0),
+            (&[0x80, 0x80, 0x01, 0x02][..], 1),
+        ] {
+            skip(&mut r).unwrap();
+            assert_eq!(r.len(), len);
+        }
+
+        for &(mut r,) in &[
+            (&[][..],),
+            (&[0x80, 0xff][..],),
+        ] {
+            match skip(&mut r) {
+                Err(ReadError::Eof) => {},
+                otherwise => panic!("{:?}", otherwise),
+            };
+        }
+    }
 }
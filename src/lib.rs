@@ -1,31 +1,149 @@
+// All reads are bounds checked through `read::Bytes`, and malformed input
+// is reported as a `ReadError` rather than a panic. The targets in `fuzz/`
+// check this: `cargo fuzz run <target>`.
+#![forbid(unsafe_code)]
+
 mod endian;
 mod leb128;
 mod read;
 mod write;
 
 pub mod abbrev;
+pub mod build;
+pub mod capability;
+pub mod chunk;
+pub mod class;
 pub mod constant;
+pub mod coverage;
+pub mod debuglink;
+pub mod dedup;
 pub mod die;
 pub mod display;
+#[cfg(feature = "elf")]
 pub mod elf;
+pub mod expr;
+pub mod frame;
+pub mod framesize;
+pub mod graph;
+pub mod jit;
+pub mod layout;
 pub mod line;
+pub mod location;
+pub mod merge;
+pub mod metrics;
+pub mod offset;
+pub mod prelude;
+pub mod quirk;
+pub mod ranges;
+pub mod rewrite;
+pub mod scope;
+pub mod search;
+pub mod split;
+pub mod strings;
+pub mod symbols;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod text;
+pub mod types;
 pub mod unit;
+pub mod verify;
+#[cfg(feature = "yaml")]
+pub mod yaml;
+
+use std::ops::Deref;
+use std::rc::Rc;
+use std::sync::Arc;
 
 pub use endian::{AnyEndian, Endian, LittleEndian, BigEndian, NativeEndian};
 pub use read::ReadError;
 pub use write::WriteError;
 
-#[derive(Debug)]
-pub struct Sections<E: Endian> {
+// The DWARF sections of a file.
+//
+// The section data is stored in `S`, which may be any container that
+// derefs to a slice. Use `Sections<E, Arc<[u8]>>` (see `into_shared`)
+// to share the sections between threads.
+#[derive(Debug, Clone)]
+pub struct Sections<E: Endian, S = Vec<u8>> {
     pub endian: E,
-    pub debug_abbrev: Vec<u8>,
-    pub debug_info: Vec<u8>,
-    pub debug_line: Vec<u8>,
-    pub debug_str: Vec<u8>,
-    pub debug_types: Vec<u8>,
+    pub debug_abbrev: S,
+    pub debug_addr: S,
+    pub debug_aranges: S,
+    pub debug_frame: S,
+    pub debug_info: S,
+    pub debug_line: S,
+    pub debug_line_str: S,
+    pub debug_loc: S,
+    pub debug_loclists: S,
+    pub debug_ranges: S,
+    pub debug_rnglists: S,
+    pub debug_str: S,
+    pub debug_str_offsets: S,
+    pub debug_types: S,
 }
 
 impl<E: Endian> Sections<E> {
+    // Create sections that are all empty.
+    //
+    // Use this with struct update syntax to set only the sections that
+    // are needed.
+    pub fn new(endian: E) -> Self {
+        Sections {
+            endian: endian,
+            debug_abbrev: Vec::new(),
+            debug_addr: Vec::new(),
+            debug_aranges: Vec::new(),
+            debug_frame: Vec::new(),
+            debug_info: Vec::new(),
+            debug_line: Vec::new(),
+            debug_line_str: Vec::new(),
+            debug_loc: Vec::new(),
+            debug_loclists: Vec::new(),
+            debug_ranges: Vec::new(),
+            debug_rnglists: Vec::new(),
+            debug_str: Vec::new(),
+            debug_str_offsets: Vec::new(),
+            debug_types: Vec::new(),
+        }
+    }
+
+    pub fn into_shared(self) -> Sections<E, Arc<[u8]>> {
+        self.map(Arc::from)
+    }
+}
+
+impl<E: Endian, S: Deref<Target = [u8]>> Sections<E, S> {
+    // Convert the container of each section.
+    pub fn map<T, F>(self, mut f: F) -> Sections<E, T>
+        where F: FnMut(S) -> T
+    {
+        Sections {
+            endian: self.endian,
+            debug_abbrev: f(self.debug_abbrev),
+            debug_addr: f(self.debug_addr),
+            debug_aranges: f(self.debug_aranges),
+            debug_frame: f(self.debug_frame),
+            debug_info: f(self.debug_info),
+            debug_line: f(self.debug_line),
+            debug_line_str: f(self.debug_line_str),
+            debug_loc: f(self.debug_loc),
+            debug_loclists: f(self.debug_loclists),
+            debug_ranges: f(self.debug_ranges),
+            debug_rnglists: f(self.debug_rnglists),
+            debug_str: f(self.debug_str),
+            debug_str_offsets: f(self.debug_str_offsets),
+            debug_types: f(self.debug_types),
+        }
+    }
+
+    // Iterate over the CIEs and FDEs in .debug_frame.
+    //
+    // `address_size` is used for CIEs before version 4, which don't
+    // contain the address size.
+    pub fn frame_entries<'a>(&'a self, address_size: u8) -> frame::FrameEntryIterator<'a, E> {
+        frame::FrameEntryIterator::new(&*self.debug_frame, self.endian, address_size)
+    }
+
     pub fn compilation_units(&self) -> unit::CompilationUnitIterator<E> {
         unit::CompilationUnitIterator::new(self.endian, &*self.debug_info)
     }
@@ -34,6 +152,42 @@ impl<E: Endian> Sections<E> {
         unit::TypeUnitIterator::new(self.endian, &*self.debug_types)
     }
 
+    // Iterate over the entries of all units.
+    //
+    // The abbreviation tables of the units are read using `abbrev_cache`.
+    pub fn all_entries<'a, 'c>(
+        &'a self,
+        abbrev_cache: &'c mut abbrev::AbbrevCache
+    ) -> unit::AllEntries<'a, 'c, E> {
+        unit::AllEntries::new(&*self.debug_abbrev,
+                              abbrev_cache,
+                              self.compilation_units(),
+                              self.type_units())
+    }
+
+    // Build an index for finding the compilation unit containing an offset.
+    //
+    // The index isn't stored in the sections, since the section data may
+    // be modified. Build it again after modifying .debug_info.
+    pub fn unit_index(&self) -> Result<unit::UnitIndex, ReadError> {
+        unit::UnitIndex::new(self.compilation_units())
+    }
+
+    // Return the compilation unit containing an offset in .debug_info.
+    pub fn unit_containing<'a>(
+        &'a self,
+        index: &unit::UnitIndex,
+        offset: usize
+    ) -> Result<Option<unit::CompilationUnit<'a, E>>, ReadError> {
+        match index.unit_containing(offset) {
+            Some(unit_offset) => {
+                let mut r = &self.debug_info[unit_offset..];
+                unit::CompilationUnit::read(&mut r, unit_offset, self.endian).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
     pub fn abbrev<'a>(
         &self,
         unit: &unit::UnitCommon<'a, E>
@@ -41,19 +195,356 @@ impl<E: Endian> Sections<E> {
         unit.abbrev(&*self.debug_abbrev)
     }
 
+    // Read the line program of a compilation unit, using the
+    // DW_AT_stmt_list of the unit's root entry.
+    //
+    // Returns `None` if the unit has no line program.
     pub fn line_program<'a>(
         &'a self,
         unit: &unit::CompilationUnit<'a, E>,
         abbrev: &abbrev::AbbrevHash
     ) -> Result<Option<line::LineProgram<'a, E>>, ReadError> {
-        unit.line_program(&*self.debug_line, &*self.debug_str, abbrev)
+        let context = self.attribute_context(&unit.common);
+        unit.line_program_in(&*self.debug_line, &context, abbrev)
     }
 
+    // Iterate over the rows of the line program of a compilation unit.
     pub fn lines<'a>(
         &'a self,
         unit: &unit::CompilationUnit<'a, E>,
         abbrev: &abbrev::AbbrevHash
     ) -> Result<Option<line::LineIterator<'a, E>>, ReadError> {
-        unit.lines(&*self.debug_line, &*self.debug_str, abbrev)
+        let program = try!(self.line_program(unit, abbrev));
+        Ok(program.map(line::LineProgram::into_lines))
+    }
+
+    // Like `line_program`, but use the program in `cache` if it has
+    // already been read.
+    pub fn cached_line_program<'a>(
+        &'a self,
+        cache: &mut line::LineProgramCache<'a, E>,
+        unit: &unit::CompilationUnit<'a, E>,
+        abbrev: &abbrev::AbbrevHash
+    ) -> Result<Option<Rc<line::LineProgram<'a, E>>>, ReadError> {
+        let context = self.attribute_context(&unit.common);
+        match try!(unit.line_program_location(&context, abbrev)) {
+            Some(location) => cache.program(&*self.debug_line, context.line_strings(), self.endian, location).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // Return all of the rows of the line program of a compilation unit,
+    // using the rows in `cache` if they have already been read.
+    pub fn cached_line_table<'a>(
+        &'a self,
+        cache: &mut line::LineProgramCache<'a, E>,
+        unit: &unit::CompilationUnit<'a, E>,
+        abbrev: &abbrev::AbbrevHash
+    ) -> Result<Option<Rc<line::LineTable<'a>>>, ReadError> {
+        let context = self.attribute_context(&unit.common);
+        match try!(unit.line_program_location(&context, abbrev)) {
+            Some(location) => cache.table(&*self.debug_line, context.line_strings(), self.endian, location).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // The context for interpreting the attribute values of a unit.
+    pub fn attribute_context<'a>(
+        &'a self,
+        unit: &unit::UnitCommon<'a, E>
+    ) -> die::AttributeContext<'a> {
+        die::AttributeContext::new(unit, &*self.debug_str, &*self.debug_line_str)
+    }
+
+    // Return the address at `index` in .debug_addr, for DW_FORM_GNU_addr_index.
+    //
+    // `base` is the DW_AT_GNU_addr_base of the skeleton unit, and `unit` is
+    // used for the address size.
+    pub fn indexed_address(
+        &self,
+        unit: &unit::UnitCommon<E>,
+        base: u64,
+        index: u64
+    ) -> Result<u64, ReadError> {
+        read::read_indexed(&*self.debug_addr, self.endian, unit.address_size, base, index)
+    }
+
+    // Return the string at `index` in .debug_str_offsets, for
+    // DW_FORM_GNU_str_index.
+    //
+    // `base` is the offset of the entries for the unit. Split units don't
+    // have a DW_AT_str_offsets_base, so their base is 0.
+    pub fn indexed_string(
+        &self,
+        unit: &unit::UnitCommon<E>,
+        base: u64,
+        index: u64
+    ) -> Result<&[u8], ReadError> {
+        let offset = try!(read::read_indexed(&*self.debug_str_offsets,
+                                             self.endian,
+                                             unit.offset_size,
+                                             base,
+                                             index));
+        if offset >= self.debug_str.len() as u64 {
+            return Err(ReadError::Invalid);
+        }
+        let mut r = &self.debug_str[offset as usize..];
+        read::read_string(&mut r)
+    }
+
+    // Iterate over the ranges of the range list at `offset` in .debug_ranges.
+    //
+    // `base_address` is the DW_AT_low_pc of the unit.
+    pub fn range_list_at<'a>(
+        &'a self,
+        unit: &unit::UnitCommon<E>,
+        offset: u64,
+        base_address: u64
+    ) -> Result<ranges::RangeListIterator<'a, E>, ReadError> {
+        ranges::RangeListIterator::new(&*self.debug_ranges,
+                                       offset as usize,
+                                       self.endian,
+                                       unit.address_size,
+                                       base_address)
+    }
+
+    // Iterate over the ranges of a DW_AT_ranges value of an entry in a unit
+    // before version 5.
+    //
+    // `bases` are the bases of the unit, from `UnitBases::read`. Returns
+    // `None` if the value isn't a range list.
+    pub fn range_list<'a>(
+        &'a self,
+        unit: &unit::UnitCommon<'a, E>,
+        bases: &unit::UnitBases,
+        data: &die::AttributeData
+    ) -> Result<Option<ranges::RangeListIterator<'a, E>>, ReadError> {
+        let context = self.attribute_context(unit);
+        let offset = match data.as_offset_in(&context) {
+            Some(offset) => offset as u64,
+            None => return Ok(None),
+        };
+        self.range_list_at(unit, offset, bases.base_address).map(Some)
+    }
+
+    // Return the offset in .debug_rnglists of the range list at `index`,
+    // for DW_FORM_rnglistx.
+    //
+    // `base` is the DW_AT_rnglists_base of the unit.
+    pub fn rnglistx_offset(
+        &self,
+        unit: &unit::UnitCommon<E>,
+        base: u64,
+        index: u64
+    ) -> Result<u64, ReadError> {
+        ranges::rnglistx_offset(&*self.debug_rnglists, self.endian, unit.offset_size, base, index)
+    }
+
+    // Iterate over the ranges of the range list at `offset` in
+    // .debug_rnglists.
+    //
+    // `base_address` is the DW_AT_low_pc of the unit, and `addr_base` is its
+    // DW_AT_addr_base.
+    pub fn rnglist_at<'a>(
+        &'a self,
+        unit: &unit::UnitCommon<E>,
+        offset: u64,
+        base_address: u64,
+        addr_base: u64
+    ) -> Result<ranges::RngListIterator<'a, E>, ReadError> {
+        let raw = try!(ranges::RawRngListIterator::new(&*self.debug_rnglists,
+                                                       offset as usize,
+                                                       self.endian,
+                                                       unit.address_size));
+        Ok(ranges::RngListIterator::new(raw, base_address, &*self.debug_addr, addr_base))
+    }
+
+    // Iterate over the ranges of a DW_AT_ranges value of an entry in a
+    // version 5 unit.
+    //
+    // `bases` are the bases of the unit, from `UnitBases::read`. Returns
+    // `None` if the value isn't a range list.
+    pub fn rnglist<'a>(
+        &'a self,
+        unit: &unit::UnitCommon<'a, E>,
+        bases: &unit::UnitBases,
+        data: &die::AttributeData
+    ) -> Result<Option<ranges::RngListIterator<'a, E>>, ReadError> {
+        let context = self.attribute_context(unit);
+        let offset = match *data {
+            die::AttributeData::RangeListIndex(index) => {
+                try!(self.rnglistx_offset(unit, bases.rnglists_base, index))
+            }
+            _ => {
+                match data.as_offset_in(&context) {
+                    Some(offset) => offset as u64,
+                    None => return Ok(None),
+                }
+            }
+        };
+        self.rnglist_at(unit, offset, bases.base_address, bases.addr_base).map(Some)
+    }
+
+    // Return the ranges of a DW_AT_ranges value of an entry, from
+    // .debug_rnglists for version 5 units, or .debug_ranges otherwise.
+    pub fn ranges<'a>(
+        &'a self,
+        unit: &unit::UnitCommon<'a, E>,
+        bases: &unit::UnitBases,
+        data: &die::AttributeData
+    ) -> Result<Vec<ranges::Range>, ReadError> {
+        let mut ranges = Vec::new();
+        if unit.version >= 5 {
+            let mut iter = match try!(self.rnglist(unit, bases, data)) {
+                Some(iter) => iter,
+                None => return Err(ReadError::Invalid),
+            };
+            while let Some(range) = try!(iter.next()) {
+                ranges.push(range);
+            }
+            return Ok(ranges);
+        }
+
+        let mut iter = match try!(self.range_list(unit, bases, data)) {
+            Some(iter) => iter,
+            None => return Err(ReadError::Invalid),
+        };
+        while let Some(range) = try!(iter.next()) {
+            ranges.push(range);
+        }
+        Ok(ranges)
+    }
+
+    // Return the address ranges of an entry, from DW_AT_low_pc and
+    // DW_AT_high_pc, or DW_AT_ranges.
+    pub fn entry_ranges<'a>(
+        &'a self,
+        unit: &unit::UnitCommon<'a, E>,
+        bases: &unit::UnitBases,
+        entry: &die::Die
+    ) -> Result<Vec<ranges::Range>, ReadError> {
+        if let Some((begin, size)) = entry.pc_range() {
+            return Ok(vec![ranges::Range {
+                               begin: begin,
+                               end: begin + size,
+                           }]);
+        }
+        match entry.attr(constant::DW_AT_ranges) {
+            Some(data) => self.ranges(unit, bases, data),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    // Iterate over the entries of the location list at `offset` in
+    // .debug_loc.
+    //
+    // `base_address` is the DW_AT_low_pc of the unit.
+    pub fn location_list_at<'a>(
+        &'a self,
+        unit: &unit::UnitCommon<E>,
+        offset: u64,
+        base_address: u64
+    ) -> Result<location::LocationListIterator<'a, E>, ReadError> {
+        location::LocationListIterator::new(&*self.debug_loc,
+                                            offset as usize,
+                                            self.endian,
+                                            unit.address_size,
+                                            base_address)
+    }
+
+    // Iterate over the entries of a DW_AT_location value of an entry in a
+    // unit before version 5.
+    //
+    // `bases` are the bases of the unit, from `UnitBases::read`. Returns
+    // `None` if the value isn't a location list, such as for a single
+    // location expression.
+    pub fn location_list<'a>(
+        &'a self,
+        unit: &unit::UnitCommon<'a, E>,
+        bases: &unit::UnitBases,
+        data: &die::AttributeData
+    ) -> Result<Option<location::LocationListIterator<'a, E>>, ReadError> {
+        let context = self.attribute_context(unit);
+        let offset = match data.as_offset_in(&context) {
+            Some(offset) => offset as u64,
+            None => return Ok(None),
+        };
+        self.location_list_at(unit, offset, bases.base_address).map(Some)
+    }
+
+    // Return the offset in .debug_loclists of the location list at `index`,
+    // for DW_FORM_loclistx.
+    //
+    // `base` is the DW_AT_loclists_base of the unit.
+    pub fn loclistx_offset(
+        &self,
+        unit: &unit::UnitCommon<E>,
+        base: u64,
+        index: u64
+    ) -> Result<u64, ReadError> {
+        location::loclistx_offset(&*self.debug_loclists, self.endian, unit.offset_size, base, index)
+    }
+
+    // Iterate over the entries of the location list at `offset` in
+    // .debug_loclists.
+    //
+    // `base_address` is the DW_AT_low_pc of the unit, and `addr_base` is its
+    // DW_AT_addr_base.
+    pub fn loclist_at<'a>(
+        &'a self,
+        unit: &unit::UnitCommon<E>,
+        offset: u64,
+        base_address: u64,
+        addr_base: u64
+    ) -> Result<location::LocListIterator<'a, E>, ReadError> {
+        let raw = try!(location::RawLocListIterator::new(&*self.debug_loclists,
+                                                         offset as usize,
+                                                         self.endian,
+                                                         unit.address_size));
+        Ok(location::LocListIterator::new(raw, base_address, &*self.debug_addr, addr_base))
+    }
+
+    // Iterate over the entries of a DW_AT_location value of an entry in a
+    // version 5 unit.
+    //
+    // `bases` are the bases of the unit, from `UnitBases::read`. Returns
+    // `None` if the value isn't a location list, such as for a single
+    // location expression.
+    pub fn loclist<'a>(
+        &'a self,
+        unit: &unit::UnitCommon<'a, E>,
+        bases: &unit::UnitBases,
+        data: &die::AttributeData
+    ) -> Result<Option<location::LocListIterator<'a, E>>, ReadError> {
+        let context = self.attribute_context(unit);
+        let offset = match *data {
+            die::AttributeData::LocationListIndex(index) => {
+                try!(self.loclistx_offset(unit, bases.loclists_base, index))
+            }
+            _ => {
+                match data.as_offset_in(&context) {
+                    Some(offset) => offset as u64,
+                    None => return Ok(None),
+                }
+            }
+        };
+        self.loclist_at(unit, offset, bases.base_address, bases.addr_base).map(Some)
+    }
+
+    // Return a handle for the entry found by `CompilationUnit::entry_at_path`.
+    pub fn entry_at_path<'a>(
+        &'a self,
+        unit: &unit::CompilationUnit<'a, E>,
+        abbrev: &abbrev::AbbrevHash,
+        path: &[(constant::DwTag, &[u8])]
+    ) -> Result<Option<die::DieRef<'a, E, S>>, ReadError> {
+        let context = self.attribute_context(&unit.common);
+        let offset = try!(unit.entry_at_path(abbrev, &context, path));
+        Ok(offset.map(|offset| die::DieRef::new(self, unit.common.offset, offset)))
+    }
+
+    // Iterate over the strings in .debug_str.
+    pub fn strings<'a>(&'a self) -> strings::StringIterator<'a> {
+        strings::StringIterator::new(&*self.debug_str)
     }
 }
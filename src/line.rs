@@ -1,7 +1,14 @@
+use std;
+use std::io::Write;
+use std::ops::Range;
+use std::rc::Rc;
+
+use chunk::{ChunkReader, ReadAt};
 use constant;
 use endian::Endian;
 use leb128;
 use read::*;
+use write::*;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct LineProgram<'data, E: Endian> {
@@ -27,10 +34,41 @@ impl<'data, E: Endian> LineProgram<'data, E> {
         LineIterator::new(self.clone())
     }
 
+    // Return the include directory for a directory index in a file entry.
+    //
+    // Index 0 is the compilation directory. Before version 5, this isn't
+    // encoded in the header, and `read` inserts it from DW_AT_comp_dir, so
+    // the index can be used for all versions without adjustment.
+    pub fn directory(&self, index: u64) -> Option<&'data [u8]> {
+        directory(&self.include_directories, index)
+    }
+
+    // Return the file entry for a file index in a row or DW_AT_decl_file.
+    //
+    // Index 0 is the primary source file. Before version 5, this isn't
+    // encoded in the header, and `read` inserts it from DW_AT_name, so
+    // the index can be used for all versions without adjustment. Use
+    // `Die::file_index` to read the index of DW_AT_decl_file, which corrects
+    // it for the quirks of the producer.
+    pub fn file(&self, index: u64) -> Option<&FileEntry<'data>> {
+        file(&self.files, index)
+    }
+
     pub fn into_lines(self) -> LineIterator<'data, E> {
         LineIterator::new(self)
     }
 
+    // Return an iterator that starts at the given offset within `data`.
+    //
+    // The registers are set to their initial values, so the offset should
+    // be the start of a sequence, such as an offset returned by
+    // `LineIterator::offset` after the end of a sequence.
+    pub fn rows_from(&self, offset: usize) -> Result<LineIterator<'data, E>, ReadError> {
+        let mut lines = self.lines();
+        try!(lines.data.skip(offset).map_err(|_| ReadError::Invalid));
+        Ok(lines)
+    }
+
     pub fn read(
         r: &mut &'data [u8],
         offset: usize,
@@ -39,12 +77,66 @@ impl<'data, E: Endian> LineProgram<'data, E> {
         comp_dir: &'data [u8],
         comp_name: &'data [u8]
     ) -> Result<LineProgram<'data, E>, ReadError> {
-        let (offset_size, len) = try!(read_initial_length(r, endian));
-        let mut data = &r[..len];
+        LineProgram::read_with_strings(r,
+                                       offset,
+                                       endian,
+                                       address_size,
+                                       comp_dir,
+                                       comp_name,
+                                       LineStrings::default())
+    }
 
+    // Like `read`, but read the DW_FORM_strp and DW_FORM_line_strp
+    // directory and file entries of version 5 programs from `strings`.
+    pub fn read_with_strings(
+        r: &mut &'data [u8],
+        offset: usize,
+        endian: E,
+        address_size: u8,
+        comp_dir: &'data [u8],
+        comp_name: &'data [u8],
+        strings: LineStrings<'data>
+    ) -> Result<LineProgram<'data, E>, ReadError> {
+        let mut data = *r;
+        let (offset_size, len) = try!(read_initial_length(&mut data, endian));
+        let header = try!(read_block(&mut data, len));
+        let location = LineProgramLocation {
+            offset: offset,
+            address_size: address_size,
+            comp_dir: comp_dir,
+            comp_name: comp_name,
+        };
+        let program = try!(LineProgram::read_header(header, &location, endian, offset_size, strings));
+        *r = data;
+        Ok(program)
+    }
+
+    // Read the header that follows the initial length. The data after the
+    // header are the opcodes of the program.
+    //
+    // Version 5 headers contain their own address size, and encode the
+    // compilation directory and primary file, so only the offset of
+    // `location` is used for them.
+    fn read_header(
+        mut data: &'data [u8],
+        location: &LineProgramLocation<'data>,
+        endian: E,
+        offset_size: u8,
+        strings: LineStrings<'data>
+    ) -> Result<LineProgram<'data, E>, ReadError> {
+        let offset = location.offset;
+        let mut address_size = location.address_size;
         let version = try!(endian.read_u16(&mut data));
-        if version < 2 || version > 4 {
-            return Err(ReadError::Unsupported);
+        if version < 2 || version > 5 {
+            return Err(ReadError::UnsupportedVersion(version));
+        }
+
+        if version >= 5 {
+            address_size = try!(read_u8(&mut data));
+            let segment_selector_size = try!(read_u8(&mut data));
+            if segment_selector_size != 0 {
+                return Err(ReadError::Unsupported);
+            }
         }
 
         let header_length = try!(read_offset(&mut data, endian, offset_size)) as usize;
@@ -83,40 +175,16 @@ impl<'data, E: Endian> LineProgram<'data, E> {
 
         let standard_opcode_lengths = try!(read_block(&mut header, opcode_base as usize - 1));
 
-        let mut include_directories = vec![comp_dir];
-        loop {
-            if header.len() < 1 {
-                return Err(ReadError::Invalid);
-            }
-            if header[0] == 0 {
-                header = &header[1..];
-                break;
-            }
-            include_directories.push(try!(read_string(&mut header)));
-        }
-
-        let mut files = vec![FileEntry {
-                                 path: comp_name,
-                                 directory: 0,
-                                 timestamp: 0,
-                                 length: 0,
-                             }];
-        loop {
-            if header.len() < 1 {
-                return Err(ReadError::Invalid);
-            }
-            if header[0] == 0 {
-                header = &header[1..];
-                break;
-            }
-            files.push(try!(FileEntry::read(&mut header)));
-        }
+        let (include_directories, files) = if version >= 5 {
+            try!(read_entries_v5(&mut header, endian, offset_size, strings))
+        } else {
+            try!(read_entries(&mut header, location.comp_dir, location.comp_name))
+        };
 
         if header.len() != 0 {
             return Err(ReadError::Invalid);
         }
 
-        *r = &r[len..];
         Ok(LineProgram {
             offset: offset,
             endian: endian,
@@ -135,6 +203,99 @@ impl<'data, E: Endian> LineProgram<'data, E> {
             data: data,
         })
     }
+
+    // Write the program header and opcodes.
+    //
+    // The first entries of `include_directories` and `files` are the
+    // compilation directory and file, and are only written for version 5.
+    // Version 5 entries are always written with inline strings.
+    pub fn write<W: Write>(&self, w: &mut W) -> Result<(), WriteError> {
+        if self.version < 2 || self.version > 5 {
+            return Err(WriteError::Unsupported(format!("line program version {}", self.version)));
+        }
+        if self.standard_opcode_lengths.len() + 1 != self.opcode_base as usize {
+            return Err(WriteError::Invalid("standard opcode lengths mismatch".to_string()));
+        }
+
+        let mut header = Vec::new();
+        try!(write_u8(&mut header, self.address_step));
+        if self.version >= 4 {
+            try!(write_u8(&mut header, self.operation_range));
+        } else if self.operation_range != 1 {
+            return Err(WriteError::Invalid(format!("operation range {}", self.operation_range)));
+        }
+        try!(write_u8(&mut header, self.default_statement as u8));
+        try!(write_u8(&mut header, self.line_base as u8));
+        try!(write_u8(&mut header, self.line_range));
+        try!(write_u8(&mut header, self.opcode_base));
+        try!(header.write_all(self.standard_opcode_lengths));
+        if self.version >= 5 {
+            try!(self.write_entries_v5(&mut header));
+        } else {
+            for dir in self.include_directories.iter().skip(1) {
+                try!(header.write_all(dir));
+                try!(write_u8(&mut header, 0));
+            }
+            try!(write_u8(&mut header, 0));
+            for file in self.files.iter().skip(1) {
+                try!(file.write(&mut header));
+            }
+            try!(write_u8(&mut header, 0));
+        }
+
+        let address_len = if self.version >= 5 { 2 } else { 0 };
+        let len = 2 + address_len + self.offset_size as usize + header.len() + self.data.len();
+        match self.offset_size {
+            4 => {
+                if len >= 0xfffffff0 {
+                    return Err(WriteError::Invalid(format!("line program length {}", len)));
+                }
+                try!(self.endian.write_u32(w, len as u32));
+            }
+            8 => {
+                try!(self.endian.write_u32(w, 0xffffffff));
+                try!(self.endian.write_u64(w, len as u64));
+            }
+            _ => return Err(WriteError::Unsupported(format!("offset size {}", self.offset_size))),
+        };
+        try!(self.endian.write_u16(w, self.version));
+        if self.version >= 5 {
+            try!(write_u8(w, self.address_size));
+            // segment_selector_size
+            try!(write_u8(w, 0));
+        }
+        try!(write_offset(w, self.endian, self.offset_size, header.len() as u64));
+        try!(w.write_all(&header));
+        try!(w.write_all(self.data));
+        Ok(())
+    }
+
+    // Write the directory and file tables of a version 5 header, using the
+    // same encoding as `FileEntry::write` for the files.
+    fn write_entries_v5<W: Write>(&self, w: &mut W) -> Result<(), WriteError> {
+        try!(write_u8(w, 1));
+        try!(leb128::write_u16(w, constant::DW_LNCT_path.0));
+        try!(leb128::write_u16(w, constant::DW_FORM_string.0));
+        try!(leb128::write_u64(w, self.include_directories.len() as u64));
+        for dir in &self.include_directories {
+            try!(w.write_all(dir));
+            try!(write_u8(w, 0));
+        }
+
+        try!(write_u8(w, 4));
+        for &(content, form) in &[(constant::DW_LNCT_path, constant::DW_FORM_string),
+                                  (constant::DW_LNCT_directory_index, constant::DW_FORM_udata),
+                                  (constant::DW_LNCT_timestamp, constant::DW_FORM_udata),
+                                  (constant::DW_LNCT_size, constant::DW_FORM_udata)] {
+            try!(leb128::write_u16(w, content.0));
+            try!(leb128::write_u16(w, form.0));
+        }
+        try!(leb128::write_u64(w, self.files.len() as u64));
+        for file in &self.files {
+            try!(file.write(w));
+        }
+        Ok(())
+    }
 }
 
 // Since line entries can modify the file entry array, the ownership
@@ -143,25 +304,103 @@ impl<'data, E: Endian> LineProgram<'data, E> {
 // than once, then you should cache it. If you don't want to cache it,
 // then reparsing the header is a small cost.
 
+#[derive(Clone)]
 pub struct LineIterator<'data, E: 'data + Endian> {
     program: LineProgram<'data, E>,
+    // The number of files in the header, excluding any added by
+    // DW_LNE_define_file.
+    header_files: usize,
     line: Line,
     copy: bool,
-    data: &'data [u8],
+    // The opcodes following the current row. The offset is relative to
+    // the start of the program data.
+    data: Bytes<'data>,
+    // The opcodes decoded for the current row, if tracing is enabled.
+    trace: Option<Vec<TraceOpcode>>,
+    // Whether an extended opcode whose length doesn't match its operands
+    // is an error.
+    strict_lengths: bool,
+}
+
+// An opcode decoded by a `LineIterator`, recorded when tracing is enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceOpcode {
+    // The offset of the opcode within the program data.
+    pub offset: usize,
+    pub opcode: u8,
+    // The extended opcode, if `opcode` is 0.
+    pub extended: Option<u8>,
+    // True if the opcode wasn't recognized and its operands were skipped.
+    pub skipped: bool,
+    // True if the declared length of an extended opcode was longer than its
+    // operands. The extra bytes are skipped.
+    pub length_mismatch: bool,
 }
 
 impl<'data, E: Endian> LineIterator<'data, E> {
     pub fn new(program: LineProgram<'data, E>) -> Self {
         let default_statement = program.default_statement;
-        let data = program.data;
+        let data = Bytes::new(program.data, 0);
+        let header_files = program.files.len();
         LineIterator {
             program: program,
+            header_files: header_files,
             line: Line::new(default_statement),
             copy: false,
             data: data,
+            trace: None,
+            strict_lengths: false,
+        }
+    }
+
+    // Return an error for extended opcodes whose declared length is longer
+    // than their operands.
+    //
+    // By default the extra bytes are skipped, and the mismatch is only
+    // reported in the trace. A declared length that is too short for the
+    // operands is always an error.
+    pub fn enable_strict_lengths(&mut self) {
+        self.strict_lengths = true;
+    }
+
+    // Record the opcodes that are decoded for each row.
+    //
+    // This is mainly useful for diagnosing producers that use opcodes
+    // which aren't known to this library, since those opcodes are
+    // otherwise silently skipped.
+    pub fn enable_trace(&mut self) {
+        if self.trace.is_none() {
+            self.trace = Some(Vec::new());
+        }
+    }
+
+    // The opcodes that were decoded for the row most recently returned by
+    // `next`, in program order.
+    //
+    // This is empty if tracing is not enabled.
+    pub fn trace(&self) -> &[TraceOpcode] {
+        match self.trace {
+            Some(ref trace) => trace,
+            None => &[],
+        }
+    }
+
+    // Restart the iteration from the beginning of the program.
+    pub fn reset(&mut self) {
+        self.program.files.truncate(self.header_files);
+        self.line = Line::new(self.program.default_statement);
+        self.copy = false;
+        self.data = Bytes::new(self.program.data, 0);
+        if let Some(ref mut trace) = self.trace {
+            trace.clear();
         }
     }
 
+    // The offset within the program data of the next opcode.
+    pub fn offset(&self) -> usize {
+        self.data.offset()
+    }
+
     pub fn directories(&self) -> &Vec<&'data [u8]> {
         &self.program.include_directories
     }
@@ -170,12 +409,43 @@ impl<'data, E: Endian> LineIterator<'data, E> {
         &self.program.files
     }
 
+    // Return the include directory for a directory index.
+    //
+    // See `LineProgram::directory`.
+    pub fn directory(&self, index: u64) -> Option<&'data [u8]> {
+        self.program.directory(index)
+    }
+
+    // Return the file entry for a file index, including files that have
+    // been added by DW_LNE_define_file.
+    //
+    // See `LineProgram::file`.
+    pub fn file(&self, index: u64) -> Option<&FileEntry<'data>> {
+        self.program.file(index)
+    }
+
     #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
     pub fn next(&mut self) -> Result<Option<(&LineIterator<E>, &Line)>, ReadError> {
-        if self.data.len() == 0 {
+        if self.data.is_empty() {
             return Ok(None);
         }
 
+        self.start_row();
+        let mut r = self.data.as_slice();
+        loop {
+            let offset = self.offset();
+            if let Some(file) = try!(self.next_opcode(&mut r, offset)) {
+                self.program.files.push(file);
+            }
+            try!(self.data.advance_to(r));
+            if self.copy {
+                self.copy = false;
+                return Ok(Some((self, &self.line)));
+            }
+        }
+    }
+
+    fn start_row(&mut self) {
         if self.line.sequence_end {
             self.line = Line::new(self.program.default_statement);
         } else {
@@ -184,42 +454,60 @@ impl<'data, E: Endian> LineIterator<'data, E> {
             self.line.epilogue_begin = false;
             self.line.discriminator = 0;
         }
-
-        let mut r = self.data;
-        loop {
-            try!(self.next_opcode(&mut r));
-            self.data = r;
-            if self.copy {
-                self.copy = false;
-                return Ok(Some((self, &self.line)));
-            }
+        if let Some(ref mut trace) = self.trace {
+            trace.clear();
         }
     }
 
-    fn next_opcode(&mut self, r: &mut &'data [u8]) -> Result<(), ReadError> {
+    // Execute the opcode at the start of `r`, which is at `offset` within
+    // the program data.
+    //
+    // Returns the file entry of a DW_LNE_define_file opcode, which the
+    // caller must add to the files. If `r` is too short for the opcode then
+    // an error is returned and the state is unchanged.
+    fn next_opcode<'r>(
+        &mut self,
+        r: &mut &'r [u8],
+        offset: usize
+    ) -> Result<Option<FileEntry<'r>>, ReadError> {
         let opcode = try!(read_u8(r));
-        match constant::DwLns(opcode) {
-            constant::DW_LNS_extended => try!(self.next_extended(r)),
-            constant::DW_LNS_copy => self.copy = true,
-            constant::DW_LNS_advance_pc => self.advance_pc(try!(leb128::read_u64(r))),
-            constant::DW_LNS_advance_line => self.advance_line(try!(leb128::read_i64(r))),
-            constant::DW_LNS_set_file => self.line.file = try!(leb128::read_u64(r)),
-            constant::DW_LNS_set_column => self.line.column = try!(leb128::read_u64(r)),
-            constant::DW_LNS_negate_stmt => self.line.statement = !self.line.statement,
-            constant::DW_LNS_set_basic_block => self.line.basic_block = true,
-            constant::DW_LNS_const_add_pc => {
-                let op_delta = (255 - self.program.opcode_base) / self.program.line_range;
-                self.advance_pc(op_delta as u64);
-            }
-            constant::DW_LNS_fixed_advance_pc => {
-                self.line.address += try!(self.program.endian.read_u16(r)) as u64;
-                self.line.operation = 0;
-            }
-            constant::DW_LNS_set_prologue_end => self.line.prologue_end = true,
-            constant::DW_LNS_set_epilogue_begin => self.line.epilogue_begin = true,
-            constant::DW_LNS_set_isa => self.line.isa = try!(leb128::read_u64(r)),
-            _ => {
-                if opcode < self.program.opcode_base {
+        let mut extended = None;
+        let mut skipped = false;
+        let mut length_mismatch = false;
+        let mut file = None;
+        if opcode == constant::DW_LNS_extended.0 {
+            let (opcode, known, mismatch) = try!(self.next_extended(r, &mut file));
+            extended = Some(opcode);
+            skipped = !known;
+            length_mismatch = mismatch;
+        } else if opcode >= self.program.opcode_base {
+            // Opcodes from opcode_base upwards are special opcodes, even if
+            // they have the value of a standard opcode. An opcode_base of 1
+            // means that there are no standard opcodes.
+            self.advance_special(opcode);
+            self.copy = true;
+        } else {
+            match constant::DwLns(opcode) {
+                constant::DW_LNS_copy => self.copy = true,
+                constant::DW_LNS_advance_pc => self.advance_pc(try!(leb128::read_u64(r))),
+                constant::DW_LNS_advance_line => self.advance_line(try!(leb128::read_i64(r))),
+                constant::DW_LNS_set_file => self.line.file = try!(leb128::read_u64(r)),
+                constant::DW_LNS_set_column => self.line.column = try!(leb128::read_u64(r)),
+                constant::DW_LNS_negate_stmt => self.line.statement = !self.line.statement,
+                constant::DW_LNS_set_basic_block => self.line.basic_block = true,
+                constant::DW_LNS_const_add_pc => {
+                    let op_delta = (255 - self.program.opcode_base) / self.program.line_range;
+                    self.advance_pc(op_delta as u64);
+                }
+                constant::DW_LNS_fixed_advance_pc => {
+                    let delta = try!(self.program.endian.read_u16(r)) as u64;
+                    self.line.address = self.line.address.wrapping_add(delta);
+                    self.line.operation = 0;
+                }
+                constant::DW_LNS_set_prologue_end => self.line.prologue_end = true,
+                constant::DW_LNS_set_epilogue_begin => self.line.epilogue_begin = true,
+                constant::DW_LNS_set_isa => self.line.isa = try!(leb128::read_u64(r)),
+                _ => {
                     // Unknown opcode, skip over it
                     let index = opcode as usize - 1;
                     if index >= self.program.standard_opcode_lengths.len() {
@@ -228,22 +516,31 @@ impl<'data, E: Endian> LineIterator<'data, E> {
                     for _ in 0..self.program.standard_opcode_lengths[index] {
                         try!(leb128::read_u64(r));
                     }
-                } else {
-                    self.advance_special(opcode);
-                    self.copy = true;
+                    skipped = true;
                 }
             }
         }
-        Ok(())
+        if let Some(ref mut trace) = self.trace {
+            trace.push(TraceOpcode {
+                offset: offset,
+                opcode: opcode,
+                extended: extended,
+                skipped: skipped,
+                length_mismatch: length_mismatch,
+            });
+        }
+        Ok(file)
     }
 
-    fn next_extended(&mut self, r: &mut &'data [u8]) -> Result<(), ReadError> {
+    // Returns the extended opcode, whether it was recognized, and whether
+    // its length was longer than its operands.
+    fn next_extended<'r>(
+        &mut self,
+        r: &mut &'r [u8],
+        file: &mut Option<FileEntry<'r>>
+    ) -> Result<(u8, bool, bool), ReadError> {
         let len = try!(leb128::read_u64(r)) as usize;
-        if len > r.len() {
-            return Err(ReadError::Invalid);
-        }
-        let mut data = &r[..len];
-        *r = &r[len..];
+        let mut data = try!(read_block(r, len));
 
         let opcode = try!(read_u8(&mut data));
         match constant::DwLne(opcode) {
@@ -258,16 +555,21 @@ impl<'data, E: Endian> LineIterator<'data, E> {
                 self.line.operation = 0;
             }
             constant::DW_LNE_define_file => {
-                self.program.files.push(try!(FileEntry::read(&mut data)));
+                *file = Some(try!(FileEntry::read(&mut data)));
             }
             constant::DW_LNE_set_discriminator => {
                 self.line.discriminator = try!(leb128::read_u64(&mut data));
             }
             _ => {
                 // Unknown opcode, we've already skipped over it
+                return Ok((opcode, false, false));
             }
         }
-        Ok(())
+        let mismatch = !data.is_empty();
+        if mismatch && self.strict_lengths {
+            return Err(ReadError::Invalid);
+        }
+        Ok((opcode, true, mismatch))
     }
 
     fn advance_special(&mut self, opcode: u8) {
@@ -279,11 +581,14 @@ impl<'data, E: Endian> LineIterator<'data, E> {
         self.advance_line(line_delta);
     }
 
+    // Addresses wrap rather than overflow, since the advances come from
+    // the input.
     fn advance_pc(&mut self, op_delta: u64) {
-        let operation = self.line.operation + op_delta;
+        let operation = self.line.operation.wrapping_add(op_delta);
         let address_delta = operation / self.program.operation_range as u64;
         self.line.operation = operation % self.program.operation_range as u64;
-        self.line.address += self.program.address_step as u64 * address_delta;
+        let address_delta = address_delta.wrapping_mul(self.program.address_step as u64);
+        self.line.address = self.line.address.wrapping_add(address_delta);
     }
 
     fn advance_line(&mut self, delta: i64) {
@@ -291,7 +596,190 @@ impl<'data, E: Endian> LineIterator<'data, E> {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+// A line program whose opcodes are read through a `ChunkReader`.
+//
+// Only the header is stored in memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkedLineProgram {
+    // The offset of the program in .debug_line.
+    pub offset: usize,
+    pub offset_size: u8,
+    // The header data following the initial length.
+    pub header: Vec<u8>,
+    // The range of the opcodes in .debug_line.
+    pub opcodes: Range<usize>,
+}
+
+impl ChunkedLineProgram {
+    pub fn read<E: Endian, R: ReadAt + ?Sized>(
+        reader: &mut ChunkReader<R>,
+        offset: usize,
+        endian: E
+    ) -> Result<ChunkedLineProgram, ReadError> {
+        let (offset_size, start, end) = {
+            let data = try!(reader.fill(offset, 12));
+            let mut r = data;
+            let (offset_size, len) = try!(read_unit_length(&mut r, endian));
+            let start = offset + data.len() - r.len();
+            (offset_size, start, try!(start.checked_add(len).ok_or(ReadError::Invalid)))
+        };
+        if end > reader.len() {
+            return Err(ReadError::Invalid);
+        }
+
+        let header_end = {
+            let mut r = try!(reader.read(start, 2));
+            let version = try!(endian.read_u16(&mut r));
+            // Version 5 has the address and segment selector sizes before
+            // the header length.
+            let fixed_len = (if version >= 5 { 4 } else { 2 }) + offset_size as usize;
+            let mut r = try!(reader.read(start + fixed_len - offset_size as usize,
+                                         offset_size as usize));
+            let header_length = try!(read_offset(&mut r, endian, offset_size)) as usize;
+            try!((start + fixed_len).checked_add(header_length).ok_or(ReadError::Invalid))
+        };
+        if header_end > end {
+            return Err(ReadError::Invalid);
+        }
+        let header = try!(reader.read(start, header_end - start)).to_vec();
+
+        Ok(ChunkedLineProgram {
+            offset: offset,
+            offset_size: offset_size,
+            header: header,
+            opcodes: header_end..end,
+        })
+    }
+
+    // Parse the header. The `data` of the returned program is empty.
+    pub fn program<'h, E: Endian>(
+        &'h self,
+        endian: E,
+        address_size: u8,
+        comp_dir: &'h [u8],
+        comp_name: &'h [u8]
+    ) -> Result<LineProgram<'h, E>, ReadError> {
+        let location = LineProgramLocation {
+            offset: self.offset,
+            address_size: address_size,
+            comp_dir: comp_dir,
+            comp_name: comp_name,
+        };
+        LineProgram::read_header(&self.header,
+                                 &location,
+                                 endian,
+                                 self.offset_size,
+                                 LineStrings::default())
+    }
+
+    // Iterate over the rows, reading the opcodes from `reader`.
+    pub fn lines<'h, 'a, E: Endian, R: ReadAt + ?Sized>(
+        &'h self,
+        reader: ChunkReader<'a, R>,
+        endian: E,
+        address_size: u8,
+        comp_dir: &'h [u8],
+        comp_name: &'h [u8]
+    ) -> Result<ChunkedLineIterator<'h, 'a, E, R>, ReadError> {
+        let program = try!(self.program(endian, address_size, comp_dir, comp_name));
+        Ok(ChunkedLineIterator {
+            lines: LineIterator::new(program),
+            reader: reader,
+            offset: self.opcodes.start,
+            opcodes: self.opcodes.clone(),
+        })
+    }
+}
+
+// An iterator over the rows of a `ChunkedLineProgram`.
+//
+// This returns `ReadError::Unsupported` for DW_LNE_define_file opcodes,
+// since the file entry can't refer to the data in the chunk buffer.
+// Producers don't normally use this opcode.
+pub struct ChunkedLineIterator<'h, 'a, E: Endian + 'h, R: ReadAt + ?Sized + 'a> {
+    lines: LineIterator<'h, E>,
+    reader: ChunkReader<'a, R>,
+    // The offset in .debug_line of the next opcode.
+    offset: usize,
+    opcodes: Range<usize>,
+}
+
+impl<'h, 'a, E: Endian, R: ReadAt + ?Sized> ChunkedLineIterator<'h, 'a, E, R> {
+    // See `LineIterator::enable_strict_lengths`.
+    pub fn enable_strict_lengths(&mut self) {
+        self.lines.enable_strict_lengths();
+    }
+
+    // See `LineIterator::enable_trace`.
+    pub fn enable_trace(&mut self) {
+        self.lines.enable_trace();
+    }
+
+    pub fn trace(&self) -> &[TraceOpcode] {
+        self.lines.trace()
+    }
+
+    // The offset within the program data of the next opcode.
+    pub fn offset(&self) -> usize {
+        self.offset - self.opcodes.start
+    }
+
+    pub fn directories(&self) -> &Vec<&'h [u8]> {
+        self.lines.directories()
+    }
+
+    pub fn files<'b>(&'b self) -> &'b Vec<FileEntry<'b>> {
+        self.lines.files()
+    }
+
+    pub fn directory(&self, index: u64) -> Option<&'h [u8]> {
+        self.lines.directory(index)
+    }
+
+    pub fn file(&self, index: u64) -> Option<&FileEntry<'h>> {
+        self.lines.file(index)
+    }
+
+    #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
+    pub fn next(&mut self) -> Result<Option<&Line>, ReadError> {
+        if self.offset >= self.opcodes.end {
+            return Ok(None);
+        }
+
+        self.lines.start_row();
+        let mut len = 1;
+        loop {
+            let remaining = self.opcodes.end - self.offset;
+            let offset = self.offset - self.opcodes.start;
+            let consumed = {
+                let data = try!(self.reader.fill(self.offset, len));
+                let data = &data[..std::cmp::min(data.len(), remaining)];
+                let mut r = data;
+                match self.lines.next_opcode(&mut r, offset) {
+                    Ok(None) => data.len() - r.len(),
+                    Ok(Some(_)) => return Err(ReadError::Unsupported),
+                    Err(e) => {
+                        if data.len() == remaining {
+                            return Err(e);
+                        }
+                        // The opcode may continue past the data, so read
+                        // more and try again.
+                        len = 2 * data.len();
+                        continue;
+                    }
+                }
+            };
+            self.offset += consumed;
+            len = 1;
+            if self.lines.copy {
+                self.lines.copy = false;
+                return Ok(Some(&self.lines.line));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Line {
     pub address: u64,
     pub operation: u64,
@@ -326,7 +814,352 @@ impl Line {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+// The rows of a line program, for lookups that need random access.
+//
+// Use `LineIterator` instead if the rows only need to be visited once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineTable<'data> {
+    pub directories: Vec<&'data [u8]>,
+    // The file entries, including any added by DW_LNE_define_file.
+    pub files: Vec<FileEntry<'data>>,
+    // The rows, in program order.
+    pub rows: Vec<Line>,
+}
+
+// Options for `LineTable::find_row`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LookupOptions {
+    // Prefer rows that are recommended breakpoint locations when several
+    // rows have the same address.
+    pub prefer_statement: bool,
+    // Only return a row whose address is equal to the requested address.
+    pub exact_only: bool,
+}
+
+impl Default for LookupOptions {
+    fn default() -> Self {
+        LookupOptions {
+            prefer_statement: true,
+            exact_only: false,
+        }
+    }
+}
+
+// The addresses found for a source line by `LineTable::find_line`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMatch {
+    // The line that the addresses are for. This is greater than the line
+    // that was requested if there were no rows for that line.
+    pub line: u64,
+    // The addresses of the rows for the line, sorted and without
+    // duplicates.
+    pub addresses: Vec<u64>,
+}
+
+impl<'data> LineTable<'data> {
+    // Read all of the rows of a line program.
+    pub fn read<E: Endian>(
+        mut lines: LineIterator<'data, E>
+    ) -> Result<LineTable<'data>, ReadError> {
+        let mut rows = Vec::new();
+        while let Some((_, line)) = try!(lines.next()) {
+            rows.push(line.clone());
+        }
+        Ok(LineTable {
+            directories: lines.program.include_directories,
+            files: lines.program.files,
+            rows: rows,
+        })
+    }
+
+    // Return the include directory for a directory index.
+    //
+    // See `LineProgram::directory`.
+    pub fn directory(&self, index: u64) -> Option<&'data [u8]> {
+        directory(&self.directories, index)
+    }
+
+    // Return the file entry for a file index.
+    //
+    // See `LineProgram::file`.
+    pub fn file(&self, index: u64) -> Option<&FileEntry<'data>> {
+        file(&self.files, index)
+    }
+
+    // Return the indices of the files with the given path.
+    //
+    // A file matches if either its path or its full path is equal to
+    // `path`. There may be more than one match, since producers sometimes
+    // emit duplicate file entries.
+    pub fn file_indices(&self, path: &[u8]) -> Vec<u64> {
+        self.files
+            .iter()
+            .enumerate()
+            .filter(|&(_, file)| file.path == path || file.full_path(&self.directories) == path)
+            .map(|(index, _)| index as u64)
+            .collect()
+    }
+
+    // Return the addresses of the rows for a line of a file.
+    //
+    // Rows that end a sequence are excluded, since their address is after
+    // the last instruction.
+    pub fn addresses(&self, file: u64, line: u64) -> Vec<u64> {
+        self.line_addresses(&[file], line)
+    }
+
+    // Return the addresses for a line of a file, or for the nearest
+    // following line that has rows.
+    //
+    // This is the lookup used to set a breakpoint on a source line, which
+    // may have no code of its own, such as a blank line or comment.
+    pub fn find_line(&self, file: u64, line: u64) -> Option<LineMatch> {
+        self.find_line_in(&[file], line)
+    }
+
+    // Like `find_line`, but for all of the files with the given path.
+    pub fn find_path_line(&self, path: &[u8], line: u64) -> Option<LineMatch> {
+        self.find_line_in(&self.file_indices(path), line)
+    }
+
+    // Return the row for an address.
+    //
+    // The row is chosen from the sequence containing the address. This is
+    // the last row whose address is less than or equal to `address`. If
+    // there are several rows at that address, then the last one is chosen,
+    // unless `options.prefer_statement` is set and some of them are
+    // statements, in which case the last statement is chosen.
+    pub fn find_row(&self, address: u64, options: LookupOptions) -> Option<&Line> {
+        let mut best: Option<&Line> = None;
+        for row in &self.rows {
+            if row.sequence_end {
+                if address < row.address {
+                    if let Some(row) = best {
+                        if options.exact_only && row.address != address {
+                            return None;
+                        }
+                        return Some(row);
+                    }
+                }
+                best = None;
+            } else if row.address <= address {
+                best = match best {
+                    Some(prev) if prev.address == row.address && options.prefer_statement &&
+                                  prev.statement && !row.statement => Some(prev),
+                    _ => Some(row),
+                };
+            }
+        }
+        // The address isn't in any sequence, or the last sequence wasn't
+        // ended.
+        None
+    }
+
+    fn find_line_in(&self, files: &[u64], line: u64) -> Option<LineMatch> {
+        let nearest = self.rows
+            .iter()
+            .filter(|row| !row.sequence_end && files.contains(&row.file) && row.line >= line)
+            .map(|row| row.line)
+            .min();
+        nearest.map(|nearest| {
+            LineMatch {
+                line: nearest,
+                addresses: self.line_addresses(files, nearest),
+            }
+        })
+    }
+
+    fn line_addresses(&self, files: &[u64], line: u64) -> Vec<u64> {
+        let mut addresses: Vec<u64> = self.rows
+            .iter()
+            .filter(|row| !row.sequence_end && files.contains(&row.file) && row.line == line)
+            .map(|row| row.address)
+            .collect();
+        addresses.sort();
+        addresses.dedup();
+        addresses
+    }
+}
+
+// The offset of a line program in .debug_line, and the values from the
+// unit entry that are needed to read it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineProgramLocation<'data> {
+    pub offset: usize,
+    pub address_size: u8,
+    // The DW_AT_comp_dir of the unit, which is directory 0.
+    pub comp_dir: &'data [u8],
+    // The DW_AT_name of the unit, which is file 0.
+    pub comp_name: &'data [u8],
+}
+
+impl<'data> LineProgramLocation<'data> {
+    pub fn read<E: Endian>(
+        &self,
+        debug_line: &'data [u8],
+        strings: LineStrings<'data>,
+        endian: E
+    ) -> Result<LineProgram<'data, E>, ReadError> {
+        if self.offset >= debug_line.len() {
+            return Err(ReadError::Invalid);
+        }
+        let mut r = &debug_line[self.offset..];
+        LineProgram::read_with_strings(&mut r,
+                                       self.offset,
+                                       endian,
+                                       self.address_size,
+                                       self.comp_dir,
+                                       self.comp_name,
+                                       strings)
+    }
+}
+
+// A cache of line programs, keyed by their location.
+//
+// Units may share a line program, such as after dwz, and symbolication
+// reads the program of the same unit repeatedly. The cache holds the
+// headers of the programs, and the rows of the programs that were
+// requested with `table`.
+//
+// The size of each program is estimated from its tables, not counting the
+// section data that it borrows. When the total exceeds the budget, the
+// least recently used programs are evicted. The most recently used program
+// is always kept, even if it exceeds the budget by itself.
+#[derive(Debug)]
+pub struct LineProgramCache<'data, E: Endian> {
+    budget: usize,
+    size: usize,
+    // The programs in order of use, with the most recently used last.
+    entries: Vec<CachedProgram<'data, E>>,
+}
+
+#[derive(Debug)]
+struct CachedProgram<'data, E: Endian> {
+    location: LineProgramLocation<'data>,
+    program: Rc<LineProgram<'data, E>>,
+    table: Option<Rc<LineTable<'data>>>,
+    size: usize,
+}
+
+impl<'data, E: Endian + 'data> LineProgramCache<'data, E> {
+    // Create a cache that holds about `budget` bytes.
+    pub fn new(budget: usize) -> Self {
+        LineProgramCache {
+            budget: budget,
+            size: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // The estimated size of the cached programs.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.size = 0;
+    }
+
+    // Return the program at `location`, reading it if it isn't in the cache.
+    //
+    // Units that share a program but have a different DW_AT_comp_dir or
+    // DW_AT_name use separate entries, since those are the first
+    // directory and file of the program.
+    pub fn program(
+        &mut self,
+        debug_line: &'data [u8],
+        strings: LineStrings<'data>,
+        endian: E,
+        location: LineProgramLocation<'data>
+    ) -> Result<Rc<LineProgram<'data, E>>, ReadError> {
+        if self.touch(&location) {
+            return Ok(self.entries.last().unwrap().program.clone());
+        }
+        let program = Rc::new(try!(location.read(debug_line, strings, endian)));
+        self.insert(CachedProgram {
+            location: location,
+            program: program.clone(),
+            table: None,
+            size: program_size(&program),
+        });
+        Ok(program)
+    }
+
+    // Return all of the rows of the program at `location`, reading them if
+    // they aren't in the cache.
+    pub fn table(
+        &mut self,
+        debug_line: &'data [u8],
+        strings: LineStrings<'data>,
+        endian: E,
+        location: LineProgramLocation<'data>
+    ) -> Result<Rc<LineTable<'data>>, ReadError> {
+        let program = try!(self.program(debug_line, strings, endian, location));
+        if let Some(ref table) = self.entries.last().unwrap().table {
+            return Ok(table.clone());
+        }
+        let table = Rc::new(try!(LineTable::read(program.lines())));
+        let size = table_size(&table);
+        self.size += size;
+        {
+            let entry = self.entries.last_mut().unwrap();
+            entry.table = Some(table.clone());
+            entry.size += size;
+        }
+        self.evict();
+        Ok(table)
+    }
+
+    // Make the entry for `location` the most recently used.
+    //
+    // Returns false if there is no entry.
+    fn touch(&mut self, location: &LineProgramLocation<'data>) -> bool {
+        match self.entries.iter().position(|entry| entry.location == *location) {
+            Some(index) => {
+                let entry = self.entries.remove(index);
+                self.entries.push(entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn insert(&mut self, entry: CachedProgram<'data, E>) {
+        self.size += entry.size;
+        self.entries.push(entry);
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.budget && self.entries.len() > 1 {
+            let entry = self.entries.remove(0);
+            self.size -= entry.size;
+        }
+    }
+}
+
+fn program_size<E: Endian>(program: &LineProgram<E>) -> usize {
+    std::mem::size_of::<LineProgram<E>>() +
+    program.include_directories.len() * std::mem::size_of::<&[u8]>() +
+    program.files.len() * std::mem::size_of::<FileEntry>()
+}
+
+fn table_size(table: &LineTable) -> usize {
+    std::mem::size_of::<LineTable>() + table.directories.len() * std::mem::size_of::<&[u8]>() +
+    table.files.len() * std::mem::size_of::<FileEntry>() +
+    table.rows.len() * std::mem::size_of::<Line>()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct FileEntry<'data> {
     pub path: &'data [u8],
     pub directory: u64,
@@ -345,6 +1178,172 @@ impl<'data> Default for FileEntry<'data> {
     }
 }
 
+fn directory<'data>(directories: &[&'data [u8]], index: u64) -> Option<&'data [u8]> {
+    if index > usize::max_value() as u64 {
+        return None;
+    }
+    directories.get(index as usize).cloned()
+}
+
+fn file<'a, 'data>(files: &'a [FileEntry<'data>], index: u64) -> Option<&'a FileEntry<'data>> {
+    if index > usize::max_value() as u64 {
+        return None;
+    }
+    files.get(index as usize)
+}
+
+// The string sections that may be referenced by the directory and file
+// entries of a version 5 line program.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LineStrings<'data> {
+    pub debug_str: &'data [u8],
+    pub debug_line_str: &'data [u8],
+}
+
+impl<'data> LineStrings<'data> {
+    fn string(&self, form: constant::DwForm, offset: u64) -> Result<&'data [u8], ReadError> {
+        let section = match form {
+            constant::DW_FORM_strp => self.debug_str,
+            constant::DW_FORM_line_strp => self.debug_line_str,
+            _ => return Err(ReadError::Unsupported),
+        };
+        if offset >= section.len() as u64 {
+            return Err(ReadError::Invalid);
+        }
+        let mut r = &section[offset as usize..];
+        read_string(&mut r).map_err(|_| ReadError::Invalid)
+    }
+}
+
+// Read the directory and file tables of a version 2-4 header. The
+// compilation directory and file are inserted at index 0.
+fn read_entries<'data>(
+    header: &mut &'data [u8],
+    comp_dir: &'data [u8],
+    comp_name: &'data [u8]
+) -> Result<(Vec<&'data [u8]>, Vec<FileEntry<'data>>), ReadError> {
+    let mut include_directories = vec![comp_dir];
+    loop {
+        if header.is_empty() {
+            return Err(ReadError::Invalid);
+        }
+        if header[0] == 0 {
+            *header = &header[1..];
+            break;
+        }
+        include_directories.push(try!(read_string(header)));
+    }
+
+    let mut files = vec![FileEntry {
+                             path: comp_name,
+                             directory: 0,
+                             timestamp: 0,
+                             length: 0,
+                         }];
+    loop {
+        if header.is_empty() {
+            return Err(ReadError::Invalid);
+        }
+        if header[0] == 0 {
+            *header = &header[1..];
+            break;
+        }
+        files.push(try!(FileEntry::read(header)));
+    }
+    Ok((include_directories, files))
+}
+
+// Read the directory and file tables of a version 5 header. Each table is
+// preceded by the content types and forms of its entries.
+fn read_entries_v5<'data, E: Endian>(
+    header: &mut &'data [u8],
+    endian: E,
+    offset_size: u8,
+    strings: LineStrings<'data>
+) -> Result<(Vec<&'data [u8]>, Vec<FileEntry<'data>>), ReadError> {
+    let formats = try!(read_entry_formats(header));
+    let count = try!(leb128::read_u64(header));
+    let mut include_directories = Vec::new();
+    for _ in 0..count {
+        let entry = try!(read_entry_v5(header, endian, offset_size, strings, &formats));
+        include_directories.push(entry.path);
+    }
+
+    let formats = try!(read_entry_formats(header));
+    let count = try!(leb128::read_u64(header));
+    let mut files = Vec::new();
+    for _ in 0..count {
+        files.push(try!(read_entry_v5(header, endian, offset_size, strings, &formats)));
+    }
+    Ok((include_directories, files))
+}
+
+fn read_entry_formats(
+    header: &mut &[u8]
+) -> Result<Vec<(constant::DwLnct, constant::DwForm)>, ReadError> {
+    let count = try!(read_u8(header));
+    let mut formats = Vec::new();
+    for _ in 0..count {
+        let content = try!(leb128::read_u16(header));
+        let form = try!(leb128::read_u16(header));
+        formats.push((constant::DwLnct(content), constant::DwForm(form)));
+    }
+    Ok(formats)
+}
+
+// Read a version 5 directory or file entry. Content types that aren't
+// stored in `FileEntry`, such as DW_LNCT_MD5, are skipped.
+fn read_entry_v5<'data, E: Endian>(
+    header: &mut &'data [u8],
+    endian: E,
+    offset_size: u8,
+    strings: LineStrings<'data>,
+    formats: &[(constant::DwLnct, constant::DwForm)]
+) -> Result<FileEntry<'data>, ReadError> {
+    let mut entry = FileEntry::default();
+    for &(content, form) in formats {
+        if content == constant::DW_LNCT_path {
+            entry.path = match form {
+                constant::DW_FORM_string => try!(read_string(header)),
+                constant::DW_FORM_strp |
+                constant::DW_FORM_line_strp => {
+                    let offset = try!(read_offset(header, endian, offset_size));
+                    try!(strings.string(form, offset))
+                }
+                _ => return Err(ReadError::Unsupported),
+            };
+            continue;
+        }
+        let val = match form {
+            constant::DW_FORM_data1 => try!(read_u8(header)) as u64,
+            constant::DW_FORM_data2 => try!(endian.read_u16(header)) as u64,
+            constant::DW_FORM_data4 => try!(endian.read_u32(header)) as u64,
+            constant::DW_FORM_data8 => try!(endian.read_u64(header)),
+            constant::DW_FORM_udata => try!(leb128::read_u64(header)),
+            constant::DW_FORM_data16 => {
+                try!(read_block(header, 16));
+                0
+            }
+            constant::DW_FORM_block => {
+                let len = try!(leb128::read_u64(header));
+                if len > header.len() as u64 {
+                    return Err(ReadError::Invalid);
+                }
+                try!(read_block(header, len as usize));
+                0
+            }
+            _ => return Err(ReadError::Unsupported),
+        };
+        match content {
+            constant::DW_LNCT_directory_index => entry.directory = val,
+            constant::DW_LNCT_timestamp => entry.timestamp = val,
+            constant::DW_LNCT_size => entry.length = val,
+            _ => {}
+        }
+    }
+    Ok(entry)
+}
+
 impl<'data> FileEntry<'data> {
     pub fn read(r: &mut &'data [u8]) -> Result<FileEntry<'data>, ReadError> {
         let path = try!(read_string(r));
@@ -359,4 +1358,430 @@ impl<'data> FileEntry<'data> {
             length: length,
         })
     }
+
+    // Return the path of the file, prefixed by its directory unless the
+    // path is absolute.
+    pub fn full_path(&self, directories: &[&[u8]]) -> Vec<u8> {
+        let mut path = Vec::new();
+        if self.path.first() != Some(&b'/') {
+            if let Some(dir) = directory(directories, self.directory) {
+                if !dir.is_empty() {
+                    path.extend_from_slice(dir);
+                    path.push(b'/');
+                }
+            }
+        }
+        path.extend_from_slice(self.path);
+        path
+    }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        try!(w.write_all(self.path));
+        try!(write_u8(w, 0));
+        try!(leb128::write_u64(w, self.directory));
+        try!(leb128::write_u64(w, self.timestamp));
+        try!(leb128::write_u64(w, self.length));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use constant::*;
+    use endian::*;
+
+    fn program<'data>(
+        opcode_base: u8,
+        standard_opcode_lengths: &'data [u8],
+        data: &'data [u8]
+    ) -> LineProgram<'data, LittleEndian> {
+        LineProgram {
+            offset: 0,
+            endian: LittleEndian,
+            version: 2,
+            address_size: 4,
+            offset_size: 4,
+            address_step: 1,
+            operation_range: 1,
+            default_statement: true,
+            line_base: 0,
+            line_range: 4,
+            opcode_base: opcode_base,
+            standard_opcode_lengths: standard_opcode_lengths,
+            include_directories: vec![b""],
+            files: vec![FileEntry::default()],
+            data: data,
+        }
+    }
+
+    fn rows(program: LineProgram<LittleEndian>) -> Vec<(u64, u64, bool)> {
+        let mut lines = program.lines();
+        let mut rows = Vec::new();
+        while let Some((_, line)) = lines.next().unwrap() {
+            rows.push((line.address, line.line, line.statement));
+        }
+        rows
+    }
+
+    #[test]
+    fn cache() {
+        let data = [DW_LNS_copy.0, 0, 1, DW_LNE_end_sequence.0];
+        let mut debug_line = Vec::new();
+        program(13, &[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1], &data).write(&mut debug_line).unwrap();
+        let second = debug_line.len();
+        program(13, &[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1], &[]).write(&mut debug_line).unwrap();
+
+        let location = |offset| {
+            LineProgramLocation {
+                offset: offset,
+                address_size: 4,
+                comp_dir: b"/tmp",
+                comp_name: b"a.c",
+            }
+        };
+        let strings = LineStrings::default();
+        let mut cache = LineProgramCache::new(usize::max_value());
+        let first = cache.program(&debug_line, strings, LittleEndian, location(0)).unwrap();
+        assert_eq!(first.include_directories[0], b"/tmp");
+        let again = cache.program(&debug_line, strings, LittleEndian, location(0)).unwrap();
+        assert!(Rc::ptr_eq(&first, &again));
+        assert_eq!(cache.len(), 1);
+
+        let table = cache.table(&debug_line, strings, LittleEndian, location(0)).unwrap();
+        assert_eq!(table.rows.len(), 2);
+        let again = cache.table(&debug_line, strings, LittleEndian, location(0)).unwrap();
+        assert!(Rc::ptr_eq(&table, &again));
+        assert_eq!(cache.len(), 1);
+
+        // A different name is a different entry.
+        let other = LineProgramLocation { comp_name: b"b.c", ..location(0) };
+        let other = cache.program(&debug_line, strings, LittleEndian, other).unwrap();
+        assert_eq!(other.files[0].path, b"b.c");
+        assert_eq!(cache.len(), 2);
+
+        // Only the most recently used program fits.
+        let mut cache = LineProgramCache::new(1);
+        cache.program(&debug_line, strings, LittleEndian, location(0)).unwrap();
+        cache.program(&debug_line, strings, LittleEndian, location(second)).unwrap();
+        assert_eq!(cache.len(), 1);
+        assert!(cache.size() > 1);
+        assert!(cache.program(&debug_line, strings, LittleEndian, location(debug_line.len()))
+            .is_err());
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(cache.size(), 0);
+    }
+
+    #[test]
+    fn file_index() {
+        let mut write_val = program(1, &[], &[]);
+        write_val.include_directories = vec![b"/comp", b"inc"];
+        write_val.files = vec![
+            FileEntry { path: b"main.c", ..Default::default() },
+            FileEntry { path: b"a.h", directory: 1, ..Default::default() },
+        ];
+        let mut buf = Vec::new();
+        write_val.write(&mut buf).unwrap();
+
+        let read_val = LineProgram::read(&mut &buf[..], 0, LittleEndian, 4, b"/comp", b"main.c")
+            .unwrap();
+        assert_eq!(read_val.directory(0), Some(&b"/comp"[..]));
+        assert_eq!(read_val.directory(1), Some(&b"inc"[..]));
+        assert_eq!(read_val.directory(2), None);
+        assert_eq!(read_val.file(0).unwrap().path, b"main.c");
+        let file = read_val.file(1).unwrap();
+        assert_eq!(file.path, b"a.h");
+        assert_eq!(read_val.directory(file.directory), Some(&b"inc"[..]));
+        assert_eq!(read_val.file(2), None);
+        assert_eq!(read_val.file(u64::max_value()), None);
+
+        let lines = read_val.lines();
+        assert_eq!(lines.file(1), Some(file));
+        let table = LineTable::read(read_val.lines()).unwrap();
+        assert_eq!(table.file(1), Some(file));
+        assert_eq!(table.directory(1), Some(&b"inc"[..]));
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn version_5() {
+        let mut write_val = program(1, &[], &[]);
+        write_val.version = 5;
+        write_val.address_size = 8;
+        write_val.include_directories = vec![b"/comp", b"inc"];
+        write_val.files = vec![
+            FileEntry { path: b"main.c", ..Default::default() },
+            FileEntry { path: b"a.h", directory: 1, timestamp: 2, length: 3 },
+        ];
+        let mut buf = Vec::new();
+        write_val.write(&mut buf).unwrap();
+
+        // Directory and file 0 are encoded in the header.
+        let read_val = LineProgram::read(&mut &buf[..], 0, LittleEndian, 4, b"", b"").unwrap();
+        assert_eq!(read_val, write_val);
+
+        // The path of file 1 in .debug_line_str, with an MD5.
+        let mut header = vec![
+            1, DW_LNCT_path.0 as u8, DW_FORM_string.0 as u8,
+            1, b'/', b'c', 0,
+            3,
+            DW_LNCT_path.0 as u8, DW_FORM_line_strp.0 as u8,
+            DW_LNCT_directory_index.0 as u8, DW_FORM_data1.0 as u8,
+            DW_LNCT_MD5.0 as u8, DW_FORM_data16.0 as u8,
+            1, 1, 0, 0, 0, 0,
+        ];
+        header.extend_from_slice(&[0xff; 16]);
+        let mut buf = vec![0, 0, 0, 0, 5, 0, 8, 0];
+        buf.extend_from_slice(&[6 + header.len() as u8, 0, 0, 0, 1, 1, 1, 0, 4, 1]);
+        buf.extend_from_slice(&header);
+        let len = buf.len() as u8 - 4;
+        buf[0] = len;
+        let strings = LineStrings { debug_line_str: b"\0a.c\0", ..Default::default() };
+        let read_val =
+            LineProgram::read_with_strings(&mut &buf[..], 0, LittleEndian, 4, b"", b"", strings)
+                .unwrap();
+        assert_eq!(read_val.address_size, 8);
+        assert_eq!(read_val.include_directories, vec![b"/c"]);
+        assert_eq!(read_val.files, vec![FileEntry { path: b"a.c", ..Default::default() }]);
+        assert!(LineProgram::read(&mut &buf[..], 0, LittleEndian, 4, b"", b"").is_err());
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn opcode_base() {
+        // With an opcode_base of 1, all opcodes except 0 are special.
+        let data = [
+            0, 5, DW_LNE_set_address.0, 0x00, 0x10, 0x00, 0x00,
+            // Special opcode 0: no advance.
+            DW_LNS_copy.0,
+            // Special opcode 5: advance address by 1 and line by 1.
+            DW_LNS_negate_stmt.0,
+            0, 1, DW_LNE_end_sequence.0,
+        ];
+        assert_eq!(rows(program(1, &[], &data)), vec![
+            (0x1000, 1, true),
+            (0x1001, 2, true),
+            (0x1001, 2, true),
+        ]);
+
+        // With an opcode_base of 10, the DWARF 3 standard opcodes are special.
+        let data = [
+            0, 5, DW_LNE_set_address.0, 0x00, 0x10, 0x00, 0x00,
+            DW_LNS_negate_stmt.0,
+            // Special opcode 0: no advance.
+            DW_LNS_set_prologue_end.0,
+            // Special opcode 2: advance line by 2.
+            DW_LNS_set_isa.0,
+            0, 1, DW_LNE_end_sequence.0,
+        ];
+        assert_eq!(rows(program(10, &[0, 1, 1, 1, 1, 0, 0, 0, 1], &data)), vec![
+            (0x1000, 1, false),
+            (0x1000, 3, false),
+            (0x1000, 3, false),
+        ]);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn address_overflow() {
+        let data = [
+            0, 5, DW_LNE_set_address.0, 0xff, 0xff, 0xff, 0xff,
+            DW_LNS_copy.0,
+            DW_LNS_fixed_advance_pc.0, 0x02, 0x00,
+            DW_LNS_copy.0,
+            DW_LNS_advance_pc.0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01,
+            0, 1, DW_LNE_end_sequence.0,
+        ];
+        let mut program = program(13, &[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1], &data);
+        program.address_step = 4;
+        assert_eq!(rows(program), vec![
+            (0xffff_ffff, 1, true),
+            (0x1_0000_0001, 1, true),
+            (0xffff_fffd, 1, true),
+        ]);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn trace() {
+        // Opcode 13 is an unknown standard opcode with two operands.
+        let data = [
+            13, 0x80, 0x01, 0x02,
+            0, 2, 0x80, 0xff,
+            DW_LNS_copy.0,
+            0, 1, DW_LNE_end_sequence.0,
+        ];
+        let program = program(14, &[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1, 2], &data);
+
+        let mut lines = program.lines();
+        lines.enable_trace();
+        {
+            let (iter, line) = lines.next().unwrap().unwrap();
+            assert_eq!(line.address, 0);
+            assert_eq!(iter.trace(), &[
+                TraceOpcode { offset: 0, opcode: 13, extended: None, skipped: true,
+                              length_mismatch: false },
+                TraceOpcode { offset: 4, opcode: 0, extended: Some(0x80), skipped: true,
+                              length_mismatch: false },
+                TraceOpcode { offset: 8, opcode: 1, extended: None, skipped: false,
+                              length_mismatch: false },
+            ]);
+        }
+        {
+            let (iter, line) = lines.next().unwrap().unwrap();
+            assert!(line.sequence_end);
+            assert_eq!(iter.trace(), &[
+                TraceOpcode {
+                    offset: 9,
+                    opcode: 0,
+                    extended: Some(DW_LNE_end_sequence.0),
+                    skipped: false,
+                    length_mismatch: false,
+                },
+            ]);
+        }
+        assert!(lines.next().unwrap().is_none());
+
+        // Tracing is off by default.
+        let mut lines = program.lines();
+        assert_eq!(lines.next().unwrap().unwrap().0.trace(), &[]);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn line_table() {
+        // File 1 line 1 at 0x1000, file 1 line 3 at 0x1004, file 2 line 3
+        // at 0x1008, file 1 line 3 at 0x100c, end at 0x1010.
+        let data = [
+            0, 5, DW_LNE_set_address.0, 0x00, 0x10, 0x00, 0x00,
+            DW_LNS_copy.0,
+            DW_LNS_advance_pc.0, 4, DW_LNS_advance_line.0, 2, DW_LNS_copy.0,
+            DW_LNS_advance_pc.0, 4, DW_LNS_set_file.0, 2, DW_LNS_copy.0,
+            DW_LNS_advance_pc.0, 4, DW_LNS_set_file.0, 1, DW_LNS_copy.0,
+            DW_LNS_advance_pc.0, 4,
+            0, 1, DW_LNE_end_sequence.0,
+        ];
+        let mut program = program(13, &[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1], &data);
+        program.include_directories = vec![b"/src", b"inc"];
+        program.files = vec![
+            FileEntry::default(),
+            FileEntry { path: b"a.c", ..Default::default() },
+            FileEntry { path: b"a.h", directory: 1, ..Default::default() },
+        ];
+        let table = LineTable::read(program.lines()).unwrap();
+        assert_eq!(table.rows.len(), 5);
+
+        assert_eq!(table.addresses(1, 3), vec![0x1004, 0x100c]);
+        assert_eq!(table.addresses(2, 3), vec![0x1008]);
+        assert_eq!(table.addresses(1, 2), vec![]);
+        assert_eq!(table.find_line(1, 2), Some(LineMatch { line: 3, addresses: vec![0x1004, 0x100c] }));
+        assert_eq!(table.find_line(1, 1), Some(LineMatch { line: 1, addresses: vec![0x1000] }));
+        assert_eq!(table.find_line(1, 4), None);
+
+        assert_eq!(table.file_indices(b"/src/a.c"), vec![1]);
+        assert_eq!(table.file_indices(b"a.h"), vec![2]);
+        assert_eq!(table.find_path_line(b"inc/a.h", 1), Some(LineMatch { line: 3, addresses: vec![0x1008] }));
+        assert_eq!(table.find_path_line(b"b.c", 1), None);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn find_row() {
+        // Sequence 1: line 1 at 0x1000, line 2 (not a statement) and line 3
+        // at 0x1004, line 4 (not a statement) at 0x1004, end at 0x1008.
+        // Sequence 2: line 10 at 0x2000, end at 0x2004.
+        let data = [
+            0, 5, DW_LNE_set_address.0, 0x00, 0x10, 0x00, 0x00,
+            DW_LNS_copy.0,
+            DW_LNS_advance_pc.0, 4, DW_LNS_advance_line.0, 1, DW_LNS_negate_stmt.0, DW_LNS_copy.0,
+            DW_LNS_advance_line.0, 1, DW_LNS_negate_stmt.0, DW_LNS_copy.0,
+            DW_LNS_advance_line.0, 1, DW_LNS_negate_stmt.0, DW_LNS_copy.0,
+            DW_LNS_advance_pc.0, 4,
+            0, 1, DW_LNE_end_sequence.0,
+            0, 5, DW_LNE_set_address.0, 0x00, 0x20, 0x00, 0x00,
+            DW_LNS_advance_line.0, 9, DW_LNS_copy.0,
+            DW_LNS_advance_pc.0, 4,
+            0, 1, DW_LNE_end_sequence.0,
+        ];
+        let program = program(13, &[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1], &data);
+        let table = LineTable::read(program.lines()).unwrap();
+        let line = |address, options| table.find_row(address, options).map(|row| row.line);
+        let default = LookupOptions::default();
+        let last = LookupOptions { prefer_statement: false, exact_only: false };
+        let exact = LookupOptions { prefer_statement: true, exact_only: true };
+
+        assert_eq!(line(0x1000, default), Some(1));
+        assert_eq!(line(0x1002, default), Some(1));
+        assert_eq!(line(0x1004, default), Some(3));
+        assert_eq!(line(0x1006, default), Some(3));
+        assert_eq!(line(0x1006, last), Some(4));
+        assert_eq!(line(0x1004, exact), Some(3));
+        assert_eq!(line(0x1006, exact), None);
+        assert_eq!(line(0x1008, default), None);
+        assert_eq!(line(0x0fff, default), None);
+        assert_eq!(line(0x2003, default), Some(10));
+        assert_eq!(line(0x2004, default), None);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn extended_length() {
+        // DW_LNE_set_address with 2 bytes of padding.
+        let data = [
+            0, 7, DW_LNE_set_address.0, 0x00, 0x10, 0x00, 0x00, 0xaa, 0xbb,
+            DW_LNS_copy.0,
+        ];
+        let padded = program(13, &[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1], &data);
+
+        let mut lines = padded.lines();
+        lines.enable_trace();
+        {
+            let (iter, line) = lines.next().unwrap().unwrap();
+            assert_eq!(line.address, 0x1000);
+            assert_eq!(iter.trace()[0], TraceOpcode {
+                offset: 0,
+                opcode: 0,
+                extended: Some(DW_LNE_set_address.0),
+                skipped: false,
+                length_mismatch: true,
+            });
+        }
+
+        let mut lines = padded.lines();
+        lines.enable_strict_lengths();
+        assert!(lines.next().is_err());
+
+        // The length is too short for the address.
+        let data = [0, 3, DW_LNE_set_address.0, 0x00, 0x10, 0x00, 0x00, DW_LNS_copy.0];
+        let short = program(13, &[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1], &data);
+        assert!(short.lines().next().is_err());
+    }
+
+    // Decode random extended opcodes, which must not panic.
+    #[test]
+    fn extended_random() {
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut random = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        for _ in 0..10000 {
+            let mut data = vec![0];
+            let len = random() % 12;
+            for _ in 0..len + 2 {
+                data.push(random() as u8);
+            }
+            // Mostly use small lengths and known opcodes.
+            data[1] = (random() % 10) as u8;
+            data[2] = (random() % 6) as u8;
+            let mut lines = program(13, &[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1], &data).lines();
+            if random() % 2 == 0 {
+                lines.enable_strict_lengths();
+            }
+            while let Ok(Some(_)) = lines.next() {}
+        }
+    }
 }
@@ -0,0 +1,542 @@
+use constant;
+use endian::Endian;
+use leb128;
+use read::*;
+
+// An entry of a location list, with its addresses resolved.
+//
+// The expression describes the location of the object for the addresses
+// from `begin` up to but not including `end`. A default location entry
+// applies to every address that isn't covered by another entry, and is
+// returned with a `begin` of 0 and an `end` of `u64::max_value()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocationListEntry<'data> {
+    pub begin: u64,
+    pub end: u64,
+    pub expression: &'data [u8],
+}
+
+// Return the offset in .debug_loclists of the location list at `index` in
+// the array of offsets at `base`, for a DW_FORM_loclistx value.
+//
+// `base` is the DW_AT_loclists_base of the unit. The offsets in the array
+// are relative to `base`. See `ranges::ListsHeader` for the header that
+// precedes the array.
+pub fn loclistx_offset<E: Endian>(
+    debug_loclists: &[u8],
+    endian: E,
+    offset_size: u8,
+    base: u64,
+    index: u64
+) -> Result<u64, ReadError> {
+    let offset = try!(read_indexed(debug_loclists, endian, offset_size, base, index));
+    base.checked_add(offset).ok_or(ReadError::Overflow)
+}
+
+// An entry in a location list in .debug_loclists.
+//
+// The `x` entries contain indexes in .debug_addr, and `OffsetPair`
+// contains offsets from the base address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocListEntry<'data> {
+    BaseAddressx(u64),
+    StartxEndx {
+        begin: u64,
+        end: u64,
+        expression: &'data [u8],
+    },
+    StartxLength {
+        begin: u64,
+        length: u64,
+        expression: &'data [u8],
+    },
+    OffsetPair {
+        begin: u64,
+        end: u64,
+        expression: &'data [u8],
+    },
+    DefaultLocation { expression: &'data [u8] },
+    BaseAddress(u64),
+    StartEnd {
+        begin: u64,
+        end: u64,
+        expression: &'data [u8],
+    },
+    StartLength {
+        begin: u64,
+        length: u64,
+        expression: &'data [u8],
+    },
+}
+
+impl<'data> LocListEntry<'data> {
+    // Read an entry, or return `None` for DW_LLE_end_of_list.
+    pub fn read<E: Endian>(
+        r: &mut &'data [u8],
+        endian: E,
+        address_size: u8
+    ) -> Result<Option<LocListEntry<'data>>, ReadError> {
+        let kind = constant::DwLle(try!(read_u8(r)));
+        let entry = match kind {
+            constant::DW_LLE_end_of_list => return Ok(None),
+            constant::DW_LLE_base_addressx => LocListEntry::BaseAddressx(try!(leb128::read_u64(r))),
+            constant::DW_LLE_startx_endx => {
+                LocListEntry::StartxEndx {
+                    begin: try!(leb128::read_u64(r)),
+                    end: try!(leb128::read_u64(r)),
+                    expression: try!(read_expression(r)),
+                }
+            }
+            constant::DW_LLE_startx_length => {
+                LocListEntry::StartxLength {
+                    begin: try!(leb128::read_u64(r)),
+                    length: try!(leb128::read_u64(r)),
+                    expression: try!(read_expression(r)),
+                }
+            }
+            constant::DW_LLE_offset_pair => {
+                LocListEntry::OffsetPair {
+                    begin: try!(leb128::read_u64(r)),
+                    end: try!(leb128::read_u64(r)),
+                    expression: try!(read_expression(r)),
+                }
+            }
+            constant::DW_LLE_default_location => {
+                LocListEntry::DefaultLocation { expression: try!(read_expression(r)) }
+            }
+            constant::DW_LLE_base_address => {
+                LocListEntry::BaseAddress(try!(read_address(r, endian, address_size)))
+            }
+            constant::DW_LLE_start_end => {
+                LocListEntry::StartEnd {
+                    begin: try!(read_address(r, endian, address_size)),
+                    end: try!(read_address(r, endian, address_size)),
+                    expression: try!(read_expression(r)),
+                }
+            }
+            constant::DW_LLE_start_length => {
+                LocListEntry::StartLength {
+                    begin: try!(read_address(r, endian, address_size)),
+                    length: try!(leb128::read_u64(r)),
+                    expression: try!(read_expression(r)),
+                }
+            }
+            _ => return Err(ReadError::Invalid),
+        };
+        Ok(Some(entry))
+    }
+}
+
+// Read a location description with a ULEB128 length.
+fn read_expression<'data>(r: &mut &'data [u8]) -> Result<&'data [u8], ReadError> {
+    let len = try!(leb128::read_u64(r));
+    if len > r.len() as u64 {
+        return Err(ReadError::Invalid);
+    }
+    read_block(r, len as usize)
+}
+
+// An iterator over the entries of a location list in .debug_loclists,
+// without resolving their addresses.
+#[derive(Debug, Clone)]
+pub struct RawLocListIterator<'data, E: Endian> {
+    r: &'data [u8],
+    endian: E,
+    address_size: u8,
+}
+
+impl<'data, E: Endian> RawLocListIterator<'data, E> {
+    // Iterate over the location list at `offset` in `debug_loclists`.
+    pub fn new(
+        debug_loclists: &'data [u8],
+        offset: usize,
+        endian: E,
+        address_size: u8
+    ) -> Result<Self, ReadError> {
+        if offset >= debug_loclists.len() {
+            return Err(ReadError::Invalid);
+        }
+        Ok(RawLocListIterator {
+            r: &debug_loclists[offset..],
+            endian: endian,
+            address_size: address_size,
+        })
+    }
+
+    #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
+    pub fn next(&mut self) -> Result<Option<LocListEntry<'data>>, ReadError> {
+        if self.r.is_empty() {
+            return Ok(None);
+        }
+        match try!(LocListEntry::read(&mut self.r, self.endian, self.address_size)) {
+            Some(entry) => Ok(Some(entry)),
+            None => {
+                self.r = &[];
+                Ok(None)
+            }
+        }
+    }
+}
+
+// An iterator over the entries of a location list in .debug_loclists.
+//
+// Base address entries are applied to the following entries, and indexes
+// are resolved using the unit's entries in .debug_addr.
+#[derive(Debug, Clone)]
+pub struct LocListIterator<'data, E: Endian> {
+    raw: RawLocListIterator<'data, E>,
+    base_address: u64,
+    debug_addr: &'data [u8],
+    addr_base: u64,
+}
+
+impl<'data, E: Endian> LocListIterator<'data, E> {
+    // `base_address` is the initial base address, which is the DW_AT_low_pc
+    // of the unit. `addr_base` is the DW_AT_addr_base of the unit.
+    pub fn new(
+        raw: RawLocListIterator<'data, E>,
+        base_address: u64,
+        debug_addr: &'data [u8],
+        addr_base: u64
+    ) -> Self {
+        LocListIterator {
+            raw: raw,
+            base_address: base_address,
+            debug_addr: debug_addr,
+            addr_base: addr_base,
+        }
+    }
+
+    fn address(&self, index: u64) -> Result<u64, ReadError> {
+        read_indexed(self.debug_addr,
+                     self.raw.endian,
+                     self.raw.address_size,
+                     self.addr_base,
+                     index)
+    }
+
+    #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
+    pub fn next(&mut self) -> Result<Option<LocationListEntry<'data>>, ReadError> {
+        loop {
+            let entry = match try!(self.raw.next()) {
+                Some(entry) => entry,
+                None => return Ok(None),
+            };
+            let (begin, end, expression) = match entry {
+                LocListEntry::BaseAddressx(index) => {
+                    self.base_address = try!(self.address(index));
+                    continue;
+                }
+                LocListEntry::BaseAddress(address) => {
+                    self.base_address = address;
+                    continue;
+                }
+                LocListEntry::StartxEndx { begin, end, expression } => {
+                    (try!(self.address(begin)), try!(self.address(end)), expression)
+                }
+                LocListEntry::StartxLength { begin, length, expression } => {
+                    let begin = try!(self.address(begin));
+                    (begin, try!(begin.checked_add(length).ok_or(ReadError::Overflow)), expression)
+                }
+                LocListEntry::OffsetPair { begin, end, expression } => {
+                    (try!(self.base_address.checked_add(begin).ok_or(ReadError::Overflow)),
+                     try!(self.base_address.checked_add(end).ok_or(ReadError::Overflow)),
+                     expression)
+                }
+                LocListEntry::DefaultLocation { expression } => {
+                    (0, u64::max_value(), expression)
+                }
+                LocListEntry::StartEnd { begin, end, expression } => (begin, end, expression),
+                LocListEntry::StartLength { begin, length, expression } => {
+                    (begin, try!(begin.checked_add(length).ok_or(ReadError::Overflow)), expression)
+                }
+            };
+            return Ok(Some(LocationListEntry {
+                begin: begin,
+                end: end,
+                expression: expression,
+            }));
+        }
+    }
+}
+
+// An iterator over the entries of a location list in .debug_loc, which is
+// used by units before version 5.
+//
+// Base address selection entries are applied to the following entries.
+#[derive(Debug, Clone)]
+pub struct LocationListIterator<'data, E: Endian> {
+    r: &'data [u8],
+    endian: E,
+    address_size: u8,
+    base_address: u64,
+}
+
+impl<'data, E: Endian> LocationListIterator<'data, E> {
+    // Iterate over the location list at `offset` in `debug_loc`.
+    //
+    // `base_address` is the initial base address, which is the
+    // DW_AT_low_pc of the unit.
+    pub fn new(
+        debug_loc: &'data [u8],
+        offset: usize,
+        endian: E,
+        address_size: u8,
+        base_address: u64
+    ) -> Result<Self, ReadError> {
+        if offset >= debug_loc.len() {
+            return Err(ReadError::Invalid);
+        }
+        Ok(LocationListIterator {
+            r: &debug_loc[offset..],
+            endian: endian,
+            address_size: address_size,
+            base_address: base_address,
+        })
+    }
+
+    #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
+    pub fn next(&mut self) -> Result<Option<LocationListEntry<'data>>, ReadError> {
+        let max = match self.address_size {
+            1 => 0xff,
+            2 => 0xffff,
+            4 => 0xffff_ffff,
+            _ => u64::max_value(),
+        };
+        loop {
+            if self.r.is_empty() {
+                return Ok(None);
+            }
+            let begin = try!(read_address(&mut self.r, self.endian, self.address_size));
+            let end = try!(read_address(&mut self.r, self.endian, self.address_size));
+            if begin == 0 && end == 0 {
+                self.r = &[];
+                return Ok(None);
+            }
+            if begin == max {
+                // Base address selection entry.
+                self.base_address = end;
+                continue;
+            }
+            let len = try!(self.endian.read_u16(&mut self.r));
+            let expression = try!(read_block(&mut self.r, len as usize));
+            return Ok(Some(LocationListEntry {
+                begin: try!(self.base_address.checked_add(begin).ok_or(ReadError::Overflow)),
+                end: try!(self.base_address.checked_add(end).ok_or(ReadError::Overflow)),
+                expression: expression,
+            }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use abbrev::*;
+    use constant::*;
+    use die::*;
+    use endian::LittleEndian;
+    use ranges::ListsHeader;
+    use unit::{CompilationUnit, UnitBases};
+    use Sections;
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn loclists() {
+        let debug_loclists = [
+            // Header
+            0x39, 0x00, 0x00, 0x00,
+            0x05, 0x00,
+            0x04,
+            0x00,
+            0x01, 0x00, 0x00, 0x00,
+            // Offsets
+            0x04, 0x00, 0x00, 0x00,
+            // List 0
+            0x04, 0x10, 0x20, 0x01, 0x50,
+            0x06, 0x00, 0x10, 0x00, 0x00,
+            0x04, 0x01, 0x02, 0x02, 0x91, 0x08,
+            0x08, 0x00, 0x20, 0x00, 0x00, 0x08, 0x01, 0x51,
+            0x02, 0x00, 0x01, 0x01, 0x52,
+            0x03, 0x01, 0x04, 0x01, 0x53,
+            0x01, 0x01,
+            0x04, 0x00, 0x04, 0x01, 0x54,
+            0x05, 0x01, 0x55,
+            0x00,
+        ];
+        let debug_addr = [
+            0x00, 0x40, 0x00, 0x00,
+            0x00, 0x50, 0x00, 0x00,
+        ];
+
+        let header = ListsHeader::read(&debug_loclists, 0, LittleEndian).unwrap();
+        assert_eq!(header.offset_entry_count, 1);
+        assert_eq!(header.end_offset(), debug_loclists.len());
+
+        let base = header.offsets_base() as u64;
+        let offset = loclistx_offset(&debug_loclists, LittleEndian, 4, base, 0).unwrap();
+        assert_eq!(offset, 16);
+        let raw = RawLocListIterator::new(&debug_loclists, offset as usize, LittleEndian, 4)
+            .unwrap();
+        let mut locations = LocListIterator::new(raw.clone(), 0x100, &debug_addr, 0);
+        let mut next = || {
+            locations.next().unwrap().map(|entry| (entry.begin, entry.end, entry.expression))
+        };
+        assert_eq!(next(), Some((0x110, 0x120, &[0x50][..])));
+        assert_eq!(next(), Some((0x1001, 0x1002, &[0x91, 0x08][..])));
+        assert_eq!(next(), Some((0x2000, 0x2008, &[0x51][..])));
+        assert_eq!(next(), Some((0x4000, 0x5000, &[0x52][..])));
+        assert_eq!(next(), Some((0x5000, 0x5004, &[0x53][..])));
+        assert_eq!(next(), Some((0x5000, 0x5004, &[0x54][..])));
+        assert_eq!(next(), Some((0, u64::max_value(), &[0x55][..])));
+        assert_eq!(next(), None);
+
+        let mut raw = raw;
+        assert_eq!(raw.next().unwrap(), Some(LocListEntry::OffsetPair {
+            begin: 0x10,
+            end: 0x20,
+            expression: &[0x50],
+        }));
+
+        // The expression is longer than the data.
+        let mut raw = RawLocListIterator::new(&[0x05, 0x02, 0x50], 0, LittleEndian, 4).unwrap();
+        assert!(raw.next().is_err());
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn location_list() {
+        let debug_loc = [
+            0x10, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x01, 0x00, 0x50,
+            // A base address selection entry.
+            0xff, 0xff, 0xff, 0xff, 0x00, 0x40, 0x00, 0x00,
+            0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x00, 0x91, 0x08,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // The expression is longer than the data.
+            0x00, 0x10, 0x00, 0x00, 0x10, 0x10, 0x00, 0x00, 0x02, 0x00, 0x50,
+        ];
+
+        let mut locations = LocationListIterator::new(&debug_loc, 0, LittleEndian, 4, 0x1000)
+            .unwrap();
+        let mut next = || {
+            locations.next().unwrap().map(|entry| (entry.begin, entry.end, entry.expression))
+        };
+        assert_eq!(next(), Some((0x1010, 0x1020, &[0x50][..])));
+        assert_eq!(next(), Some((0x4001, 0x4002, &[0x91, 0x08][..])));
+        assert_eq!(next(), None);
+        assert_eq!(next(), None);
+
+        let mut locations = LocationListIterator::new(&debug_loc, 39, LittleEndian, 4, 0)
+            .unwrap();
+        assert!(locations.next().is_err());
+
+        assert!(LocationListIterator::new(&debug_loc, 50, LittleEndian, 4, 0).is_err());
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn sections_location_list() {
+        let abbrev = AbbrevVec::new(vec![
+            Abbrev { code: 1, tag: DW_TAG_compile_unit, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_low_pc, DW_FORM_addr),
+                AbbrevAttribute::new(DW_AT_location, DW_FORM_sec_offset),
+            ] },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev.write(&mut debug_abbrev).unwrap();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        let entry = Die::new(0, 1, DW_TAG_compile_unit, false, vec![
+            Attribute { at: DW_AT_low_pc, data: AttributeData::Address(0x1000) },
+            Attribute { at: DW_AT_location, data: AttributeData::SecOffset(0) },
+        ]);
+        let mut unit = CompilationUnit::<LittleEndian>::default();
+        unit.common.version = 4;
+        let mut data = Vec::new();
+        entry.write(&mut data, &unit.common, &abbrev_hash).unwrap();
+        unit.common.data = &data;
+        let mut debug_info = Vec::new();
+        unit.write(&mut debug_info).unwrap();
+
+        let sections = Sections {
+            debug_abbrev: debug_abbrev,
+            debug_info: debug_info,
+            debug_loc: vec![
+                0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x01, 0x00, 0x50,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ],
+            ..Sections::new(LittleEndian)
+        };
+        let unit = sections.compilation_units().next().unwrap().unwrap();
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let bases = UnitBases::read(&unit, &abbrev).unwrap();
+        let data = {
+            let mut entries = unit.entries(&abbrev);
+            entries.next().unwrap().unwrap().attr(DW_AT_location).cloned().unwrap()
+        };
+        let expected = LocationListEntry { begin: 0x1000, end: 0x1010, expression: &[0x50] };
+        let mut locations = sections.location_list(&unit.common, &bases, &data).unwrap().unwrap();
+        assert_eq!(locations.next().unwrap(), Some(expected));
+        assert_eq!(locations.next().unwrap(), None);
+
+        let data = AttributeData::ExprLoc(&[0x50]);
+        assert!(sections.location_list(&unit.common, &bases, &data).unwrap().is_none());
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn sections_loclist() {
+        let abbrev = AbbrevVec::new(vec![
+            Abbrev { code: 1, tag: DW_TAG_compile_unit, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_low_pc, DW_FORM_addr),
+                AbbrevAttribute::new(DW_AT_loclists_base, DW_FORM_sec_offset),
+                AbbrevAttribute::new(DW_AT_location, DW_FORM_loclistx),
+            ] },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev.write(&mut debug_abbrev).unwrap();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        let entry = Die::new(0, 1, DW_TAG_compile_unit, false, vec![
+            Attribute { at: DW_AT_low_pc, data: AttributeData::Address(0x1000) },
+            Attribute { at: DW_AT_loclists_base, data: AttributeData::SecOffset(12) },
+            Attribute { at: DW_AT_location, data: AttributeData::LocationListIndex(0) },
+        ]);
+        let mut unit = CompilationUnit::<LittleEndian>::default();
+        unit.common.version = 5;
+        let mut data = Vec::new();
+        entry.write(&mut data, &unit.common, &abbrev_hash).unwrap();
+        unit.common.data = &data;
+        let mut debug_info = Vec::new();
+        unit.write(&mut debug_info).unwrap();
+
+        let sections = Sections {
+            debug_abbrev: debug_abbrev,
+            debug_info: debug_info,
+            debug_loclists: vec![
+                0x12, 0x00, 0x00, 0x00, 0x05, 0x00, 0x04, 0x00, 0x01, 0x00, 0x00, 0x00,
+                0x04, 0x00, 0x00, 0x00,
+                0x04, 0x00, 0x10, 0x01, 0x50, 0x00,
+            ],
+            ..Sections::new(LittleEndian)
+        };
+        let unit = sections.compilation_units().next().unwrap().unwrap();
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let bases = UnitBases::read(&unit, &abbrev).unwrap();
+        let data = {
+            let mut entries = unit.entries(&abbrev);
+            entries.next().unwrap().unwrap().attr(DW_AT_location).cloned().unwrap()
+        };
+        let expected = LocationListEntry { begin: 0x1000, end: 0x1010, expression: &[0x50] };
+        let mut locations = sections.loclist(&unit.common, &bases, &data).unwrap().unwrap();
+        assert_eq!(locations.next().unwrap(), Some(expected));
+        assert_eq!(locations.next().unwrap(), None);
+
+        let data = AttributeData::SecOffset(16);
+        let mut locations = sections.loclist(&unit.common, &bases, &data).unwrap().unwrap();
+        assert_eq!(locations.next().unwrap(), Some(expected));
+
+        let data = AttributeData::ExprLoc(&[0x50]);
+        assert!(sections.loclist(&unit.common, &bases, &data).unwrap().is_none());
+    }
+}
@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+
+use constant;
+use endian::Endian;
+use leb128;
+use line::{FileEntry, LineProgram};
+use read::*;
+use write::*;
+
+// Concatenates line programs into a new .debug_line section.
+//
+// Each call to `add` writes one line program, which combines the header
+// tables and opcodes of a group of input programs. The file and directory
+// tables are merged, and file indices in the opcodes are remapped.
+#[derive(Debug)]
+pub struct LineMerger<E: Endian> {
+    endian: E,
+    data: Vec<u8>,
+}
+
+// The result of adding a group of line programs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergedLineProgram {
+    // The offset of the merged program in the new section. This is the
+    // new value of DW_AT_stmt_list for the units of the input programs.
+    pub offset: usize,
+    // For each input program, the new file index for each old file index.
+    // This can be used to update DW_AT_decl_file and DW_AT_call_file.
+    pub files: Vec<Vec<u64>>,
+}
+
+impl<E: Endian> LineMerger<E> {
+    pub fn new(endian: E) -> Self {
+        LineMerger {
+            endian: endian,
+            data: Vec::new(),
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    // Add a group of line programs as a single program.
+    //
+    // The programs must have been produced with the same header parameters
+    // (address size, line base and range, and opcode base), since special
+    // opcodes are copied without being decoded.
+    //
+    // The compilation directory of the first program is used for the merged
+    // program. Files in other compilation directories are given an explicit
+    // include directory.
+    pub fn add<'data>(
+        &mut self,
+        programs: &[LineProgram<'data, E>]
+    ) -> Result<MergedLineProgram, WriteError> {
+        let first = match programs.first() {
+            Some(first) => first,
+            None => return Err(WriteError::Invalid("no line programs".to_string())),
+        };
+        for program in &programs[1..] {
+            if program.address_size != first.address_size ||
+               program.address_step != first.address_step ||
+               program.operation_range != first.operation_range ||
+               program.default_statement != first.default_statement ||
+               program.line_base != first.line_base ||
+               program.line_range != first.line_range ||
+               program.opcode_base != first.opcode_base ||
+               program.standard_opcode_lengths != first.standard_opcode_lengths {
+                return Err(WriteError::Unsupported(format!("line program parameters at {:x} \
+                                                            differ from {:x}",
+                                                           program.offset,
+                                                           first.offset)));
+            }
+        }
+
+        let comp_dir = first.include_directories.first().cloned().unwrap_or(&[]);
+        let comp_name = first.files.first().map(|file| file.path).unwrap_or(&[]);
+        let mut tables = Tables {
+            directories: vec![comp_dir],
+            directory_index: HashMap::new(),
+            files: vec![FileEntry {
+                            path: comp_name,
+                            directory: 0,
+                            timestamp: 0,
+                            length: 0,
+                        }],
+            file_index: HashMap::new(),
+        };
+
+        let mut data = Vec::new();
+        let mut files = Vec::new();
+        for program in programs {
+            let mut directories = Vec::new();
+            for (i, dir) in program.include_directories.iter().enumerate() {
+                if i == 0 && (dir.is_empty() || *dir == comp_dir) {
+                    directories.push(0);
+                } else {
+                    directories.push(tables.add_directory(dir));
+                }
+            }
+            let mut map = vec![0];
+            for file in program.files.iter().skip(1) {
+                map.push(try!(tables.add_file(file, &directories)));
+            }
+            try!(copy_opcodes(program, &mut data, &mut map, &directories, &mut tables));
+            files.push(map);
+        }
+
+        let merged = LineProgram {
+            offset: self.data.len(),
+            endian: self.endian,
+            version: programs.iter().map(|program| program.version).max().unwrap_or(first.version),
+            address_size: first.address_size,
+            offset_size: programs.iter()
+                .map(|program| program.offset_size)
+                .max()
+                .unwrap_or(first.offset_size),
+            address_step: first.address_step,
+            operation_range: first.operation_range,
+            default_statement: first.default_statement,
+            line_base: first.line_base,
+            line_range: first.line_range,
+            opcode_base: first.opcode_base,
+            standard_opcode_lengths: first.standard_opcode_lengths,
+            include_directories: tables.directories,
+            files: tables.files,
+            data: &data,
+        };
+        try!(merged.write(&mut self.data));
+        Ok(MergedLineProgram {
+            offset: merged.offset,
+            files: files,
+        })
+    }
+}
+
+struct Tables<'data> {
+    directories: Vec<&'data [u8]>,
+    directory_index: HashMap<&'data [u8], u64>,
+    files: Vec<FileEntry<'data>>,
+    file_index: HashMap<FileEntry<'data>, u64>,
+}
+
+impl<'data> Tables<'data> {
+    fn add_directory(&mut self, dir: &'data [u8]) -> u64 {
+        let directories = &mut self.directories;
+        *self.directory_index.entry(dir).or_insert_with(|| {
+            directories.push(dir);
+            directories.len() as u64 - 1
+        })
+    }
+
+    fn add_file(&mut self, file: &FileEntry<'data>, directories: &[u64]) -> Result<u64, WriteError> {
+        let directory = match directories.get(file.directory as usize) {
+            Some(&directory) => directory,
+            None => return Err(WriteError::Invalid(format!("file directory {}", file.directory))),
+        };
+        let file = FileEntry {
+            path: file.path,
+            directory: directory,
+            timestamp: file.timestamp,
+            length: file.length,
+        };
+        let files = &mut self.files;
+        Ok(*self.file_index.entry(file.clone()).or_insert_with(|| {
+            files.push(file);
+            files.len() as u64 - 1
+        }))
+    }
+}
+
+// Copy the opcodes of a program, remapping file indices.
+//
+// DW_LNE_define_file opcodes are removed, and their entries are added to
+// the merged file table instead.
+fn copy_opcodes<'data, E: Endian>(
+    program: &LineProgram<'data, E>,
+    w: &mut Vec<u8>,
+    map: &mut Vec<u64>,
+    directories: &[u64],
+    tables: &mut Tables<'data>
+) -> Result<(), WriteError> {
+    let mut r = program.data;
+    // The file register is reset to 1 at the start of each sequence.
+    let mut sequence_start = true;
+    while !r.is_empty() {
+        let start = r;
+        let opcode = try!(read_u8(&mut r));
+        let set_file = opcode == constant::DW_LNS_set_file.0;
+        if sequence_start && opcode != 0 && !set_file {
+            sequence_start = false;
+            if let Some(&file) = map.get(1) {
+                if file != 1 {
+                    try!(write_u8(w, constant::DW_LNS_set_file.0));
+                    try!(leb128::write_u64(w, file));
+                }
+            }
+        }
+
+        if opcode == 0 {
+            let len = try!(leb128::read_u64(&mut r)) as usize;
+            let mut data = try!(read_block(&mut r, len));
+            let opcode = try!(read_u8(&mut data));
+            match constant::DwLne(opcode) {
+                constant::DW_LNE_define_file => {
+                    let file = try!(FileEntry::read(&mut data));
+                    map.push(try!(tables.add_file(&file, directories)));
+                    continue;
+                }
+                constant::DW_LNE_end_sequence => sequence_start = true,
+                _ => {}
+            }
+        } else if set_file {
+            sequence_start = false;
+            let file = try!(leb128::read_u64(&mut r));
+            let file = match map.get(file as usize) {
+                Some(&file) => file,
+                None => return Err(WriteError::Invalid(format!("line program file {}", file))),
+            };
+            try!(write_u8(w, opcode));
+            try!(leb128::write_u64(w, file));
+            continue;
+        } else if opcode == constant::DW_LNS_fixed_advance_pc.0 {
+            try!(read_block(&mut r, 2));
+        } else if opcode < program.opcode_base {
+            let index = opcode as usize - 1;
+            let count = match program.standard_opcode_lengths.get(index) {
+                Some(&count) => count,
+                None => return Err(WriteError::Invalid(format!("line program opcode {}", opcode))),
+            };
+            for _ in 0..count {
+                try!(leb128::read_u64(&mut r));
+            }
+        }
+        let len = start.len() - r.len();
+        w.extend_from_slice(&start[..len]);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use constant::*;
+    use endian::*;
+    use line::*;
+    use leb128;
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn program<'a>(
+        dir: &'a [u8],
+        files: Vec<FileEntry<'a>>,
+        include_directories: Vec<&'a [u8]>,
+        data: &'a [u8]
+    ) -> LineProgram<'a, LittleEndian> {
+        let mut all_files = vec![FileEntry { path: b"main.c", ..Default::default() }];
+        all_files.extend(files);
+        let mut all_directories = vec![dir];
+        all_directories.extend(include_directories);
+        LineProgram {
+            offset: 0,
+            endian: LittleEndian,
+            version: 2,
+            address_size: 4,
+            offset_size: 4,
+            address_step: 1,
+            operation_range: 1,
+            default_statement: true,
+            line_base: -5,
+            line_range: 14,
+            opcode_base: 13,
+            standard_opcode_lengths: &[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1],
+            include_directories: all_directories,
+            files: all_files,
+            data: data,
+        }
+    }
+
+    fn read_files(data: &[u8], offset: usize) -> Vec<(Vec<u8>, u64)> {
+        let mut r = &data[offset..];
+        let program = LineProgram::read(&mut r, offset, LittleEndian, 4, b"/a", b"main.c").unwrap();
+        let mut lines = program.lines();
+        let mut result = Vec::new();
+        while let Some((iter, line)) = lines.next().unwrap() {
+            if line.sequence_end {
+                continue;
+            }
+            let file = iter.file(line.file).unwrap();
+            let mut path = iter.directory(file.directory).unwrap().to_vec();
+            path.push(b'/');
+            path.extend_from_slice(file.path);
+            result.push((path, line.line));
+        }
+        result
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn merge() {
+        // Row in file 1, then row in file 2, then end sequence.
+        let mut data = Vec::new();
+        data.push(DW_LNS_copy.0);
+        data.push(DW_LNS_set_file.0);
+        leb128::write_u64(&mut data, 2).unwrap();
+        data.push(DW_LNS_copy.0);
+        data.extend_from_slice(&[0, 1, DW_LNE_end_sequence.0]);
+
+        let a = program(b"/a", vec![
+            FileEntry { path: b"x.c", directory: 0, ..Default::default() },
+            FileEntry { path: b"y.h", directory: 1, ..Default::default() },
+        ], vec![b"/inc"], &data);
+        let b = program(b"/b", vec![
+            FileEntry { path: b"y.h", directory: 1, ..Default::default() },
+            FileEntry { path: b"z.c", directory: 0, ..Default::default() },
+        ], vec![b"/inc"], &data);
+
+        let mut merger = LineMerger::new(LittleEndian);
+        let first = merger.add(std::slice::from_ref(&a)).unwrap();
+        let merged = merger.add(&[a, b]).unwrap();
+        assert_eq!(first.offset, 0);
+        assert_eq!(first.files, vec![vec![0, 1, 2]]);
+        assert_eq!(merged.files, vec![vec![0, 1, 2], vec![0, 2, 3]]);
+
+        let data = merger.into_data();
+        assert_eq!(read_files(&data, first.offset), vec![
+            (b"/a/x.c".to_vec(), 1),
+            (b"/inc/y.h".to_vec(), 1),
+        ]);
+        assert_eq!(read_files(&data, merged.offset), vec![
+            (b"/a/x.c".to_vec(), 1),
+            (b"/inc/y.h".to_vec(), 1),
+            (b"/inc/y.h".to_vec(), 1),
+            (b"/b/z.c".to_vec(), 1),
+        ]);
+    }
+}
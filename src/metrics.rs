@@ -0,0 +1,246 @@
+use std;
+use std::ops::Deref;
+use std::time::{Duration, Instant};
+
+use abbrev::AbbrevHash;
+use die::{Die, DieIterator};
+use endian::Endian;
+use line::{Line, LineIterator};
+use read::ReadError;
+use unit::UnitCommon;
+use Sections;
+
+// The amount of data decoded by a phase of parsing, and the time taken.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PhaseMetrics {
+    // The number of abbreviations, entries or rows decoded.
+    pub count: u64,
+    // The number of section bytes decoded.
+    pub bytes: u64,
+    pub time: Duration,
+}
+
+impl PhaseMetrics {
+    fn add(&mut self, count: u64, bytes: u64, start: Instant) {
+        self.count += count;
+        self.bytes += bytes;
+        self.time += start.elapsed();
+    }
+
+    // Returns 0 if no time has been recorded.
+    pub fn count_per_second(&self) -> f64 {
+        per_second(self.count, self.time)
+    }
+
+    // Returns 0 if no time has been recorded.
+    pub fn bytes_per_second(&self) -> f64 {
+        per_second(self.bytes, self.time)
+    }
+}
+
+fn per_second(val: u64, time: Duration) -> f64 {
+    let secs = time.as_secs() as f64 + time.subsec_nanos() as f64 * 1e-9;
+    if secs == 0.0 {
+        0.0
+    } else {
+        val as f64 / secs
+    }
+}
+
+// Counts and timings for the phases of parsing, for profiling.
+//
+// Use the methods of this type in place of the corresponding methods of
+// units and iterators to record each phase, or use `from_sections` to
+// measure all of the phases for the sections of a file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Metrics {
+    // Reading abbreviation tables from .debug_abbrev.
+    pub abbrev: PhaseMetrics,
+    // Decoding entries from .debug_info and .debug_types.
+    pub entries: PhaseMetrics,
+    // Executing line programs from .debug_line.
+    pub lines: PhaseMetrics,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // Read the abbreviations and entries of all units, and the line
+    // programs of all compilation units.
+    pub fn from_sections<E, S>(sections: &Sections<E, S>) -> Result<Metrics, ReadError>
+        where E: Endian,
+              S: Deref<Target = [u8]>
+    {
+        let mut metrics = Metrics::new();
+        let mut units = sections.compilation_units();
+        while let Some(unit) = try!(units.next()) {
+            let abbrev = try!(metrics.abbrev(&unit.common, &*sections.debug_abbrev));
+            try!(metrics.entries(unit.entries(&abbrev), |_| {}));
+            if let Some(lines) = try!(sections.lines(&unit, &abbrev)) {
+                try!(metrics.lines(lines, |_| {}));
+            }
+        }
+        let mut units = sections.type_units();
+        while let Some(unit) = try!(units.next()) {
+            let abbrev = try!(metrics.abbrev(&unit.common, &*sections.debug_abbrev));
+            try!(metrics.entries(unit.entries(&abbrev), |_| {}));
+        }
+        Ok(metrics)
+    }
+
+    // Read the abbreviation table of a unit.
+    pub fn abbrev<E: Endian>(
+        &mut self,
+        unit: &UnitCommon<E>,
+        debug_abbrev: &[u8]
+    ) -> Result<AbbrevHash, ReadError> {
+        let start = Instant::now();
+        let offset = unit.abbrev_offset as usize;
+        if offset >= debug_abbrev.len() {
+            return Err(ReadError::Invalid);
+        }
+        let mut r = &debug_abbrev[offset..];
+        let abbrev = try!(AbbrevHash::read(&mut r));
+        let bytes = debug_abbrev.len() - offset - r.len();
+        self.abbrev.add(abbrev.len() as u64, bytes as u64, start);
+        Ok(abbrev)
+    }
+
+    // Decode the remaining entries of an iterator, calling `f` for each.
+    //
+    // The time includes the time spent in `f`.
+    pub fn entries<E, F>(&mut self, mut entries: DieIterator<E>, mut f: F) -> Result<(), ReadError>
+        where E: Endian,
+              F: FnMut(&Die)
+    {
+        let start = Instant::now();
+        let offset = entries.offset();
+        let mut count = 0;
+        let result = loop {
+            match entries.next() {
+                Ok(Some(entry)) => {
+                    count += 1;
+                    f(entry);
+                }
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+        self.entries.add(count, (entries.offset() - offset) as u64, start);
+        result
+    }
+
+    // Execute the remaining rows of a line program, calling `f` for each.
+    //
+    // The time includes the time spent in `f`.
+    pub fn lines<E, F>(&mut self, mut lines: LineIterator<E>, mut f: F) -> Result<(), ReadError>
+        where E: Endian,
+              F: FnMut(&Line)
+    {
+        let start = Instant::now();
+        let offset = lines.offset();
+        let mut count = 0;
+        let result = loop {
+            match lines.next() {
+                Ok(Some((_, line))) => {
+                    count += 1;
+                    f(line);
+                }
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+        self.lines.add(count, (lines.offset() - offset) as u64, start);
+        result
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.abbrev.time + self.entries.time + self.lines.time
+    }
+}
+
+impl std::fmt::Display for Metrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for &(name, phase) in &[("abbrev", &self.abbrev),
+                                ("entries", &self.entries),
+                                ("lines", &self.lines)] {
+            try!(writeln!(f,
+                          "{}: {} items, {} bytes, {:?} ({:.0} items/s)",
+                          name,
+                          phase.count,
+                          phase.bytes,
+                          phase.time,
+                          phase.count_per_second()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use abbrev::*;
+    use constant::*;
+    use endian::*;
+    use unit::*;
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn entries() {
+        let abbrevs = AbbrevVec::new(vec![
+            Abbrev {
+                code: 1,
+                tag: DW_TAG_compile_unit,
+                children: true,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_byte_size, DW_FORM_data1),
+                ],
+            },
+            Abbrev {
+                code: 2,
+                tag: DW_TAG_base_type,
+                children: false,
+                attributes: vec![],
+            },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrevs.write(&mut debug_abbrev).unwrap();
+
+        let data = [1, 4, 2, 2, 0];
+        let unit = CompilationUnit {
+            common: UnitCommon {
+                endian: LittleEndian,
+                data: &data,
+                ..Default::default()
+            },
+        };
+
+        let mut metrics = Metrics::new();
+        let abbrev = metrics.abbrev(&unit.common, &debug_abbrev).unwrap();
+        assert_eq!(metrics.abbrev.count, 2);
+        assert_eq!(metrics.abbrev.bytes, debug_abbrev.len() as u64);
+
+        let mut tags = Vec::new();
+        metrics.entries(unit.entries(&abbrev), |entry| tags.push(entry.tag)).unwrap();
+        assert_eq!(tags, vec![
+            DW_TAG_compile_unit, DW_TAG_base_type, DW_TAG_base_type, DW_TAG_null,
+        ]);
+        assert_eq!(metrics.entries.count, 4);
+        assert_eq!(metrics.entries.bytes, data.len() as u64);
+        assert_eq!(metrics.lines, PhaseMetrics::default());
+
+        // Errors still record the entries that were decoded.
+        let data = [1, 4, 3];
+        let unit = CompilationUnit {
+            common: UnitCommon {
+                endian: LittleEndian,
+                data: &data,
+                ..Default::default()
+            },
+        };
+        assert!(metrics.entries(unit.entries(&abbrev), |_| {}).is_err());
+        assert_eq!(metrics.entries.count, 5);
+    }
+}
@@ -0,0 +1,81 @@
+use std;
+
+use endian::Endian;
+use unit::{CompilationUnit, UnitCommon};
+
+// An offset relative to the start of a unit header.
+//
+// This is the value of the DW_FORM_ref* forms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct UnitOffset(pub u64);
+
+// An offset relative to the start of .debug_info.
+//
+// This is the value of the DW_FORM_ref_addr form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct DebugInfoOffset(pub u64);
+
+impl UnitOffset {
+    // Convert to an offset in the section containing the unit.
+    //
+    // This is the same as the offset of the entry in `Die::offset`.
+    pub fn to_section_offset<E: Endian>(self, unit: &UnitCommon<E>) -> usize {
+        unit.offset + self.0 as usize
+    }
+
+    pub fn to_debug_info_offset<E: Endian>(self, unit: &CompilationUnit<E>) -> DebugInfoOffset {
+        DebugInfoOffset(self.to_section_offset(&unit.common) as u64)
+    }
+}
+
+impl DebugInfoOffset {
+    // Convert to an offset in the given unit.
+    //
+    // Returns `None` if the offset isn't within the unit.
+    pub fn to_unit_offset<E: Endian>(self, unit: &CompilationUnit<E>) -> Option<UnitOffset> {
+        let offset = self.0 as usize;
+        if offset < unit.common.offset || offset >= unit.common.offset + unit.total_len() {
+            return None;
+        }
+        Some(UnitOffset((offset - unit.common.offset) as u64))
+    }
+}
+
+impl std::fmt::Display for UnitOffset {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::fmt::Display for DebugInfoOffset {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use endian::*;
+    use unit::*;
+
+    #[test]
+    fn convert() {
+        let data = [0; 4];
+        let unit = CompilationUnit {
+            common: UnitCommon {
+                offset: 0x100,
+                endian: LittleEndian,
+                data: &data,
+                ..Default::default()
+            },
+        };
+        let offset = UnitOffset(0xb);
+        assert_eq!(offset.to_section_offset(&unit.common), 0x10b);
+        assert_eq!(offset.to_debug_info_offset(&unit), DebugInfoOffset(0x10b));
+        assert_eq!(DebugInfoOffset(0x10b).to_unit_offset(&unit), Some(offset));
+        assert_eq!(DebugInfoOffset(0x100).to_unit_offset(&unit), Some(UnitOffset(0)));
+        assert_eq!(DebugInfoOffset(0x0ff).to_unit_offset(&unit), None);
+        assert_eq!(DebugInfoOffset(0x10f).to_unit_offset(&unit), None);
+    }
+}
@@ -0,0 +1,15 @@
+// The types and traits that are needed by most users of the crate.
+//
+// ```ignore
+// use dwarf::prelude::*;
+// ```
+
+pub use {AnyEndian, BigEndian, Endian, LittleEndian, NativeEndian};
+pub use {ReadError, Sections, WriteError};
+pub use abbrev::{Abbrev, AbbrevAttribute, AbbrevHash};
+pub use constant;
+pub use constant::{DwAt, DwForm, DwTag};
+pub use die::{Attribute, AttributeData, Die, DieIterator, DieRef, DieTree};
+pub use display::{DefaultFormatter, Formatter, StringPolicy};
+pub use offset::{DebugInfoOffset, UnitOffset};
+pub use unit::{CompilationUnit, CompilationUnitIterator, TypeUnit, TypeUnitIterator, UnitCommon};
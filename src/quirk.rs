@@ -0,0 +1,319 @@
+use std;
+
+use abbrev::AbbrevHash;
+use constant;
+use die::AttributeContext;
+use endian::Endian;
+use read::ReadError;
+use unit::CompilationUnit;
+
+// Corrections for known bugs in the DWARF generated by some producers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quirks {
+    // DW_FORM_ref_addr values in version 2 units use the offset size
+    // instead of the address size.
+    pub ref_addr_offset_size: bool,
+    // File indices in attributes such as DW_AT_decl_file are numbered from 0
+    // instead of 1, so they are one less than the line program file index.
+    pub file_index_from_zero: bool,
+}
+
+impl Quirks {
+    // Convert a file index from an attribute into a line program file index.
+    //
+    // Use `Die::file_index` to read the index from an entry.
+    pub fn file_index(&self, index: u64) -> u64 {
+        if self.file_index_from_zero {
+            index + 1
+        } else {
+            index
+        }
+    }
+
+    // Add the corrections of `other`.
+    pub fn merge(&mut self, other: &Quirks) {
+        self.ref_addr_offset_size |= other.ref_addr_offset_size;
+        self.file_index_from_zero |= other.file_index_from_zero;
+    }
+}
+
+// A producer that has known bugs.
+pub trait Quirk: std::fmt::Debug {
+    // Return true if the DW_AT_producer value is for an affected producer.
+    fn matches(&self, producer: &[u8]) -> bool;
+
+    // Set the corrections for the bugs of the producer.
+    fn apply(&self, quirks: &mut Quirks);
+}
+
+// A quirk for producers with a DW_AT_producer that starts with a prefix.
+#[derive(Debug, Clone)]
+pub struct ProducerPrefix {
+    pub prefix: Vec<u8>,
+    pub quirks: Quirks,
+}
+
+impl ProducerPrefix {
+    pub fn new(prefix: &[u8], quirks: Quirks) -> Self {
+        ProducerPrefix {
+            prefix: prefix.to_vec(),
+            quirks: quirks,
+        }
+    }
+}
+
+impl Quirk for ProducerPrefix {
+    fn matches(&self, producer: &[u8]) -> bool {
+        producer.starts_with(&self.prefix)
+    }
+
+    fn apply(&self, quirks: &mut Quirks) {
+        quirks.merge(&self.quirks);
+    }
+}
+
+// A quirk for GCC versions before `major.minor`, which have a DW_AT_producer
+// such as "GNU C 4.1.2".
+#[derive(Debug, Clone)]
+pub struct GccBefore {
+    pub major: u32,
+    pub minor: u32,
+    pub quirks: Quirks,
+}
+
+impl GccBefore {
+    pub fn new(major: u32, minor: u32, quirks: Quirks) -> Self {
+        GccBefore {
+            major: major,
+            minor: minor,
+            quirks: quirks,
+        }
+    }
+}
+
+impl Quirk for GccBefore {
+    fn matches(&self, producer: &[u8]) -> bool {
+        match gcc_version(producer) {
+            Some(version) => version < (self.major, self.minor),
+            None => false,
+        }
+    }
+
+    fn apply(&self, quirks: &mut Quirks) {
+        quirks.merge(&self.quirks);
+    }
+}
+
+// Return the major and minor version of a GCC DW_AT_producer.
+//
+// The version is the first word after "GNU" that starts with a digit and
+// contains a dot, since the language name may also contain digits. The
+// GNU assembler uses a producer such as "GNU AS 2.30", which isn't GCC.
+fn gcc_version(producer: &[u8]) -> Option<(u32, u32)> {
+    if !producer.starts_with(b"GNU ") || producer.starts_with(b"GNU AS ") {
+        return None;
+    }
+    let producer = match std::str::from_utf8(producer) {
+        Ok(producer) => producer,
+        Err(_) => return None,
+    };
+    let word = producer.split_whitespace()
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()) && word.contains('.'));
+    let mut parts = match word {
+        Some(word) => word.split('.'),
+        None => return None,
+    };
+    let major = parts.next().and_then(|part| part.parse().ok());
+    let minor = parts.next().and_then(|part| part.parse().ok());
+    match (major, minor) {
+        (Some(major), Some(minor)) => Some((major, minor)),
+        _ => None,
+    }
+}
+
+// The quirks that are checked for each unit.
+//
+// `new` returns an empty database, and `known` returns a database with the
+// quirks of known producers. Add quirks for other producers that are used
+// for the files being read.
+#[derive(Debug, Default)]
+pub struct QuirkDatabase {
+    quirks: Vec<Box<Quirk>>,
+}
+
+impl QuirkDatabase {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // Return a database containing the quirks of:
+    //
+    // - GCC before 4.3, which used the offset size for DW_FORM_ref_addr in
+    //   version 2 units.
+    // - The Intel C and C++ compilers, which number the files of
+    //   DW_AT_decl_file and DW_AT_call_file from 0.
+    pub fn known() -> Self {
+        let mut database = QuirkDatabase::new();
+        database.add(Box::new(GccBefore::new(4, 3, Quirks {
+            ref_addr_offset_size: true,
+            ..Default::default()
+        })));
+        database.add(Box::new(ProducerPrefix::new(b"Intel(R) C", Quirks {
+            file_index_from_zero: true,
+            ..Default::default()
+        })));
+        database
+    }
+
+    pub fn add(&mut self, quirk: Box<Quirk>) {
+        self.quirks.push(quirk);
+    }
+
+    // Return the corrections for all of the quirks that match the producer.
+    pub fn quirks(&self, producer: &[u8]) -> Quirks {
+        let mut quirks = Quirks::default();
+        for quirk in &self.quirks {
+            if quirk.matches(producer) {
+                quirk.apply(&mut quirks);
+            }
+        }
+        quirks
+    }
+
+    // Set the quirks of a unit using the DW_AT_producer of its first entry.
+    //
+    // This must be done before reading the other entries of the unit.
+    // The first entry is read without the corrections.
+    pub fn apply<'data, E: Endian>(
+        &self,
+        unit: &mut CompilationUnit<'data, E>,
+        abbrev: &AbbrevHash,
+        context: &AttributeContext<'data>
+    ) -> Result<Quirks, ReadError> {
+        let quirks = {
+            let mut entries = unit.entries(abbrev);
+            let entry = match try!(entries.next()) {
+                Some(entry) => entry,
+                None => return Err(ReadError::Invalid),
+            };
+            match entry.attr(constant::DW_AT_producer).and_then(|p| p.as_string_in(context)) {
+                Some(producer) => self.quirks(producer),
+                None => Quirks::default(),
+            }
+        };
+        unit.common.quirks = quirks;
+        Ok(quirks)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use abbrev::*;
+    use constant::*;
+    use die::*;
+    use endian::*;
+    use offset::*;
+    use unit::*;
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn ref_addr_offset_size() {
+        let mut abbrev_hash = AbbrevHash::new();
+        abbrev_hash.insert(Abbrev {
+            code: 1,
+            tag: DW_TAG_compile_unit,
+            children: true,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_producer, DW_FORM_string),
+            ],
+        });
+        abbrev_hash.insert(Abbrev {
+            code: 2,
+            tag: DW_TAG_variable,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_type, DW_FORM_ref_addr),
+            ],
+        });
+
+        let mut database = QuirkDatabase::new();
+        database.add(Box::new(ProducerPrefix::new(b"buggy", Quirks {
+            ref_addr_offset_size: true,
+            ..Default::default()
+        })));
+        assert!(database.quirks(b"buggy 1.0").ref_addr_offset_size);
+        assert!(!database.quirks(b"good 1.0").ref_addr_offset_size);
+
+        // A version 2 unit with 8 byte addresses, but a 4 byte ref_addr.
+        let data = [
+            1, b'b', b'u', b'g', b'g', b'y', 0,
+            2, 0x34, 0x12, 0, 0,
+            0,
+        ];
+        let mut unit = CompilationUnit {
+            common: UnitCommon {
+                endian: LittleEndian,
+                version: 2,
+                address_size: 8,
+                data: &data,
+                ..Default::default()
+            },
+        };
+        let abbrev = &abbrev_hash;
+        {
+            let mut entries = unit.entries(abbrev);
+            entries.next().unwrap();
+            assert!(entries.next().is_err());
+        }
+
+        let context = AttributeContext::new(&unit.common, &[], &[]);
+        let quirks = database.apply(&mut unit, abbrev, &context).unwrap();
+        assert!(quirks.ref_addr_offset_size);
+        let mut entries = unit.entries(abbrev);
+        entries.next().unwrap();
+        let entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.attr(DW_AT_type), Some(&AttributeData::RefAddress(DebugInfoOffset(0x1234))));
+        assert!(entries.next().unwrap().unwrap().is_null());
+    }
+
+    #[test]
+    fn file_index() {
+        let quirks = Quirks {
+            file_index_from_zero: true,
+            ..Default::default()
+        };
+        assert_eq!(quirks.file_index(0), 1);
+        assert_eq!(Quirks::default().file_index(1), 1);
+
+        let entry = Die::new(0, 1, DW_TAG_variable, false, vec![
+            Attribute { at: DW_AT_decl_file, data: AttributeData::Data1(2) },
+        ]);
+        let mut unit = UnitCommon::<LittleEndian>::default();
+        assert_eq!(entry.file_index(DW_AT_decl_file, &unit), Some(2));
+        unit.quirks = quirks;
+        assert_eq!(entry.file_index(DW_AT_decl_file, &unit), Some(3));
+        assert_eq!(entry.file_index(DW_AT_call_file, &unit), None);
+    }
+
+    #[test]
+    fn known() {
+        let database = QuirkDatabase::known();
+        for &(producer, ref_addr_offset_size, file_index_from_zero) in &[
+            (&b"GNU C 4.1.2 20080704 (Red Hat 4.1.2-55)"[..], true, false),
+            (&b"GNU C++ 3.4.6"[..], true, false),
+            (&b"GNU C99 4.2.1 -g"[..], true, false),
+            (&b"GNU Fortran 95 4.3.0"[..], false, false),
+            (&b"GNU C11 7.5.0 -mtune=generic -g"[..], false, false),
+            (&b"GNU AS 2.30"[..], false, false),
+            (&b"Intel(R) C++ Intel(R) 64 Compiler XE, Version 12.1"[..], false, true),
+            (&b"clang version 3.4"[..], false, false),
+        ] {
+            let quirks = database.quirks(producer);
+            assert_eq!((quirks.ref_addr_offset_size, quirks.file_index_from_zero),
+                       (ref_addr_offset_size, file_index_from_zero),
+                       "{}",
+                       String::from_utf8_lossy(producer));
+        }
+    }
+}
@@ -0,0 +1,505 @@
+use constant;
+use endian::Endian;
+use leb128;
+use read::*;
+
+// A range of addresses, from `begin` up to but not including `end`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    pub begin: u64,
+    pub end: u64,
+}
+
+// The header of the range lists or location lists of a unit in
+// .debug_rnglists or .debug_loclists.
+//
+// The header is followed by `offset_entry_count` offsets, which are used
+// for DW_FORM_rnglistx and DW_FORM_loclistx values, and then the lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListsHeader {
+    pub offset: usize,
+    pub offset_size: u8,
+    pub version: u16,
+    pub address_size: u8,
+    pub segment_selector_size: u8,
+    pub offset_entry_count: u32,
+    // The length of the lists, including the initial length field.
+    pub len: usize,
+}
+
+impl ListsHeader {
+    pub fn read<E: Endian>(data: &[u8], offset: usize, endian: E) -> Result<Self, ReadError> {
+        if offset >= data.len() {
+            return Err(ReadError::Invalid);
+        }
+        let mut r = &data[offset..];
+        let (offset_size, len) = try!(read_initial_length(&mut r, endian));
+        let len = data.len() - offset - r.len() + len;
+        let version = try!(endian.read_u16(&mut r));
+        if version != 5 {
+            return Err(ReadError::UnsupportedVersion(version));
+        }
+        let address_size = try!(read_u8(&mut r));
+        let segment_selector_size = try!(read_u8(&mut r));
+        if segment_selector_size != 0 {
+            return Err(ReadError::Unsupported);
+        }
+        let offset_entry_count = try!(endian.read_u32(&mut r));
+        Ok(ListsHeader {
+            offset: offset,
+            offset_size: offset_size,
+            version: version,
+            address_size: address_size,
+            segment_selector_size: segment_selector_size,
+            offset_entry_count: offset_entry_count,
+            len: len,
+        })
+    }
+
+    // The offset of the array of offsets that follows the header. This is
+    // the DW_AT_rnglists_base or DW_AT_loclists_base of the units that use
+    // these lists.
+    pub fn offsets_base(&self) -> usize {
+        // initial length + version + address_size + segment_selector_size
+        // + offset_entry_count
+        self.offset + self.offset_size as usize * 2 - 4 + 2 + 1 + 1 + 4
+    }
+
+    pub fn end_offset(&self) -> usize {
+        self.offset + self.len
+    }
+}
+
+// Return the offset in .debug_rnglists of the range list at `index` in the
+// array of offsets at `base`, for a DW_FORM_rnglistx value.
+//
+// `base` is the DW_AT_rnglists_base of the unit. The offsets in the array
+// are relative to `base`.
+pub fn rnglistx_offset<E: Endian>(
+    debug_rnglists: &[u8],
+    endian: E,
+    offset_size: u8,
+    base: u64,
+    index: u64
+) -> Result<u64, ReadError> {
+    let offset = try!(read_indexed(debug_rnglists, endian, offset_size, base, index));
+    base.checked_add(offset).ok_or(ReadError::Overflow)
+}
+
+// An entry in a range list in .debug_rnglists.
+//
+// The `x` entries contain indexes in .debug_addr, and `OffsetPair`
+// contains offsets from the base address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RngListEntry {
+    BaseAddressx(u64),
+    StartxEndx { begin: u64, end: u64 },
+    StartxLength { begin: u64, length: u64 },
+    OffsetPair { begin: u64, end: u64 },
+    BaseAddress(u64),
+    StartEnd { begin: u64, end: u64 },
+    StartLength { begin: u64, length: u64 },
+}
+
+impl RngListEntry {
+    // Read an entry, or return `None` for DW_RLE_end_of_list.
+    pub fn read<E: Endian>(
+        r: &mut &[u8],
+        endian: E,
+        address_size: u8
+    ) -> Result<Option<RngListEntry>, ReadError> {
+        let kind = constant::DwRle(try!(read_u8(r)));
+        let entry = match kind {
+            constant::DW_RLE_end_of_list => return Ok(None),
+            constant::DW_RLE_base_addressx => RngListEntry::BaseAddressx(try!(leb128::read_u64(r))),
+            constant::DW_RLE_startx_endx => {
+                RngListEntry::StartxEndx {
+                    begin: try!(leb128::read_u64(r)),
+                    end: try!(leb128::read_u64(r)),
+                }
+            }
+            constant::DW_RLE_startx_length => {
+                RngListEntry::StartxLength {
+                    begin: try!(leb128::read_u64(r)),
+                    length: try!(leb128::read_u64(r)),
+                }
+            }
+            constant::DW_RLE_offset_pair => {
+                RngListEntry::OffsetPair {
+                    begin: try!(leb128::read_u64(r)),
+                    end: try!(leb128::read_u64(r)),
+                }
+            }
+            constant::DW_RLE_base_address => {
+                RngListEntry::BaseAddress(try!(read_address(r, endian, address_size)))
+            }
+            constant::DW_RLE_start_end => {
+                RngListEntry::StartEnd {
+                    begin: try!(read_address(r, endian, address_size)),
+                    end: try!(read_address(r, endian, address_size)),
+                }
+            }
+            constant::DW_RLE_start_length => {
+                RngListEntry::StartLength {
+                    begin: try!(read_address(r, endian, address_size)),
+                    length: try!(leb128::read_u64(r)),
+                }
+            }
+            _ => return Err(ReadError::Invalid),
+        };
+        Ok(Some(entry))
+    }
+}
+
+// An iterator over the entries of a range list in .debug_rnglists,
+// without resolving their addresses.
+#[derive(Debug, Clone)]
+pub struct RawRngListIterator<'data, E: Endian> {
+    r: &'data [u8],
+    endian: E,
+    address_size: u8,
+}
+
+impl<'data, E: Endian> RawRngListIterator<'data, E> {
+    // Iterate over the range list at `offset` in `debug_rnglists`.
+    pub fn new(
+        debug_rnglists: &'data [u8],
+        offset: usize,
+        endian: E,
+        address_size: u8
+    ) -> Result<Self, ReadError> {
+        if offset >= debug_rnglists.len() {
+            return Err(ReadError::Invalid);
+        }
+        Ok(RawRngListIterator {
+            r: &debug_rnglists[offset..],
+            endian: endian,
+            address_size: address_size,
+        })
+    }
+
+    #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
+    pub fn next(&mut self) -> Result<Option<RngListEntry>, ReadError> {
+        if self.r.is_empty() {
+            return Ok(None);
+        }
+        match try!(RngListEntry::read(&mut self.r, self.endian, self.address_size)) {
+            Some(entry) => Ok(Some(entry)),
+            None => {
+                self.r = &[];
+                Ok(None)
+            }
+        }
+    }
+}
+
+// An iterator over the address ranges of a range list in .debug_rnglists.
+//
+// Base address entries are applied to the following entries, and indexes
+// are resolved using the unit's entries in .debug_addr.
+#[derive(Debug, Clone)]
+pub struct RngListIterator<'data, E: Endian> {
+    raw: RawRngListIterator<'data, E>,
+    base_address: u64,
+    debug_addr: &'data [u8],
+    addr_base: u64,
+}
+
+impl<'data, E: Endian> RngListIterator<'data, E> {
+    // `base_address` is the initial base address, which is the DW_AT_low_pc
+    // of the unit. `addr_base` is the DW_AT_addr_base of the unit.
+    pub fn new(
+        raw: RawRngListIterator<'data, E>,
+        base_address: u64,
+        debug_addr: &'data [u8],
+        addr_base: u64
+    ) -> Self {
+        RngListIterator {
+            raw: raw,
+            base_address: base_address,
+            debug_addr: debug_addr,
+            addr_base: addr_base,
+        }
+    }
+
+    fn address(&self, index: u64) -> Result<u64, ReadError> {
+        read_indexed(self.debug_addr,
+                     self.raw.endian,
+                     self.raw.address_size,
+                     self.addr_base,
+                     index)
+    }
+
+    #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
+    pub fn next(&mut self) -> Result<Option<Range>, ReadError> {
+        loop {
+            let entry = match try!(self.raw.next()) {
+                Some(entry) => entry,
+                None => return Ok(None),
+            };
+            let (begin, end) = match entry {
+                RngListEntry::BaseAddressx(index) => {
+                    self.base_address = try!(self.address(index));
+                    continue;
+                }
+                RngListEntry::BaseAddress(address) => {
+                    self.base_address = address;
+                    continue;
+                }
+                RngListEntry::StartxEndx { begin, end } => {
+                    (try!(self.address(begin)), try!(self.address(end)))
+                }
+                RngListEntry::StartxLength { begin, length } => {
+                    let begin = try!(self.address(begin));
+                    (begin, try!(begin.checked_add(length).ok_or(ReadError::Overflow)))
+                }
+                RngListEntry::OffsetPair { begin, end } => {
+                    (try!(self.base_address.checked_add(begin).ok_or(ReadError::Overflow)),
+                     try!(self.base_address.checked_add(end).ok_or(ReadError::Overflow)))
+                }
+                RngListEntry::StartEnd { begin, end } => (begin, end),
+                RngListEntry::StartLength { begin, length } => {
+                    (begin, try!(begin.checked_add(length).ok_or(ReadError::Overflow)))
+                }
+            };
+            return Ok(Some(Range {
+                begin: begin,
+                end: end,
+            }));
+        }
+    }
+}
+
+// An iterator over the address ranges of a range list in .debug_ranges,
+// which is used by units before version 5.
+//
+// Base address selection entries are applied to the following entries.
+#[derive(Debug, Clone)]
+pub struct RangeListIterator<'data, E: Endian> {
+    r: &'data [u8],
+    endian: E,
+    address_size: u8,
+    base_address: u64,
+}
+
+impl<'data, E: Endian> RangeListIterator<'data, E> {
+    // Iterate over the range list at `offset` in `debug_ranges`.
+    //
+    // `base_address` is the initial base address, which is the
+    // DW_AT_low_pc of the unit.
+    pub fn new(
+        debug_ranges: &'data [u8],
+        offset: usize,
+        endian: E,
+        address_size: u8,
+        base_address: u64
+    ) -> Result<Self, ReadError> {
+        if offset >= debug_ranges.len() {
+            return Err(ReadError::Invalid);
+        }
+        Ok(RangeListIterator {
+            r: &debug_ranges[offset..],
+            endian: endian,
+            address_size: address_size,
+            base_address: base_address,
+        })
+    }
+
+    #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
+    pub fn next(&mut self) -> Result<Option<Range>, ReadError> {
+        let max = match self.address_size {
+            1 => 0xff,
+            2 => 0xffff,
+            4 => 0xffff_ffff,
+            _ => u64::max_value(),
+        };
+        loop {
+            if self.r.is_empty() {
+                return Ok(None);
+            }
+            let begin = try!(read_address(&mut self.r, self.endian, self.address_size));
+            let end = try!(read_address(&mut self.r, self.endian, self.address_size));
+            if begin == 0 && end == 0 {
+                self.r = &[];
+                return Ok(None);
+            }
+            if begin == max {
+                // Base address selection entry.
+                self.base_address = end;
+                continue;
+            }
+            return Ok(Some(Range {
+                begin: try!(self.base_address.checked_add(begin).ok_or(ReadError::Overflow)),
+                end: try!(self.base_address.checked_add(end).ok_or(ReadError::Overflow)),
+            }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use abbrev::*;
+    use constant::*;
+    use die::*;
+    use endian::LittleEndian;
+    use unit::{CompilationUnit, UnitBases};
+    use Sections;
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn rnglists() {
+        let debug_rnglists = [
+            // Header
+            0x37, 0x00, 0x00, 0x00,
+            0x05, 0x00,
+            0x04,
+            0x00,
+            0x02, 0x00, 0x00, 0x00,
+            // Offsets
+            0x08, 0x00, 0x00, 0x00,
+            0x1d, 0x00, 0x00, 0x00,
+            // List 0
+            0x04, 0x10, 0x20,
+            0x05, 0x00, 0x10, 0x00, 0x00,
+            0x04, 0x01, 0x02,
+            0x07, 0x00, 0x20, 0x00, 0x00, 0x08,
+            0x02, 0x00, 0x01,
+            0x00,
+            // List 1
+            0x01, 0x01,
+            0x04, 0x00, 0x04,
+            0x03, 0x00, 0x10,
+            0x06, 0x00, 0x30, 0x00, 0x00, 0x10, 0x30, 0x00, 0x00,
+            0x00,
+        ];
+        let debug_addr = [
+            0x00, 0x40, 0x00, 0x00,
+            0x00, 0x50, 0x00, 0x00,
+        ];
+
+        let header = ListsHeader::read(&debug_rnglists, 0, LittleEndian).unwrap();
+        assert_eq!(header.offset_size, 4);
+        assert_eq!(header.address_size, 4);
+        assert_eq!(header.offset_entry_count, 2);
+        assert_eq!(header.offsets_base(), 12);
+        assert_eq!(header.end_offset(), debug_rnglists.len());
+
+        let base = header.offsets_base() as u64;
+        let offset = rnglistx_offset(&debug_rnglists, LittleEndian, 4, base, 0).unwrap();
+        assert_eq!(offset, 20);
+        let raw = RawRngListIterator::new(&debug_rnglists, offset as usize, LittleEndian, 4)
+            .unwrap();
+        let mut ranges = RngListIterator::new(raw, 0x100, &debug_addr, 0);
+        assert_eq!(ranges.next().unwrap(), Some(Range { begin: 0x110, end: 0x120 }));
+        assert_eq!(ranges.next().unwrap(), Some(Range { begin: 0x1001, end: 0x1002 }));
+        assert_eq!(ranges.next().unwrap(), Some(Range { begin: 0x2000, end: 0x2008 }));
+        assert_eq!(ranges.next().unwrap(), Some(Range { begin: 0x4000, end: 0x5000 }));
+        assert_eq!(ranges.next().unwrap(), None);
+        assert_eq!(ranges.next().unwrap(), None);
+
+        let offset = rnglistx_offset(&debug_rnglists, LittleEndian, 4, base, 1).unwrap();
+        assert_eq!(offset, 41);
+        let mut raw = RawRngListIterator::new(&debug_rnglists, offset as usize, LittleEndian, 4)
+            .unwrap();
+        let mut ranges = RngListIterator::new(raw.clone(), 0, &debug_addr, 0);
+        assert_eq!(raw.next().unwrap(), Some(RngListEntry::BaseAddressx(1)));
+        assert_eq!(ranges.next().unwrap(), Some(Range { begin: 0x5000, end: 0x5004 }));
+        assert_eq!(raw.next().unwrap(), Some(RngListEntry::OffsetPair { begin: 0, end: 4 }));
+        assert_eq!(raw.next().unwrap(), Some(RngListEntry::StartxLength { begin: 0, length: 0x10 }));
+        assert_eq!(ranges.next().unwrap(), Some(Range { begin: 0x4000, end: 0x4010 }));
+        assert_eq!(ranges.next().unwrap(), Some(Range { begin: 0x3000, end: 0x3010 }));
+        assert_eq!(ranges.next().unwrap(), None);
+
+        // The index is past the end of .debug_addr.
+        let raw = RawRngListIterator::new(&[0x02, 0x00, 0x02], 0, LittleEndian, 4).unwrap();
+        let mut ranges = RngListIterator::new(raw, 0, &debug_addr, 0);
+        assert!(ranges.next().is_err());
+
+        assert!(rnglistx_offset(&debug_rnglists, LittleEndian, 4, base, 20).is_err());
+        assert!(ListsHeader::read(&debug_rnglists, 12, LittleEndian).is_err());
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn range_list() {
+        let debug_ranges = [
+            0x10, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00,
+            // A base address selection entry.
+            0xff, 0xff, 0xff, 0xff, 0x00, 0x40, 0x00, 0x00,
+            0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // Truncated.
+            0x00, 0x10, 0x00, 0x00,
+        ];
+
+        let mut ranges = RangeListIterator::new(&debug_ranges, 0, LittleEndian, 4, 0x1000)
+            .unwrap();
+        assert_eq!(ranges.next().unwrap(), Some(Range { begin: 0x1010, end: 0x1020 }));
+        assert_eq!(ranges.next().unwrap(), Some(Range { begin: 0x4001, end: 0x4002 }));
+        assert_eq!(ranges.next().unwrap(), None);
+        assert_eq!(ranges.next().unwrap(), None);
+
+        let mut ranges = RangeListIterator::new(&debug_ranges, 32, LittleEndian, 4, 0)
+            .unwrap();
+        assert!(ranges.next().is_err());
+
+        assert!(RangeListIterator::new(&debug_ranges, 36, LittleEndian, 4, 0).is_err());
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn sections_rnglist() {
+        let abbrev = AbbrevVec::new(vec![
+            Abbrev { code: 1, tag: DW_TAG_compile_unit, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_low_pc, DW_FORM_addr),
+                AbbrevAttribute::new(DW_AT_rnglists_base, DW_FORM_sec_offset),
+                AbbrevAttribute::new(DW_AT_ranges, DW_FORM_rnglistx),
+            ] },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev.write(&mut debug_abbrev).unwrap();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        let entry = Die::new(0, 1, DW_TAG_compile_unit, false, vec![
+            Attribute { at: DW_AT_low_pc, data: AttributeData::Address(0x1000) },
+            Attribute { at: DW_AT_rnglists_base, data: AttributeData::SecOffset(12) },
+            Attribute { at: DW_AT_ranges, data: AttributeData::RangeListIndex(0) },
+        ]);
+        let mut unit = CompilationUnit::<LittleEndian>::default();
+        unit.common.version = 5;
+        let mut data = Vec::new();
+        entry.write(&mut data, &unit.common, &abbrev_hash).unwrap();
+        unit.common.data = &data;
+        let mut debug_info = Vec::new();
+        unit.write(&mut debug_info).unwrap();
+
+        let sections = Sections {
+            debug_abbrev: debug_abbrev,
+            debug_info: debug_info,
+            debug_rnglists: vec![
+                0x10, 0x00, 0x00, 0x00, 0x05, 0x00, 0x04, 0x00, 0x01, 0x00, 0x00, 0x00,
+                0x04, 0x00, 0x00, 0x00,
+                0x04, 0x00, 0x10, 0x00,
+            ],
+            ..Sections::new(LittleEndian)
+        };
+        let unit = sections.compilation_units().next().unwrap().unwrap();
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let bases = UnitBases::read(&unit, &abbrev).unwrap();
+        let data = {
+            let mut entries = unit.entries(&abbrev);
+            entries.next().unwrap().unwrap().attr(DW_AT_ranges).cloned().unwrap()
+        };
+        let mut ranges = sections.rnglist(&unit.common, &bases, &data).unwrap().unwrap();
+        assert_eq!(ranges.next().unwrap(), Some(Range { begin: 0x1000, end: 0x1010 }));
+        assert_eq!(ranges.next().unwrap(), None);
+
+        let data = AttributeData::SecOffset(16);
+        let mut ranges = sections.rnglist(&unit.common, &bases, &data).unwrap().unwrap();
+        assert_eq!(ranges.next().unwrap(), Some(Range { begin: 0x1000, end: 0x1010 }));
+
+        let data = AttributeData::Flag(true);
+        assert!(sections.rnglist(&unit.common, &bases, &data).unwrap().is_none());
+    }
+}
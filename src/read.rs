@@ -8,7 +8,14 @@ pub enum ReadError {
     Eof,
     Invalid,
     Unsupported,
+    // The version of a unit or line program header isn't supported.
+    UnsupportedVersion(u16),
     Overflow,
+    // A chain of references returned to the entry at this offset.
+    ReferenceCycle(usize),
+    // A chain of references was longer than the limit, and was stopped
+    // at the entry at this offset.
+    ReferenceLimit(usize),
 }
 
 impl std::convert::From<std::io::Error> for ReadError {
@@ -17,14 +24,142 @@ impl std::convert::From<std::io::Error> for ReadError {
     }
 }
 
+// A cursor over section data that tracks the section offset of the data.
+//
+// This is the only place that advances through a slice. The invariants are:
+//
+// - `data` is always a suffix of the data the cursor was created with, or
+//   a prefix of it for a cursor returned by `split`.
+// - `offset` is the section offset of the first byte of `data`, so
+//   `offset + len()` doesn't change as the cursor advances.
+// - A read that fails doesn't advance the cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bytes<'data> {
+    data: &'data [u8],
+    offset: usize,
+}
+
+impl<'data> Bytes<'data> {
+    // Create a cursor for `data`, which starts at `offset` in its section.
+    pub fn new(data: &'data [u8], offset: usize) -> Self {
+        Bytes {
+            data: data,
+            offset: offset,
+        }
+    }
+
+    // The number of bytes remaining.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    // The section offset of the next byte.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    // The section offset of the end of the data.
+    #[inline]
+    pub fn end_offset(&self) -> usize {
+        self.offset + self.data.len()
+    }
+
+    // The remaining bytes.
+    #[inline]
+    pub fn as_slice(&self) -> &'data [u8] {
+        self.data
+    }
+
+    // Skip `len` bytes.
+    #[inline]
+    pub fn skip(&mut self, len: usize) -> Result<(), ReadError> {
+        self.split(len).map(|_| ())
+    }
+
+    // Skip to the section offset `offset`, which must be within the
+    // remaining data or at its end.
+    pub fn skip_to(&mut self, offset: usize) -> Result<(), ReadError> {
+        if offset < self.offset {
+            return Err(ReadError::Invalid);
+        }
+        self.skip(offset - self.offset)
+    }
+
+    // Return a cursor for the next `len` bytes, and skip them.
+    #[inline]
+    pub fn split(&mut self, len: usize) -> Result<Bytes<'data>, ReadError> {
+        if len > self.data.len() {
+            return Err(ReadError::Eof);
+        }
+        let (head, tail) = self.data.split_at(len);
+        let bytes = Bytes::new(head, self.offset);
+        self.data = tail;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    // Return the next `len` bytes, and skip them.
+    #[inline]
+    pub fn read_slice(&mut self, len: usize) -> Result<&'data [u8], ReadError> {
+        self.split(len).map(|bytes| bytes.data)
+    }
+
+    #[inline]
+    pub fn read_u8(&mut self) -> Result<u8, ReadError> {
+        self.read_slice(1).map(|val| val[0])
+    }
+
+    // Return the bytes before the next null byte, and skip them and the
+    // null byte.
+    pub fn read_string(&mut self) -> Result<&'data [u8], ReadError> {
+        let len = match self.data.iter().position(|&x| x == 0) {
+            Some(len) => len,
+            None => return Err(ReadError::Invalid),
+        };
+        let val = try!(self.read_slice(len));
+        try!(self.skip(1));
+        Ok(val)
+    }
+
+    // Advance to `rest`, which is the remaining data after reading from
+    // `as_slice` with a function that takes `&mut &[u8]`.
+    //
+    // Returns `ReadError::Invalid` if `rest` isn't a suffix of the
+    // remaining data.
+    pub fn advance_to(&mut self, rest: &'data [u8]) -> Result<(), ReadError> {
+        if rest.len() > self.data.len() {
+            return Err(ReadError::Invalid);
+        }
+        let len = self.data.len() - rest.len();
+        if self.data[len..].as_ptr() != rest.as_ptr() {
+            return Err(ReadError::Invalid);
+        }
+        self.skip(len)
+    }
+}
+
+// Call a function that reads from a `Bytes`, and advance `r` past the
+// data that it read.
+#[inline]
+fn read_with<'data, T, F>(r: &mut &'data [u8], f: F) -> Result<T, ReadError>
+    where F: FnOnce(&mut Bytes<'data>) -> Result<T, ReadError>
+{
+    let mut bytes = Bytes::new(*r, 0);
+    let val = try!(f(&mut bytes));
+    *r = bytes.as_slice();
+    Ok(val)
+}
+
 #[inline]
 pub fn read_u8(r: &mut &[u8]) -> Result<u8, ReadError> {
-    if r.len() < 1 {
-        return Err(ReadError::Eof);
-    }
-    let byte = r[0];
-    *r = &r[1..];
-    Ok(byte)
+    read_with(r, |bytes| bytes.read_u8())
 }
 
 #[inline]
@@ -32,23 +167,30 @@ pub fn read_i8(r: &mut &[u8]) -> Result<i8, ReadError> {
     read_u8(r).map(|val| val as i8)
 }
 
+// Like `read_slice`, but return `ReadError::Invalid` if `r` is too short,
+// since the length has been read from the data.
 pub fn read_block<'a>(r: &mut &'a [u8], len: usize) -> Result<&'a [u8], ReadError> {
-    if len > r.len() {
-        return Err(ReadError::Invalid);
-    }
-    let val = &r[..len];
-    *r = &r[len..];
-    Ok(val)
+    read_with(r, |bytes| bytes.read_slice(len).map_err(|_| ReadError::Invalid))
+}
+
+// Return the next `len` bytes, or `ReadError::Eof` if `r` is too short.
+pub fn read_slice<'a>(r: &mut &'a [u8], len: usize) -> Result<&'a [u8], ReadError> {
+    read_with(r, |bytes| bytes.read_slice(len))
 }
 
 pub fn read_string<'a>(r: &mut &'a [u8]) -> Result<&'a [u8], ReadError> {
-    let len = match r.iter().position(|&x| x == 0) {
-        Some(len) => len,
-        None => return Err(ReadError::Invalid),
+    read_with(r, |bytes| bytes.read_string())
+}
+
+// Read a 3 byte unsigned value, for DW_FORM_strx3 and DW_FORM_addrx3.
+pub fn read_u24<E: Endian>(r: &mut &[u8], endian: E) -> Result<u32, ReadError> {
+    let val = try!(read_slice(r, 3));
+    let (b0, b1, b2) = if endian.is_big_endian() {
+        (val[2], val[1], val[0])
+    } else {
+        (val[0], val[1], val[2])
     };
-    let val = &r[..len];
-    *r = &r[len + 1..];
-    Ok(val)
+    Ok((b2 as u32) << 16 | (b1 as u32) << 8 | b0 as u32)
 }
 
 pub fn read_offset<E: Endian>(r: &mut &[u8], endian: E, offset_size: u8) -> Result<u64, ReadError> {
@@ -73,7 +215,36 @@ pub fn read_address<E: Endian>(
     Ok(val)
 }
 
+// Read the entry at `index` in an array of addresses or offsets of `size`
+// bytes that starts at `base` in `data`, such as the entries of a unit in
+// .debug_addr.
+pub fn read_indexed<E: Endian>(
+    data: &[u8],
+    endian: E,
+    size: u8,
+    base: u64,
+    index: u64
+) -> Result<u64, ReadError> {
+    let offset = try!(index.checked_mul(size as u64)
+        .and_then(|offset| offset.checked_add(base))
+        .ok_or(ReadError::Overflow));
+    if offset >= data.len() as u64 {
+        return Err(ReadError::Invalid);
+    }
+    let mut r = &data[offset as usize..];
+    read_address(&mut r, endian, size)
+}
+
 pub fn read_initial_length<E: Endian>(r: &mut &[u8], endian: E) -> Result<(u8, usize), ReadError> {
+    let (offset_size, len) = try!(read_unit_length(r, endian));
+    if len > r.len() {
+        return Err(ReadError::Invalid);
+    }
+    Ok((offset_size, len))
+}
+
+// Like `read_initial_length`, but don't check that `r` contains the data.
+pub fn read_unit_length<E: Endian>(r: &mut &[u8], endian: E) -> Result<(u8, usize), ReadError> {
     let mut offset_size = 4;
     let mut len = try!(endian.read_u32(r)) as usize;
     if len == 0xffffffff {
@@ -82,8 +253,48 @@ pub fn read_initial_length<E: Endian>(r: &mut &[u8], endian: E) -> Result<(u8, u
     } else if len >= 0xfffffff0 {
         return Err(ReadError::Unsupported);
     }
-    if len > r.len() {
-        return Err(ReadError::Invalid);
-    }
     Ok((offset_size, len))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bytes() {
+        let data = [1, 2, 3, b'a', 0, 4];
+        let mut bytes = Bytes::new(&data, 0x10);
+        assert_eq!(bytes.read_u8().unwrap(), 1);
+        assert_eq!((bytes.offset(), bytes.len(), bytes.end_offset()), (0x11, 5, 0x16));
+
+        let mut head = bytes.split(2).unwrap();
+        assert_eq!((head.offset(), head.as_slice()), (0x11, &[2, 3][..]));
+        assert_eq!(head.read_slice(2).unwrap(), &[2, 3]);
+        assert!(head.is_empty());
+        assert_eq!(head.offset(), 0x13);
+
+        assert_eq!(bytes.read_string().unwrap(), b"a");
+        assert_eq!(bytes.offset(), 0x15);
+        assert!(bytes.read_string().is_err());
+        assert!(bytes.skip(2).is_err());
+        assert!(bytes.skip_to(0x14).is_err());
+        assert_eq!(bytes.offset(), 0x15);
+        bytes.skip_to(0x16).unwrap();
+        assert!(bytes.is_empty());
+        assert!(bytes.read_u8().is_err());
+
+        let mut bytes = Bytes::new(&data, 0);
+        let mut r = bytes.as_slice();
+        read_block(&mut r, 4).unwrap();
+        bytes.advance_to(r).unwrap();
+        assert_eq!(bytes.offset(), 4);
+        assert!(bytes.advance_to(&data[1..]).is_err());
+        assert!(bytes.advance_to(&[4]).is_err());
+        assert_eq!(bytes.offset(), 4);
+
+        let mut r = &data[..];
+        assert!(read_block(&mut r, 7).is_err());
+        assert!(read_slice(&mut r, 7).is_err());
+        assert_eq!(r.len(), 6);
+    }
+}
@@ -0,0 +1,1299 @@
+use std;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Deref;
+
+use abbrev::{AbbrevHash, AbbrevVec};
+use class;
+use constant;
+use die::{AttributeData, Die};
+use endian::Endian;
+use leb128;
+use line::LineProgram;
+use offset::{DebugInfoOffset, UnitOffset};
+use read::*;
+use strings::{str_offsets, StringIterator, StringUsage};
+use unit::{CompilationUnit, CompilationUnitIterator, TypeUnitIterator, UnitCommon};
+use write::*;
+use Sections;
+
+// A mapping from offsets in an original section to offsets in the
+// rewritten section.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OffsetMap(BTreeMap<usize, usize>);
+
+impl OffsetMap {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter<'a>(&'a self) -> std::collections::btree_map::Iter<'a, usize, usize> {
+        self.0.iter()
+    }
+
+    pub fn get(&self, old: usize) -> Option<usize> {
+        self.0.get(&old).cloned()
+    }
+
+    pub fn insert(&mut self, old: usize, new: usize) -> Option<usize> {
+        self.0.insert(old, new)
+    }
+}
+
+struct RewriteUnit<'data, E: Endian> {
+    unit: CompilationUnit<'data, E>,
+    abbrev: AbbrevHash,
+    entries: Vec<Die<'data>>,
+}
+
+// Rewrite the compilation units in .debug_info.
+//
+// `f` is called for each non-null entry, and may modify the entry's
+// attribute values. The attributes must still match the abbreviation.
+//
+// Entries are laid out again, and references to entries in .debug_info
+// (`Ref` and `RefAddress`) are updated to the new offsets.
+//
+// Returns the new section data, and the mapping from old to new unit and
+// entry offsets. Other sections that refer to .debug_info offsets can be
+// updated using this mapping, such as with `update_aranges_offsets`. An
+// error is returned if a reference no longer fits its form.
+//
+// Type units in .debug_types aren't rewritten. They refer to each other by
+// signature, which doesn't change, but a DW_FORM_ref_addr in .debug_types
+// would need updating, so it is rejected.
+pub fn rewrite_debug_info<E, S, F>(
+    sections: &Sections<E, S>,
+    mut f: F
+) -> Result<(Vec<u8>, OffsetMap), WriteError>
+    where E: Endian,
+          S: Deref<Target = [u8]>,
+          F: FnMut(&mut Die)
+{
+    let mut iter = sections.type_units();
+    while let Some(unit) = try!(iter.next()) {
+        let abbrev = try!(sections.abbrev(&unit.common));
+        let mut entries = unit.entries(&abbrev);
+        while let Some(entry) = try!(entries.next()) {
+            for attribute in &entry.attributes {
+                if let AttributeData::RefAddress(_) = attribute.data {
+                    let message = format!("reference from .debug_types entry {:x}", entry.offset);
+                    return Err(WriteError::Unsupported(message));
+                }
+            }
+        }
+    }
+
+    let mut units = Vec::new();
+    let mut iter = sections.compilation_units();
+    while let Some(unit) = try!(iter.next()) {
+        let abbrev = try!(sections.abbrev(&unit.common));
+        let mut entries = Vec::new();
+        {
+            let mut iter = unit.entries(&abbrev);
+            while let Some(entry) = try!(iter.next()) {
+                let mut entry = entry.clone();
+                if !entry.is_null() {
+                    f(&mut entry);
+                    entry.update_attribute_mask();
+                }
+                entries.push(entry);
+            }
+        }
+        units.push(RewriteUnit {
+            unit: unit,
+            abbrev: abbrev,
+            entries: entries,
+        });
+    }
+
+    // The size of entries may depend on the offsets of the entries they
+    // reference (for DW_FORM_ref_udata), so repeat the layout until the
+    // offsets don't change.
+    let mut map = None;
+    for _ in 0..8 {
+        let (data, new_map) = try!(layout(&units, map.as_ref()));
+        if map.as_ref() == Some(&new_map) {
+            return Ok((data, new_map));
+        }
+        map = Some(new_map);
+    }
+    Err(WriteError::Invalid("entry offsets did not converge".to_string()))
+}
+
+// Write all units, remapping references using `map` if given.
+fn layout<'data, E: Endian>(
+    units: &[RewriteUnit<'data, E>],
+    map: Option<&OffsetMap>
+) -> Result<(Vec<u8>, OffsetMap), WriteError> {
+    let mut data = Vec::new();
+    let mut new_map = OffsetMap::new();
+    for unit in units {
+        let old = &unit.unit.common;
+        let mut new_unit = CompilationUnit {
+            common: UnitCommon {
+                offset: data.len(),
+                endian: old.endian,
+                version: old.version,
+                address_size: old.address_size,
+                offset_size: old.offset_size,
+                abbrev_offset: old.abbrev_offset,
+                unit_type: old.unit_type,
+                unit_id: old.unit_id,
+                type_offset: old.type_offset,
+                data: &[],
+                padding: old.padding,
+                quirks: old.quirks,
+            },
+        };
+        let data_offset = new_unit.data_offset();
+        new_map.insert(old.offset, new_unit.common.offset);
+
+        let mut unit_data = Vec::new();
+        for entry in &unit.entries {
+            new_map.insert(entry.offset, data_offset + unit_data.len());
+            match map {
+                Some(map) => {
+                    let mut entry = entry.clone();
+                    for attribute in &mut entry.attributes {
+                        try!(remap(&mut attribute.data, map, old.offset, new_unit.common.offset));
+                    }
+                    try!(entry.write(&mut unit_data, &new_unit.common, &unit.abbrev));
+                }
+                None => try!(entry.write(&mut unit_data, &new_unit.common, &unit.abbrev)),
+            }
+        }
+
+        new_unit.common.data = &unit_data;
+        try!(new_unit.write(&mut data));
+    }
+    Ok((data, new_map))
+}
+
+fn remap(
+    data: &mut AttributeData,
+    map: &OffsetMap,
+    old_unit_offset: usize,
+    new_unit_offset: usize
+) -> Result<(), WriteError> {
+    match *data {
+        AttributeData::Ref(ref mut val) => {
+            let old = match old_unit_offset.checked_add(val.0 as usize) {
+                Some(old) => old,
+                None => return Err(WriteError::Invalid(format!("invalid reference {:x}", val.0))),
+            };
+            match map.get(old).and_then(|new| new.checked_sub(new_unit_offset)) {
+                Some(new) => *val = UnitOffset(new as u64),
+                None => return Err(WriteError::Invalid(format!("invalid reference {:x}", old))),
+            }
+        }
+        AttributeData::RefAddress(ref mut val) => {
+            match map.get(val.0 as usize) {
+                Some(new) => *val = DebugInfoOffset(new as u64),
+                None => return Err(WriteError::Invalid(format!("invalid reference {:x}", val.0))),
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// Update line program offsets in .debug_info, without rewriting the entries.
+//
+// The values of lineptr class attributes (such as DW_AT_stmt_list) are
+// looked up in `map`, and the new offset is written over the old value.
+// This is possible because the forms for lineptr values have a fixed size.
+//
+// Returns the number of attributes that were updated.
+pub fn update_line_offsets<E: Endian>(
+    endian: E,
+    debug_abbrev: &[u8],
+    debug_info: &mut [u8],
+    map: &OffsetMap
+) -> Result<usize, WriteError> {
+    patch_attributes(endian, debug_abbrev, debug_info, |_, at, form, value| {
+        let is_line_ptr = class::attribute_classes(at)
+            .is_some_and(|classes| classes.contains(&class::AttributeClass::LinePtr));
+        if !is_line_ptr {
+            return Ok(None);
+        }
+        let old = match *value {
+            AttributeData::Data4(val) => val as u64,
+            AttributeData::Data8(val) => val,
+            AttributeData::SecOffset(val) => val,
+            _ => return Err(WriteError::Unsupported(format!("form {} for attribute {}", form, at))),
+        };
+        let new = match map.get(old as usize) {
+            Some(new) => new as u64,
+            None => return Err(WriteError::Invalid(format!("invalid line offset {:x}", old))),
+        };
+        Ok(Some(match *value {
+            AttributeData::Data4(_) => AttributeData::Data4(new as u32),
+            AttributeData::Data8(_) => AttributeData::Data8(new),
+            _ => AttributeData::SecOffset(new),
+        }))
+    })
+}
+
+// Update the unit offsets in .debug_aranges after rewriting .debug_info.
+//
+// The offset of each set of ranges is looked up in `map`, which must
+// contain the unit offsets, such as the map returned by
+// `rewrite_debug_info`.
+//
+// Returns the number of sets that were updated.
+pub fn update_aranges_offsets<E: Endian>(
+    endian: E,
+    debug_aranges: &mut [u8],
+    map: &OffsetMap
+) -> Result<usize, WriteError> {
+    let mut count = 0;
+    let mut offset = 0;
+    while offset < debug_aranges.len() {
+        let mut r = &debug_aranges[offset..];
+        let (offset_size, len) = try!(read_initial_length(&mut r, endian));
+        let header_start = debug_aranges.len() - r.len();
+        if len > r.len() {
+            return Err(WriteError::Read(ReadError::Invalid));
+        }
+        let version = try!(endian.read_u16(&mut r));
+        if version != 2 {
+            return Err(WriteError::Unsupported(format!("aranges version {}", version)));
+        }
+        let old = try!(read_offset(&mut r, endian, offset_size));
+        let new = match map.get(old as usize) {
+            Some(new) => new as u64,
+            None => return Err(WriteError::Invalid(format!("invalid unit offset {:x}", old))),
+        };
+        try!(write_offset(&mut &mut debug_aranges[header_start + 2..],
+                          endian,
+                          offset_size,
+                          new));
+        count += 1;
+        offset = header_start + len;
+    }
+    Ok(count)
+}
+
+// Add `slide` to all addresses, for rebasing an image.
+//
+// This updates:
+// - DW_FORM_addr values in .debug_info
+// - DW_LNE_set_address operands in .debug_line
+// - address ranges in .debug_aranges
+// - base address selection entries in .debug_ranges
+//
+// Other entries in .debug_ranges are relative to the unit's base address,
+// so they don't need changing. Addresses within location expressions are
+// not updated.
+//
+// A negative slide can be given using a wrapping negation.
+pub fn slide_addresses<E: Endian>(sections: &mut Sections<E>, slide: u64) -> Result<(), WriteError> {
+    let endian = sections.endian;
+
+    // (offset, address size) of range lists.
+    let mut range_lists = Vec::new();
+    try!(patch_attributes(endian,
+                          &sections.debug_abbrev,
+                          &mut sections.debug_info,
+                          |unit, at, form, value| {
+        match (at, value) {
+            (_, &AttributeData::Address(val)) => {
+                Ok(Some(AttributeData::Address(add_address(val, slide, unit.address_size))))
+            }
+            (constant::DW_AT_ranges, _) |
+            (constant::DW_AT_start_scope, _) => {
+                // Before version 4, DW_AT_ranges used data4 or data8 for its
+                // rangelistptr, but a data4 or data8 DW_AT_start_scope is
+                // a constant offset from the start of the scope.
+                let offset = match (at, form, value) {
+                    (_, constant::DW_FORM_sec_offset, &AttributeData::SecOffset(val)) => Some(val),
+                    (constant::DW_AT_ranges,
+                     constant::DW_FORM_data4,
+                     &AttributeData::Data4(val)) if unit.version < 4 => Some(val as u64),
+                    (constant::DW_AT_ranges,
+                     constant::DW_FORM_data8,
+                     &AttributeData::Data8(val)) if unit.version < 4 => Some(val),
+                    _ => None,
+                };
+                if let Some(offset) = offset {
+                    range_lists.push((offset as usize, unit.address_size));
+                }
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }));
+
+    try!(slide_line_programs(endian, &mut sections.debug_line, slide));
+    try!(slide_aranges(endian, &mut sections.debug_aranges, slide));
+
+    range_lists.sort();
+    range_lists.dedup();
+    for (offset, address_size) in range_lists {
+        try!(slide_range_list(endian, &mut sections.debug_ranges, offset, address_size, slide));
+    }
+    Ok(())
+}
+
+// A group of units that use identical abbreviation tables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbbrevTable {
+    // The offsets in .debug_abbrev of the copies of the table.
+    pub offsets: Vec<u64>,
+    // The offsets of the compilation units in .debug_info.
+    pub units: Vec<usize>,
+    // The offsets of the type units in .debug_types.
+    pub type_units: Vec<usize>,
+    // The size of one copy of the table.
+    pub len: usize,
+}
+
+impl AbbrevTable {
+    // The number of bytes that would be saved by sharing a single copy.
+    pub fn duplicate_len(&self) -> usize {
+        self.len * (self.offsets.len() - 1)
+    }
+}
+
+// Find the units that use identical abbreviation tables.
+//
+// Tables are compared by their encoded bytes. The groups are in order of
+// the first use of each table.
+pub fn abbrev_tables<E, S>(sections: &Sections<E, S>) -> Result<Vec<AbbrevTable>, ReadError>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    let debug_abbrev = &*sections.debug_abbrev;
+    let mut tables: Vec<AbbrevTable> = Vec::new();
+    let mut index = HashMap::new();
+    let mut add = |abbrev_offset: u64, unit: usize, type_unit: bool| {
+        let offset = abbrev_offset as usize;
+        if offset >= debug_abbrev.len() {
+            return Err(ReadError::Invalid);
+        }
+        let mut r = &debug_abbrev[offset..];
+        try!(AbbrevVec::read(&mut r));
+        let bytes = &debug_abbrev[offset..debug_abbrev.len() - r.len()];
+        let i = *index.entry(bytes).or_insert_with(|| {
+            tables.push(AbbrevTable {
+                offsets: Vec::new(),
+                units: Vec::new(),
+                type_units: Vec::new(),
+                len: bytes.len(),
+            });
+            tables.len() - 1
+        });
+        let table = &mut tables[i];
+        if !table.offsets.contains(&abbrev_offset) {
+            table.offsets.push(abbrev_offset);
+        }
+        if type_unit {
+            table.type_units.push(unit);
+        } else {
+            table.units.push(unit);
+        }
+        Ok(())
+    };
+
+    let mut units = sections.compilation_units();
+    while let Some(unit) = try!(units.next()) {
+        try!(add(unit.common.abbrev_offset, unit.common.offset, false));
+    }
+    let mut units = sections.type_units();
+    while let Some(unit) = try!(units.next()) {
+        try!(add(unit.common.abbrev_offset, unit.common.offset, true));
+    }
+    Ok(tables)
+}
+
+// Rewrite .debug_abbrev so that identical tables are only stored once.
+//
+// The abbrev_offset of each unit header is updated in place. Tables that
+// aren't used by any unit are removed.
+//
+// Returns the number of bytes removed from .debug_abbrev.
+pub fn share_abbrevs<E: Endian>(sections: &mut Sections<E>) -> Result<usize, WriteError> {
+    let tables = try!(abbrev_tables(sections));
+    let mut debug_abbrev = Vec::new();
+    // (section is .debug_types, unit offset, new abbrev offset)
+    let mut patches = Vec::new();
+    for table in &tables {
+        let new_offset = debug_abbrev.len() as u64;
+        let offset = table.offsets[0] as usize;
+        debug_abbrev.extend_from_slice(&sections.debug_abbrev[offset..offset + table.len]);
+        for &unit in &table.units {
+            patches.push((false, unit, new_offset));
+        }
+        for &unit in &table.type_units {
+            patches.push((true, unit, new_offset));
+        }
+    }
+
+    let endian = sections.endian;
+    for (type_unit, offset, abbrev_offset) in patches {
+        let data = if type_unit {
+            &mut sections.debug_types
+        } else {
+            &mut sections.debug_info
+        };
+        let mut r = &data[offset..];
+        let (offset_size, _) = try!(read_initial_length(&mut r, endian));
+        let version = try!(endian.read_u16(&mut r));
+        // initial length + version, and then unit type + address size in
+        // version 5
+        let mut position = offset + if offset_size == 8 { 12 } else { 4 } + 2;
+        if version >= 5 {
+            position += 2;
+        }
+        try!(write_offset(&mut &mut data[position..], endian, offset_size, abbrev_offset));
+    }
+
+    let saved = sections.debug_abbrev.len().saturating_sub(debug_abbrev.len());
+    sections.debug_abbrev = debug_abbrev;
+    Ok(saved)
+}
+
+// Remove the strings in .debug_str that aren't referenced.
+//
+// The DW_FORM_strp values in .debug_info and .debug_types, and the entries
+// of .debug_str_offsets, are updated in place. References to the tail of a
+// string are kept pointing within the moved string.
+//
+// Returns the mapping from the old to the new offset of each string that
+// was kept.
+pub fn gc_strings<E: Endian>(sections: &mut Sections<E>) -> Result<OffsetMap, WriteError> {
+    let usage = try!(StringUsage::from_sections(sections));
+    if let Some(&offset) = usage.invalid(&sections.debug_str).first() {
+        return Err(WriteError::Invalid(format!("string offset {:x}", offset)));
+    }
+
+    let mut map = OffsetMap::new();
+    let mut debug_str = Vec::new();
+    {
+        let mut strings = StringIterator::new(&sections.debug_str);
+        while let Some((offset, string)) = try!(strings.next()) {
+            if usage.is_used(offset, string.len()) {
+                map.insert(offset, debug_str.len());
+                debug_str.extend_from_slice(string);
+                debug_str.push(0);
+            }
+        }
+    }
+
+    {
+        let mut remap = |_: &UnitCommon<E>, _, _, value: &AttributeData| {
+            match *value {
+                AttributeData::StringOffset(old) => {
+                    let new = try!(remap_string(&map, old));
+                    Ok(Some(AttributeData::StringOffset(new)))
+                }
+                _ => Ok(None),
+            }
+        };
+        try!(patch_section_attributes(sections.endian,
+                                      &sections.debug_abbrev,
+                                      &mut sections.debug_info,
+                                      false,
+                                      &mut remap));
+        try!(patch_section_attributes(sections.endian,
+                                      &sections.debug_abbrev,
+                                      &mut sections.debug_types,
+                                      true,
+                                      &mut remap));
+    }
+    for entry in try!(str_offsets(sections)) {
+        let new = try!(remap_string(&map, entry.offset));
+        let mut w = &mut sections.debug_str_offsets[entry.position..];
+        try!(write_offset(&mut w, sections.endian, entry.offset_size, new));
+    }
+    sections.debug_str = debug_str;
+    Ok(map)
+}
+
+// Return the new offset of a string reference, given the map from old to
+// new string offsets.
+fn remap_string(map: &OffsetMap, old: u64) -> Result<u64, WriteError> {
+    // The string containing the offset is the last one that starts at or
+    // before it.
+    match map.0.range(..old as usize + 1).next_back() {
+        Some((&start, &new)) => Ok((new + old as usize - start) as u64),
+        None => Err(WriteError::Invalid(format!("string offset {:x}", old))),
+    }
+}
+
+fn add_address(address: u64, slide: u64, address_size: u8) -> u64 {
+    if address_size == 4 {
+        (address as u32).wrapping_add(slide as u32) as u64
+    } else {
+        address.wrapping_add(slide)
+    }
+}
+
+fn patch_address<E: Endian>(
+    endian: E,
+    data: &mut [u8],
+    address_size: u8,
+    slide: u64
+) -> Result<(), WriteError> {
+    let address = try!(read_address(&mut &data[..], endian, address_size));
+    try!(write_address(&mut &mut data[..],
+                       endian,
+                       address_size,
+                       add_address(address, slide, address_size)));
+    Ok(())
+}
+
+fn slide_line_programs<E: Endian>(
+    endian: E,
+    debug_line: &mut [u8],
+    slide: u64
+) -> Result<(), WriteError> {
+    // Positions of the addresses, and their size.
+    let mut addresses = Vec::new();
+    {
+        let mut r = &debug_line[..];
+        while !r.is_empty() {
+            let offset = debug_line.len() - r.len();
+            // The address size isn't needed because we only use the header.
+            let program = try!(LineProgram::read(&mut r, offset, endian, 0, &[], &[]));
+            let end = debug_line.len() - r.len();
+            let start = end - program.data.len();
+
+            let mut data = program.data;
+            while !data.is_empty() {
+                let opcode = try!(read_u8(&mut data));
+                if opcode == 0 {
+                    let len = try!(leb128::read_u64(&mut data)) as usize;
+                    let mut op = try!(read_block(&mut data, len));
+                    let position = start + program.data.len() - data.len() - len;
+                    if try!(read_u8(&mut op)) == constant::DW_LNE_set_address.0 {
+                        addresses.push((position + 1, op.len() as u8));
+                    }
+                } else if opcode == constant::DW_LNS_fixed_advance_pc.0 {
+                    try!(read_block(&mut data, 2));
+                } else if opcode < program.opcode_base {
+                    let index = opcode as usize - 1;
+                    let count = match program.standard_opcode_lengths.get(index) {
+                        Some(&count) => count,
+                        None => return Err(WriteError::Read(ReadError::Invalid)),
+                    };
+                    for _ in 0..count {
+                        try!(leb128::read_u64(&mut data));
+                    }
+                }
+            }
+        }
+    }
+
+    for (position, address_size) in addresses {
+        try!(patch_address(endian, &mut debug_line[position..], address_size, slide));
+    }
+    Ok(())
+}
+
+fn slide_aranges<E: Endian>(
+    endian: E,
+    debug_aranges: &mut [u8],
+    slide: u64
+) -> Result<(), WriteError> {
+    let mut offset = 0;
+    while offset < debug_aranges.len() {
+        let mut r = &debug_aranges[offset..];
+        let (offset_size, len) = try!(read_initial_length(&mut r, endian));
+        let header_start = debug_aranges.len() - r.len();
+        if len > r.len() {
+            return Err(WriteError::Read(ReadError::Invalid));
+        }
+        let next = header_start + len;
+        let mut r = &r[..len];
+        let version = try!(endian.read_u16(&mut r));
+        if version != 2 {
+            return Err(WriteError::Unsupported(format!("aranges version {}", version)));
+        }
+        try!(read_offset(&mut r, endian, offset_size));
+        let address_size = try!(read_u8(&mut r));
+        let segment_size = try!(read_u8(&mut r)) as usize;
+        if address_size == 0 {
+            return Err(WriteError::Read(ReadError::Invalid));
+        }
+
+        // Tuples are aligned to twice the address size, relative to the
+        // start of the set.
+        let tuple_size = 2 * address_size as usize;
+        let mut position = next - r.len() - offset;
+        position += (tuple_size - position % tuple_size) % tuple_size;
+        let mut position = offset + position;
+        let tuple_size = segment_size + tuple_size;
+        while position + tuple_size <= next {
+            let mut r = &debug_aranges[position + segment_size..];
+            let address = try!(read_address(&mut r, endian, address_size));
+            let length = try!(read_address(&mut r, endian, address_size));
+            if address == 0 && length == 0 {
+                break;
+            }
+            try!(patch_address(endian,
+                               &mut debug_aranges[position + segment_size..],
+                               address_size,
+                               slide));
+            position += tuple_size;
+        }
+        offset = next;
+    }
+    Ok(())
+}
+
+fn slide_range_list<E: Endian>(
+    endian: E,
+    debug_ranges: &mut [u8],
+    offset: usize,
+    address_size: u8,
+    slide: u64
+) -> Result<(), WriteError> {
+    let max = if address_size == 4 {
+        u32::max_value() as u64
+    } else {
+        u64::max_value()
+    };
+    let mut position = offset;
+    loop {
+        if position > debug_ranges.len() {
+            return Err(WriteError::Read(ReadError::Invalid));
+        }
+        let mut r = &debug_ranges[position..];
+        let begin = try!(read_address(&mut r, endian, address_size));
+        let end = try!(read_address(&mut r, endian, address_size));
+        if begin == 0 && end == 0 {
+            return Ok(());
+        }
+        position += address_size as usize;
+        if begin == max {
+            // Base address selection entry.
+            try!(patch_address(endian, &mut debug_ranges[position..], address_size, slide));
+        }
+        position += address_size as usize;
+    }
+}
+
+// Call `f` for each attribute of the compilation units in `debug_info`.
+//
+// If `f` returns a new value, then it is written over the existing value.
+// The new value must have the same encoded length as the existing value.
+//
+// Returns the number of values that were written.
+fn patch_attributes<E, F>(
+    endian: E,
+    debug_abbrev: &[u8],
+    debug_info: &mut [u8],
+    f: F
+) -> Result<usize, WriteError>
+    where E: Endian,
+          F: FnMut(&UnitCommon<E>, constant::DwAt, constant::DwForm, &AttributeData)
+                   -> Result<Option<AttributeData<'static>>, WriteError>
+{
+    patch_section_attributes(endian, debug_abbrev, debug_info, false, f)
+}
+
+// Like `patch_attributes`, but for either .debug_info or .debug_types.
+fn patch_section_attributes<E, F>(
+    endian: E,
+    debug_abbrev: &[u8],
+    section: &mut [u8],
+    types: bool,
+    mut f: F
+) -> Result<usize, WriteError>
+    where E: Endian,
+          F: FnMut(&UnitCommon<E>, constant::DwAt, constant::DwForm, &AttributeData)
+                   -> Result<Option<AttributeData<'static>>, WriteError>
+{
+    // (position, encoded value)
+    let mut patches = Vec::new();
+    if types {
+        let mut units = TypeUnitIterator::new(endian, section);
+        while let Some(unit) = try!(units.next()) {
+            try!(patch_unit_attributes(&unit.common,
+                                       unit.data_offset(),
+                                       debug_abbrev,
+                                       &mut f,
+                                       &mut patches));
+        }
+    } else {
+        let mut units = CompilationUnitIterator::new(endian, section);
+        while let Some(unit) = try!(units.next()) {
+            try!(patch_unit_attributes(&unit.common,
+                                       unit.data_offset(),
+                                       debug_abbrev,
+                                       &mut f,
+                                       &mut patches));
+        }
+    }
+
+    for &(position, ref buf) in &patches {
+        section[position..position + buf.len()].copy_from_slice(buf);
+    }
+    Ok(patches.len())
+}
+
+fn patch_unit_attributes<E, F>(
+    unit: &UnitCommon<E>,
+    data_offset: usize,
+    debug_abbrev: &[u8],
+    f: &mut F,
+    patches: &mut Vec<(usize, Vec<u8>)>
+) -> Result<(), WriteError>
+    where E: Endian,
+          F: FnMut(&UnitCommon<E>, constant::DwAt, constant::DwForm, &AttributeData)
+                   -> Result<Option<AttributeData<'static>>, WriteError>
+{
+    let abbrev = try!(unit.abbrev(debug_abbrev));
+    let data = unit.data();
+    let mut r = data;
+    while !r.is_empty() {
+        let code = try!(leb128::read_u64(&mut r));
+        if code == 0 {
+            continue;
+        }
+        let abbrev = match abbrev.get(code) {
+            Some(abbrev) => abbrev,
+            None => return Err(WriteError::Read(ReadError::Invalid)),
+        };
+        for attribute in &abbrev.attributes {
+            let mut form = attribute.form;
+            if form == constant::DW_FORM_indirect {
+                form = constant::DwForm(try!(leb128::read_u16(&mut r)));
+            }
+            let start = r;
+            let position = data_offset + data.len() - r.len();
+            let value = if form == constant::DW_FORM_implicit_const {
+                AttributeData::SData(attribute.implicit_const)
+            } else {
+                try!(AttributeData::read(&mut r, unit, form))
+            };
+            let new = match try!(f(unit, attribute.at, form, &value)) {
+                Some(new) => new,
+                None => continue,
+            };
+            if form == constant::DW_FORM_implicit_const {
+                if new == value {
+                    continue;
+                }
+                return Err(WriteError::Unsupported(format!("attribute {} value is in abbrev",
+                                                           attribute.at)));
+            }
+            let mut buf = Vec::new();
+            try!(new.write(&mut buf, unit, form, false));
+            if buf.len() != start.len() - r.len() ||
+               try!(AttributeData::read(&mut &buf[..], unit, form)) != new {
+                return Err(WriteError::Invalid(format!("value does not fit form {}", form)));
+            }
+            patches.push((position, buf));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use abbrev::*;
+    use constant::*;
+    use die::*;
+    use endian::*;
+    use unit::*;
+    use Sections;
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn rewrite() {
+        let abbrev = AbbrevVec::new(vec![
+            Abbrev {
+                code: 1,
+                tag: DW_TAG_compile_unit,
+                children: true,
+                attributes: vec![],
+            },
+            Abbrev {
+                code: 2,
+                tag: DW_TAG_variable,
+                children: false,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                    AbbrevAttribute::new(DW_AT_type, DW_FORM_ref4),
+                ],
+            },
+            Abbrev {
+                code: 3,
+                tag: DW_TAG_base_type,
+                children: false,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                ],
+            },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev.write(&mut debug_abbrev).unwrap();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        // The header is 11 bytes, the root is 1 byte, and the variable is 7 bytes.
+        let entries = [
+            Die::new(0, 1, DW_TAG_compile_unit, true, vec![]),
+            Die::new(0, 2, DW_TAG_variable, false, vec![
+                Attribute { at: DW_AT_name, data: AttributeData::String(b"v") },
+                Attribute { at: DW_AT_type, data: AttributeData::Ref(UnitOffset(19)) },
+            ]),
+            Die::new(0, 3, DW_TAG_base_type, false, vec![
+                Attribute { at: DW_AT_name, data: AttributeData::String(b"int") },
+            ]),
+            Die::null(0),
+        ];
+        let mut unit = CompilationUnit::<LittleEndian>::default();
+        let mut data = Vec::new();
+        for entry in &entries {
+            entry.write(&mut data, &unit.common, &abbrev_hash).unwrap();
+        }
+        unit.common.data = &data;
+        let mut debug_info = Vec::new();
+        unit.write(&mut debug_info).unwrap();
+
+        let sections = Sections {
+            debug_abbrev: debug_abbrev,
+            debug_info: debug_info,
+            ..Sections::new(LittleEndian)
+        };
+
+        // No changes.
+        let (data, map) = rewrite_debug_info(&sections, |_| {}).unwrap();
+        assert_eq!(data, sections.debug_info);
+        assert_eq!(map.iter().map(|(&old, &new)| (old, new)).collect::<Vec<_>>(),
+                   vec![(0, 0), (11, 11), (12, 12), (19, 19), (24, 24)]);
+
+        // Lengthen the variable name.
+        let (data, map) = rewrite_debug_info(&sections, |entry| {
+            if entry.tag == DW_TAG_variable {
+                entry.attributes[0].data = AttributeData::String(b"var");
+            }
+        }).unwrap();
+        assert_eq!(map.get(19), Some(21));
+        let unit = CompilationUnit::read(&mut &data[..], 0, LittleEndian).unwrap();
+        let mut entries = unit.entries(&abbrev_hash);
+        entries.next().unwrap();
+        let entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.attr(DW_AT_type), Some(&AttributeData::Ref(UnitOffset(21))));
+        let entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.offset, 21);
+        assert_eq!(entry.tag, DW_TAG_base_type);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn rewrite_unsupported() {
+        static NAME: [u8; 0x100] = [b'v'; 0x100];
+        let abbrev = AbbrevVec::new(vec![
+            Abbrev {
+                code: 1,
+                tag: DW_TAG_compile_unit,
+                children: true,
+                attributes: vec![],
+            },
+            Abbrev {
+                code: 2,
+                tag: DW_TAG_variable,
+                children: false,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                    AbbrevAttribute::new(DW_AT_type, DW_FORM_ref1),
+                ],
+            },
+            Abbrev {
+                code: 3,
+                tag: DW_TAG_base_type,
+                children: false,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_type, DW_FORM_ref_addr),
+                ],
+            },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev.write(&mut debug_abbrev).unwrap();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        // The header is 11 bytes, the root is 1 byte, and the variable is 4 bytes.
+        let entries = [
+            Die::new(0, 1, DW_TAG_compile_unit, true, vec![]),
+            Die::new(0, 2, DW_TAG_variable, false, vec![
+                Attribute { at: DW_AT_name, data: AttributeData::String(b"v") },
+                Attribute { at: DW_AT_type, data: AttributeData::Ref(UnitOffset(16)) },
+            ]),
+            Die::new(0, 3, DW_TAG_base_type, false, vec![
+                Attribute { at: DW_AT_type, data: AttributeData::RefAddress(DebugInfoOffset(16)) },
+            ]),
+            Die::null(0),
+        ];
+        let mut unit = CompilationUnit::<LittleEndian>::default();
+        let mut data = Vec::new();
+        for entry in &entries {
+            entry.write(&mut data, &unit.common, &abbrev_hash).unwrap();
+        }
+        unit.common.data = &data;
+        let mut debug_info = Vec::new();
+        unit.write(&mut debug_info).unwrap();
+
+        let mut sections = Sections {
+            debug_abbrev: debug_abbrev,
+            debug_info: debug_info,
+            ..Sections::new(LittleEndian)
+        };
+
+        // The base type moves past the range of DW_FORM_ref1.
+        match rewrite_debug_info(&sections, |entry| {
+            if entry.tag == DW_TAG_variable {
+                entry.attributes[0].data = AttributeData::String(&NAME);
+            }
+        }) {
+            Err(WriteError::Invalid(_)) => {}
+            otherwise => panic!("{:?}", otherwise),
+        }
+
+        // A reference from .debug_types to .debug_info.
+        let mut data = Vec::new();
+        entries[2].write(&mut data, &unit.common, &abbrev_hash).unwrap();
+        let type_unit = TypeUnit {
+            common: UnitCommon {
+                endian: LittleEndian,
+                unit_type: DW_UT_type,
+                data: &data,
+                ..Default::default()
+            },
+            type_signature: 0x1234,
+            type_offset: 0x17,
+        };
+        type_unit.write(&mut sections.debug_types).unwrap();
+        match rewrite_debug_info(&sections, |_| {}) {
+            Err(WriteError::Unsupported(_)) => {}
+            otherwise => panic!("{:?}", otherwise),
+        }
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn line_offsets() {
+        let abbrev = AbbrevVec::new(vec![
+            Abbrev {
+                code: 1,
+                tag: DW_TAG_compile_unit,
+                children: false,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                    AbbrevAttribute::new(DW_AT_stmt_list, DW_FORM_sec_offset),
+                ],
+            },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev.write(&mut debug_abbrev).unwrap();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        let mut debug_info = Vec::new();
+        for &offset in &[0x10, 0x20] {
+            let entry = Die::new(0, 1, DW_TAG_compile_unit, false, vec![
+                Attribute { at: DW_AT_name, data: AttributeData::String(b"a.c") },
+                Attribute { at: DW_AT_stmt_list, data: AttributeData::SecOffset(offset) },
+            ]);
+            let mut unit = CompilationUnit::<LittleEndian>::default();
+            unit.common.offset = debug_info.len();
+            let mut data = Vec::new();
+            entry.write(&mut data, &unit.common, &abbrev_hash).unwrap();
+            unit.common.data = &data;
+            unit.write(&mut debug_info).unwrap();
+        }
+
+        let mut map = OffsetMap::new();
+        map.insert(0x10, 0x30);
+        let err = update_line_offsets(LittleEndian, &debug_abbrev, &mut debug_info, &map);
+        assert!(err.is_err());
+
+        map.insert(0x20, 0x1234);
+        let count = update_line_offsets(LittleEndian, &debug_abbrev, &mut debug_info, &map).unwrap();
+        assert_eq!(count, 2);
+
+        let mut offsets = Vec::new();
+        let mut units = CompilationUnitIterator::new(LittleEndian, &debug_info);
+        while let Some(unit) = units.next().unwrap() {
+            let mut entries = unit.entries(&abbrev_hash);
+            let entry = entries.next().unwrap().unwrap();
+            offsets.push(entry.attr(DW_AT_stmt_list).cloned());
+        }
+        assert_eq!(offsets, vec![
+            Some(AttributeData::SecOffset(0x30)),
+            Some(AttributeData::SecOffset(0x1234)),
+        ]);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn aranges_offsets() {
+        let set = |unit| vec![
+            // Length, version, debug_info offset, address size, segment size.
+            0x1c, 0, 0, 0, 2, 0, unit, 0, 0, 0, 4, 0,
+            // Padding.
+            0, 0, 0, 0,
+            0x00, 0x10, 0, 0, 0x10, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        let mut debug_aranges = set(0);
+        debug_aranges.extend(set(0x40));
+
+        let mut map = OffsetMap::new();
+        map.insert(0, 0);
+        let err = update_aranges_offsets(LittleEndian, &mut debug_aranges, &map);
+        assert!(err.is_err());
+
+        map.insert(0x40, 0x30);
+        let count = update_aranges_offsets(LittleEndian, &mut debug_aranges, &map).unwrap();
+        assert_eq!(count, 2);
+        let mut expect = set(0);
+        expect.extend(set(0x30));
+        assert_eq!(debug_aranges, expect);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn slide_aranges_ranges() {
+        let mut debug_aranges = vec![
+            // Length, version, debug_info offset, address size, segment size.
+            0x24, 0, 0, 0, 2, 0, 0, 0, 0, 0, 4, 0,
+            // Padding.
+            0, 0, 0, 0,
+            0x00, 0x10, 0, 0, 0x10, 0, 0, 0,
+            0x00, 0x20, 0, 0, 0x20, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        slide_aranges(LittleEndian, &mut debug_aranges, 0x100).unwrap();
+        assert_eq!(&debug_aranges[16..], &[
+            0x00, 0x11, 0, 0, 0x10, 0, 0, 0,
+            0x00, 0x21, 0, 0, 0x20, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+        ][..]);
+
+        let mut debug_ranges = vec![
+            0x10, 0, 0, 0, 0x20, 0, 0, 0,
+            0xff, 0xff, 0xff, 0xff, 0x00, 0x10, 0, 0,
+            0x10, 0, 0, 0, 0x20, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        slide_range_list(LittleEndian, &mut debug_ranges, 0, 4, 0x100).unwrap();
+        assert_eq!(debug_ranges, vec![
+            0x10, 0, 0, 0, 0x20, 0, 0, 0,
+            0xff, 0xff, 0xff, 0xff, 0x00, 0x11, 0, 0,
+            0x10, 0, 0, 0, 0x20, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+        ]);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn slide_start_scope() {
+        let abbrev = AbbrevVec::new(vec![
+            Abbrev {
+                code: 1,
+                tag: DW_TAG_compile_unit,
+                children: false,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_ranges, DW_FORM_data4),
+                    AbbrevAttribute::new(DW_AT_start_scope, DW_FORM_data4),
+                ],
+            },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev.write(&mut debug_abbrev).unwrap();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        // In version 3, a data4 DW_AT_start_scope is a constant, even if it
+        // happens to be the offset of a range list.
+        let entry = Die::new(0, 1, DW_TAG_compile_unit, false, vec![
+            Attribute { at: DW_AT_ranges, data: AttributeData::Data4(0) },
+            Attribute { at: DW_AT_start_scope, data: AttributeData::Data4(0x10) },
+        ]);
+        let mut unit = CompilationUnit::<LittleEndian>::default();
+        unit.common.version = 3;
+        unit.common.address_size = 4;
+        let mut data = Vec::new();
+        entry.write(&mut data, &unit.common, &abbrev_hash).unwrap();
+        unit.common.data = &data;
+        let mut debug_info = Vec::new();
+        unit.write(&mut debug_info).unwrap();
+
+        let debug_ranges = vec![
+            0xff, 0xff, 0xff, 0xff, 0x00, 0x10, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+            0xff, 0xff, 0xff, 0xff, 0x00, 0x20, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        let mut sections = Sections {
+            debug_abbrev: debug_abbrev,
+            debug_info: debug_info,
+            debug_ranges: debug_ranges,
+            ..Sections::new(LittleEndian)
+        };
+        slide_addresses(&mut sections, 0x100).unwrap();
+        assert_eq!(sections.debug_ranges, vec![
+            0xff, 0xff, 0xff, 0xff, 0x00, 0x11, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+            0xff, 0xff, 0xff, 0xff, 0x00, 0x20, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+        ]);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn share_abbrevs() {
+        // Two copies of one table, then a different table.
+        let table = [1, 17, 0, 0, 0, 0];
+        let other = [1, 52, 0, 0, 0, 0];
+        let mut debug_abbrev = Vec::new();
+        debug_abbrev.extend_from_slice(&table);
+        debug_abbrev.extend_from_slice(&table);
+        debug_abbrev.extend_from_slice(&other);
+
+        let data = [1];
+        let mut debug_info = Vec::new();
+        for &(version, abbrev_offset) in &[(4, 0), (5, 6), (4, 12)] {
+            let unit = CompilationUnit {
+                common: UnitCommon {
+                    offset: debug_info.len(),
+                    endian: LittleEndian,
+                    version: version,
+                    abbrev_offset: abbrev_offset,
+                    data: &data,
+                    ..Default::default()
+                },
+            };
+            unit.write(&mut debug_info).unwrap();
+        }
+        let mut sections = Sections {
+            debug_abbrev: debug_abbrev,
+            debug_info: debug_info,
+            ..Sections::new(LittleEndian)
+        };
+
+        let tables = abbrev_tables(&sections).unwrap();
+        assert_eq!(tables, vec![
+            AbbrevTable { offsets: vec![0, 6], units: vec![0, 12], type_units: vec![], len: 6 },
+            AbbrevTable { offsets: vec![12], units: vec![25], type_units: vec![], len: 6 },
+        ]);
+        assert_eq!(tables[0].duplicate_len(), 6);
+
+        assert_eq!(super::share_abbrevs(&mut sections).unwrap(), 6);
+        let mut abbrev_offsets = Vec::new();
+        let mut tags = Vec::new();
+        let mut units = sections.compilation_units();
+        while let Some(unit) = units.next().unwrap() {
+            abbrev_offsets.push(unit.common.abbrev_offset);
+            let abbrev = sections.abbrev(&unit.common).unwrap();
+            tags.push(unit.entries(&abbrev).next().unwrap().unwrap().tag);
+        }
+        assert_eq!(abbrev_offsets, vec![0, 0, 6]);
+        assert_eq!(tags, vec![DW_TAG_compile_unit, DW_TAG_compile_unit, DW_TAG_variable]);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn gc_strings() {
+        let abbrev = AbbrevVec::new(vec![
+            Abbrev {
+                code: 1,
+                tag: DW_TAG_compile_unit,
+                children: false,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_name, DW_FORM_strp),
+                    AbbrevAttribute::new(DW_AT_producer, DW_FORM_strx1),
+                    AbbrevAttribute::new(DW_AT_str_offsets_base, DW_FORM_sec_offset),
+                ],
+            },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev.write(&mut debug_abbrev).unwrap();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        let entry = Die::new(0, 1, DW_TAG_compile_unit, false, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::StringOffset(7) },
+            Attribute { at: DW_AT_producer, data: AttributeData::StringIndex(0) },
+            Attribute { at: DW_AT_str_offsets_base, data: AttributeData::SecOffset(8) },
+        ]);
+        let mut unit = CompilationUnit::<LittleEndian>::default();
+        unit.common.version = 5;
+        let mut data = Vec::new();
+        entry.write(&mut data, &unit.common, &abbrev_hash).unwrap();
+        unit.common.data = &data;
+        let mut debug_info = Vec::new();
+        unit.write(&mut debug_info).unwrap();
+
+        let mut sections = Sections {
+            debug_abbrev: debug_abbrev,
+            debug_info: debug_info,
+            debug_str: b"unused\0a.c\0gcc\0".to_vec(),
+            debug_str_offsets: vec![0x08, 0, 0, 0, 5, 0, 0, 0, 11, 0, 0, 0],
+            ..Sections::new(LittleEndian)
+        };
+        let map = super::gc_strings(&mut sections).unwrap();
+        assert_eq!(map.iter().map(|(&old, &new)| (old, new)).collect::<Vec<_>>(),
+                   vec![(7, 0), (11, 4)]);
+        assert_eq!(sections.debug_str, b"a.c\0gcc\0");
+        assert_eq!(sections.debug_str_offsets, vec![0x08, 0, 0, 0, 5, 0, 0, 0, 4, 0, 0, 0]);
+
+        let unit = sections.compilation_units().next().unwrap().unwrap();
+        let mut entries = unit.entries(&abbrev_hash);
+        let entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.attr(DW_AT_name), Some(&AttributeData::StringOffset(0)));
+        assert_eq!(sections.indexed_string(&unit.common, 8, 0).unwrap(), b"gcc");
+
+        // A GNU split DWARF unit, for which .debug_str_offsets has no header.
+        let abbrev = AbbrevVec::new(vec![
+            Abbrev {
+                code: 1,
+                tag: DW_TAG_compile_unit,
+                children: false,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_name, DW_FORM_GNU_str_index),
+                    AbbrevAttribute::new(DW_AT_GNU_dwo_id, DW_FORM_data8),
+                ],
+            },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev.write(&mut debug_abbrev).unwrap();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        let entry = Die::new(0, 1, DW_TAG_compile_unit, false, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::StringIndex(1) },
+            Attribute { at: DW_AT_GNU_dwo_id, data: AttributeData::Data8(0x1234) },
+        ]);
+        let mut unit = CompilationUnit::<LittleEndian>::default();
+        let mut data = Vec::new();
+        entry.write(&mut data, &unit.common, &abbrev_hash).unwrap();
+        unit.common.data = &data;
+        let mut debug_info = Vec::new();
+        unit.write(&mut debug_info).unwrap();
+
+        let mut sections = Sections {
+            debug_abbrev: debug_abbrev,
+            debug_info: debug_info,
+            debug_str: b"unused\0a.c\0gcc\0".to_vec(),
+            debug_str_offsets: vec![11, 0, 0, 0, 7, 0, 0, 0],
+            ..Sections::new(LittleEndian)
+        };
+        let map = super::gc_strings(&mut sections).unwrap();
+        assert_eq!(map.iter().map(|(&old, &new)| (old, new)).collect::<Vec<_>>(),
+                   vec![(7, 0), (11, 4)]);
+        assert_eq!(sections.debug_str, b"a.c\0gcc\0");
+        assert_eq!(sections.debug_str_offsets, vec![4, 0, 0, 0, 0, 0, 0, 0]);
+
+        let unit = sections.compilation_units().next().unwrap().unwrap();
+        assert_eq!(sections.indexed_string(&unit.common, 0, 1).unwrap(), b"a.c");
+    }
+}
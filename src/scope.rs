@@ -0,0 +1,295 @@
+use std::ops::Deref;
+
+use abbrev::AbbrevHash;
+use constant;
+use die::{AttributeData, Die};
+use endian::Endian;
+use ranges::Range;
+use read::*;
+use unit::{CompilationUnit, UnitBases};
+use Sections;
+
+// An entry that may contain variables, such as a subprogram or lexical
+// block.
+#[derive(Debug, Clone, Copy)]
+struct Scope {
+    // Whether the scope contains the address.
+    contains: bool,
+    // The lowest address of the scope. DW_AT_start_scope constants are
+    // relative to this.
+    low_pc: u64,
+}
+
+// Return the section offsets of the variables and formal parameters of a
+// unit that are in scope at `address`.
+//
+// An entry is in scope if `address` is within every enclosing subprogram,
+// lexical block and inlined subroutine, using their DW_AT_low_pc and
+// DW_AT_high_pc or DW_AT_ranges, and isn't before the DW_AT_start_scope of
+// the entry. Subprograms and inlined subroutines without code contain no
+// addresses, but lexical blocks without code use the scope that encloses
+// them. Declarations are ignored.
+//
+// This doesn't check that the location of the variable is known at
+// `address`, only that the variable is valid there.
+pub fn variables_at<'a, E, S>(
+    sections: &'a Sections<E, S>,
+    unit: &CompilationUnit<'a, E>,
+    abbrev: &AbbrevHash,
+    address: u64
+) -> Result<Vec<usize>, ReadError>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    let mut variables = Vec::new();
+    let mut scopes: Vec<Scope> = Vec::new();
+    let mut bases = UnitBases::default();
+    let mut entries = unit.entries(abbrev);
+    while let Some(entry) = try!(entries.next()) {
+        if entry.is_null() {
+            scopes.pop();
+            continue;
+        }
+        let parent = scopes.last().cloned();
+        if parent.is_none() {
+            bases = UnitBases::from_root(&unit.common, entry);
+        }
+
+        if let Some(parent) = parent {
+            if parent.contains && is_variable(entry) &&
+               try!(after_start_scope(sections, unit, &bases, entry, parent, address)) {
+                variables.push(entry.offset);
+            }
+        }
+
+        if entry.children {
+            let scope = match parent {
+                Some(parent) if !parent.contains => parent,
+                _ => try!(entry_scope(sections, unit, &bases, entry, parent, address)),
+            };
+            scopes.push(scope);
+        }
+    }
+    Ok(variables)
+}
+
+// Return true if the entry at `offset` is a variable or formal parameter
+// that is in scope at `address`. See `variables_at`.
+pub fn in_scope<'a, E, S>(
+    sections: &'a Sections<E, S>,
+    unit: &CompilationUnit<'a, E>,
+    abbrev: &AbbrevHash,
+    offset: usize,
+    address: u64
+) -> Result<bool, ReadError>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    let variables = try!(variables_at(sections, unit, abbrev, address));
+    Ok(variables.contains(&offset))
+}
+
+fn is_variable(entry: &Die) -> bool {
+    match entry.tag {
+        constant::DW_TAG_variable | constant::DW_TAG_formal_parameter => {}
+        _ => return false,
+    }
+    !matches!(entry.attr(constant::DW_AT_declaration), Some(&AttributeData::Flag(true)))
+}
+
+fn entry_scope<'a, E, S>(
+    sections: &'a Sections<E, S>,
+    unit: &CompilationUnit<'a, E>,
+    bases: &UnitBases,
+    entry: &Die,
+    parent: Option<Scope>,
+    address: u64
+) -> Result<Scope, ReadError>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    let inherit = parent.unwrap_or(Scope {
+        contains: true,
+        low_pc: bases.base_address,
+    });
+    let empty = Scope {
+        contains: false,
+        low_pc: 0,
+    };
+    match entry.tag {
+        constant::DW_TAG_compile_unit |
+        constant::DW_TAG_subprogram |
+        constant::DW_TAG_lexical_block |
+        constant::DW_TAG_inlined_subroutine => {}
+        _ => return Ok(inherit),
+    }
+    let ranges = try!(sections.entry_ranges(&unit.common, bases, entry));
+    if ranges.is_empty() {
+        return Ok(match entry.tag {
+            constant::DW_TAG_subprogram |
+            constant::DW_TAG_inlined_subroutine => empty,
+            _ => inherit,
+        });
+    }
+    Ok(Scope {
+        contains: contains(&ranges, address),
+        low_pc: ranges.iter().map(|range| range.begin).min().unwrap_or(0),
+    })
+}
+
+fn after_start_scope<'a, E, S>(
+    sections: &'a Sections<E, S>,
+    unit: &CompilationUnit<'a, E>,
+    bases: &UnitBases,
+    entry: &Die,
+    scope: Scope,
+    address: u64
+) -> Result<bool, ReadError>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    let data = match entry.attr(constant::DW_AT_start_scope) {
+        Some(data) => data,
+        None => return Ok(true),
+    };
+    match *data {
+        // A range list, for scopes that aren't contiguous.
+        AttributeData::SecOffset(_) |
+        AttributeData::RangeListIndex(_) => {
+            let ranges = try!(sections.ranges(&unit.common, bases, data));
+            Ok(contains(&ranges, address))
+        }
+        _ => {
+            match data.as_udata().and_then(|offset| scope.low_pc.checked_add(offset)) {
+                Some(begin) => Ok(address >= begin),
+                None => Ok(true),
+            }
+        }
+    }
+}
+
+fn contains(ranges: &[Range], address: u64) -> bool {
+    ranges.iter().any(|range| address >= range.begin && address < range.end)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use abbrev::*;
+    use constant::*;
+    use die::*;
+    use endian::LittleEndian;
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn variables_at() {
+        let abbrev = AbbrevVec::new(vec![
+            Abbrev { code: 1, tag: DW_TAG_compile_unit, children: true, attributes: vec![
+                AbbrevAttribute::new(DW_AT_low_pc, DW_FORM_addr),
+            ] },
+            Abbrev { code: 2, tag: DW_TAG_subprogram, children: true, attributes: vec![
+                AbbrevAttribute::new(DW_AT_low_pc, DW_FORM_addr),
+                AbbrevAttribute::new(DW_AT_high_pc, DW_FORM_data4),
+            ] },
+            Abbrev { code: 3, tag: DW_TAG_lexical_block, children: true, attributes: vec![
+                AbbrevAttribute::new(DW_AT_ranges, DW_FORM_sec_offset),
+            ] },
+            Abbrev { code: 4, tag: DW_TAG_variable, children: false, attributes: vec![] },
+            Abbrev { code: 5, tag: DW_TAG_variable, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_start_scope, DW_FORM_data1),
+            ] },
+            Abbrev { code: 6, tag: DW_TAG_variable, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_start_scope, DW_FORM_sec_offset),
+            ] },
+            Abbrev { code: 7, tag: DW_TAG_subprogram, children: true, attributes: vec![] },
+            Abbrev { code: 8, tag: DW_TAG_formal_parameter, children: false, attributes: vec![] },
+            Abbrev { code: 9, tag: DW_TAG_subprogram, children: true, attributes: vec![
+                AbbrevAttribute::new(DW_AT_ranges, DW_FORM_sec_offset),
+            ] },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev.write(&mut debug_abbrev).unwrap();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        let entries = [
+            Die::new(0, 1, DW_TAG_compile_unit, true, vec![
+                Attribute { at: DW_AT_low_pc, data: AttributeData::Address(0x1000) },
+            ]),
+            // A global variable.
+            Die::new(0, 4, DW_TAG_variable, false, vec![]),
+            Die::new(0, 2, DW_TAG_subprogram, true, vec![
+                Attribute { at: DW_AT_low_pc, data: AttributeData::Address(0x1000) },
+                Attribute { at: DW_AT_high_pc, data: AttributeData::Data4(0x100) },
+            ]),
+            Die::new(0, 4, DW_TAG_variable, false, vec![]),
+            Die::new(0, 5, DW_TAG_variable, false, vec![
+                Attribute { at: DW_AT_start_scope, data: AttributeData::Data1(0x10) },
+            ]),
+            // Ranges 0x1020..0x1030 and 0x1040..0x1050.
+            Die::new(0, 3, DW_TAG_lexical_block, true, vec![
+                Attribute { at: DW_AT_ranges, data: AttributeData::SecOffset(0) },
+            ]),
+            Die::new(0, 4, DW_TAG_variable, false, vec![]),
+            // Range 0x1048..0x1050.
+            Die::new(0, 6, DW_TAG_variable, false, vec![
+                Attribute { at: DW_AT_start_scope, data: AttributeData::SecOffset(0x30) },
+            ]),
+            Die::null(0),
+            Die::null(0),
+            // A subprogram without code.
+            Die::new(0, 7, DW_TAG_subprogram, true, vec![]),
+            Die::new(0, 8, DW_TAG_formal_parameter, false, vec![]),
+            Die::null(0),
+            // Range 0x2000..0x2010, using a base address selection entry.
+            Die::new(0, 9, DW_TAG_subprogram, true, vec![
+                Attribute { at: DW_AT_ranges, data: AttributeData::SecOffset(0x18) },
+            ]),
+            Die::new(0, 4, DW_TAG_variable, false, vec![]),
+            Die::null(0),
+            Die::null(0),
+        ];
+        let mut unit = CompilationUnit::<LittleEndian>::default();
+        let mut data = Vec::new();
+        let mut offsets = Vec::new();
+        for entry in &entries {
+            offsets.push(unit.common.header_len() + data.len());
+            entry.write(&mut data, &unit.common, &abbrev_hash).unwrap();
+        }
+        unit.common.data = &data;
+        let mut debug_info = Vec::new();
+        unit.write(&mut debug_info).unwrap();
+
+        let sections = Sections {
+            debug_abbrev: debug_abbrev,
+            debug_info: debug_info,
+            debug_ranges: vec![
+                0x20, 0x00, 0x00, 0x00, 0x30, 0x00, 0x00, 0x00,
+                0x40, 0x00, 0x00, 0x00, 0x50, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                // A base address selection entry.
+                0xff, 0xff, 0xff, 0xff, 0x00, 0x20, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x48, 0x00, 0x00, 0x00, 0x50, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ],
+            ..Sections::new(LittleEndian)
+        };
+        let unit = sections.compilation_units().next().unwrap().unwrap();
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let at = |address| super::variables_at(&sections, &unit, &abbrev, address).unwrap();
+        assert_eq!(at(0x900), vec![offsets[1]]);
+        assert_eq!(at(0x1000), vec![offsets[1], offsets[3]]);
+        assert_eq!(at(0x1010), vec![offsets[1], offsets[3], offsets[4]]);
+        assert_eq!(at(0x1020), vec![offsets[1], offsets[3], offsets[4], offsets[6]]);
+        assert_eq!(at(0x1038), vec![offsets[1], offsets[3], offsets[4]]);
+        assert_eq!(at(0x1048), vec![offsets[1], offsets[3], offsets[4], offsets[6], offsets[7]]);
+        assert_eq!(at(0x1100), vec![offsets[1]]);
+        assert_eq!(at(0x2000), vec![offsets[1], offsets[14]]);
+        assert_eq!(at(0x2010), vec![offsets[1]]);
+
+        assert!(in_scope(&sections, &unit, &abbrev, offsets[7], 0x104f).unwrap());
+        assert!(!in_scope(&sections, &unit, &abbrev, offsets[7], 0x1040).unwrap());
+        assert!(!in_scope(&sections, &unit, &abbrev, offsets[11], 0x1000).unwrap());
+    }
+}
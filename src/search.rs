@@ -0,0 +1,284 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Deref;
+
+use abbrev::AbbrevHash;
+use constant;
+use die::{AttributeContext, Die, DieRef};
+use endian::Endian;
+use read::ReadError;
+use unit::CompilationUnit;
+use Sections;
+
+// How to compare names when searching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameMatch<'a> {
+    // Match DW_AT_name or the linkage name exactly.
+    //
+    // Units with a DW_AT_identifier_case other than DW_ID_case_sensitive
+    // are compared case-insensitively.
+    Exact(&'a [u8]),
+    // Match DW_AT_name or the linkage name, ignoring ASCII case.
+    CaseInsensitive(&'a [u8]),
+    // Match entries whose DW_AT_name contains the string.
+    //
+    // DW_AT_name is the unqualified source name, so this matches demangled
+    // names rather than linkage names.
+    Substring(&'a [u8]),
+}
+
+impl<'a> NameMatch<'a> {
+    fn matches(&self, die: &Die, context: &AttributeContext, case_sensitive: bool) -> bool {
+        let name = die.attr(constant::DW_AT_name).and_then(|name| name.as_string_in(context));
+        let linkage_name = die.attr(constant::DW_AT_linkage_name)
+            .or_else(|| die.attr(constant::DW_AT_MIPS_linkage_name))
+            .and_then(|name| name.as_string_in(context));
+        let either = |f: &Fn(&[u8]) -> bool| name.is_some_and(f) || linkage_name.is_some_and(f);
+        match *self {
+            NameMatch::Exact(needle) if case_sensitive => either(&|name| name == needle),
+            NameMatch::Exact(needle) |
+            NameMatch::CaseInsensitive(needle) => either(&|name| name.eq_ignore_ascii_case(needle)),
+            NameMatch::Substring(needle) => name.is_some_and(|name| contains(name, needle)),
+        }
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    needle.is_empty() || haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+// An entry found by a name search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NameEntry {
+    // The offset of the unit header in .debug_info.
+    pub unit_offset: usize,
+    // The offset of the entry in .debug_info.
+    pub offset: usize,
+    pub tag: constant::DwTag,
+}
+
+impl NameEntry {
+    // Return a handle that can be used to read the entry.
+    pub fn die_ref<'a, E, S>(&self, sections: &'a Sections<E, S>) -> DieRef<'a, E, S>
+        where E: Endian,
+              S: Deref<Target = [u8]>
+    {
+        DieRef::new(sections, self.unit_offset, self.offset)
+    }
+}
+
+// Search for entries by name.
+//
+// Accelerator tables aren't supported yet, so this always scans the
+// entries of every compilation unit.
+#[derive(Debug)]
+pub struct NameSearch<'a, E: Endian + 'a, S: 'a = Vec<u8>> {
+    sections: &'a Sections<E, S>,
+}
+
+impl<'a, E, S> NameSearch<'a, E, S>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    pub fn new(sections: &'a Sections<E, S>) -> Self {
+        NameSearch { sections: sections }
+    }
+
+    // Return the entries that match, in section order.
+    pub fn find(&self, name: NameMatch) -> Result<Vec<NameEntry>, ReadError> {
+        let mut found = Vec::new();
+        let mut units = self.sections.compilation_units();
+        while let Some(unit) = try!(units.next()) {
+            let abbrev = try!(self.sections.abbrev(&unit.common));
+            let context = self.sections.attribute_context(&unit.common);
+            let mut entries = unit.entries(&abbrev);
+            let mut case_sensitive = true;
+            let mut first = true;
+            while let Some(entry) = try!(entries.next()) {
+                if first {
+                    first = false;
+                    if let Some(case) = entry.attr(constant::DW_AT_identifier_case)
+                        .and_then(|case| case.as_udata()) {
+                        case_sensitive = case == constant::DW_ID_case_sensitive.0 as u64;
+                    }
+                }
+                if !entry.is_null() && name.matches(entry, &context, case_sensitive) {
+                    found.push(NameEntry {
+                        unit_offset: unit.common.offset,
+                        offset: entry.offset,
+                        tag: entry.tag,
+                    });
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    // Return handles for the entries that match, in section order.
+    pub fn find_refs(&self, name: NameMatch) -> Result<Vec<DieRef<'a, E, S>>, ReadError> {
+        let found = try!(self.find(name));
+        Ok(found.iter().map(|entry| entry.die_ref(self.sections)).collect())
+    }
+}
+
+// An index from source file paths to the compilation units that use them.
+//
+// This finds the units to search for queries such as setting a breakpoint
+// on a line of "foo.c". The paths are the DW_AT_name of each unit and
+// the file entries of its line program, prefixed by their directories.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FileIndex {
+    // The offsets of the units for each path, in section order.
+    pub paths: BTreeMap<Vec<u8>, Vec<usize>>,
+    // The paths for each file name, which is the last path component.
+    names: BTreeMap<Vec<u8>, BTreeSet<Vec<u8>>>,
+}
+
+impl FileIndex {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // Build an index of all compilation units.
+    pub fn from_sections<E, S>(sections: &Sections<E, S>) -> Result<FileIndex, ReadError>
+        where E: Endian,
+              S: Deref<Target = [u8]>
+    {
+        let mut index = FileIndex::new();
+        let mut units = sections.compilation_units();
+        while let Some(unit) = try!(units.next()) {
+            let abbrev = try!(sections.abbrev(&unit.common));
+            try!(index.add_unit(sections, &unit, &abbrev));
+        }
+        Ok(index)
+    }
+
+    // Add the paths of a compilation unit.
+    pub fn add_unit<'a, E, S>(
+        &mut self,
+        sections: &'a Sections<E, S>,
+        unit: &CompilationUnit<'a, E>,
+        abbrev: &AbbrevHash
+    ) -> Result<(), ReadError>
+        where E: Endian,
+              S: Deref<Target = [u8]>
+    {
+        let offset = unit.common.offset;
+        {
+            let mut entries = unit.entries(abbrev);
+            if let Some(entry) = try!(entries.next()) {
+                let context = sections.attribute_context(&unit.common);
+                let string = |at| entry.attr(at).and_then(|data| data.as_string_in(&context));
+                if let Some(name) = string(constant::DW_AT_name) {
+                    let mut path = Vec::new();
+                    if name.first() != Some(&b'/') {
+                        if let Some(dir) = string(constant::DW_AT_comp_dir) {
+                            if !dir.is_empty() {
+                                path.extend_from_slice(dir);
+                                path.push(b'/');
+                            }
+                        }
+                    }
+                    path.extend_from_slice(name);
+                    self.add_path(path, offset);
+                }
+            }
+        }
+        if let Some(program) = try!(sections.line_program(unit, abbrev)) {
+            for file in &program.files {
+                if !file.path.is_empty() {
+                    self.add_path(file.full_path(&program.include_directories), offset);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Record that the unit at `unit_offset` uses a path.
+    pub fn add_path(&mut self, path: Vec<u8>, unit_offset: usize) {
+        let name = file_name(&path).to_vec();
+        let units = self.paths.entry(path.clone()).or_default();
+        if let Err(i) = units.binary_search(&unit_offset) {
+            units.insert(i, unit_offset);
+        }
+        self.names.entry(name).or_default().insert(path);
+    }
+
+    // Return the offsets of the units that use a path, in section order.
+    //
+    // A path matches if it is equal to `path`, or if `path` is relative
+    // and is a suffix of it that starts at a path component. For example,
+    // "foo.c" and "src/foo.c" both match "/home/src/foo.c", but "oo.c"
+    // doesn't.
+    pub fn find(&self, path: &[u8]) -> Vec<usize> {
+        let mut found = Vec::new();
+        let candidates = match self.names.get(file_name(path)) {
+            Some(candidates) => candidates,
+            None => return found,
+        };
+        for candidate in candidates {
+            let matches = if candidate.len() == path.len() || path.first() == Some(&b'/') {
+                &candidate[..] == path
+            } else {
+                candidate.ends_with(path) && candidate[candidate.len() - path.len() - 1] == b'/'
+            };
+            if matches {
+                found.extend_from_slice(&self.paths[candidate]);
+            }
+        }
+        found.sort();
+        found.dedup();
+        found
+    }
+}
+
+// The last component of a path.
+fn file_name(path: &[u8]) -> &[u8] {
+    match path.iter().rposition(|&c| c == b'/') {
+        Some(i) => &path[i + 1..],
+        None => path,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use constant::*;
+    use die::*;
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn name_match() {
+        let die = Die::new(0, 1, DW_TAG_subprogram, false, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(b"Foo") },
+            Attribute { at: DW_AT_linkage_name, data: AttributeData::String(b"_Z3Foov") },
+        ]);
+        let context = AttributeContext {
+            offset_size: 4,
+            debug_str: &[],
+            debug_line_str: &[],
+        };
+        assert!(NameMatch::Exact(b"Foo").matches(&die, &context, true));
+        assert!(NameMatch::Exact(b"_Z3Foov").matches(&die, &context, true));
+        assert!(!NameMatch::Exact(b"foo").matches(&die, &context, true));
+        assert!(NameMatch::Exact(b"foo").matches(&die, &context, false));
+        assert!(NameMatch::CaseInsensitive(b"FOO").matches(&die, &context, true));
+        assert!(NameMatch::Substring(b"oo").matches(&die, &context, true));
+        assert!(!NameMatch::Substring(b"Z3").matches(&die, &context, true));
+    }
+
+    #[test]
+    fn file_index() {
+        let mut index = FileIndex::new();
+        index.add_path(b"/home/src/foo.c".to_vec(), 0x40);
+        index.add_path(b"/home/src/foo.c".to_vec(), 0x10);
+        index.add_path(b"/home/lib/foo.c".to_vec(), 0x20);
+        index.add_path(b"/home/src/foo.h".to_vec(), 0x10);
+        index.add_path(b"foo.c".to_vec(), 0x30);
+        assert_eq!(index.find(b"foo.c"), vec![0x10, 0x20, 0x30, 0x40]);
+        assert_eq!(index.find(b"src/foo.c"), vec![0x10, 0x40]);
+        assert_eq!(index.find(b"/home/lib/foo.c"), vec![0x20]);
+        assert_eq!(index.find(b"/lib/foo.c"), vec![]);
+        assert_eq!(index.find(b"oo.c"), vec![]);
+        assert_eq!(index.find(b"bar.c"), vec![]);
+    }
+}
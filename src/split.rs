@@ -0,0 +1,490 @@
+#[cfg(feature = "elf")]
+use std;
+use std::collections::HashMap;
+use std::ops::Deref;
+#[cfg(feature = "elf")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "elf")]
+use std::sync::Arc;
+
+use abbrev::AbbrevHash;
+use constant;
+use die::{AttributeContext, AttributeData, DieIterator};
+#[cfg(feature = "elf")]
+use elf;
+#[cfg(feature = "elf")]
+use endian::AnyEndian;
+use endian::Endian;
+use line::{LineIterator, LineProgram};
+use read::ReadError;
+use unit::CompilationUnit;
+use Sections;
+
+// A skeleton unit and the split unit that it refers to.
+//
+// The skeleton unit is in the main file, and contains the attributes that
+// describe the code, such as DW_AT_low_pc and DW_AT_stmt_list. The split
+// unit is in the .dwo file, and contains the entries for the source.
+#[derive(Debug)]
+pub struct SplitUnit<'a, E: Endian + 'a, S: 'a = Vec<u8>> {
+    pub dwo_id: u64,
+    pub skeleton_sections: &'a Sections<E, S>,
+    pub skeleton: CompilationUnit<'a, E>,
+    pub skeleton_abbrev: AbbrevHash,
+    pub dwo_sections: &'a Sections<E, S>,
+    pub dwo: CompilationUnit<'a, E>,
+    pub dwo_abbrev: AbbrevHash,
+}
+
+impl<'a, E, S> SplitUnit<'a, E, S>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    // The entries of the split unit.
+    pub fn entries<'b>(&'b self) -> DieIterator<'b, 'a, E> {
+        self.dwo.entries(&self.dwo_abbrev)
+    }
+
+    // The entries of the skeleton unit.
+    pub fn skeleton_entries<'b>(&'b self) -> DieIterator<'b, 'a, E> {
+        self.skeleton.entries(&self.skeleton_abbrev)
+    }
+
+    // Return an attribute of the unit entry.
+    //
+    // The split unit entry is searched first, followed by the skeleton unit
+    // entry. The second value is true if the attribute is from the split
+    // unit, in which case it must be resolved using `dwo_sections`.
+    pub fn attr(&self, at: constant::DwAt) -> Result<Option<(AttributeData<'a>, bool)>, ReadError> {
+        if let Some(data) = try!(root_attr(self.entries(), at)) {
+            return Ok(Some((data, true)));
+        }
+        if let Some(data) = try!(root_attr(self.skeleton_entries(), at)) {
+            return Ok(Some((data, false)));
+        }
+        Ok(None)
+    }
+
+    // Return a string attribute of the unit entry, such as DW_AT_name or
+    // DW_AT_comp_dir, using the string section of the unit it is from.
+    //
+    // DW_FORM_GNU_str_index values in the split unit are resolved using
+    // the .debug_str_offsets section of `dwo_sections`.
+    pub fn attr_string(&self, at: constant::DwAt) -> Result<Option<&'a [u8]>, ReadError> {
+        match try!(self.attr(at)) {
+            Some((AttributeData::StringIndex(index), true)) => {
+                self.dwo_sections.indexed_string(&self.dwo.common, 0, index).map(Some)
+            }
+            Some((data, true)) => {
+                Ok(data.as_string_in(&self.dwo_sections.attribute_context(&self.dwo.common)))
+            }
+            Some((data, false)) => Ok(data.as_string_in(&self.skeleton_context())),
+            None => Ok(None),
+        }
+    }
+
+    // Return an address attribute of the unit entry, such as DW_AT_low_pc.
+    //
+    // DW_FORM_GNU_addr_index values in the split unit are resolved using
+    // `address`.
+    pub fn attr_address(&self, at: constant::DwAt) -> Result<Option<u64>, ReadError> {
+        match try!(self.attr(at)) {
+            Some((AttributeData::Address(val), _)) => Ok(Some(val)),
+            Some((AttributeData::AddressIndex(index), true)) => self.address(index).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    // The name of the .dwo file, from the DW_AT_GNU_dwo_name of the skeleton
+    // unit, or DW_AT_dwo_name for DWARF 5 producers.
+    pub fn dwo_name(&self) -> Result<Option<&'a [u8]>, ReadError> {
+        let entries = self.skeleton_entries();
+        let data = match try!(root_attr(entries, constant::DW_AT_GNU_dwo_name)) {
+            Some(data) => Some(data),
+            None => try!(root_attr(self.skeleton_entries(), constant::DW_AT_dwo_name)),
+        };
+        Ok(data.and_then(|data| data.as_string_in(&self.skeleton_context())))
+    }
+
+    fn skeleton_context(&self) -> AttributeContext<'a> {
+        self.skeleton_sections.attribute_context(&self.skeleton.common)
+    }
+
+    // The offset of the split unit's entries in the .debug_addr section of
+    // `skeleton_sections`, from DW_AT_GNU_addr_base. This is 0 if the
+    // skeleton unit doesn't have the attribute.
+    pub fn addr_base(&self) -> Result<u64, ReadError> {
+        self.skeleton_offset(constant::DW_AT_GNU_addr_base)
+    }
+
+    // The value that is added to DW_AT_ranges of the split unit's entries
+    // to give an offset in the .debug_ranges section of `skeleton_sections`,
+    // from DW_AT_GNU_ranges_base. This is 0 if the skeleton unit doesn't
+    // have the attribute.
+    pub fn ranges_base(&self) -> Result<u64, ReadError> {
+        self.skeleton_offset(constant::DW_AT_GNU_ranges_base)
+    }
+
+    // Return the address at `index` in the .debug_addr section of
+    // `skeleton_sections`, for a DW_FORM_GNU_addr_index value in the split
+    // unit.
+    pub fn address(&self, index: u64) -> Result<u64, ReadError> {
+        let base = try!(self.addr_base());
+        self.skeleton_sections.indexed_address(&self.skeleton.common, base, index)
+    }
+
+    // The offset in the .debug_ranges section of `skeleton_sections` for a
+    // DW_AT_ranges value of an entry in the split unit.
+    pub fn ranges_offset(&self, data: &AttributeData) -> Result<Option<u64>, ReadError> {
+        let context = self.dwo_sections.attribute_context(&self.dwo.common);
+        match data.as_offset_in(&context) {
+            Some(offset) => {
+                let base = try!(self.ranges_base());
+                Ok(Some(try!(base.checked_add(offset as u64).ok_or(ReadError::Overflow))))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn skeleton_offset(&self, at: constant::DwAt) -> Result<u64, ReadError> {
+        let context = self.skeleton_context();
+        match try!(root_attr(self.skeleton_entries(), at)) {
+            Some(data) => {
+                data.as_offset_in(&context).map(|offset| offset as u64).ok_or(ReadError::Invalid)
+            }
+            None => Ok(0),
+        }
+    }
+
+    // The line number information, which is referenced by the skeleton unit.
+    pub fn lines(&self) -> Result<Option<LineIterator<'a, E>>, ReadError> {
+        let program = try!(self.skeleton.line_program_in(&*self.skeleton_sections.debug_line,
+                                                         &self.skeleton_context(),
+                                                         &self.skeleton_abbrev));
+        Ok(program.map(LineProgram::into_lines))
+    }
+}
+
+fn root_attr<'a, 'data, E: Endian>(
+    mut entries: DieIterator<'a, 'data, E>,
+    at: constant::DwAt
+) -> Result<Option<AttributeData<'data>>, ReadError> {
+    match try!(entries.next()) {
+        Some(entry) => Ok(entry.attr(at).cloned()),
+        None => Err(ReadError::Invalid),
+    }
+}
+
+// Pair the skeleton units in `skeleton_sections` with the split units in
+// `dwo_sections` that have the same dwo_id.
+//
+// The pairs are returned in the order of the skeleton units. Units without
+// a dwo_id, or without a matching unit, are ignored.
+pub fn match_units<'a, E, S>(
+    skeleton_sections: &'a Sections<E, S>,
+    dwo_sections: &'a Sections<E, S>
+) -> Result<Vec<SplitUnit<'a, E, S>>, ReadError>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    let mut dwo_units = HashMap::new();
+    let mut units = dwo_sections.compilation_units();
+    while let Some(unit) = try!(units.next()) {
+        let abbrev = try!(dwo_sections.abbrev(&unit.common));
+        if let Some(dwo_id) = try!(unit.dwo_id(&abbrev)) {
+            dwo_units.insert(dwo_id, (unit, abbrev));
+        }
+    }
+
+    let mut split_units = Vec::new();
+    let mut units = skeleton_sections.compilation_units();
+    while let Some(unit) = try!(units.next()) {
+        let abbrev = try!(skeleton_sections.abbrev(&unit.common));
+        let dwo_id = match try!(unit.dwo_id(&abbrev)) {
+            Some(dwo_id) => dwo_id,
+            None => continue,
+        };
+        if let Some((dwo, dwo_abbrev)) = dwo_units.remove(&dwo_id) {
+            split_units.push(SplitUnit {
+                dwo_id: dwo_id,
+                skeleton_sections: skeleton_sections,
+                skeleton: unit,
+                skeleton_abbrev: abbrev,
+                dwo_sections: dwo_sections,
+                dwo: dwo,
+                dwo_abbrev: dwo_abbrev,
+            });
+        }
+    }
+    Ok(split_units)
+}
+
+// The .dwo files in a directory tree, indexed by dwo_id.
+//
+// The files are opened when the directory is scanned to read their ids,
+// and then opened again on demand. A limited number of the most recently
+// used files are kept in memory.
+#[cfg(feature = "elf")]
+#[derive(Debug)]
+pub struct DwoDirectory {
+    paths: HashMap<u64, PathBuf>,
+    errors: Vec<(PathBuf, ReadError)>,
+    cache: Lru<PathBuf, Arc<Sections<AnyEndian>>>,
+}
+
+#[cfg(feature = "elf")]
+impl DwoDirectory {
+    // Scan `dir` and its subdirectories for files with a `.dwo` extension.
+    //
+    // Files that can't be read are skipped, and their errors are returned
+    // by `errors`. Errors reading the directories are returned. At most
+    // `capacity` files are kept in memory, and at least one is always kept.
+    pub fn scan<P: AsRef<Path>>(dir: P, capacity: usize) -> Result<DwoDirectory, ReadError> {
+        let mut paths = HashMap::new();
+        let mut errors = Vec::new();
+        let mut dirs = vec![dir.as_ref().to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            for entry in try!(std::fs::read_dir(&dir)) {
+                let path = try!(entry).path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().is_some_and(|ext| ext == "dwo") {
+                    match elf::load(&path).and_then(|sections| dwo_ids(&sections)) {
+                        Ok(dwo_ids) => {
+                            for dwo_id in dwo_ids {
+                                paths.insert(dwo_id, path.clone());
+                            }
+                        }
+                        Err(e) => errors.push((path, e)),
+                    }
+                }
+            }
+        }
+        Ok(DwoDirectory {
+            paths: paths,
+            errors: errors,
+            cache: Lru::new(capacity),
+        })
+    }
+
+    // The paths of the files, indexed by dwo_id.
+    pub fn paths(&self) -> &HashMap<u64, PathBuf> {
+        &self.paths
+    }
+
+    // The files that were skipped by `scan`, and the errors reading them.
+    pub fn errors(&self) -> &[(PathBuf, ReadError)] {
+        &self.errors
+    }
+
+    // Return the sections of the file containing the split unit with the
+    // given dwo_id, loading the file if needed.
+    //
+    // Use `match_units` to pair the units in the file with skeleton units.
+    pub fn get(&mut self, dwo_id: u64) -> Result<Option<Arc<Sections<AnyEndian>>>, ReadError> {
+        let path = match self.paths.get(&dwo_id) {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        if let Some(sections) = self.cache.get(path) {
+            return Ok(Some(sections.clone()));
+        }
+        let sections = Arc::new(try!(elf::load(path)));
+        self.cache.insert(path.clone(), sections.clone());
+        Ok(Some(sections))
+    }
+}
+
+#[cfg(feature = "elf")]
+fn dwo_ids<E, S>(sections: &Sections<E, S>) -> Result<Vec<u64>, ReadError>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    let mut ids = Vec::new();
+    let mut units = sections.compilation_units();
+    while let Some(unit) = try!(units.next()) {
+        let abbrev = try!(sections.abbrev(&unit.common));
+        if let Some(dwo_id) = try!(unit.dwo_id(&abbrev)) {
+            ids.push(dwo_id);
+        }
+    }
+    Ok(ids)
+}
+
+// A least recently used cache.
+//
+// The number of entries is expected to be small, so this is a list in
+// order of use, with the most recently used last.
+#[cfg(feature = "elf")]
+#[derive(Debug)]
+struct Lru<K, V> {
+    capacity: usize,
+    entries: Vec<(K, V)>,
+}
+
+#[cfg(feature = "elf")]
+impl<K: PartialEq, V> Lru<K, V> {
+    fn new(capacity: usize) -> Self {
+        Lru {
+            capacity: std::cmp::max(capacity, 1),
+            entries: Vec::new(),
+        }
+    }
+
+    fn get(&mut self, key: &K) -> Option<&V> {
+        match self.entries.iter().position(|entry| entry.0 == *key) {
+            Some(index) => {
+                let entry = self.entries.remove(index);
+                self.entries.push(entry);
+                self.entries.last().map(|entry| &entry.1)
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        if let Some(index) = self.entries.iter().position(|entry| entry.0 == key) {
+            self.entries.remove(index);
+        }
+        if self.entries.len() >= self.capacity {
+            self.entries.remove(0);
+        }
+        self.entries.push((key, value));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use abbrev::*;
+    use constant::*;
+    use die::*;
+    use endian::*;
+    use unit::*;
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn sections(dwo_id: u64, attributes: Vec<Attribute>) -> Sections<LittleEndian> {
+        let mut abbrev_attributes = vec![
+            AbbrevAttribute::new(DW_AT_GNU_dwo_id, DW_FORM_data8),
+        ];
+        for attribute in &attributes {
+            let form = match attribute.data {
+                AttributeData::Address(_) => DW_FORM_addr,
+                AttributeData::SecOffset(_) => DW_FORM_sec_offset,
+                AttributeData::AddressIndex(_) => DW_FORM_GNU_addr_index,
+                AttributeData::StringIndex(_) => DW_FORM_GNU_str_index,
+                AttributeData::RangeListIndex(_) => DW_FORM_rnglistx,
+                AttributeData::LocationListIndex(_) => DW_FORM_loclistx,
+                _ => DW_FORM_string,
+            };
+            abbrev_attributes.push(AbbrevAttribute::new(attribute.at, form));
+        }
+        let abbrev_vec = AbbrevVec::new(vec![Abbrev {
+            code: 1,
+            tag: DW_TAG_compile_unit,
+            children: false,
+            attributes: abbrev_attributes,
+        }]);
+        let mut debug_abbrev = Vec::new();
+        abbrev_vec.write(&mut debug_abbrev).unwrap();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        let mut all_attributes = vec![
+            Attribute { at: DW_AT_GNU_dwo_id, data: AttributeData::Data8(dwo_id) },
+        ];
+        all_attributes.extend(attributes);
+        let mut unit = CompilationUnit {
+            common: UnitCommon { endian: LittleEndian, ..Default::default() },
+        };
+        let mut data = Vec::new();
+        Die::new(0, 1, DW_TAG_compile_unit, false, all_attributes)
+            .write(&mut data, &unit.common, &abbrev_hash).unwrap();
+        unit.common.data = &data;
+        let mut debug_info = Vec::new();
+        unit.write(&mut debug_info).unwrap();
+
+        Sections {
+            debug_abbrev: debug_abbrev,
+            debug_info: debug_info,
+            ..Sections::new(LittleEndian)
+        }
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn match_units() {
+        let skeleton = sections(0x1234, vec![
+            Attribute { at: DW_AT_comp_dir, data: AttributeData::String(b"/build") },
+        ]);
+        let dwo = sections(0x1234, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(b"main.c") },
+        ]);
+        let other = sections(0x5678, vec![]);
+
+        let units = super::match_units(&skeleton, &dwo).unwrap();
+        assert_eq!(units.len(), 1);
+        let unit = &units[0];
+        assert_eq!(unit.dwo_id, 0x1234);
+        assert_eq!(unit.attr_string(DW_AT_name).unwrap(), Some(&b"main.c"[..]));
+        assert_eq!(unit.attr_string(DW_AT_comp_dir).unwrap(), Some(&b"/build"[..]));
+        assert_eq!(unit.attr(DW_AT_comp_dir).unwrap(),
+                   Some((AttributeData::String(b"/build"), false)));
+        assert_eq!(unit.attr(DW_AT_low_pc).unwrap(), None);
+
+        assert!(super::match_units(&skeleton, &other).unwrap().is_empty());
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn gnu_split() {
+        let mut skeleton = sections(0x1234, vec![
+            Attribute { at: DW_AT_GNU_dwo_name, data: AttributeData::String(b"main.dwo") },
+            Attribute { at: DW_AT_GNU_addr_base, data: AttributeData::SecOffset(8) },
+            Attribute { at: DW_AT_GNU_ranges_base, data: AttributeData::SecOffset(0x20) },
+        ]);
+        skeleton.debug_addr = vec![
+            0, 0, 0, 0, 0, 0, 0, 0,
+            0x00, 0x10, 0, 0,
+            0x00, 0x20, 0, 0,
+        ];
+        let mut dwo = sections(0x1234, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::StringIndex(1) },
+            Attribute { at: DW_AT_low_pc, data: AttributeData::AddressIndex(1) },
+            Attribute { at: DW_AT_ranges, data: AttributeData::SecOffset(0x10) },
+        ]);
+        dwo.debug_str = b"main.c\0/build\0".to_vec();
+        dwo.debug_str_offsets = vec![7, 0, 0, 0, 0, 0, 0, 0];
+
+        let units = super::match_units(&skeleton, &dwo).unwrap();
+        let unit = &units[0];
+        assert_eq!(unit.dwo_name().unwrap(), Some(&b"main.dwo"[..]));
+        assert_eq!(unit.addr_base().unwrap(), 8);
+        assert_eq!(unit.ranges_base().unwrap(), 0x20);
+        assert_eq!(unit.address(0).unwrap(), 0x1000);
+        assert!(unit.address(2).is_err());
+        assert_eq!(unit.attr_address(DW_AT_low_pc).unwrap(), Some(0x2000));
+        assert_eq!(unit.attr_string(DW_AT_name).unwrap(), Some(&b"main.c"[..]));
+        let (ranges, _) = unit.attr(DW_AT_ranges).unwrap().unwrap();
+        assert_eq!(unit.ranges_offset(&ranges).unwrap(), Some(0x30));
+
+        dwo.debug_str_offsets = vec![20, 0, 0, 0];
+        let units = super::match_units(&skeleton, &dwo).unwrap();
+        assert!(units[0].attr_string(DW_AT_name).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "elf")]
+    fn lru() {
+        let mut lru = Lru::new(2);
+        lru.insert(1, "a");
+        lru.insert(2, "b");
+        assert_eq!(lru.get(&1), Some(&"a"));
+        lru.insert(3, "c");
+        assert_eq!(lru.get(&2), None);
+        assert_eq!(lru.get(&1), Some(&"a"));
+        assert_eq!(lru.get(&3), Some(&"c"));
+        lru.insert(3, "d");
+        assert_eq!(lru.get(&3), Some(&"d"));
+        assert_eq!(lru.get(&1), Some(&"a"));
+    }
+}
@@ -0,0 +1,306 @@
+use std;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Deref;
+
+use die::{Attribute, AttributeData};
+use endian::Endian;
+use read::*;
+use Sections;
+
+// Strings in the debug info are byte slices. DWARF doesn't specify their
+// encoding, and producers may use the encoding of the host, such as for
+// paths in objects built on Windows. Use these functions to convert them
+// when a `str` is needed.
+
+// Convert a string to UTF-8, returning `ReadError::Invalid` if it isn't
+// valid UTF-8.
+pub fn to_str(s: &[u8]) -> Result<&str, ReadError> {
+    std::str::from_utf8(s).map_err(|_| ReadError::Invalid)
+}
+
+// Convert a string to UTF-8, replacing invalid sequences with U+FFFD.
+pub fn to_str_lossy<'a>(s: &'a [u8]) -> Cow<'a, str> {
+    String::from_utf8_lossy(s)
+}
+
+// An iterator over the null terminated strings in a string section, such
+// as .debug_str.
+//
+// Each string is returned with its offset, and without its terminator.
+// Note that attributes may refer to offsets within a string, since
+// producers can share the tail of a string between several attributes.
+#[derive(Debug, Clone)]
+pub struct StringIterator<'data> {
+    data: &'data [u8],
+    offset: usize,
+}
+
+impl<'data> StringIterator<'data> {
+    pub fn new(data: &'data [u8]) -> Self {
+        StringIterator {
+            data: data,
+            offset: 0,
+        }
+    }
+
+    #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
+    pub fn next(&mut self) -> Result<Option<(usize, &'data [u8])>, ReadError> {
+        if self.offset >= self.data.len() {
+            return Ok(None);
+        }
+        let offset = self.offset;
+        let mut r = &self.data[offset..];
+        let string = try!(read_string(&mut r));
+        self.offset = self.data.len() - r.len();
+        Ok(Some((offset, string)))
+    }
+}
+
+// Build a map from each string in a string section to its offset.
+//
+// If a string occurs more than once, then the offset of the first
+// occurrence is used. This is the map needed to deduplicate strings when
+// writing, and to check which strings are duplicated.
+pub fn reverse_map(data: &[u8]) -> Result<HashMap<&[u8], usize>, ReadError> {
+    let mut map = HashMap::new();
+    let mut strings = StringIterator::new(data);
+    while let Some((offset, string)) = try!(strings.next()) {
+        map.entry(string).or_insert(offset);
+    }
+    Ok(map)
+}
+
+// An entry in .debug_str_offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrOffset {
+    // The position of the entry in .debug_str_offsets.
+    pub position: usize,
+    pub offset_size: u8,
+    // The offset in .debug_str.
+    pub offset: u64,
+}
+
+// Read the entries in .debug_str_offsets, which are used by DW_FORM_strx
+// and DW_FORM_GNU_str_index.
+//
+// In version 5, the entries of each unit follow a header containing their
+// length and version. GNU split DWARF units (before version 5) use a
+// section without headers, so if the first unit in .debug_info is before
+// version 5 then the whole section is read as entries with the offset size
+// of that unit.
+pub fn str_offsets<E, S>(sections: &Sections<E, S>) -> Result<Vec<StrOffset>, ReadError>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    let endian = sections.endian;
+    let data = &*sections.debug_str_offsets;
+    let mut entries = Vec::new();
+    if data.is_empty() {
+        return Ok(entries);
+    }
+    if let Some(unit) = try!(sections.compilation_units().next()) {
+        if unit.common.version < 5 {
+            try!(add_str_offsets(&mut entries, data, 0, endian, unit.common.offset_size));
+            return Ok(entries);
+        }
+    }
+    let mut r = data;
+    while !r.is_empty() {
+        let (offset_size, len) = try!(read_initial_length(&mut r, endian));
+        let mut contribution = &r[..len];
+        r = &r[len..];
+        let version = try!(endian.read_u16(&mut contribution));
+        if version != 5 {
+            return Err(ReadError::UnsupportedVersion(version));
+        }
+        let _padding = try!(endian.read_u16(&mut contribution));
+        let position = data.len() - r.len() - contribution.len();
+        try!(add_str_offsets(&mut entries, contribution, position, endian, offset_size));
+    }
+    Ok(entries)
+}
+
+// Add the entries in `data`, which starts at `position` in the section.
+fn add_str_offsets<E: Endian>(
+    entries: &mut Vec<StrOffset>,
+    data: &[u8],
+    position: usize,
+    endian: E,
+    offset_size: u8
+) -> Result<(), ReadError> {
+    let mut r = data;
+    while !r.is_empty() {
+        let entry_position = position + data.len() - r.len();
+        let offset = try!(read_offset(&mut r, endian, offset_size));
+        entries.push(StrOffset {
+            position: entry_position,
+            offset_size: offset_size,
+            offset: offset,
+        });
+    }
+    Ok(())
+}
+
+// The references to .debug_str from the entries of all units.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StringUsage {
+    // The offsets referenced by DW_FORM_strp attributes and by the entries
+    // of .debug_str_offsets, with the number of references to each.
+    pub references: BTreeMap<usize, usize>,
+}
+
+impl StringUsage {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // Find the references in .debug_info, .debug_types and
+    // .debug_str_offsets.
+    pub fn from_sections<E, S>(sections: &Sections<E, S>) -> Result<StringUsage, ReadError>
+        where E: Endian,
+              S: Deref<Target = [u8]>
+    {
+        let mut usage = StringUsage::new();
+        let mut units = sections.compilation_units();
+        while let Some(unit) = try!(units.next()) {
+            let abbrev = try!(sections.abbrev(&unit.common));
+            let mut entries = unit.entries(&abbrev);
+            while let Some(entry) = try!(entries.next()) {
+                usage.add_attributes(&entry.attributes);
+            }
+        }
+        let mut units = sections.type_units();
+        while let Some(unit) = try!(units.next()) {
+            let abbrev = try!(sections.abbrev(&unit.common));
+            let mut entries = unit.entries(&abbrev);
+            while let Some(entry) = try!(entries.next()) {
+                usage.add_attributes(&entry.attributes);
+            }
+        }
+        for entry in try!(str_offsets(sections)) {
+            *usage.references.entry(entry.offset as usize).or_insert(0) += 1;
+        }
+        Ok(usage)
+    }
+
+    fn add_attributes(&mut self, attributes: &[Attribute]) {
+        for attribute in attributes {
+            if let AttributeData::StringOffset(offset) = attribute.data {
+                *self.references.entry(offset as usize).or_insert(0) += 1;
+            }
+        }
+    }
+
+    // Return true if any part of the string at `offset` with length `len`,
+    // including its terminator, is referenced.
+    pub fn is_used(&self, offset: usize, len: usize) -> bool {
+        self.references.range(offset..offset + len + 1).next().is_some()
+    }
+
+    // Return the strings that aren't referenced, as the offset and length of
+    // each string, including its terminator.
+    //
+    // A string is used if any offset within it is referenced, since
+    // producers may share the tail of a string.
+    pub fn unused(&self, debug_str: &[u8]) -> Result<Vec<(usize, usize)>, ReadError> {
+        let mut unused = Vec::new();
+        let mut strings = StringIterator::new(debug_str);
+        while let Some((offset, string)) = try!(strings.next()) {
+            if !self.is_used(offset, string.len()) {
+                unused.push((offset, string.len() + 1));
+            }
+        }
+        Ok(unused)
+    }
+
+    // Return the number of bytes in strings that aren't referenced.
+    pub fn unused_len(&self, debug_str: &[u8]) -> Result<usize, ReadError> {
+        let unused = try!(self.unused(debug_str));
+        Ok(unused.iter().map(|&(_, len)| len).sum())
+    }
+
+    // Return the references that are past the end of .debug_str.
+    pub fn invalid(&self, debug_str: &[u8]) -> Vec<usize> {
+        self.references.range(debug_str.len()..).map(|(&offset, _)| offset).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use endian::LittleEndian;
+
+    #[test]
+    fn to_str() {
+        assert_eq!(super::to_str(b"main").unwrap(), "main");
+        assert!(super::to_str(b"C:\\\xe9t\xe9").is_err());
+        assert_eq!(to_str_lossy(b"main"), "main");
+        assert_eq!(to_str_lossy(b"C:\\\xe9t\xe9"), "C:\\\u{fffd}t\u{fffd}");
+    }
+
+    #[test]
+    fn strings() {
+        let data = b"\0main\0int\0main\0";
+        let mut strings = Vec::new();
+        let mut iter = StringIterator::new(data);
+        while let Some(string) = iter.next().unwrap() {
+            strings.push(string);
+        }
+        assert_eq!(strings, vec![
+            (0, &b""[..]),
+            (1, &b"main"[..]),
+            (6, &b"int"[..]),
+            (10, &b"main"[..]),
+        ]);
+
+        let map = reverse_map(data).unwrap();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(&b"main"[..]), Some(&1));
+        assert_eq!(map.get(&b"int"[..]), Some(&6));
+
+        let mut iter = StringIterator::new(b"a\0b");
+        assert!(iter.next().unwrap().is_some());
+        assert!(iter.next().is_err());
+    }
+
+    #[test]
+    fn string_usage() {
+        let data = b"\0main\0int\0unused\0";
+        let mut usage = StringUsage::new();
+        usage.references.insert(1, 1);
+        // A reference to the tail of "int".
+        usage.references.insert(8, 2);
+        usage.references.insert(100, 1);
+        assert_eq!(usage.unused(data).unwrap(), vec![(0, 1), (10, 7)]);
+        assert_eq!(usage.unused_len(data).unwrap(), 8);
+        assert_eq!(usage.invalid(data), vec![100]);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn str_offsets() {
+        let sections = Sections {
+            debug_str_offsets: vec![
+                0x0c, 0, 0, 0, 5, 0, 0, 0,
+                0x10, 0, 0, 0,
+                0x20, 0, 0, 0,
+                0x08, 0, 0, 0, 5, 0, 0, 0,
+                0x30, 0, 0, 0,
+            ],
+            ..Sections::new(LittleEndian)
+        };
+        let entries = super::str_offsets(&sections).unwrap();
+        assert_eq!(entries.iter().map(|e| (e.position, e.offset)).collect::<Vec<_>>(),
+                   vec![(8, 0x10), (12, 0x20), (24, 0x30)]);
+
+        let sections = Sections {
+            debug_str_offsets: vec![0x08, 0, 0, 0, 4, 0, 0, 0, 0x10, 0, 0, 0],
+            ..Sections::new(LittleEndian)
+        };
+        match super::str_offsets(&sections) {
+            Err(ReadError::UnsupportedVersion(4)) => {}
+            otherwise => panic!("{:?}", otherwise),
+        }
+    }
+}
@@ -0,0 +1,1394 @@
+use std;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::ops::Deref;
+
+use abbrev::AbbrevHash;
+use constant;
+use die::{AttributeContext, AttributeData, Die, DieResolver, ReferenceChain, UnitResolver};
+use endian::Endian;
+use frame::{CfaRule, FrameEntry, FrameEntryIterator, RegisterRule, UnwindRow};
+use line::{Line, LineIterator};
+use read::{read_address, ReadError};
+use types;
+use unit::{CompilationUnit, UnitBases, UnitCommon};
+use Sections;
+
+// A contiguous range of code of a function.
+//
+// A function with DW_AT_ranges has one of these for each range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub address: u64,
+    pub size: u64,
+    // The DW_AT_name of the function, or an empty name if it has none.
+    pub name: Vec<u8>,
+    // The DW_AT_linkage_name of the function, if any.
+    pub linkage_name: Option<Vec<u8>>,
+    // The section offset of the DW_TAG_subprogram entry.
+    pub offset: usize,
+}
+
+impl Function {
+    // Return true if the function contains the address.
+    pub fn contains(&self, address: u64) -> bool {
+        address >= self.address && address - self.address < self.size
+    }
+}
+
+// Two functions with overlapping address ranges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FunctionOverlap {
+    // The section offsets of the DW_TAG_subprogram entries. The `first`
+    // function starts at or before the `second` function.
+    pub first: usize,
+    pub second: usize,
+    // The lowest address contained in both functions.
+    pub address: u64,
+}
+
+// The functions of the compilation units, sorted by address.
+//
+// Only functions with DW_AT_low_pc and DW_AT_high_pc, or DW_AT_ranges,
+// are included. Functions and ranges at address 0 are assumed to have been
+// removed by the linker, and are ignored.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FunctionIndex {
+    functions: Vec<Function>,
+    // The highest end address of the functions up to and including each
+    // index, which bounds the search for overlapping functions in `find`.
+    max_end: Vec<u64>,
+}
+
+impl FunctionIndex {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn from_sections<E, S>(sections: &Sections<E, S>) -> Result<FunctionIndex, ReadError>
+        where E: Endian,
+              S: Deref<Target = [u8]>
+    {
+        let mut index = FunctionIndex::new();
+        let mut units = sections.compilation_units();
+        while let Some(unit) = try!(units.next()) {
+            let abbrev = try!(sections.abbrev(&unit.common));
+            try!(index.add_unit(sections, &unit, &abbrev));
+        }
+        Ok(index)
+    }
+
+    // Add the functions of a compilation unit.
+    pub fn add_unit<'a, E, S>(
+        &mut self,
+        sections: &'a Sections<E, S>,
+        unit: &CompilationUnit<'a, E>,
+        abbrev: &AbbrevHash
+    ) -> Result<(), ReadError>
+        where E: Endian,
+              S: Deref<Target = [u8]>
+    {
+        let mut bases = None;
+        let mut entries = unit.entries(abbrev);
+        while let Some(entry) = try!(entries.next()) {
+            // The first entry is the root entry.
+            let bases = *bases.get_or_insert_with(|| UnitBases::from_root(&unit.common, entry));
+            if entry.tag != constant::DW_TAG_subprogram {
+                continue;
+            }
+            let ranges = try!(sections.entry_ranges(&unit.common, &bases, entry));
+            let ranges: Vec<_> = ranges.into_iter()
+                .filter(|range| range.begin != 0 && range.end > range.begin)
+                .collect();
+            if ranges.is_empty() {
+                continue;
+            }
+            let mut name = Vec::new();
+            let mut linkage_name = None;
+            try!(set_names(&mut name,
+                           &mut linkage_name,
+                           entry,
+                           unit,
+                           abbrev,
+                           &sections.attribute_context(&unit.common)));
+            for range in ranges {
+                self.functions.push(Function {
+                    address: range.begin,
+                    size: range.end - range.begin,
+                    name: name.clone(),
+                    linkage_name: linkage_name.clone(),
+                    offset: entry.offset,
+                });
+            }
+        }
+        self.sort();
+        Ok(())
+    }
+
+    fn sort(&mut self) {
+        self.functions.sort_by_key(|function| (function.address, function.size));
+        self.max_end.clear();
+        let mut max_end = 0;
+        for function in &self.functions {
+            max_end = std::cmp::max(max_end, function.address.saturating_add(function.size));
+            self.max_end.push(max_end);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.functions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+
+    pub fn iter<'a>(&'a self) -> std::slice::Iter<'a, Function> {
+        self.functions.iter()
+    }
+
+    // Return the function containing the address.
+    //
+    // If functions overlap, the one with the highest start address is
+    // returned.
+    pub fn find(&self, address: u64) -> Option<&Function> {
+        let index = match self.functions.binary_search_by_key(&(address, std::u64::MAX),
+                                                              |f| (f.address, f.size)) {
+            Ok(index) => index + 1,
+            Err(index) => index,
+        };
+        for i in (0..index).rev() {
+            if self.max_end[i] <= address {
+                break;
+            }
+            if self.functions[i].contains(address) {
+                return Some(&self.functions[i]);
+            }
+        }
+        None
+    }
+
+    // Return every pair of functions whose address ranges overlap, within
+    // or across units.
+    //
+    // Overlaps are usually a producer or linker bug, such as code that
+    // was merged by LTO while keeping the entries for each copy.
+    pub fn overlaps(&self) -> Vec<FunctionOverlap> {
+        let mut overlaps = Vec::new();
+        for (i, first) in self.functions.iter().enumerate() {
+            let end = first.address.saturating_add(first.size);
+            for second in &self.functions[i + 1..] {
+                if second.address >= end {
+                    break;
+                }
+                overlaps.push(FunctionOverlap {
+                    first: first.offset,
+                    second: second.offset,
+                    address: second.address,
+                });
+            }
+        }
+        overlaps
+    }
+}
+
+// A variable with a fixed address, such as a global or static variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+    pub address: u64,
+    // The DW_AT_byte_size of the type of the variable, if known.
+    pub size: Option<u64>,
+    // The DW_AT_name of the variable, or an empty name if it has none.
+    pub name: Vec<u8>,
+    // The DW_AT_linkage_name of the variable, if any.
+    pub linkage_name: Option<Vec<u8>>,
+    // The section offset of the DW_TAG_variable entry.
+    pub offset: usize,
+    // The section offset of the unit containing the entry.
+    pub unit_offset: usize,
+}
+
+impl Variable {
+    // Return true if the variable contains the address.
+    //
+    // If the size isn't known, then only the first address is contained.
+    pub fn contains(&self, address: u64) -> bool {
+        match self.size {
+            Some(size) => address >= self.address && address - self.address < size,
+            None => address == self.address,
+        }
+    }
+}
+
+// The variables of the compilation units that have a fixed address,
+// sorted by address.
+//
+// This is the data equivalent of `FunctionIndex`, and can be used in place
+// of the symbol table of a stripped file. A variable is included if its
+// DW_AT_location is a single DW_OP_addr, or a location list in which an
+// entry is a single DW_OP_addr. Variables at address 0 are assumed to
+// have been removed by the linker, and are ignored.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VariableIndex {
+    variables: Vec<Variable>,
+}
+
+impl VariableIndex {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn from_sections<E, S>(sections: &Sections<E, S>) -> Result<VariableIndex, ReadError>
+        where E: Endian,
+              S: Deref<Target = [u8]>
+    {
+        let mut index = VariableIndex::new();
+        let mut units = sections.compilation_units();
+        while let Some(unit) = try!(units.next()) {
+            let abbrev = try!(sections.abbrev(&unit.common));
+            try!(index.add_unit(sections, &unit, &abbrev));
+        }
+        Ok(index)
+    }
+
+    // Add the variables of a compilation unit.
+    pub fn add_unit<'a, E, S>(
+        &mut self,
+        sections: &'a Sections<E, S>,
+        unit: &CompilationUnit<'a, E>,
+        abbrev: &AbbrevHash
+    ) -> Result<(), ReadError>
+        where E: Endian,
+              S: Deref<Target = [u8]>
+    {
+        let mut bases = None;
+        let mut entries = unit.entries(abbrev);
+        while let Some(entry) = try!(entries.next()) {
+            // The first entry is the root entry.
+            let bases = *bases.get_or_insert_with(|| UnitBases::from_root(&unit.common, entry));
+            if entry.tag != constant::DW_TAG_variable {
+                continue;
+            }
+            let address = match try!(variable_address(sections, unit, &bases, entry)) {
+                Some(address) if address != 0 => address,
+                _ => continue,
+            };
+            let mut resolver = UnitResolver::new(unit, abbrev);
+            let mut variable = Variable {
+                address: address,
+                size: try!(variable_size(entry, &mut resolver)),
+                name: Vec::new(),
+                linkage_name: None,
+                offset: entry.offset,
+                unit_offset: unit.common.offset,
+            };
+            try!(set_names(&mut variable.name,
+                           &mut variable.linkage_name,
+                           entry,
+                           unit,
+                           abbrev,
+                           &sections.attribute_context(&unit.common)));
+            self.variables.push(variable);
+        }
+        self.variables.sort_by_key(|variable| (variable.address, variable.size));
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.variables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variables.is_empty()
+    }
+
+    pub fn iter<'a>(&'a self) -> std::slice::Iter<'a, Variable> {
+        self.variables.iter()
+    }
+
+    // Return the variable containing the address.
+    //
+    // If variables overlap, the one with the highest start address is
+    // returned.
+    pub fn find(&self, address: u64) -> Option<&Variable> {
+        let index = match self.variables
+            .binary_search_by_key(&(address, Some(std::u64::MAX)), |v| (v.address, v.size)) {
+            Ok(index) => index + 1,
+            Err(index) => index,
+        };
+        self.variables[..index].iter().rev().find(|variable| variable.contains(address))
+    }
+}
+
+// Return the address of a variable whose location is a single DW_OP_addr.
+fn variable_address<'a, E, S>(
+    sections: &'a Sections<E, S>,
+    unit: &CompilationUnit<'a, E>,
+    bases: &UnitBases,
+    entry: &Die
+) -> Result<Option<u64>, ReadError>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    let data = match entry.attr(constant::DW_AT_location) {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+    match *data {
+        AttributeData::ExprLoc(expression) |
+        AttributeData::Block(expression) => expression_address(unit, expression),
+        _ if unit.common.version >= 5 => {
+            let mut locations = match try!(sections.loclist(&unit.common, bases, data)) {
+                Some(locations) => locations,
+                None => return Ok(None),
+            };
+            while let Some(location) = try!(locations.next()) {
+                if let Some(address) = try!(expression_address(unit, location.expression)) {
+                    return Ok(Some(address));
+                }
+            }
+            Ok(None)
+        }
+        _ => {
+            let mut locations = match try!(sections.location_list(&unit.common, bases, data)) {
+                Some(locations) => locations,
+                None => return Ok(None),
+            };
+            while let Some(location) = try!(locations.next()) {
+                if let Some(address) = try!(expression_address(unit, location.expression)) {
+                    return Ok(Some(address));
+                }
+            }
+            Ok(None)
+        }
+    }
+}
+
+fn expression_address<E: Endian>(
+    unit: &CompilationUnit<E>,
+    expression: &[u8]
+) -> Result<Option<u64>, ReadError> {
+    let address_size = unit.common.address_size as usize;
+    if expression.len() != 1 + address_size || expression[0] != constant::DW_OP_addr.0 {
+        return Ok(None);
+    }
+    let mut r = &expression[1..];
+    read_address(&mut r, unit.common.endian, unit.common.address_size).map(Some)
+}
+
+// Return the size of the type of a variable.
+fn variable_size<'data, R>(entry: &Die<'data>, resolver: &mut R) -> Result<Option<u64>, ReadError>
+    where R: DieResolver<'data>
+{
+    match try!(entry.attr_resolved(constant::DW_AT_type, resolver)) {
+        Some(data) => {
+            let type_die = try!(resolver.resolve(entry, &data));
+            types::type_byte_size(&type_die, resolver)
+        }
+        None => Ok(None),
+    }
+}
+
+// Return the rows of a line program that contribute to the code of a
+// function, grouped by the full path of their file.
+//
+// A row contributes if the addresses it covers, up to the next row in its
+// sequence, overlap the DW_AT_low_pc and DW_AT_high_pc of the function, or
+// one of its DW_AT_ranges. The rows for each file are in the order of the
+// line program, and include rows for line 0. `lines` must be the line
+// program of `unit`, and `bases` are the bases of `unit`. Functions without
+// an address range have no rows.
+pub fn function_lines<'a, 'data, E, S>(
+    sections: &'a Sections<E, S>,
+    unit: &UnitCommon<'a, E>,
+    bases: &UnitBases,
+    entry: &Die,
+    mut lines: LineIterator<'data, E>
+) -> Result<BTreeMap<Vec<u8>, Vec<Line>>, ReadError>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    let mut files = BTreeMap::new();
+    let ranges = try!(sections.entry_ranges(unit, bases, entry));
+    if ranges.is_empty() {
+        return Ok(files);
+    }
+    // Paths indexed by file number, since most rows reuse a few files.
+    let mut paths = HashMap::new();
+    let mut previous: Option<Line> = None;
+    while let Some((iter, line)) = try!(lines.next()) {
+        if let Some(row) = previous.take() {
+            let overlaps = ranges.iter()
+                .any(|range| row.address < range.end && line.address > range.begin);
+            if overlaps && line.address > row.address {
+                let path = paths.entry(row.file)
+                    .or_insert_with(|| match iter.file(row.file) {
+                        Some(entry) => entry.full_path(iter.directories()),
+                        None => Vec::new(),
+                    })
+                    .clone();
+                files.entry(path).or_insert_with(Vec::new).push(row);
+            }
+        }
+        if !line.sequence_end {
+            previous = Some(line.clone());
+        }
+    }
+    Ok(files)
+}
+
+// Set the names of a function or variable, following references to the
+// declaration or abstract instance if needed.
+fn set_names<E: Endian>(
+    name: &mut Vec<u8>,
+    linkage_name: &mut Option<Vec<u8>>,
+    entry: &Die,
+    unit: &CompilationUnit<E>,
+    abbrev: &AbbrevHash,
+    context: &AttributeContext
+) -> Result<(), ReadError> {
+    let mut chain = ReferenceChain::new(entry.offset);
+    let mut entry = entry.clone();
+    loop {
+        if name.is_empty() {
+            if let Some(data) = entry.attr(constant::DW_AT_name) {
+                *name = try!(data.as_string_strict_in(context)).unwrap_or(&[]).to_vec();
+            }
+        }
+        if linkage_name.is_none() {
+            let data = entry.attr(constant::DW_AT_linkage_name)
+                .or_else(|| entry.attr(constant::DW_AT_MIPS_linkage_name));
+            if let Some(data) = data {
+                *linkage_name = try!(data.as_string_strict_in(context)).map(|name| name.to_vec());
+            }
+        }
+        if !name.is_empty() && linkage_name.is_some() {
+            break;
+        }
+
+        // References to other units aren't followed.
+        let offset = match entry.attr(constant::DW_AT_specification)
+            .or_else(|| entry.attr(constant::DW_AT_abstract_origin)) {
+            Some(&AttributeData::Ref(offset)) => offset.to_section_offset(&unit.common),
+            _ => break,
+        };
+        try!(chain.visit(offset));
+        let mut entries = try!(unit.entry(offset, abbrev).ok_or(ReadError::Invalid));
+        entry = match try!(entries.next()) {
+            Some(entry) => entry.clone(),
+            None => return Err(ReadError::Invalid),
+        };
+    }
+    Ok(())
+}
+
+// Write the functions in the format of the `/tmp/perf-<pid>.map` files
+// read by perf and other profilers.
+//
+// Each line is the address and size in hexadecimal, followed by the
+// linkage name, or the name if there is no linkage name. Functions without
+// a name are omitted.
+pub fn write_perf_map<W: Write>(w: &mut W, index: &FunctionIndex) -> std::io::Result<()> {
+    for function in index.iter() {
+        let name = function.linkage_name.as_ref().unwrap_or(&function.name);
+        if name.is_empty() {
+            continue;
+        }
+        try!(write!(w, "{:x} {:x} ", function.address, function.size));
+        try!(w.write_all(name));
+        try!(writeln!(w, ""));
+    }
+    Ok(())
+}
+
+// The fields of the MODULE record of a Breakpad symbol file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreakpadModule {
+    // The operating system, such as "Linux".
+    pub os: String,
+    // The architecture, such as "x86_64".
+    pub arch: String,
+    // The build id, as uppercase hexadecimal.
+    pub id: String,
+    // The file name of the module.
+    pub name: String,
+    // The identifier for the INFO CODE_ID record, if any.
+    pub code_id: Option<String>,
+}
+
+// A range of addresses for a source line, for a LINE record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LineRange {
+    pub address: u64,
+    pub size: u64,
+    pub line: u64,
+    // The index of the path in `BreakpadSymbols::files`.
+    pub file: usize,
+}
+
+// A symbol from the symbol table, for a PUBLIC record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicSymbol {
+    pub address: u64,
+    pub name: Vec<u8>,
+}
+
+// The unwind rows of an FDE, for STACK CFI records.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallFrame {
+    pub address: u64,
+    pub size: u64,
+    pub return_address_register: u64,
+    // The rows don't contain DWARF expressions, since STACK CFI records
+    // can't describe them.
+    pub rows: Vec<UnwindRow<'static>>,
+}
+
+// The records of a Breakpad symbol file.
+#[derive(Debug, Default, Clone)]
+pub struct BreakpadSymbols {
+    pub functions: FunctionIndex,
+    // The paths of the files, indexed by their FILE number.
+    pub files: Vec<Vec<u8>>,
+    // The line ranges, sorted by address.
+    pub lines: Vec<LineRange>,
+    // Symbols for PUBLIC records, sorted by address.
+    //
+    // Symbols that are within a function are not written.
+    pub publics: Vec<PublicSymbol>,
+    // The call frame information for STACK CFI records, sorted by address.
+    pub call_frames: Vec<CallFrame>,
+    file_numbers: HashMap<Vec<u8>, usize>,
+}
+
+impl BreakpadSymbols {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // Read the functions and line programs of all compilation units, and
+    // the FDEs in .debug_frame.
+    //
+    // Public symbols aren't in the DWARF sections, so add them using
+    // `add_public`.
+    pub fn from_sections<E, S>(sections: &Sections<E, S>) -> Result<BreakpadSymbols, ReadError>
+        where E: Endian,
+              S: Deref<Target = [u8]>
+    {
+        let mut symbols = BreakpadSymbols::new();
+        symbols.functions = try!(FunctionIndex::from_sections(sections));
+        let mut address_size = None;
+        let mut units = sections.compilation_units();
+        while let Some(unit) = try!(units.next()) {
+            address_size = Some(unit.common.address_size);
+            let abbrev = try!(sections.abbrev(&unit.common));
+            if let Some(lines) = try!(sections.lines(&unit, &abbrev)) {
+                try!(symbols.add_lines(lines));
+            }
+        }
+        // CIEs before version 4 don't contain the address size, so use the
+        // address size of the units.
+        if let Some(address_size) = address_size {
+            try!(symbols.add_frame_entries(sections.frame_entries(address_size), sections.endian));
+        }
+        Ok(symbols)
+    }
+
+    // Add the rows of a line program.
+    //
+    // Each row covers the addresses up to the next row in its sequence.
+    // Rows for line 0 have no source line, and are ignored.
+    pub fn add_lines<'data, E: Endian>(
+        &mut self,
+        mut lines: LineIterator<'data, E>
+    ) -> Result<(), ReadError> {
+        // File numbers indexed by the file index of the line program.
+        let mut numbers = HashMap::new();
+        let mut previous: Option<(u64, u64, u64)> = None;
+        while let Some((iter, line)) = try!(lines.next()) {
+            if let Some((address, file, line_number)) = previous {
+                if line.address > address && line_number != 0 {
+                    let files = &mut self.files;
+                    let file_numbers = &mut self.file_numbers;
+                    let number = *numbers.entry(file).or_insert_with(|| {
+                        let path = match iter.file(file) {
+                            Some(entry) => entry.full_path(iter.directories()),
+                            None => Vec::new(),
+                        };
+                        *file_numbers.entry(path.clone()).or_insert_with(|| {
+                            files.push(path);
+                            files.len() - 1
+                        })
+                    });
+                    self.lines.push(LineRange {
+                        address: address,
+                        size: line.address - address,
+                        line: line_number,
+                        file: number,
+                    });
+                }
+            }
+            previous = if line.sequence_end {
+                None
+            } else {
+                Some((line.address, line.file, line.line))
+            };
+        }
+        self.lines.sort();
+        Ok(())
+    }
+
+    // Add the unwind rows of the FDEs from an iterator.
+    //
+    // FDEs with rules that are DWARF expressions are skipped, since STACK
+    // CFI records can't describe them.
+    pub fn add_frame_entries<E: Endian>(
+        &mut self,
+        mut entries: FrameEntryIterator<E>,
+        endian: E
+    ) -> Result<(), ReadError> {
+        while let Some(entry) = try!(entries.next()) {
+            let (fde, cie) = match entry {
+                FrameEntry::Fde(fde, cie) => (fde, cie),
+                FrameEntry::Cie(_) => continue,
+            };
+            let rows = try!(fde.rows(&cie, endian));
+            let rows = match rows.iter().map(owned_row).collect::<Option<Vec<_>>>() {
+                Some(rows) => rows,
+                None => continue,
+            };
+            self.call_frames.push(CallFrame {
+                address: fde.initial_location,
+                size: fde.address_range,
+                return_address_register: cie.return_address_register,
+                rows: rows,
+            });
+        }
+        self.call_frames.sort_by_key(|frame| frame.address);
+        Ok(())
+    }
+
+    pub fn add_public(&mut self, address: u64, name: &[u8]) {
+        self.publics.push(PublicSymbol {
+            address: address,
+            name: name.to_vec(),
+        });
+        self.publics.sort_by_key(|public| public.address);
+    }
+
+    // Write the records of the symbol file.
+    //
+    // Each FUNC record is followed by the LINE records for the function,
+    // limited to the function's address range. The STACK CFI records are
+    // written last, and only for architectures with known register names.
+    pub fn write<W: Write>(&self, w: &mut W, module: &BreakpadModule) -> std::io::Result<()> {
+        try!(writeln!(w, "MODULE {} {} {} {}", module.os, module.arch, module.id, module.name));
+        if let Some(ref code_id) = module.code_id {
+            try!(writeln!(w, "INFO CODE_ID {}", code_id));
+        }
+        for (number, path) in self.files.iter().enumerate() {
+            try!(write!(w, "FILE {} ", number));
+            try!(w.write_all(path));
+            try!(writeln!(w, ""));
+        }
+        for function in self.functions.iter() {
+            try!(write!(w, "FUNC {:x} {:x} 0 ", function.address, function.size));
+            if function.name.is_empty() {
+                try!(write!(w, "<name omitted>"));
+            } else {
+                try!(w.write_all(&function.name));
+            }
+            try!(writeln!(w, ""));
+
+            let end = function.address + function.size;
+            // A range that starts before the function may extend into it.
+            let mut index = match self.lines.binary_search_by_key(&function.address,
+                                                                  |line| line.address) {
+                Ok(index) | Err(index) => index.saturating_sub(1),
+            };
+            while let Some(line) = self.lines.get(index) {
+                if line.address >= end {
+                    break;
+                }
+                let begin = std::cmp::max(line.address, function.address);
+                let line_end = std::cmp::min(line.address + line.size, end);
+                if begin < line_end {
+                    try!(writeln!(w,
+                                  "{:x} {:x} {} {}",
+                                  begin,
+                                  line_end - begin,
+                                  line.line,
+                                  line.file));
+                }
+                index += 1;
+            }
+        }
+        for public in &self.publics {
+            if self.functions.find(public.address).is_some() {
+                continue;
+            }
+            try!(write!(w, "PUBLIC {:x} 0 ", public.address));
+            try!(w.write_all(&public.name));
+            try!(writeln!(w, ""));
+        }
+        for frame in &self.call_frames {
+            if let Some(records) = stack_cfi(&module.arch, frame) {
+                try!(w.write_all(records.as_bytes()));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Convert a row to one that doesn't borrow the section, or return `None`
+// if it contains a DWARF expression.
+fn owned_row(row: &UnwindRow) -> Option<UnwindRow<'static>> {
+    let cfa = match row.cfa {
+        CfaRule::RegisterOffset { register, offset } => {
+            CfaRule::RegisterOffset {
+                register: register,
+                offset: offset,
+            }
+        }
+        CfaRule::Expression(_) => return None,
+    };
+    let mut registers = Vec::new();
+    for &(register, rule) in &row.registers {
+        let rule = match rule {
+            RegisterRule::Undefined => RegisterRule::Undefined,
+            RegisterRule::SameValue => RegisterRule::SameValue,
+            RegisterRule::Offset(offset) => RegisterRule::Offset(offset),
+            RegisterRule::ValOffset(offset) => RegisterRule::ValOffset(offset),
+            RegisterRule::Register(register) => RegisterRule::Register(register),
+            RegisterRule::Expression(_) |
+            RegisterRule::ValExpression(_) => return None,
+        };
+        registers.push((register, rule));
+    }
+    Some(UnwindRow {
+        address: row.address,
+        end: row.end,
+        cfa: cfa,
+        registers: registers,
+    })
+}
+
+// The Breakpad names of the DWARF registers of each architecture.
+const X86_REGISTERS: &[&str] = &["$eax", "$ecx", "$edx", "$ebx", "$esp", "$ebp", "$esi", "$edi",
+                                 "$eip"];
+const X86_64_REGISTERS: &[&str] = &["$rax", "$rdx", "$rcx", "$rbx", "$rsi", "$rdi", "$rbp",
+                                    "$rsp", "$r8", "$r9", "$r10", "$r11", "$r12", "$r13",
+                                    "$r14", "$r15", "$rip"];
+const ARM_REGISTERS: &[&str] = &["r0", "r1", "r2", "r3", "r4", "r5", "r6", "r7", "r8", "r9",
+                                 "r10", "r11", "r12", "sp", "lr", "pc"];
+const ARM64_REGISTERS: &[&str] = &["x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9",
+                                   "x10", "x11", "x12", "x13", "x14", "x15", "x16", "x17",
+                                   "x18", "x19", "x20", "x21", "x22", "x23", "x24", "x25",
+                                   "x26", "x27", "x28", "x29", "x30", "sp", "pc"];
+
+fn register_name(arch: &str, register: u64) -> Option<&'static str> {
+    let names = match arch {
+        "x86" => X86_REGISTERS,
+        "x86_64" => X86_64_REGISTERS,
+        "arm" => ARM_REGISTERS,
+        "arm64" => ARM64_REGISTERS,
+        _ => return None,
+    };
+    names.get(register as usize).cloned()
+}
+
+// Return a postfix expression that adds an offset to `base`.
+fn add_offset(base: &str, offset: i64) -> String {
+    if offset < 0 {
+        format!("{} {} -", base, offset.wrapping_neg() as u64)
+    } else {
+        format!("{} {} +", base, offset)
+    }
+}
+
+// Format the STACK CFI records for the rows of an FDE.
+//
+// The STACK CFI INIT record contains all of the rules of the first row, and
+// the following records contain the rules that change. A register that no
+// longer has a rule is given its own value. Returns `None` if a register
+// has no name for the architecture.
+fn stack_cfi(arch: &str, frame: &CallFrame) -> Option<String> {
+    let name = |register: u64| if register == frame.return_address_register {
+        Some(".ra")
+    } else {
+        register_name(arch, register)
+    };
+    let mut records = String::new();
+    let mut previous: Option<&UnwindRow> = None;
+    for row in &frame.rows {
+        let mut rules = String::new();
+        if previous.map(|previous| previous.cfa) != Some(row.cfa) {
+            if let CfaRule::RegisterOffset { register, offset } = row.cfa {
+                match register_name(arch, register) {
+                    Some(base) => rules.push_str(&format!(" .cfa: {}", add_offset(base, offset))),
+                    None => return None,
+                }
+            }
+        }
+        for &(register, rule) in &row.registers {
+            if previous.and_then(|previous| previous.register(register)) == Some(rule) {
+                continue;
+            }
+            let rule = match rule {
+                RegisterRule::Undefined => Some(".undef".to_string()),
+                RegisterRule::SameValue => register_name(arch, register).map(str::to_string),
+                RegisterRule::Offset(offset) => Some(format!("{} ^", add_offset(".cfa", offset))),
+                RegisterRule::ValOffset(offset) => Some(add_offset(".cfa", offset)),
+                RegisterRule::Register(target) => register_name(arch, target).map(str::to_string),
+                RegisterRule::Expression(_) |
+                RegisterRule::ValExpression(_) => None,
+            };
+            match (name(register), rule) {
+                (Some(name), Some(rule)) => rules.push_str(&format!(" {}: {}", name, rule)),
+                _ => return None,
+            }
+        }
+        if let Some(previous) = previous {
+            for &(register, _) in &previous.registers {
+                if row.register(register).is_none() {
+                    match (name(register), register_name(arch, register)) {
+                        (Some(name), Some(rule)) => {
+                            rules.push_str(&format!(" {}: {}", name, rule))
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+        }
+        match previous {
+            None => {
+                records.push_str(&format!("STACK CFI INIT {:x} {:x}{}\n",
+                                          frame.address,
+                                          frame.size,
+                                          rules))
+            }
+            Some(_) if !rules.is_empty() => {
+                records.push_str(&format!("STACK CFI {:x}{}\n", row.address, rules))
+            }
+            Some(_) => {}
+        }
+        previous = Some(row);
+    }
+    Some(records)
+}
+
+// Write a Breakpad symbol file containing a FUNC record for each function.
+//
+// The records have no parameter size or line records. Functions without a
+// name are omitted.
+pub fn write_breakpad<W: Write>(
+    w: &mut W,
+    module: &BreakpadModule,
+    index: &FunctionIndex
+) -> std::io::Result<()> {
+    try!(writeln!(w, "MODULE {} {} {} {}", module.os, module.arch, module.id, module.name));
+    for function in index.iter() {
+        if function.name.is_empty() {
+            continue;
+        }
+        try!(write!(w, "FUNC {:x} {:x} 0 ", function.address, function.size));
+        try!(w.write_all(&function.name));
+        try!(writeln!(w, ""));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use abbrev::*;
+    use constant::*;
+    use endian::*;
+    use unit::*;
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn index() -> FunctionIndex {
+        let abbrevs = AbbrevVec::new(vec![
+            Abbrev {
+                code: 1,
+                tag: DW_TAG_compile_unit,
+                children: true,
+                attributes: vec![],
+            },
+            Abbrev {
+                code: 2,
+                tag: DW_TAG_subprogram,
+                children: false,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                    AbbrevAttribute::new(DW_AT_linkage_name, DW_FORM_string),
+                    AbbrevAttribute::new(DW_AT_declaration, DW_FORM_flag_present),
+                ],
+            },
+            Abbrev {
+                code: 3,
+                tag: DW_TAG_subprogram,
+                children: false,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_specification, DW_FORM_ref1),
+                    AbbrevAttribute::new(DW_AT_low_pc, DW_FORM_addr),
+                    AbbrevAttribute::new(DW_AT_high_pc, DW_FORM_data1),
+                ],
+            },
+            Abbrev {
+                code: 4,
+                tag: DW_TAG_subprogram,
+                children: false,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                    AbbrevAttribute::new(DW_AT_low_pc, DW_FORM_addr),
+                    AbbrevAttribute::new(DW_AT_high_pc, DW_FORM_addr),
+                ],
+            },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrevs.write(&mut debug_abbrev).unwrap();
+        let abbrev = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        let data = [
+            1,
+            // 0xc: declaration
+            2, b'f', 0, b'_', b'Z', b'1', b'f', 0,
+            // 0x14: definition
+            3, 0xc, 0x00, 0x20, 0, 0, 0, 0, 0, 0, 0x10,
+            // 0x1f
+            4, b'g', 0, 0x00, 0x10, 0, 0, 0, 0, 0, 0, 0x20, 0x10, 0, 0, 0, 0, 0, 0,
+            // removed by the linker
+            4, b'h', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x10, 0, 0, 0, 0, 0, 0, 0,
+            0,
+        ];
+        let unit = CompilationUnit {
+            common: UnitCommon {
+                endian: LittleEndian,
+                address_size: 8,
+                data: &data,
+                ..Default::default()
+            },
+        };
+        let sections = Sections::new(LittleEndian);
+        let mut index = FunctionIndex::new();
+        index.add_unit(&sections, &unit, &abbrev).unwrap();
+        index
+    }
+
+    #[test]
+    fn function_index() {
+        let index = index();
+        assert_eq!(index.len(), 2);
+        let functions: Vec<_> = index.iter().collect();
+        assert_eq!(functions[0].name, b"g");
+        assert_eq!(functions[0].linkage_name, None);
+        assert_eq!((functions[0].address, functions[0].size), (0x1000, 0x20));
+        assert_eq!(functions[1].name, b"f");
+        assert_eq!(functions[1].linkage_name, Some(b"_Z1f".to_vec()));
+        assert_eq!((functions[1].address, functions[1].size), (0x2000, 0x10));
+        assert_eq!(functions[1].offset, 0x14);
+
+        assert_eq!(index.find(0xfff), None);
+        assert_eq!(index.find(0x1000).map(|f| &f.name[..]), Some(&b"g"[..]));
+        assert_eq!(index.find(0x101f).map(|f| &f.name[..]), Some(&b"g"[..]));
+        assert_eq!(index.find(0x1020), None);
+        assert_eq!(index.find(0x200f).map(|f| &f.name[..]), Some(&b"f"[..]));
+        assert_eq!(index.find(0x2010), None);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn function_ranges() {
+        use die::Attribute;
+
+        let abbrev = AbbrevVec::new(vec![
+            Abbrev { code: 1, tag: DW_TAG_compile_unit, children: true, attributes: vec![
+                AbbrevAttribute::new(DW_AT_low_pc, DW_FORM_addr),
+            ] },
+            Abbrev { code: 2, tag: DW_TAG_subprogram, children: false, attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                AbbrevAttribute::new(DW_AT_ranges, DW_FORM_sec_offset),
+            ] },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrev.write(&mut debug_abbrev).unwrap();
+        let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        for &(version, offset) in &[(4, 0), (5, 12)] {
+            let entries = [
+                Die::new(0, 1, DW_TAG_compile_unit, true, vec![
+                    Attribute { at: DW_AT_low_pc, data: AttributeData::Address(0x1000) },
+                ]),
+                Die::new(0, 2, DW_TAG_subprogram, false, vec![
+                    Attribute { at: DW_AT_name, data: AttributeData::String(b"k") },
+                    Attribute { at: DW_AT_ranges, data: AttributeData::SecOffset(offset) },
+                ]),
+                Die::null(0),
+            ];
+            let mut unit = CompilationUnit::<LittleEndian>::default();
+            unit.common.version = version;
+            let mut data = Vec::new();
+            for entry in &entries {
+                entry.write(&mut data, &unit.common, &abbrev_hash).unwrap();
+            }
+            unit.common.data = &data;
+            let mut debug_info = Vec::new();
+            unit.write(&mut debug_info).unwrap();
+
+            // The ranges 0x1000..0x1010 and 0x1100..0x1108.
+            let sections = Sections {
+                debug_abbrev: debug_abbrev.clone(),
+                debug_info: debug_info,
+                debug_ranges: vec![
+                    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00,
+                    0x00, 0x01, 0x00, 0x00, 0x08, 0x01, 0x00, 0x00,
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                ],
+                debug_rnglists: vec![
+                    0x11, 0x00, 0x00, 0x00, 0x05, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00,
+                    DW_RLE_offset_pair.0, 0x00, 0x10,
+                    DW_RLE_offset_pair.0, 0x80, 0x02, 0x88, 0x02,
+                    DW_RLE_end_of_list.0,
+                ],
+                ..Sections::new(LittleEndian)
+            };
+            let index = FunctionIndex::from_sections(&sections).unwrap();
+            let functions: Vec<_> = index.iter()
+                .map(|function| (&function.name[..], function.address, function.size))
+                .collect();
+            assert_eq!(functions, vec![(&b"k"[..], 0x1000, 0x10), (&b"k"[..], 0x1100, 0x8)]);
+            assert_eq!(index.find(0x1104).map(|f| &f.name[..]), Some(&b"k"[..]));
+            assert_eq!(index.find(0x1010), None);
+        }
+    }
+
+    #[test]
+    fn overlaps() {
+        let mut index = index();
+        assert_eq!(index.overlaps(), Vec::new());
+
+        let function = |address, size, offset| {
+            Function {
+                address: address,
+                size: size,
+                name: Vec::new(),
+                linkage_name: None,
+                offset: offset,
+            }
+        };
+        index.functions.push(function(0x1010, 0x8, 0x100));
+        index.functions.push(function(0x1018, 0x10, 0x200));
+        index.functions.push(function(0x2000, 0x10, 0x300));
+        index.sort();
+        assert_eq!(index.overlaps(), vec![
+            FunctionOverlap { first: 0x1f, second: 0x100, address: 0x1010 },
+            FunctionOverlap { first: 0x1f, second: 0x200, address: 0x1018 },
+            FunctionOverlap { first: 0x14, second: 0x300, address: 0x2000 },
+        ]);
+
+        // The function with the highest start address is found, and the
+        // search stops before functions that end before the address.
+        let find = |address| index.find(address).map(|function| function.offset);
+        assert_eq!(find(0x1014), Some(0x100));
+        assert_eq!(find(0x101c), Some(0x200));
+        assert_eq!(find(0x1024), Some(0x200));
+        assert_eq!(find(0x1030), None);
+        assert_eq!(find(0x2008), Some(0x300));
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn variable_index() {
+        let abbrevs = AbbrevVec::new(vec![
+            Abbrev {
+                code: 1,
+                tag: DW_TAG_compile_unit,
+                children: true,
+                attributes: vec![],
+            },
+            Abbrev {
+                code: 2,
+                tag: DW_TAG_variable,
+                children: false,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                    AbbrevAttribute::new(DW_AT_type, DW_FORM_ref1),
+                    AbbrevAttribute::new(DW_AT_location, DW_FORM_exprloc),
+                ],
+            },
+            Abbrev {
+                code: 3,
+                tag: DW_TAG_base_type,
+                children: false,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_byte_size, DW_FORM_data1),
+                ],
+            },
+            Abbrev {
+                code: 4,
+                tag: DW_TAG_typedef,
+                children: false,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_type, DW_FORM_ref1),
+                ],
+            },
+            Abbrev {
+                code: 5,
+                tag: DW_TAG_pointer_type,
+                children: false,
+                attributes: vec![],
+            },
+        ]);
+        let mut debug_abbrev = Vec::new();
+        abbrevs.write(&mut debug_abbrev).unwrap();
+        let abbrev = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+
+        let data = [
+            1,
+            // 0xc: int
+            3, 4,
+            // 0xe: typedef int
+            4, 0xc,
+            // 0x10: pointer
+            5,
+            // 0x11: a typedef int at 0x2000
+            2, b'a', 0, 0xe, 9, DW_OP_addr.0, 0x00, 0x20, 0, 0, 0, 0, 0, 0,
+            // 0x1f: a pointer at 0x1000
+            2, b'b', 0, 0x10, 9, DW_OP_addr.0, 0x00, 0x10, 0, 0, 0, 0, 0, 0,
+            // 0x2d: not a fixed address
+            2, b'c', 0, 0xc, 2, DW_OP_fbreg.0, 0x10,
+            // 0x35: removed by the linker
+            2, b'd', 0, 0xc, 9, DW_OP_addr.0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0,
+        ];
+        let unit = CompilationUnit {
+            common: UnitCommon {
+                endian: LittleEndian,
+                address_size: 8,
+                data: &data,
+                ..Default::default()
+            },
+        };
+        let sections = Sections::new(LittleEndian);
+        let mut index = VariableIndex::new();
+        index.add_unit(&sections, &unit, &abbrev).unwrap();
+        assert_eq!(index.len(), 2);
+        let variables: Vec<_> = index.iter().collect();
+        assert_eq!(variables[0].name, b"b");
+        assert_eq!((variables[0].address, variables[0].size), (0x1000, Some(8)));
+        assert_eq!(variables[0].offset, 0x1f);
+        assert_eq!(variables[1].name, b"a");
+        assert_eq!((variables[1].address, variables[1].size), (0x2000, Some(4)));
+        assert_eq!(variables[1].offset, 0x11);
+
+        assert_eq!(index.find(0xfff), None);
+        assert_eq!(index.find(0x1007).map(|v| &v.name[..]), Some(&b"b"[..]));
+        assert_eq!(index.find(0x1008), None);
+        assert_eq!(index.find(0x2003).map(|v| &v.name[..]), Some(&b"a"[..]));
+        assert_eq!(index.find(0x2004), None);
+    }
+
+    #[test]
+    fn perf_map() {
+        let mut buf = Vec::new();
+        write_perf_map(&mut buf, &index()).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "1000 20 g\n2000 10 _Z1f\n");
+    }
+
+    #[test]
+    fn breakpad() {
+        let module = BreakpadModule {
+            os: "Linux".to_string(),
+            arch: "x86_64".to_string(),
+            id: "0123456789ABCDEF0123456789ABCDEF0".to_string(),
+            name: "test".to_string(),
+            code_id: None,
+        };
+        let mut buf = Vec::new();
+        write_breakpad(&mut buf, &module, &index()).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(),
+                   "MODULE Linux x86_64 0123456789ABCDEF0123456789ABCDEF0 test\n\
+                    FUNC 1000 20 0 g\n\
+                    FUNC 2000 10 0 f\n");
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn breakpad_symbols() {
+        let mut symbols = BreakpadSymbols::new();
+        symbols.functions = index();
+        symbols.files = vec![b"/src/a.c".to_vec(), b"/src/b.c".to_vec()];
+        symbols.lines = vec![
+            LineRange { address: 0x0ff0, size: 0x14, line: 1, file: 0 },
+            LineRange { address: 0x1004, size: 0x8, line: 2, file: 0 },
+            LineRange { address: 0x2000, size: 0x20, line: 3, file: 1 },
+        ];
+        symbols.add_public(0x3000, b"h");
+        symbols.add_public(0x1010, b"g");
+        let debug_frame = [
+            // 0x00: CIE
+            0x10, 0x00, 0x00, 0x00,
+            0xff, 0xff, 0xff, 0xff,
+            0x01, 0x00, 0x01, 0x78, 0x10,
+            DW_CFA_def_cfa.0, 0x07, 0x08,
+            DW_CFA_offset.0 | 0x10, 0x01,
+            DW_CFA_nop.0, DW_CFA_nop.0,
+            // 0x14: FDE for g
+            0x24, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            DW_CFA_advance_loc.0 | 0x01,
+            DW_CFA_def_cfa_offset.0, 0x10,
+            DW_CFA_offset.0 | 0x06, 0x02,
+            DW_CFA_advance_loc.0 | 0x03,
+            DW_CFA_def_cfa_register.0, 0x06,
+            DW_CFA_advance_loc.0 | 0x18,
+            DW_CFA_def_cfa.0, 0x07, 0x08,
+            DW_CFA_restore.0 | 0x06,
+            DW_CFA_nop.0, DW_CFA_nop.0, DW_CFA_nop.0,
+            // 0x3c: FDE for f, which uses an expression
+            0x1c, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            DW_CFA_advance_loc.0 | 0x01,
+            DW_CFA_def_cfa_expression.0, 0x02, DW_OP_breg7.0, 0x08,
+            DW_CFA_nop.0, DW_CFA_nop.0, DW_CFA_nop.0,
+        ];
+        let entries = FrameEntryIterator::new(&debug_frame, LittleEndian, 8);
+        symbols.add_frame_entries(entries, LittleEndian).unwrap();
+        assert_eq!(symbols.call_frames.len(), 1);
+        let module = BreakpadModule {
+            os: "Linux".to_string(),
+            arch: "x86_64".to_string(),
+            id: "0123".to_string(),
+            name: "test".to_string(),
+            code_id: Some("abcd".to_string()),
+        };
+        let mut buf = Vec::new();
+        symbols.write(&mut buf, &module).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(),
+                   "MODULE Linux x86_64 0123 test\n\
+                    INFO CODE_ID abcd\n\
+                    FILE 0 /src/a.c\n\
+                    FILE 1 /src/b.c\n\
+                    FUNC 1000 20 0 g\n\
+                    1000 4 1 0\n\
+                    1004 8 2 0\n\
+                    FUNC 2000 10 0 f\n\
+                    2000 10 3 1\n\
+                    PUBLIC 3000 0 h\n\
+                    STACK CFI INIT 1000 20 .cfa: $rsp 8 + .ra: .cfa 8 - ^\n\
+                    STACK CFI 1001 .cfa: $rsp 16 + $rbp: .cfa 16 - ^\n\
+                    STACK CFI 1004 .cfa: $rbp 16 +\n\
+                    STACK CFI 101c .cfa: $rsp 8 + $rbp: $rbp\n");
+
+        // There are no register names for other architectures.
+        let module = BreakpadModule {
+            arch: "mips".to_string(),
+            ..module
+        };
+        let mut buf = Vec::new();
+        symbols.write(&mut buf, &module).unwrap();
+        assert!(String::from_utf8(buf).unwrap().ends_with("PUBLIC 3000 0 h\n"));
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn function_lines() {
+        use die::Attribute;
+        use leb128;
+        use line::*;
+
+        let mut data = Vec::new();
+        // Line 1 at 0xff0, line 2 at 0x1000, line 3 of file 2 at 0x1008,
+        // line 4 at 0x1010, end at 0x1018.
+        data.extend_from_slice(&[0, 5, DW_LNE_set_address.0, 0xf0, 0x0f, 0x00, 0x00]);
+        data.push(DW_LNS_copy.0);
+        for &(file, advance) in &[(1, 0x10), (2, 0x8), (2, 0x8)] {
+            data.push(DW_LNS_advance_pc.0);
+            leb128::write_u64(&mut data, advance).unwrap();
+            data.push(DW_LNS_set_file.0);
+            leb128::write_u64(&mut data, file).unwrap();
+            data.push(DW_LNS_advance_line.0);
+            leb128::write_i64(&mut data, 1).unwrap();
+            data.push(DW_LNS_copy.0);
+        }
+        data.push(DW_LNS_advance_pc.0);
+        leb128::write_u64(&mut data, 8).unwrap();
+        data.extend_from_slice(&[0, 1, DW_LNE_end_sequence.0]);
+
+        let program = LineProgram {
+            offset: 0,
+            endian: LittleEndian,
+            version: 2,
+            address_size: 4,
+            offset_size: 4,
+            address_step: 1,
+            operation_range: 1,
+            default_statement: true,
+            line_base: -5,
+            line_range: 14,
+            opcode_base: 13,
+            standard_opcode_lengths: &[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1],
+            include_directories: vec![b"/src"],
+            files: vec![
+                FileEntry { path: b"a.c", ..Default::default() },
+                FileEntry { path: b"a.c", directory: 0, ..Default::default() },
+                FileEntry { path: b"b.h", directory: 0, ..Default::default() },
+            ],
+            data: &data,
+        };
+
+        let sections = Sections {
+            debug_ranges: vec![
+                0xf0, 0x0f, 0x00, 0x00, 0xf8, 0x0f, 0x00, 0x00,
+                0x10, 0x10, 0x00, 0x00, 0x18, 0x10, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ],
+            ..Sections::new(LittleEndian)
+        };
+        let unit = UnitCommon::<LittleEndian>::default();
+        let bases = UnitBases::default();
+
+        // The function covers part of the rows for lines 2 and 3.
+        let entry = Die::new(0, 1, DW_TAG_subprogram, false, vec![
+            Attribute { at: DW_AT_low_pc, data: AttributeData::Address(0x1004) },
+            Attribute { at: DW_AT_high_pc, data: AttributeData::UData(0x8) },
+        ]);
+        let lines = |entry: &Die| -> Vec<(Vec<u8>, Vec<u64>)> {
+            let files = super::function_lines(&sections, &unit, &bases, entry, program.lines())
+                .unwrap();
+            files.into_iter()
+                .map(|(path, rows)| (path, rows.iter().map(|row| row.line).collect()))
+                .collect()
+        };
+        assert_eq!(lines(&entry), vec![
+            (b"/src/a.c".to_vec(), vec![2]),
+            (b"/src/b.h".to_vec(), vec![3]),
+        ]);
+
+        let entry = Die::new(0, 1, DW_TAG_subprogram, false, vec![
+            Attribute { at: DW_AT_low_pc, data: AttributeData::Address(0x1000) },
+            Attribute { at: DW_AT_high_pc, data: AttributeData::Address(0x1018) },
+        ]);
+        assert_eq!(lines(&entry), vec![
+            (b"/src/a.c".to_vec(), vec![2]),
+            (b"/src/b.h".to_vec(), vec![3, 4]),
+        ]);
+
+        // The ranges 0xff0..0xff8 and 0x1010..0x1018.
+        let entry = Die::new(0, 1, DW_TAG_subprogram, false, vec![
+            Attribute { at: DW_AT_ranges, data: AttributeData::SecOffset(0) },
+        ]);
+        assert_eq!(lines(&entry), vec![
+            (b"/src/a.c".to_vec(), vec![1]),
+            (b"/src/b.h".to_vec(), vec![4]),
+        ]);
+
+        let entry = Die::new(0, 1, DW_TAG_subprogram, false, vec![]);
+        assert_eq!(lines(&entry), vec![]);
+    }
+}
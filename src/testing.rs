@@ -0,0 +1,373 @@
+// Generators for DWARF sections that contain edge cases.
+//
+// These are valid DWARF, but exercise limits that compilers rarely reach.
+// Only the sections that are needed are populated.
+use std::io::Write;
+
+use abbrev::{Abbrev, AbbrevAttribute, AbbrevHash, AbbrevVec};
+use constant;
+use die::{Attribute, AttributeData, Die};
+use endian::Endian;
+use line::{FileEntry, LineProgram};
+use unit::{CompilationUnit, UnitCommon};
+use write::write_u8;
+use Sections;
+
+const STANDARD_OPCODE_LENGTHS: [u8; 12] = [0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1];
+
+fn sections<E: Endian>(
+    endian: E,
+    debug_abbrev: Vec<u8>,
+    debug_info: Vec<u8>,
+    debug_line: Vec<u8>,
+    debug_str: Vec<u8>
+) -> Sections<E> {
+    Sections {
+        debug_abbrev: debug_abbrev,
+        debug_info: debug_info,
+        debug_line: debug_line,
+        debug_str: debug_str,
+        ..Sections::new(endian)
+    }
+}
+
+fn abbrevs(abbrevs: Vec<Abbrev>) -> (Vec<u8>, AbbrevHash) {
+    let mut debug_abbrev = Vec::new();
+    AbbrevVec::new(abbrevs).write(&mut debug_abbrev).unwrap();
+    let abbrev_hash = AbbrevHash::read(&mut &debug_abbrev[..]).unwrap();
+    (debug_abbrev, abbrev_hash)
+}
+
+fn unit<'data, E: Endian>(offset: usize, endian: E, offset_size: u8) -> CompilationUnit<'data, E> {
+    CompilationUnit {
+        common: UnitCommon {
+            offset: offset,
+            endian: endian,
+            version: 4,
+            address_size: 8,
+            offset_size: offset_size,
+            abbrev_offset: 0,
+            unit_type: constant::DW_UT_compile,
+            unit_id: 0,
+            type_offset: 0,
+            data: &[],
+            padding: 0,
+            quirks: Default::default(),
+        },
+    }
+}
+
+fn write_unit<E: Endian>(
+    w: &mut Vec<u8>,
+    endian: E,
+    offset_size: u8,
+    entries: &[Die],
+    abbrev: &AbbrevHash
+) {
+    let mut unit = unit(w.len(), endian, offset_size);
+    let mut data = Vec::new();
+    for entry in entries {
+        entry.write(&mut data, &unit.common, abbrev).unwrap();
+    }
+    unit.common.data = &data;
+    unit.write(w).unwrap();
+}
+
+fn entry<'data>(
+    code: u64,
+    tag: constant::DwTag,
+    children: bool,
+    attributes: Vec<Attribute<'data>>
+) -> Die<'data> {
+    Die::new(0, code, tag, children, attributes)
+}
+
+// An abbreviation for a unit root with a name and a line program.
+fn root_abbrev(name_form: constant::DwForm) -> Abbrev {
+    Abbrev {
+        code: 1,
+        tag: constant::DW_TAG_compile_unit,
+        children: false,
+        attributes: vec![
+            AbbrevAttribute::new(constant::DW_AT_name, name_form),
+            AbbrevAttribute::new(constant::DW_AT_stmt_list, constant::DW_FORM_sec_offset),
+        ],
+    }
+}
+
+fn root(name: AttributeData) -> Die {
+    let attributes = vec![
+        Attribute {
+            at: constant::DW_AT_name,
+            data: name,
+        },
+        Attribute {
+            at: constant::DW_AT_stmt_list,
+            data: AttributeData::SecOffset(0),
+        },
+    ];
+    entry(1, constant::DW_TAG_compile_unit, false, attributes)
+}
+
+// Write an unsigned LEB128 value using the maximum encoded length.
+fn write_long_leb128<W: Write>(w: &mut W, mut value: u64) {
+    for _ in 0..9 {
+        write_u8(w, (value as u8 & 0x7f) | 0x80).unwrap();
+        value >>= 7;
+    }
+    write_u8(w, value as u8).unwrap();
+}
+
+// A unit containing a chain of lexical blocks, each nested in the previous.
+pub fn nested_entries<E: Endian>(endian: E, depth: usize) -> Sections<E> {
+    let (debug_abbrev, abbrev) = abbrevs(vec![
+        Abbrev {
+            code: 1,
+            tag: constant::DW_TAG_compile_unit,
+            children: true,
+            attributes: vec![],
+        },
+        Abbrev {
+            code: 2,
+            tag: constant::DW_TAG_lexical_block,
+            children: true,
+            attributes: vec![],
+        },
+    ]);
+    let mut entries = vec![entry(1, constant::DW_TAG_compile_unit, true, vec![])];
+    for _ in 0..depth {
+        entries.push(entry(2, constant::DW_TAG_lexical_block, true, vec![]));
+    }
+    for _ in 0..depth + 1 {
+        entries.push(Die::null(0));
+    }
+    let mut debug_info = Vec::new();
+    write_unit(&mut debug_info, endian, 4, &entries, &abbrev);
+    sections(endian, debug_abbrev, debug_info, Vec::new(), Vec::new())
+}
+
+// A unit where every LEB128 value uses the maximum encoded length.
+//
+// This includes the abbreviation codes, tags, attribute names and forms,
+// and attribute values.
+pub fn long_leb128<E: Endian>(endian: E) -> Sections<E> {
+    let mut debug_abbrev = Vec::new();
+    write_long_leb128(&mut debug_abbrev, 1);
+    write_long_leb128(&mut debug_abbrev, constant::DW_TAG_compile_unit.0 as u64);
+    write_u8(&mut debug_abbrev, constant::DW_CHILDREN_yes.0).unwrap();
+    write_long_leb128(&mut debug_abbrev, constant::DW_AT_language.0 as u64);
+    write_long_leb128(&mut debug_abbrev, constant::DW_FORM_udata.0 as u64);
+    write_long_leb128(&mut debug_abbrev, constant::DW_AT_decl_line.0 as u64);
+    write_long_leb128(&mut debug_abbrev, constant::DW_FORM_sdata.0 as u64);
+    write_long_leb128(&mut debug_abbrev, 0);
+    write_long_leb128(&mut debug_abbrev, 0);
+    write_long_leb128(&mut debug_abbrev, 0);
+
+    let mut data = Vec::new();
+    write_long_leb128(&mut data, 1);
+    write_long_leb128(&mut data, constant::DW_LANG_Rust.0 as u64);
+    // The maximum length encoding of 0 is also valid for sdata.
+    write_long_leb128(&mut data, 0);
+    // Null entry.
+    write_long_leb128(&mut data, 0);
+
+    let mut unit = unit(0, endian, 4);
+    unit.common.data = &data;
+    let mut debug_info = Vec::new();
+    unit.write(&mut debug_info).unwrap();
+    sections(endian, debug_abbrev, debug_info, Vec::new(), Vec::new())
+}
+
+// A unit and line program using the 64-bit DWARF format.
+pub fn offset_size_64<E: Endian>(endian: E) -> Sections<E> {
+    let (debug_abbrev, abbrev) = abbrevs(vec![root_abbrev(constant::DW_FORM_strp)]);
+    let debug_str = b"\0a.c\0".to_vec();
+    let entries = [root(AttributeData::StringOffset(1))];
+    let mut debug_info = Vec::new();
+    write_unit(&mut debug_info, endian, 8, &entries, &abbrev);
+
+    let mut data = Vec::new();
+    data.extend_from_slice(&[0, 9, constant::DW_LNE_set_address.0]);
+    endian.write_u64(&mut data, 0x1000).unwrap();
+    data.push(constant::DW_LNS_copy.0);
+    data.extend_from_slice(&[constant::DW_LNS_advance_pc.0, 4]);
+    data.extend_from_slice(&[0, 1, constant::DW_LNE_end_sequence.0]);
+    let mut debug_line = Vec::new();
+    line_program(endian, 8, b"a.c", &data).write(&mut debug_line).unwrap();
+
+    sections(endian, debug_abbrev, debug_info, debug_line, debug_str)
+}
+
+fn line_program<'data, E: Endian>(
+    endian: E,
+    offset_size: u8,
+    comp_name: &'data [u8],
+    data: &'data [u8]
+) -> LineProgram<'data, E> {
+    LineProgram {
+        offset: 0,
+        endian: endian,
+        version: 4,
+        address_size: 8,
+        offset_size: offset_size,
+        address_step: 1,
+        operation_range: 1,
+        default_statement: true,
+        line_base: -5,
+        line_range: 14,
+        opcode_base: STANDARD_OPCODE_LENGTHS.len() as u8 + 1,
+        standard_opcode_lengths: &STANDARD_OPCODE_LENGTHS,
+        include_directories: vec![&[]],
+        files: vec![FileEntry {
+                        path: comp_name,
+                        ..Default::default()
+                    }],
+        data: data,
+    }
+}
+
+// Units that contain no entries, followed by a unit containing only a
+// null entry.
+pub fn empty_units<E: Endian>(endian: E, count: usize) -> Sections<E> {
+    let (debug_abbrev, abbrev) = abbrevs(vec![]);
+    let mut debug_info = Vec::new();
+    for _ in 0..count {
+        write_unit(&mut debug_info, endian, 4, &[], &abbrev);
+    }
+    write_unit(&mut debug_info, endian, 4, &[Die::null(0)], &abbrev);
+    sections(endian, debug_abbrev, debug_info, Vec::new(), Vec::new())
+}
+
+// A unit whose entry uses the largest possible abbreviation code.
+pub fn large_abbrev_code<E: Endian>(endian: E) -> Sections<E> {
+    let code = u64::max_value();
+    let (debug_abbrev, abbrev) = abbrevs(vec![Abbrev {
+        code: code,
+        tag: constant::DW_TAG_compile_unit,
+        children: false,
+        attributes: vec![],
+    }]);
+    let entries = [entry(code, constant::DW_TAG_compile_unit, false, vec![])];
+    let mut debug_info = Vec::new();
+    write_unit(&mut debug_info, endian, 4, &entries, &abbrev);
+    sections(endian, debug_abbrev, debug_info, Vec::new(), Vec::new())
+}
+
+// A unit whose line program has a header but no opcodes.
+pub fn empty_line_program<E: Endian>(endian: E) -> Sections<E> {
+    let (debug_abbrev, abbrev) = abbrevs(vec![root_abbrev(constant::DW_FORM_string)]);
+    let entries = [root(AttributeData::String(b"a.c"))];
+    let mut debug_info = Vec::new();
+    write_unit(&mut debug_info, endian, 4, &entries, &abbrev);
+    let mut debug_line = Vec::new();
+    line_program(endian, 4, b"a.c", &[]).write(&mut debug_line).unwrap();
+    sections(endian, debug_abbrev, debug_info, debug_line, Vec::new())
+}
+
+// A unit with a line program but no DW_AT_name, as produced by some
+// assemblers.
+pub fn unnamed_unit<E: Endian>(endian: E) -> Sections<E> {
+    let (debug_abbrev, abbrev) = abbrevs(vec![Abbrev {
+        code: 1,
+        tag: constant::DW_TAG_compile_unit,
+        children: false,
+        attributes: vec![AbbrevAttribute::new(constant::DW_AT_stmt_list, constant::DW_FORM_sec_offset)],
+    }]);
+    let entries = [entry(1,
+                         constant::DW_TAG_compile_unit,
+                         false,
+                         vec![Attribute {
+                                  at: constant::DW_AT_stmt_list,
+                                  data: AttributeData::SecOffset(0),
+                              }])];
+    let mut debug_info = Vec::new();
+    write_unit(&mut debug_info, endian, 4, &entries, &abbrev);
+
+    let mut data = Vec::new();
+    data.extend_from_slice(&[0, 9, constant::DW_LNE_set_address.0]);
+    endian.write_u64(&mut data, 0x1000).unwrap();
+    data.push(constant::DW_LNS_copy.0);
+    data.extend_from_slice(&[0, 1, constant::DW_LNE_end_sequence.0]);
+    let mut debug_line = Vec::new();
+    line_program(endian, 4, &[], &data).write(&mut debug_line).unwrap();
+    sections(endian, debug_abbrev, debug_info, debug_line, Vec::new())
+}
+
+// All of the generators, with their names.
+pub fn all<E: Endian>(endian: E) -> Vec<(&'static str, Sections<E>)> {
+    vec![
+        ("nested_entries", nested_entries(endian, 10000)),
+        ("long_leb128", long_leb128(endian)),
+        ("offset_size_64", offset_size_64(endian)),
+        ("empty_units", empty_units(endian, 3)),
+        ("large_abbrev_code", large_abbrev_code(endian)),
+        ("empty_line_program", empty_line_program(endian)),
+        ("unnamed_unit", unnamed_unit(endian)),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use constant::*;
+    use endian::*;
+    use die::AttributeData;
+
+    // Return the number of entries and lines.
+    fn read_all<E: Endian>(sections: &Sections<E>) -> (usize, usize) {
+        let mut entry_count = 0;
+        let mut line_count = 0;
+        let mut units = sections.compilation_units();
+        while let Some(unit) = units.next().unwrap() {
+            let abbrev = sections.abbrev(&unit.common).unwrap();
+            let mut entries = unit.entries(&abbrev);
+            while entries.next().unwrap().is_some() {
+                entry_count += 1;
+            }
+            if unit.data().is_empty() {
+                continue;
+            }
+            if let Some(mut lines) = sections.lines(&unit, &abbrev).unwrap() {
+                while lines.next().unwrap().is_some() {
+                    line_count += 1;
+                }
+            }
+        }
+        (entry_count, line_count)
+    }
+
+    #[test]
+    fn generators() {
+        for (name, sections) in all(LittleEndian) {
+            let counts = read_all(&sections);
+            let expected = match name {
+                "nested_entries" => (20002, 0),
+                "long_leb128" => (2, 0),
+                "offset_size_64" => (1, 2),
+                "empty_units" => (1, 0),
+                "large_abbrev_code" => (1, 0),
+                "empty_line_program" => (1, 0),
+                "unnamed_unit" => (1, 2),
+                _ => panic!("unknown generator {}", name),
+            };
+            assert_eq!(counts, expected, "{}", name);
+        }
+        for (_, sections) in all(BigEndian) {
+            read_all(&sections);
+        }
+    }
+
+    #[test]
+    fn long_leb128_values() {
+        let sections = long_leb128(LittleEndian);
+        let mut units = sections.compilation_units();
+        let unit = units.next().unwrap().unwrap();
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let mut entries = unit.entries(&abbrev);
+        let entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.tag, DW_TAG_compile_unit);
+        assert_eq!(entry.attr(DW_AT_language),
+                   Some(&AttributeData::UData(DW_LANG_Rust.0 as u64)));
+        assert_eq!(entry.attr(DW_AT_decl_line), Some(&AttributeData::SData(0)));
+    }
+}
@@ -0,0 +1,929 @@
+// A textual format for abbreviations, units and entries.
+//
+// `dump` writes the contents of .debug_abbrev, .debug_info and
+// .debug_types as text, and `assemble` parses the text and writes the
+// sections again. This allows test cases to be written by hand, and
+// allows the sections of a file to be edited.
+//
+// The format is line based. Blank lines are ignored, and `#` starts a
+// comment that continues to the end of the line. Indentation is ignored.
+//
+//     abbrev_table 0x0
+//     abbrev 1 DW_TAG_compile_unit children
+//       attr DW_AT_name DW_FORM_string
+//     abbrev 2 DW_TAG_base_type
+//       attr DW_AT_byte_size DW_FORM_data1
+//     unit version 4 address_size 8 offset_size 4 abbrev_table 0x0
+//     die 1 DW_TAG_compile_unit # 0xb
+//       DW_AT_name "test.c"
+//       die 2 DW_TAG_base_type # 0x13
+//         DW_AT_byte_size 4
+//       null
+//
+// Each `die` is followed by its attribute values, in the order of the
+// abbreviation. Values are written as follows:
+//
+// - numbers are decimal, or hexadecimal with a `0x` prefix
+// - flags are `true` or `false`
+// - strings are quoted, and may use the `\\`, `\"` and `\xHH` escapes
+// - blocks and expressions are a list of hexadecimal bytes
+//
+// Version 5 units also have a `unit_type` field, which defaults to
+// DW_UT_compile, and `unit_id` and `type_offset` fields for the unit
+// types that have them. The value of a DW_FORM_implicit_const attribute
+// follows the form in the abbreviation, and must be repeated in each
+// entry.
+//
+// Type units are written as `type_unit`, with additional `signature`
+// and `type_offset` fields. The offsets of the abbreviation tables are
+// only used to match units with their table; the tables are written in
+// the order that they are given.
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Write;
+use std::ops::Deref;
+
+use abbrev::{Abbrev, AbbrevAttribute, AbbrevHash, AbbrevVec};
+use constant;
+use die::{AttributeData, Die, DieIterator};
+use endian::Endian;
+use leb128;
+use offset::{DebugInfoOffset, UnitOffset};
+use unit::{CompilationUnit, TypeUnit, UnitCommon};
+use write::*;
+use Sections;
+
+// Write the abbreviations and units of the sections as text.
+pub fn dump<E, S, W>(w: &mut W, sections: &Sections<E, S>) -> Result<(), WriteError>
+    where E: Endian,
+          S: Deref<Target = [u8]>,
+          W: Write
+{
+    let mut tables = BTreeSet::new();
+    let mut units = sections.compilation_units();
+    while let Some(unit) = try!(units.next()) {
+        tables.insert(unit.common.abbrev_offset);
+    }
+    let mut units = sections.type_units();
+    while let Some(unit) = try!(units.next()) {
+        tables.insert(unit.common.abbrev_offset);
+    }
+
+    for offset in tables {
+        let mut r = match sections.debug_abbrev.get(offset as usize..) {
+            Some(r) => r,
+            None => return Err(WriteError::Invalid(format!("abbrev offset 0x{:x}", offset))),
+        };
+        try!(writeln!(w, "abbrev_table 0x{:x}", offset));
+        for abbrev in try!(AbbrevVec::read(&mut r)).iter() {
+            try!(write!(w, "abbrev {} {}", abbrev.code, abbrev.tag.name_or_hex()));
+            if abbrev.children {
+                try!(write!(w, " children"));
+            }
+            try!(writeln!(w, ""));
+            for attribute in &abbrev.attributes {
+                try!(write!(w,
+                            "  attr {} {}",
+                            attribute.at.name_or_hex(),
+                            attribute.form.name_or_hex()));
+                if attribute.form == constant::DW_FORM_implicit_const {
+                    try!(write!(w, " {}", attribute.implicit_const));
+                }
+                try!(writeln!(w, ""));
+            }
+        }
+    }
+
+    let mut units = sections.compilation_units();
+    while let Some(unit) = try!(units.next()) {
+        try!(writeln!(w, ""));
+        try!(write!(w, "unit"));
+        try!(dump_common(w, &unit.common));
+        try!(writeln!(w, ""));
+        let abbrev = try!(sections.abbrev(&unit.common));
+        try!(dump_entries(w, unit.common.offset, unit.entries(&abbrev)));
+    }
+
+    let mut units = sections.type_units();
+    while let Some(unit) = try!(units.next()) {
+        try!(writeln!(w, ""));
+        try!(write!(w, "type_unit"));
+        try!(dump_common(w, &unit.common));
+        try!(writeln!(w,
+                      " signature 0x{:x} type_offset 0x{:x}",
+                      unit.type_signature,
+                      unit.type_offset));
+        let abbrev = try!(sections.abbrev(&unit.common));
+        try!(dump_entries(w, unit.common.offset, unit.entries(&abbrev)));
+    }
+    Ok(())
+}
+
+fn dump_common<E: Endian, W: Write>(w: &mut W, unit: &UnitCommon<E>) -> Result<(), WriteError> {
+    try!(write!(w,
+                " version {} address_size {} offset_size {} abbrev_table 0x{:x}",
+                unit.version,
+                unit.address_size,
+                unit.offset_size,
+                unit.abbrev_offset));
+    if unit.version >= 5 {
+        try!(write!(w, " unit_type {}", unit.unit_type.0));
+        match unit.unit_type {
+            constant::DW_UT_skeleton | constant::DW_UT_split_compile => {
+                try!(write!(w, " unit_id 0x{:x}", unit.unit_id));
+            }
+            constant::DW_UT_type | constant::DW_UT_split_type => {
+                try!(write!(w,
+                            " unit_id 0x{:x} type_offset 0x{:x}",
+                            unit.unit_id,
+                            unit.type_offset));
+            }
+            _ => {}
+        }
+    }
+    if unit.padding != 0 {
+        try!(write!(w, " padding {}", unit.padding));
+    }
+    Ok(())
+}
+
+fn dump_entries<E: Endian, W: Write>(
+    w: &mut W,
+    unit_offset: usize,
+    mut entries: DieIterator<E>
+) -> Result<(), WriteError> {
+    let mut depth = 0;
+    while let Some(entry) = try!(entries.next()) {
+        let indent = depth * 2;
+        if entry.is_null() {
+            try!(writeln!(w, "{:1$}null", "", indent));
+            depth = depth.saturating_sub(1);
+            continue;
+        }
+        if entry.children {
+            depth += 1;
+        }
+        try!(writeln!(w,
+                      "{:4$}die {} {} # 0x{:x}",
+                      "",
+                      entry.code,
+                      entry.tag.name_or_hex(),
+                      entry.offset - unit_offset,
+                      indent));
+        for attribute in &entry.attributes {
+            try!(write!(w, "{:2$}  {}", "", attribute.at.name_or_hex(), indent));
+            try!(dump_value(w, &attribute.data));
+            try!(writeln!(w, ""));
+        }
+    }
+    Ok(())
+}
+
+fn dump_value<W: Write>(w: &mut W, data: &AttributeData) -> Result<(), WriteError> {
+    match *data {
+        AttributeData::Null => {
+            return Err(WriteError::Unsupported("null attribute value".to_string()));
+        }
+        AttributeData::Address(val) |
+        AttributeData::StringOffset(val) |
+        AttributeData::LineStringOffset(val) |
+        AttributeData::RefSig(val) |
+        AttributeData::SecOffset(val) |
+        AttributeData::AddressIndex(val) |
+        AttributeData::StringIndex(val) |
+        AttributeData::RangeListIndex(val) |
+        AttributeData::LocationListIndex(val) |
+        AttributeData::Ref(UnitOffset(val)) |
+        AttributeData::RefAddress(DebugInfoOffset(val)) => try!(write!(w, " 0x{:x}", val)),
+        AttributeData::Block(val) |
+        AttributeData::Data16(val) |
+        AttributeData::ExprLoc(val) => {
+            for byte in val {
+                try!(write!(w, " {:02x}", byte));
+            }
+        }
+        AttributeData::Data1(val) => try!(write!(w, " {}", val)),
+        AttributeData::Data2(val) => try!(write!(w, " {}", val)),
+        AttributeData::Data4(val) => try!(write!(w, " {}", val)),
+        AttributeData::Data8(val) |
+        AttributeData::UData(val) => try!(write!(w, " {}", val)),
+        AttributeData::SData(val) => try!(write!(w, " {}", val)),
+        AttributeData::Flag(val) => try!(write!(w, " {}", val)),
+        AttributeData::String(val) => {
+            try!(write!(w, " \""));
+            for &byte in val {
+                match byte {
+                    b'"' | b'\\' => try!(write!(w, "\\{}", byte as char)),
+                    _ if byte >= 0x20 && byte < 0x7f => try!(write!(w, "{}", byte as char)),
+                    _ => try!(write!(w, "\\x{:02x}", byte)),
+                }
+            }
+            try!(write!(w, "\""));
+        }
+    }
+    Ok(())
+}
+
+// Parse text in the format written by `dump`, and write the sections.
+//
+// This replaces .debug_abbrev, .debug_info and .debug_types. The other
+// sections are unchanged, so values that refer to them (such as string
+// offsets) must be valid for the existing sections.
+pub fn assemble<E: Endian>(sections: &mut Sections<E>, text: &str) -> Result<(), WriteError> {
+    let mut lines = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let tokens = try!(tokenize(line).map_err(|e| error(index + 1, &e)));
+        if !tokens.is_empty() {
+            lines.push((index + 1, tokens));
+        }
+    }
+
+    // Write the abbreviation tables first, since the units need them.
+    let mut debug_abbrev = Vec::new();
+    let mut table_offsets = HashMap::new();
+    let mut table: Option<Vec<Abbrev>> = None;
+    let mut unit_lines = Vec::new();
+    for (line, tokens) in lines {
+        let result = match word(&tokens, 0) {
+            Ok("abbrev_table") => {
+                if let Some(abbrevs) = table.take() {
+                    try!(AbbrevVec::new(abbrevs).write(&mut debug_abbrev));
+                }
+                let offset = try!(number(&tokens, 1).map_err(|e| error(line, &e)));
+                if table_offsets.insert(offset, debug_abbrev.len() as u64).is_some() {
+                    return Err(error(line, "duplicate abbrev_table"));
+                }
+                table = Some(Vec::new());
+                expect_len(&tokens, 2)
+            }
+            Ok("abbrev") => parse_abbrev(&tokens, table.as_mut()),
+            Ok("attr") => parse_abbrev_attribute(&tokens, table.as_mut()),
+            _ => {
+                unit_lines.push((line, tokens));
+                Ok(())
+            }
+        };
+        try!(result.map_err(|e| error(line, &e)));
+    }
+    if let Some(abbrevs) = table.take() {
+        try!(AbbrevVec::new(abbrevs).write(&mut debug_abbrev));
+    }
+
+    let mut assembler = Assembler {
+        endian: sections.endian,
+        debug_abbrev: &debug_abbrev,
+        table_offsets: table_offsets,
+        abbrev_hashes: HashMap::new(),
+        debug_info: Vec::new(),
+        debug_types: Vec::new(),
+        unit: None,
+    };
+    for (line, tokens) in unit_lines {
+        try!(assembler.line(&tokens).map_err(|e| error(line, &e)));
+    }
+    try!(assembler.finish_unit().map_err(|e| error(0, &e)));
+
+    sections.debug_info = assembler.debug_info;
+    sections.debug_types = assembler.debug_types;
+    sections.debug_abbrev = debug_abbrev;
+    Ok(())
+}
+
+fn error(line: usize, message: &str) -> WriteError {
+    if line == 0 {
+        WriteError::Invalid(message.to_string())
+    } else {
+        WriteError::Invalid(format!("line {}: {}", line, message))
+    }
+}
+
+fn parse_abbrev(tokens: &[Token], table: Option<&mut Vec<Abbrev>>) -> Result<(), String> {
+    let table = match table {
+        Some(table) => table,
+        None => return Err("abbrev must follow abbrev_table".to_string()),
+    };
+    let code = try!(number(tokens, 1));
+    if code == 0 {
+        return Err("abbrev code must not be 0".to_string());
+    }
+    let tag = try!(parse_constant(tokens, 2, tag_value)).map(constant::DwTag);
+    let tag = try!(tag.ok_or_else(|| format!("invalid tag {:?}", tokens[2])));
+    let children = match tokens.len() {
+        3 => false,
+        4 if try!(word(tokens, 3)) == "children" => true,
+        _ => return Err("expected abbrev <code> <tag> [children]".to_string()),
+    };
+    table.push(Abbrev {
+        code: code,
+        tag: tag,
+        children: children,
+        attributes: Vec::new(),
+    });
+    Ok(())
+}
+
+fn parse_abbrev_attribute(
+    tokens: &[Token],
+    table: Option<&mut Vec<Abbrev>>
+) -> Result<(), String> {
+    let abbrev = match table.and_then(|table| table.last_mut()) {
+        Some(abbrev) => abbrev,
+        None => return Err("attr must follow abbrev".to_string()),
+    };
+    let at = try!(parse_constant(tokens, 1, at_value)).map(constant::DwAt);
+    let at = try!(at.ok_or_else(|| format!("invalid attribute {:?}", tokens[1])));
+    let form = try!(parse_constant(tokens, 2, form_value)).map(constant::DwForm);
+    let form = try!(form.ok_or_else(|| format!("invalid form {:?}", tokens[2])));
+    if form == constant::DW_FORM_implicit_const {
+        try!(expect_len(tokens, 4));
+        let value = try!(signed_number(tokens, 3));
+        abbrev.attributes.push(AbbrevAttribute::implicit_const(at, value));
+    } else {
+        try!(expect_len(tokens, 3));
+        abbrev.attributes.push(AbbrevAttribute::new(at, form));
+    }
+    Ok(())
+}
+
+struct AssemblerUnit<'a, E: Endian> {
+    common: UnitCommon<'a, E>,
+    // The type signature and type offset of a type unit.
+    type_unit: Option<(u64, u64)>,
+    abbrev_offset: u64,
+    data: Vec<u8>,
+    // The attributes that still need a value in the current entry.
+    attributes: Vec<AbbrevAttribute>,
+}
+
+struct Assembler<'a, E: Endian> {
+    endian: E,
+    debug_abbrev: &'a [u8],
+    // A mapping from the offsets given in the text to the new offsets.
+    table_offsets: HashMap<u64, u64>,
+    abbrev_hashes: HashMap<u64, AbbrevHash>,
+    debug_info: Vec<u8>,
+    debug_types: Vec<u8>,
+    unit: Option<AssemblerUnit<'a, E>>,
+}
+
+impl<'a, E: Endian> Assembler<'a, E> {
+    fn line(&mut self, tokens: &[Token]) -> Result<(), String> {
+        match try!(word(tokens, 0)) {
+            "unit" => self.start_unit(tokens, false),
+            "type_unit" => self.start_unit(tokens, true),
+            "die" => self.entry(tokens),
+            "null" => {
+                try!(expect_len(tokens, 1));
+                let unit = try!(current_entry(&mut self.unit));
+                try!(Die::write_null(&mut unit.data).map_err(|e| e.to_string()));
+                Ok(())
+            }
+            _ => self.value(tokens),
+        }
+    }
+
+    fn start_unit(&mut self, tokens: &[Token], type_unit: bool) -> Result<(), String> {
+        try!(self.finish_unit());
+
+        // Ordered so that the unknown field reported is always the first.
+        let mut fields = BTreeMap::new();
+        let mut i = 1;
+        while i < tokens.len() {
+            let name = try!(word(tokens, i));
+            let value = try!(number(tokens, i + 1));
+            if fields.insert(name, value).is_some() {
+                return Err(format!("duplicate field {}", name));
+            }
+            i += 2;
+        }
+        let mut field = |name: &str| {
+            fields.remove(name).ok_or_else(|| format!("missing field {}", name))
+        };
+        let version = try!(limit(try!(field("version")), 0xffff, "version"));
+        let address_size = try!(limit(try!(field("address_size")), 0xff, "address_size"));
+        let offset_size = try!(limit(try!(field("offset_size")), 0xff, "offset_size"));
+        let abbrev_offset = try!(field("abbrev_table"));
+        let padding = field("padding").unwrap_or(0);
+        let mut unit_type = constant::DW_UT_compile;
+        let mut unit_id = 0;
+        let mut unit_type_offset = 0;
+        if version >= 5 {
+            if let Ok(val) = field("unit_type") {
+                unit_type = constant::DwUt(try!(limit(val, 0xff, "unit_type")) as u8);
+            }
+            if !type_unit {
+                unit_id = field("unit_id").unwrap_or(0);
+                unit_type_offset = field("type_offset").unwrap_or(0);
+            }
+        }
+        let type_unit = if type_unit {
+            Some((try!(field("signature")), try!(field("type_offset"))))
+        } else {
+            None
+        };
+        if let Some(name) = fields.keys().next() {
+            return Err(format!("unknown field {}", name));
+        }
+
+        let new_offset = match self.table_offsets.get(&abbrev_offset) {
+            Some(&new_offset) => new_offset,
+            None => return Err(format!("unknown abbrev_table 0x{:x}", abbrev_offset)),
+        };
+        if !self.abbrev_hashes.contains_key(&abbrev_offset) {
+            let mut r = &self.debug_abbrev[new_offset as usize..];
+            let abbrev = try!(AbbrevHash::read(&mut r).map_err(|e| format!("{:?}", e)));
+            self.abbrev_hashes.insert(abbrev_offset, abbrev);
+        }
+
+        self.unit = Some(AssemblerUnit {
+            common: UnitCommon {
+                offset: 0,
+                endian: self.endian,
+                version: version as u16,
+                address_size: address_size as u8,
+                offset_size: offset_size as u8,
+                abbrev_offset: new_offset,
+                unit_type: unit_type,
+                unit_id: unit_id,
+                type_offset: unit_type_offset,
+                data: &[],
+                padding: padding as usize,
+                quirks: Default::default(),
+            },
+            type_unit: type_unit,
+            abbrev_offset: abbrev_offset,
+            data: Vec::new(),
+            attributes: Vec::new(),
+        });
+        Ok(())
+    }
+
+    fn finish_unit(&mut self) -> Result<(), String> {
+        let mut unit = match self.unit.take() {
+            Some(unit) => unit,
+            None => return Ok(()),
+        };
+        if let Some(attribute) = unit.attributes.last() {
+            return Err(format!("missing value for {}", attribute.at.name_or_hex()));
+        }
+        let result = match unit.type_unit {
+            Some((type_signature, type_offset)) => {
+                unit.common.offset = self.debug_types.len();
+                unit.common.data = &unit.data;
+                let unit = TypeUnit {
+                    common: unit.common,
+                    type_signature: type_signature,
+                    type_offset: type_offset,
+                };
+                unit.write(&mut self.debug_types)
+            }
+            None => {
+                unit.common.offset = self.debug_info.len();
+                unit.common.data = &unit.data;
+                let unit = CompilationUnit { common: unit.common };
+                unit.write(&mut self.debug_info)
+            }
+        };
+        result.map_err(|e| format!("{:?}", e))
+    }
+
+    fn entry(&mut self, tokens: &[Token]) -> Result<(), String> {
+        let code = try!(number(tokens, 1));
+        let tag = try!(parse_constant(tokens, 2, tag_value)).map(constant::DwTag);
+        try!(expect_len(tokens, 3));
+        let unit = try!(current_entry(&mut self.unit));
+        let abbrev = match self.abbrev_hashes[&unit.abbrev_offset].get(code) {
+            Some(abbrev) => abbrev,
+            None => return Err(format!("missing abbrev {}", code)),
+        };
+        if tag != Some(abbrev.tag) {
+            return Err(format!("abbrev {} has tag {}", code, abbrev.tag.name_or_hex()));
+        }
+        try!(leb128::write_u64(&mut unit.data, code).map_err(|e| e.to_string()));
+        // Reversed so that the next attribute can be popped.
+        unit.attributes = abbrev.attributes
+            .iter()
+            .rev()
+            .cloned()
+            .collect();
+        Ok(())
+    }
+
+    fn value(&mut self, tokens: &[Token]) -> Result<(), String> {
+        let unit = match self.unit.as_mut() {
+            Some(unit) => unit,
+            None => return Err("attribute must follow die".to_string()),
+        };
+        let attribute = match unit.attributes.pop() {
+            Some(attribute) => attribute,
+            None => return Err("unexpected attribute".to_string()),
+        };
+        let at = try!(parse_constant(tokens, 0, at_value)).map(constant::DwAt);
+        if at != Some(attribute.at) {
+            return Err(format!("expected value for {}", attribute.at.name_or_hex()));
+        }
+
+        let bytes;
+        let data = match attribute.form {
+            constant::DW_FORM_block1 |
+            constant::DW_FORM_block2 |
+            constant::DW_FORM_block4 |
+            constant::DW_FORM_block |
+            constant::DW_FORM_exprloc => {
+                bytes = try!(tokens[1..].iter().map(hex_byte).collect::<Result<Vec<_>, _>>());
+                if attribute.form == constant::DW_FORM_exprloc {
+                    AttributeData::ExprLoc(&bytes)
+                } else {
+                    AttributeData::Block(&bytes)
+                }
+            }
+            constant::DW_FORM_string => {
+                try!(expect_len(tokens, 2));
+                match tokens[1] {
+                    Token::String(ref val) => AttributeData::String(val),
+                    Token::Word(_) => return Err("expected string".to_string()),
+                }
+            }
+            constant::DW_FORM_flag |
+            constant::DW_FORM_flag_present => {
+                try!(expect_len(tokens, 2));
+                match try!(word(tokens, 1)) {
+                    "true" => AttributeData::Flag(true),
+                    "false" if attribute.form == constant::DW_FORM_flag => {
+                        AttributeData::Flag(false)
+                    }
+                    _ => return Err("expected true or false".to_string()),
+                }
+            }
+            constant::DW_FORM_sdata => {
+                try!(expect_len(tokens, 2));
+                AttributeData::SData(try!(signed_number(tokens, 1)))
+            }
+            constant::DW_FORM_implicit_const => {
+                try!(expect_len(tokens, 2));
+                let val = try!(signed_number(tokens, 1));
+                if val != attribute.implicit_const {
+                    return Err(format!("abbrev has value {}", attribute.implicit_const));
+                }
+                AttributeData::SData(val)
+            }
+            constant::DW_FORM_data16 => {
+                bytes = try!(tokens[1..].iter().map(hex_byte).collect::<Result<Vec<_>, _>>());
+                if bytes.len() != 16 {
+                    return Err("expected 16 bytes".to_string());
+                }
+                AttributeData::Data16(&bytes)
+            }
+            form => {
+                try!(expect_len(tokens, 2));
+                let max = match form {
+                    constant::DW_FORM_data1 => 0xff,
+                    constant::DW_FORM_data2 => 0xffff,
+                    constant::DW_FORM_data4 => 0xffff_ffff,
+                    _ => u64::max_value(),
+                };
+                let val = try!(limit(try!(number(tokens, 1)), max, "value"));
+                match form {
+                    constant::DW_FORM_addr => AttributeData::Address(val),
+                    constant::DW_FORM_data1 => AttributeData::Data1(val as u8),
+                    constant::DW_FORM_data2 => AttributeData::Data2(val as u16),
+                    constant::DW_FORM_data4 => AttributeData::Data4(val as u32),
+                    constant::DW_FORM_data8 => AttributeData::Data8(val),
+                    constant::DW_FORM_udata => AttributeData::UData(val),
+                    constant::DW_FORM_strp => AttributeData::StringOffset(val),
+                    constant::DW_FORM_line_strp => AttributeData::LineStringOffset(val),
+                    constant::DW_FORM_ref1 |
+                    constant::DW_FORM_ref2 |
+                    constant::DW_FORM_ref4 |
+                    constant::DW_FORM_ref8 |
+                    constant::DW_FORM_ref_udata => AttributeData::Ref(UnitOffset(val)),
+                    constant::DW_FORM_ref_addr => AttributeData::RefAddress(DebugInfoOffset(val)),
+                    constant::DW_FORM_ref_sig8 => AttributeData::RefSig(val),
+                    constant::DW_FORM_sec_offset => AttributeData::SecOffset(val),
+                    constant::DW_FORM_addrx |
+                    constant::DW_FORM_addrx1 |
+                    constant::DW_FORM_addrx2 |
+                    constant::DW_FORM_addrx3 |
+                    constant::DW_FORM_addrx4 |
+                    constant::DW_FORM_GNU_addr_index => AttributeData::AddressIndex(val),
+                    constant::DW_FORM_strx |
+                    constant::DW_FORM_strx1 |
+                    constant::DW_FORM_strx2 |
+                    constant::DW_FORM_strx3 |
+                    constant::DW_FORM_strx4 |
+                    constant::DW_FORM_GNU_str_index => AttributeData::StringIndex(val),
+                    constant::DW_FORM_rnglistx => AttributeData::RangeListIndex(val),
+                    constant::DW_FORM_loclistx => AttributeData::LocationListIndex(val),
+                    _ => return Err(format!("unsupported form {}", form.name_or_hex())),
+                }
+            }
+        };
+        data.write(&mut unit.data, &unit.common, attribute.form, false)
+            .map_err(|e| format!("{:?}", e))
+    }
+}
+
+// Return the current unit, after checking that the current entry is
+// complete.
+fn current_entry<'a, 'b, E: Endian>(
+    unit: &'b mut Option<AssemblerUnit<'a, E>>
+) -> Result<&'b mut AssemblerUnit<'a, E>, String> {
+    let unit = match unit.as_mut() {
+        Some(unit) => unit,
+        None => return Err("entry must follow unit".to_string()),
+    };
+    if let Some(attribute) = unit.attributes.last() {
+        return Err(format!("missing value for {}", attribute.at.name_or_hex()));
+    }
+    Ok(unit)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token<'a> {
+    Word(&'a str),
+    String(Vec<u8>),
+}
+
+fn tokenize<'a>(line: &'a str) -> Result<Vec<Token<'a>>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() && !rest.starts_with('#') {
+        if rest.starts_with('"') {
+            let mut val = Vec::new();
+            let mut chars = rest[1..].char_indices();
+            let end;
+            loop {
+                match chars.next() {
+                    Some((i, '"')) => {
+                        end = i + 2;
+                        break;
+                    }
+                    Some((_, '\\')) => {
+                        match chars.next() {
+                            Some((_, 'x')) => {
+                                let hex: String = chars.by_ref().take(2).map(|(_, c)| c).collect();
+                                match u8::from_str_radix(&hex, 16) {
+                                    Ok(byte) if hex.len() == 2 => val.push(byte),
+                                    _ => return Err(format!("invalid escape \\x{}", hex)),
+                                }
+                            }
+                            Some((_, c)) if c == '"' || c == '\\' => val.push(c as u8),
+                            _ => return Err("invalid escape".to_string()),
+                        }
+                    }
+                    Some((_, c)) => {
+                        let mut buf = [0; 4];
+                        val.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                    }
+                    None => return Err("unterminated string".to_string()),
+                }
+            }
+            tokens.push(Token::String(val));
+            rest = &rest[end..];
+        } else {
+            let end = rest.find(|c: char| c.is_whitespace()).unwrap_or(rest.len());
+            tokens.push(Token::Word(&rest[..end]));
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+fn expect_len(tokens: &[Token], len: usize) -> Result<(), String> {
+    if tokens.len() != len {
+        return Err(format!("expected {} fields, found {}", len, tokens.len()));
+    }
+    Ok(())
+}
+
+fn word<'a>(tokens: &[Token<'a>], index: usize) -> Result<&'a str, String> {
+    match tokens.get(index) {
+        Some(&Token::Word(word)) => Ok(word),
+        Some(&Token::String(_)) => Err("unexpected string".to_string()),
+        None => Err("missing field".to_string()),
+    }
+}
+
+fn parse_number(word: &str) -> Option<u64> {
+    if let Some(hex) = word.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16).ok()
+    } else {
+        word.parse().ok()
+    }
+}
+
+fn number(tokens: &[Token], index: usize) -> Result<u64, String> {
+    let word = try!(word(tokens, index));
+    parse_number(word).ok_or_else(|| format!("invalid number {}", word))
+}
+
+fn signed_number(tokens: &[Token], index: usize) -> Result<i64, String> {
+    let word = try!(word(tokens, index));
+    let val = if let Some(magnitude) = word.strip_prefix('-') {
+        // The magnitude of i64::MIN doesn't fit in an i64.
+        parse_number(magnitude)
+            .and_then(|val| if val <= 1 << 63 { Some((val as i64).wrapping_neg()) } else { None })
+    } else {
+        parse_number(word).and_then(|val| if val <= i64::max_value() as u64 {
+            Some(val as i64)
+        } else {
+            None
+        })
+    };
+    val.ok_or_else(|| format!("invalid number {}", word))
+}
+
+// Return an error if a number is larger than `max`.
+fn limit(val: u64, max: u64, name: &str) -> Result<u64, String> {
+    if val > max {
+        return Err(format!("invalid {} {}", name, val));
+    }
+    Ok(val)
+}
+
+fn hex_byte(token: &Token) -> Result<u8, String> {
+    match *token {
+        Token::Word(word) if word.len() == 2 => {
+            u8::from_str_radix(word, 16).map_err(|_| format!("invalid byte {}", word))
+        }
+        _ => Err(format!("invalid byte {:?}", token)),
+    }
+}
+
+fn tag_value(name: &str) -> Option<u16> {
+    constant::DwTag::from_name(name).map(|tag| tag.0)
+}
+
+fn at_value(name: &str) -> Option<u16> {
+    constant::DwAt::from_name(name).map(|at| at.0)
+}
+
+fn form_value(name: &str) -> Option<u16> {
+    constant::DwForm::from_name(name).map(|form| form.0)
+}
+
+// Parse a constant from its name, or from its value for unknown constants.
+fn parse_constant(
+    tokens: &[Token],
+    index: usize,
+    from_name: fn(&str) -> Option<u16>
+) -> Result<Option<u16>, String> {
+    let word = try!(word(tokens, index));
+    match from_name(word) {
+        Some(val) => Ok(Some(val)),
+        None => {
+            match parse_number(word) {
+                Some(val) => Ok(Some(try!(limit(val, 0xffff, "constant")) as u16)),
+                None => Ok(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use constant::*;
+    use endian::*;
+
+    #[test]
+    fn assemble_and_dump() {
+        let text = "\
+abbrev_table 0x0
+abbrev 1 DW_TAG_compile_unit children
+  attr DW_AT_name DW_FORM_string
+  attr DW_AT_low_pc DW_FORM_addr
+abbrev 2 DW_TAG_variable
+  attr DW_AT_type DW_FORM_ref4
+  attr DW_AT_external DW_FORM_flag_present
+  attr DW_AT_location DW_FORM_exprloc
+  attr DW_AT_const_value DW_FORM_sdata
+
+unit version 4 address_size 8 offset_size 4 abbrev_table 0x0
+die 1 DW_TAG_compile_unit # 0xb
+  DW_AT_name \"a \\\"b\\\"\\x01\"
+  DW_AT_low_pc 0x1000
+  die 2 DW_TAG_variable # 0x1b
+    DW_AT_type 0x1b
+    DW_AT_external true
+    DW_AT_location 91 7f
+    DW_AT_const_value -2
+  null
+";
+        let mut sections = Sections::new(LittleEndian);
+        assemble(&mut sections, text).unwrap();
+
+        let mut units = sections.compilation_units();
+        let unit = units.next().unwrap().unwrap();
+        assert!(units.next().unwrap().is_none());
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let mut entries = unit.entries(&abbrev);
+        let entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.tag, DW_TAG_compile_unit);
+        assert_eq!(entry.attr(DW_AT_name), Some(&AttributeData::String(b"a \"b\"\x01")));
+        let entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.offset, 0x1b);
+        assert_eq!(entry.attr(DW_AT_location), Some(&AttributeData::ExprLoc(&[0x91, 0x7f])));
+        assert_eq!(entry.attr(DW_AT_const_value), Some(&AttributeData::SData(-2)));
+        assert!(entries.next().unwrap().unwrap().is_null());
+        assert!(entries.next().unwrap().is_none());
+
+        let mut buf = Vec::new();
+        dump(&mut buf, &sections).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), text);
+    }
+
+    #[test]
+    fn assemble_and_dump_v5() {
+        let text = "\
+abbrev_table 0x0
+abbrev 1 DW_TAG_compile_unit children
+  attr DW_AT_name DW_FORM_strx1
+abbrev 2 DW_TAG_variable
+  attr DW_AT_decl_file DW_FORM_implicit_const -3
+  attr DW_AT_const_value DW_FORM_data16
+
+unit version 5 address_size 8 offset_size 4 abbrev_table 0x0 unit_type 1
+die 1 DW_TAG_compile_unit # 0xc
+  DW_AT_name 0x2
+  die 2 DW_TAG_variable # 0xe
+    DW_AT_decl_file -3
+    DW_AT_const_value 00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f
+  null
+";
+        let mut sections = Sections::new(LittleEndian);
+        assemble(&mut sections, text).unwrap();
+
+        let mut units = sections.compilation_units();
+        let unit = units.next().unwrap().unwrap();
+        assert_eq!(unit.common.unit_type, DW_UT_compile);
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let mut entries = unit.entries(&abbrev);
+        let entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.attr(DW_AT_name), Some(&AttributeData::StringIndex(2)));
+        let entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.attr(DW_AT_decl_file), Some(&AttributeData::SData(-3)));
+
+        let mut buf = Vec::new();
+        dump(&mut buf, &sections).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), text);
+
+        let text = text.replace("DW_AT_decl_file -3", "DW_AT_decl_file 3");
+        assert!(assemble(&mut sections, &text).is_err());
+    }
+
+    #[test]
+    fn assemble_errors() {
+        let header = "\
+abbrev_table 0
+abbrev 1 DW_TAG_base_type
+  attr DW_AT_byte_size DW_FORM_data1
+unit version 4 address_size 8 offset_size 4 abbrev_table 0
+";
+        for &(text, line) in &[
+            ("die 2 DW_TAG_base_type\n", 5),
+            ("die 1 DW_TAG_variable\n", 5),
+            ("die 1 DW_TAG_base_type\nDW_AT_name 4\n", 6),
+            ("die 1 DW_TAG_base_type\nnull\n", 6),
+            ("die 1 DW_TAG_base_type\nDW_AT_byte_size \"4\"\n", 6),
+            ("die 1 DW_TAG_base_type\nDW_AT_byte_size 4 5\n", 6),
+            ("die 1 DW_TAG_base_type\nDW_AT_byte_size 256\n", 6),
+            ("die 1 0x10024\n", 5),
+        ] {
+            let mut sections = Sections::new(LittleEndian);
+            match assemble(&mut sections, &format!("{}{}", header, text)) {
+                Err(WriteError::Invalid(message)) => {
+                    assert!(message.starts_with(&format!("line {}:", line)), "{}", message);
+                }
+                result => panic!("{}: {:?}", text, result),
+            }
+        }
+
+        let mut sections = Sections::new(LittleEndian);
+        assert!(assemble(&mut sections, "unit version 4 abbrev_table 0\n").is_err());
+        let text = header.replace("address_size 8", "address_size 0x108");
+        assert!(assemble(&mut sections, &text).is_err());
+        let text = header.replace("DW_FORM_data1", "DW_FORM_sdata");
+        assert!(assemble(&mut sections, &format!("{}die 1 DW_TAG_base_type\nDW_AT_byte_size \
+                                                   -9223372036854775808\nnull\n",
+                                                  text))
+            .is_ok());
+        assert!(assemble(&mut sections, &format!("{}die 1 DW_TAG_base_type\nDW_AT_byte_size \
+                                                   9223372036854775808\nnull\n",
+                                                  text))
+            .is_err());
+        assert!(assemble(&mut sections, "abbrev_table 0\nattr DW_AT_name DW_FORM_string\n")
+            .is_err());
+        assert!(assemble(&mut sections, &format!("{}die 1 DW_TAG_base_type\n", header))
+            .is_err());
+    }
+
+    #[test]
+    fn tokenize_line() {
+        assert_eq!(tokenize("  a 0x1 \"b c\\x00\" # d").unwrap(),
+                   vec![Token::Word("a"), Token::Word("0x1"), Token::String(b"b c\0".to_vec())]);
+        assert_eq!(tokenize("# a").unwrap(), vec![]);
+        assert!(tokenize("\"a").is_err());
+        assert!(tokenize("\"\\x0\"").is_err());
+    }
+}
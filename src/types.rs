@@ -0,0 +1,1834 @@
+use std::fmt;
+
+use constant;
+use die::*;
+use endian::Endian;
+use expr::*;
+use read::ReadError;
+use unit::UnitCommon;
+
+// A DW_AT_const_value, interpreted using the type of the entry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstValue<'data> {
+    Signed(i64),
+    Unsigned(u64),
+    Bool(bool),
+    Float(f64),
+    String(&'data [u8]),
+    // A value that couldn't be interpreted as a scalar, such as a structure
+    // constant.
+    Block(&'data [u8]),
+}
+
+// The representation of a scalar type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Signed,
+    Unsigned,
+    Bool,
+    Float,
+}
+
+impl<'data> ConstValue<'data> {
+    // Interpret a DW_AT_const_value attribute.
+    //
+    // `type_die` is the entry referenced by the DW_AT_type attribute of the
+    // constant, after resolving any typedefs and qualifiers. The form of the
+    // attribute only gives the size of the value, so the type is needed to
+    // determine its signedness, and to decode blocks. If there is no type,
+    // then the form is used to choose the signedness.
+    //
+    // Returns `None` if the attribute isn't a constant or string.
+    pub fn new<E: Endian>(
+        data: &AttributeData<'data>,
+        type_die: Option<&Die<'data>>,
+        endian: E,
+        context: &AttributeContext<'data>
+    ) -> Option<ConstValue<'data>> {
+        let encoding = type_die.and_then(type_encoding);
+        let byte_size = type_die.and_then(|die| die.attr(constant::DW_AT_byte_size))
+            .and_then(AttributeData::as_udata);
+        let (value, size) = match *data {
+            AttributeData::Data1(val) => (val as u64, 1),
+            AttributeData::Data2(val) => (val as u64, 2),
+            AttributeData::Data4(val) => (val as u64, 4),
+            AttributeData::Data8(val) => (val, 8),
+            AttributeData::UData(val) => (val, byte_size.unwrap_or(8)),
+            AttributeData::SData(val) => {
+                let size = byte_size.unwrap_or(8);
+                match encoding {
+                    None | Some(Encoding::Signed) => return Some(ConstValue::Signed(val)),
+                    Some(encoding) => return Some(scalar(val as u64, size, encoding)),
+                }
+            }
+            AttributeData::Block(val) |
+            AttributeData::ExprLoc(val) => {
+                if encoding.is_none() ||
+                   byte_size.is_some_and(|size| size != val.len() as u64) {
+                    return Some(ConstValue::Block(val));
+                }
+                let mut r = val;
+                let value = match val.len() {
+                    1 => val[0] as u64,
+                    2 => endian.read_u16(&mut r).unwrap() as u64,
+                    4 => endian.read_u32(&mut r).unwrap() as u64,
+                    8 => endian.read_u64(&mut r).unwrap(),
+                    _ => return Some(ConstValue::Block(val)),
+                };
+                (value, val.len() as u64)
+            }
+            AttributeData::String(_) |
+            AttributeData::StringOffset(_) |
+            AttributeData::LineStringOffset(_) => {
+                return data.as_string_in(context).map(ConstValue::String);
+            }
+            _ => return None,
+        };
+        Some(scalar(value, size, encoding.unwrap_or(Encoding::Unsigned)))
+    }
+
+    // Return the value as a signed integer, if it is an integer.
+    //
+    // Unsigned values are reinterpreted, so that they can be compared with
+    // the values of enumerators regardless of how each was encoded.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            ConstValue::Signed(val) => Some(val),
+            ConstValue::Unsigned(val) => Some(val as i64),
+            ConstValue::Bool(val) => Some(val as i64),
+            _ => None,
+        }
+    }
+}
+
+// Build a scalar value from the low `size` bytes of `value`.
+fn scalar<'data>(value: u64, size: u64, encoding: Encoding) -> ConstValue<'data> {
+    let bits = if size == 0 || size >= 8 { 64 } else { size * 8 };
+    let value = if bits == 64 {
+        value
+    } else {
+        value & ((1 << bits) - 1)
+    };
+    match encoding {
+        Encoding::Signed => {
+            let shift = 64 - bits;
+            ConstValue::Signed(((value << shift) as i64) >> shift)
+        }
+        Encoding::Unsigned => ConstValue::Unsigned(value),
+        Encoding::Bool => ConstValue::Bool(value != 0),
+        Encoding::Float => {
+            match bits {
+                32 => ConstValue::Float(f32::from_bits(value as u32) as f64),
+                64 => ConstValue::Float(f64::from_bits(value)),
+                _ => ConstValue::Unsigned(value),
+            }
+        }
+    }
+}
+
+// Return the encoding of a base or enumeration type.
+fn type_encoding(die: &Die) -> Option<Encoding> {
+    match die.tag {
+        constant::DW_TAG_base_type => {
+            let encoding = match die.attr(constant::DW_AT_encoding)
+                .and_then(AttributeData::as_udata) {
+                Some(encoding) => constant::DwAte(encoding as u8),
+                None => return None,
+            };
+            match encoding {
+                constant::DW_ATE_signed |
+                constant::DW_ATE_signed_char |
+                constant::DW_ATE_signed_fixed => Some(Encoding::Signed),
+                constant::DW_ATE_unsigned |
+                constant::DW_ATE_unsigned_char |
+                constant::DW_ATE_unsigned_fixed |
+                constant::DW_ATE_address |
+                constant::DW_ATE_UTF |
+                constant::DW_ATE_UCS |
+                constant::DW_ATE_ASCII => Some(Encoding::Unsigned),
+                constant::DW_ATE_boolean => Some(Encoding::Bool),
+                constant::DW_ATE_float => Some(Encoding::Float),
+                _ => None,
+            }
+        }
+        // The underlying type of an enumeration is usually signed, but
+        // producers may give it with DW_AT_encoding.
+        constant::DW_TAG_enumeration_type => {
+            match die.attr(constant::DW_AT_encoding).and_then(AttributeData::as_udata) {
+                Some(encoding) if constant::DwAte(encoding as u8) == constant::DW_ATE_unsigned ||
+                                  constant::DwAte(encoding as u8) ==
+                                  constant::DW_ATE_unsigned_char => Some(Encoding::Unsigned),
+                _ => Some(Encoding::Signed),
+            }
+        }
+        _ => None,
+    }
+}
+
+impl<'data> fmt::Display for ConstValue<'data> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConstValue::Signed(val) => write!(f, "{}", val),
+            ConstValue::Unsigned(val) => write!(f, "{}", val),
+            ConstValue::Bool(val) => write!(f, "{}", val),
+            ConstValue::Float(val) => write!(f, "{}", val),
+            ConstValue::String(val) => write!(f, "\"{}\"", String::from_utf8_lossy(val)),
+            ConstValue::Block(val) => {
+                try!(write!(f, "["));
+                for (i, byte) in val.iter().enumerate() {
+                    if i != 0 {
+                        try!(write!(f, " "));
+                    }
+                    try!(write!(f, "{:02x}", byte));
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+// An enumerator of an enumeration type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Enumerator<'data> {
+    pub name: &'data [u8],
+    pub value: ConstValue<'data>,
+}
+
+impl<'data> ConstValue<'data> {
+    // Render the value, using the name of the matching enumerator if there
+    // is one.
+    //
+    // `enumerators` should be the enumerators of the type that was given to
+    // `ConstValue::new`, or empty if it isn't an enumeration type.
+    pub fn render(&self, enumerators: &[Enumerator]) -> String {
+        if let Some(value) = self.as_i64() {
+            for enumerator in enumerators {
+                if enumerator.value.as_i64() == Some(value) {
+                    return String::from_utf8_lossy(enumerator.name).into_owned();
+                }
+            }
+        }
+        self.to_string()
+    }
+}
+
+// A DW_TAG_enumeration_type entry and its enumerators.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumerationType<'data> {
+    pub offset: usize,
+    pub name: Option<&'data [u8]>,
+    // True for a C++ `enum class`, whose enumerators are scoped by the type.
+    pub enum_class: bool,
+    enumerators: Vec<Enumerator<'data>>,
+}
+
+impl<'data> EnumerationType<'data> {
+    // Read an enumeration type and its enumerators.
+    //
+    // `entries` must start at the enumeration type entry, such as the
+    // iterator returned by `CompilationUnit::entry`. The enumerator values
+    // are interpreted using the enumeration type, so negative values are
+    // handled correctly when they are encoded with a fixed size form.
+    //
+    // Returns `None` if the entry isn't an enumeration type.
+    pub fn read<'a, E: Endian>(
+        mut entries: DieIterator<'a, 'data, E>,
+        context: &AttributeContext<'data>
+    ) -> Result<Option<EnumerationType<'data>>, ReadError> {
+        let endian = entries.unit().endian;
+        let die = match try!(entries.next()) {
+            Some(die) if die.tag == constant::DW_TAG_enumeration_type => die.clone(),
+            _ => return Ok(None),
+        };
+        let mut enumerators = Vec::new();
+        if die.children {
+            let mut child = try!(entries.next());
+            while let Some(entry) = child {
+                if entry.is_null() {
+                    break;
+                }
+                if entry.tag == constant::DW_TAG_enumerator {
+                    let name = entry.attr(constant::DW_AT_name)
+                        .and_then(|name| name.as_string_in(context));
+                    let value = entry.attr(constant::DW_AT_const_value)
+                        .and_then(|value| ConstValue::new(value, Some(&die), endian, context));
+                    if let (Some(name), Some(value)) = (name, value) {
+                        enumerators.push(Enumerator {
+                            name: name,
+                            value: value,
+                        });
+                    }
+                }
+                child = try!(entries.next_sibling());
+            }
+        }
+        Ok(Some(EnumerationType {
+            offset: die.offset,
+            name: die.attr(constant::DW_AT_name).and_then(|name| name.as_string_in(context)),
+            enum_class: die.attr(constant::DW_AT_enum_class) == Some(&AttributeData::Flag(true)),
+            enumerators: enumerators,
+        }))
+    }
+
+    // The enumerators, in the order that they were declared.
+    pub fn enumerators(&self) -> &[Enumerator<'data>] {
+        &self.enumerators
+    }
+
+    // Return the name of the first enumerator with the value.
+    //
+    // Values are compared as signed integers, so `-1` matches an enumerator
+    // of an unsigned type with all bits set.
+    pub fn name_for_value(&self, value: i64) -> Option<&'data [u8]> {
+        self.enumerators
+            .iter()
+            .find(|enumerator| enumerator.value.as_i64() == Some(value))
+            .map(|enumerator| enumerator.name)
+    }
+
+    // Render a value of this type.
+    //
+    // The enumerator name is used if there is one, and is qualified with the
+    // type name for an `enum class`.
+    pub fn render(&self, value: &ConstValue) -> String {
+        let name = match value.as_i64().and_then(|value| self.name_for_value(value)) {
+            Some(name) => name,
+            None => return value.to_string(),
+        };
+        let name = String::from_utf8_lossy(name);
+        match self.name {
+            Some(type_name) if self.enum_class => {
+                format!("{}::{}", String::from_utf8_lossy(type_name), name)
+            }
+            _ => name.into_owned(),
+        }
+    }
+}
+
+// The value of an array bound or count attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound<'data> {
+    Constant(i64),
+    // A DWARF expression that computes the value at run time.
+    Expression(&'data [u8]),
+    // The section offset of an entry, such as a variable, that holds the
+    // value at run time.
+    Reference(usize),
+}
+
+impl<'data> Bound<'data> {
+    fn new<E: Endian>(data: &AttributeData<'data>, unit: &UnitCommon<E>) -> Option<Bound<'data>> {
+        match *data {
+            AttributeData::SData(val) => Some(Bound::Constant(val)),
+            AttributeData::Block(val) |
+            AttributeData::ExprLoc(val) => Some(Bound::Expression(val)),
+            AttributeData::Ref(_) |
+            AttributeData::RefAddress(_) => reference(data, unit).map(Bound::Reference),
+            _ => data.as_udata().map(|val| Bound::Constant(val as i64)),
+        }
+    }
+
+    pub fn constant(&self) -> Option<i64> {
+        match *self {
+            Bound::Constant(val) => Some(val),
+            _ => None,
+        }
+    }
+}
+
+// A dimension of an array type, from a DW_TAG_subrange_type entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dimension<'data> {
+    pub lower_bound: Option<Bound<'data>>,
+    pub upper_bound: Option<Bound<'data>>,
+    pub count: Option<Bound<'data>>,
+}
+
+impl<'data> Dimension<'data> {
+    // Return the number of elements, if it is known statically.
+    //
+    // `default_lower_bound` is used if there is no DW_AT_lower_bound. This
+    // depends on the language: it is 0 for C-like languages and 1 for
+    // Fortran. An upper bound that is less than the lower bound gives a
+    // count of 0, as for C flexible array members. Returns `None` if
+    // computing the count from the bounds overflows.
+    pub fn len(&self, default_lower_bound: i64) -> Option<u64> {
+        if let Some(count) = self.count {
+            return count.constant().map(|count| if count < 0 { 0 } else { count as u64 });
+        }
+        let lower = match self.lower_bound {
+            Some(bound) => bound.constant(),
+            None => Some(default_lower_bound),
+        };
+        let upper = self.upper_bound.and_then(|bound| bound.constant());
+        let (lower, upper) = match (lower, upper) {
+            (Some(lower), Some(upper)) => (lower, upper),
+            _ => return None,
+        };
+        if upper < lower {
+            return Some(0);
+        }
+        upper.checked_sub(lower).and_then(|len| (len as u64).checked_add(1))
+    }
+}
+
+// Return the lower bound of an array dimension that has no
+// DW_AT_lower_bound, for the DW_AT_language of its unit.
+//
+// This is 1 for Ada, COBOL, Fortran, Julia, Modula-2, Modula-3, Pascal and
+// PL/I, and 0 for other languages.
+pub fn default_lower_bound(language: constant::DwLang) -> i64 {
+    match language {
+        constant::DW_LANG_Ada83 |
+        constant::DW_LANG_Ada95 |
+        constant::DW_LANG_Cobol74 |
+        constant::DW_LANG_Cobol85 |
+        constant::DW_LANG_Fortran77 |
+        constant::DW_LANG_Fortran90 |
+        constant::DW_LANG_Fortran95 |
+        constant::DW_LANG_Fortran03 |
+        constant::DW_LANG_Fortran08 |
+        constant::DW_LANG_Julia |
+        constant::DW_LANG_Modula2 |
+        constant::DW_LANG_Modula3 |
+        constant::DW_LANG_Pascal83 |
+        constant::DW_LANG_PLI => 1,
+        _ => 0,
+    }
+}
+
+// A DW_TAG_array_type entry and its dimensions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArrayType<'data> {
+    pub offset: usize,
+    // The section offset of the element type.
+    pub element_type: Option<usize>,
+    // The dimensions, outermost first.
+    pub dimensions: Vec<Dimension<'data>>,
+}
+
+impl<'data> ArrayType<'data> {
+    // Read an array type and its dimensions.
+    //
+    // `entries` must start at the array type entry, such as the iterator
+    // returned by `CompilationUnit::entry`. Children other than
+    // DW_TAG_subrange_type, such as the enumeration types used for Ada
+    // index types, are ignored.
+    //
+    // Returns `None` if the entry isn't an array type.
+    pub fn read<'a, E: Endian>(
+        mut entries: DieIterator<'a, 'data, E>
+    ) -> Result<Option<ArrayType<'data>>, ReadError> {
+        let unit = entries.unit();
+        let (offset, element_type, children) = match try!(entries.next()) {
+            Some(die) if die.tag == constant::DW_TAG_array_type => {
+                let element_type = die.attr(constant::DW_AT_type)
+                    .and_then(|data| reference(data, unit));
+                (die.offset, element_type, die.children)
+            }
+            _ => return Ok(None),
+        };
+        let mut dimensions = Vec::new();
+        if children {
+            let mut child = try!(entries.next());
+            while let Some(entry) = child {
+                if entry.is_null() {
+                    break;
+                }
+                if entry.tag == constant::DW_TAG_subrange_type {
+                    let bound = |at| entry.attr(at).and_then(|data| Bound::new(data, unit));
+                    dimensions.push(Dimension {
+                        lower_bound: bound(constant::DW_AT_lower_bound),
+                        upper_bound: bound(constant::DW_AT_upper_bound),
+                        count: bound(constant::DW_AT_count),
+                    });
+                }
+                child = try!(entries.next_sibling());
+            }
+        }
+        Ok(Some(ArrayType {
+            offset: offset,
+            element_type: element_type,
+            dimensions: dimensions,
+        }))
+    }
+
+    // Return the total number of elements, if all dimensions are static.
+    //
+    // See `Dimension::len` for `default_lower_bound`, which can be found
+    // using the `default_lower_bound` function.
+    pub fn element_count(&self, default_lower_bound: i64) -> Option<u64> {
+        self.dimensions.iter().try_fold(1u64, |count, dimension| {
+            dimension.len(default_lower_bound).and_then(|len| count.checked_mul(len))
+        })
+    }
+}
+
+// Return the size in bytes of a type.
+//
+// This is the DW_AT_byte_size of the type if it has one. Otherwise, the
+// size is found by following typedefs and type qualifiers, and the
+// underlying type of enumerations. Arrays are the size of their elements
+// multiplied by the number of elements, and pointers and references are
+// the address size of the unit.
+//
+// The lower bound of an array dimension defaults to the
+// `default_lower_bound` for the DW_AT_language of the array's unit, which
+// is read using the resolver. Returns `None` if the size isn't known
+// statically, such as for incomplete types and variable length arrays.
+pub fn type_byte_size<'data, R>(
+    die: &Die<'data>,
+    resolver: &mut R
+) -> Result<Option<u64>, ReadError>
+    where R: DieResolver<'data>
+{
+    let mut chain = ReferenceChain::new(die.offset);
+    byte_size(die, resolver, &mut chain)
+}
+
+fn byte_size<'data, R>(
+    die: &Die<'data>,
+    resolver: &mut R,
+    chain: &mut ReferenceChain
+) -> Result<Option<u64>, ReadError>
+    where R: DieResolver<'data>
+{
+    let mut die = die.clone();
+    loop {
+        if let Some(size) = die.attr(constant::DW_AT_byte_size) {
+            return Ok(size.as_udata());
+        }
+        match die.tag {
+            constant::DW_TAG_typedef |
+            constant::DW_TAG_const_type |
+            constant::DW_TAG_volatile_type |
+            constant::DW_TAG_restrict_type |
+            constant::DW_TAG_atomic_type |
+            constant::DW_TAG_enumeration_type => {}
+            constant::DW_TAG_pointer_type |
+            constant::DW_TAG_reference_type |
+            constant::DW_TAG_rvalue_reference_type => {
+                return resolver.address_size(&die).map(|size| Some(size as u64));
+            }
+            constant::DW_TAG_array_type => return array_byte_size(&die, resolver, chain),
+            _ => return Ok(None),
+        }
+        die = match die.attr(constant::DW_AT_type) {
+            Some(data) => try!(resolver.resolve(&die, data)),
+            None => return Ok(None),
+        };
+        try!(chain.visit(die.offset));
+    }
+}
+
+fn array_byte_size<'data, R>(
+    die: &Die<'data>,
+    resolver: &mut R,
+    chain: &mut ReferenceChain
+) -> Result<Option<u64>, ReadError>
+    where R: DieResolver<'data>
+{
+    let element = match die.attr(constant::DW_AT_type) {
+        Some(data) => try!(resolver.resolve(die, data)),
+        None => return Ok(None),
+    };
+    try!(chain.visit(element.offset));
+    let mut size = match try!(byte_size(&element, resolver, chain)) {
+        Some(size) => size,
+        None => return Ok(None),
+    };
+    if !die.children {
+        return Ok(None);
+    }
+    let mut lower_bound = None;
+    for child in try!(resolver.children(die)) {
+        if child.tag != constant::DW_TAG_subrange_type {
+            continue;
+        }
+        let dimension = match static_dimension(&child) {
+            Some(dimension) => dimension,
+            None => return Ok(None),
+        };
+        if dimension.lower_bound.is_none() && lower_bound.is_none() {
+            let language = try!(resolver.language(die));
+            lower_bound = Some(language.map_or(0, default_lower_bound));
+        }
+        let len = match dimension.len(lower_bound.unwrap_or(0)) {
+            Some(len) => len,
+            None => return Ok(None),
+        };
+        size = match size.checked_mul(len) {
+            Some(size) => size,
+            None => return Err(ReadError::Overflow),
+        };
+    }
+    Ok(Some(size))
+}
+
+// Return the dimension of a DW_TAG_subrange_type entry, or `None` if any
+// of its bounds aren't constants.
+fn static_dimension<'data>(die: &Die<'data>) -> Option<Dimension<'data>> {
+    let mut dynamic = false;
+    let mut bound = |at| {
+        match die.attr(at) {
+            Some(&AttributeData::SData(val)) => Some(Bound::Constant(val)),
+            Some(data) => {
+                let bound = data.as_udata().map(|val| Bound::Constant(val as i64));
+                dynamic |= bound.is_none();
+                bound
+            }
+            None => None,
+        }
+    };
+    let dimension = Dimension {
+        lower_bound: bound(constant::DW_AT_lower_bound),
+        upper_bound: bound(constant::DW_AT_upper_bound),
+        count: bound(constant::DW_AT_count),
+    };
+    if dynamic { None } else { Some(dimension) }
+}
+
+// Return the section offset of the entry referenced by an attribute.
+fn reference<E: Endian>(data: &AttributeData, unit: &UnitCommon<E>) -> Option<usize> {
+    match *data {
+        AttributeData::Ref(offset) => Some(offset.to_section_offset(unit)),
+        AttributeData::RefAddress(offset) => Some(offset.0 as usize),
+        _ => None,
+    }
+}
+
+// A member function of a class, structure or union type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberFunction<'data> {
+    // The section offset of the DW_TAG_subprogram entry within the class.
+    pub offset: usize,
+    pub name: Option<&'data [u8]>,
+    pub linkage_name: Option<&'data [u8]>,
+    pub virtuality: constant::DwVirtuality,
+    // The index of the function in the virtual function table, decoded from
+    // DW_AT_vtable_elem_location.
+    pub vtable_index: Option<u64>,
+    // The accessibility, after applying the default for the kind of type.
+    pub accessibility: constant::DwAccess,
+    // True if the entry within the class is only a declaration.
+    pub declaration: bool,
+    // True for compiler generated functions, such as implicit constructors.
+    pub artificial: bool,
+    // The section offsets of the out of line definitions that refer to this
+    // function with DW_AT_specification. These are found by
+    // `ClassType::find_definitions`.
+    pub definitions: Vec<usize>,
+}
+
+impl<'data> MemberFunction<'data> {
+    pub fn is_virtual(&self) -> bool {
+        self.virtuality != constant::DW_VIRTUALITY_none
+    }
+}
+
+// A class, structure or union type and its member functions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassType<'data> {
+    pub offset: usize,
+    pub tag: constant::DwTag,
+    pub name: Option<&'data [u8]>,
+    // The section offset of the type that contains the virtual function
+    // table pointer, from DW_AT_containing_type.
+    pub containing_type: Option<usize>,
+    pub member_functions: Vec<MemberFunction<'data>>,
+}
+
+impl<'data> ClassType<'data> {
+    // Read a class type and its member functions.
+    //
+    // `entries` must start at the type entry, such as the iterator returned
+    // by `CompilationUnit::entry`. Only direct children are read, so the
+    // member functions of nested types aren't included.
+    //
+    // Returns `None` if the entry isn't a class, structure or union type.
+    pub fn read<'a, E: Endian>(
+        mut entries: DieIterator<'a, 'data, E>,
+        context: &AttributeContext<'data>
+    ) -> Result<Option<ClassType<'data>>, ReadError> {
+        let unit = entries.unit();
+        let string = |die: &Die<'data>, at| {
+            die.attr(at).and_then(|name| name.as_string_in(context))
+        };
+        let (mut class, children) = match try!(entries.next()) {
+            Some(die) if die.tag == constant::DW_TAG_class_type ||
+                         die.tag == constant::DW_TAG_structure_type ||
+                         die.tag == constant::DW_TAG_union_type => {
+                let class = ClassType {
+                    offset: die.offset,
+                    tag: die.tag,
+                    name: string(die, constant::DW_AT_name),
+                    containing_type: die.attr(constant::DW_AT_containing_type)
+                        .and_then(|data| reference(data, unit)),
+                    member_functions: Vec::new(),
+                };
+                (class, die.children)
+            }
+            _ => return Ok(None),
+        };
+        if !children {
+            return Ok(Some(class));
+        }
+        // Members of a class are private by default, and members of a
+        // structure or union are public.
+        let default_accessibility = if class.tag == constant::DW_TAG_class_type {
+            constant::DW_ACCESS_private
+        } else {
+            constant::DW_ACCESS_public
+        };
+        let mut child = try!(entries.next());
+        while let Some(entry) = child {
+            if entry.is_null() {
+                break;
+            }
+            if entry.tag == constant::DW_TAG_subprogram {
+                let flag = |at| entry.attr(at) == Some(&AttributeData::Flag(true));
+                let linkage_name = match string(entry, constant::DW_AT_linkage_name) {
+                    Some(name) => Some(name),
+                    None => string(entry, constant::DW_AT_MIPS_linkage_name),
+                };
+                class.member_functions.push(MemberFunction {
+                    offset: entry.offset,
+                    name: string(entry, constant::DW_AT_name),
+                    linkage_name: linkage_name,
+                    virtuality: entry.attr(constant::DW_AT_virtuality)
+                        .and_then(AttributeData::as_udata)
+                        .map(|val| constant::DwVirtuality(val as u8))
+                        .unwrap_or(constant::DW_VIRTUALITY_none),
+                    vtable_index: entry.attr(constant::DW_AT_vtable_elem_location)
+                        .and_then(|data| vtable_index(data, unit)),
+                    accessibility: entry.attr(constant::DW_AT_accessibility)
+                        .and_then(AttributeData::as_udata)
+                        .map(|val| constant::DwAccess(val as u8))
+                        .unwrap_or(default_accessibility),
+                    declaration: flag(constant::DW_AT_declaration),
+                    artificial: flag(constant::DW_AT_artificial),
+                    definitions: Vec::new(),
+                });
+            }
+            child = try!(entries.next_sibling());
+        }
+        Ok(Some(class))
+    }
+
+    // Find the definitions of the member functions.
+    //
+    // Definitions are usually outside of the class, and refer to the
+    // declaration with DW_AT_specification. `entries` is searched for
+    // these, and would normally be all of the entries of the unit.
+    pub fn find_definitions<'a, E: Endian>(
+        &mut self,
+        mut entries: DieIterator<'a, 'data, E>
+    ) -> Result<(), ReadError> {
+        let unit = entries.unit();
+        while let Some(die) = try!(entries.next()) {
+            if die.tag != constant::DW_TAG_subprogram {
+                continue;
+            }
+            let specification = match die.attr(constant::DW_AT_specification)
+                .and_then(|data| reference(data, unit)) {
+                Some(specification) => specification,
+                None => continue,
+            };
+            for function in &mut self.member_functions {
+                if function.offset == specification && !function.definitions.contains(&die.offset) {
+                    function.definitions.push(die.offset);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Decode a DW_AT_vtable_elem_location.
+//
+// Producers use an expression consisting of a single DW_OP_constu.
+fn vtable_index<E: Endian>(data: &AttributeData, unit: &UnitCommon<E>) -> Option<u64> {
+    let expr = match *data {
+        AttributeData::Block(val) |
+        AttributeData::ExprLoc(val) => val,
+        _ => return data.as_udata(),
+    };
+    let mut ops = ExpressionIterator::new(expr, unit);
+    let index = match ops.next() {
+        Ok(Some(Operation { opcode: constant::DW_OP_constu,
+                            operands: Operands::Unsigned(index),
+                            .. })) => index,
+        _ => return None,
+    };
+    match ops.next() {
+        Ok(None) => Some(index),
+        _ => None,
+    }
+}
+
+// The location of a bit field member of a structure, class or union.
+//
+// `bit_offset` is the number of bits from the start of the containing
+// type to the start of the member, using the same convention as
+// DW_AT_data_bit_offset: for little endian targets, bits are numbered
+// from the least significant bit of the first byte, and for big endian
+// targets, from the most significant bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitField {
+    pub bit_offset: u64,
+    pub bit_size: u64,
+}
+
+impl BitField {
+    // Read the location of a bit field from a DW_TAG_member entry.
+    //
+    // DW_AT_data_bit_offset was added in version 4, and is returned
+    // unchanged. Earlier versions use DW_AT_bit_offset, and GCC before
+    // version 11 still uses it for version 4. This is the number of bits
+    // from the most significant bit of a storage unit at
+    // DW_AT_data_member_location to the most significant bit of the member.
+    // The size of the storage unit is the DW_AT_byte_size of the member, or
+    // the size of its type, which is found using `resolver`.
+    //
+    // DW_AT_bit_offset is negative if the member extends past the end of
+    // the storage unit. Values with a fixed size form are sign extended.
+    //
+    // Returns `None` if the member isn't a bit field, if its location
+    // isn't a constant, or if computing its offset overflows.
+    pub fn read<'data, E, R>(
+        die: &Die<'data>,
+        unit: &UnitCommon<E>,
+        resolver: &mut R
+    ) -> Result<Option<BitField>, ReadError>
+        where E: Endian,
+              R: DieResolver<'data>
+    {
+        let bit_size = match die.attr(constant::DW_AT_bit_size).and_then(AttributeData::as_udata) {
+            Some(bit_size) => bit_size,
+            None => return Ok(None),
+        };
+        if let Some(data) = die.attr(constant::DW_AT_data_bit_offset) {
+            return Ok(data.as_udata().map(|bit_offset| {
+                BitField {
+                    bit_offset: bit_offset,
+                    bit_size: bit_size,
+                }
+            }));
+        }
+
+        let byte_offset = match die.attr(constant::DW_AT_data_member_location) {
+            Some(data) => {
+                match member_offset(data, unit) {
+                    Some(offset) => offset,
+                    None => return Ok(None),
+                }
+            }
+            // Members of unions may omit the location.
+            None => 0,
+        };
+        let msb_offset = match die.attr(constant::DW_AT_bit_offset) {
+            Some(&AttributeData::Data1(val)) => val as i8 as i64,
+            Some(&AttributeData::Data2(val)) => val as i16 as i64,
+            Some(&AttributeData::Data4(val)) => val as i32 as i64,
+            Some(&AttributeData::Data8(val)) => val as i64,
+            Some(&AttributeData::SData(val)) => val,
+            Some(&AttributeData::UData(val)) => val as i64,
+            Some(_) => return Ok(None),
+            None => 0,
+        };
+        let bit_offset = if unit.endian.is_big_endian() {
+            msb_offset
+        } else {
+            // The most significant bit is at the end of the storage unit.
+            let byte_size = match die.attr(constant::DW_AT_byte_size) {
+                Some(data) => data.as_udata(),
+                None => {
+                    match try!(die.attr_resolved(constant::DW_AT_type, resolver)) {
+                        Some(data) => {
+                            let type_die = try!(resolver.resolve(die, &data));
+                            try!(type_byte_size(&type_die, resolver))
+                        }
+                        None => None,
+                    }
+                }
+            };
+            let bit_offset = byte_size.and_then(|byte_size| (byte_size as i64).checked_mul(8))
+                .and_then(|bits| bits.checked_sub(msb_offset))
+                .and_then(|bits| bits.checked_sub(bit_size as i64));
+            match bit_offset {
+                Some(bit_offset) => bit_offset,
+                None => return Ok(None),
+            }
+        };
+        let bit_offset = match (byte_offset as i64)
+            .checked_mul(8)
+            .and_then(|bits| bits.checked_add(bit_offset)) {
+            Some(bit_offset) if bit_offset >= 0 => bit_offset,
+            _ => return Ok(None),
+        };
+        Ok(Some(BitField {
+            bit_offset: bit_offset as u64,
+            bit_size: bit_size,
+        }))
+    }
+}
+
+// Decode a DW_AT_data_member_location that is a constant offset.
+//
+// Version 2 producers use an expression consisting of a single
+// DW_OP_plus_uconst.
+fn member_offset<E: Endian>(data: &AttributeData, unit: &UnitCommon<E>) -> Option<u64> {
+    let expr = match *data {
+        AttributeData::Block(val) |
+        AttributeData::ExprLoc(val) => val,
+        _ => return data.as_udata(),
+    };
+    let mut ops = ExpressionIterator::new(expr, unit);
+    let offset = match ops.next() {
+        Ok(Some(Operation { opcode: constant::DW_OP_plus_uconst,
+                            operands: Operands::Unsigned(offset),
+                            .. })) => offset,
+        _ => return None,
+    };
+    match ops.next() {
+        Ok(None) => Some(offset),
+        _ => None,
+    }
+}
+
+// A language-specific string or slice type.
+//
+// These types don't store their length in the type, so pretty-printers
+// need to find the length from the value or from another variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceType<'data> {
+    // A DW_TAG_string_type, as used by Fortran.
+    String {
+        // The length in bytes of a fixed length string.
+        byte_size: Option<u64>,
+        // The location of the length of a variable length string, from
+        // DW_AT_string_length. Location lists aren't supported yet.
+        length: Option<Bound<'data>>,
+        // The size in bytes of the length, if it isn't the size of an
+        // address.
+        length_size: Option<u64>,
+    },
+    // A structure containing a pointer to the elements and the number of
+    // elements, such as a Rust slice or `&str`, or a Go slice or string.
+    Slice {
+        // The offset of the pointer within the structure.
+        data_offset: u64,
+        // The section offset of the type of the pointer.
+        data_type: Option<usize>,
+        // The offset of the number of elements within the structure.
+        length_offset: u64,
+    },
+}
+
+impl<'data> SequenceType<'data> {
+    // Read a string or slice type.
+    //
+    // `entries` must start at the type entry, such as the iterator returned
+    // by `CompilationUnit::entry`. `language` is the DW_AT_language of the
+    // compilation unit, and is used to recognize slices, which are
+    // represented as ordinary structures:
+    //
+    // - Rust: `&[T]`, `&mut [T]` and `&str`, with `data_ptr` and `length`
+    //   members.
+    // - Go: `[]T` with `array` and `len` members, and `string` with `str`
+    //   and `len` members.
+    //
+    // Returns `None` if the entry isn't a string or slice type for the
+    // language.
+    pub fn read<'a, E: Endian>(
+        mut entries: DieIterator<'a, 'data, E>,
+        language: constant::DwLang,
+        context: &AttributeContext<'data>
+    ) -> Result<Option<SequenceType<'data>>, ReadError> {
+        let unit = entries.unit();
+        let (data_member, length_member) = match try!(entries.next()) {
+            Some(die) if die.tag == constant::DW_TAG_string_type => {
+                let udata = |at| die.attr(at).and_then(AttributeData::as_udata);
+                return Ok(Some(SequenceType::String {
+                    byte_size: udata(constant::DW_AT_byte_size),
+                    length: die.attr(constant::DW_AT_string_length)
+                        .and_then(|data| Bound::new(data, unit)),
+                    length_size: udata(constant::DW_AT_string_length_byte_size),
+                }));
+            }
+            Some(die) if die.tag == constant::DW_TAG_structure_type && die.children => {
+                let name = match die.attr(constant::DW_AT_name)
+                    .and_then(|name| name.as_string_in(context)) {
+                    Some(name) => name,
+                    None => return Ok(None),
+                };
+                match slice_members(language, name) {
+                    Some(members) => members,
+                    None => return Ok(None),
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        let mut data = None;
+        let mut length_offset = None;
+        let mut child = try!(entries.next());
+        while let Some(entry) = child {
+            if entry.is_null() {
+                break;
+            }
+            if entry.tag == constant::DW_TAG_member {
+                let name = entry.attr(constant::DW_AT_name)
+                    .and_then(|name| name.as_string_in(context));
+                let offset = entry.attr(constant::DW_AT_data_member_location)
+                    .and_then(AttributeData::as_udata);
+                if name == Some(data_member) {
+                    let data_type = entry.attr(constant::DW_AT_type)
+                        .and_then(|data| reference(data, unit));
+                    data = offset.map(|offset| (offset, data_type));
+                } else if name == Some(length_member) {
+                    length_offset = offset;
+                }
+            }
+            child = try!(entries.next_sibling());
+        }
+        match (data, length_offset) {
+            (Some((data_offset, data_type)), Some(length_offset)) => {
+                Ok(Some(SequenceType::Slice {
+                    data_offset: data_offset,
+                    data_type: data_type,
+                    length_offset: length_offset,
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+// Return the names of the pointer and length members of a slice type.
+fn slice_members(
+    language: constant::DwLang,
+    name: &[u8]
+) -> Option<(&'static [u8], &'static [u8])> {
+    match language {
+        constant::DW_LANG_Rust => {
+            if name.starts_with(b"&[") || name.starts_with(b"&mut [") || name == b"&str" {
+                Some((b"data_ptr", b"length"))
+            } else {
+                None
+            }
+        }
+        constant::DW_LANG_Go => {
+            if name.starts_with(b"[]") {
+                Some((b"array", b"len"))
+            } else if name == b"string" {
+                Some((b"str", b"len"))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use abbrev::*;
+    use constant::*;
+    use endian::*;
+    use offset::*;
+    use unit::*;
+
+    // Writes the entries of a unit with the default header.
+    struct UnitWriter<'a, E: Endian> {
+        header: CompilationUnit<'static, E>,
+        abbrev: &'a AbbrevHash,
+        data: Vec<u8>,
+    }
+
+    impl<'a, E: Endian + Default> UnitWriter<'a, E> {
+        fn new(endian: E, abbrev: &'a AbbrevHash) -> Self {
+            UnitWriter {
+                header: CompilationUnit {
+                    common: UnitCommon { endian: endian, ..Default::default() },
+                },
+                abbrev: abbrev,
+                data: Vec::new(),
+            }
+        }
+
+        // The offset in the unit of the next entry.
+        fn offset(&self) -> usize {
+            self.header.data_offset() + self.data.len()
+        }
+
+        // Write an entry, and return its offset in the unit.
+        fn write(
+            &mut self,
+            code: u64,
+            tag: DwTag,
+            children: bool,
+            attributes: Vec<Attribute>
+        ) -> usize {
+            let offset = self.offset();
+            Die::new(0, code, tag, children, attributes)
+                .write(&mut self.data, &self.header.common, self.abbrev)
+                .unwrap();
+            offset
+        }
+
+        fn write_null(&mut self) {
+            Die::write_null(&mut self.data).unwrap();
+        }
+
+        fn unit<'b>(&'b self) -> CompilationUnit<'b, E> {
+            CompilationUnit {
+                common: UnitCommon { data: &self.data, ..self.header.common },
+            }
+        }
+    }
+
+    fn base_type<'data>(encoding: DwAte, byte_size: u8) -> Die<'data> {
+        Die::new(0, 1, DW_TAG_base_type, false, vec![
+            Attribute { at: DW_AT_encoding, data: AttributeData::Data1(encoding.0) },
+            Attribute { at: DW_AT_byte_size, data: AttributeData::Data1(byte_size) },
+        ])
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn const_value() {
+        let int = base_type(DW_ATE_signed, 4);
+        let uint = base_type(DW_ATE_unsigned, 4);
+        let float = base_type(DW_ATE_float, 4);
+        let double = base_type(DW_ATE_float, 8);
+        let boolean = base_type(DW_ATE_boolean, 1);
+        let context = AttributeContext {
+            offset_size: 4,
+            debug_str: b"\0str\0",
+            debug_line_str: &[],
+        };
+        let value = |data, type_die| ConstValue::new(&data, type_die, LittleEndian, &context);
+
+        assert_eq!(value(AttributeData::Data4(0xffff_fff6), Some(&int)), Some(ConstValue::Signed(-10)));
+        assert_eq!(value(AttributeData::Data4(0xffff_fff6), Some(&uint)),
+                   Some(ConstValue::Unsigned(0xffff_fff6)));
+        assert_eq!(value(AttributeData::Data4(0xffff_fff6), None),
+                   Some(ConstValue::Unsigned(0xffff_fff6)));
+        assert_eq!(value(AttributeData::Data1(0xff), Some(&int)), Some(ConstValue::Signed(-1)));
+        assert_eq!(value(AttributeData::SData(-1), None), Some(ConstValue::Signed(-1)));
+        assert_eq!(value(AttributeData::SData(-1), Some(&uint)),
+                   Some(ConstValue::Unsigned(0xffff_ffff)));
+        assert_eq!(value(AttributeData::UData(0xffff_ffff), Some(&int)), Some(ConstValue::Signed(-1)));
+        assert_eq!(value(AttributeData::Data1(1), Some(&boolean)), Some(ConstValue::Bool(true)));
+        assert_eq!(value(AttributeData::Block(&[0x00, 0x00, 0xc0, 0x3f]), Some(&float)),
+                   Some(ConstValue::Float(1.5)));
+        assert_eq!(value(AttributeData::Data8(0x4004_0000_0000_0000), Some(&double)),
+                   Some(ConstValue::Float(2.5)));
+        assert_eq!(value(AttributeData::Block(&[0xf6, 0xff, 0xff, 0xff]), Some(&int)),
+                   Some(ConstValue::Signed(-10)));
+        assert_eq!(value(AttributeData::Block(&[1, 2, 3]), Some(&int)),
+                   Some(ConstValue::Block(&[1, 2, 3])));
+        assert_eq!(value(AttributeData::Block(&[1, 2]), None), Some(ConstValue::Block(&[1, 2])));
+        assert_eq!(value(AttributeData::StringOffset(1), None), Some(ConstValue::String(b"str")));
+        assert_eq!(value(AttributeData::Flag(true), None), None);
+
+        let colour = Die::new(0, 1, DW_TAG_enumeration_type, true, vec![
+            Attribute { at: DW_AT_byte_size, data: AttributeData::Data1(4) },
+        ]);
+        let enumerators = [
+            Enumerator { name: b"red", value: ConstValue::Signed(0) },
+            Enumerator { name: b"none", value: ConstValue::Signed(-1) },
+        ];
+        let none = value(AttributeData::Data4(0xffff_ffff), Some(&colour)).unwrap();
+        assert_eq!(none, ConstValue::Signed(-1));
+        assert_eq!(none.render(&enumerators), "none");
+        assert_eq!(ConstValue::Signed(2).render(&enumerators), "2");
+        assert_eq!(ConstValue::Block(&[1, 0xab]).render(&[]), "[01 ab]");
+        assert_eq!(ConstValue::String(b"str").render(&[]), "\"str\"");
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn enumeration_type() {
+        let mut abbrev = AbbrevHash::new();
+        abbrev.insert(Abbrev {
+            code: 1,
+            tag: DW_TAG_enumeration_type,
+            children: true,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                AbbrevAttribute::new(DW_AT_byte_size, DW_FORM_data1),
+                AbbrevAttribute::new(DW_AT_enum_class, DW_FORM_flag_present),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 2,
+            tag: DW_TAG_enumerator,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                AbbrevAttribute::new(DW_AT_const_value, DW_FORM_data1),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 3,
+            tag: DW_TAG_base_type,
+            children: false,
+            attributes: vec![],
+        });
+
+        let mut writer = UnitWriter::new(LittleEndian, &abbrev);
+        writer.write(1, DW_TAG_enumeration_type, true, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(b"Colour") },
+            Attribute { at: DW_AT_byte_size, data: AttributeData::Data1(1) },
+            Attribute { at: DW_AT_enum_class, data: AttributeData::Flag(true) },
+        ]);
+        for &(name, value) in &[(&b"red"[..], 0u8), (b"green", 1), (b"none", 0xff)] {
+            writer.write(2, DW_TAG_enumerator, false, vec![
+                Attribute { at: DW_AT_name, data: AttributeData::String(name) },
+                Attribute { at: DW_AT_const_value, data: AttributeData::Data1(value) },
+            ]);
+        }
+        writer.write_null();
+        let base_type = writer.write(3, DW_TAG_base_type, false, vec![]);
+        let unit = writer.unit();
+
+        let context = AttributeContext::new(&unit.common, &[], &[]);
+        let colour = EnumerationType::read(unit.entries(&abbrev), &context).unwrap().unwrap();
+        assert_eq!(colour.name, Some(&b"Colour"[..]));
+        assert!(colour.enum_class);
+        assert_eq!(colour.enumerators(), &[
+            Enumerator { name: b"red", value: ConstValue::Signed(0) },
+            Enumerator { name: b"green", value: ConstValue::Signed(1) },
+            Enumerator { name: b"none", value: ConstValue::Signed(-1) },
+        ]);
+        assert_eq!(colour.name_for_value(-1), Some(&b"none"[..]));
+        assert_eq!(colour.name_for_value(2), None);
+        assert_eq!(colour.render(&ConstValue::Signed(1)), "Colour::green");
+        assert_eq!(colour.render(&ConstValue::Unsigned(5)), "5");
+
+        let entries = unit.entry(base_type, &abbrev).unwrap();
+        assert_eq!(EnumerationType::read(entries, &context).unwrap(), None);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn array_type() {
+        let mut abbrev = AbbrevHash::new();
+        abbrev.insert(Abbrev {
+            code: 1,
+            tag: DW_TAG_array_type,
+            children: true,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_type, DW_FORM_ref4),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 2,
+            tag: DW_TAG_subrange_type,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_upper_bound, DW_FORM_data1),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 3,
+            tag: DW_TAG_subrange_type,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_lower_bound, DW_FORM_sdata),
+                AbbrevAttribute::new(DW_AT_count, DW_FORM_data1),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 4,
+            tag: DW_TAG_subrange_type,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_count, DW_FORM_exprloc),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 5,
+            tag: DW_TAG_subrange_type,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_upper_bound, DW_FORM_ref4),
+            ],
+        });
+
+        let mut writer = UnitWriter::new(LittleEndian, &abbrev);
+        // int a[3][-1..2]
+        let fixed = writer.write(1, DW_TAG_array_type, true, vec![
+            Attribute { at: DW_AT_type, data: AttributeData::Ref(UnitOffset(0x40)) },
+        ]);
+        writer.write(2, DW_TAG_subrange_type, false, vec![
+            Attribute { at: DW_AT_upper_bound, data: AttributeData::Data1(2) },
+        ]);
+        writer.write(3, DW_TAG_subrange_type, false, vec![
+            Attribute { at: DW_AT_lower_bound, data: AttributeData::SData(-1) },
+            Attribute { at: DW_AT_count, data: AttributeData::Data1(4) },
+        ]);
+        writer.write_null();
+        // A variable length array, and an array bounded by a variable.
+        let vla = writer.write(1, DW_TAG_array_type, true, vec![
+            Attribute { at: DW_AT_type, data: AttributeData::Ref(UnitOffset(0x40)) },
+        ]);
+        writer.write(4, DW_TAG_subrange_type, false, vec![
+            Attribute { at: DW_AT_count, data: AttributeData::ExprLoc(&[DW_OP_fbreg.0, 0x68]) },
+        ]);
+        writer.write(5, DW_TAG_subrange_type, false, vec![
+            Attribute { at: DW_AT_upper_bound, data: AttributeData::Ref(UnitOffset(0x50)) },
+        ]);
+        writer.write_null();
+        let unit = writer.unit();
+
+        let array = ArrayType::read(unit.entry(fixed, &abbrev).unwrap()).unwrap().unwrap();
+        assert_eq!(array.element_type, Some(0x40));
+        assert_eq!(array.dimensions, vec![
+            Dimension { lower_bound: None, upper_bound: Some(Bound::Constant(2)), count: None },
+            Dimension {
+                lower_bound: Some(Bound::Constant(-1)),
+                upper_bound: None,
+                count: Some(Bound::Constant(4)),
+            },
+        ]);
+        assert_eq!(array.dimensions[0].len(0), Some(3));
+        assert_eq!(array.dimensions[0].len(1), Some(2));
+        let huge = Dimension {
+            lower_bound: Some(Bound::Constant(-1)),
+            upper_bound: Some(Bound::Constant(i64::max_value())),
+            count: None,
+        };
+        assert_eq!(huge.len(0), None);
+        let huge = Dimension { lower_bound: Some(Bound::Constant(0)), ..huge };
+        assert_eq!(huge.len(0), Some(1 << 63));
+        assert_eq!(array.element_count(0), Some(12));
+
+        let array = ArrayType::read(unit.entry(vla, &abbrev).unwrap()).unwrap().unwrap();
+        assert_eq!(array.dimensions, vec![
+            Dimension {
+                lower_bound: None,
+                upper_bound: None,
+                count: Some(Bound::Expression(&[DW_OP_fbreg.0, 0x68])),
+            },
+            Dimension { lower_bound: None, upper_bound: Some(Bound::Reference(0x50)), count: None },
+        ]);
+        assert_eq!(array.element_count(0), None);
+
+        assert_eq!(ArrayType::read(unit.entry(fixed + 5, &abbrev).unwrap()).unwrap(), None);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn bit_field() {
+        let mut abbrev = AbbrevHash::new();
+        abbrev.insert(Abbrev {
+            code: 1,
+            tag: DW_TAG_base_type,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_byte_size, DW_FORM_data1),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 2,
+            tag: DW_TAG_member,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_bit_size, DW_FORM_data1),
+                AbbrevAttribute::new(DW_AT_data_bit_offset, DW_FORM_data1),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 3,
+            tag: DW_TAG_member,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_byte_size, DW_FORM_data1),
+                AbbrevAttribute::new(DW_AT_bit_size, DW_FORM_data1),
+                AbbrevAttribute::new(DW_AT_bit_offset, DW_FORM_data1),
+                AbbrevAttribute::new(DW_AT_data_member_location, DW_FORM_data1),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 4,
+            tag: DW_TAG_member,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_type, DW_FORM_ref4),
+                AbbrevAttribute::new(DW_AT_bit_size, DW_FORM_data1),
+                AbbrevAttribute::new(DW_AT_bit_offset, DW_FORM_data1),
+                AbbrevAttribute::new(DW_AT_data_member_location, DW_FORM_block1),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 5,
+            tag: DW_TAG_member,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_data_member_location, DW_FORM_data1),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 6,
+            tag: DW_TAG_member,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_byte_size, DW_FORM_udata),
+                AbbrevAttribute::new(DW_AT_bit_size, DW_FORM_data1),
+                AbbrevAttribute::new(DW_AT_bit_offset, DW_FORM_data1),
+                AbbrevAttribute::new(DW_AT_data_member_location, DW_FORM_udata),
+            ],
+        });
+
+        fn bit_fields<E: Endian + Default>(abbrev: &AbbrevHash) -> Vec<Option<BitField>> {
+            let mut writer = UnitWriter::new(E::default(), abbrev);
+            let int = writer.write(1, DW_TAG_base_type, false, vec![
+                Attribute { at: DW_AT_byte_size, data: AttributeData::Data1(4) },
+            ]);
+            let offsets = [
+                writer.write(2, DW_TAG_member, false, vec![
+                    Attribute { at: DW_AT_bit_size, data: AttributeData::Data1(3) },
+                    Attribute { at: DW_AT_data_bit_offset, data: AttributeData::Data1(3) },
+                ]),
+                writer.write(3, DW_TAG_member, false, vec![
+                    Attribute { at: DW_AT_byte_size, data: AttributeData::Data1(4) },
+                    Attribute { at: DW_AT_bit_size, data: AttributeData::Data1(5) },
+                    Attribute { at: DW_AT_bit_offset, data: AttributeData::Data1(27) },
+                    Attribute { at: DW_AT_data_member_location, data: AttributeData::Data1(4) },
+                ]),
+                writer.write(4, DW_TAG_member, false, vec![
+                    Attribute { at: DW_AT_type, data: AttributeData::Ref(UnitOffset(int as u64)) },
+                    Attribute { at: DW_AT_bit_size, data: AttributeData::Data1(3) },
+                    Attribute { at: DW_AT_bit_offset, data: AttributeData::Data1(24) },
+                    Attribute {
+                        at: DW_AT_data_member_location,
+                        data: AttributeData::Block(&[DW_OP_plus_uconst.0, 4]),
+                    },
+                ]),
+                writer.write(5, DW_TAG_member, false, vec![
+                    Attribute { at: DW_AT_data_member_location, data: AttributeData::Data1(8) },
+                ]),
+                // A DW_AT_bit_offset of -2.
+                writer.write(3, DW_TAG_member, false, vec![
+                    Attribute { at: DW_AT_byte_size, data: AttributeData::Data1(4) },
+                    Attribute { at: DW_AT_bit_size, data: AttributeData::Data1(4) },
+                    Attribute { at: DW_AT_bit_offset, data: AttributeData::Data1(0xfe) },
+                    Attribute { at: DW_AT_data_member_location, data: AttributeData::Data1(4) },
+                ]),
+                // Locations that overflow when converted to bits.
+                writer.write(6, DW_TAG_member, false, vec![
+                    Attribute { at: DW_AT_byte_size, data: AttributeData::UData(4) },
+                    Attribute { at: DW_AT_bit_size, data: AttributeData::Data1(1) },
+                    Attribute { at: DW_AT_bit_offset, data: AttributeData::Data1(0) },
+                    Attribute {
+                        at: DW_AT_data_member_location,
+                        data: AttributeData::UData(1 << 62),
+                    },
+                ]),
+                writer.write(6, DW_TAG_member, false, vec![
+                    Attribute { at: DW_AT_byte_size, data: AttributeData::UData(1 << 61) },
+                    Attribute { at: DW_AT_bit_size, data: AttributeData::Data1(1) },
+                    Attribute { at: DW_AT_bit_offset, data: AttributeData::Data1(0) },
+                    Attribute { at: DW_AT_data_member_location, data: AttributeData::UData(0) },
+                ]),
+            ];
+            let unit = writer.unit();
+            let mut resolver = UnitResolver::new(&unit, abbrev);
+            offsets.iter().map(|&offset| {
+                let die = unit.entry(offset, abbrev).unwrap().next().unwrap().unwrap().clone();
+                BitField::read(&die, &unit.common, &mut resolver).unwrap()
+            }).collect()
+        }
+
+        let field = |bit_offset, bit_size| {
+            Some(BitField { bit_offset: bit_offset, bit_size: bit_size })
+        };
+        assert_eq!(bit_fields::<LittleEndian>(&abbrev),
+                   vec![field(3, 3), field(32, 5), field(37, 3), None, field(62, 4), None, None]);
+        assert_eq!(bit_fields::<BigEndian>(&abbrev),
+                   vec![field(3, 3), field(59, 5), field(56, 3), None, field(30, 4), None,
+                        field(0, 1)]);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn type_byte_size() {
+        let mut abbrev = AbbrevHash::new();
+        abbrev.insert(Abbrev {
+            code: 1,
+            tag: DW_TAG_base_type,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_byte_size, DW_FORM_data1),
+            ],
+        });
+        for &(code, tag, children) in &[(2, DW_TAG_typedef, false),
+                                         (3, DW_TAG_enumeration_type, false),
+                                         (4, DW_TAG_pointer_type, false),
+                                         (5, DW_TAG_array_type, true)] {
+            abbrev.insert(Abbrev {
+                code: code,
+                tag: tag,
+                children: children,
+                attributes: vec![
+                    AbbrevAttribute::new(DW_AT_type, DW_FORM_ref4),
+                ],
+            });
+        }
+        abbrev.insert(Abbrev {
+            code: 6,
+            tag: DW_TAG_subrange_type,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_lower_bound, DW_FORM_sdata),
+                AbbrevAttribute::new(DW_AT_upper_bound, DW_FORM_data1),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 7,
+            tag: DW_TAG_subrange_type,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_count, DW_FORM_exprloc),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 8,
+            tag: DW_TAG_structure_type,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_declaration, DW_FORM_flag_present),
+            ],
+        });
+
+        let mut writer = UnitWriter::new(LittleEndian, &abbrev);
+        let type_ref = |offset: usize| {
+            vec![Attribute { at: DW_AT_type, data: AttributeData::Ref(UnitOffset(offset as u64)) }]
+        };
+        let int = writer.write(1, DW_TAG_base_type, false, vec![
+            Attribute { at: DW_AT_byte_size, data: AttributeData::Data1(4) },
+        ]);
+        let typedef = writer.write(2, DW_TAG_typedef, false, type_ref(int));
+        let enumeration = writer.write(3, DW_TAG_enumeration_type, false, type_ref(typedef));
+        let pointer = writer.write(4, DW_TAG_pointer_type, false, type_ref(int));
+        // typedef int a[3][-1..2]
+        let array = writer.write(5, DW_TAG_array_type, true, type_ref(typedef));
+        writer.write(6, DW_TAG_subrange_type, false, vec![
+            Attribute { at: DW_AT_lower_bound, data: AttributeData::SData(0) },
+            Attribute { at: DW_AT_upper_bound, data: AttributeData::Data1(2) },
+        ]);
+        writer.write(6, DW_TAG_subrange_type, false, vec![
+            Attribute { at: DW_AT_lower_bound, data: AttributeData::SData(-1) },
+            Attribute { at: DW_AT_upper_bound, data: AttributeData::Data1(2) },
+        ]);
+        writer.write_null();
+        // An array of arrays, and a variable length array.
+        let nested = writer.write(5, DW_TAG_array_type, true, type_ref(array));
+        writer.write(6, DW_TAG_subrange_type, false, vec![
+            Attribute { at: DW_AT_lower_bound, data: AttributeData::SData(0) },
+            Attribute { at: DW_AT_upper_bound, data: AttributeData::Data1(1) },
+        ]);
+        writer.write_null();
+        let vla = writer.write(5, DW_TAG_array_type, true, type_ref(int));
+        writer.write(7, DW_TAG_subrange_type, false, vec![
+            Attribute { at: DW_AT_count, data: AttributeData::ExprLoc(&[DW_OP_fbreg.0, 0x68]) },
+        ]);
+        writer.write_null();
+        let incomplete = writer.write(8, DW_TAG_structure_type, false, vec![
+            Attribute { at: DW_AT_declaration, data: AttributeData::Flag(true) },
+        ]);
+        let incomplete_typedef = writer.write(2, DW_TAG_typedef, false, type_ref(incomplete));
+        // Typedefs that refer to each other.
+        let cycle = writer.offset();
+        writer.write(2, DW_TAG_typedef, false, type_ref(cycle + 5));
+        writer.write(2, DW_TAG_typedef, false, type_ref(cycle));
+        let unit = CompilationUnit {
+            common: UnitCommon { address_size: 8, ..writer.unit().common },
+        };
+
+        let mut resolver = UnitResolver::new(&unit, &abbrev);
+        let mut size = |offset| {
+            let die = unit.entry(offset, &abbrev).unwrap().next().unwrap().unwrap().clone();
+            super::type_byte_size(&die, &mut resolver)
+        };
+        assert_eq!(size(int).unwrap(), Some(4));
+        assert_eq!(size(typedef).unwrap(), Some(4));
+        assert_eq!(size(enumeration).unwrap(), Some(4));
+        assert_eq!(size(pointer).unwrap(), Some(8));
+        assert_eq!(size(array).unwrap(), Some(48));
+        assert_eq!(size(nested).unwrap(), Some(96));
+        assert_eq!(size(vla).unwrap(), None);
+        assert_eq!(size(incomplete).unwrap(), None);
+        assert_eq!(size(incomplete_typedef).unwrap(), None);
+        match size(cycle) {
+            Err(ReadError::ReferenceCycle(offset)) => assert_eq!(offset, cycle),
+            otherwise => panic!("{:?}", otherwise),
+        }
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn array_default_lower_bound() {
+        let mut abbrev = AbbrevHash::new();
+        abbrev.insert(Abbrev {
+            code: 1,
+            tag: DW_TAG_compile_unit,
+            children: true,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_language, DW_FORM_data1),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 2,
+            tag: DW_TAG_base_type,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_byte_size, DW_FORM_data1),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 3,
+            tag: DW_TAG_array_type,
+            children: true,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_type, DW_FORM_ref4),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 4,
+            tag: DW_TAG_subrange_type,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_upper_bound, DW_FORM_data1),
+            ],
+        });
+
+        for &(language, expect) in &[(DW_LANG_C99, 16), (DW_LANG_Fortran90, 12)] {
+            let mut writer = UnitWriter::new(LittleEndian, &abbrev);
+            writer.write(1, DW_TAG_compile_unit, true, vec![
+                Attribute { at: DW_AT_language, data: AttributeData::Data1(language.0 as u8) },
+            ]);
+            let int = writer.write(2, DW_TAG_base_type, false, vec![
+                Attribute { at: DW_AT_byte_size, data: AttributeData::Data1(4) },
+            ]);
+            // int a[3], or integer a(3)
+            let array = writer.write(3, DW_TAG_array_type, true, vec![
+                Attribute { at: DW_AT_type, data: AttributeData::Ref(UnitOffset(int as u64)) },
+            ]);
+            writer.write(4, DW_TAG_subrange_type, false, vec![
+                Attribute { at: DW_AT_upper_bound, data: AttributeData::Data1(3) },
+            ]);
+            writer.write_null();
+            writer.write_null();
+            let unit = writer.unit();
+
+            let die = unit.entry(array, &abbrev).unwrap().next().unwrap().unwrap().clone();
+            let mut resolver = UnitResolver::new(&unit, &abbrev);
+            assert_eq!(super::type_byte_size(&die, &mut resolver).unwrap(), Some(expect));
+        }
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn implicit_const() {
+        let mut abbrev = AbbrevHash::new();
+        abbrev.insert(Abbrev {
+            code: 1,
+            tag: DW_TAG_base_type,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::implicit_const(DW_AT_byte_size, 4),
+                AbbrevAttribute::implicit_const(DW_AT_decl_file, 2),
+            ],
+        });
+
+        let mut writer = UnitWriter::new(LittleEndian, &abbrev);
+        writer.header.common.version = 5;
+        let int = writer.write(1, DW_TAG_base_type, false, vec![
+            Attribute { at: DW_AT_byte_size, data: AttributeData::SData(4) },
+            Attribute { at: DW_AT_decl_file, data: AttributeData::SData(2) },
+        ]);
+        let unit = writer.unit();
+
+        let die = unit.entry(int, &abbrev).unwrap().next().unwrap().unwrap().clone();
+        let mut resolver = UnitResolver::new(&unit, &abbrev);
+        assert_eq!(super::type_byte_size(&die, &mut resolver).unwrap(), Some(4));
+        assert_eq!(die.file_index(DW_AT_decl_file, &unit.common), Some(2));
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn class_type() {
+        let mut abbrev = AbbrevHash::new();
+        abbrev.insert(Abbrev {
+            code: 1,
+            tag: DW_TAG_class_type,
+            children: true,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 2,
+            tag: DW_TAG_subprogram,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                AbbrevAttribute::new(DW_AT_virtuality, DW_FORM_data1),
+                AbbrevAttribute::new(DW_AT_vtable_elem_location, DW_FORM_exprloc),
+                AbbrevAttribute::new(DW_AT_accessibility, DW_FORM_data1),
+                AbbrevAttribute::new(DW_AT_declaration, DW_FORM_flag_present),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 3,
+            tag: DW_TAG_subprogram,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                AbbrevAttribute::new(DW_AT_declaration, DW_FORM_flag_present),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 4,
+            tag: DW_TAG_subprogram,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_specification, DW_FORM_ref4),
+            ],
+        });
+
+        let mut writer = UnitWriter::new(LittleEndian, &abbrev);
+        let class = writer.write(1, DW_TAG_class_type, true, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(b"Shape") },
+        ]);
+        let area = writer.write(2, DW_TAG_subprogram, false, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(b"area") },
+            Attribute { at: DW_AT_virtuality, data: AttributeData::Data1(DW_VIRTUALITY_pure_virtual.0) },
+            Attribute {
+                at: DW_AT_vtable_elem_location,
+                data: AttributeData::ExprLoc(&[DW_OP_constu.0, 2]),
+            },
+            Attribute { at: DW_AT_accessibility, data: AttributeData::Data1(DW_ACCESS_public.0) },
+            Attribute { at: DW_AT_declaration, data: AttributeData::Flag(true) },
+        ]);
+        let reset = writer.write(3, DW_TAG_subprogram, false, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(b"reset") },
+            Attribute { at: DW_AT_declaration, data: AttributeData::Flag(true) },
+        ]);
+        writer.write_null();
+        let definition = writer.write(4, DW_TAG_subprogram, false, vec![
+            Attribute { at: DW_AT_specification, data: AttributeData::Ref(UnitOffset(reset as u64)) },
+        ]);
+        let unit = writer.unit();
+
+        let context = AttributeContext::new(&unit.common, &[], &[]);
+        let mut shape = ClassType::read(unit.entry(class, &abbrev).unwrap(), &context)
+            .unwrap()
+            .unwrap();
+        shape.find_definitions(unit.entries(&abbrev)).unwrap();
+        assert_eq!(shape.name, Some(&b"Shape"[..]));
+        assert_eq!(shape.member_functions, vec![
+            MemberFunction {
+                offset: area,
+                name: Some(b"area"),
+                linkage_name: None,
+                virtuality: DW_VIRTUALITY_pure_virtual,
+                vtable_index: Some(2),
+                accessibility: DW_ACCESS_public,
+                declaration: true,
+                artificial: false,
+                definitions: vec![],
+            },
+            MemberFunction {
+                offset: reset,
+                name: Some(b"reset"),
+                linkage_name: None,
+                virtuality: DW_VIRTUALITY_none,
+                vtable_index: None,
+                accessibility: DW_ACCESS_private,
+                declaration: true,
+                artificial: false,
+                definitions: vec![definition],
+            },
+        ]);
+        assert!(shape.member_functions[0].is_virtual());
+
+        assert_eq!(ClassType::read(unit.entry(area, &abbrev).unwrap(), &context).unwrap(), None);
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn sequence_type() {
+        let mut abbrev = AbbrevHash::new();
+        abbrev.insert(Abbrev {
+            code: 1,
+            tag: DW_TAG_string_type,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_string_length, DW_FORM_exprloc),
+                AbbrevAttribute::new(DW_AT_string_length_byte_size, DW_FORM_data1),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 2,
+            tag: DW_TAG_structure_type,
+            children: true,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+            ],
+        });
+        abbrev.insert(Abbrev {
+            code: 3,
+            tag: DW_TAG_member,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_string),
+                AbbrevAttribute::new(DW_AT_type, DW_FORM_ref4),
+                AbbrevAttribute::new(DW_AT_data_member_location, DW_FORM_data1),
+            ],
+        });
+
+        let mut writer = UnitWriter::new(LittleEndian, &abbrev);
+        let string = writer.write(1, DW_TAG_string_type, false, vec![
+            Attribute { at: DW_AT_string_length, data: AttributeData::ExprLoc(&[DW_OP_fbreg.0, 0x68]) },
+            Attribute { at: DW_AT_string_length_byte_size, data: AttributeData::Data1(4) },
+        ]);
+        let slice = writer.write(2, DW_TAG_structure_type, true, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(b"&[u8]") },
+        ]);
+        writer.write(3, DW_TAG_member, false, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(b"data_ptr") },
+            Attribute { at: DW_AT_type, data: AttributeData::Ref(UnitOffset(0x40)) },
+            Attribute { at: DW_AT_data_member_location, data: AttributeData::Data1(0) },
+        ]);
+        writer.write(3, DW_TAG_member, false, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::String(b"length") },
+            Attribute { at: DW_AT_type, data: AttributeData::Ref(UnitOffset(0x50)) },
+            Attribute { at: DW_AT_data_member_location, data: AttributeData::Data1(8) },
+        ]);
+        writer.write_null();
+        let unit = writer.unit();
+
+        let context = AttributeContext::new(&unit.common, &[], &[]);
+        let read = |offset, language| {
+            SequenceType::read(unit.entry(offset, &abbrev).unwrap(), language, &context).unwrap()
+        };
+        assert_eq!(read(string, DW_LANG_Fortran90), Some(SequenceType::String {
+            byte_size: None,
+            length: Some(Bound::Expression(&[DW_OP_fbreg.0, 0x68])),
+            length_size: Some(4),
+        }));
+        assert_eq!(read(slice, DW_LANG_Rust), Some(SequenceType::Slice {
+            data_offset: 0,
+            data_type: Some(0x40),
+            length_offset: 8,
+        }));
+        // The structure is only a slice in Rust.
+        assert_eq!(read(slice, DW_LANG_C), None);
+    }
+}
@@ -1,18 +1,25 @@
 use std::io::Write;
+use std::ops::Deref;
+use std::rc::Rc;
+use std::sync::Arc;
 
-use abbrev::AbbrevHash;
+use abbrev::{AbbrevCache, AbbrevHash};
 use constant;
-use die::DieIterator;
+use die::{AttributeContext, AttributeData, Die, DieChildIterator, DieIterator, DieTree};
 use endian::Endian;
-use line::{LineIterator, LineProgram};
+use leb128;
+use line::{LineIterator, LineProgram, LineProgramLocation};
+use quirk::Quirks;
 use read::*;
 use write::*;
+use Sections;
 
 #[derive(Debug)]
 pub struct CompilationUnitIterator<'data, E: Endian> {
     endian: E,
     data: &'data [u8],
     offset: usize,
+    unsupported: Option<Vec<UnsupportedUnit>>,
 }
 
 impl<'data, E: Endian> CompilationUnitIterator<'data, E> {
@@ -21,6 +28,7 @@ impl<'data, E: Endian> CompilationUnitIterator<'data, E> {
             endian: endian,
             data: data,
             offset: 0,
+            unsupported: None,
         }
     }
 
@@ -28,17 +36,235 @@ impl<'data, E: Endian> CompilationUnitIterator<'data, E> {
         self.offset
     }
 
+    // Skip units with an unsupported version instead of returning an error.
+    //
+    // The skipped units are available from `unsupported`.
+    pub fn skip_unsupported(&mut self) {
+        self.unsupported = Some(Vec::new());
+    }
+
+    // The units that were skipped because their version isn't supported.
+    pub fn unsupported(&self) -> &[UnsupportedUnit] {
+        match self.unsupported {
+            Some(ref unsupported) => unsupported,
+            None => &[],
+        }
+    }
+
     #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
     pub fn next(&mut self) -> Result<Option<CompilationUnit<'data, E>>, ReadError> {
-        if self.data.len() == 0 {
-            return Ok(None);
+        loop {
+            if self.data.is_empty() {
+                return Ok(None);
+            }
+
+            let mut r = self.data;
+            match CompilationUnit::read(&mut r, self.offset, self.endian) {
+                Ok(unit) => {
+                    self.offset += self.data.len() - r.len();
+                    self.data = r;
+                    return Ok(Some(unit));
+                }
+                Err(ReadError::UnsupportedVersion(version)) if self.unsupported.is_some() => {
+                    r = self.data;
+                    try!(UnitCommon::<E>::skip(&mut r, self.endian));
+                    self.unsupported.as_mut().unwrap().push(UnsupportedUnit {
+                        offset: self.offset,
+                        version: version,
+                    });
+                    self.offset += self.data.len() - r.len();
+                    self.data = r;
+                }
+                Err(e) => return Err(e),
+            }
         }
+    }
+}
 
-        let mut r = self.data;
-        let unit = try!(CompilationUnit::read(&mut r, self.offset, self.endian));
-        self.offset += self.data.len() - r.len();
-        self.data = r;
-        Ok(Some(unit))
+// An entry returned by `AllEntries`, along with the unit containing it.
+#[derive(Debug)]
+pub struct UnitEntry<'a, 'data: 'a, E: Endian + 'a> {
+    pub unit: &'a UnitCommon<'data, E>,
+    // True if the unit is a type unit in .debug_types.
+    pub type_unit: bool,
+    pub entry: &'a Die<'data>,
+}
+
+// An iterator over the entries of all compilation units and then all
+// type units.
+//
+// Null entries are included.
+#[derive(Debug)]
+pub struct AllEntries<'data, 'c, E: Endian> {
+    debug_abbrev: &'data [u8],
+    abbrev_cache: &'c mut AbbrevCache,
+    compilation_units: CompilationUnitIterator<'data, E>,
+    type_units: TypeUnitIterator<'data, E>,
+    type_unit: bool,
+    unit: Option<UnitCommon<'data, E>>,
+    abbrev: Rc<AbbrevHash>,
+    r: &'data [u8],
+    offset: usize,
+    entry: Die<'data>,
+}
+
+impl<'data, 'c, E: Endian> AllEntries<'data, 'c, E> {
+    pub fn new(
+        debug_abbrev: &'data [u8],
+        abbrev_cache: &'c mut AbbrevCache,
+        compilation_units: CompilationUnitIterator<'data, E>,
+        type_units: TypeUnitIterator<'data, E>
+    ) -> Self {
+        AllEntries {
+            debug_abbrev: debug_abbrev,
+            abbrev_cache: abbrev_cache,
+            compilation_units: compilation_units,
+            type_units: type_units,
+            type_unit: false,
+            unit: None,
+            abbrev: Default::default(),
+            r: &[],
+            offset: 0,
+            entry: Die::null(0),
+        }
+    }
+
+    #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
+    pub fn next<'me>(&'me mut self) -> Result<Option<UnitEntry<'me, 'data, E>>, ReadError> {
+        while self.r.is_empty() {
+            let (common, header_len) = if !self.type_unit {
+                match try!(self.compilation_units.next()) {
+                    Some(unit) => {
+                        let header_len = unit.header_len();
+                        (unit.common, header_len)
+                    }
+                    None => {
+                        self.type_unit = true;
+                        continue;
+                    }
+                }
+            } else {
+                match try!(self.type_units.next()) {
+                    Some(unit) => {
+                        let header_len = unit.header_len();
+                        (unit.common, header_len)
+                    }
+                    None => {
+                        self.unit = None;
+                        return Ok(None);
+                    }
+                }
+            };
+            self.abbrev = try!(self.abbrev_cache.get(self.debug_abbrev, common.abbrev_offset));
+            self.r = common.data;
+            self.offset = common.offset + header_len;
+            self.unit = Some(common);
+        }
+
+        let unit = self.unit.as_ref().unwrap();
+        let mut r = self.r;
+        try!(self.entry.read(&mut r, self.offset, unit, &self.abbrev));
+        self.offset += self.r.len() - r.len();
+        self.r = r;
+        Ok(Some(UnitEntry {
+            unit: unit,
+            type_unit: self.type_unit,
+            entry: &self.entry,
+        }))
+    }
+}
+
+// The ranges of the compilation units in .debug_info, sorted by offset.
+//
+// This is used to find the unit containing a section offset, such as
+// the target of a DW_FORM_ref_addr reference.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnitIndex {
+    // The start and end offsets of each unit, including padding.
+    ranges: Vec<(usize, usize)>,
+}
+
+impl UnitIndex {
+    pub fn new<'data, E: Endian>(
+        mut units: CompilationUnitIterator<'data, E>
+    ) -> Result<UnitIndex, ReadError> {
+        let mut ranges = Vec::new();
+        while let Some(unit) = try!(units.next()) {
+            ranges.push((unit.common.offset, unit.end_offset()));
+        }
+        // Units are read in order, so this is already sorted.
+        Ok(UnitIndex { ranges: ranges })
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    // Return the offset of the header of the unit containing `offset`.
+    pub fn unit_containing(&self, offset: usize) -> Option<usize> {
+        let index = match self.ranges.binary_search_by_key(&offset, |&(start, _)| start) {
+            Ok(index) => index,
+            Err(0) => return None,
+            Err(index) => index - 1,
+        };
+        let (start, end) = self.ranges[index];
+        if offset < end { Some(start) } else { None }
+    }
+}
+
+// A unit that was skipped because its version isn't supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedUnit {
+    pub offset: usize,
+    pub version: u16,
+}
+
+// The attributes of the root entry of a unit that are used when reading
+// the range lists, location lists and indexed values of its entries.
+//
+// Attributes that are missing are 0.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct UnitBases {
+    // The DW_AT_low_pc, which is the base address of range lists and
+    // location lists.
+    pub base_address: u64,
+    pub addr_base: u64,
+    pub rnglists_base: u64,
+    pub loclists_base: u64,
+}
+
+impl UnitBases {
+    // Read the bases from the root entry of a compilation unit.
+    pub fn read<'data, E: Endian>(
+        unit: &CompilationUnit<'data, E>,
+        abbrev: &AbbrevHash
+    ) -> Result<UnitBases, ReadError> {
+        let mut entries = unit.entries(abbrev);
+        match try!(entries.next()) {
+            Some(root) => Ok(UnitBases::from_root(&unit.common, root)),
+            None => Err(ReadError::Invalid),
+        }
+    }
+
+    // Return the bases of a unit whose root entry has already been read.
+    pub fn from_root<E: Endian>(unit: &UnitCommon<E>, root: &Die) -> UnitBases {
+        let context = AttributeContext::new(unit, &[], &[]);
+        let offset = |at| {
+            root.attr(at).and_then(|data| data.as_offset_in(&context)).unwrap_or(0) as u64
+        };
+        UnitBases {
+            base_address: match root.attr(constant::DW_AT_low_pc) {
+                Some(&AttributeData::Address(address)) => address,
+                _ => 0,
+            },
+            addr_base: offset(constant::DW_AT_addr_base),
+            rnglists_base: offset(constant::DW_AT_rnglists_base),
+            loclists_base: offset(constant::DW_AT_loclists_base),
+        }
     }
 }
 
@@ -59,7 +285,9 @@ impl<'data, E: Endian> CompilationUnit<'data, E> {
         2 + offset_size as usize + 1
     }
 
-    fn total_header_len(offset_size: u8) -> usize {
+    // The length of the header of a version 2 to 4 unit with the given
+    // offset size.
+    pub fn total_header_len(offset_size: u8) -> usize {
         // len + version + abbrev_offset + address_size
         // Includes an extra 4 bytes if offset_size is 8
         (offset_size as usize * 2 - 4) + Self::base_header_len(offset_size)
@@ -70,7 +298,24 @@ impl<'data, E: Endian> CompilationUnit<'data, E> {
     }
 
     pub fn data_offset(&'data self) -> usize {
-        self.common.offset + Self::total_header_len(self.common.offset_size)
+        self.common.offset + self.header_len()
+    }
+
+    // The length of the unit header, including the initial length field.
+    pub fn header_len(&self) -> usize {
+        self.common.header_len()
+    }
+
+    // The length of the unit, including the header but not any padding.
+    pub fn total_len(&self) -> usize {
+        self.header_len() + self.common.len()
+    }
+
+    // The offset of the end of the unit, including any padding.
+    //
+    // This is the offset of the next unit in the section.
+    pub fn end_offset(&self) -> usize {
+        self.common.offset + self.total_len() + self.common.padding
     }
 
     pub fn abbrev(&self, debug_abbrev: &[u8]) -> Result<AbbrevHash, ReadError> {
@@ -83,6 +328,36 @@ impl<'data, E: Endian> CompilationUnit<'data, E> {
         debug_str: &'data [u8],
         abbrev: &AbbrevHash
     ) -> Result<Option<LineProgram<'data, E>>, ReadError> {
+        let context = AttributeContext::new(&self.common, debug_str, &[]);
+        self.line_program_in(debug_line, &context, abbrev)
+    }
+
+    // Like `line_program`, but read the strings of the unit entry from
+    // the sections of `context`.
+    pub fn line_program_in(
+        &self,
+        debug_line: &'data [u8],
+        context: &AttributeContext<'data>,
+        abbrev: &AbbrevHash
+    ) -> Result<Option<LineProgram<'data, E>>, ReadError> {
+        match try!(self.line_program_location(context, abbrev)) {
+            Some(location) => {
+                location.read(debug_line, context.line_strings(), self.common.endian).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Return the offset of the line program from the DW_AT_stmt_list of
+    // the unit entry, and the values of the unit entry that are needed to
+    // read it.
+    //
+    // Returns `None` if the unit has no line program.
+    pub fn line_program_location(
+        &self,
+        context: &AttributeContext<'data>,
+        abbrev: &AbbrevHash
+    ) -> Result<Option<LineProgramLocation<'data>>, ReadError> {
         let mut entries = self.entries(abbrev);
         let entry = if let Some(entry) = try!(entries.next()) {
             entry
@@ -94,26 +369,48 @@ impl<'data, E: Endian> CompilationUnit<'data, E> {
             Some(offset) => offset,
             None => return Ok(None),
         };
-        let offset = try!(offset.as_offset().ok_or(ReadError::Invalid)) as usize;
+        let offset = try!(offset.as_offset_in(context).ok_or(ReadError::Invalid));
         let comp_dir = match entry.attr(constant::DW_AT_comp_dir) {
-            Some(attr) => try!(attr.as_string(debug_str).ok_or(ReadError::Invalid)),
+            Some(attr) => try!(attr.as_string_in(context).ok_or(ReadError::Invalid)),
+            None => &[],
+        };
+        // Units produced by assemblers may not have a name. The name is only
+        // needed for file 0, so use an empty entry for it.
+        let comp_name = match entry.attr(constant::DW_AT_name) {
+            Some(attr) => try!(attr.as_string_in(context).ok_or(ReadError::Invalid)),
             None => &[],
         };
-        let comp_name = try!(entry.attr(constant::DW_AT_name).ok_or(ReadError::Invalid));
-        let comp_name = try!(comp_name.as_string(debug_str).ok_or(ReadError::Invalid));
 
-        if offset >= debug_line.len() {
-            return Err(ReadError::Invalid);
-        }
-        let mut r = &debug_line[offset..];
+        Ok(Some(LineProgramLocation {
+            offset: offset,
+            address_size: self.common.address_size,
+            comp_dir: comp_dir,
+            comp_name: comp_name,
+        }))
+    }
 
-        LineProgram::read(&mut r,
-                          offset,
-                          self.common.endian,
-                          self.common.address_size,
-                          comp_dir,
-                          comp_name)
-            .map(Some)
+    // Return the identifier that links a skeleton unit and its split unit.
+    //
+    // This is the DW_AT_GNU_dwo_id attribute of the unit entry. DWARF 5
+    // stores the id in the unit header instead.
+    pub fn dwo_id(&self, abbrev: &AbbrevHash) -> Result<Option<u64>, ReadError> {
+        if self.common.version >= 5 {
+            return Ok(match self.common.unit_type {
+                constant::DW_UT_skeleton |
+                constant::DW_UT_split_compile => Some(self.common.unit_id),
+                _ => None,
+            });
+        }
+        let mut entries = self.entries(abbrev);
+        let entry = match try!(entries.next()) {
+            Some(entry) => entry,
+            None => return Err(ReadError::Invalid),
+        };
+        match entry.attr(constant::DW_AT_GNU_dwo_id) {
+            Some(&AttributeData::Data8(id)) => Ok(Some(id)),
+            Some(_) => Err(ReadError::Invalid),
+            None => Ok(None),
+        }
     }
 
     pub fn lines(
@@ -130,6 +427,24 @@ impl<'data, E: Endian> CompilationUnit<'data, E> {
         self.common.entries(self.data_offset(), abbrev)
     }
 
+    // Iterate over the entries that start before `offset`.
+    pub fn entries_until<'a>(
+        &'a self,
+        offset: usize,
+        abbrev: &'a AbbrevHash
+    ) -> DieIterator<'a, 'data, E> {
+        self.entries(abbrev).until(offset)
+    }
+
+    // Iterate over the first `count` entries, not including null entries.
+    pub fn entries_limited<'a>(
+        &'a self,
+        count: usize,
+        abbrev: &'a AbbrevHash
+    ) -> DieIterator<'a, 'data, E> {
+        self.entries(abbrev).limited(count)
+    }
+
     pub fn entry<'a>(
         &'a self,
         offset: usize,
@@ -138,6 +453,63 @@ impl<'data, E: Endian> CompilationUnit<'data, E> {
         self.common.entry(self.data_offset(), offset, abbrev)
     }
 
+    // Iterate over the direct children of the entry at `offset`.
+    pub fn children<'a>(
+        &'a self,
+        offset: usize,
+        abbrev: &'a AbbrevHash
+    ) -> Option<DieChildIterator<'a, 'data, E>> {
+        self.entry(offset, abbrev).map(DieIterator::children)
+    }
+
+    // Return the offset of the entry found by descending from the root
+    // entry through a child matching each tag and DW_AT_name of `path`.
+    //
+    // For example, `[(DW_TAG_namespace, b"app"), (DW_TAG_subprogram, b"main")]`
+    // finds the subprogram "main" in the namespace "app". Only the first
+    // matching child is descended into. An empty path returns the root.
+    //
+    // The names are read from the string sections of `context`.
+    pub fn entry_at_path(
+        &self,
+        abbrev: &AbbrevHash,
+        context: &AttributeContext,
+        path: &[(constant::DwTag, &[u8])]
+    ) -> Result<Option<usize>, ReadError> {
+        let mut entries = self.entries(abbrev);
+        let (mut offset, mut children) = match try!(entries.next()) {
+            Some(entry) if !entry.is_null() => (entry.offset, entry.children),
+            _ => return Ok(None),
+        };
+        for &(tag, name) in path {
+            if !children {
+                return Ok(None);
+            }
+            let mut entry = try!(entries.next());
+            loop {
+                let child = match entry {
+                    Some(child) if !child.is_null() => child,
+                    _ => return Ok(None),
+                };
+                if child.tag == tag &&
+                   child.attr(constant::DW_AT_name)
+                    .and_then(|data| data.as_string_in(context)) == Some(name) {
+                    offset = child.offset;
+                    children = child.children;
+                    break;
+                }
+                entry = try!(entries.next_sibling());
+            }
+        }
+        Ok(Some(offset))
+    }
+
+    pub fn scan<F>(&self, abbrev: &AbbrevHash, f: F) -> Result<(), ReadError>
+        where F: FnMut(usize, constant::DwTag, usize)
+    {
+        self.common.scan(self.data_offset(), abbrev, f)
+    }
+
     pub fn read(
         r: &mut &'data [u8],
         offset: usize,
@@ -149,9 +521,10 @@ impl<'data, E: Endian> CompilationUnit<'data, E> {
     }
 
     pub fn write<W: Write>(&self, w: &mut W) -> Result<(), WriteError> {
-        let len = Self::base_header_len(self.common.offset_size) + self.common.len();
+        let len = self.total_len() - initial_length_len(self.common.offset_size);
         try!(self.common.write(w, len));
         try!(w.write_all(self.data()));
+        try!(self.common.write_padding(w));
         Ok(())
     }
 }
@@ -161,6 +534,7 @@ pub struct TypeUnitIterator<'data, E: Endian> {
     endian: E,
     data: &'data [u8],
     offset: usize,
+    unsupported: Option<Vec<UnsupportedUnit>>,
 }
 
 impl<'data, E: Endian> TypeUnitIterator<'data, E> {
@@ -169,6 +543,7 @@ impl<'data, E: Endian> TypeUnitIterator<'data, E> {
             endian: endian,
             data: data,
             offset: 0,
+            unsupported: None,
         }
     }
 
@@ -176,17 +551,48 @@ impl<'data, E: Endian> TypeUnitIterator<'data, E> {
         self.offset
     }
 
+    // Skip units with an unsupported version instead of returning an error.
+    //
+    // The skipped units are available from `unsupported`.
+    pub fn skip_unsupported(&mut self) {
+        self.unsupported = Some(Vec::new());
+    }
+
+    // The units that were skipped because their version isn't supported.
+    pub fn unsupported(&self) -> &[UnsupportedUnit] {
+        match self.unsupported {
+            Some(ref unsupported) => unsupported,
+            None => &[],
+        }
+    }
+
     #[cfg_attr(feature = "clippy", allow(should_implement_trait))]
     pub fn next(&mut self) -> Result<Option<TypeUnit<'data, E>>, ReadError> {
-        if self.data.len() == 0 {
-            return Ok(None);
-        }
+        loop {
+            if self.data.is_empty() {
+                return Ok(None);
+            }
 
-        let mut r = self.data;
-        let unit = try!(TypeUnit::read(&mut r, self.offset, self.endian));
-        self.offset += self.data.len() - r.len();
-        self.data = r;
-        Ok(Some(unit))
+            let mut r = self.data;
+            match TypeUnit::read(&mut r, self.offset, self.endian) {
+                Ok(unit) => {
+                    self.offset += self.data.len() - r.len();
+                    self.data = r;
+                    return Ok(Some(unit));
+                }
+                Err(ReadError::UnsupportedVersion(version)) if self.unsupported.is_some() => {
+                    r = self.data;
+                    try!(UnitCommon::<E>::skip(&mut r, self.endian));
+                    self.unsupported.as_mut().unwrap().push(UnsupportedUnit {
+                        offset: self.offset,
+                        version: version,
+                    });
+                    self.offset += self.data.len() - r.len();
+                    self.data = r;
+                }
+                Err(e) => return Err(e),
+            }
+        }
     }
 }
 
@@ -199,22 +605,30 @@ pub struct TypeUnit<'data, E: Endian> {
 }
 
 impl<'data, E: Endian> TypeUnit<'data, E> {
-    fn base_header_len(offset_size: u8) -> usize {
-        // version + abbrev_offset + address_size + type_signature + type_offset
-        2 + offset_size as usize + 1 + 8 + offset_size as usize
+    pub fn data(&'data self) -> &'data [u8] {
+        self.common.data()
     }
 
-    fn total_header_len(offset_size: u8) -> usize {
-        // Includes an extra 4 bytes if offset_size is 8
-        (offset_size as usize * 2 - 4) + Self::base_header_len(offset_size)
+    pub fn data_offset(&'data self) -> usize {
+        self.common.offset + self.header_len()
     }
 
-    pub fn data(&'data self) -> &'data [u8] {
-        self.common.data()
+    // The length of the unit header, including the initial length field.
+    pub fn header_len(&self) -> usize {
+        // type_signature + type_offset
+        self.common.header_len() + 8 + self.common.offset_size as usize
     }
 
-    pub fn data_offset(&'data self) -> usize {
-        self.common.offset + Self::total_header_len(self.common.offset_size)
+    // The length of the unit, including the header but not any padding.
+    pub fn total_len(&self) -> usize {
+        self.header_len() + self.common.len()
+    }
+
+    // The offset of the end of the unit, including any padding.
+    //
+    // This is the offset of the next unit in the section.
+    pub fn end_offset(&self) -> usize {
+        self.common.offset + self.total_len() + self.common.padding
     }
 
     pub fn abbrev(&self, debug_abbrev: &[u8]) -> Result<AbbrevHash, ReadError> {
@@ -225,6 +639,24 @@ impl<'data, E: Endian> TypeUnit<'data, E> {
         self.common.entries(self.data_offset(), abbrev)
     }
 
+    // Iterate over the entries that start before `offset`.
+    pub fn entries_until<'a>(
+        &'a self,
+        offset: usize,
+        abbrev: &'a AbbrevHash
+    ) -> DieIterator<'a, 'data, E> {
+        self.entries(abbrev).until(offset)
+    }
+
+    // Iterate over the first `count` entries, not including null entries.
+    pub fn entries_limited<'a>(
+        &'a self,
+        count: usize,
+        abbrev: &'a AbbrevHash
+    ) -> DieIterator<'a, 'data, E> {
+        self.entries(abbrev).limited(count)
+    }
+
     pub fn entry<'a>(
         &'a self,
         offset: usize,
@@ -233,6 +665,21 @@ impl<'data, E: Endian> TypeUnit<'data, E> {
         self.common.entry(self.data_offset(), offset, abbrev)
     }
 
+    // Iterate over the direct children of the entry at `offset`.
+    pub fn children<'a>(
+        &'a self,
+        offset: usize,
+        abbrev: &'a AbbrevHash
+    ) -> Option<DieChildIterator<'a, 'data, E>> {
+        self.entry(offset, abbrev).map(DieIterator::children)
+    }
+
+    pub fn scan<F>(&self, abbrev: &AbbrevHash, f: F) -> Result<(), ReadError>
+        where F: FnMut(usize, constant::DwTag, usize)
+    {
+        self.common.scan(self.data_offset(), abbrev, f)
+    }
+
     pub fn type_entry<'a>(&'a self, abbrev: &'a AbbrevHash) -> Option<DieIterator<'a, 'data, E>> {
         self.common.entry(self.data_offset(), self.type_offset as usize, abbrev)
     }
@@ -243,6 +690,11 @@ impl<'data, E: Endian> TypeUnit<'data, E> {
         endian: E
     ) -> Result<TypeUnit<'data, E>, ReadError> {
         let (mut common, mut data) = try!(UnitCommon::read(r, offset, endian));
+        // Version 5 type units are in .debug_info.
+        if common.version >= 5 {
+            return Err(ReadError::UnsupportedVersion(common.version));
+        }
+        common.unit_type = constant::DW_UT_type;
 
         // Read the remaining fields out of data
         let type_signature = try!(endian.read_u64(&mut data));
@@ -257,7 +709,10 @@ impl<'data, E: Endian> TypeUnit<'data, E> {
     }
 
     pub fn write<W: Write>(&self, w: &mut W) -> Result<(), WriteError> {
-        let len = Self::base_header_len(self.common.offset_size) + self.common.len();
+        if self.common.version >= 5 {
+            return Err(WriteError::Unsupported("version 5 type unit in .debug_types".to_string()));
+        }
+        let len = self.total_len() - initial_length_len(self.common.offset_size);
         try!(self.common.write(w, len));
         try!(self.common.endian.write_u64(w, self.type_signature));
         try!(write_offset(w,
@@ -265,10 +720,104 @@ impl<'data, E: Endian> TypeUnit<'data, E> {
                           self.common.offset_size,
                           self.type_offset));
         try!(w.write_all(self.data()));
+        try!(self.common.write_padding(w));
         Ok(())
     }
 }
 
+// A unit that owns its section data and abbreviations.
+//
+// The entries of `CompilationUnit` and `TypeUnit` borrow from the section
+// data, so they can't be stored alongside the `Sections` they were read
+// from. This instead holds a reference counted copy of the section, such
+// as the sections returned by `Sections::into_shared`, and reads the unit
+// header again whenever the entries are needed.
+#[derive(Debug)]
+pub struct OwnedUnit<E: Endian, S = Arc<[u8]>> {
+    endian: E,
+    section: S,
+    offset: usize,
+    type_unit: bool,
+    abbrev: AbbrevHash,
+}
+
+impl<E, S> OwnedUnit<E, S>
+    where E: Endian,
+          S: Deref<Target = [u8]> + Clone
+{
+    // Read the compilation unit at the given offset in .debug_info.
+    pub fn compilation_unit(sections: &Sections<E, S>, offset: usize) -> Result<Self, ReadError> {
+        Self::new(sections, sections.debug_info.clone(), offset, false)
+    }
+
+    // Read the type unit at the given offset in .debug_types.
+    pub fn type_unit(sections: &Sections<E, S>, offset: usize) -> Result<Self, ReadError> {
+        Self::new(sections, sections.debug_types.clone(), offset, true)
+    }
+
+    fn new(
+        sections: &Sections<E, S>,
+        section: S,
+        offset: usize,
+        type_unit: bool
+    ) -> Result<Self, ReadError> {
+        if offset >= section.len() {
+            return Err(ReadError::Invalid);
+        }
+        let abbrev = {
+            let mut r = &section[offset..];
+            let (common, _) = try!(UnitCommon::read(&mut r, offset, sections.endian));
+            try!(sections.abbrev(&common))
+        };
+        Ok(OwnedUnit {
+            endian: sections.endian,
+            section: section,
+            offset: offset,
+            type_unit: type_unit,
+            abbrev: abbrev,
+        })
+    }
+}
+
+impl<E, S> OwnedUnit<E, S>
+    where E: Endian,
+          S: Deref<Target = [u8]>
+{
+    // The offset of the unit in its section.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn is_type_unit(&self) -> bool {
+        self.type_unit
+    }
+
+    pub fn abbrev(&self) -> &AbbrevHash {
+        &self.abbrev
+    }
+
+    // Call `f` with an iterator over the entries of the unit.
+    pub fn with_entries<F, R>(&self, f: F) -> Result<R, ReadError>
+        where F: FnOnce(DieIterator<E>) -> R
+    {
+        let mut r = &self.section[self.offset..];
+        if self.type_unit {
+            let unit = try!(TypeUnit::read(&mut r, self.offset, self.endian));
+            Ok(f(unit.entries(&self.abbrev)))
+        } else {
+            let unit = try!(CompilationUnit::read(&mut r, self.offset, self.endian));
+            Ok(f(unit.entries(&self.abbrev)))
+        }
+    }
+
+    // Call `f` with a tree of the entries of the unit.
+    pub fn with_tree<F, R>(&self, f: F) -> Result<R, ReadError>
+        where F: FnOnce(DieTree<E>) -> R
+    {
+        self.with_entries(|entries| f(entries.tree()))
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct UnitCommon<'data, E: Endian> {
     pub offset: usize,
@@ -277,7 +826,29 @@ pub struct UnitCommon<'data, E: Endian> {
     pub address_size: u8,
     pub offset_size: u8,
     pub abbrev_offset: u64,
+    // The type of the unit.
+    //
+    // This is only in the header in version 5. For earlier versions, it is
+    // DW_UT_compile for units in .debug_info and DW_UT_type for units in
+    // .debug_types.
+    pub unit_type: constant::DwUt,
+    // The dwo_id of a version 5 skeleton or split compilation unit, or the
+    // type signature of a version 5 type unit. Otherwise this is 0.
+    pub unit_id: u64,
+    // The offset of the type entry of a version 5 type unit, relative to
+    // the start of the unit. Otherwise this is 0.
+    pub type_offset: u64,
     pub data: &'data [u8],
+    // The number of padding bytes following the unit.
+    //
+    // These are zero bytes that are written after the unit, so that
+    // rewriting a unit doesn't change the offsets of subsequent units.
+    // Set this to 0 to remove the padding.
+    pub padding: usize,
+    // Corrections for bugs in the producer of the unit.
+    //
+    // These aren't read from the unit header. See `QuirkDatabase::apply`.
+    pub quirks: Quirks,
 }
 
 impl<'data, E: Endian + Default> Default for UnitCommon<'data, E> {
@@ -289,7 +860,12 @@ impl<'data, E: Endian + Default> Default for UnitCommon<'data, E> {
             address_size: 4,
             offset_size: 4,
             abbrev_offset: 0,
+            unit_type: constant::DW_UT_compile,
+            unit_id: 0,
+            type_offset: 0,
             data: &[],
+            padding: 0,
+            quirks: Default::default(),
         }
     }
 }
@@ -304,6 +880,17 @@ impl<'data, E: Endian> UnitCommon<'data, E> {
         self.data.len()
     }
 
+    // The size of DW_FORM_ref_addr values.
+    //
+    // This is the address size in version 2, and the offset size otherwise.
+    pub fn ref_addr_size(&self) -> u8 {
+        if self.version == 2 && !self.quirks.ref_addr_offset_size {
+            self.address_size
+        } else {
+            self.offset_size
+        }
+    }
+
     pub fn abbrev(&self, debug_abbrev: &[u8]) -> Result<AbbrevHash, ReadError> {
         let offset = self.abbrev_offset as usize;
         let len = debug_abbrev.len();
@@ -337,6 +924,33 @@ impl<'data, E: Endian> UnitCommon<'data, E> {
         Some(DieIterator::new(&self.data[relative_offset..], offset, self, abbrev))
     }
 
+    // Call `f` with the offset, tag, and attribute count of each entry.
+    //
+    // Null entries are skipped. Attribute values are skipped without being
+    // decoded, so this is much faster than `entries` when only the tags
+    // are needed.
+    pub fn scan<F>(&self, data_offset: usize, abbrev: &AbbrevHash, mut f: F) -> Result<(), ReadError>
+        where F: FnMut(usize, constant::DwTag, usize)
+    {
+        let mut r = self.data;
+        while !r.is_empty() {
+            let offset = data_offset + self.data.len() - r.len();
+            let code = try!(leb128::read_u64(&mut r));
+            if code == 0 {
+                continue;
+            }
+            let abbrev = match abbrev.get(code) {
+                Some(abbrev) => abbrev,
+                None => return Err(ReadError::Invalid),
+            };
+            for attribute in &abbrev.attributes {
+                try!(AttributeData::skip(&mut r, self, attribute.form));
+            }
+            f(offset, abbrev.tag, abbrev.attributes.len());
+        }
+        Ok(())
+    }
+
     pub fn read(
         r: &mut &'data [u8],
         offset: usize,
@@ -346,15 +960,38 @@ impl<'data, E: Endian> UnitCommon<'data, E> {
         let mut data = &r[..len];
 
         let version = try!(endian.read_u16(&mut data));
-        // TODO: is this correct?
-        if version < 2 || version > 4 {
-            return Err(ReadError::Unsupported);
+        // Version 1 units use a different format, which isn't supported.
+        if version < 2 || version > 5 {
+            return Err(ReadError::UnsupportedVersion(version));
         }
 
-        let abbrev_offset = try!(read_offset(&mut data, endian, offset_size));
-        let address_size = try!(read_u8(&mut data));
+        let mut unit_type = constant::DW_UT_compile;
+        let mut unit_id = 0;
+        let mut type_offset = 0;
+        let abbrev_offset;
+        let address_size;
+        if version >= 5 {
+            unit_type = constant::DwUt(try!(read_u8(&mut data)));
+            address_size = try!(read_u8(&mut data));
+            abbrev_offset = try!(read_offset(&mut data, endian, offset_size));
+            match unit_type {
+                constant::DW_UT_compile | constant::DW_UT_partial => {}
+                constant::DW_UT_skeleton | constant::DW_UT_split_compile => {
+                    unit_id = try!(endian.read_u64(&mut data));
+                }
+                constant::DW_UT_type | constant::DW_UT_split_type => {
+                    unit_id = try!(endian.read_u64(&mut data));
+                    type_offset = try!(read_offset(&mut data, endian, offset_size));
+                }
+                _ => return Err(ReadError::Unsupported),
+            }
+        } else {
+            abbrev_offset = try!(read_offset(&mut data, endian, offset_size));
+            address_size = try!(read_u8(&mut data));
+        }
 
         *r = &r[len..];
+        let padding = Self::read_padding(r);
         Ok((UnitCommon {
             offset: offset,
             endian: endian,
@@ -362,11 +999,47 @@ impl<'data, E: Endian> UnitCommon<'data, E> {
             address_size: address_size,
             offset_size: offset_size,
             abbrev_offset: abbrev_offset,
+            unit_type: unit_type,
+            unit_id: unit_id,
+            type_offset: type_offset,
             data: Default::default(),
+            padding: padding,
+            quirks: Default::default(),
         },
             data))
     }
 
+    // Skip over a unit and the padding following it, without reading
+    // the rest of the header.
+    fn skip(r: &mut &'data [u8], endian: E) -> Result<(), ReadError> {
+        let (_, len) = try!(read_initial_length(r, endian));
+        *r = &r[len..];
+        Self::read_padding(r);
+        Ok(())
+    }
+
+    // Skip over padding between units.
+    //
+    // A unit length of 0 is never valid, so whole words of zeros are padding.
+    // Zeros at the end of the section are also padding. Padding that isn't a
+    // multiple of 4 bytes can't be distinguished from the start of the next
+    // unit, so it isn't handled.
+    fn read_padding(r: &mut &'data [u8]) -> usize {
+        let mut len = r.iter().take_while(|&&x| x == 0).count();
+        if len != r.len() {
+            len -= len % 4;
+        }
+        *r = &r[len..];
+        len
+    }
+
+    pub fn write_padding<W: Write>(&self, w: &mut W) -> Result<(), WriteError> {
+        for _ in 0..self.padding {
+            try!(write_u8(w, 0));
+        }
+        Ok(())
+    }
+
     pub fn write<W: Write>(&self, w: &mut W, len: usize) -> Result<(), WriteError> {
         match self.offset_size {
             4 => {
@@ -382,10 +1055,50 @@ impl<'data, E: Endian> UnitCommon<'data, E> {
             _ => return Err(WriteError::Unsupported(format!("offset size {}", self.offset_size))),
         };
         try!(self.endian.write_u16(w, self.version));
-        try!(write_offset(w, self.endian, self.offset_size, self.abbrev_offset));
-        try!(write_u8(w, self.address_size));
+        if self.version >= 5 {
+            try!(write_u8(w, self.unit_type.0));
+            try!(write_u8(w, self.address_size));
+            try!(write_offset(w, self.endian, self.offset_size, self.abbrev_offset));
+            match self.unit_type {
+                constant::DW_UT_compile | constant::DW_UT_partial => {}
+                constant::DW_UT_skeleton | constant::DW_UT_split_compile => {
+                    try!(self.endian.write_u64(w, self.unit_id));
+                }
+                constant::DW_UT_type | constant::DW_UT_split_type => {
+                    try!(self.endian.write_u64(w, self.unit_id));
+                    try!(write_offset(w, self.endian, self.offset_size, self.type_offset));
+                }
+                _ => return Err(WriteError::Unsupported(format!("unit type {}", self.unit_type))),
+            }
+        } else {
+            try!(write_offset(w, self.endian, self.offset_size, self.abbrev_offset));
+            try!(write_u8(w, self.address_size));
+        }
         Ok(())
     }
+
+    // The length of the header fields that `read` and `write` handle,
+    // including the initial length field.
+    pub fn header_len(&self) -> usize {
+        // version + abbrev_offset + address_size
+        let mut len = 2 + self.offset_size as usize + 1;
+        if self.version >= 5 {
+            len += match self.unit_type {
+                constant::DW_UT_skeleton | constant::DW_UT_split_compile => 1 + 8,
+                constant::DW_UT_type | constant::DW_UT_split_type => {
+                    1 + 8 + self.offset_size as usize
+                }
+                _ => 1,
+            };
+        }
+        initial_length_len(self.offset_size) + len
+    }
+}
+
+// The length of the initial length field, which includes an extra 4 bytes
+// if offset_size is 8.
+fn initial_length_len(offset_size: u8) -> usize {
+    offset_size as usize * 2 - 4
 }
 
 #[cfg(test)]
@@ -408,7 +1121,12 @@ mod test {
                 address_size: 4,
                 offset_size: offset_size,
                 abbrev_offset: 0x12,
+                unit_type: constant::DW_UT_compile,
+                unit_id: 0,
+                type_offset: 0,
                 data: &data[..],
+                padding: 0,
+                quirks: Default::default(),
             },
         };
 
@@ -427,6 +1145,9 @@ mod test {
         ]);
         assert_eq!(r.len(), 0);
         assert_eq!(read_val, write_val);
+        assert_eq!(read_val.header_len(), 11);
+        assert_eq!(read_val.total_len(), buf.len());
+        assert_eq!(read_val.end_offset(), buf.len());
     }
 
     #[test]
@@ -444,7 +1165,12 @@ mod test {
                 address_size: 4,
                 offset_size: offset_size,
                 abbrev_offset: 0x12,
+                unit_type: constant::DW_UT_compile,
+                unit_id: 0,
+                type_offset: 0,
                 data: &data,
+                padding: 0,
+                quirks: Default::default(),
             },
         };
 
@@ -463,6 +1189,61 @@ mod test {
         ]);
         assert_eq!(r.len(), 0);
         assert_eq!(read_val, write_val);
+        assert_eq!(read_val.header_len(), 23);
+        assert_eq!(read_val.total_len(), buf.len());
+    }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn compilation_unit_5() {
+        let offset = 0;
+        let endian = LittleEndian;
+        let data = [0x01, 0x23, 0x45, 0x67];
+        let write_val = CompilationUnit {
+            common: UnitCommon {
+                offset: offset,
+                endian: endian,
+                version: 5,
+                address_size: 8,
+                offset_size: 4,
+                abbrev_offset: 0x12,
+                unit_type: constant::DW_UT_skeleton,
+                unit_id: 0x0123456789abcdef,
+                type_offset: 0,
+                data: &data[..],
+                padding: 0,
+                quirks: Default::default(),
+            },
+        };
+
+        let mut buf = Vec::new();
+        write_val.write(&mut buf).unwrap();
+
+        let mut r = &buf[..];
+        let read_val = CompilationUnit::read(&mut r, offset, endian).unwrap();
+
+        assert_eq!(&buf[..], [
+            0x14, 0x00, 0x00, 0x00,
+            0x05, 0x00,
+            0x04,
+            0x08,
+            0x12, 0x00, 0x00, 0x00,
+            0xef, 0xcd, 0xab, 0x89, 0x67, 0x45, 0x23, 0x01,
+            0x01, 0x23, 0x45, 0x67
+        ]);
+        assert_eq!(r.len(), 0);
+        assert_eq!(read_val, write_val);
+        assert_eq!(read_val.header_len(), 20);
+        assert_eq!(read_val.total_len(), buf.len());
+        let abbrev = AbbrevHash::default();
+        assert_eq!(read_val.dwo_id(&abbrev).unwrap(), Some(0x0123456789abcdef));
+
+        // Version 6 isn't supported.
+        buf[4] = 6;
+        match CompilationUnit::read(&mut &buf[..], offset, endian) {
+            Err(ReadError::UnsupportedVersion(6)) => {}
+            otherwise => panic!("{:?}", otherwise),
+        }
     }
 
     #[test]
@@ -480,7 +1261,12 @@ mod test {
                 address_size: 4,
                 offset_size: offset_size,
                 abbrev_offset: 0x12,
+                unit_type: constant::DW_UT_type,
+                unit_id: 0,
+                type_offset: 0,
                 data: &data,
+                padding: 0,
+                quirks: Default::default(),
             },
             type_signature: 0x0123456789abcdef,
             type_offset: 0x02,
@@ -503,6 +1289,8 @@ mod test {
         ]);
         assert_eq!(r.len(), 0);
         assert_eq!(read_val, write_val);
+        assert_eq!(read_val.header_len(), 23);
+        assert_eq!(read_val.total_len(), buf.len());
     }
 
     #[test]
@@ -520,7 +1308,12 @@ mod test {
                 address_size: 4,
                 offset_size: offset_size,
                 abbrev_offset: 0x12,
+                unit_type: constant::DW_UT_type,
+                unit_id: 0,
+                type_offset: 0,
                 data: &data,
+                padding: 0,
+                quirks: Default::default(),
             },
             type_signature: 0x0123456789abcdef,
             type_offset: 0x02,
@@ -544,4 +1337,129 @@ mod test {
         assert_eq!(r.len(), 0);
         assert_eq!(read_val, write_val);
     }
+
+    #[test]
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    fn unit_padding() {
+        let endian = BigEndian;
+        let data = [0x01, 0x23, 0x45, 0x67];
+        let padded = CompilationUnit {
+            common: UnitCommon {
+                offset: 0,
+                endian: endian,
+                version: 4,
+                address_size: 4,
+                offset_size: 4,
+                abbrev_offset: 0x12,
+                unit_type: constant::DW_UT_compile,
+                unit_id: 0,
+                type_offset: 0,
+                data: &data,
+                padding: 4,
+                quirks: Default::default(),
+            },
+        };
+        let trailing = CompilationUnit {
+            common: UnitCommon {
+                offset: 0x13,
+                padding: 2,
+                ..Default::default()
+            },
+        };
+
+        let mut buf = Vec::new();
+        padded.write(&mut buf).unwrap();
+        trailing.write(&mut buf).unwrap();
+
+        assert_eq!(&buf[..], [
+            0x00, 0x00, 0x00, 0x0b,
+            0x00, 0x04,
+            0x00, 0x00, 0x00, 0x12,
+            0x04,
+            0x01, 0x23, 0x45, 0x67,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x07,
+            0x00, 0x04,
+            0x00, 0x00, 0x00, 0x00,
+            0x04,
+            0x00, 0x00
+        ]);
+
+        let mut units = CompilationUnitIterator::new(endian, &buf);
+        assert_eq!(padded.total_len(), 0x0f);
+        assert_eq!(padded.end_offset(), 0x13);
+        assert_eq!(trailing.end_offset(), buf.len());
+        assert_eq!(units.next().unwrap(), Some(padded));
+        assert_eq!(units.offset(), 0x13);
+        assert_eq!(units.next().unwrap(), Some(trailing));
+        assert_eq!(units.offset(), buf.len());
+        assert_eq!(units.next().unwrap(), None);
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let endian = LittleEndian;
+        let data = [0x01, 0x23, 0x45, 0x67];
+        let old = CompilationUnit {
+            common: UnitCommon {
+                endian: endian,
+                version: 1,
+                data: &data,
+                padding: 4,
+                ..Default::default()
+            },
+        };
+        let new = CompilationUnit {
+            common: UnitCommon {
+                offset: 0x13,
+                endian: endian,
+                data: &data,
+                ..Default::default()
+            },
+        };
+
+        let mut buf = Vec::new();
+        old.write(&mut buf).unwrap();
+        new.write(&mut buf).unwrap();
+
+        let mut units = CompilationUnitIterator::new(endian, &buf);
+        match units.next() {
+            Err(ReadError::UnsupportedVersion(1)) => {}
+            otherwise => panic!("{:?}", otherwise),
+        }
+
+        let mut units = CompilationUnitIterator::new(endian, &buf);
+        units.skip_unsupported();
+        assert_eq!(units.next().unwrap(), Some(new));
+        assert_eq!(units.next().unwrap(), None);
+        assert_eq!(units.unsupported(), &[UnsupportedUnit { offset: 0, version: 1 }]);
+    }
+
+    #[test]
+    fn unit_index() {
+        let endian = LittleEndian;
+        let data = [0x01, 0x23, 0x45, 0x67];
+        let mut buf = Vec::new();
+        for &(offset, padding) in &[(0, 4), (0x13, 0)] {
+            let unit = CompilationUnit {
+                common: UnitCommon {
+                    offset: offset,
+                    endian: endian,
+                    data: &data,
+                    padding: padding,
+                    ..Default::default()
+                },
+            };
+            unit.write(&mut buf).unwrap();
+        }
+
+        let index = UnitIndex::new(CompilationUnitIterator::new(endian, &buf)).unwrap();
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.unit_containing(0), Some(0));
+        assert_eq!(index.unit_containing(0x12), Some(0));
+        assert_eq!(index.unit_containing(0x13), Some(0x13));
+        assert_eq!(index.unit_containing(0x21), Some(0x13));
+        assert_eq!(index.unit_containing(0x22), None);
+        assert_eq!(UnitIndex::default().unit_containing(0), None);
+    }
 }
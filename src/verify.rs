@@ -0,0 +1,388 @@
+use std;
+use std::io::Write;
+use std::ops::Deref;
+
+use abbrev::AbbrevHash;
+use class;
+use die::{AttributeContext, AttributeData, Die, DieIterator};
+use endian::Endian;
+use read::ReadError;
+use symbols::FunctionIndex;
+use unit::UnsupportedUnit;
+use Sections;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    // The debug info is valid, but is likely to be a producer bug or to
+    // cause problems for consumers.
+    Warning,
+    // The debug info is invalid, or can't be read.
+    Error,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub section: &'static str,
+    pub offset: usize,
+    pub message: String,
+    // Other offsets in `section` that the diagnostic refers to, such as the
+    // entry that a subprogram overlaps.
+    pub related: Vec<usize>,
+}
+
+impl Diagnostic {
+    // Write the diagnostic as a JSON object.
+    pub fn write_json<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        try!(write!(w,
+                    "{{\"severity\":\"{}\",\"section\":\"{}\",\"offset\":{},\"message\":\"",
+                    self.severity.name(),
+                    self.section,
+                    self.offset));
+        try!(write_json_string(w, &self.message));
+        try!(write!(w, "\",\"related\":["));
+        for (i, offset) in self.related.iter().enumerate() {
+            if i != 0 {
+                try!(write!(w, ","));
+            }
+            try!(write!(w, "{}", offset));
+        }
+        write!(w, "]}}")
+    }
+}
+
+fn write_json_string<W: Write>(w: &mut W, s: &str) -> std::io::Result<()> {
+    for c in s.chars() {
+        match c {
+            '"' => try!(write!(w, "\\\"")),
+            '\\' => try!(write!(w, "\\\\")),
+            '\n' => try!(write!(w, "\\n")),
+            c if (c as u32) < 0x20 => try!(write!(w, "\\u{:04x}", c as u32)),
+            c => try!(write!(w, "{}", c)),
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct Verifier {
+    // Report abbrev attributes that use a form which is not valid for any
+    // of the attribute's classes.
+    pub strict_forms: bool,
+    // Report DW_AT_sibling attributes that don't match the offset of the
+    // entry following the children.
+    pub strict_siblings: bool,
+    // Report DW_TAG_subprogram entries whose address ranges overlap
+    // another subprogram in any compilation unit.
+    pub overlapping_functions: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Verifier {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+
+    // Return true if any of the diagnostics are errors.
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    // Report an error.
+    pub fn report(&mut self, section: &'static str, offset: usize, message: String) {
+        self.add(Severity::Error, section, offset, message, Vec::new());
+    }
+
+    pub fn add(
+        &mut self,
+        severity: Severity,
+        section: &'static str,
+        offset: usize,
+        message: String,
+        related: Vec<usize>
+    ) {
+        self.diagnostics.push(Diagnostic {
+            severity: severity,
+            section: section,
+            offset: offset,
+            message: message,
+            related: related,
+        });
+    }
+
+    // Write the diagnostics as a JSON object with a "diagnostics" array.
+    pub fn write_json<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        try!(write!(w, "{{\"diagnostics\":["));
+        for (i, diagnostic) in self.diagnostics.iter().enumerate() {
+            if i != 0 {
+                try!(write!(w, ","));
+            }
+            try!(diagnostic.write_json(w));
+        }
+        writeln!(w, "]}}")
+    }
+
+    pub fn verify<E, S>(&mut self, sections: &Sections<E, S>) -> Result<(), ReadError>
+        where E: Endian,
+              S: Deref<Target = [u8]>
+    {
+        let mut abbrev_offsets = Vec::new();
+        let mut functions = FunctionIndex::new();
+
+        let mut units = sections.compilation_units();
+        units.skip_unsupported();
+        while let Some(unit) = try!(units.next()) {
+            abbrev_offsets.push(unit.common.abbrev_offset as usize);
+            if let Ok(abbrev) = sections.abbrev(&unit.common) {
+                let context = sections.attribute_context(&unit.common);
+                self.verify_entries(".debug_info", unit.entries(&abbrev), &context);
+                if self.overlapping_functions {
+                    // Read errors have already been reported.
+                    let _ = functions.add_unit(sections, &unit, &abbrev);
+                }
+            }
+        }
+        if self.overlapping_functions {
+            self.verify_functions(&functions);
+        }
+        for unit in units.unsupported() {
+            self.report_unsupported(".debug_info", unit);
+        }
+        let mut units = sections.type_units();
+        units.skip_unsupported();
+        while let Some(unit) = try!(units.next()) {
+            abbrev_offsets.push(unit.common.abbrev_offset as usize);
+            if let Ok(abbrev) = sections.abbrev(&unit.common) {
+                let context = sections.attribute_context(&unit.common);
+                self.verify_entries(".debug_types", unit.entries(&abbrev), &context);
+            }
+        }
+        for unit in units.unsupported() {
+            self.report_unsupported(".debug_types", unit);
+        }
+
+        abbrev_offsets.sort();
+        abbrev_offsets.dedup();
+        for offset in abbrev_offsets {
+            if offset >= sections.debug_abbrev.len() {
+                self.report(".debug_abbrev",
+                            offset,
+                            "abbrev offset is outside section".to_string());
+                continue;
+            }
+            match AbbrevHash::read(&mut &sections.debug_abbrev[offset..]) {
+                Ok(abbrev_hash) => self.verify_abbrev_hash(offset, &abbrev_hash),
+                Err(e) => self.report(".debug_abbrev", offset, format!("read error {:?}", e)),
+            }
+        }
+        Ok(())
+    }
+
+    fn report_unsupported(&mut self, section: &'static str, unit: &UnsupportedUnit) {
+        self.add(Severity::Warning,
+                 section,
+                 unit.offset,
+                 format!("unit version {} is not supported", unit.version),
+                 Vec::new());
+    }
+
+    fn verify_entries<'a, 'data, E>(&mut self,
+                                    section: &'static str,
+                                    mut entries: DieIterator<'a, 'data, E>,
+                                    context: &AttributeContext)
+        where E: Endian
+    {
+        if self.strict_siblings {
+            entries.enable_sibling_validation();
+        }
+        entries.enable_attribute_spans();
+        loop {
+            match entries.next() {
+                Ok(Some(entry)) => self.verify_entry(section, entry, context),
+                Ok(None) => break,
+                Err(e) => {
+                    let offset = entries.offset();
+                    self.report(section, offset, format!("read error {:?}", e));
+                    break;
+                }
+            }
+        }
+        for mismatch in entries.sibling_mismatches() {
+            let message = format!("sibling 0x{:x} does not match actual sibling 0x{:x}",
+                                  mismatch.sibling,
+                                  mismatch.actual);
+            let related = vec![mismatch.sibling, mismatch.actual];
+            self.add(Severity::Error, section, mismatch.offset, message, related);
+        }
+    }
+
+    // Verify the attributes of an entry in the given section.
+    //
+    // Diagnostics for an attribute use its offset if the entry has
+    // `attribute_spans`, and otherwise the offset of the entry.
+    pub fn verify_entry(&mut self, section: &'static str, entry: &Die, context: &AttributeContext) {
+        for (i, attribute) in entry.attributes.iter().enumerate() {
+            if let AttributeData::StringOffset(val) = attribute.data {
+                if attribute.data.as_string_strict_in(context).is_err() {
+                    let message = format!("attribute {} has invalid string offset 0x{:x}",
+                                          attribute.at,
+                                          val);
+                    let offset = entry.attribute_spans
+                        .get(i)
+                        .map_or(entry.offset, |span| span.offset);
+                    self.report(section, offset, message);
+                }
+            }
+        }
+    }
+
+    // Verify that the address ranges of the functions don't overlap.
+    pub fn verify_functions(&mut self, functions: &FunctionIndex) {
+        for overlap in functions.overlaps() {
+            let message = format!("subprogram overlaps subprogram 0x{:x} at address 0x{:x}",
+                                  overlap.first,
+                                  overlap.address);
+            self.add(Severity::Warning,
+                     ".debug_info",
+                     overlap.second,
+                     message,
+                     vec![overlap.first]);
+        }
+    }
+
+    // Verify the abbrev declarations in the table at the given offset.
+    pub fn verify_abbrev_hash(&mut self, offset: usize, abbrev_hash: &AbbrevHash) {
+        if !self.strict_forms {
+            return;
+        }
+        let mut abbrevs: Vec<_> = abbrev_hash.iter().map(|(_, abbrev)| abbrev).collect();
+        abbrevs.sort_by_key(|abbrev| abbrev.code);
+        for abbrev in abbrevs {
+            for attribute in &abbrev.attributes {
+                if !class::is_valid_form(attribute.at, attribute.form) {
+                    let message = format!("abbrev {}: attribute {} has invalid form {}",
+                                          abbrev.code,
+                                          attribute.at,
+                                          attribute.form);
+                    self.add(Severity::Warning, ".debug_abbrev", offset, message, Vec::new());
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}+0x{:x}: {}", self.section, self.offset, self.message)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use abbrev::*;
+    use constant::*;
+    use die::*;
+
+    #[test]
+    fn strict_forms() {
+        let mut abbrev_hash = AbbrevHash::new();
+        abbrev_hash.insert(Abbrev {
+            code: 1,
+            tag: DW_TAG_variable,
+            children: false,
+            attributes: vec![
+                AbbrevAttribute::new(DW_AT_name, DW_FORM_addr),
+                AbbrevAttribute::new(DW_AT_location, DW_FORM_exprloc),
+            ],
+        });
+
+        let mut verifier = Verifier::new();
+        verifier.verify_abbrev_hash(0x10, &abbrev_hash);
+        assert!(verifier.diagnostics().is_empty());
+
+        verifier.strict_forms = true;
+        verifier.verify_abbrev_hash(0x10, &abbrev_hash);
+        assert_eq!(verifier.diagnostics(),
+                   &[Diagnostic {
+                         severity: Severity::Warning,
+                         section: ".debug_abbrev",
+                         offset: 0x10,
+                         message: "abbrev 1: attribute name has invalid form addr".to_string(),
+                         related: Vec::new(),
+                     }]);
+        assert!(!verifier.has_errors());
+    }
+
+    #[test]
+    fn dangling_strp() {
+        let context = AttributeContext {
+            offset_size: 4,
+            debug_str: b"\0main\0",
+            debug_line_str: &[],
+        };
+        let entry = Die::new(0x20, 1, DW_TAG_variable, false, vec![
+            Attribute { at: DW_AT_name, data: AttributeData::StringOffset(1) },
+            Attribute { at: DW_AT_linkage_name, data: AttributeData::StringOffset(0x40) },
+        ]);
+
+        let mut verifier = Verifier::new();
+        verifier.verify_entry(".debug_info", &entry, &context);
+        assert_eq!(verifier.diagnostics(),
+                   &[Diagnostic {
+                         severity: Severity::Error,
+                         section: ".debug_info",
+                         offset: 0x20,
+                         message: "attribute linkage_name has invalid string offset 0x40"
+                             .to_string(),
+                         related: Vec::new(),
+                     }]);
+        assert!(verifier.has_errors());
+
+        let mut entry = entry;
+        entry.attribute_spans = vec![
+            AttributeSpan { offset: 0x21, len: 4 },
+            AttributeSpan { offset: 0x25, len: 4 },
+        ];
+        let mut verifier = Verifier::new();
+        verifier.verify_entry(".debug_info", &entry, &context);
+        assert_eq!(verifier.diagnostics()[0].offset, 0x25);
+    }
+
+    #[test]
+    fn json() {
+        let mut verifier = Verifier::new();
+        verifier.add(Severity::Warning,
+                     ".debug_info",
+                     0x40,
+                     "subprogram \"f\" overlaps".to_string(),
+                     vec![0x20, 0x30]);
+        verifier.report(".debug_abbrev", 8, "read error Eof".to_string());
+        let mut buf = Vec::new();
+        verifier.write_json(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), concat!(
+            r#"{"diagnostics":["#,
+            r#"{"severity":"warning","section":".debug_info","offset":64,"#,
+            r#""message":"subprogram \"f\" overlaps","related":[32,48]},"#,
+            r#"{"severity":"error","section":".debug_abbrev","offset":8,"#,
+            r#""message":"read error Eof","related":[]}]}"#,
+            "\n"));
+    }
+}
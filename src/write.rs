@@ -1,11 +1,21 @@
 use std;
 use std::io::Write;
 
+use constant;
 use endian::Endian;
+use read::ReadError;
 
+// An error from one of the functions that write sections.
+//
+// These functions (in `build`, `dedup`, `merge`, `rewrite`, `text` and
+// `yaml`) produce byte-identical output for identical input. Hash maps are
+// only used for lookups; anything that determines the layout of the output,
+// such as the order of units, abbreviations, strings and file entries,
+// follows the order of the input or uses an ordered map.
 #[derive(Debug)]
 pub enum WriteError {
     Io(std::io::Error),
+    Read(ReadError),
     Invalid(String),
     Unsupported(String),
 }
@@ -16,12 +26,44 @@ impl std::convert::From<std::io::Error> for WriteError {
     }
 }
 
+impl std::convert::From<ReadError> for WriteError {
+    fn from(e: ReadError) -> Self {
+        WriteError::Read(e)
+    }
+}
+
+// Return true for the forms of references within a unit.
+pub fn is_ref_form(form: constant::DwForm) -> bool {
+    matches!(
+        form,
+        constant::DW_FORM_ref1 |
+        constant::DW_FORM_ref2 |
+        constant::DW_FORM_ref4 |
+        constant::DW_FORM_ref8 |
+        constant::DW_FORM_ref_udata
+    )
+}
+
 #[inline]
 pub fn write_u8<W: Write>(w: &mut W, val: u8) -> Result<(), std::io::Error> {
     let buf = [val];
     w.write_all(&buf)
 }
 
+// Write a 3 byte unsigned value, for DW_FORM_strx3 and DW_FORM_addrx3.
+pub fn write_u24<W: Write, E: Endian>(w: &mut W, endian: E, val: u32) -> Result<(), WriteError> {
+    if val > 0xffffff {
+        return Err(WriteError::Invalid(format!("value {} does not fit in 3 bytes", val)));
+    }
+    let buf = if endian.is_big_endian() {
+        [(val >> 16) as u8, (val >> 8) as u8, val as u8]
+    } else {
+        [val as u8, (val >> 8) as u8, (val >> 16) as u8]
+    };
+    try!(w.write_all(&buf));
+    Ok(())
+}
+
 pub fn write_offset<W: Write, E: Endian>(
     w: &mut W,
     endian: E,
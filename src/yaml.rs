@@ -0,0 +1,794 @@
+// Read and write DWARF sections in the YAML format used by LLVM's
+// obj2yaml and yaml2obj.
+//
+// Only the `debug_str`, `debug_abbrev` and `debug_info` keys of the
+// `DWARF` mapping are supported. Both the current format of
+// `debug_abbrev` (a list of tables with an `ID` and a `Table`) and the
+// older format (a single list of abbreviations) are accepted.
+extern crate yaml_rust;
+
+use std;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::ops::Deref;
+
+use self::yaml_rust::{Yaml, YamlLoader};
+
+use abbrev::{Abbrev, AbbrevAttribute, AbbrevHash, AbbrevVec};
+use constant;
+use die::{AttributeData, Die};
+use endian::Endian;
+use leb128;
+use offset::{DebugInfoOffset, UnitOffset};
+use unit::{CompilationUnit, UnitCommon};
+use write::*;
+use Sections;
+
+// Write the sections as the `DWARF` mapping of an obj2yaml document.
+//
+// The output can be placed in an ELF YAML document for use with yaml2obj.
+// Type units and padding between units can't be represented in this
+// format, and return an error.
+pub fn dump<E, S, W>(w: &mut W, sections: &Sections<E, S>) -> Result<(), WriteError>
+    where E: Endian,
+          S: Deref<Target = [u8]>,
+          W: Write
+{
+    if !sections.debug_types.is_empty() {
+        return Err(WriteError::Unsupported("type units".to_string()));
+    }
+    try!(writeln!(w, "DWARF:"));
+
+    if !sections.debug_str.is_empty() {
+        try!(writeln!(w, "  debug_str:"));
+        let mut strings = sections.strings();
+        while let Some((_, string)) = try!(strings.next()) {
+            try!(write!(w, "    - "));
+            try!(write_string(w, string));
+            try!(writeln!(w, ""));
+        }
+    }
+
+    let mut tables = BTreeSet::new();
+    let mut units = sections.compilation_units();
+    while let Some(unit) = try!(units.next()) {
+        tables.insert(unit.common.abbrev_offset);
+    }
+    // The tables are identified by their index, in the order of their offsets.
+    let tables: Vec<u64> = tables.into_iter().collect();
+    if !tables.is_empty() {
+        try!(writeln!(w, "  debug_abbrev:"));
+    }
+    for (id, &offset) in tables.iter().enumerate() {
+        let mut r = match sections.debug_abbrev.get(offset as usize..) {
+            Some(r) => r,
+            None => return Err(WriteError::Invalid(format!("abbrev offset 0x{:x}", offset))),
+        };
+        try!(writeln!(w, "    - ID:              {}", id));
+        try!(writeln!(w, "      Table:"));
+        for abbrev in try!(AbbrevVec::read(&mut r)).iter() {
+            try!(writeln!(w, "        - Code:            0x{:016X}", abbrev.code));
+            try!(writeln!(w, "          Tag:             {}", abbrev.tag.name_or_hex()));
+            let children = if abbrev.children {
+                constant::DW_CHILDREN_yes
+            } else {
+                constant::DW_CHILDREN_no
+            };
+            try!(writeln!(w, "          Children:        {}", children.name().unwrap()));
+            if !abbrev.attributes.is_empty() {
+                try!(writeln!(w, "          Attributes:"));
+            }
+            for attribute in &abbrev.attributes {
+                try!(writeln!(w, "            - Attribute:       {}", attribute.at.name_or_hex()));
+                try!(writeln!(w,
+                              "              Form:            {}",
+                              attribute.form.name_or_hex()));
+                if attribute.form == constant::DW_FORM_implicit_const {
+                    try!(writeln!(w,
+                                  "              Value:           0x{:016X}",
+                                  attribute.implicit_const as u64));
+                }
+            }
+        }
+    }
+
+    let mut units = sections.compilation_units();
+    let mut first = true;
+    while let Some(unit) = try!(units.next()) {
+        if unit.common.padding != 0 {
+            return Err(WriteError::Unsupported("padding between units".to_string()));
+        }
+        if first {
+            try!(writeln!(w, "  debug_info:"));
+            first = false;
+        }
+        // The length doesn't include the initial length field.
+        if unit.common.offset_size == 8 {
+            try!(writeln!(w, "    - Format:          DWARF64"));
+            try!(writeln!(w, "      Length:          0x{:016X}", unit.total_len() - 12));
+        } else {
+            try!(writeln!(w, "    - Length:          0x{:016X}", unit.total_len() - 4));
+        }
+        try!(writeln!(w, "      Version:         {}", unit.common.version));
+        if unit.common.version >= 5 {
+            match unit.common.unit_type {
+                constant::DW_UT_compile | constant::DW_UT_partial => {
+                    try!(writeln!(w,
+                                  "      UnitType:        {}",
+                                  unit.common.unit_type.name().unwrap()));
+                }
+                unit_type => {
+                    return Err(WriteError::Unsupported(format!("unit type {}", unit_type)));
+                }
+            }
+        }
+        let id = tables.binary_search(&unit.common.abbrev_offset).unwrap_or(0);
+        try!(writeln!(w, "      AbbrevTableID:   {}", id));
+        try!(writeln!(w, "      AddrSize:        0x{:02X}", unit.common.address_size));
+        try!(writeln!(w, "      Entries:"));
+        let abbrev = try!(sections.abbrev(&unit.common));
+        let mut entries = unit.entries(&abbrev);
+        while let Some(entry) = try!(entries.next()) {
+            try!(writeln!(w, "        - AbbrCode:        0x{:08X}", entry.code));
+            if !entry.attributes.is_empty() {
+                try!(writeln!(w, "          Values:"));
+            }
+            for attribute in &entry.attributes {
+                try!(write_value(w, &attribute.data));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_value<W: Write>(w: &mut W, data: &AttributeData) -> Result<(), WriteError> {
+    let val = match *data {
+        AttributeData::Null => {
+            return Err(WriteError::Unsupported("null attribute value".to_string()));
+        }
+        AttributeData::Address(val) |
+        AttributeData::Data8(val) |
+        AttributeData::UData(val) |
+        AttributeData::StringOffset(val) |
+        AttributeData::LineStringOffset(val) |
+        AttributeData::RefSig(val) |
+        AttributeData::SecOffset(val) |
+        AttributeData::AddressIndex(val) |
+        AttributeData::StringIndex(val) |
+        AttributeData::RangeListIndex(val) |
+        AttributeData::LocationListIndex(val) |
+        AttributeData::Ref(UnitOffset(val)) |
+        AttributeData::RefAddress(DebugInfoOffset(val)) => val,
+        AttributeData::Data1(val) => val as u64,
+        AttributeData::Data2(val) => val as u64,
+        AttributeData::Data4(val) => val as u64,
+        AttributeData::SData(val) => val as u64,
+        AttributeData::Flag(val) => val as u64,
+        AttributeData::Block(val) |
+        AttributeData::Data16(val) |
+        AttributeData::ExprLoc(val) => {
+            try!(writeln!(w, "            - Value:           0x{:016X}", val.len()));
+            try!(write!(w, "              BlockData:       ["));
+            for (i, byte) in val.iter().enumerate() {
+                let separator = if i == 0 { " " } else { ", " };
+                try!(write!(w, "{}0x{:02X}", separator, byte));
+            }
+            try!(writeln!(w, " ]"));
+            return Ok(());
+        }
+        AttributeData::String(val) => {
+            try!(write!(w, "            - CStr:            "));
+            try!(write_string(w, val));
+            try!(writeln!(w, ""));
+            return Ok(());
+        }
+    };
+    try!(writeln!(w, "            - Value:           0x{:016X}", val));
+    Ok(())
+}
+
+// Write a quoted YAML string.
+//
+// YAML strings are unicode, so strings that aren't valid UTF-8 can't be
+// written.
+fn write_string<W: Write>(w: &mut W, val: &[u8]) -> Result<(), WriteError> {
+    let val = match std::str::from_utf8(val) {
+        Ok(val) => val,
+        Err(_) => return Err(WriteError::Unsupported("string is not UTF-8".to_string())),
+    };
+    if val.chars().any(char::is_control) {
+        try!(write!(w, "\""));
+        for c in val.chars() {
+            match c {
+                '"' | '\\' => try!(write!(w, "\\{}", c)),
+                _ if c.is_control() => try!(write!(w, "\\x{:02X}", c as u32)),
+                _ => try!(write!(w, "{}", c)),
+            }
+        }
+        try!(write!(w, "\""));
+    } else {
+        try!(write!(w, "'{}'", val.replace('\'', "''")));
+    }
+    Ok(())
+}
+
+// Parse an obj2yaml document, and write the sections.
+//
+// The `DWARF` mapping is used if the document has one, otherwise the
+// document itself must be the `DWARF` mapping. This replaces .debug_abbrev
+// and .debug_info, and replaces .debug_str if the document has a
+// `debug_str` key. The other sections are unchanged.
+//
+// The `Length` of units is ignored, and is calculated from the entries.
+pub fn assemble<E: Endian>(sections: &mut Sections<E>, text: &str) -> Result<(), WriteError> {
+    let docs = try!(YamlLoader::load_from_str(text)
+        .map_err(|e| WriteError::Invalid(e.to_string())));
+    let doc = match docs.first() {
+        Some(doc) => doc,
+        None => return Err(invalid("empty document")),
+    };
+    let dwarf = match doc["DWARF"] {
+        Yaml::BadValue => doc,
+        ref dwarf => dwarf,
+    };
+
+    let debug_str = match dwarf["debug_str"] {
+        Yaml::Array(ref strings) => {
+            let mut debug_str = Vec::new();
+            for string in strings {
+                debug_str.extend_from_slice(try!(string_value(string)).as_bytes());
+                debug_str.push(0);
+            }
+            Some(debug_str)
+        }
+        Yaml::BadValue => None,
+        _ => return Err(invalid("debug_str must be a list")),
+    };
+
+    // A list of the ID, offset and abbreviations of each table.
+    let mut debug_abbrev = Vec::new();
+    let mut tables = Vec::new();
+    match dwarf["debug_abbrev"] {
+        Yaml::Array(ref items) => {
+            if items.iter().all(|item| item["Table"].is_badvalue() && item["ID"].is_badvalue()) {
+                // Older versions of LLVM have a single table.
+                try!(add_table(&mut debug_abbrev, &mut tables, 0, items));
+            } else {
+                for (index, item) in items.iter().enumerate() {
+                    let id = match item["ID"] {
+                        Yaml::BadValue => index as u64,
+                        ref id => try!(number(id, "ID")),
+                    };
+                    let table = match item["Table"] {
+                        Yaml::Array(ref table) => &table[..],
+                        Yaml::BadValue => &[],
+                        _ => return Err(invalid("abbrev table must be a list")),
+                    };
+                    try!(add_table(&mut debug_abbrev, &mut tables, id, table));
+                }
+            }
+        }
+        Yaml::BadValue => {}
+        _ => return Err(invalid("debug_abbrev must be a list")),
+    }
+
+    let mut debug_info = Vec::new();
+    match dwarf["debug_info"] {
+        Yaml::Array(ref units) => {
+            for (index, unit) in units.iter().enumerate() {
+                try!(add_unit(&mut debug_info, sections.endian, &tables, unit)
+                    .map_err(|e| match e {
+                        WriteError::Invalid(message) => {
+                            WriteError::Invalid(format!("debug_info unit {}: {}", index, message))
+                        }
+                        e => e,
+                    }));
+            }
+        }
+        Yaml::BadValue => {}
+        _ => return Err(invalid("debug_info must be a list")),
+    }
+
+    sections.debug_abbrev = debug_abbrev;
+    sections.debug_info = debug_info;
+    if let Some(debug_str) = debug_str {
+        sections.debug_str = debug_str;
+    }
+    Ok(())
+}
+
+fn invalid(message: &str) -> WriteError {
+    WriteError::Invalid(message.to_string())
+}
+
+fn add_table(
+    debug_abbrev: &mut Vec<u8>,
+    tables: &mut Vec<(u64, u64, AbbrevHash)>,
+    id: u64,
+    items: &[Yaml]
+) -> Result<(), WriteError> {
+    let mut abbrevs = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        // yaml2obj numbers the abbreviations if the code is omitted.
+        let code = match item["Code"] {
+            Yaml::BadValue => index as u64 + 1,
+            ref code => try!(number(code, "Code")),
+        };
+        let tag = constant::DwTag(try!(constant_value(&item["Tag"], "Tag", |name| {
+            constant::DwTag::from_name(name).map(|tag| tag.0)
+        })));
+        let children = match item["Children"].as_str() {
+            Some("DW_CHILDREN_yes") => true,
+            Some("DW_CHILDREN_no") => false,
+            _ => return Err(invalid("invalid Children")),
+        };
+        let mut attributes = Vec::new();
+        if let Some(items) = item["Attributes"].as_vec() {
+            for item in items {
+                let at = try!(constant_value(&item["Attribute"], "Attribute", |name| {
+                    constant::DwAt::from_name(name).map(|at| at.0)
+                }));
+                let form = try!(constant_value(&item["Form"], "Form", |name| {
+                    constant::DwForm::from_name(name).map(|form| form.0)
+                }));
+                let (at, form) = (constant::DwAt(at), constant::DwForm(form));
+                if form == constant::DW_FORM_implicit_const {
+                    // The value is written as an unsigned number.
+                    let value = try!(number(&item["Value"], "Value")) as i64;
+                    attributes.push(AbbrevAttribute::implicit_const(at, value));
+                } else if !item["Value"].is_badvalue() {
+                    return Err(WriteError::Invalid(format!("Value with form {}", form)));
+                } else {
+                    attributes.push(AbbrevAttribute::new(at, form));
+                }
+            }
+        }
+        abbrevs.push(Abbrev {
+            code: code,
+            tag: tag,
+            children: children,
+            attributes: attributes,
+        });
+    }
+
+    let offset = debug_abbrev.len();
+    try!(AbbrevVec::new(abbrevs).write(debug_abbrev));
+    let abbrev = try!(AbbrevHash::read(&mut &debug_abbrev[offset..]));
+    tables.push((id, offset as u64, abbrev));
+    Ok(())
+}
+
+fn add_unit<E: Endian>(
+    debug_info: &mut Vec<u8>,
+    endian: E,
+    tables: &[(u64, u64, AbbrevHash)],
+    yaml: &Yaml
+) -> Result<(), WriteError> {
+    let offset_size = match yaml["Format"].as_str() {
+        Some("DWARF64") => 8,
+        Some("DWARF32") | None => 4,
+        Some(format) => return Err(WriteError::Invalid(format!("invalid Format {}", format))),
+    };
+    let version = try!(number(&yaml["Version"], "Version"));
+    if version < 2 || version > 5 {
+        return Err(WriteError::Unsupported(format!("unit version {}", version)));
+    }
+    // The unit types with a unit ID or type offset can't be represented.
+    let unit_type = match yaml["UnitType"] {
+        Yaml::BadValue => constant::DW_UT_compile,
+        _ if version < 5 => return Err(invalid("UnitType requires version 5")),
+        ref unit_type => {
+            let val = try!(constant_value(unit_type, "UnitType", |name| {
+                constant::DwUt::from_name(name).map(|unit_type| unit_type.0 as u16)
+            }));
+            match constant::DwUt(val as u8) {
+                unit_type @ constant::DW_UT_compile |
+                unit_type @ constant::DW_UT_partial if val <= 0xff => unit_type,
+                _ => return Err(WriteError::Unsupported(format!("unit type 0x{:x}", val))),
+            }
+        }
+    };
+    let address_size = match yaml["AddrSize"] {
+        Yaml::BadValue => 8,
+        ref size => try!(limit(try!(number(size, "AddrSize")), 0xff, "AddrSize")),
+    };
+    // Find the table using the ID if given, otherwise the offset.
+    let table = if !yaml["AbbrevTableID"].is_badvalue() {
+        let id = try!(number(&yaml["AbbrevTableID"], "AbbrevTableID"));
+        tables.iter().find(|table| table.0 == id)
+    } else if !yaml["AbbrOffset"].is_badvalue() {
+        let offset = try!(number(&yaml["AbbrOffset"], "AbbrOffset"));
+        tables.iter().find(|table| table.1 == offset)
+    } else {
+        tables.first()
+    };
+    let &(_, abbrev_offset, ref abbrev) = match table {
+        Some(table) => table,
+        None => return Err(invalid("missing abbrev table")),
+    };
+
+    let mut unit = CompilationUnit {
+        common: UnitCommon {
+            offset: debug_info.len(),
+            endian: endian,
+            version: version as u16,
+            address_size: address_size as u8,
+            offset_size: offset_size,
+            abbrev_offset: abbrev_offset,
+            unit_type: unit_type,
+            unit_id: 0,
+            type_offset: 0,
+            data: &[],
+            padding: 0,
+            quirks: Default::default(),
+        },
+    };
+    let mut data = Vec::new();
+    if let Some(entries) = yaml["Entries"].as_vec() {
+        for entry in entries {
+            try!(add_entry(&mut data, &unit.common, abbrev, entry));
+        }
+    }
+    unit.common.data = &data;
+    unit.write(debug_info)
+}
+
+fn add_entry<E: Endian>(
+    data: &mut Vec<u8>,
+    unit: &UnitCommon<E>,
+    abbrev: &AbbrevHash,
+    entry: &Yaml
+) -> Result<(), WriteError> {
+    let code = try!(number(&entry["AbbrCode"], "AbbrCode"));
+    if code == 0 {
+        try!(Die::write_null(data));
+        return Ok(());
+    }
+    let abbrev = match abbrev.get(code) {
+        Some(abbrev) => abbrev,
+        None => return Err(WriteError::Invalid(format!("missing abbrev {}", code))),
+    };
+    let values = match entry["Values"] {
+        Yaml::Array(ref values) => &values[..],
+        Yaml::BadValue => &[],
+        _ => return Err(invalid("Values must be a list")),
+    };
+    if values.len() != abbrev.attributes.len() {
+        return Err(WriteError::Invalid(format!("abbrev {} has {} attributes, found {} values",
+                                               code,
+                                               abbrev.attributes.len(),
+                                               values.len())));
+    }
+
+    try!(leb128::write_u64(data, code));
+    for (value, attribute) in values.iter().zip(&abbrev.attributes) {
+        let bytes;
+        let attribute_data = match attribute.form {
+            constant::DW_FORM_block1 |
+            constant::DW_FORM_block2 |
+            constant::DW_FORM_block4 |
+            constant::DW_FORM_block |
+            constant::DW_FORM_exprloc => {
+                bytes = try!(block_data(&value["BlockData"]));
+                if attribute.form == constant::DW_FORM_exprloc {
+                    AttributeData::ExprLoc(&bytes)
+                } else {
+                    AttributeData::Block(&bytes)
+                }
+            }
+            constant::DW_FORM_data16 => {
+                bytes = try!(block_data(&value["BlockData"]));
+                AttributeData::Data16(&bytes)
+            }
+            // The value is in the abbreviation, so the entry value is optional.
+            constant::DW_FORM_implicit_const => {
+                if !value["Value"].is_badvalue() &&
+                   try!(number(&value["Value"], "Value")) != attribute.implicit_const as u64 {
+                    return Err(WriteError::Invalid("implicit_const value mismatch".to_string()));
+                }
+                AttributeData::SData(attribute.implicit_const)
+            }
+            constant::DW_FORM_string => {
+                AttributeData::String(try!(string_value(&value["CStr"])).as_bytes())
+            }
+            form => {
+                let max = match form {
+                    constant::DW_FORM_data1 => 0xff,
+                    constant::DW_FORM_data2 => 0xffff,
+                    constant::DW_FORM_data4 => 0xffff_ffff,
+                    constant::DW_FORM_flag => 1,
+                    _ => u64::max_value(),
+                };
+                let val = try!(limit(try!(number(&value["Value"], "Value")), max, "Value"));
+                match form {
+                    constant::DW_FORM_addr => AttributeData::Address(val),
+                    constant::DW_FORM_data1 => AttributeData::Data1(val as u8),
+                    constant::DW_FORM_data2 => AttributeData::Data2(val as u16),
+                    constant::DW_FORM_data4 => AttributeData::Data4(val as u32),
+                    constant::DW_FORM_data8 => AttributeData::Data8(val),
+                    constant::DW_FORM_udata => AttributeData::UData(val),
+                    constant::DW_FORM_sdata => AttributeData::SData(val as i64),
+                    constant::DW_FORM_flag => AttributeData::Flag(val != 0),
+                    constant::DW_FORM_flag_present => AttributeData::Flag(true),
+                    constant::DW_FORM_strp => AttributeData::StringOffset(val),
+                    constant::DW_FORM_line_strp => AttributeData::LineStringOffset(val),
+                    constant::DW_FORM_ref1 |
+                    constant::DW_FORM_ref2 |
+                    constant::DW_FORM_ref4 |
+                    constant::DW_FORM_ref8 |
+                    constant::DW_FORM_ref_udata => AttributeData::Ref(UnitOffset(val)),
+                    constant::DW_FORM_ref_addr => AttributeData::RefAddress(DebugInfoOffset(val)),
+                    constant::DW_FORM_ref_sig8 => AttributeData::RefSig(val),
+                    constant::DW_FORM_sec_offset => AttributeData::SecOffset(val),
+                    constant::DW_FORM_addrx |
+                    constant::DW_FORM_addrx1 |
+                    constant::DW_FORM_addrx2 |
+                    constant::DW_FORM_addrx3 |
+                    constant::DW_FORM_addrx4 |
+                    constant::DW_FORM_GNU_addr_index => AttributeData::AddressIndex(val),
+                    constant::DW_FORM_strx |
+                    constant::DW_FORM_strx1 |
+                    constant::DW_FORM_strx2 |
+                    constant::DW_FORM_strx3 |
+                    constant::DW_FORM_strx4 |
+                    constant::DW_FORM_GNU_str_index => AttributeData::StringIndex(val),
+                    constant::DW_FORM_rnglistx => AttributeData::RangeListIndex(val),
+                    constant::DW_FORM_loclistx => AttributeData::LocationListIndex(val),
+                    _ => {
+                        return Err(WriteError::Unsupported(format!("form {}", form.name_or_hex())))
+                    }
+                }
+            }
+        };
+        try!(attribute_data.write(data, unit, attribute.form, false));
+    }
+    Ok(())
+}
+
+fn parse_number(val: &str) -> Option<u64> {
+    if val.starts_with("0x") || val.starts_with("0X") {
+        u64::from_str_radix(&val[2..], 16).ok()
+    } else {
+        val.parse().ok()
+    }
+}
+
+// Return the value of an integer.
+//
+// yaml_rust parses integers as `i64`, and hexadecimal values that don't
+// fit are strings.
+fn number(val: &Yaml, name: &str) -> Result<u64, WriteError> {
+    let val = match *val {
+        Yaml::Integer(val) => Some(val as u64),
+        Yaml::String(ref val) => parse_number(val),
+        _ => None,
+    };
+    val.ok_or_else(|| WriteError::Invalid(format!("invalid {}", name)))
+}
+
+// Return the value of a constant, which may be its name or its value.
+fn constant_value<F>(val: &Yaml, name: &str, from_name: F) -> Result<u16, WriteError>
+    where F: Fn(&str) -> Option<u16>
+{
+    if let Some(val) = val.as_str().and_then(&from_name) {
+        return Ok(val);
+    }
+    let val = try!(number(val, name));
+    limit(val, 0xffff, name).map(|val| val as u16)
+}
+
+// Return an error if a number is larger than `max`.
+fn limit(val: u64, max: u64, name: &str) -> Result<u64, WriteError> {
+    if val > max {
+        return Err(WriteError::Invalid(format!("invalid {} 0x{:x}", name, val)));
+    }
+    Ok(val)
+}
+
+// Return the bytes of a `BlockData` list, which is empty if missing.
+fn block_data(val: &Yaml) -> Result<Vec<u8>, WriteError> {
+    let items = match val.as_vec() {
+        Some(items) => items,
+        None => return Ok(Vec::new()),
+    };
+    items.iter()
+        .map(|item| number(item, "BlockData").and_then(|b| limit(b, 0xff, "BlockData")))
+        .map(|b| b.map(|b| b as u8))
+        .collect()
+}
+
+fn string_value(val: &Yaml) -> Result<&str, WriteError> {
+    match *val {
+        Yaml::String(ref val) |
+        Yaml::Real(ref val) => Ok(val),
+        Yaml::Null => Ok(""),
+        _ => Err(invalid("invalid string")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use constant::*;
+    use endian::*;
+
+    #[test]
+    fn assemble_llvm() {
+        let text = "\
+--- !ELF
+FileHeader:
+  Class: ELFCLASS64
+  Data:  ELFDATA2LSB
+  Type:  ET_REL
+DWARF:
+  debug_str:
+    - ''
+    - main
+  debug_abbrev:
+    - Table:
+        - Code:     1
+          Tag:      DW_TAG_compile_unit
+          Children: DW_CHILDREN_yes
+          Attributes:
+            - Attribute: DW_AT_name
+              Form:      DW_FORM_string
+        - Tag:      DW_TAG_subprogram
+          Children: DW_CHILDREN_no
+          Attributes:
+            - Attribute: DW_AT_name
+              Form:      DW_FORM_strp
+            - Attribute: DW_AT_external
+              Form:      DW_FORM_flag_present
+            - Attribute: DW_AT_frame_base
+              Form:      DW_FORM_exprloc
+            - Attribute: DW_AT_const_value
+              Form:      DW_FORM_sdata
+  debug_info:
+    - Version:  4
+      AddrSize: 8
+      Entries:
+        - AbbrCode: 1
+          Values:
+            - CStr: 'a.c'
+        - AbbrCode: 2
+          Values:
+            - Value: 1
+            - Value: 1
+            - BlockData: [ 0x56 ]
+            - Value: 0xFFFFFFFFFFFFFFFE
+        - AbbrCode: 0
+...
+";
+        let mut sections = Sections::new(LittleEndian);
+        assemble(&mut sections, text).unwrap();
+        assert_eq!(sections.debug_str, b"\0main\0");
+
+        let mut units = sections.compilation_units();
+        let unit = units.next().unwrap().unwrap();
+        assert!(units.next().unwrap().is_none());
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let mut entries = unit.entries(&abbrev);
+        let entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.attr(DW_AT_name), Some(&AttributeData::String(b"a.c")));
+        let entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.tag, DW_TAG_subprogram);
+        assert_eq!(entry.code, 2);
+        let context = sections.attribute_context(&unit.common);
+        assert_eq!(entry.attr(DW_AT_name).unwrap().as_string_in(&context), Some(&b"main"[..]));
+        assert_eq!(entry.attr(DW_AT_frame_base), Some(&AttributeData::ExprLoc(&[0x56])));
+        assert_eq!(entry.attr(DW_AT_const_value), Some(&AttributeData::SData(-2)));
+        assert!(entries.next().unwrap().unwrap().is_null());
+        assert!(entries.next().unwrap().is_none());
+
+        let mut buf = Vec::new();
+        dump(&mut buf, &sections).unwrap();
+        let buf = String::from_utf8(buf).unwrap();
+        assert!(buf.contains("\n      AbbrevTableID:   0\n"));
+        let mut redump = Sections::new(LittleEndian);
+        assemble(&mut redump, &buf).unwrap();
+        assert_eq!(redump.debug_abbrev, sections.debug_abbrev);
+        assert_eq!(redump.debug_info, sections.debug_info);
+        assert_eq!(redump.debug_str, sections.debug_str);
+    }
+
+    #[test]
+    fn assemble_v5() {
+        let text = "\
+DWARF:
+  debug_abbrev:
+    - Table:
+        - Code:     1
+          Tag:      DW_TAG_variable
+          Children: DW_CHILDREN_no
+          Attributes:
+            - Attribute: DW_AT_decl_file
+              Form:      DW_FORM_implicit_const
+              Value:     0xFFFFFFFFFFFFFFFE
+            - Attribute: DW_AT_name
+              Form:      DW_FORM_strx1
+  debug_info:
+    - Version:  5
+      UnitType: DW_UT_compile
+      AddrSize: 8
+      Entries:
+        - AbbrCode: 1
+          Values:
+            - Value: 0xFFFFFFFFFFFFFFFE
+            - Value: 3
+";
+        let mut sections = Sections::new(LittleEndian);
+        assemble(&mut sections, text).unwrap();
+
+        let mut units = sections.compilation_units();
+        let unit = units.next().unwrap().unwrap();
+        assert_eq!(unit.common.version, 5);
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let mut entries = unit.entries(&abbrev);
+        let entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.attr(DW_AT_decl_file), Some(&AttributeData::SData(-2)));
+        assert_eq!(entry.attr(DW_AT_name), Some(&AttributeData::StringIndex(3)));
+
+        let mut buf = Vec::new();
+        dump(&mut buf, &sections).unwrap();
+        let mut redump = Sections::new(LittleEndian);
+        assemble(&mut redump, &String::from_utf8(buf).unwrap()).unwrap();
+        assert_eq!(redump.debug_abbrev, sections.debug_abbrev);
+        assert_eq!(redump.debug_info, sections.debug_info);
+    }
+
+    #[test]
+    fn assemble_errors() {
+        let abbrev = "\
+debug_abbrev:
+  - Code: 1
+    Tag: DW_TAG_base_type
+    Children: DW_CHILDREN_no
+    Attributes:
+      - Attribute: DW_AT_byte_size
+        Form: DW_FORM_data1
+";
+        for units in &[
+            "debug_info:\n  - Version: 6\n",
+            "debug_info:\n  - Version: 5\n    UnitType: DW_UT_skeleton\n",
+            "debug_info:\n  - Version: 4\n    Entries:\n      - AbbrCode: 2\n",
+            "debug_info:\n  - Version: 4\n    Entries:\n      - AbbrCode: 1\n",
+            "debug_info:\n  - Version: 4\n    AbbrevTableID: 1\n",
+            "debug_info:\n  - Version: 4\n    AddrSize: 0x108\n",
+            "debug_info:\n  - Version: 4\n    Entries:\n      - AbbrCode: 1\n        \
+             Values:\n          - Value: 0x100\n",
+        ] {
+            let mut sections = Sections::new(LittleEndian);
+            assert!(assemble(&mut sections, &format!("{}{}", abbrev, units)).is_err(),
+                    "{}",
+                    units);
+        }
+
+        let text = "\
+debug_abbrev:
+  - Code: 1
+    Tag: DW_TAG_variable
+    Children: DW_CHILDREN_no
+    Attributes:
+      - Attribute: DW_AT_location
+        Form: DW_FORM_exprloc
+debug_info:
+  - Version: 4
+    Entries:
+      - AbbrCode: 1
+        Values:
+          - BlockData: [ 0x100 ]
+";
+        let mut sections = Sections::new(LittleEndian);
+        assert!(assemble(&mut sections, text).is_err());
+        assert!(assemble(&mut sections, &text.replace("0x100", "0xFF")).is_ok());
+    }
+
+    #[test]
+    fn dump_string() {
+        let mut buf = Vec::new();
+        write_string(&mut buf, b"it's").unwrap();
+        write_string(&mut buf, b" \"a\"\n").unwrap();
+        assert_eq!(buf, &b"'it''s'\" \\\"a\\\"\\x0A\""[..]);
+        assert!(write_string(&mut buf, b"\xff").is_err());
+    }
+}
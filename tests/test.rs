@@ -55,3 +55,904 @@ fn read_and_write() {
         assert_eq!(read_unit, write_unit);
     }
 }
+
+#[test]
+fn verify() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let mut verifier = dwarf::verify::Verifier::new();
+    verifier.strict_forms = true;
+    verifier.strict_siblings = true;
+    verifier.overlapping_functions = true;
+    verifier.verify(&sections).unwrap();
+    for diagnostic in verifier.diagnostics() {
+        println!("{}", diagnostic);
+    }
+    assert!(verifier.diagnostics().is_empty());
+}
+
+#[test]
+fn rewrite() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let (debug_info, map) = dwarf::rewrite::rewrite_debug_info(&sections, |_| {}).unwrap();
+    assert_eq!(debug_info, sections.debug_info);
+    for (old, new) in map.iter() {
+        assert_eq!(old, new);
+    }
+    let mut debug_aranges = sections.debug_aranges.clone();
+    dwarf::rewrite::update_aranges_offsets(sections.endian, &mut debug_aranges, &map).unwrap();
+    assert_eq!(debug_aranges, sections.debug_aranges);
+}
+
+#[test]
+fn eliminate_duplicates() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let (new_sections, map) = dwarf::dedup::eliminate_duplicates(&sections).unwrap();
+    assert!(new_sections.debug_info.len() <= sections.debug_info.len());
+
+    // Every entry can still be read, and every reference has a target.
+    let mut offsets = std::collections::HashSet::new();
+    let mut refs = Vec::new();
+    let mut units = new_sections.compilation_units();
+    while let Some(unit) = units.next().unwrap() {
+        let abbrev = new_sections.abbrev(&unit.common).unwrap();
+        let mut entries = unit.entries(&abbrev);
+        while let Some(entry) = entries.next().unwrap() {
+            offsets.insert(entry.offset);
+            for attribute in &entry.attributes {
+                if let dwarf::die::AttributeData::RefAddress(offset) = attribute.data {
+                    refs.push(offset.0 as usize);
+                }
+            }
+        }
+    }
+    for offset in refs {
+        assert!(offsets.contains(&offset));
+    }
+    for (_, new) in map.iter() {
+        assert!(offsets.contains(new));
+    }
+}
+
+#[test]
+fn merge_line_programs() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let mut merger = dwarf::merge::LineMerger::new(sections.endian);
+    let mut units = sections.compilation_units();
+    while let Some(unit) = units.next().unwrap() {
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let program = match sections.line_program(&unit, &abbrev).unwrap() {
+            Some(program) => program,
+            None => continue,
+        };
+
+        // Writing a program without changes gives the original data.
+        let mut data = Vec::new();
+        program.write(&mut data).unwrap();
+        assert_eq!(&data[..], &sections.debug_line[program.offset..][..data.len()]);
+
+        let merged = merger.add(std::slice::from_ref(&program)).unwrap();
+        let comp_dir = program.include_directories[0];
+        let comp_name = program.files[0].path;
+        let mut r = &merger.data()[merged.offset..];
+        let new_program = dwarf::line::LineProgram::read(&mut r,
+                                                         merged.offset,
+                                                         sections.endian,
+                                                         program.address_size,
+                                                         comp_dir,
+                                                         comp_name)
+            .unwrap();
+        let mut old_lines = program.lines();
+        let mut new_lines = new_program.lines();
+        loop {
+            match (old_lines.next().unwrap(), new_lines.next().unwrap()) {
+                (Some((old_iter, old_line)), Some((new_iter, new_line))) => {
+                    assert_eq!(old_line.address, new_line.address);
+                    assert_eq!(old_line.line, new_line.line);
+                    let old_file = &old_iter.files()[old_line.file as usize];
+                    let new_file = &new_iter.files()[new_line.file as usize];
+                    assert_eq!(old_file.path, new_file.path);
+                    assert_eq!(old_iter.directories()[old_file.directory as usize],
+                               new_iter.directories()[new_file.directory as usize]);
+                }
+                (None, None) => break,
+                _ => panic!("line count mismatch"),
+            }
+        }
+    }
+}
+
+#[test]
+fn update_line_offsets() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let mut map = dwarf::rewrite::OffsetMap::new();
+    let mut count = 0;
+    let mut units = sections.compilation_units();
+    while let Some(unit) = units.next().unwrap() {
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        if let Some(program) = sections.line_program(&unit, &abbrev).unwrap() {
+            map.insert(program.offset, program.offset);
+            count += 1;
+        }
+    }
+
+    let mut debug_info = sections.debug_info.clone();
+    assert_eq!(dwarf::rewrite::update_line_offsets(sections.endian,
+                                                   &sections.debug_abbrev,
+                                                   &mut debug_info,
+                                                   &map)
+                   .unwrap(),
+               count);
+    assert_eq!(debug_info, sections.debug_info);
+}
+
+#[test]
+fn slide_addresses() {
+    fn addresses(sections: &dwarf::Sections<dwarf::AnyEndian>) -> Vec<u64> {
+        let mut addresses = Vec::new();
+        let mut units = sections.compilation_units();
+        while let Some(unit) = units.next().unwrap() {
+            let abbrev = sections.abbrev(&unit.common).unwrap();
+            let mut entries = unit.entries(&abbrev);
+            while let Some(entry) = entries.next().unwrap() {
+                for attribute in &entry.attributes {
+                    if let dwarf::die::AttributeData::Address(address) = attribute.data {
+                        addresses.push(address);
+                    }
+                }
+            }
+            if let Some(mut lines) = sections.lines(&unit, &abbrev).unwrap() {
+                while let Some((_, line)) = lines.next().unwrap() {
+                    addresses.push(line.address);
+                }
+            }
+        }
+        addresses
+    }
+
+    let path = std::env::args_os().next().unwrap();
+    let mut sections = dwarf::elf::load(path).unwrap();
+    let debug_info = sections.debug_info.clone();
+    let debug_line = sections.debug_line.clone();
+    let debug_aranges = sections.debug_aranges.clone();
+    let debug_ranges = sections.debug_ranges.clone();
+    let old = addresses(&sections);
+
+    dwarf::rewrite::slide_addresses(&mut sections, 0x1000).unwrap();
+    let new = addresses(&sections);
+    assert_eq!(old.len(), new.len());
+    for (old, new) in old.iter().zip(&new) {
+        assert_eq!(old.wrapping_add(0x1000), *new);
+    }
+
+    dwarf::rewrite::slide_addresses(&mut sections, 0x1000u64.wrapping_neg()).unwrap();
+    assert_eq!(sections.debug_info, debug_info);
+    assert_eq!(sections.debug_line, debug_line);
+    assert_eq!(sections.debug_aranges, debug_aranges);
+    assert_eq!(sections.debug_ranges, debug_ranges);
+}
+
+#[test]
+fn shared_sections() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<dwarf::Sections<dwarf::AnyEndian, std::sync::Arc<[u8]>>>();
+    assert_send_sync::<dwarf::abbrev::AbbrevHash>();
+    assert_send_sync::<dwarf::unit::CompilationUnitIterator<dwarf::AnyEndian>>();
+    assert_send_sync::<dwarf::unit::TypeUnitIterator<dwarf::AnyEndian>>();
+    assert_send_sync::<dwarf::die::DieIterator<dwarf::AnyEndian>>();
+    assert_send_sync::<dwarf::line::LineIterator<dwarf::AnyEndian>>();
+
+    let path = std::env::args_os().next().unwrap();
+    let sections = std::sync::Arc::new(dwarf::elf::load(path).unwrap().into_shared());
+    let threads: Vec<_> = (0..2)
+        .map(|_| {
+            let sections = sections.clone();
+            std::thread::spawn(move || {
+                let mut count = 0;
+                let mut units = sections.compilation_units();
+                while let Some(unit) = units.next().unwrap() {
+                    let abbrev = sections.abbrev(&unit.common).unwrap();
+                    let mut entries = unit.entries(&abbrev);
+                    while entries.next().unwrap().is_some() {
+                        count += 1;
+                    }
+                }
+                count
+            })
+        })
+        .collect();
+    let counts: Vec<usize> = threads.into_iter().map(|thread| thread.join().unwrap()).collect();
+    assert_eq!(counts[0], counts[1]);
+    assert!(counts[0] > 0);
+}
+
+#[test]
+fn scan() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let mut units = sections.compilation_units();
+    while let Some(unit) = units.next().unwrap() {
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let mut scanned = Vec::new();
+        unit.scan(&abbrev, |offset, tag, count| scanned.push((offset, tag, count))).unwrap();
+
+        let mut read = Vec::new();
+        let mut entries = unit.entries(&abbrev);
+        while let Some(entry) = entries.next().unwrap() {
+            if !entry.is_null() {
+                read.push((entry.offset, entry.tag, entry.attributes.len()));
+            }
+        }
+        assert_eq!(scanned, read);
+    }
+}
+
+#[test]
+fn graph() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let mut units = sections.compilation_units();
+    let unit = units.next().unwrap().unwrap();
+    let abbrev = sections.abbrev(&unit.common).unwrap();
+    let mut graph = dwarf::graph::Graph::new();
+    let context = sections.attribute_context(&unit.common);
+    graph.add_entries(&unit.common, unit.entries(&abbrev), &context).unwrap();
+
+    let mut count = 0;
+    unit.scan(&abbrev, |_, _, _| count += 1).unwrap();
+    assert_eq!(graph.nodes.len(), count);
+
+    let mut dot = Vec::new();
+    graph.write_dot(&mut dot).unwrap();
+    let mut graphml = Vec::new();
+    graph.write_graphml(&mut graphml).unwrap();
+}
+
+#[test]
+fn coverage() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let map = dwarf::coverage::CoverageMap::from_sections(&sections).unwrap();
+    assert!(!map.files.is_empty());
+
+    // Every row that starts a non-empty range must be covered by the map.
+    let mut units = sections.compilation_units();
+    while let Some(unit) = units.next().unwrap() {
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let mut lines = match sections.lines(&unit, &abbrev).unwrap() {
+            Some(lines) => lines,
+            None => continue,
+        };
+        let mut previous: Option<(Vec<u8>, u64, u64)> = None;
+        while let Some((iter, line)) = lines.next().unwrap() {
+            if let Some((path, line_number, address)) = previous.take() {
+                if line.address > address && line_number != 0 {
+                    let ranges = map.ranges(&path, line_number);
+                    assert!(ranges.iter().any(|range| range.begin <= address && address < range.end));
+                }
+            }
+            if !line.sequence_end {
+                let path = iter.files()[line.file as usize].full_path(iter.directories());
+                previous = Some((path, line.line, line.address));
+            }
+        }
+    }
+}
+
+#[test]
+fn expressions() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let mut units = sections.compilation_units();
+    while let Some(unit) = units.next().unwrap() {
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let mut entries = unit.entries(&abbrev);
+        while let Some(entry) = entries.next().unwrap() {
+            for attribute in &entry.attributes {
+                if let dwarf::die::AttributeData::ExprLoc(data) = attribute.data {
+                    let mut ops = dwarf::expr::ExpressionIterator::new(data, &unit.common);
+                    while ops.next().unwrap().is_some() {}
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn abbrev_vec() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let mut units = sections.compilation_units();
+    while let Some(unit) = units.next().unwrap() {
+        let input = &sections.debug_abbrev[unit.common.abbrev_offset as usize..];
+        let mut r = input;
+        let abbrev_vec = dwarf::abbrev::AbbrevVec::read(&mut r).unwrap();
+        let mut output = Vec::new();
+        abbrev_vec.write(&mut output).unwrap();
+        assert_eq!(&output[..], &input[..input.len() - r.len()]);
+    }
+}
+
+#[test]
+fn name_search() {
+    use dwarf::search::{NameMatch, NameSearch};
+
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let search = NameSearch::new(&sections);
+    let exact = search.find(NameMatch::Exact(b"read_and_display")).unwrap();
+    assert!(exact.iter().any(|entry| entry.tag == dwarf::constant::DW_TAG_subprogram));
+    let insensitive = search.find(NameMatch::CaseInsensitive(b"READ_AND_DISPLAY")).unwrap();
+    assert_eq!(insensitive, exact);
+    let substring = search.find(NameMatch::Substring(b"and_displa")).unwrap();
+    assert!(exact.iter().all(|entry| substring.contains(entry)));
+
+    let refs = search.find_refs(NameMatch::Exact(b"read_and_display")).unwrap();
+    assert_eq!(refs.len(), exact.len());
+    for (die_ref, entry) in refs.iter().zip(exact.iter()) {
+        assert_eq!(die_ref.entry().unwrap().tag, entry.tag);
+        let name = die_ref.string(dwarf::constant::DW_AT_name).unwrap();
+        let linkage_name = die_ref.string(dwarf::constant::DW_AT_linkage_name).unwrap();
+        assert!(name == Some(b"read_and_display") || linkage_name.is_some());
+    }
+}
+
+#[test]
+fn share_abbrevs() {
+    let path = std::env::args_os().next().unwrap();
+    let mut sections = dwarf::elf::load(path).unwrap();
+    let tables = dwarf::rewrite::abbrev_tables(&sections).unwrap();
+    let duplicate_len: usize = tables.iter().map(|table| table.duplicate_len()).sum();
+    let len = sections.debug_abbrev.len();
+    let saved = dwarf::rewrite::share_abbrevs(&mut sections).unwrap();
+    assert!(saved >= duplicate_len);
+    assert_eq!(sections.debug_abbrev.len(), len - saved);
+
+    let mut verifier = dwarf::verify::Verifier::new();
+    verifier.verify(&sections).unwrap();
+    assert!(verifier.diagnostics().is_empty());
+}
+
+#[test]
+fn dwo_directory() {
+    let path = std::env::args_os().next().unwrap();
+    let dir = std::env::temp_dir().join(format!("dwarf-test-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    // Neither file has split units, and the second isn't an ELF file.
+    std::fs::copy(&path, dir.join("sub/a.dwo")).unwrap();
+    std::fs::write(dir.join("b.dwo"), b"not elf").unwrap();
+    let mut dwo = dwarf::split::DwoDirectory::scan(&dir, 4).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(dwo.paths().is_empty());
+    assert_eq!(dwo.errors().len(), 1);
+    assert_eq!(dwo.errors()[0].0, dir.join("b.dwo"));
+    assert!(dwo.get(0x1234).unwrap().is_none());
+}
+
+#[test]
+fn strings() {
+    let sections = dwarf::elf::load(std::env::args_os().next().unwrap()).unwrap();
+    let mut strings = sections.strings();
+    let mut count = 0;
+    let mut end = 0;
+    while let Some((offset, string)) = strings.next().unwrap() {
+        assert_eq!(offset, end);
+        end = offset + string.len() + 1;
+        count += 1;
+    }
+    assert_eq!(end, sections.debug_str.len());
+    assert!(count > 0);
+
+    let map = dwarf::strings::reverse_map(&sections.debug_str).unwrap();
+    let offset = map[&b"strings"[..]];
+    assert_eq!(&sections.debug_str[offset..offset + 8], b"strings\0");
+}
+
+#[test]
+fn gc_strings() {
+    fn strings(sections: &dwarf::Sections<dwarf::AnyEndian>) -> Vec<Vec<u8>> {
+        let mut strings = Vec::new();
+        let mut units = sections.compilation_units();
+        while let Some(unit) = units.next().unwrap() {
+            let abbrev = sections.abbrev(&unit.common).unwrap();
+            let context = sections.attribute_context(&unit.common);
+            let mut entries = unit.entries(&abbrev);
+            while let Some(entry) = entries.next().unwrap() {
+                for attribute in &entry.attributes {
+                    if let dwarf::die::AttributeData::StringOffset(_) = attribute.data {
+                        strings.push(attribute.data.as_string_in(&context).unwrap().to_vec());
+                    }
+                }
+            }
+        }
+        strings
+    }
+
+    let path = std::env::args_os().next().unwrap();
+    let mut sections = dwarf::elf::load(path).unwrap();
+    let before = strings(&sections);
+    let usage = dwarf::strings::StringUsage::from_sections(&sections).unwrap();
+    let unused_len = usage.unused_len(&sections.debug_str).unwrap();
+    let len = sections.debug_str.len();
+    dwarf::rewrite::gc_strings(&mut sections).unwrap();
+    assert_eq!(sections.debug_str.len(), len - unused_len);
+    assert_eq!(strings(&sections), before);
+
+    let usage = dwarf::strings::StringUsage::from_sections(&sections).unwrap();
+    assert_eq!(usage.unused_len(&sections.debug_str).unwrap(), 0);
+}
+
+#[test]
+fn owned_unit() {
+    struct Debug {
+        sections: dwarf::Sections<dwarf::AnyEndian, std::sync::Arc<[u8]>>,
+        units: Vec<dwarf::unit::OwnedUnit<dwarf::AnyEndian>>,
+    }
+
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap().into_shared();
+    let mut counts = Vec::new();
+    let mut offsets = Vec::new();
+    {
+        let mut units = sections.compilation_units();
+        while let Some(unit) = units.next().unwrap() {
+            let abbrev = sections.abbrev(&unit.common).unwrap();
+            let mut entries = unit.entries(&abbrev);
+            let mut count = 0;
+            while entries.next().unwrap().is_some() {
+                count += 1;
+            }
+            counts.push(count);
+            offsets.push(unit.common.offset);
+        }
+    }
+    let units = offsets.iter()
+        .map(|&offset| dwarf::unit::OwnedUnit::compilation_unit(&sections, offset).unwrap())
+        .collect();
+    let debug = Debug {
+        sections: sections,
+        units: units,
+    };
+
+    let handle = std::thread::spawn(move || {
+        let mut owned_counts = Vec::new();
+        for unit in &debug.units {
+            let count = unit.with_entries(|mut entries| {
+                    let mut count = 0;
+                    while entries.next().unwrap().is_some() {
+                        count += 1;
+                    }
+                    count
+                })
+                .unwrap();
+            owned_counts.push(count);
+        }
+        assert!(!debug.sections.debug_info.is_empty());
+        owned_counts
+    });
+    assert_eq!(handle.join().unwrap(), counts);
+}
+
+#[test]
+fn unit_containing() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let index = sections.unit_index().unwrap();
+    let mut count = 0;
+    let mut units = sections.compilation_units();
+    while let Some(unit) = units.next().unwrap() {
+        count += 1;
+        for &offset in &[unit.common.offset, unit.data_offset(), unit.end_offset() - 1] {
+            let found = sections.unit_containing(&index, offset).unwrap().unwrap();
+            assert_eq!(found, unit);
+        }
+    }
+    assert_eq!(index.len(), count);
+    assert!(sections.unit_containing(&index, sections.debug_info.len()).unwrap().is_none());
+}
+
+#[test]
+fn all_entries() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let mut expected = Vec::new();
+    let mut units = sections.compilation_units();
+    while let Some(unit) = units.next().unwrap() {
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let mut entries = unit.entries(&abbrev);
+        while let Some(entry) = entries.next().unwrap() {
+            expected.push((unit.common.offset, false, entry.offset, entry.tag));
+        }
+    }
+    let mut units = sections.type_units();
+    while let Some(unit) = units.next().unwrap() {
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let mut entries = unit.entries(&abbrev);
+        while let Some(entry) = entries.next().unwrap() {
+            expected.push((unit.common.offset, true, entry.offset, entry.tag));
+        }
+    }
+
+    let mut abbrev_cache = dwarf::abbrev::AbbrevCache::new();
+    let mut found = Vec::new();
+    {
+        let mut entries = sections.all_entries(&mut abbrev_cache);
+        while let Some(item) = entries.next().unwrap() {
+            found.push((item.unit.offset, item.type_unit, item.entry.offset, item.entry.tag));
+        }
+    }
+    assert_eq!(found, expected);
+    assert!(!abbrev_cache.is_empty());
+}
+
+#[test]
+fn text_dump_and_assemble() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let mut text = Vec::new();
+    dwarf::text::dump(&mut text, &sections).unwrap();
+    let text = String::from_utf8(text).unwrap();
+
+    let mut assembled = sections.clone();
+    dwarf::text::assemble(&mut assembled, &text).unwrap();
+    assert_eq!(assembled.debug_info, sections.debug_info);
+    assert_eq!(assembled.debug_types, sections.debug_types);
+
+    let mut redump = Vec::new();
+    dwarf::text::dump(&mut redump, &assembled).unwrap();
+    assert_eq!(String::from_utf8(redump).unwrap(), text);
+}
+
+#[test]
+#[cfg(feature = "yaml")]
+fn yaml_dump_and_assemble() {
+    let path = std::env::args_os().next().unwrap();
+    let mut sections = dwarf::elf::load(path).unwrap();
+    if !sections.debug_types.is_empty() {
+        return;
+    }
+    // Parsing the YAML for every unit is slow, so only keep the first few.
+    let mut end = 0;
+    {
+        let mut units = sections.compilation_units();
+        for _ in 0..8 {
+            if let Some(unit) = units.next().unwrap() {
+                end = unit.end_offset();
+            }
+        }
+    }
+    sections.debug_info.truncate(end);
+    let mut text = Vec::new();
+    dwarf::yaml::dump(&mut text, &sections).unwrap();
+
+    let mut assembled = sections.clone();
+    dwarf::yaml::assemble(&mut assembled, &String::from_utf8(text).unwrap()).unwrap();
+    assert_eq!(assembled.debug_info, sections.debug_info);
+    assert_eq!(assembled.debug_str, sections.debug_str);
+}
+
+#[test]
+fn metrics() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let metrics = dwarf::metrics::Metrics::from_sections(&sections).unwrap();
+
+    let mut count = 0;
+    let mut units = sections.compilation_units();
+    while let Some(unit) = units.next().unwrap() {
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let mut entries = unit.entries(&abbrev);
+        while entries.next().unwrap().is_some() {
+            count += 1;
+        }
+    }
+    let mut units = sections.type_units();
+    while let Some(unit) = units.next().unwrap() {
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let mut entries = unit.entries(&abbrev);
+        while entries.next().unwrap().is_some() {
+            count += 1;
+        }
+    }
+    assert_eq!(metrics.entries.count, count);
+    assert!(metrics.abbrev.count > 0);
+    assert!(metrics.lines.count > 0);
+    assert!(metrics.lines.bytes > 0);
+    assert!(metrics.total_time() >= metrics.entries.time);
+    assert!(!metrics.to_string().is_empty());
+}
+
+#[test]
+fn function_index() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let index = dwarf::symbols::FunctionIndex::from_sections(&sections).unwrap();
+    assert!(!index.is_empty());
+    let function = index.iter().find(|f| f.name == b"function_index").unwrap();
+    let found = index.find(function.address + function.size - 1).unwrap();
+    assert!(found.contains(function.address + function.size - 1));
+
+    let mut buf = Vec::new();
+    dwarf::symbols::write_perf_map(&mut buf, &index).unwrap();
+    let line = format!("{:x} {:x} ", function.address, function.size).into_bytes();
+    assert!(buf.split(|&c| c == b'\n').any(|l| l.starts_with(&line)));
+}
+
+#[test]
+fn breakpad_symbols() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let symbols = dwarf::symbols::BreakpadSymbols::from_sections(&sections).unwrap();
+    assert!(!symbols.files.is_empty());
+    assert!(!symbols.lines.is_empty());
+    let module = dwarf::symbols::BreakpadModule {
+        os: "Linux".to_string(),
+        arch: "x86_64".to_string(),
+        id: "0".to_string(),
+        name: "test".to_string(),
+        code_id: None,
+    };
+    let mut buf = Vec::new();
+    symbols.write(&mut buf, &module).unwrap();
+    let text = String::from_utf8_lossy(&buf);
+    let func = text.lines().position(|line| line.ends_with(" breakpad_symbols")).unwrap();
+    let line = text.lines().nth(func + 1).unwrap();
+    assert!(!line.starts_with("FUNC") && !line.starts_with("PUBLIC"));
+}
+
+#[test]
+fn layout() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let layout = dwarf::layout::Layout::from_sections(&sections).unwrap();
+
+    let debug_info = &layout.sections[0];
+    assert_eq!(debug_info.name, ".debug_info");
+    assert_eq!(debug_info.size, sections.debug_info.len());
+    for section in &layout.sections {
+        // The contributions of a valid file cover each section without gaps.
+        let mut end = 0;
+        for contribution in &section.contributions {
+            assert!(contribution.offset <= end);
+            end = std::cmp::max(end, contribution.offset + contribution.size);
+        }
+        assert_eq!(end, section.size);
+    }
+
+    let mut text = Vec::new();
+    layout.write_text(&mut text).unwrap();
+    assert!(text.starts_with(b".debug_info: "));
+    let mut json = Vec::new();
+    layout.write_json(&mut json).unwrap();
+    assert!(json.ends_with(b"]}\n"));
+}
+
+#[test]
+fn chunked_lines() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let mut units = sections.compilation_units();
+    while let Some(unit) = units.next().unwrap() {
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let program = match sections.line_program(&unit, &abbrev).unwrap() {
+            Some(program) => program,
+            None => continue,
+        };
+        let mut all = Vec::new();
+        let mut lines = program.lines();
+        while let Some((_, line)) = lines.next().unwrap() {
+            all.push(line.clone());
+        }
+
+        for &chunk_size in &[1, 64] {
+            let mut reader = dwarf::chunk::ChunkReader::with_chunk_size(&sections.debug_line[..],
+                                                                       chunk_size);
+            let chunked =
+                dwarf::line::ChunkedLineProgram::read(&mut reader, program.offset, sections.endian)
+                    .unwrap();
+            let mut lines = chunked.lines(reader,
+                                          sections.endian,
+                                          unit.common.address_size,
+                                          program.include_directories[0],
+                                          program.files[0].path)
+                .unwrap();
+            assert_eq!(lines.files().len(), program.files.len());
+            let mut rows = Vec::new();
+            while let Some(line) = lines.next().unwrap() {
+                rows.push(line.clone());
+            }
+            assert_eq!(rows, all);
+        }
+    }
+}
+
+#[test]
+fn chunked_aranges() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let index = sections.unit_index().unwrap();
+    let reader = dwarf::chunk::ChunkReader::with_chunk_size(&sections.debug_aranges[..], 32);
+    let mut aranges = dwarf::chunk::ArangeIterator::new(reader, sections.endian);
+    while let Some(arange) = aranges.next().unwrap() {
+        assert_eq!(index.unit_containing(arange.unit_offset), Some(arange.unit_offset));
+    }
+}
+
+#[test]
+fn debuglink_crc() {
+    let path = std::env::args_os().next().unwrap();
+    let data = std::fs::read(&path).unwrap();
+    let crc = dwarf::debuglink::crc32(&data);
+    let file = std::fs::File::open(&path).unwrap();
+    assert_eq!(dwarf::debuglink::read_crc32(file).unwrap(), crc);
+
+    let mut link = dwarf::debuglink::DebugLink {
+        filename: b"test",
+        crc: crc,
+    };
+    assert!(link.matches(&path).unwrap());
+    link.crc ^= 1;
+    assert!(!link.matches(&path).unwrap());
+}
+
+#[test]
+fn reproducible_output() {
+    // Each writer is run twice, and the maps used by each run have
+    // different hash seeds, so iteration order must not affect the output.
+    fn write<E: dwarf::Endian>(sections: &dwarf::Sections<E>) -> Vec<Vec<u8>> {
+        let mut output = Vec::new();
+
+        let (deduped, _) = dwarf::dedup::eliminate_duplicates(sections).unwrap();
+        output.push(deduped.debug_abbrev);
+        output.push(deduped.debug_info);
+
+        let mut shared = sections.clone();
+        dwarf::rewrite::share_abbrevs(&mut shared).unwrap();
+        dwarf::rewrite::gc_strings(&mut shared).unwrap();
+        output.push(shared.debug_abbrev);
+        output.push(shared.debug_info);
+        output.push(shared.debug_str);
+
+        let mut merger = dwarf::merge::LineMerger::new(sections.endian);
+        let mut programs = Vec::new();
+        let mut units = sections.compilation_units();
+        while let Some(unit) = units.next().unwrap() {
+            let abbrev = sections.abbrev(&unit.common).unwrap();
+            if let Some(program) = sections.line_program(&unit, &abbrev).unwrap() {
+                programs.push(program);
+            }
+        }
+        merger.add(&programs).unwrap();
+        output.push(merger.data().to_vec());
+
+        let mut text = Vec::new();
+        dwarf::text::dump(&mut text, sections).unwrap();
+        let mut assembled = sections.clone();
+        dwarf::text::assemble(&mut assembled, std::str::from_utf8(&text).unwrap()).unwrap();
+        output.push(text);
+        output.push(assembled.debug_abbrev);
+        output.push(assembled.debug_info);
+        output
+    }
+
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    assert!(write(&sections) == write(&sections));
+}
+
+#[test]
+fn jit_elf() {
+    for &address_size in &[4, 8] {
+        let mut jit = dwarf::jit::JitDebugInfo::new(dwarf::LittleEndian, address_size, 62, b"jit");
+        let file = jit.add_file(b"jit.js");
+        jit.add_function(b"jitted", 0x1000, 0x20);
+        jit.add_line(0x1000, file, 7);
+        let image = jit.write_elf().unwrap();
+
+        let path = std::env::temp_dir()
+            .join(format!("dwarf-jit-{}-{}.o", std::process::id(), address_size));
+        std::fs::write(&path, &image).unwrap();
+        let sections = dwarf::elf::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let sections = sections.unwrap();
+        let index = dwarf::symbols::FunctionIndex::from_sections(&sections).unwrap();
+        let function = index.find(0x1010).unwrap();
+        assert_eq!(function.name, b"jitted");
+        assert_eq!((function.address, function.size), (0x1000, 0x20));
+
+        let mut entries = sections.frame_entries(address_size);
+        let mut fdes = Vec::new();
+        while let Some(entry) = entries.next().unwrap() {
+            if let dwarf::frame::FrameEntry::Fde(fde, _) = entry {
+                fdes.push((fde.initial_location, fde.address_range));
+            }
+        }
+        assert_eq!(fdes, vec![(0x1000, 0x20)]);
+    }
+}
+
+#[test]
+fn file_index() {
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let index = dwarf::search::FileIndex::from_sections(&sections).unwrap();
+    let units = index.find(b"tests/test.rs");
+    assert!(!units.is_empty());
+    let all = index.find(b"test.rs");
+    assert!(units.iter().all(|unit| all.contains(unit)));
+    assert!(index.find(b"est.rs").is_empty());
+}
+
+#[test]
+fn entry_at_path() {
+    use dwarf::constant::*;
+
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let mut found = Vec::new();
+    let mut units = sections.compilation_units();
+    while let Some(unit) = units.next().unwrap() {
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let path = [(DW_TAG_namespace, &b"test"[..]), (DW_TAG_subprogram, &b"entry_at_path"[..])];
+        if let Some(entry) = sections.entry_at_path(&unit, &abbrev, &path).unwrap() {
+            found.push(entry.string(DW_AT_name).unwrap().unwrap());
+        }
+        let root = sections.entry_at_path(&unit, &abbrev, &[]).unwrap().unwrap();
+        assert_eq!(root.entry().unwrap().tag, DW_TAG_compile_unit);
+        let path = [(DW_TAG_namespace, &b"test"[..]), (DW_TAG_subprogram, &b"missing"[..])];
+        assert!(sections.entry_at_path(&unit, &abbrev, &path).unwrap().is_none());
+    }
+    assert_eq!(found, vec![b"entry_at_path"]);
+}
+
+#[test]
+fn lines_reset() {
+    fn collect(lines: &mut dwarf::line::LineIterator<dwarf::AnyEndian>) -> Vec<dwarf::line::Line> {
+        let mut result = Vec::new();
+        while let Some((_, line)) = lines.next().unwrap() {
+            result.push(line.clone());
+        }
+        result
+    }
+
+    let path = std::env::args_os().next().unwrap();
+    let sections = dwarf::elf::load(path).unwrap();
+    let mut units = sections.compilation_units();
+    while let Some(unit) = units.next().unwrap() {
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let program = match sections.line_program(&unit, &abbrev).unwrap() {
+            Some(program) => program,
+            None => continue,
+        };
+        let mut lines = program.lines();
+        let all = collect(&mut lines);
+        lines.reset();
+        assert_eq!(collect(&mut lines), all);
+
+        // A clone continues from the same row.
+        let mut lines = program.lines();
+        if lines.next().unwrap().is_some() {
+            let mut clone = lines.clone();
+            assert_eq!(collect(&mut clone), &all[1..]);
+        }
+
+        // Record the offset and index of the start of each sequence.
+        let mut lines = program.lines();
+        let mut sequences = vec![(0, 0)];
+        let mut index = 0;
+        while let Some((lines, line)) = lines.next().unwrap() {
+            index += 1;
+            if line.sequence_end {
+                sequences.push((lines.offset(), index));
+            }
+        }
+        for (offset, index) in sequences {
+            let mut lines = program.rows_from(offset).unwrap();
+            assert_eq!(collect(&mut lines), &all[index..]);
+        }
+    }
+}